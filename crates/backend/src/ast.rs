@@ -1,3 +1,6 @@
+use std::env;
+
+use proc_macro2::Span;
 use quote::{ToTokens, Tokens};
 use shared;
 use syn;
@@ -8,16 +11,32 @@ pub struct Program {
     pub imports: Vec<Import>,
     pub enums: Vec<Enum>,
     pub structs: Vec<Struct>,
+    pub dictionaries: Vec<Dictionary>,
+    pub consts: Vec<Const>,
 }
 
 pub struct Export {
     pub class: Option<syn::Ident>,
-    pub method: bool,
-    pub mutable: bool,
+    pub method_self: Option<MethodSelf>,
+    /// Whether the method returns a reference to its receiver, for chaining,
+    /// which isn't passed to JS at all.
+    pub returns_self: bool,
     pub constructor: Option<String>,
+    pub iterator: Option<String>,
     pub function: Function,
 }
 
+/// How a method takes `self`.
+#[derive(Copy, Clone, PartialEq)]
+pub enum MethodSelf {
+    /// `self`, moving the value out of its JS object.
+    ByValue,
+    /// `&mut self`
+    RefMutable,
+    /// `&self`
+    RefShared,
+}
+
 pub struct Import {
     pub module: Option<String>,
     pub js_namespace: Option<syn::Ident>,
@@ -28,6 +47,7 @@ pub enum ImportKind {
     Function(ImportFunction),
     Static(ImportStatic),
     Type(ImportType),
+    Enum(ImportEnum),
 }
 
 pub struct ImportFunction {
@@ -56,6 +76,14 @@ pub struct ImportType {
     pub name: syn::Ident,
 }
 
+/// An enum whose variants are given JS string values, like
+/// `enum Mode { Cors = "cors" }`.
+pub struct ImportEnum {
+    pub name: syn::Ident,
+    pub variants: Vec<syn::Ident>,
+    pub values: Vec<String>,
+}
+
 pub struct Function {
     pub name: syn::Ident,
     pub arguments: Vec<syn::Type>,
@@ -69,6 +97,19 @@ pub struct Function {
 pub struct Struct {
     pub name: syn::Ident,
     pub fields: Vec<StructField>,
+    pub superclass: Option<Superclass>,
+    /// The message of the struct's `#[deprecated]` attribute, see
+    /// `deprecation`.
+    pub deprecated: Option<String>,
+}
+
+/// The JS class an exported struct's class extends, see
+/// `#[wasm_bindgen(extends = ...)]`.
+pub struct Superclass {
+    pub name: syn::Ident,
+    pub module: Option<String>,
+    pub js_namespace: Option<syn::Ident>,
+    pub forward_args: bool,
 }
 
 pub struct StructField {
@@ -80,6 +121,23 @@ pub struct StructField {
     pub setter: syn::Ident,
 }
 
+/// A struct converted into a plain JS object when it's passed to JS, see
+/// `#[wasm_bindgen(dictionary)]`.
+pub struct Dictionary {
+    pub name: syn::Ident,
+    pub fields: Vec<DictionaryField>,
+}
+
+pub struct DictionaryField {
+    pub name: syn::Ident,
+    pub js_name: String,
+    /// The type of the field, or of the value in it if it's an `Option`.
+    pub ty: syn::Type,
+    /// Whether the field is an `Option`, left out of the JS object when it's
+    /// `None`.
+    pub optional: bool,
+}
+
 pub struct Enum {
     pub name: syn::Ident,
     pub variants: Vec<Variant>,
@@ -90,6 +148,19 @@ pub struct Variant {
     pub value: u32,
 }
 
+pub struct Const {
+    pub name: syn::Ident,
+    pub value: ConstValue,
+}
+
+pub enum ConstValue {
+    Number(f64),
+    Boolean(bool),
+    String(String),
+    /// Not a literal, so JS reads it through this exported getter.
+    Getter(Export),
+}
+
 #[derive(Copy, Clone)]
 pub enum TypeKind {
     ByRef,
@@ -125,15 +196,20 @@ impl Program {
                 f.to_tokens(tokens);
                 self.exports.push(Export {
                     class: None,
-                    method: false,
-                    mutable: false,
+                    method_self: None,
+                    returns_self: false,
                     constructor: None,
+                    iterator: None,
                     function: Function::from(f, opts),
                 });
             }
             syn::Item::Struct(mut s) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut s.attrs));
-                self.structs.push(Struct::from(&mut s, opts));
+                if opts.dictionary() {
+                    self.dictionaries.push(Dictionary::from(&mut s));
+                } else {
+                    self.structs.push(Struct::from(&mut s, opts));
+                }
                 s.to_tokens(tokens);
             }
             syn::Item::Impl(mut i) => {
@@ -147,12 +223,29 @@ impl Program {
             }
             syn::Item::Enum(mut e) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut e.attrs));
-                e.to_tokens(tokens);
-                self.push_enum(e, opts);
+                if is_string_enum(&e) {
+                    self.push_import_enum(e, opts, tokens);
+                } else {
+                    e.to_tokens(tokens);
+                    self.push_enum(e, opts);
+                }
+            }
+            syn::Item::Const(mut c) => {
+                let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut c.attrs));
+                c.to_tokens(tokens);
+                self.push_const(c.vis, c.ident, *c.ty, &c.expr, opts, tokens);
+            }
+            syn::Item::Static(mut s) => {
+                let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut s.attrs));
+                if s.mutability.is_some() {
+                    panic!("cannot export mutable statics");
+                }
+                s.to_tokens(tokens);
+                self.push_const(s.vis, s.ident, *s.ty, &s.expr, opts, tokens);
             }
             _ => panic!(
                 "#[wasm_bindgen] can only be applied to a function, \
-                 struct, enum, impl, or extern block"
+                 struct, enum, const, static, impl, or extern block"
             ),
         }
     }
@@ -215,8 +308,14 @@ impl Program {
         } else {
             None
         };
+        let iterator = opts.iterator().map(|role| {
+            match role.as_ref() {
+                "len" | "get" => role.as_ref().to_string(),
+                _ => panic!("`iterator` must be either `len` or `get`, not `{}`", role),
+            }
+        });
 
-        let (function, mutable) = Function::from_decl(
+        let (function, method_self) = Function::from_decl(
             method.sig.ident,
             Box::new(method.sig.decl.clone()),
             method.attrs.clone(),
@@ -224,12 +323,19 @@ impl Program {
             method.vis.clone(),
             true,
         );
+        let returns_self = match method_self {
+            Some(MethodSelf::RefMutable) | Some(MethodSelf::RefShared) => {
+                function.ret.as_ref().map_or(false, |ret| is_ref_to(ret, class))
+            }
+            _ => false,
+        };
 
         self.exports.push(Export {
             class: Some(class),
-            method: mutable.is_some(),
-            mutable: mutable.unwrap_or(false),
+            method_self,
+            returns_self,
             constructor,
+            iterator,
             function,
         });
     }
@@ -277,6 +383,101 @@ impl Program {
         });
     }
 
+    /// Records an enum with string values, emitting it without them since
+    /// Rust only allows integer discriminants.
+    pub fn push_import_enum(
+        &mut self,
+        mut item: syn::ItemEnum,
+        _opts: BindgenAttrs,
+        tokens: &mut Tokens,
+    ) {
+        let mut variants = Vec::new();
+        let mut values: Vec<String> = Vec::new();
+        for v in item.variants.iter_mut() {
+            match v.fields {
+                syn::Fields::Unit => (),
+                _ => panic!("Only C-Style enums allowed"),
+            }
+            let value = match v.discriminant.take() {
+                Some((
+                    _,
+                    syn::Expr::Lit(syn::ExprLit {
+                        attrs: _,
+                        lit: syn::Lit::Str(ref s),
+                    }),
+                )) => s.value(),
+                _ => panic!("enums with string values must give every variant \
+                             a string literal value"),
+            };
+            if values.contains(&value) {
+                panic!("the string value {:?} is given to more than one variant", value);
+            }
+            variants.push(v.ident);
+            values.push(value);
+        }
+        item.to_tokens(tokens);
+        self.imports.push(Import {
+            module: None,
+            js_namespace: None,
+            kind: ImportKind::Enum(ImportEnum {
+                name: item.ident,
+                variants,
+                values,
+            }),
+        });
+    }
+
+    pub fn push_const(
+        &mut self,
+        vis: syn::Visibility,
+        name: syn::Ident,
+        ty: syn::Type,
+        expr: &syn::Expr,
+        _opts: BindgenAttrs,
+        tokens: &mut Tokens,
+    ) {
+        match vis {
+            syn::Visibility::Public(_) => {}
+            _ => panic!("only public consts and statics are allowed"),
+        }
+
+        let value = match const_literal(expr) {
+            Some(value) => value,
+            None => {
+                // The value isn't known until it's evaluated, so generate a
+                // function returning it for JS to call instead.
+                let (ret, value) = match ty {
+                    syn::Type::Reference(syn::TypeReference { ref elem, .. }) => {
+                        match **elem {
+                            syn::Type::Path(syn::TypePath { qself: None, ref path })
+                                if extract_path_ident(path).map_or(false, |i| i == "str") => {}
+                            _ => panic!("only `&str` references can be exported"),
+                        }
+                        (quote! { String }, quote! { #name.to_string() })
+                    }
+                    ref ty => (quote! { #ty }, quote! { #name }),
+                };
+                let getter = syn::Ident::from(shared::const_getter(name.as_ref()));
+                let f: syn::ItemFn = syn::parse2(quote! {
+                    #[allow(non_snake_case)]
+                    pub fn #getter() -> #ret {
+                        #value
+                    }
+                }.into()).unwrap();
+                f.to_tokens(tokens);
+                ConstValue::Getter(Export {
+                    class: None,
+                    method_self: None,
+                    returns_self: false,
+                    constructor: None,
+                    iterator: None,
+                    function: Function::from(f, BindgenAttrs::default()),
+                })
+            }
+        };
+        self.consts.push(Const { name, value });
+    }
+
     pub fn push_foreign_mod(&mut self, f: syn::ItemForeignMod, opts: BindgenAttrs) {
         match f.abi.name {
             Some(ref l) if l.value() == "C" => {}
@@ -332,6 +533,21 @@ impl Program {
                 .expect("can't `catch` without returning a Result");
         }
 
+        if wasm.opts.variadic() {
+            let is_slice = match wasm.arguments.last() {
+                Some(&syn::Type::Reference(syn::TypeReference { ref elem, .. })) => {
+                    match **elem {
+                        syn::Type::Slice(_) => true,
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+            if !is_slice {
+                panic!("the last argument of a `variadic` import must be a slice");
+            }
+        }
+
         let kind = if wasm.opts.method() {
             let class = wasm.arguments
                 .get(0)
@@ -429,8 +645,12 @@ impl Program {
             structs: self.structs.iter().map(|a| a.shared()).collect(),
             enums: self.enums.iter().map(|a| a.shared()).collect(),
             imports: self.imports.iter().map(|a| a.shared()).collect(),
+            consts: self.consts.iter().map(|a| a.shared()).collect(),
             version: shared::version(),
             schema_version: shared::SCHEMA_VERSION.to_string(),
+            // Procedural macros run inside rustc, which Cargo invokes with
+            // the name of the crate being compiled in the environment.
+            crate_name: env::var("CARGO_PKG_NAME").unwrap_or_default(),
         }
     }
 }
@@ -465,7 +685,7 @@ impl Function {
         opts: BindgenAttrs,
         vis: syn::Visibility,
         allow_self: bool,
-    ) -> (Function, Option<bool>) {
+    ) -> (Function, Option<MethodSelf>) {
         if decl.variadic.is_some() {
             panic!("can't bindgen variadic functions")
         }
@@ -475,17 +695,23 @@ impl Function {
 
         assert_no_lifetimes(&mut decl);
 
-        let mut mutable = None;
+        let mut method_self = None;
         let arguments = decl.inputs
             .iter()
             .filter_map(|arg| match *arg {
                 syn::FnArg::Captured(ref c) => Some(c),
-                syn::FnArg::SelfValue(_) => {
-                    panic!("by-value `self` not yet supported");
+                syn::FnArg::SelfValue(_) if allow_self => {
+                    assert!(method_self.is_none());
+                    method_self = Some(MethodSelf::ByValue);
+                    None
                 }
                 syn::FnArg::SelfRef(ref a) if allow_self => {
-                    assert!(mutable.is_none());
-                    mutable = Some(a.mutability.is_some());
+                    assert!(method_self.is_none());
+                    method_self = Some(if a.mutability.is_some() {
+                        MethodSelf::RefMutable
+                    } else {
+                        MethodSelf::RefShared
+                    });
                     None
                 }
                 _ => panic!("arguments cannot be `self` or ignored"),
@@ -508,17 +734,74 @@ impl Function {
                 rust_decl: decl,
                 rust_attrs: attrs,
             },
-            mutable,
+            method_self,
         )
     }
 
     fn shared(&self) -> shared::Function {
         shared::Function {
             name: self.name.as_ref().to_string(),
+            location: location(self.name.span()),
         }
     }
 }
 
+/// Where the code `span` covers starts, unless it's not from a file, like
+/// code generated by another macro.
+fn location(span: Span) -> Option<shared::Location> {
+    let span = span.unstable();
+    let file = span.source_file();
+    if !file.is_real() {
+        return None
+    }
+    let start = span.start();
+    Some(shared::Location {
+        file: file.path().to_string(),
+        line: start.line as u32,
+        column: start.column as u32,
+    })
+}
+
+/// Finds a `#[deprecated]` attribute in `attrs`, returning its message (its
+/// `note`, or the string it's set to) or an empty string if it has none.
+fn deprecation(attrs: &[syn::Attribute]) -> Option<String> {
+    let meta = attrs.iter()
+        .filter_map(|a| a.interpret_meta())
+        .find(|m| m.name() == "deprecated")?;
+    let note = match meta {
+        syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(ref s), .. }) => {
+            Some(s.value())
+        }
+        syn::Meta::List(ref list) => {
+            list.nested.iter().filter_map(|n| match *n {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ident,
+                    lit: syn::Lit::Str(ref s),
+                    ..
+                })) if ident == "note" => Some(s.value()),
+                _ => None,
+            }).next()
+        }
+        _ => None,
+    };
+    Some(note.unwrap_or_default())
+}
+
+/// Whether `ty` is `&class` or `&mut class`, which is what `&Self` and
+/// `&mut Self` are by the time methods are looked at.
+fn is_ref_to(ty: &syn::Type, class: syn::Ident) -> bool {
+    let elem = match *ty {
+        syn::Type::Reference(syn::TypeReference { ref elem, .. }) => elem,
+        _ => return false,
+    };
+    match **elem {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
+            extract_path_ident(path) == Some(class)
+        }
+        _ => false,
+    }
+}
+
 pub fn extract_path_ident(path: &syn::Path) -> Option<syn::Ident> {
     if path.leading_colon.is_some() {
         return None;
@@ -557,8 +840,13 @@ impl Export {
     fn shared(&self) -> shared::Export {
         shared::Export {
             class: self.class.map(|s| s.as_ref().to_string()),
-            method: self.method,
+            method: self.method_self.is_some(),
+            consumed: self.method_self == Some(MethodSelf::ByValue),
+            returns_self: self.returns_self,
             constructor: self.constructor.clone(),
+            iterator: self.iterator.clone(),
+            is_async: self.function.opts.is_async(),
+            deprecated: deprecation(&self.function.rust_attrs),
             function: self.function.shared(),
         }
     }
@@ -573,6 +861,20 @@ impl Enum {
     }
 }
 
+impl Const {
+    fn shared(&self) -> shared::Const {
+        shared::Const {
+            name: self.name.as_ref().to_string(),
+            value: match self.value {
+                ConstValue::Number(n) => shared::ConstValue::Number(n),
+                ConstValue::Boolean(b) => shared::ConstValue::Boolean(b),
+                ConstValue::String(ref s) => shared::ConstValue::String(s.clone()),
+                ConstValue::Getter(_) => shared::ConstValue::Getter,
+            },
+        }
+    }
+}
+
 impl Variant {
     fn shared(&self) -> shared::EnumVariant {
         shared::EnumVariant {
@@ -598,6 +900,16 @@ impl ImportKind {
             ImportKind::Function(ref f) => shared::ImportKind::Function(f.shared()),
             ImportKind::Static(ref f) => shared::ImportKind::Static(f.shared()),
             ImportKind::Type(ref f) => shared::ImportKind::Type(f.shared()),
+            ImportKind::Enum(ref f) => shared::ImportKind::Enum(f.shared()),
+        }
+    }
+}
+
+impl ImportEnum {
+    fn shared(&self) -> shared::ImportEnum {
+        shared::ImportEnum {
+            name: self.name.as_ref().to_string(),
+            values: self.values.clone(),
         }
     }
 }
@@ -645,6 +957,8 @@ impl ImportFunction {
             method,
             js_new,
             structural: self.function.opts.structural(),
+            variadic: self.function.opts.variadic(),
+            lazy: self.function.opts.lazy(),
             getter,
             setter,
             class: class_name.cloned(),
@@ -669,7 +983,7 @@ impl ImportType {
 }
 
 impl Struct {
-    fn from(s: &mut syn::ItemStruct, _opts: BindgenAttrs) -> Struct {
+    fn from(s: &mut syn::ItemStruct, opts: BindgenAttrs) -> Struct {
         let mut fields = Vec::new();
         if let syn::Fields::Named(names) = &mut s.fields {
             for field in names.named.iter_mut() {
@@ -694,9 +1008,28 @@ impl Struct {
                 });
             }
         }
+        let superclass = match opts.extends() {
+            Some(name) => Some(Superclass {
+                name,
+                module: opts.module().map(|s| s.to_string()),
+                js_namespace: opts.js_namespace(),
+                forward_args: opts.super_args(),
+            }),
+            None => {
+                if opts.module().is_some() || opts.js_namespace().is_some() ||
+                    opts.super_args()
+                {
+                    panic!("`module`, `js_namespace` and `super_args` only apply to \
+                            structs which `extends` a JS class");
+                }
+                None
+            }
+        };
         Struct {
             name: s.ident,
             fields,
+            superclass,
+            deprecated: deprecation(&s.attrs),
         }
     }
 
@@ -704,6 +1037,46 @@ impl Struct {
         shared::Struct {
             name: self.name.as_ref().to_string(),
             fields: self.fields.iter().map(|s| s.shared()).collect(),
+            superclass: self.superclass.as_ref().map(|s| s.shared()),
+            deprecated: self.deprecated.clone(),
+        }
+    }
+}
+
+impl Superclass {
+    fn shared(&self) -> shared::Superclass {
+        shared::Superclass {
+            name: self.name.as_ref().to_string(),
+            module: self.module.clone(),
+            js_namespace: self.js_namespace.map(|s| s.as_ref().to_string()),
+            forward_args: self.forward_args,
+        }
+    }
+}
+
+impl Dictionary {
+    fn from(s: &mut syn::ItemStruct) -> Dictionary {
+        let names = match s.fields {
+            syn::Fields::Named(ref mut names) => names,
+            _ => panic!("dictionaries must have named fields"),
+        };
+        let fields = names.named.iter_mut().map(|field| {
+            let name = field.ident.unwrap();
+            let opts = BindgenAttrs::find(&mut field.attrs);
+            let (ty, optional) = match option_inner(&field.ty) {
+                Some(ty) => (ty.clone(), true),
+                None => (field.ty.clone(), false),
+            };
+            DictionaryField {
+                name,
+                js_name: opts.js_name().unwrap_or(name).as_ref().to_string(),
+                ty,
+                optional,
+            }
+        }).collect();
+        Dictionary {
+            name: s.ident,
+            fields,
         }
     }
 }
@@ -760,6 +1133,16 @@ impl BindgenAttrs {
         })
     }
 
+    fn iterator(&self) -> Option<syn::Ident> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::Iterator(s) => Some(s),
+                _ => None,
+            })
+            .next()
+    }
+
     fn method(&self) -> bool {
         self.attrs.iter().any(|a| match *a {
             BindgenAttr::Method => true,
@@ -804,6 +1187,20 @@ impl BindgenAttrs {
         })
     }
 
+    pub fn variadic(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Variadic => true,
+            _ => false,
+        })
+    }
+
+    pub fn lazy(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Lazy => true,
+            _ => false,
+        })
+    }
+
     pub fn readonly(&self) -> bool {
         self.attrs.iter().any(|a| match *a {
             BindgenAttr::Readonly => true,
@@ -811,6 +1208,37 @@ impl BindgenAttrs {
         })
     }
 
+    fn extends(&self) -> Option<syn::Ident> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::Extends(s) => Some(s),
+                _ => None,
+            })
+            .next()
+    }
+
+    fn super_args(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::SuperArgs => true,
+            _ => false,
+        })
+    }
+
+    pub fn dictionary(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Dictionary => true,
+            _ => false,
+        })
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Async => true,
+            _ => false,
+        })
+    }
+
     pub fn js_name(&self) -> Option<syn::Ident> {
         self.attrs
             .iter()
@@ -841,13 +1269,20 @@ impl syn::synom::Synom for BindgenAttrs {
 enum BindgenAttr {
     Catch,
     Constructor,
+    Iterator(syn::Ident),
     Method,
     JsNamespace(syn::Ident),
     Module(String),
     Getter(Option<syn::Ident>),
     Setter(Option<syn::Ident>),
     Structural,
+    Variadic,
+    Lazy,
     Readonly,
+    Dictionary,
+    Extends(syn::Ident),
+    SuperArgs,
+    Async,
     JsName(syn::Ident),
 }
 
@@ -859,6 +1294,13 @@ impl syn::synom::Synom for BindgenAttr {
         |
         call!(term, "method") => { |_| BindgenAttr::Method }
         |
+        do_parse!(
+            call!(term, "iterator") >>
+            punct!(=) >>
+            s: syn!(syn::Ident) >>
+            (s)
+        )=> { BindgenAttr::Iterator }
+        |
         do_parse!(
             call!(term, "getter") >>
             val: option!(do_parse!(
@@ -881,8 +1323,25 @@ impl syn::synom::Synom for BindgenAttr {
         |
         call!(term, "structural") => { |_| BindgenAttr::Structural }
         |
+        call!(term, "variadic") => { |_| BindgenAttr::Variadic }
+        |
+        call!(term, "lazy") => { |_| BindgenAttr::Lazy }
+        |
         call!(term, "readonly") => { |_| BindgenAttr::Readonly }
         |
+        call!(term, "dictionary") => { |_| BindgenAttr::Dictionary }
+        |
+        call!(term, "super_args") => { |_| BindgenAttr::SuperArgs }
+        |
+        do_parse!(
+            call!(term, "extends") >>
+            punct!(=) >>
+            name: syn!(syn::Ident) >>
+            (name)
+        )=> { BindgenAttr::Extends }
+        |
+        call!(term, "async") => { |_| BindgenAttr::Async }
+        |
         do_parse!(
             call!(term, "js_namespace") >>
             punct!(=) >>
@@ -906,6 +1365,45 @@ impl syn::synom::Synom for BindgenAttr {
     ));
 }
 
+/// Whether any variant of the enum is given a string value, making it an
+/// `ImportEnum`.
+fn is_string_enum(item: &syn::ItemEnum) -> bool {
+    item.variants.iter().any(|v| {
+        match v.discriminant {
+            Some((_, syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(_), .. }))) => true,
+            _ => false,
+        }
+    })
+}
+
+/// The value of `expr` if it's a (possibly negated) literal which can be
+/// written out in JS as is.
+fn const_literal(expr: &syn::Expr) -> Option<ConstValue> {
+    let (negated, lit) = match *expr {
+        syn::Expr::Lit(syn::ExprLit { ref lit, .. }) => (false, lit),
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), ref expr, .. }) => {
+            match **expr {
+                syn::Expr::Lit(syn::ExprLit { ref lit, .. }) => (true, lit),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let sign = if negated { -1.0 } else { 1.0 };
+    Some(match *lit {
+        syn::Lit::Int(ref i) => {
+            if i.value() > 1 << 53 {
+                panic!("integer consts must fit in a JS number without losing precision");
+            }
+            ConstValue::Number(sign * i.value() as f64)
+        }
+        syn::Lit::Float(ref f) => ConstValue::Number(sign * f.value()),
+        syn::Lit::Str(ref s) if !negated => ConstValue::String(s.value()),
+        syn::Lit::Bool(ref b) if !negated => ConstValue::Boolean(b.value),
+        _ => return None,
+    })
+}
+
 fn extract_first_ty_param(ty: Option<&syn::Type>) -> Option<Option<syn::Type>> {
     let t = match ty {
         Some(t) => t,
@@ -934,6 +1432,29 @@ fn extract_first_ty_param(ty: Option<&syn::Type>) -> Option<Option<syn::Type>> {
     Some(Some(ty.clone()))
 }
 
+/// The `T` of `ty` if it's an `Option<T>`.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) => path,
+        _ => return None,
+    };
+    let seg = path.segments.last()?.into_value();
+    if seg.ident != "Option" {
+        return None
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    match *generics.args.first()?.into_value() {
+        syn::GenericArgument::Type(ref t) => Some(t),
+        _ => None,
+    }
+}
+
 fn term<'a>(cursor: syn::buffer::Cursor<'a>, name: &str) -> syn::synom::PResult<'a, ()> {
     if let Some((term, next)) = cursor.term() {
         if term.as_str() == name {
@@ -10,6 +10,13 @@ use serde_json;
 use shared;
 use syn;
 
+fn is_slice(ty: &syn::Type) -> bool {
+    match *ty {
+        syn::Type::Slice(_) => true,
+        _ => false,
+    }
+}
+
 fn to_ident_name(s: &str) -> Cow<str> {
     if s.chars().all(|c| match c {
         'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => true,
@@ -37,6 +44,9 @@ impl ToTokens for ast::Program {
         for s in self.structs.iter() {
             s.to_tokens(tokens);
         }
+        for d in self.dictionaries.iter() {
+            d.to_tokens(tokens);
+        }
         let mut types = HashSet::new();
         for i in self.imports.iter() {
             if let ast::ImportKind::Type(ref t) = i.kind {
@@ -56,6 +66,11 @@ impl ToTokens for ast::Program {
         for e in self.enums.iter() {
             e.to_tokens(tokens);
         }
+        for c in self.consts.iter() {
+            if let ast::ConstValue::Getter(ref getter) = c.value {
+                getter.to_tokens(tokens);
+            }
+        }
 
         // Generate a static which will eventually be what lives in a custom section
         // of the wasm executable. For now it's just a plain old static, but we'll
@@ -106,7 +121,13 @@ impl ToTokens for ast::Struct {
         let name_chars = name.as_ref().chars().map(|c| c as u32);
         let new_fn = syn::Ident::from(shared::new_function(self.name.as_ref()));
         let free_fn = syn::Ident::from(shared::free_function(self.name.as_ref()));
+        let instanceof_fn = syn::Ident::from(shared::instanceof_function(self.name.as_ref()));
+        let ptr_fn = syn::Ident::from(shared::ptr_function(self.name.as_ref()));
+        let unwrap_fn = syn::Ident::from(shared::unwrap_function(self.name.as_ref()));
+        // The struct may be `#[deprecated]`, which is only meant for code
+        // using it rather than the glue bridging it to JS.
         (quote! {
+            #[allow(deprecated)]
             impl ::wasm_bindgen::describe::WasmDescribe for #name {
                 fn describe() {
                     use wasm_bindgen::describe::*;
@@ -123,6 +144,7 @@ impl ToTokens for ast::Struct {
                 }
             }
 
+            #[allow(deprecated)]
             impl ::wasm_bindgen::convert::IntoWasmAbi for #name {
                 type Abi = u32;
 
@@ -135,6 +157,7 @@ impl ToTokens for ast::Struct {
                 }
             }
 
+            #[allow(deprecated)]
             impl ::wasm_bindgen::convert::FromWasmAbi for #name {
                 type Abi = u32;
 
@@ -152,6 +175,20 @@ impl ToTokens for ast::Struct {
                 }
             }
 
+            // Instances are boxed, so a null pointer is never one of them.
+            #[allow(deprecated)]
+            impl ::wasm_bindgen::convert::OptionIntoWasmAbi for #name {
+                fn none(_extra: &mut ::wasm_bindgen::convert::Stack) -> u32 { 0 }
+            }
+
+            #[allow(deprecated)]
+            impl ::wasm_bindgen::convert::OptionFromWasmAbi for #name {
+                fn is_none(js: &u32, _extra: &mut ::wasm_bindgen::convert::Stack) -> bool {
+                    *js == 0
+                }
+            }
+
+            #[allow(deprecated)]
             impl ::wasm_bindgen::__rt::core::convert::From<#name> for
                 ::wasm_bindgen::JsValue
             {
@@ -176,6 +213,80 @@ impl ToTokens for ast::Struct {
                 }
             }
 
+            #[allow(deprecated)]
+            impl ::wasm_bindgen::TryFromJsValue for #name {
+                fn is_instance(value: &::wasm_bindgen::JsValue) -> bool {
+                    #[wasm_import_module = "__wbindgen_placeholder__"]
+                    extern {
+                        fn #instanceof_fn(idx: u32) -> u32;
+                    }
+
+                    unsafe {
+                        #instanceof_fn(::wasm_bindgen::convert::IntoWasmAbi::into_abi(
+                            value,
+                            &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                        )) != 0
+                    }
+                }
+
+                fn try_ref<'a>(value: &'a ::wasm_bindgen::JsValue)
+                    -> ::wasm_bindgen::__rt::core::option::Option<
+                        ::wasm_bindgen::__rt::Ref<'a, Self>
+                    >
+                {
+                    #[wasm_import_module = "__wbindgen_placeholder__"]
+                    extern {
+                        fn #ptr_fn(idx: u32) -> u32;
+                    }
+
+                    // The pointer is left with the JS object, which can't be
+                    // freed or used mutably while it's borrowed here.
+                    let ptr = unsafe {
+                        #ptr_fn(::wasm_bindgen::convert::IntoWasmAbi::into_abi(
+                            value,
+                            &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                        ))
+                    };
+                    if ptr == 0 {
+                        return ::wasm_bindgen::__rt::core::option::Option::None
+                    }
+                    let ptr = ptr as *mut ::wasm_bindgen::__rt::WasmRefCell<#name>;
+                    unsafe {
+                        ::wasm_bindgen::__rt::core::option::Option::Some((*ptr).borrow())
+                    }
+                }
+
+                fn try_from(value: ::wasm_bindgen::JsValue)
+                    -> ::wasm_bindgen::__rt::core::result::Result<Self, ::wasm_bindgen::JsValue>
+                {
+                    #[wasm_import_module = "__wbindgen_placeholder__"]
+                    extern {
+                        fn #unwrap_fn(idx: u32) -> u32;
+                    }
+
+                    // The JS object's pointer is cleared as ownership of what
+                    // it points to moves here.
+                    let ptr = unsafe {
+                        #unwrap_fn(::wasm_bindgen::convert::IntoWasmAbi::into_abi(
+                            &value,
+                            &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                        ))
+                    };
+                    if ptr == 0 {
+                        return ::wasm_bindgen::__rt::core::result::Result::Err(value)
+                    }
+                    unsafe {
+                        ::wasm_bindgen::__rt::core::result::Result::Ok(
+                            <#name as ::wasm_bindgen::convert::FromWasmAbi>::from_abi(
+                                ptr,
+                                &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                            )
+                        )
+                    }
+                }
+            }
+
+            #[allow(deprecated)]
             #[no_mangle]
             pub unsafe extern fn #free_fn(ptr: u32) {
                 <#name as ::wasm_bindgen::convert::FromWasmAbi>::from_abi(
@@ -184,6 +295,7 @@ impl ToTokens for ast::Struct {
                 );
             }
 
+            #[allow(deprecated)]
             impl ::wasm_bindgen::convert::RefFromWasmAbi for #name {
                 type Abi = u32;
                 type Anchor = ::wasm_bindgen::__rt::Ref<'static, #name>;
@@ -198,6 +310,7 @@ impl ToTokens for ast::Struct {
                 }
             }
 
+            #[allow(deprecated)]
             impl ::wasm_bindgen::convert::RefMutFromWasmAbi for #name {
                 type Abi = u32;
                 type Anchor = ::wasm_bindgen::__rt::RefMut<'static, #name>;
@@ -219,6 +332,81 @@ impl ToTokens for ast::Struct {
     }
 }
 
+impl ToTokens for ast::Dictionary {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let name = &self.name;
+        let name_len = name.as_ref().chars().count() as u32;
+        let name_chars = name.as_ref().chars().map(|c| c as u32);
+        let nfields = self.fields.len() as u32;
+        let sets = self.fields.iter().map(|f| {
+            let field = &f.name;
+            let js_name = &f.js_name;
+            if f.optional {
+                quote! {
+                    if let Some(val) = dict.#field {
+                        ::wasm_bindgen::__rt::object_set(
+                            &obj,
+                            #js_name,
+                            ::wasm_bindgen::JsValue::from(val),
+                        );
+                    }
+                }
+            } else {
+                quote! {
+                    ::wasm_bindgen::__rt::object_set(
+                        &obj,
+                        #js_name,
+                        ::wasm_bindgen::JsValue::from(dict.#field),
+                    );
+                }
+            }
+        });
+        let describe_fields = self.fields.iter().map(|f| {
+            let ty = &f.ty;
+            let len = f.js_name.chars().count() as u32;
+            let chars = f.js_name.chars().map(|c| c as u32);
+            let optional = f.optional as u32;
+            quote! {
+                inform(#len);
+                #(inform(#chars);)*
+                inform(#optional);
+                <#ty as WasmDescribe>::describe();
+            }
+        });
+        (quote! {
+            impl From<#name> for ::wasm_bindgen::JsValue {
+                fn from(dict: #name) -> ::wasm_bindgen::JsValue {
+                    let obj = ::wasm_bindgen::__rt::object_new();
+                    #(#sets)*
+                    obj
+                }
+            }
+
+            impl ::wasm_bindgen::convert::IntoWasmAbi for #name {
+                type Abi = u32;
+
+                fn into_abi(self, extra: &mut ::wasm_bindgen::convert::Stack) -> u32 {
+                    ::wasm_bindgen::convert::IntoWasmAbi::into_abi(
+                        ::wasm_bindgen::JsValue::from(self),
+                        extra,
+                    )
+                }
+            }
+
+            impl ::wasm_bindgen::describe::WasmDescribe for #name {
+                fn describe() {
+                    use wasm_bindgen::describe::*;
+                    inform(DICTIONARY);
+                    inform(#name_len);
+                    #(inform(#name_chars);)*
+                    inform(#nfields);
+                    #(#describe_fields)*
+                }
+            }
+        }).to_tokens(tokens);
+    }
+}
+
 impl ToTokens for ast::StructField {
     fn to_tokens(&self, tokens: &mut Tokens) {
         let name = &self.name;
@@ -228,6 +416,7 @@ impl ToTokens for ast::StructField {
         let setter = &self.setter;
         let desc = syn::Ident::from(format!("__wbindgen_describe_{}", getter));
         (quote! {
+            #[allow(deprecated)]
             #[no_mangle]
             pub unsafe extern fn #getter(js: u32)
                 -> <#ty as ::wasm_bindgen::convert::IntoWasmAbi>::Abi
@@ -247,6 +436,7 @@ impl ToTokens for ast::StructField {
                 )
             }
 
+            #[allow(deprecated)]
             #[no_mangle]
             pub extern fn #desc() {
                 use wasm_bindgen::describe::*;
@@ -259,6 +449,7 @@ impl ToTokens for ast::StructField {
         }
 
         (quote! {
+            #[allow(deprecated)]
             #[no_mangle]
             pub unsafe extern fn #setter(
                 js: u32,
@@ -289,14 +480,30 @@ impl ToTokens for ast::Export {
         let ret = syn::Ident::from("_ret");
 
         let mut offset = 0;
-        if self.method {
-            let class = self.class.unwrap();
-            args.push(quote! { me: *mut ::wasm_bindgen::__rt::WasmRefCell<#class> });
-            arg_conversions.push(quote! {
-                ::wasm_bindgen::__rt::assert_not_null(me);
-                let me = unsafe { &*me };
-            });
-            offset = 1;
+        match self.method_self {
+            // The JS object has already let go of the pointer, so the value
+            // is moved out of its box.
+            Some(ast::MethodSelf::ByValue) => {
+                let class = self.class.unwrap();
+                args.push(quote! { me: u32 });
+                arg_conversions.push(quote! {
+                    let me = unsafe {
+                        <#class as ::wasm_bindgen::convert::FromWasmAbi>
+                            ::from_abi(me, &mut __stack)
+                    };
+                });
+                offset = 1;
+            }
+            Some(_) => {
+                let class = self.class.unwrap();
+                args.push(quote! { me: *mut ::wasm_bindgen::__rt::WasmRefCell<#class> });
+                arg_conversions.push(quote! {
+                    ::wasm_bindgen::__rt::assert_not_null(me);
+                    let me = unsafe { &*me };
+                });
+                offset = 1;
+            }
+            None => {}
         }
 
         for (i, ty) in self.function.arguments.iter().enumerate() {
@@ -347,7 +554,26 @@ impl ToTokens for ast::Export {
         }
         let ret_ty;
         let convert_ret;
+        let mut convert_view = None;
         match self.function.ret {
+            // The receiver being returned is kept in JS, so nothing is.
+            Some(_) if self.returns_self => {
+                ret_ty = quote!{};
+                convert_ret = quote!{};
+            }
+            // Borrowed slices are handed to JS as a view into wasm's memory,
+            // so they're converted while the borrow of `me` is still alive.
+            Some(syn::Type::Reference(syn::TypeReference {
+                mutability: None,
+                ref elem,
+                ..
+            })) if is_slice(elem) => {
+                ret_ty = quote! {
+                    -> <&'static #elem as ::wasm_bindgen::convert::IntoWasmAbi>::Abi
+                };
+                convert_view = Some(quote! { <&#elem as ::wasm_bindgen::convert::IntoWasmAbi> });
+                convert_ret = quote! { #ret };
+            }
             Some(syn::Type::Reference(_)) => panic!("can't return a borrowed ref"),
             Some(ref ty) => {
                 ret_ty = quote! {
@@ -366,6 +592,13 @@ impl ToTokens for ast::Export {
             }
         }
         let describe_ret = match self.function.ret {
+            Some(_) if self.returns_self => quote! { inform(0); },
+            Some(syn::Type::Reference(syn::TypeReference { ref elem, .. })) => {
+                quote! {
+                    inform(1);
+                    <&#elem as WasmDescribe>::describe();
+                }
+            }
             Some(ref ty) => {
                 quote! {
                     inform(1);
@@ -376,16 +609,34 @@ impl ToTokens for ast::Export {
         };
 
         let name = self.function.name;
-        let receiver = match self.class {
-            Some(_) if self.method => {
-                if self.mutable {
-                    quote! { me.borrow_mut().#name }
-                } else {
-                    quote! { me.borrow().#name }
+        let receiver = match (self.class, self.method_self) {
+            (Some(_), Some(ast::MethodSelf::ByValue)) => quote! { me.#name },
+            (Some(_), Some(ast::MethodSelf::RefMutable)) => quote! { me.borrow_mut().#name },
+            (Some(_), Some(ast::MethodSelf::RefShared)) => quote! { me.borrow().#name },
+            (Some(class), None) => quote! { #class::#name },
+            (None, _) => quote!{ #name },
+        };
+        let invoke = match convert_view {
+            // JS returns `this`, which is only right if that's what Rust
+            // returned a reference to.
+            None if self.returns_self => {
+                let class = self.class.unwrap();
+                let message = format!("`{}::{}` returned a reference to something other than \
+                                       `self`, which can't be passed to JS",
+                                      class, name);
+                quote! {
+                    let me_ptr = &*me.borrow() as *const #class;
+                    if #receiver(#(#converted_arguments),*) as *const #class != me_ptr {
+                        ::wasm_bindgen::throw(#message);
+                    }
                 }
             }
-            Some(class) => quote! { #class::#name },
-            None => quote!{ #name },
+            Some(view) => quote! {
+                #view::into_abi(#receiver(#(#converted_arguments),*), &mut unsafe {
+                    ::wasm_bindgen::convert::GlobalStack::new()
+                })
+            },
+            None => quote! { #receiver(#(#converted_arguments),*) },
         };
         let descriptor_name = format!("__wbindgen_describe_{}", export_name);
         let descriptor_name = syn::Ident::from(descriptor_name);
@@ -394,7 +645,7 @@ impl ToTokens for ast::Export {
 
         let tokens = quote! {
             #[export_name = #export_name]
-            #[allow(non_snake_case)]
+            #[allow(non_snake_case, deprecated)]
             pub extern fn #generated_name(#(#args),*) #ret_ty {
                 ::wasm_bindgen::__rt::link_this_library();
                 let #ret = {
@@ -402,7 +653,7 @@ impl ToTokens for ast::Export {
                         ::wasm_bindgen::convert::GlobalStack::new()
                     };
                     #(#arg_conversions)*
-                    #receiver(#(#converted_arguments),*)
+                    #invoke
                 };
                 #convert_ret
             }
@@ -424,6 +675,7 @@ impl ToTokens for ast::Export {
             // this, but the tl;dr; is that this is stripped from the final wasm
             // binary along with anything it references.
             #[no_mangle]
+            #[allow(deprecated)]
             pub extern fn #descriptor_name() {
                 use wasm_bindgen::describe::*;
                 inform(FUNCTION);
@@ -442,6 +694,7 @@ impl ToTokens for ast::ImportKind {
             ast::ImportKind::Function(ref f) => f.to_tokens(tokens),
             ast::ImportKind::Static(ref s) => s.to_tokens(tokens),
             ast::ImportKind::Type(ref t) => t.to_tokens(tokens),
+            ast::ImportKind::Enum(ref e) => e.to_tokens(tokens),
         }
     }
 }
@@ -485,6 +738,20 @@ impl ToTokens for ast::ImportType {
                 }
             }
 
+            impl ::wasm_bindgen::convert::OptionIntoWasmAbi for #name {
+                fn none(extra: &mut ::wasm_bindgen::convert::Stack) -> Self::Abi {
+                    <::wasm_bindgen::JsValue as
+                        ::wasm_bindgen::convert::OptionIntoWasmAbi>::none(extra)
+                }
+            }
+
+            impl ::wasm_bindgen::convert::OptionFromWasmAbi for #name {
+                fn is_none(js: &Self::Abi, extra: &mut ::wasm_bindgen::convert::Stack) -> bool {
+                    <::wasm_bindgen::JsValue as
+                        ::wasm_bindgen::convert::OptionFromWasmAbi>::is_none(js, extra)
+                }
+            }
+
             impl<'a> ::wasm_bindgen::convert::IntoWasmAbi for &'a #name {
                 type Abi = <&'a ::wasm_bindgen::JsValue as
                     ::wasm_bindgen::convert::IntoWasmAbi>::Abi;
@@ -691,6 +958,7 @@ impl<'a> ToTokens for DescribeImport<'a> {
             ast::ImportKind::Function(ref f) => f,
             ast::ImportKind::Static(_) => return,
             ast::ImportKind::Type(_) => return,
+            ast::ImportKind::Enum(_) => return,
         };
         let describe_name = format!("__wbindgen_describe_{}", f.shim);
         let describe_name = syn::Ident::from(describe_name);
@@ -757,6 +1025,70 @@ impl ToTokens for ast::Enum {
     }
 }
 
+impl ToTokens for ast::ImportEnum {
+    fn to_tokens(&self, into: &mut Tokens) {
+        let enum_name = &self.name;
+        let name_len = enum_name.as_ref().chars().count() as u32;
+        let name_chars = enum_name.as_ref().chars().map(|c| c as u32);
+        let nvalues = self.values.len() as u32;
+        let value_chars = self.values.iter()
+            .flat_map(|v| {
+                Some(v.chars().count() as u32).into_iter().chain(v.chars().map(|c| c as u32))
+            });
+        let variants = &self.variants;
+        let values = &self.values;
+        let enum_names = self.variants.iter().map(|_| enum_name);
+        let cast_clauses = self.variants.iter().map(|variant| {
+            quote! {
+                if js == #enum_name::#variant as u32 {
+                    #enum_name::#variant
+                }
+            }
+        });
+        (quote! {
+            impl ::wasm_bindgen::convert::IntoWasmAbi for #enum_name {
+                type Abi = u32;
+
+                fn into_abi(self, _extra: &mut ::wasm_bindgen::convert::Stack) -> u32 {
+                    self as u32
+                }
+            }
+
+            impl ::wasm_bindgen::convert::FromWasmAbi for #enum_name {
+                type Abi = u32;
+
+                unsafe fn from_abi(
+                    js: u32,
+                    _extra: &mut ::wasm_bindgen::convert::Stack,
+                ) -> Self {
+                    #(#cast_clauses else)* {
+                        wasm_bindgen::throw("invalid enum value passed")
+                    }
+                }
+            }
+
+            impl From<#enum_name> for ::wasm_bindgen::JsValue {
+                fn from(val: #enum_name) -> ::wasm_bindgen::JsValue {
+                    ::wasm_bindgen::JsValue::from_str(match val {
+                        #(#enum_names::#variants => #values,)*
+                    })
+                }
+            }
+
+            impl ::wasm_bindgen::describe::WasmDescribe for #enum_name {
+                fn describe() {
+                    use wasm_bindgen::describe::*;
+                    inform(STRING_ENUM);
+                    inform(#name_len);
+                    #(inform(#name_chars);)*
+                    inform(#nvalues);
+                    #(inform(#value_chars);)*
+                }
+            }
+        }).to_tokens(into);
+    }
+}
+
 impl ToTokens for ast::ImportStatic {
     fn to_tokens(&self, into: &mut Tokens) {
         let name = self.rust_name;
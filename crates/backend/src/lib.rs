@@ -1,4 +1,5 @@
 #![recursion_limit = "256"]
+#![feature(proc_macro)]
 
 extern crate proc_macro2;
 #[macro_use]
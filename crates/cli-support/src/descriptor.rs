@@ -1,5 +1,7 @@
 use std::char;
 
+use serde_json;
+
 macro_rules! tys {
     ($($a:ident)*) => (tys! { @ ($($a)*) 0 });
     (@ () $v:expr) => {};
@@ -32,6 +34,11 @@ tys! {
     ANYREF
     ENUM
     RUST_STRUCT
+    STRING_ENUM
+    DICTIONARY
+    FN_POINTER
+    OPTIONAL
+    UNIT
 }
 
 #[derive(Debug)]
@@ -57,6 +64,38 @@ pub enum Descriptor {
     Anyref,
     Enum,
     RustStruct(String),
+    StringEnum(StringEnum),
+    Dictionary(Dictionary),
+    /// A plain Rust `fn` pointer, which has no captured state.
+    FnPointer(Box<Function>),
+    /// An `Option` of a type whose ABI has a spare value for `None`, which
+    /// is `undefined` in JS.
+    Optional(Box<Descriptor>),
+    /// `()`, which functions return when they return nothing, like those
+    /// returning `Result<(), JsValue>`.
+    Unit,
+}
+
+/// An imported enum whose variants stand for the JS strings in `values`.
+#[derive(Debug)]
+pub struct StringEnum {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+/// A struct passed to JS as a plain object with these fields.
+#[derive(Debug)]
+pub struct Dictionary {
+    pub name: String,
+    pub fields: Vec<DictionaryField>,
+}
+
+#[derive(Debug)]
+pub struct DictionaryField {
+    pub name: String,
+    /// Whether the field is left out of the object when it's `None`.
+    pub optional: bool,
+    pub ty: Descriptor,
 }
 
 #[derive(Debug)]
@@ -86,14 +125,47 @@ pub enum VectorKind {
 }
 
 impl Descriptor {
-    pub fn decode(mut data: &[u32]) -> Descriptor {
-        let descriptor = Descriptor::_decode(&mut data);
-        assert!(data.is_empty());
-        descriptor
+    /// Decodes the words a descriptor shim passed to `__wbindgen_describe`,
+    /// failing with a message saying what's wrong where for words which
+    /// aren't a descriptor this version of the CLI understands.
+    pub fn decode(data: &[u32]) -> Result<Descriptor, String> {
+        let mut rest = data;
+        let descriptor = Descriptor::_decode(&mut rest).map_err(|e| {
+            format!("{} at word {}", e, data.len() - rest.len())
+        })?;
+        if !rest.is_empty() {
+            return Err(format!("{} unexpected words after the descriptor ended at \
+                                word {}", rest.len(), data.len() - rest.len()))
+        }
+        Ok(descriptor)
+    }
+
+    /// Says which part of the function described by `data` it stops in the
+    /// middle of, like `argument 2` or `the return type`, which points at the
+    /// type whose descriptor shim failed. `None` if `data` isn't the start of
+    /// a function's descriptor.
+    pub fn incomplete_part(mut data: &[u32]) -> Option<String> {
+        if get(&mut data) != Ok(FUNCTION) {
+            return None
+        }
+        let arguments = match get(&mut data) {
+            Ok(n) => n,
+            Err(_) => return Some("the argument list".to_string()),
+        };
+        for i in 0..arguments {
+            if Descriptor::_decode(&mut data).is_err() {
+                return Some(format!("argument {}", i))
+            }
+        }
+        match get(&mut data) {
+            Ok(0) => None,
+            Ok(_) if Descriptor::_decode(&mut data).is_ok() => None,
+            _ => Some("the return type".to_string()),
+        }
     }
 
-    fn _decode(data: &mut &[u32]) -> Descriptor {
-        match get(data) {
+    fn _decode(data: &mut &[u32]) -> Result<Descriptor, String> {
+        Ok(match get(data)? {
             I8 => Descriptor::I8,
             I16 => Descriptor::I16,
             I32 => Descriptor::I32,
@@ -105,23 +177,40 @@ impl Descriptor {
             F32 => Descriptor::F32,
             F64 => Descriptor::F64,
             BOOLEAN => Descriptor::Boolean,
-            FUNCTION => Descriptor::Function(Box::new(Function::decode(data))),
-            CLOSURE => Descriptor::Closure(Box::new(Closure::decode(data))),
-            REF => Descriptor::Ref(Box::new(Descriptor::_decode(data))),
-            REFMUT => Descriptor::RefMut(Box::new(Descriptor::_decode(data))),
-            SLICE => Descriptor::Slice(Box::new(Descriptor::_decode(data))),
-            VECTOR => Descriptor::Vector(Box::new(Descriptor::_decode(data))),
+            FUNCTION => Descriptor::Function(Box::new(Function::decode(data)?)),
+            CLOSURE => Descriptor::Closure(Box::new(Closure::decode(data)?)),
+            REF => Descriptor::Ref(Box::new(Descriptor::_decode(data)?)),
+            REFMUT => Descriptor::RefMut(Box::new(Descriptor::_decode(data)?)),
+            SLICE => Descriptor::Slice(Box::new(Descriptor::_decode(data)?)),
+            VECTOR => Descriptor::Vector(Box::new(Descriptor::_decode(data)?)),
             STRING => Descriptor::String,
             ANYREF => Descriptor::Anyref,
             ENUM => Descriptor::Enum,
-            RUST_STRUCT => {
-                let name = (0..get(data))
-                    .map(|_| char::from_u32(get(data)).unwrap())
-                    .collect();
-                Descriptor::RustStruct(name)
+            RUST_STRUCT => Descriptor::RustStruct(get_string(data)?),
+            STRING_ENUM => {
+                let name = get_string(data)?;
+                let values = (0..get(data)?)
+                    .map(|_| get_string(data))
+                    .collect::<Result<_, _>>()?;
+                Descriptor::StringEnum(StringEnum { name, values })
             }
-            other => panic!("unknown descriptor: {}", other),
-        }
+            DICTIONARY => {
+                let name = get_string(data)?;
+                let fields = (0..get(data)?).map(|_| {
+                    let name = get_string(data)?;
+                    let optional = get(data)? != 0;
+                    Ok(DictionaryField { name, optional, ty: Descriptor::_decode(data)? })
+                }).collect::<Result<_, String>>()?;
+                Descriptor::Dictionary(Dictionary { name, fields })
+            }
+            FN_POINTER => {
+                expect(data, FUNCTION, "a function pointer")?;
+                Descriptor::FnPointer(Box::new(Function::decode(data)?))
+            }
+            OPTIONAL => Descriptor::Optional(Box::new(Descriptor::_decode(data)?)),
+            UNIT => Descriptor::Unit,
+            other => return Err(format!("unknown descriptor discriminant {}", other)),
+        })
     }
 
     pub fn unwrap_function(&self) -> &Function {
@@ -148,6 +237,15 @@ impl Descriptor {
         }
     }
 
+    /// Whether this is `i64` or `u64`, which are `BigInt`s in JS unless
+    /// `Bindgen::split_i64` is used.
+    pub fn is_64bit(&self) -> bool {
+        match *self {
+            Descriptor::I64 | Descriptor::U64 => true,
+            _ => return false,
+        }
+    }
+
     pub fn is_ref_anyref(&self) -> bool {
         match *self {
             Descriptor::Ref(ref s) => s.is_anyref(),
@@ -169,6 +267,93 @@ impl Descriptor {
         }
     }
 
+    pub fn fn_pointer(&self) -> Option<&Function> {
+        match *self {
+            Descriptor::FnPointer(ref f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// The type wrapped in an `Option`, if this is one.
+    pub fn optional(&self) -> Option<&Descriptor> {
+        match *self {
+            Descriptor::Optional(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// The Rust type this describes, for error messages.
+    pub fn rust_ty(&self) -> String {
+        let name = match *self {
+            Descriptor::I8 => "i8",
+            Descriptor::U8 => "u8",
+            Descriptor::I16 => "i16",
+            Descriptor::U16 => "u16",
+            Descriptor::I32 => "i32",
+            Descriptor::U32 => "u32",
+            Descriptor::I64 => "i64",
+            Descriptor::U64 => "u64",
+            Descriptor::F32 => "f32",
+            Descriptor::F64 => "f64",
+            Descriptor::Boolean => "bool",
+            Descriptor::String => "String",
+            Descriptor::Anyref => "JsValue",
+            Descriptor::Enum => "enum",
+            Descriptor::Function(ref f) => return f.rust_signature("Fn"),
+            Descriptor::Closure(ref c) => {
+                return format!("Closure<{}>", c.function.rust_signature("Fn"))
+            }
+            Descriptor::FnPointer(ref f) => return f.rust_signature("fn"),
+            Descriptor::Optional(ref d) => return format!("Option<{}>", d.rust_ty()),
+            Descriptor::Ref(ref d) => match **d {
+                Descriptor::String => "&str",
+                ref d => return format!("&{}", d.rust_ty()),
+            },
+            Descriptor::RefMut(ref d) => return format!("&mut {}", d.rust_ty()),
+            Descriptor::Slice(ref d) => return format!("[{}]", d.rust_ty()),
+            Descriptor::Vector(ref d) => return format!("Vec<{}>", d.rust_ty()),
+            Descriptor::RustStruct(ref name) => name,
+            Descriptor::StringEnum(ref e) => &e.name,
+            Descriptor::Dictionary(ref d) => &d.name,
+            Descriptor::Unit => "()",
+        };
+        name.to_string()
+    }
+
+    /// The tag of this type in the bindings metadata, see the `metadata`
+    /// module.
+    pub fn metadata_ty(&self) -> String {
+        let name = match *self {
+            Descriptor::I8 => "i8",
+            Descriptor::U8 => "u8",
+            Descriptor::I16 => "i16",
+            Descriptor::U16 => "u16",
+            Descriptor::I32 => "i32",
+            Descriptor::U32 => "u32",
+            Descriptor::I64 => "i64",
+            Descriptor::U64 => "u64",
+            Descriptor::F32 => "f32",
+            Descriptor::F64 => "f64",
+            Descriptor::Boolean => "bool",
+            Descriptor::String => "string",
+            Descriptor::Anyref => "any",
+            Descriptor::Enum => "enum",
+            Descriptor::Function(_) |
+            Descriptor::Closure(_) |
+            Descriptor::FnPointer(_) => "function",
+            Descriptor::Optional(ref d) => return format!("option:{}", d.metadata_ty()),
+            Descriptor::Ref(ref d) |
+            Descriptor::RefMut(ref d) => return d.metadata_ty(),
+            Descriptor::Slice(ref d) |
+            Descriptor::Vector(ref d) => return format!("array:{}", d.metadata_ty()),
+            Descriptor::RustStruct(ref name) => return format!("class:{}", name),
+            Descriptor::StringEnum(ref e) => return format!("string_enum:{}", e.name),
+            Descriptor::Dictionary(ref d) => return format!("dictionary:{}", d.name),
+            Descriptor::Unit => "void",
+        };
+        name.to_string()
+    }
+
     pub fn is_anyref(&self) -> bool {
         match *self {
             Descriptor::Anyref => true,
@@ -215,6 +400,20 @@ impl Descriptor {
         }
     }
 
+    pub fn string_enum(&self) -> Option<&StringEnum> {
+        match *self {
+            Descriptor::StringEnum(ref e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn dictionary(&self) -> Option<&Dictionary> {
+        match *self {
+            Descriptor::Dictionary(ref d) => Some(d),
+            _ => None,
+        }
+    }
+
     pub fn stack_closure(&self) -> Option<(&Function, bool)> {
         let (inner, mutable) = match *self {
             Descriptor::Ref(ref d) => (&**d, false),
@@ -234,36 +433,88 @@ impl Descriptor {
             _ => false,
         }
     }
+
+    pub fn is_mut_ref(&self) -> bool {
+        matches!(*self, Descriptor::RefMut(_))
+    }
+}
+
+fn get(a: &mut &[u32]) -> Result<u32, String> {
+    match a.split_first() {
+        Some((&ret, rest)) => {
+            *a = rest;
+            Ok(ret)
+        }
+        None => Err("the descriptor ended early".to_string()),
+    }
 }
 
-fn get(a: &mut &[u32]) -> u32 {
-    let ret = a[0];
-    *a = &a[1..];
-    ret
+fn get_string(a: &mut &[u32]) -> Result<String, String> {
+    (0..get(a)?)
+        .map(|_| {
+            let c = get(a)?;
+            char::from_u32(c).ok_or_else(|| format!("invalid character {} in a name", c))
+        })
+        .collect()
+}
+
+/// Reads the discriminant `ty` which the descriptor of `what` continues with.
+fn expect(a: &mut &[u32], ty: u32, what: &str) -> Result<(), String> {
+    match get(a)? {
+        found if found == ty => Ok(()),
+        found => Err(format!("expected the descriptor of {} to continue with {}, \
+                              found {}", what, ty, found)),
+    }
 }
 
 impl Closure {
-    fn decode(data: &mut &[u32]) -> Closure {
-        let mutable = get(data) == REFMUT;
-        assert_eq!(get(data), FUNCTION);
-        Closure {
+    fn decode(data: &mut &[u32]) -> Result<Closure, String> {
+        let mutable = get(data)? == REFMUT;
+        expect(data, FUNCTION, "a closure")?;
+        Ok(Closure {
             mutable,
-            function: Function::decode(data),
-        }
+            function: Function::decode(data)?,
+        })
     }
 }
 
 impl Function {
-    fn decode(data: &mut &[u32]) -> Function {
-        let arguments = (0..get(data))
+    fn decode(data: &mut &[u32]) -> Result<Function, String> {
+        let arguments = (0..get(data)?)
             .map(|_| Descriptor::_decode(data))
-            .collect::<Vec<_>>();
-        let ret = if get(data) == 0 {
-            None
-        } else {
-            Some(Descriptor::_decode(data))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ret = match get(data)? {
+            0 => None,
+            _ => match Descriptor::_decode(data)? {
+                Descriptor::Unit => None,
+                ret => Some(ret),
+            },
         };
-        Function { arguments, ret }
+        Ok(Function { arguments, ret })
+    }
+
+    /// The tags of the arguments and of the return type of this function in
+    /// the bindings metadata, see `Descriptor::metadata_ty`.
+    pub fn metadata_tys(&self) -> (Vec<String>, String) {
+        let arguments = self.arguments.iter().map(|a| a.metadata_ty()).collect();
+        let ret = self.ret.as_ref()
+            .map(|r| r.metadata_ty())
+            .unwrap_or_else(|| "void".to_string());
+        (arguments, ret)
+    }
+
+    /// The Rust signature of this function, like `fn(u32) -> String` for the
+    /// `kind` "fn".
+    pub fn rust_signature(&self, kind: &str) -> String {
+        match self.ret {
+            Some(ref ret) => format!("{}{} -> {}", kind, self.rust_args(), ret.rust_ty()),
+            None => format!("{}{}", kind, self.rust_args()),
+        }
+    }
+
+    fn rust_args(&self) -> String {
+        let args = self.arguments.iter().map(|a| a.rust_ty()).collect::<Vec<_>>();
+        format!("({})", args.join(", "))
     }
 }
 
@@ -283,6 +534,15 @@ impl VectorKind {
         }
     }
 
+    /// The type of the values JS can pass for this kind of vector, which for
+    /// numbers includes plain arrays.
+    pub fn js_arg_ty(&self) -> String {
+        match *self {
+            VectorKind::String | VectorKind::Anyref => self.js_ty().to_string(),
+            _ => format!("{} | number[]", self.js_ty()),
+        }
+    }
+
     pub fn size(&self) -> usize {
         match *self {
             VectorKind::String => 1,
@@ -298,3 +558,13 @@ impl VectorKind {
         }
     }
 }
+
+impl StringEnum {
+    /// The TypeScript union of this enum's string literals.
+    pub fn js_ty(&self) -> String {
+        self.values.iter()
+            .map(|v| serde_json::to_string(v).unwrap())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
@@ -0,0 +1,383 @@
+//! Removes everything from a wasm module that nothing uses, see
+//! `Bindgen::run_gc`.
+//!
+//! What's used is found by walking from the exports, the start function and
+//! the element segments through every function they can call and every
+//! global and type they refer to. Everything else is removed and the indices
+//! of what's left are renumbered, including in the `name` section. Data
+//! segments are only removed along with the memory they initialize, once no
+//! import, export or function uses it.
+
+use std::mem;
+
+use parity_wasm::elements::*;
+
+use GcStats;
+
+/// Functions of compiler-builtins which older toolchains exported from every
+/// module, even though nothing outside of it is meant to call them. They're
+/// unexported, and removed unless the module itself uses them.
+const BUILTINS: &[&str] = &[
+    "__ashldi3", "__ashlti3", "__ashrdi3", "__ashrti3", "__lshrdi3", "__lshrti3",
+    "__floatsisf", "__floatsidf", "__floatdidf", "__floattisf", "__floattidf",
+    "__floatunsisf", "__floatunsidf", "__floatundidf", "__floatuntisf", "__floatuntidf",
+    "__fixsfsi", "__fixsfdi", "__fixsfti", "__fixdfsi", "__fixdfdi", "__fixdfti",
+    "__fixunssfsi", "__fixunssfdi", "__fixunssfti", "__fixunsdfsi", "__fixunsdfdi",
+    "__fixunsdfti", "__udivsi3", "__umodsi3", "__udivmodsi4", "__udivdi3",
+    "__udivmoddi4", "__umoddi3", "__udivti3", "__udivmodti4", "__umodti3", "memcpy",
+    "memmove", "memset", "memcmp", "__powisf2", "__powidf2", "__addsf3", "__adddf3",
+    "__subsf3", "__subdf3", "__divsi3", "__divdi3", "__divti3", "__divdf3", "__divsf3",
+    "__modsi3", "__moddi3", "__modti3", "__divmodsi4", "__divmoddi4", "__muldi3",
+    "__multi3", "__muldf3", "__mulsf3", "__mulosi4", "__mulodi4", "__muloti4",
+    "__lesf2", "__gesf2", "__unordsf2", "__eqsf2", "__ltsf2", "__nesf2", "__gtsf2",
+    "__ledf2", "__gedf2", "__unorddf2", "__eqdf2", "__ltdf2", "__nedf2", "__gtdf2",
+    "rust_eh_personality", "__extendsfdf2",
+];
+
+/// Removes what's unused from `module`, adding what was removed to `stats`.
+pub fn run(module: &mut Module, stats: &mut GcStats) {
+    // The name section refers to functions by index so it needs to be
+    // updated too, and if we can't parse it it'd just be wrong afterwards.
+    let parsed = match mem::take(module).parse_names() {
+        Ok(m) => m,
+        Err((_, mut m)) => {
+            m.sections_mut().retain(|s| match *s {
+                Section::Custom(ref s) => s.name() != "name",
+                _ => true,
+            });
+            m
+        }
+    };
+    *module = parsed;
+
+    let live = Live::find(module);
+    let functions = live.functions.remap();
+    let globals = live.globals.remap();
+    let types = live.types.remap();
+    let memory = live.memory;
+    let func = |i: &mut u32| {
+        *i = functions[*i as usize].expect("removed a function which is still used");
+    };
+    let global = |i: &mut u32| {
+        *i = globals[*i as usize].expect("removed a global which is still used");
+    };
+    let ty = |i: &mut u32| {
+        *i = types[*i as usize].expect("removed a type which is still used");
+    };
+    // Names of functions which don't exist are dropped too.
+    let remapped = |i: u32| functions.get(i as usize).and_then(|i| *i);
+    let code = |ops: &mut Vec<Opcode>| {
+        for op in ops.iter_mut() {
+            match *op {
+                Opcode::Call(ref mut i) => func(i),
+                Opcode::CallIndirect(ref mut i, _) => ty(i),
+                Opcode::GetGlobal(ref mut i) |
+                Opcode::SetGlobal(ref mut i) => global(i),
+                _ => {}
+            }
+        }
+    };
+
+    let imported_functions = live.imported_functions;
+    let imported_globals = live.imported_globals;
+    stats.functions_removed += functions.iter().filter(|i| i.is_none()).count();
+    for section in module.sections_mut() {
+        match *section {
+            Section::Type(ref mut s) => {
+                let mut idx = 0;
+                s.types_mut().retain(|_| {
+                    idx += 1;
+                    types[idx - 1].is_some()
+                });
+            }
+            Section::Import(ref mut s) => {
+                let (mut f, mut g) = (0, 0);
+                s.entries_mut().retain(|entry| {
+                    match *entry.external() {
+                        External::Function(_) => {
+                            f += 1;
+                            functions[f - 1].is_some()
+                        }
+                        External::Global(_) => {
+                            g += 1;
+                            globals[g - 1].is_some()
+                        }
+                        External::Memory(_) | External::Table(_) => true,
+                    }
+                });
+                for entry in s.entries_mut() {
+                    if let External::Function(ref mut i) = *entry.external_mut() {
+                        ty(i);
+                    }
+                }
+            }
+            Section::Function(ref mut s) => {
+                let mut idx = imported_functions;
+                s.entries_mut().retain(|_| {
+                    idx += 1;
+                    functions[idx - 1].is_some()
+                });
+                for f in s.entries_mut() {
+                    ty(f.type_ref_mut());
+                }
+            }
+            Section::Memory(ref mut s) if !memory => s.entries_mut().clear(),
+            Section::Global(ref mut s) => {
+                let mut idx = imported_globals;
+                s.entries_mut().retain(|_| {
+                    idx += 1;
+                    globals[idx - 1].is_some()
+                });
+                for g in s.entries_mut() {
+                    code(g.init_expr_mut().code_mut());
+                }
+            }
+            Section::Export(ref mut s) => {
+                s.entries_mut().retain(|e| !BUILTINS.contains(&e.field()));
+                for e in s.entries_mut() {
+                    match *e.internal_mut() {
+                        Internal::Function(ref mut i) => func(i),
+                        Internal::Global(ref mut i) => global(i),
+                        Internal::Memory(_) | Internal::Table(_) => {}
+                    }
+                }
+            }
+            Section::Start(ref mut i) => func(i),
+            Section::Element(ref mut s) => {
+                for seg in s.entries_mut() {
+                    for i in seg.members_mut() {
+                        func(i);
+                    }
+                    code(seg.offset_mut().code_mut());
+                }
+            }
+            Section::Code(ref mut s) => {
+                let mut idx = imported_functions;
+                let mut removed = 0;
+                s.bodies_mut().retain(|body| {
+                    idx += 1;
+                    if functions[idx - 1].is_some() {
+                        return true
+                    }
+                    removed += serialized_size(body.clone());
+                    false
+                });
+                stats.bytes_removed += removed;
+                for body in s.bodies_mut() {
+                    code(body.code_mut().elements_mut());
+                }
+            }
+            Section::Data(ref mut s) => {
+                if memory {
+                    for seg in s.entries_mut() {
+                        code(seg.offset_mut().code_mut());
+                    }
+                } else {
+                    for seg in s.entries_mut().drain(..) {
+                        stats.bytes_removed += serialized_size(seg);
+                    }
+                }
+            }
+            Section::Name(NameSection::Function(ref mut f)) => {
+                let map = f.names_mut();
+                let new = IndexMap::with_capacity(map.len());
+                for (i, name) in mem::replace(map, new) {
+                    if let Some(i) = remapped(i) {
+                        map.insert(i, name);
+                    }
+                }
+            }
+            Section::Name(NameSection::Local(ref mut l)) => {
+                let map = l.local_names_mut();
+                let new = IndexMap::with_capacity(map.len());
+                for (i, names) in mem::replace(map, new) {
+                    if let Some(i) = remapped(i) {
+                        map.insert(i, names);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    module.sections_mut().retain(|s| match *s {
+        Section::Type(ref s) => !s.types().is_empty(),
+        Section::Import(ref s) => !s.entries().is_empty(),
+        Section::Function(ref s) => !s.entries().is_empty(),
+        Section::Memory(ref s) => !s.entries().is_empty(),
+        Section::Global(ref s) => !s.entries().is_empty(),
+        Section::Export(ref s) => !s.entries().is_empty(),
+        Section::Code(ref s) => !s.bodies().is_empty(),
+        Section::Data(ref s) => !s.entries().is_empty(),
+        _ => true,
+    });
+}
+
+fn serialized_size<T: Serialize>(t: T) -> usize {
+    let mut bytes = Vec::new();
+    t.serialize(&mut bytes).map(|_| bytes.len()).unwrap_or(0)
+}
+
+/// Which items of one index space are used.
+struct Used(Vec<bool>);
+
+impl Used {
+    fn new(len: usize) -> Used {
+        Used(vec![false; len])
+    }
+
+    /// Marks `idx` as used, returning whether it wasn't already.
+    fn insert(&mut self, idx: u32) -> bool {
+        match self.0.get_mut(idx as usize) {
+            Some(used) if !*used => {
+                *used = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The new index of each item, `None` for those which are removed.
+    fn remap(&self) -> Vec<Option<u32>> {
+        let mut next = 0;
+        self.0.iter()
+            .map(|&used| {
+                if !used {
+                    return None
+                }
+                next += 1;
+                Some(next - 1)
+            })
+            .collect()
+    }
+}
+
+/// What in a module is used.
+struct Live {
+    functions: Used,
+    globals: Used,
+    types: Used,
+    /// Whether the memory is, in which case its data segments are too.
+    memory: bool,
+    imported_functions: usize,
+    imported_globals: usize,
+}
+
+impl Live {
+    fn find(module: &Module) -> Live {
+        // The type of each function and global import, by their index.
+        let mut function_imports = Vec::new();
+        let mut global_imports = 0;
+        let mut memory = false;
+        if let Some(s) = module.import_section() {
+            for entry in s.entries() {
+                match *entry.external() {
+                    External::Function(ty) => function_imports.push(ty),
+                    External::Global(_) => global_imports += 1,
+                    External::Memory(_) => memory = true,
+                    External::Table(_) => {}
+                }
+            }
+        }
+        let defined = module.function_section().map(|s| s.entries()).unwrap_or(&[]);
+        let bodies = module.code_section().map(|s| s.bodies()).unwrap_or(&[]);
+        let global_entries = module.global_section().map(|s| s.entries()).unwrap_or(&[]);
+        let mut live = Live {
+            functions: Used::new(function_imports.len() + defined.len()),
+            globals: Used::new(global_imports + global_entries.len()),
+            types: Used::new(module.type_section().map(|s| s.types().len()).unwrap_or(0)),
+            memory,
+            imported_functions: function_imports.len(),
+            imported_globals: global_imports,
+        };
+
+        let mut functions = Vec::new();
+        let mut globals = Vec::new();
+        if let Some(s) = module.export_section() {
+            for e in s.entries() {
+                if BUILTINS.contains(&e.field()) {
+                    continue
+                }
+                match *e.internal() {
+                    Internal::Function(i) => functions.push(i),
+                    Internal::Global(i) => globals.push(i),
+                    Internal::Memory(_) => live.memory = true,
+                    Internal::Table(_) => {}
+                }
+            }
+        }
+        if let Some(i) = module.start_section() {
+            functions.push(i);
+        }
+        if let Some(s) = module.elements_section() {
+            for seg in s.entries() {
+                functions.extend(seg.members());
+                live.code(seg.offset().code(), &mut functions, &mut globals);
+            }
+        }
+
+        // Tables are always kept, so the types `call_indirect` refers to are
+        // found as the code using it is.
+        while !functions.is_empty() || !globals.is_empty() {
+            while let Some(i) = functions.pop() {
+                if !live.functions.insert(i) {
+                    continue
+                }
+                let i = i as usize;
+                if i < function_imports.len() {
+                    live.types.insert(function_imports[i]);
+                    continue
+                }
+                let i = i - function_imports.len();
+                live.types.insert(defined[i].type_ref());
+                live.code(bodies[i].code().elements(), &mut functions, &mut globals);
+            }
+            while let Some(i) = globals.pop() {
+                if !live.globals.insert(i) {
+                    continue
+                }
+                if let Some(g) = (i as usize).checked_sub(global_imports) {
+                    live.code(global_entries[g].init_expr().code(), &mut functions, &mut globals);
+                }
+            }
+        }
+
+        // Data segments are only kept along with the memory, but what their
+        // offsets refer to (only imported globals) must then be kept too.
+        if live.memory {
+            if let Some(s) = module.data_section() {
+                for seg in s.entries() {
+                    live.code(seg.offset().code(), &mut functions, &mut globals);
+                }
+            }
+            for i in globals {
+                live.globals.insert(i);
+            }
+        }
+        live
+    }
+
+    /// Marks what `ops` use, adding the functions and globals it refers to
+    /// to those left to look at.
+    fn code(&mut self, ops: &[Opcode], functions: &mut Vec<u32>, globals: &mut Vec<u32>) {
+        for op in ops {
+            match *op {
+                Opcode::Call(i) => functions.push(i),
+                Opcode::CallIndirect(i, _) => {
+                    self.types.insert(i);
+                }
+                Opcode::GetGlobal(i) | Opcode::SetGlobal(i) => globals.push(i),
+                Opcode::I32Load(..) | Opcode::I64Load(..) | Opcode::F32Load(..) |
+                Opcode::F64Load(..) | Opcode::I32Load8S(..) | Opcode::I32Load8U(..) |
+                Opcode::I32Load16S(..) | Opcode::I32Load16U(..) | Opcode::I64Load8S(..) |
+                Opcode::I64Load8U(..) | Opcode::I64Load16S(..) | Opcode::I64Load16U(..) |
+                Opcode::I64Load32S(..) | Opcode::I64Load32U(..) | Opcode::I32Store(..) |
+                Opcode::I64Store(..) | Opcode::F32Store(..) | Opcode::F64Store(..) |
+                Opcode::I32Store8(..) | Opcode::I32Store16(..) | Opcode::I64Store8(..) |
+                Opcode::I64Store16(..) | Opcode::I64Store32(..) | Opcode::CurrentMemory(_) |
+                Opcode::GrowMemory(_) => self.memory = true,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! A tiny interpreter for the `__wbindgen_describe_*` shims.
+//!
+//! The descriptor shims emitted by `#[wasm_bindgen]` only ever push constants
+//! onto the stack, shuffle the stack pointer around, call each other and
+//! finally call the `__wbindgen_describe` import with each word of the
+//! descriptor. Instantiating the whole module with wasmi to run them means
+//! resolving every import and allocating all of linear memory, so instead we
+//! walk just the function bodies reachable from a describe export here.
+//!
+//! Only a handful of opcodes are supported. Anything else causes
+//! `interpret` to return an error describing what went wrong, and the caller
+//! is expected to fall back to executing the shim with wasmi.
+
+use std::collections::HashMap;
+
+use parity_wasm::elements::*;
+
+/// Maximum call depth before we give up, guarding against runaway recursion.
+const MAX_DEPTH: usize = 512;
+
+pub struct Interpreter<'a> {
+    module: &'a Module,
+
+    /// Number of imported functions, which come first in the function index
+    /// space.
+    imported_functions: u32,
+
+    /// Index of the `__wbindgen_describe` import, if any.
+    describe_idx: Option<u32>,
+
+    /// Initial value of each global in the module, or `None` if it's
+    /// imported or not a constant `i32`.
+    initial_globals: Vec<Option<i32>>,
+
+    /// Current value of each global while running a shim.
+    globals: Vec<Option<i32>>,
+
+    /// Sparse memory of all words stored by the shims (typically into the
+    /// shadow stack). Reading memory that wasn't written here isn't supported.
+    mem: HashMap<u32, i32>,
+
+    /// Words passed to `__wbindgen_describe` so far.
+    descriptor: Vec<u32>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(module: &'a Module) -> Interpreter<'a> {
+        let mut imported_functions = 0;
+        let mut describe_idx = None;
+        let mut globals = Vec::new();
+        if let Some(s) = module.import_section() {
+            for entry in s.entries() {
+                match *entry.external() {
+                    External::Function(_) => {
+                        if entry.module() == "__wbindgen_placeholder__" &&
+                            entry.field() == "__wbindgen_describe"
+                        {
+                            describe_idx = Some(imported_functions);
+                        }
+                        imported_functions += 1;
+                    }
+                    External::Global(_) => globals.push(None),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(s) = module.global_section() {
+            for entry in s.entries() {
+                let init = match entry.init_expr().code().first() {
+                    Some(&Opcode::I32Const(c)) => Some(c),
+                    _ => None,
+                };
+                globals.push(init);
+            }
+        }
+
+        Interpreter {
+            module,
+            imported_functions,
+            describe_idx,
+            initial_globals: globals.clone(),
+            globals,
+            mem: HashMap::new(),
+            descriptor: Vec::new(),
+        }
+    }
+
+    /// Runs the exported function `name`, returning the words it passed to
+    /// `__wbindgen_describe`.
+    pub fn interpret(&mut self, name: &str) -> Result<Vec<u32>, String> {
+        let idx = self.module.export_section()
+            .and_then(|s| {
+                s.entries().iter().find(|e| e.field() == name)
+            })
+            .and_then(|e| match *e.internal() {
+                Internal::Function(i) => Some(i),
+                _ => None,
+            })
+            .ok_or_else(|| format!("failed to find function export `{}`", name))?;
+
+        // The shims restore the stack pointer on the way out, but reset all
+        // state before each call regardless in case a previous one failed.
+        self.globals.clone_from(&self.initial_globals);
+        self.mem.clear();
+        self.descriptor.truncate(0);
+        self.call(idx, Vec::new(), 0)?;
+        Ok(self.descriptor.split_off(0))
+    }
+
+    fn call(&mut self, idx: u32, args: Vec<i32>, depth: usize)
+        -> Result<Option<i32>, String>
+    {
+        if depth > MAX_DEPTH {
+            return Err("call stack too deep".to_string())
+        }
+        if idx < self.imported_functions {
+            if Some(idx) == self.describe_idx && args.len() == 1 {
+                self.descriptor.push(args[0] as u32);
+                return Ok(None)
+            }
+            return Err(format!("call to unsupported import {}", idx))
+        }
+        let local_idx = (idx - self.imported_functions) as usize;
+        let module = self.module;
+        let body = module.code_section()
+            .and_then(|s| s.bodies().get(local_idx))
+            .ok_or_else(|| format!("missing body for function {}", idx))?;
+        let ty = self.function_type(idx)?;
+
+        let mut locals = args;
+        for local in body.locals() {
+            match local.value_type() {
+                ValueType::I32 => {}
+                t => return Err(format!("unsupported local type {:?}", t)),
+            }
+            locals.extend((0..local.count()).map(|_| 0));
+        }
+
+        let mut stack = Vec::new();
+        let mut blocks = 0;
+        for op in body.code().elements() {
+            match *op {
+                Opcode::Nop => {}
+                Opcode::I32Const(c) => stack.push(c),
+                Opcode::GetLocal(i) => {
+                    let v = *locals.get(i as usize)
+                        .ok_or_else(|| format!("invalid local {}", i))?;
+                    stack.push(v);
+                }
+                Opcode::SetLocal(i) => {
+                    let v = pop(&mut stack)?;
+                    *locals.get_mut(i as usize)
+                        .ok_or_else(|| format!("invalid local {}", i))? = v;
+                }
+                Opcode::TeeLocal(i) => {
+                    let v = *stack.last().ok_or_else(|| "empty stack".to_string())?;
+                    *locals.get_mut(i as usize)
+                        .ok_or_else(|| format!("invalid local {}", i))? = v;
+                }
+                Opcode::GetGlobal(i) => {
+                    let v = self.globals.get(i as usize).and_then(|g| *g)
+                        .ok_or_else(|| format!("unsupported global {}", i))?;
+                    stack.push(v);
+                }
+                Opcode::SetGlobal(i) => {
+                    let v = pop(&mut stack)?;
+                    *self.globals.get_mut(i as usize)
+                        .ok_or_else(|| format!("invalid global {}", i))? = Some(v);
+                }
+                Opcode::I32Add => binop(&mut stack, |a, b| a.wrapping_add(b))?,
+                Opcode::I32Sub => binop(&mut stack, |a, b| a.wrapping_sub(b))?,
+                Opcode::I32Mul => binop(&mut stack, |a, b| a.wrapping_mul(b))?,
+                Opcode::I32And => binop(&mut stack, |a, b| a & b)?,
+                Opcode::I32Or => binop(&mut stack, |a, b| a | b)?,
+                Opcode::I32Load(_align, offset) => {
+                    let addr = (pop(&mut stack)? as u32).wrapping_add(offset);
+                    let v = *self.mem.get(&addr)
+                        .ok_or_else(|| format!("load from unwritten address {}", addr))?;
+                    stack.push(v);
+                }
+                Opcode::I32Store(_align, offset) => {
+                    let v = pop(&mut stack)?;
+                    let addr = (pop(&mut stack)? as u32).wrapping_add(offset);
+                    self.mem.insert(addr, v);
+                }
+                Opcode::Drop => {
+                    pop(&mut stack)?;
+                }
+                Opcode::Call(f) => {
+                    let nparams = self.function_type(f)?.params().len();
+                    if stack.len() < nparams {
+                        return Err("not enough arguments on the stack".to_string())
+                    }
+                    let args = stack.split_off(stack.len() - nparams);
+                    if let Some(v) = self.call(f, args, depth + 1)? {
+                        stack.push(v);
+                    }
+                }
+                // Branches aren't supported so blocks are just straight-line
+                // code, and the outermost `end` terminates the function.
+                Opcode::Block(BlockType::NoResult) => blocks += 1,
+                Opcode::End if blocks > 0 => blocks -= 1,
+                Opcode::Return | Opcode::End => break,
+                ref other => return Err(format!("unsupported opcode {:?}", other)),
+            }
+        }
+
+        match ty.return_type() {
+            Some(ValueType::I32) => Ok(Some(pop(&mut stack)?)),
+            Some(t) => Err(format!("unsupported return type {:?}", t)),
+            None => Ok(None),
+        }
+    }
+
+    fn function_type(&self, idx: u32) -> Result<&'a FunctionType, String> {
+        let module = self.module;
+        let type_ref = if idx < self.imported_functions {
+            module.import_section()
+                .and_then(|s| {
+                    s.entries()
+                        .iter()
+                        .filter_map(|e| match *e.external() {
+                            External::Function(t) => Some(t),
+                            _ => None,
+                        })
+                        .nth(idx as usize)
+                })
+        } else {
+            module.function_section()
+                .and_then(|s| s.entries().get((idx - self.imported_functions) as usize))
+                .map(|f| f.type_ref())
+        };
+        let ty = type_ref
+            .and_then(|t| module.type_section().and_then(|s| s.types().get(t as usize)))
+            .ok_or_else(|| format!("missing type for function {}", idx))?;
+        match *ty {
+            Type::Function(ref f) => Ok(f),
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<i32>) -> Result<i32, String> {
+    stack.pop().ok_or_else(|| "empty stack".to_string())
+}
+
+fn binop(stack: &mut Vec<i32>, f: fn(i32, i32) -> i32) -> Result<(), String> {
+    let b = pop(stack)?;
+    let a = pop(stack)?;
+    stack.push(f(a, b));
+    Ok(())
+}
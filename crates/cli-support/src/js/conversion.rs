@@ -0,0 +1,136 @@
+//! The conversions shims make of the values they pass on, and the rendering
+//! of a shim's body from them.
+//!
+//! Both directions describe each argument (and bits of state like `this` or
+//! the stack pointer) as a `Conversion`, so that whatever a conversion sets
+//! up is released on every path out of the shim, including a later
+//! conversion throwing before the call is made.
+
+use super::indent;
+
+/// How a shim passes one value to the function it calls.
+#[derive(Clone, Default)]
+pub struct Conversion {
+    /// Statements run before the call, like copying a string into wasm's
+    /// memory, ending in a newline.
+    pub prologue: String,
+
+    /// The expressions passed to the call for this value.
+    pub args: Vec<String>,
+
+    /// What undoes `prologue`.
+    pub cleanup: Cleanup,
+}
+
+#[derive(Clone, Default)]
+pub enum Cleanup {
+    #[default]
+    None,
+
+    /// Statements run once the call is over, whether it returned or threw,
+    /// or when a later conversion throws. This is for what's only lent for
+    /// the duration of the call, like a borrowed slice.
+    Finally(String),
+
+    /// Statements run only when a later conversion throws, so that the call
+    /// is never made. This is for what the call takes ownership of, like a
+    /// string moved into Rust, which is Rust's to free once it has it.
+    UnlessCalled(String),
+}
+
+impl Conversion {
+    /// A conversion which only runs `prologue`, like a debug assertion.
+    pub fn prologue(prologue: &str) -> Conversion {
+        Conversion {
+            prologue: lines(prologue),
+            ..Conversion::default()
+        }
+    }
+
+    /// A conversion which passes `arg` as is.
+    pub fn arg(arg: &str) -> Conversion {
+        Conversion {
+            args: vec![arg.to_string()],
+            ..Conversion::default()
+        }
+    }
+}
+
+/// Normalizes the statements `s` to end each line, including the last, in a
+/// newline.
+pub fn lines(s: &str) -> String {
+    let mut ret = String::new();
+    for line in s.lines() {
+        ret.push_str(line);
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Renders the statements running `conversions` in order and then `call`,
+/// which uses their `args`.
+///
+/// The cleanups of a conversion are scheduled in a `finally` covering all
+/// that follows it, so a conversion with a cleanup followed by others which
+/// can't throw (as they don't have a prologue) shares its `try` with them.
+/// `UnlessCalled` cleanups are skipped once the local `called` is set, which
+/// is only declared if there's anything for them to cover.
+pub fn render(conversions: &[Conversion], call: &str, called: &str) -> String {
+    render_from(conversions, call, called, false)
+}
+
+fn render_from(conversions: &[Conversion], call: &str, called: &str, flagged: bool) -> String {
+    let mut dst = String::new();
+    let mut rest = conversions;
+    while let Some((conversion, tail)) = rest.split_first() {
+        dst.push_str(&conversion.prologue);
+        rest = tail;
+        if let Cleanup::None = conversion.cleanup {
+            continue
+        }
+        let mut cleanups = vec![&conversion.cleanup];
+        while let Some((next, tail)) = rest.split_first() {
+            if !next.prologue.is_empty() {
+                break
+            }
+            cleanups.push(&next.cleanup);
+            rest = tail;
+        }
+
+        // What's moved into the call can only leak if a later prologue
+        // throws, and there's none when nothing is left.
+        let mut finally = String::new();
+        let mut unless_called = false;
+        for cleanup in cleanups {
+            match *cleanup {
+                Cleanup::None => {}
+                Cleanup::Finally(ref s) => finally.push_str(&lines(s)),
+                Cleanup::UnlessCalled(_) if rest.is_empty() => {}
+                Cleanup::UnlessCalled(ref s) => {
+                    unless_called = true;
+                    finally.push_str(&format!("if (!{}) {{\n{}}}\n", called, indent(s)));
+                }
+            }
+        }
+        if finally.is_empty() {
+            continue
+        }
+        if unless_called && !flagged {
+            dst.push_str(&format!("let {} = false;\n", called));
+        }
+        let body = render_from(rest, call, called, flagged || unless_called);
+        dst.push_str(&format!("\
+            try {{\n\
+                {}\
+            }} finally {{\n\
+                {}\
+            }}\n\
+        ", indent(&body), indent(&finally)));
+        return dst
+    }
+    if flagged {
+        dst.push_str(&format!("{} = true;\n", called));
+    }
+    dst.push_str(call);
+    dst
+}
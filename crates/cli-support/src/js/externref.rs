@@ -0,0 +1,573 @@
+//! Passes `JsValue`s between JS and wasm as `externref`s, see
+//! `Bindgen::reference_types`.
+//!
+//! The JS values wasm holds on to are kept in a table of `externref`s, whose
+//! slots are the indices Rust works with, and the exports and imports taking
+//! or returning them are wrapped by functions moving them in and out of the
+//! table. Slots below `JSIDX_RESERVED` hold `undefined`, `null`, `true` and
+//! `false`, which the glue puts there, and an empty slot is a free one.
+//!
+//! `parity_wasm` can't describe `externref`s, tables of them or the
+//! instructions using them, so what's added here has `i32`s in place of the
+//! `externref`s and bodies which only trap, and `patch` puts the real ones in
+//! once the module is serialized. This runs after gc, so nothing renumbers
+//! the functions in between.
+
+use std::collections::BTreeMap;
+
+use parity_wasm::elements::*;
+
+use super::{add_function, exported_function, function_type, redirect_calls, repoint_export,
+            JSIDX_RESERVED};
+use {each_section, read_leb, replace_section};
+
+/// The export of the table of JS values.
+pub const TABLE: &str = "__wbindgen_externrefs";
+
+/// The export storing a JS value in a free slot of the table, returning its
+/// index, which is `null`'s for `null`.
+pub const ALLOC: &str = "__wbindgen_externref_alloc";
+
+/// The export freeing a slot of the table, unless it's a reserved one.
+pub const DEALLOC: &str = "__wbindgen_externref_dealloc";
+
+/// Which of the values an export or import takes and returns are
+/// `externref`s.
+#[derive(Clone, Debug, Default)]
+pub struct Signature {
+    /// The positions of the `externref` parameters, each with whether the
+    /// callee owns it (a `JsValue`) rather than borrowing it (a `&JsValue`).
+    pub params: Vec<(usize, bool)>,
+
+    /// Whether the result is an `externref`.
+    pub ret: bool,
+}
+
+impl Signature {
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty() && !self.ret
+    }
+
+    fn is_externref(&self, param: usize) -> bool {
+        self.params.iter().any(|p| p.0 == param)
+    }
+}
+
+/// What's left to do once the module `run` rewrote is serialized, see
+/// `patch`.
+#[derive(Debug, Default)]
+pub struct Patches {
+    /// The types whose `externref`s are `i32`s for now.
+    types: Vec<(usize, Signature)>,
+
+    /// The encoded bodies of the functions added, by their index in the code
+    /// section.
+    bodies: Vec<(usize, Vec<u8>)>,
+
+    /// The index of the table of JS values among those the module defines,
+    /// which is a table of functions for now.
+    table: usize,
+}
+
+/// Adds the table of JS values and the functions managing it to `module`,
+/// and wraps the `exports` and `imports`, by name, to pass their
+/// `externref`s.
+///
+/// Rust calls the `__wbindgen_object_clone_ref` and
+/// `__wbindgen_object_drop_ref` intrinsics to clone and drop `JsValue`s,
+/// whose uses are redirected to do so in wasm.
+pub fn run(module: &mut Module,
+           exports: &BTreeMap<String, Signature>,
+           imports: &BTreeMap<String, Signature>)
+    -> Patches
+{
+    let mut patches = Patches::default();
+    let (table_idx, table) = add_table(module);
+    patches.table = table_idx;
+    let hint = add_hint(module);
+    let imported = module.import_count(ImportCountType::Function) as u32;
+
+    let dealloc = patches.add(module, vec![ValueType::I32], None, Signature::default());
+    let mut body = Body::new(0);
+    body.op(Opcode::GetLocal(0))
+        .op(Opcode::I32Const(JSIDX_RESERVED as i32))
+        .op(Opcode::I32LtU)
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::Return)
+        .op(Opcode::End)
+        .op(Opcode::GetLocal(0))
+        .ref_null()
+        .table(TABLE_SET, table)
+        // The next search for a free slot starts here if it's the lowest.
+        .op(Opcode::GetLocal(0))
+        .op(Opcode::GetGlobal(hint))
+        .op(Opcode::I32LtU)
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::GetLocal(0))
+        .op(Opcode::SetGlobal(hint))
+        .op(Opcode::End);
+    patches.body(module, dealloc, body);
+
+    let sig = Signature { params: vec![(0, true)], ret: false };
+    let alloc = patches.add(module, vec![ValueType::I32], Some(ValueType::I32), sig);
+    let mut body = Body::new(1);
+    body.op(Opcode::GetLocal(0))
+        .raw(&[REF_IS_NULL])
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::I32Const(2))
+        .op(Opcode::Return)
+        .op(Opcode::End)
+        // Look for an empty slot from the hint on, doubling the table if
+        // there's none.
+        .op(Opcode::GetGlobal(hint))
+        .op(Opcode::SetLocal(1))
+        .op(Opcode::Block(BlockType::NoResult))
+        .op(Opcode::Loop(BlockType::NoResult))
+        .op(Opcode::GetLocal(1))
+        .table(TABLE_SIZE, table)
+        .op(Opcode::I32LtU)
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::GetLocal(1))
+        .table(TABLE_GET, table)
+        .raw(&[REF_IS_NULL])
+        .op(Opcode::BrIf(2))
+        .op(Opcode::GetLocal(1))
+        .op(Opcode::I32Const(1))
+        .op(Opcode::I32Add)
+        .op(Opcode::SetLocal(1))
+        .op(Opcode::Br(1))
+        .op(Opcode::End)
+        .op(Opcode::End)
+        .ref_null()
+        .op(Opcode::GetLocal(1))
+        .table(TABLE_GROW, table)
+        .op(Opcode::I32Const(-1))
+        .op(Opcode::I32Eq)
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::Unreachable)
+        .op(Opcode::End)
+        .op(Opcode::End)
+        .op(Opcode::GetLocal(1))
+        .op(Opcode::GetLocal(0))
+        .table(TABLE_SET, table)
+        .op(Opcode::GetLocal(1))
+        .op(Opcode::I32Const(1))
+        .op(Opcode::I32Add)
+        .op(Opcode::SetGlobal(hint))
+        .op(Opcode::GetLocal(1));
+    patches.body(module, alloc, body);
+
+    // Cloning a `JsValue` stores it in a slot of its own, as each is freed
+    // when dropped.
+    let clone = patches.add(module, vec![ValueType::I32], Some(ValueType::I32),
+                            Signature::default());
+    let mut body = Body::new(0);
+    body.op(Opcode::GetLocal(0))
+        .op(Opcode::I32Const(JSIDX_RESERVED as i32))
+        .op(Opcode::I32LtU)
+        .op(Opcode::If(BlockType::NoResult))
+        .op(Opcode::GetLocal(0))
+        .op(Opcode::Return)
+        .op(Opcode::End)
+        .op(Opcode::GetLocal(0))
+        .table(TABLE_GET, table)
+        .op(Opcode::Call(alloc));
+    patches.body(module, clone, body);
+
+    let sig = Signature { params: Vec::new(), ret: true };
+    let take = patches.add(module, vec![ValueType::I32], Some(ValueType::I32), sig);
+    let mut body = Body::new(0);
+    body.op(Opcode::GetLocal(0))
+        .table(TABLE_GET, table)
+        .op(Opcode::GetLocal(0))
+        .op(Opcode::Call(dealloc));
+    patches.body(module, take, body);
+
+    for &(name, idx) in [(ALLOC, alloc), (DEALLOC, dealloc)].iter() {
+        export(module, name, Internal::Function(idx));
+    }
+    if let Some((idx, _)) = imported_function(module, "__wbindgen_object_clone_ref") {
+        redirect_calls(module, idx, clone);
+    }
+    if let Some((idx, _)) = imported_function(module, "__wbindgen_object_drop_ref") {
+        redirect_calls(module, idx, dealloc);
+    }
+
+    // The import gets a type with `externref`s, and everything using it a
+    // function with its original type moving them out of and into the
+    // table instead. What the import owns is freed once it has returned.
+    for (name, sig) in imports.iter() {
+        let (idx, ty) = match imported_function(module, name) {
+            Some(import) => import,
+            None => continue,
+        };
+        let ty = match module.type_section().unwrap().types()[ty as usize] {
+            Type::Function(ref f) => f.clone(),
+        };
+        if !takes_indices(&ty, sig) {
+            continue
+        }
+        let wrapper = imported +
+            module.function_section().map(|s| s.entries().len() as u32).unwrap_or(0);
+        redirect_calls(module, idx, wrapper);
+        let import_ty = add_type(module, ty.clone());
+        patches.types.push((import_ty as usize, sig.clone()));
+        set_import_type(module, name, import_ty);
+        patches.add(module, ty.params().to_vec(), ty.return_type(), Signature::default());
+
+        let mut body = Body::new(0);
+        for i in 0..ty.params().len() {
+            body.op(Opcode::GetLocal(i as u32));
+            if sig.is_externref(i) {
+                body.table(TABLE_GET, table);
+            }
+        }
+        body.op(Opcode::Call(idx));
+        if sig.ret {
+            body.op(Opcode::Call(alloc));
+        }
+        for &(i, owned) in sig.params.iter() {
+            if owned {
+                body.op(Opcode::GetLocal(i as u32)).op(Opcode::Call(dealloc));
+            }
+        }
+        patches.body(module, wrapper, body);
+    }
+
+    // The export is replaced by a function taking and returning the
+    // `externref`s, storing them in the table for the original. What's only
+    // borrowed is freed once it has returned.
+    for (name, sig) in exports.iter() {
+        let idx = match exported_function(module, name) {
+            Some(i) if i >= imported => i,
+            _ => continue,
+        };
+        let ty = function_type(module, idx).clone();
+        if !takes_indices(&ty, sig) {
+            continue
+        }
+        let wrapper = patches.add(module, ty.params().to_vec(), ty.return_type(), sig.clone());
+        repoint_export(module, name, wrapper);
+
+        let borrowed = sig.params.iter().filter(|p| !p.1).count() as u32;
+        let mut body = Body::new(borrowed);
+        let first = ty.params().len() as u32;
+        let mut local = first;
+        for i in 0..ty.params().len() {
+            body.op(Opcode::GetLocal(i as u32));
+            match sig.params.iter().find(|p| p.0 == i) {
+                Some(&(_, true)) => {
+                    body.op(Opcode::Call(alloc));
+                }
+                Some(&(_, false)) => {
+                    body.op(Opcode::Call(alloc)).op(Opcode::TeeLocal(local));
+                    local += 1;
+                }
+                None => {}
+            }
+        }
+        body.op(Opcode::Call(idx));
+        if sig.ret {
+            body.op(Opcode::Call(take));
+        }
+        for lent in first..local {
+            body.op(Opcode::GetLocal(lent)).op(Opcode::Call(dealloc));
+        }
+        patches.body(module, wrapper, body);
+    }
+    patches
+}
+
+/// Gives the wasm file `wasm`, serialized from the module `patches` was
+/// returned for, its `externref`s and the bodies of the functions added.
+pub fn patch(wasm: &mut Vec<u8>, patches: &Patches) {
+    let mut externrefs = Vec::new();
+    let mut code = None;
+    each_section(wasm, |id, offset, contents| {
+        let mut pos = 0;
+        let count = read_leb(contents, &mut pos);
+        match id {
+            // Every type is a function type: 0x60, the parameters and the
+            // results, each a count followed by a byte per value type.
+            1 => {
+                for i in 0..count {
+                    pos += 1;
+                    let params = read_leb(contents, &mut pos);
+                    let start = offset + pos;
+                    pos += params;
+                    let results = read_leb(contents, &mut pos);
+                    if let Some(sig) = patches.types.iter().find(|t| t.0 == i).map(|t| &t.1) {
+                        externrefs.extend(sig.params.iter().map(|p| start + p.0));
+                        if sig.ret && results == 1 {
+                            externrefs.push(offset + pos);
+                        }
+                    }
+                    pos += results;
+                }
+            }
+            // Each table is its element type followed by its limits.
+            4 => {
+                for i in 0..count {
+                    if i == patches.table {
+                        externrefs.push(offset + pos);
+                    }
+                    pos += 1;
+                    let flags = contents.get(pos).cloned().unwrap_or(0);
+                    pos += 1;
+                    read_leb(contents, &mut pos);
+                    if flags & 1 != 0 {
+                        read_leb(contents, &mut pos);
+                    }
+                }
+            }
+            10 => code = Some(contents.to_vec()),
+            _ => {}
+        }
+    });
+    for pos in externrefs {
+        wasm[pos] = EXTERNREF;
+    }
+
+    let old = match code {
+        Some(old) => old,
+        None => return,
+    };
+    let mut pos = 0;
+    let count = read_leb(&old, &mut pos);
+    let mut contents = old[..pos].to_vec();
+    for i in 0..count {
+        let start = pos;
+        let size = read_leb(&old, &mut pos);
+        pos += size;
+        match patches.bodies.iter().find(|b| b.0 == i).map(|b| &b.1) {
+            Some(body) => {
+                leb(&mut contents, body.len() as u32);
+                contents.extend_from_slice(body);
+            }
+            None => contents.extend_from_slice(&old[start..pos]),
+        }
+    }
+    contents.extend_from_slice(&old[pos..]);
+    replace_section(wasm, 10, &contents);
+}
+
+const EXTERNREF: u8 = 0x6f;
+const REF_NULL: u8 = 0xd0;
+const REF_IS_NULL: u8 = 0xd1;
+const TABLE_GET: &[u8] = &[0x25];
+const TABLE_SET: &[u8] = &[0x26];
+const TABLE_GROW: &[u8] = &[0xfc, 15];
+const TABLE_SIZE: &[u8] = &[0xfc, 16];
+
+impl Patches {
+    /// Adds a function taking `params` and returning `ret`, of which `sig`
+    /// says which are `externref`s, returning its index. Its body is set
+    /// with `body`.
+    /// The TypeScript type of the parameter `Some(i)` or the result `None`
+    /// of the functions of the type `ty`, if it's an `externref`.
+    pub fn typescript_type(&self, ty: u32, value: Option<usize>) -> Option<&'static str> {
+        let sig = self.types.iter().find(|t| t.0 == ty as usize).map(|t| &t.1)?;
+        let externref = match value {
+            Some(param) => sig.is_externref(param),
+            None => sig.ret,
+        };
+        if externref { Some("any") } else { None }
+    }
+
+    fn add(&mut self,
+           module: &mut Module,
+           params: Vec<ValueType>,
+           ret: Option<ValueType>,
+           sig: Signature)
+        -> u32
+    {
+        let code = vec![Opcode::Unreachable, Opcode::End];
+        let idx = add_function(module, FunctionType::new(params, ret), Vec::new(), code);
+        if !sig.is_empty() {
+            let ty = module.type_section().map(|s| s.types().len()).unwrap_or(0) - 1;
+            self.types.push((ty, sig));
+        }
+        idx
+    }
+
+    /// Sets the body of the function `idx` added with `add`.
+    fn body(&mut self, module: &Module, idx: u32, body: Body) {
+        let imported = module.import_count(ImportCountType::Function);
+        self.bodies.push((idx as usize - imported, body.encode()));
+    }
+}
+
+/// The code of a function added by `run`, with `i32` locals after its
+/// parameters.
+struct Body {
+    locals: u32,
+    code: Vec<u8>,
+}
+
+impl Body {
+    fn new(locals: u32) -> Body {
+        Body { locals, code: Vec::new() }
+    }
+
+    fn op(&mut self, op: Opcode) -> &mut Body {
+        op.serialize(&mut self.code).expect("failed to encode an instruction");
+        self
+    }
+
+    fn raw(&mut self, bytes: &[u8]) -> &mut Body {
+        self.code.extend_from_slice(bytes);
+        self
+    }
+
+    /// Appends the instruction `op` on the table `table`.
+    fn table(&mut self, op: &[u8], table: u32) -> &mut Body {
+        self.raw(op);
+        leb(&mut self.code, table);
+        self
+    }
+
+    fn ref_null(&mut self) -> &mut Body {
+        self.raw(&[REF_NULL, EXTERNREF])
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        if self.locals > 0 {
+            ret.push(1);
+            leb(&mut ret, self.locals);
+            ret.push(0x7f);
+        } else {
+            ret.push(0);
+        }
+        ret.extend_from_slice(&self.code);
+        ret.push(0x0b);
+        ret
+    }
+}
+
+fn leb(dst: &mut Vec<u8>, n: u32) {
+    VarUint32::from(n).serialize(dst).expect("failed to encode a number");
+}
+
+/// Whether the parameters and result of `ty` which `sig` says are
+/// `externref`s are the `i32` indices Rust passes them as.
+fn takes_indices(ty: &FunctionType, sig: &Signature) -> bool {
+    sig.params.iter().all(|p| ty.params().get(p.0) == Some(&ValueType::I32)) &&
+        (!sig.ret || ty.return_type() == Some(ValueType::I32))
+}
+
+/// Adds the table of JS values with room for the reserved ones, and exports
+/// it. Returns its index among the tables `module` defines and among all of
+/// them.
+fn add_table(module: &mut Module) -> (usize, u32) {
+    let imported = module.import_count(ImportCountType::Table);
+    let table = TableType::new(JSIDX_RESERVED as u32, None);
+    let defined = match module.table_section().map(|s| s.entries().len()) {
+        Some(defined) => defined,
+        None => {
+            insert_section(module, Section::Table(TableSection::with_entries(Vec::new())));
+            0
+        }
+    };
+    for section in module.sections_mut() {
+        if let Section::Table(ref mut s) = *section {
+            s.entries_mut().push(table);
+        }
+    }
+    let idx = (imported + defined) as u32;
+    export(module, TABLE, Internal::Table(idx));
+    (defined, idx)
+}
+
+/// Adds the global holding the lowest slot of the table which may be free,
+/// returning its index.
+fn add_hint(module: &mut Module) -> u32 {
+    let imported = module.import_count(ImportCountType::Global);
+    let init = InitExpr::new(vec![Opcode::I32Const(JSIDX_RESERVED as i32), Opcode::End]);
+    let global = GlobalEntry::new(GlobalType::new(ValueType::I32, true), init);
+    if module.global_section().is_none() {
+        insert_section(module, Section::Global(GlobalSection::with_entries(Vec::new())));
+    }
+    let mut idx = 0;
+    for section in module.sections_mut() {
+        if let Section::Global(ref mut s) = *section {
+            idx = imported + s.entries().len();
+            s.entries_mut().push(global.clone());
+        }
+    }
+    idx as u32
+}
+
+fn add_type(module: &mut Module, ty: FunctionType) -> u32 {
+    let mut idx = 0;
+    for section in module.sections_mut() {
+        if let Section::Type(ref mut s) = *section {
+            s.types_mut().push(Type::Function(ty.clone()));
+            idx = s.types().len() as u32 - 1;
+        }
+    }
+    idx
+}
+
+fn set_import_type(module: &mut Module, field: &str, ty: u32) {
+    for section in module.sections_mut() {
+        if let Section::Import(ref mut s) = *section {
+            for entry in s.entries_mut() {
+                if entry.field() == field {
+                    *entry.external_mut() = External::Function(ty);
+                }
+            }
+        }
+    }
+}
+
+fn export(module: &mut Module, name: &str, internal: Internal) {
+    for section in module.sections_mut() {
+        if let Section::Export(ref mut s) = *section {
+            s.entries_mut().push(ExportEntry::new(name.to_string(), internal));
+        }
+    }
+}
+
+/// The index and type of the imported function `field`, if there is one.
+fn imported_function(module: &Module, field: &str) -> Option<(u32, u32)> {
+    module.import_section()?
+        .entries()
+        .iter()
+        .filter_map(|e| match *e.external() {
+            External::Function(ty) => Some((e.field(), ty)),
+            _ => None,
+        })
+        .enumerate()
+        .find(|&(_, (f, _))| f == field)
+        .map(|(i, (_, ty))| (i as u32, ty))
+}
+
+/// Inserts the (not yet present) `section` into `module` where the order of
+/// sections puts it.
+fn insert_section(module: &mut Module, section: Section) {
+    let id = section_id(&section);
+    let pos = module.sections()
+        .iter()
+        .position(|s| section_id(s) > id)
+        .unwrap_or(module.sections().len());
+    module.sections_mut().insert(pos, section);
+}
+
+/// The id of `section`, or 0 for custom ones, whose order isn't fixed.
+fn section_id(section: &Section) -> u8 {
+    match *section {
+        Section::Type(_) => 1,
+        Section::Import(_) => 2,
+        Section::Function(_) => 3,
+        Section::Table(_) => 4,
+        Section::Memory(_) => 5,
+        Section::Global(_) => 6,
+        Section::Export(_) => 7,
+        Section::Start(_) => 8,
+        Section::Element(_) => 9,
+        Section::Code(_) => 10,
+        Section::Data(_) => 11,
+        _ => 0,
+    }
+}
@@ -1,25 +1,25 @@
-use super::{indent, Context};
-use descriptor::{Descriptor, Function};
+use std::collections::BTreeSet;
+
+use super::{deprecation_warning, externref, indent, js_string, Context};
+use super::conversion::{self, Cleanup, Conversion};
+use descriptor::{Descriptor, Function, VectorKind};
 
 /// Helper struct for manfuacturing a shim in JS used to translate JS types to
 /// Rust, aka pass from JS back into Rust
 pub struct Js2Rust<'a, 'b: 'a> {
     cx: &'a mut Context<'b>,
 
-    /// Arguments passed to the invocation of the wasm function, aka things that
-    /// are only numbers.
-    rust_arguments: Vec<String>,
+    /// How each argument is passed to the wasm function, in order, along with
+    /// everything else to do before the call, such as converting a string to
+    /// a ptr/length pair, and to undo afterwards.
+    conversions: Vec<Conversion>,
 
     /// Arguments and their types to the JS shim.
     pub js_arguments: Vec<(String, String)>,
 
-    /// Conversions that happen before we invoke the wasm function, such as
-    /// converting a string to a ptr/length pair.
-    prelude: String,
-
-    /// "Destructors" or cleanup that must happen after the wasm function
-    /// finishes. This is scheduled in a `finally` block.
-    finally: String,
+    /// How many of the last `js_arguments` are optional, which TypeScript
+    /// lets callers leave out.
+    trailing_optional: usize,
 
     /// Next global index to write to when passing arguments via the single
     /// global stack.
@@ -30,7 +30,7 @@ pub struct Js2Rust<'a, 'b: 'a> {
 
     /// Typescript expression representing the type of the return value of this
     /// function.
-    ret_ty: String,
+    pub ret_ty: String,
 
     /// Expression used to generate the return value. The string "RET" in this
     /// expression is replaced with the actual wasm invocation eventually.
@@ -39,132 +39,531 @@ pub struct Js2Rust<'a, 'b: 'a> {
     /// Name of the JS shim/function that we're generating, primarily for
     /// TypeScript right now.
     js_name: String,
+
+    /// Whether the generated shim wraps its result (or any exception thrown)
+    /// in a `Promise`.
+    returns_promise: bool,
+
+    /// Whether the shim returns a view into wasm's memory rather than a copy,
+    /// which callers should document.
+    pub returns_view: bool,
+
+    /// Names of the parameters and locals already used in the shim, so that
+    /// generated names never shadow one another.
+    scope: BTreeSet<String>,
+
+    /// The expression of the pointer of `this`, for methods.
+    this_ptr: String,
+
+    /// The class of `this`, for methods.
+    this_class: String,
+
+    /// Name of the local holding the stack pointer from before any JS values
+    /// were lent to Rust on the stack, if any are.
+    stack_pointer: Option<String>,
+
+    /// Whether a second value is returned as well, which comes from the
+    /// global argument unless the wasm function is rewritten to return both,
+    /// see `Bindgen::multi_value`.
+    multi_value: bool,
+    returns_pair: bool,
+
+    /// Whether 64-bit integers are passed as two 32-bit halves, which the
+    /// wasm function is rewritten to take, see `Bindgen::split_i64`.
+    split_i64: bool,
+    splits_i64: bool,
+
+    /// Whether `JsValue`s are passed as they are, as `externref`s which the
+    /// wasm function is wrapped to take and return, see
+    /// `Bindgen::reference_types`. Those passed are the arguments of the
+    /// conversions at the given indices, each with whether Rust owns it.
+    externrefs: bool,
+    externref_args: Vec<(usize, bool)>,
+    returns_externref: bool,
 }
 
 impl<'a, 'b> Js2Rust<'a, 'b> {
     pub fn new(js_name: &str, cx: &'a mut Context<'b>) -> Js2Rust<'a, 'b> {
+        // In debug mode every call into wasm invalidates the views previously
+        // returned from it, see `Context::expose_view_of_wasm`.
+        let mut conversions = Vec::new();
+        if cx.config.debug && cx.returns_views {
+            cx.expose_view_of_wasm();
+            conversions.push(Conversion::prologue("viewGeneration++;"));
+        }
+        let multi_value = cx.config.multi_value;
+        let split_i64 = cx.config.split_i64;
+        let externrefs = cx.config.reference_types;
         Js2Rust {
             cx,
             js_name: js_name.to_string(),
-            rust_arguments: Vec::new(),
+            conversions,
             js_arguments: Vec::new(),
-            prelude: String::new(),
-            finally: String::new(),
+            trailing_optional: 0,
             global_idx: 0,
             arg_idx: 0,
             ret_ty: String::new(),
             ret_expr: String::new(),
+            returns_promise: false,
+            returns_view: false,
+            scope: BTreeSet::new(),
+            this_ptr: String::new(),
+            this_class: String::new(),
+            stack_pointer: None,
+            multi_value,
+            returns_pair: false,
+            split_i64,
+            splits_i64: false,
+            externrefs,
+            externref_args: Vec::new(),
+            returns_externref: false,
+        }
+    }
+
+    /// Returns the second value through the global argument, passes 64-bit
+    /// integers as `BigInt`s and `JsValue`s as indices even with
+    /// `Bindgen::multi_value`, `Bindgen::split_i64` and
+    /// `Bindgen::reference_types`, for calls of functions in the function
+    /// table, which aren't exports and so can't be rewritten.
+    pub fn through_table(&mut self) -> &mut Self {
+        self.multi_value = false;
+        self.split_i64 = false;
+        self.externrefs = false;
+        self
+    }
+
+    /// Passes the JS value `name` as it is, see `externrefs`.
+    fn externref_argument(&mut self, name: &str, owned: bool) {
+        self.externref_args.push((self.conversions.len(), owned));
+        self.rust_argument(name);
+    }
+
+    /// Declares the locals `first`, from `RET`, and `second` of functions
+    /// returning two values, the latter of which can be read once `first`
+    /// is checked.
+    fn ret_pair(&mut self, first: &str, second: &str) -> (String, String) {
+        self.returns_pair = true;
+        if self.multi_value {
+            (format!("const [{}, {}] = RET;\n", first, second), String::new())
+        } else {
+            self.cx.expose_get_global_argument();
+            (format!("const {} = RET;\n", first),
+             format!("const {} = getGlobalArgument(0);\n", second))
+        }
+    }
+
+    /// Reserves a name for a parameter or local of the shim, returning `name`
+    /// itself if it's free and otherwise `name` with a numeric suffix.
+    pub fn local(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut i = 1;
+        while !self.scope.insert(candidate.clone()) {
+            candidate = format!("{}_{}", name, i);
+            i += 1;
+        }
+        candidate
+    }
+
+    /// Notes that the shim lends JS values to Rust on the stack, which are
+    /// popped once the call is over.
+    fn borrow_stack(&mut self) {
+        if self.stack_pointer.is_none() {
+            self.cx.expose_borrowed_objects();
+            self.stack_pointer = Some(self.local("sp"));
         }
     }
 
     /// Generates all bindings necessary for the signature in `Function`,
     /// creating necessary argument conversions and return value processing.
-    pub fn process(&mut self, function: &Function) -> &mut Self {
+    pub fn process(&mut self, function: &Function) -> Result<&mut Self, String> {
         for arg in function.arguments.iter() {
-            self.argument(arg);
+            self.argument(arg)?;
         }
-        self.ret(&function.ret);
+        self.ret(&function.ret)?;
+        Ok(self)
+    }
+
+    /// Flag this shim as a method call into Rust on an instance of `class`,
+    /// so the first Rust argument passed should be the pointer of `this`.
+    pub fn method(&mut self, class: &str) -> &mut Self {
+        self.this_ptr = format!("this.{}", self.cx.ptr_property(class));
+        self.this_class = class.to_string();
+        let this = Conversion::arg(&self.this_ptr);
+        self.conversions.insert(0, this);
         self
     }
 
-    /// Flag this shim as a method call into Rust, so the first Rust argument
-    /// passed should be `this.ptr`.
-    pub fn method(&mut self, method: bool) -> &mut Self {
-        if method {
-            self.rust_arguments.insert(0, "this.ptr".to_string());
+    /// Flag this method as taking `self` by value, so the JS object lets go
+    /// of its pointer before the call and counts as moved afterwards. It
+    /// gets it back if the call isn't made after all.
+    pub fn consumes_this(&mut self) -> &mut Self {
+        let ptr = self.local("ptr");
+        let this_ptr = self.this_ptr.clone();
+        self.conversions.push(Conversion {
+            prologue: format!("\
+                const {ptr} = {this_ptr};\n\
+                {this_ptr} = 0;\n\
+                {unregister}\
+            ", ptr = ptr, this_ptr = this_ptr,
+               unregister = self.cx.unregister_instance(&self.this_class, "this")),
+            args: Vec::new(),
+            cleanup: Cleanup::UnlessCalled(format!("\
+                {this_ptr} = {ptr};\n\
+                {register}\
+            ", ptr = ptr, this_ptr = this_ptr,
+               register = self.cx.register_instance(&self.this_class, "this", &ptr))),
+        });
+        self.conversions[0].args[0] = ptr;
+        self
+    }
+
+    /// In debug mode, check that the pointer of `this` is still live (not
+    /// freed or moved into Rust) before the call, naming `what` in the thrown
+    /// error.
+    pub fn assert_not_moved(&mut self, what: &str) -> &mut Self {
+        if self.cx.config.debug {
+            let this_ptr = self.this_ptr.clone();
+            self.prelude(&format!("\
+                if ({} === 0)\n\
+                    throw new Error('Attempt to use a moved value: {}');\n\
+            ", this_ptr, what));
         }
         self
     }
 
+    /// In debug mode, warn on the console the first time this shim is called
+    /// that `name` is deprecated, with the `#[deprecated]` note `message`.
+    pub fn deprecated(&mut self, name: &str, message: &str) -> &mut Self {
+        if self.cx.config.debug {
+            self.cx.expose_warn_deprecated();
+            self.prelude(&format!(
+                "_warnDeprecated({}, {});",
+                js_string(name),
+                js_string(&deprecation_warning(name, message)),
+            ));
+        }
+        self
+    }
+
+    /// Flag this method as returning its receiver, which Rust passes back as
+    /// nothing, so that calls to it can be chained in JS.
+    pub fn returns_this(&mut self) -> &mut Self {
+        self.ret_ty = "this".to_string();
+        self.ret_expr = "RET;\nreturn this;\n".to_string();
+        self
+    }
+
+    /// Flag this shim as returning a `Promise` of its result, where exceptions
+    /// thrown by the wasm function turn into a rejected promise.
+    pub fn returns_promise(&mut self, returns_promise: bool) -> &mut Self {
+        self.returns_promise = returns_promise;
+        self
+    }
+
     /// Add extra processing to the prelude of this shim.
     pub fn prelude(&mut self, s: &str) -> &mut Self {
-        for line in s.lines() {
-            self.prelude.push_str(line);
-            self.prelude.push_str("\n");
+        if !s.is_empty() {
+            self.conversions.push(Conversion::prologue(s));
         }
         self
     }
 
-    /// Add extra processing to the finally block of this shim.
-    pub fn finally(&mut self, s: &str) -> &mut Self {
-        for line in s.lines() {
-            self.finally.push_str(line);
-            self.finally.push_str("\n");
-        }
+    /// Add a conversion of something passed to Rust manually, after those
+    /// added so far.
+    pub fn conversion(&mut self, conversion: Conversion) -> &mut Self {
+        self.conversions.push(conversion);
         self
     }
 
     /// Add an Rust argument to be passed manually.
     pub fn rust_argument(&mut self, s: &str) -> &mut Self {
-        self.rust_arguments.push(s.to_string());
+        self.conversions.push(Conversion::arg(s));
         self
     }
 
-    pub fn argument(&mut self, arg: &Descriptor) -> &mut Self {
+    pub fn argument(&mut self, arg: &Descriptor) -> Result<&mut Self, String> {
         let i = self.arg_idx;
         self.arg_idx += 1;
-        let name = format!("arg{}", i);
+        let name = self.local(&format!("arg{}", i));
+        let what = if self.js_name.is_empty() {
+            format!("argument {}", i)
+        } else {
+            format!("argument {} of `{}`", i, self.js_name)
+        };
+
+        // `None` is passed as `undefined` (or `null`), which gets the value
+        // of the ABI that's never used otherwise.
+        let (arg, optional) = match arg.optional() {
+            Some(inner) => (inner, true),
+            None => (arg, false),
+        };
+        if optional {
+            self.trailing_optional += 1;
+        } else {
+            self.trailing_optional = 0;
+        }
+        let js_ty = |ty: String| if optional { format!("{} | undefined", ty) } else { ty };
+        let if_some = |check: String| {
+            if optional && !check.is_empty() {
+                format!("if ({} != null) {{\n{}}}\n", name, check)
+            } else {
+                check
+            }
+        };
 
         if let Some(kind) = arg.vector_kind() {
-            self.js_arguments.push((name.clone(), kind.js_ty().to_string()));
+            self.js_arguments.push((name.clone(), js_ty(kind.js_arg_ty())));
+
+            if self.cx.config.debug {
+                let check = match kind {
+                    VectorKind::String => {
+                        self.cx.expose_assert_string();
+                        format!("_assertString({}, '{}');\n", name, what)
+                    }
+                    VectorKind::Anyref => String::new(),
+                    _ => {
+                        self.cx.expose_assert_array();
+                        format!("_assertArray({}, {}, '{}');\n", name, kind.js_ty(), what)
+                    }
+                };
+                self.prelude(&if_some(check));
+            }
 
-            let func = self.cx.pass_to_wasm_function(kind);
+            // A borrowed slice of JS values only lends them to Rust, so
+            // they're pushed on the stack for the duration of the call.
+            let borrowed_js_values = match kind {
+                VectorKind::Anyref => arg.is_by_ref(),
+                _ => false,
+            };
+            let func = if borrowed_js_values {
+                self.borrow_stack();
+                self.cx.expose_pass_borrowed_array_js_value_to_wasm();
+                "passBorrowedArrayJsValueToWasm"
+            } else {
+                self.cx.pass_to_wasm_function(kind)
+            };
             self.cx.expose_set_global_argument();
             let global_idx = self.global_idx();
-            self.prelude(&format!("\
-                const [ptr{i}, len{i}] = {func}({arg});\n\
-                setGlobalArgument(len{i}, {global_idx});\n\
-            ", i = i, func = func, arg = name, global_idx = global_idx));
-            if arg.is_by_ref() {
-                self.finally(&format!("\
-                    wasm.__wbindgen_free(ptr{i}, len{i} * {size});\n\
-                ", i = i, size = kind.size()));
-                self.cx.require_internal_export("__wbindgen_free");
-            }
-            self.rust_arguments.push(format!("ptr{}", i));
-            return self
+            let ptr = self.local(&format!("ptr{}", i));
+            let len = self.local(&format!("len{}", i));
+            let tmp = self.local(&format!("pass{}", i));
+            let pass = if optional {
+                format!("{} == null ? [0, 0] : {}({})", name, func, name)
+            } else {
+                format!("{}({})", func, name)
+            };
+            let pass = self.cx.destructure(&[&ptr, &len], &tmp, &pass);
+            let free = format!("wasm.__wbindgen_free({}, {} * {});\n", ptr, len, kind.size());
+
+            // Rust only sees a copy of the array, so what it leaves in a
+            // `&mut` slice of numbers is copied back before the copy's freed.
+            let free = match kind {
+                VectorKind::String | VectorKind::Anyref => free,
+                _ if arg.is_mut_ref() => {
+                    self.cx.expose_copy_back_from_wasm();
+                    format!("\
+                        copyBackFromWasm({name}, new {ty}(wasm.memory.buffer, {ptr}, {len}));\n\
+                        {free}\
+                    ", name = name, ty = kind.js_ty(), ptr = ptr, len = len, free = free)
+                }
+                _ => free,
+            };
+            let free = if optional && arg.is_mut_ref() {
+                format!("if ({} !== 0) {{\n{}}}\n", ptr, free)
+            } else if optional {
+                format!("if ({} !== 0)\n{}", ptr, free)
+            } else {
+                free
+            };
+            self.cx.require_internal_export("__wbindgen_free");
+
+            // Rust frees what it's given ownership of itself, which only
+            // leaks if the call isn't made.
+            self.conversions.push(Conversion {
+                prologue: format!("\
+                    {pass}\
+                    setGlobalArgument({len}, {global_idx});\n\
+                ", pass = pass, len = len, global_idx = global_idx),
+                args: vec![ptr],
+                cleanup: if arg.is_by_ref() {
+                    Cleanup::Finally(free)
+                } else {
+                    Cleanup::UnlessCalled(free)
+                },
+            });
+            return Ok(self)
         }
 
         if let Some(s) = arg.rust_struct() {
-            self.js_arguments.push((name.clone(), s.to_string()));
+            self.js_arguments.push((name.clone(), js_ty(s.to_string())));
+            let arg_ptr = format!("{}.{}", name, self.cx.ptr_property(s));
 
-            if self.cx.config.debug {
+            let check = if self.cx.config.debug {
                 self.cx.expose_assert_class();
-                self.prelude(&format!("\
-                    _assertClass({arg}, {struct_});\n\
-                ", arg = name, struct_ = s));
+                format!("\
+                    _assertClass({arg}, {struct_}, '{what}');\n\
+                    if ({arg_ptr} === 0)\n\
+                        throw new Error('Attempt to use a moved value: {struct_}');\n\
+                ", arg = name, arg_ptr = arg_ptr, struct_ = s, what = what)
+            } else if self.cx.shared_classes.contains_key(s) {
+                // An instance of a class other inputs export too may be one
+                // of theirs, whose pointer means nothing to this wasm.
+                self.cx.expose_assert_class();
+                format!("_assertClass({}, {}, '{}');\n", name, s, what)
+            } else {
+                String::new()
+            };
+
+            // Values moved into Rust are handed back to the JS object if the
+            // call isn't made.
+            if optional && arg.is_by_ref() {
+                self.prelude(&if_some(check));
+                self.rust_argument(&format!("{} == null ? 0 : {}", name, arg_ptr));
+            } else if optional {
+                let ptr = self.local(&format!("ptr{}", i));
+                let unregister = self.cx.unregister_instance(s, &name);
+                let register = self.cx.register_instance(s, &name, &ptr);
+                self.conversions.push(Conversion {
+                    prologue: format!("\
+                        let {ptr} = 0;\n\
+                        if ({name} != null) {{\n\
+                        {check}\
+                        {ptr} = {arg_ptr};\n\
+                        {arg_ptr} = 0;\n\
+                        {unregister}\
+                        }}\n\
+                    ", ptr = ptr, name = name, check = check, arg_ptr = arg_ptr,
+                       unregister = unregister),
+                    args: vec![ptr.clone()],
+                    cleanup: Cleanup::UnlessCalled(format!("\
+                        if ({ptr} !== 0) {{\n\
+                        {arg_ptr} = {ptr};\n\
+                        {register}\
+                        }}\n\
+                    ", ptr = ptr, arg_ptr = arg_ptr, register = register)),
+                });
+            } else if arg.is_by_ref() {
+                self.prelude(&check);
+                self.rust_argument(&arg_ptr);
+            } else {
+                self.prelude(&check);
+                let ptr = self.local(&format!("ptr{}", i));
+                let unregister = self.cx.unregister_instance(s, &name);
+                let register = self.cx.register_instance(s, &name, &ptr);
+                self.conversions.push(Conversion {
+                    prologue: format!("\
+                        const {ptr} = {arg_ptr};\n\
+                        {arg_ptr} = 0;\n\
+                        {unregister}\
+                    ", ptr = ptr, arg_ptr = arg_ptr, unregister = unregister),
+                    args: vec![ptr.clone()],
+                    cleanup: Cleanup::UnlessCalled(format!("\
+                        {arg_ptr} = {ptr};\n\
+                        {register}\
+                    ", ptr = ptr, arg_ptr = arg_ptr, register = register)),
+                });
+            }
+            return Ok(self)
+        }
+
+        if arg.is_number() && !arg.is_64bit() {
+            self.js_arguments.push((name.clone(), js_ty("number".to_string())));
+
+            if self.cx.config.debug {
+                let range = match *arg {
+                    Descriptor::I8 => Some((-0x80i64, 0x7fi64)),
+                    Descriptor::U8 => Some((0, 0xff)),
+                    Descriptor::I16 => Some((-0x8000, 0x7fff)),
+                    Descriptor::U16 => Some((0, 0xffff)),
+                    Descriptor::I32 => Some((-0x8000_0000, 0x7fff_ffff)),
+                    Descriptor::U32 => Some((0, 0xffff_ffff)),
+                    _ => None,
+                };
+                let check = match range {
+                    Some((min, max)) => {
+                        self.cx.expose_assert_int();
+                        format!("_assertInt({}, {}, {}, '{}');", name, min, max, what)
+                    }
+                    None => {
+                        self.cx.expose_assert_num();
+                        format!("_assertNum({}, '{}');", name, what)
+                    }
+                };
+                if optional {
+                    self.prelude(&if_some(format!("{}\n", check)));
+                } else {
+                    self.prelude(&check);
+                }
             }
 
-            if arg.is_by_ref() {
-                self.rust_arguments.push(format!("{}.ptr", name));
+            // Numbers use every value of their ABI, so whether they're
+            // `Some` is passed in the global argument instead.
+            if optional {
+                self.cx.expose_set_global_argument();
+                let global_idx = self.global_idx();
+                self.conversions.push(Conversion {
+                    prologue: format!("setGlobalArgument({} == null ? 0 : 1, {});\n",
+                                      name, global_idx),
+                    args: vec![format!("{0} == null ? 0 : {0}", name)],
+                    cleanup: Cleanup::None,
+                });
             } else {
-                self.prelude(&format!("\
-                    const ptr{i} = {arg}.ptr;\n\
-                    {arg}.ptr = 0;\n\
-                ", i = i, arg = name));
-                self.rust_arguments.push(format!("ptr{}", i));
+                self.rust_argument(&name);
             }
-            return self
+            return Ok(self)
         }
 
-        if arg.is_number() {
-            self.js_arguments.push((name.clone(), "number".to_string()));
+        // `undefined` and `null` have indices of their own, which Rust takes
+        // as `None`.
+        if optional && arg.is_anyref() {
+            self.js_arguments.push((name.clone(), js_ty("any".to_string())));
+            self.cx.expose_add_heap_object();
+            self.rust_argument(&format!("addHeapObject({})", name));
+            return Ok(self)
+        }
+
+        if optional {
+            return Err(format!("unsupported optional argument to rust function {:?}", arg))
+        }
+
+        if let Some(e) = arg.string_enum() {
+            self.js_arguments.push((name.clone(), e.js_ty()));
+            let idx = self.cx.string_enum_index(e, &name, &what);
+            self.rust_argument(&idx);
+            return Ok(self)
+        }
 
+        if arg.is_64bit() && self.split_i64 {
+            self.js_arguments.push((name.clone(), "number".to_string()));
             if self.cx.config.debug {
                 self.cx.expose_assert_num();
-                self.prelude(&format!("_assertNum({});", name));
+                self.prelude(&format!("_assertNum({}, '{}');", name, what));
             }
+            self.splits_i64 = true;
+            self.rust_argument(&format!("{} >>> 0", name));
+            self.rust_argument(&format!("Math.floor({} / 4294967296)", name));
+            return Ok(self)
+        }
 
-            self.rust_arguments.push(name);
-            return self
+        if arg.is_64bit() {
+            self.js_arguments.push((name.clone(), "bigint".to_string()));
+            if self.cx.config.debug {
+                self.cx.expose_assert_bigint();
+                self.prelude(&format!("_assertBigInt({}, '{}');", name, what));
+            }
+            self.rust_argument(&name);
+            return Ok(self)
         }
 
         if arg.is_ref_anyref() {
             self.js_arguments.push((name.clone(), "any".to_string()));
-            self.cx.expose_borrowed_objects();
-            self.finally("stack.pop();");
-            self.rust_arguments.push(format!("addBorrowedObject({})", name));
-            return self
+            if self.externrefs {
+                self.externref_argument(&name, false);
+                return Ok(self)
+            }
+            self.borrow_stack();
+            self.rust_argument(&format!("addBorrowedObject({})", name));
+            return Ok(self)
         }
 
         match *arg {
@@ -173,69 +572,171 @@ impl<'a, 'b> Js2Rust<'a, 'b> {
                 if self.cx.config.debug {
                     self.cx.expose_assert_bool();
                     self.prelude(&format!("\
-                        _assertBoolean({name});\n\
-                    ", name = name));
+                        _assertBoolean({name}, '{what}');\n\
+                    ", name = name, what = what));
                 }
-                self.rust_arguments.push(format!("arg{i} ? 1 : 0", i = i));
+                self.rust_argument(&format!("{} ? 1 : 0", name));
+            }
+            Descriptor::Anyref if self.externrefs => {
+                self.js_arguments.push((name.clone(), "any".to_string()));
+                self.externref_argument(&name, true);
             }
             Descriptor::Anyref => {
                 self.js_arguments.push((name.clone(), "any".to_string()));
                 self.cx.expose_add_heap_object();
-                self.rust_arguments.push(format!("addHeapObject({})", name));
+                self.rust_argument(&format!("addHeapObject({})", name));
             }
             _ => {
-                panic!("unsupported argument to rust function {:?}", arg)
+                return Err(format!("unsupported argument to rust function {:?}", arg))
             }
         }
-        self
+        Ok(self)
     }
 
-    pub fn ret(&mut self, ret: &Option<Descriptor>) -> &mut Self {
+    pub fn ret(&mut self, ret: &Option<Descriptor>) -> Result<&mut Self, String> {
         let ty = match *ret {
             Some(ref t) => t,
             None => {
                 self.ret_ty = "void".to_string();
                 self.ret_expr = format!("return RET;");
-                return self
+                return Ok(self)
             }
         };
 
+        if let Some(inner) = ty.optional() {
+            return self.optional_ret(inner)
+        }
+
         if ty.is_ref_anyref() {
             self.ret_ty = "any".to_string();
             self.cx.expose_get_object();
             self.ret_expr = format!("return getObject(RET);");
-            return self
+            return Ok(self)
+        }
+
+        // Borrowed slices of numbers are returned without a copy, as a view
+        // into wasm's memory which is only valid until wasm runs again.
+        match ty.vector_kind() {
+            Some(VectorKind::String) | Some(VectorKind::Anyref) | None => {}
+            Some(kind) if ty.is_by_ref() => {
+                self.ret_ty = kind.js_ty().to_string();
+                self.returns_view = true;
+                let ret = self.local("ret");
+                let len = self.local("len");
+                let (pair, second) = self.ret_pair(&ret, &len);
+                let view = format!("new {}(wasm.memory.buffer, {}, {})", kind.js_ty(), ret, len);
+                let view = if self.cx.config.debug {
+                    self.cx.expose_view_of_wasm();
+                    format!("viewOfWasm({}, '`{}`')", view, self.js_name)
+                } else {
+                    view
+                };
+                self.ret_expr = format!("\
+                    {pair}{second}\
+                    // This is a view of wasm's memory rather than a copy, valid\n\
+                    // until the next call into wasm or until its memory grows.\n\
+                    return {view};\n\
+                ", pair = pair, second = second, view = view);
+                return Ok(self)
+            }
+            Some(_) => {}
         }
 
         if ty.is_by_ref() {
-            panic!("cannot return references from Rust to JS yet")
+            return Err("cannot return references from Rust to JS yet".to_string())
         }
 
         if let Some(ty) = ty.vector_kind() {
             self.ret_ty = ty.js_ty().to_string();
-            let f = self.cx.expose_get_vector_from_wasm(ty);
-            self.cx.expose_get_global_argument();
+            let f = self.cx.expose_take_vector_from_wasm(ty);
             self.cx.require_internal_export("__wbindgen_free");
+            let ret = self.local("ret");
+            let len = self.local("len");
+            let real_ret = self.local("realRet");
+            let (pair, second) = self.ret_pair(&ret, &len);
             self.ret_expr = format!("\
-                const ret = RET;\n\
-                const len = getGlobalArgument(0);\n\
-                const realRet = {}(ret, len);\n\
-                wasm.__wbindgen_free(ret, len * {});\n\
-                return realRet;\n\
-            ", f, ty.size());
-            return self
+                {pair}{second}\
+                const {real_ret} = {f}({ret}, {len});\n\
+                wasm.__wbindgen_free({ret}, {len} * {size});\n\
+                return {real_ret};\n\
+            ", pair = pair, second = second, ret = ret, len = len, real_ret = real_ret, f = f, size = ty.size());
+            return Ok(self)
         }
 
         if let Some(name) = ty.rust_struct() {
             self.ret_ty = name.to_string();
             self.ret_expr = format!("return {name}.__construct(RET);", name = name);
-            return self
+            return Ok(self)
+        }
+
+        if let Some(d) = ty.dictionary() {
+            self.ret_ty = self.cx.expose_dictionary(d)?;
+            self.cx.expose_take_object();
+            self.ret_expr = "return takeObject(RET);".to_string();
+            return Ok(self)
+        }
+
+        if let Some(f) = ty.fn_pointer() {
+            let ret = self.local("ret");
+            let (pair, ptr) = if self.multi_value {
+                let ptr = self.local("ptr");
+                (self.ret_pair(&ret, &ptr).0, ptr)
+            } else {
+                self.cx.expose_get_global_argument();
+                (format!("const {} = RET;\n", ret), "getGlobalArgument(0)".to_string())
+            };
+            let (js, ts) = self.cx.fn_pointer(f, &ret, &ptr)?;
+            self.ret_ty = ts;
+            self.ret_expr = format!("\
+                {pair}\
+                return {js};\n\
+            ", pair = pair, js = js);
+            return Ok(self)
+        }
+
+        if let Some(e) = ty.string_enum() {
+            self.ret_ty = e.js_ty();
+            let values = self.cx.expose_string_enum(e);
+            let ret = self.local("ret");
+            self.ret_expr = format!("\
+                const {ret} = RET;\n\
+                return {values}[{ret}];\n\
+            ", ret = ret, values = values);
+            return Ok(self)
+        }
+
+        // The high half of a split 64-bit integer is left in the global
+        // argument, and signed unless it's a `u64`.
+        if ty.is_64bit() && self.split_i64 {
+            self.ret_ty = "number".to_string();
+            self.cx.expose_get_global_argument();
+            self.splits_i64 = true;
+            let ret = self.local("ret");
+            let high = match *ty {
+                Descriptor::I64 => "(getGlobalArgument(0) | 0)",
+                _ => "getGlobalArgument(0)",
+            };
+            self.ret_expr = format!("\
+                const {ret} = RET;\n\
+                return {high} * 4294967296 + ({ret} >>> 0);\n\
+            ", ret = ret, high = high);
+            return Ok(self)
+        }
+
+        // wasm hands out `i64`s as signed `BigInt`s.
+        if ty.is_64bit() {
+            self.ret_ty = "bigint".to_string();
+            self.ret_expr = match *ty {
+                Descriptor::U64 => "return BigInt.asUintN(64, RET);".to_string(),
+                _ => "return RET;".to_string(),
+            };
+            return Ok(self)
         }
 
         if ty.is_number() {
             self.ret_ty = "number".to_string();
             self.ret_expr = format!("return RET;");
-            return self
+            return Ok(self)
         }
 
         match *ty {
@@ -243,14 +744,75 @@ impl<'a, 'b> Js2Rust<'a, 'b> {
                 self.ret_ty = "boolean".to_string();
                 self.ret_expr = format!("return (RET) !== 0;");
             }
+            Descriptor::Anyref if self.externrefs => {
+                self.ret_ty = "any".to_string();
+                self.returns_externref = true;
+                self.ret_expr = "return RET;".to_string();
+            }
             Descriptor::Anyref => {
                 self.ret_ty = "any".to_string();
                 self.cx.expose_take_object();
                 self.ret_expr = format!("return takeObject(RET);");
             }
-            _ => panic!("unsupported return from Rust to JS {:?}", ty),
+            _ => return Err(format!("unsupported return from Rust to JS {:?}", ty)),
         }
-        self
+        Ok(self)
+    }
+
+    /// The return value for `Option`s of strings and vectors, which are
+    /// returned as a null pointer for `None`, and of exported classes, whose
+    /// `None` is a null pointer too. `Some("")` still has a pointer. Numbers
+    /// leave whether they're `Some` in the global argument, and JS values
+    /// are `undefined` for `None`.
+    fn optional_ret(&mut self, ty: &Descriptor) -> Result<&mut Self, String> {
+        let ret = self.local("ret");
+        if ty.is_number() && !ty.is_64bit() {
+            self.ret_ty = "number | undefined".to_string();
+            self.cx.expose_get_global_argument();
+            self.ret_expr = format!("\
+                const {ret} = RET;\n\
+                return getGlobalArgument(0) === 0 ? undefined : {ret};\n\
+            ", ret = ret);
+            return Ok(self)
+        }
+
+        if ty.is_anyref() {
+            self.ret_ty = "any".to_string();
+            self.cx.expose_take_object();
+            self.ret_expr = "return takeObject(RET);".to_string();
+            return Ok(self)
+        }
+
+        if let (Some(kind), false) = (ty.vector_kind(), ty.is_by_ref()) {
+            self.ret_ty = format!("{} | undefined", kind.js_ty());
+            let f = self.cx.expose_take_vector_from_wasm(kind);
+            self.cx.require_internal_export("__wbindgen_free");
+            let len = self.local("len");
+            let real_ret = self.local("realRet");
+            let (pair, second) = self.ret_pair(&ret, &len);
+            self.ret_expr = format!("\
+                {pair}\
+                if ({ret} === 0)\n\
+                    return undefined;\n\
+                {second}\
+                const {real_ret} = {f}({ret}, {len});\n\
+                wasm.__wbindgen_free({ret}, {len} * {size});\n\
+                return {real_ret};\n\
+            ", pair = pair, second = second, ret = ret, real_ret = real_ret, f = f,
+               len = len, size = kind.size());
+            return Ok(self)
+        }
+
+        if let (Some(name), false) = (ty.rust_struct(), ty.is_by_ref()) {
+            self.ret_ty = format!("{} | undefined", name);
+            self.ret_expr = format!("\
+                const {ret} = RET;\n\
+                return {ret} === 0 ? undefined : {name}.__construct({ret});\n\
+            ", ret = ret, name = name);
+            return Ok(self)
+        }
+
+        Err(format!("unsupported optional return from Rust to JS {:?}", ty))
     }
 
     /// Generate the actual function.
@@ -262,40 +824,94 @@ impl<'a, 'b> Js2Rust<'a, 'b> {
     /// Returns two strings, the first of which is the JS expression for the
     /// generated function shim and the second is a TyepScript signature of rthe
     /// JS expression.
-    pub fn finish(&self, prefix: &str, invoc: &str) -> (String, String) {
+    pub fn finish(&mut self, prefix: &str, invoc: &str) -> (String, String) {
         let js_args = self.js_arguments
             .iter()
             .map(|s| &s.0[..])
             .collect::<Vec<_>>()
             .join(", ");
-        let mut js = format!("{}({}) {{\n", prefix, js_args);
-        js.push_str(&indent(&self.prelude));
-        let rust_args = self.rust_arguments.join(", ");
 
-        let invoc = self.ret_expr.replace("RET", &format!("{}({})", invoc, rust_args));
-        let invoc = if self.finally.len() == 0 {
-            invoc
-        } else {
+        // Rather than popping each borrowed value, the stack pointer is reset
+        // to where it was before the first of them was pushed. That way none
+        // are left behind if converting a later argument throws, and none of
+        // the caller's are popped if an earlier one does. Calls back into wasm
+        // in the meantime have reset it to above this point already.
+        let mut conversions = Vec::new();
+        if let Some(ref sp) = self.stack_pointer {
+            conversions.push(Conversion {
+                prologue: format!("const {} = stack_pointer;\n", sp),
+                args: Vec::new(),
+                cleanup: Cleanup::Finally(format!("dropBorrowedObjects({});", sp)),
+            });
+        }
+        conversions.extend(self.conversions.iter().cloned());
+        let rust_args = conversions.iter()
+            .flat_map(|c| c.args.iter().map(|a| &a[..]))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.returns_pair && self.multi_value {
+            self.cx.multi_value_exports.insert(invoc["wasm.".len()..].to_string());
+        }
+        if self.splits_i64 {
+            self.cx.split_i64_exports.insert(invoc["wasm.".len()..].to_string());
+        }
+        if !self.externref_args.is_empty() || self.returns_externref {
+            let params = self.externref_args.iter()
+                .map(|&(i, owned)| {
+                    let pos = self.conversions[..i].iter().map(|c| c.args.len()).sum();
+                    (pos, owned)
+                })
+                .collect();
+            let sig = externref::Signature { params, ret: self.returns_externref };
+            self.cx.externref_exports.insert(invoc["wasm.".len()..].to_string(), sig);
+        }
+        let invoc = self.ret_expr.replacen("RET", &format!("{}({})", invoc, rust_args), 1);
+        let called = self.local("called");
+        let body = conversion::render(&conversions, &invoc, &called);
+
+        // Async shims run the normal body inside an arrow function (to keep
+        // `this` intact for methods) so both the return value and any thrown
+        // exception are funneled into the returned promise. ES5 passes `this`
+        // along explicitly instead.
+        let body = if self.returns_promise {
+            let (open, close) = if self.cx.es5() {
+                ("(function() {", "}).call(this)")
+            } else {
+                ("(() => {", "})()")
+            };
             format!("\
                 try {{\n\
-                    {}\
-                }} finally {{\n\
-                    {}\
+                    return Promise.resolve({}\n\
+                {}\
+                    {});\n\
+                }} catch (e) {{\n\
+                    return Promise.reject(e);\n\
                 }}\n\
-            ",
-                indent(&invoc),
-                indent(&self.finally),
-            )
+            ", open, indent(&indent(&body)), close)
+        } else {
+            body
         };
-        js.push_str(&indent(&invoc));
+        let mut js = format!("{}({}) {{\n", prefix, js_args);
+        js.push_str(&indent(&body));
         js.push_str("}");
 
+        // Optional arguments no other argument follows can be left out.
+        let required = self.js_arguments.len() - self.trailing_optional;
         let ts_args = self.js_arguments
             .iter()
-            .map(|s| format!("{}: {}", s.0, s.1))
+            .enumerate()
+            .map(|(i, s)| match s.1.rfind(" | undefined") {
+                Some(end) if i >= required => format!("{}?: {}", s.0, &s.1[..end]),
+                _ => format!("{}: {}", s.0, s.1),
+            })
             .collect::<Vec<_>>()
             .join(", ");
-        let ts = format!("{} {}({}): {};\n", prefix, self.js_name, ts_args, self.ret_ty);
+        let ret_ty = if self.returns_promise {
+            format!("Promise<{}>", self.ret_ty)
+        } else {
+            self.ret_ty.clone()
+        };
+        let ts = format!("{} {}({}): {};\n", prefix, self.js_name, ts_args, ret_ty);
         (js, ts)
     }
 
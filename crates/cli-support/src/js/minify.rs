@@ -0,0 +1,245 @@
+//! Shrinks the generated JS glue, see `Bindgen::minify_js`.
+//!
+//! This isn't a general purpose minifier, it only needs to handle the code we
+//! generate ourselves. Names the glue declares for its own use are shortened,
+//! while anything visible from outside the module (exports, class names,
+//! property names, imports) is left alone. Comments and whitespace are
+//! dropped, and lines are only joined where that can't change how automatic
+//! semicolon insertion treats them.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::tokens::{tokenize, Token};
+
+const KEYWORDS: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "export", "extends", "false", "finally",
+    "for", "function", "if", "import", "in", "instanceof", "let", "new",
+    "null", "return", "static", "super", "switch", "this", "throw", "true",
+    "try", "typeof", "var", "void", "while", "with", "yield", "await", "async",
+    "get", "set", "of", "as", "from", "undefined", "arguments", "eval",
+];
+
+/// Globals the glue uses. Where it also declares one (like `TextDecoder` on
+/// node) the other references are to the real global, so these stay as is.
+const GLOBALS: &[&str] = &[
+    "self", "window", "globalThis", "global", "console", "require", "module",
+    "exports", "__dirname", "WebAssembly", "TextEncoder", "TextDecoder",
+    "Object", "Symbol", "Error", "TypeError", "Number", "Array", "Math",
+    "JSON", "Promise", "Proxy", "fetch",
+];
+
+pub fn minify(js: &str) -> String {
+    let tokens = tokenize(js);
+    let names = rename(&tokens);
+    print(&tokens, &names)
+}
+
+/// Picks shorter names for the names the glue declares for itself.
+///
+/// As every occurrence of a name is replaced by the same fresh name, scoping
+/// is unaffected, which is what makes it fine to not resolve scopes at all.
+fn rename<'a>(tokens: &[Token<'a>]) -> HashMap<&'a str, String> {
+    let mut declared = BTreeSet::new();
+    let mut keep = BTreeSet::new();
+    let mut counts = HashMap::new();
+    let mut brackets: Vec<(&str, bool)> = Vec::new();
+    let mut class_body_next = false;
+    let mut in_import = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = if i > 0 { tokens[i - 1] } else { Token::Newline };
+        let next = tokens.get(i + 1).cloned().unwrap_or(Token::Newline);
+        match *token {
+            Token::Punct(p) if p == "{" || p == "(" || p == "[" => {
+                brackets.push((p, p == "{" && class_body_next));
+                class_body_next = false;
+            }
+            Token::Punct(p) if p == "}" || p == ")" || p == "]" => {
+                brackets.pop();
+            }
+            Token::Punct(";") | Token::Newline => in_import = false,
+            Token::Ident("import") if prev == Token::Newline || prev == Token::Punct(";") => {
+                in_import = true;
+            }
+            // The names of an `export { ... }` list are visible outside too.
+            Token::Ident("export") if next == Token::Punct("{") => in_import = true,
+            Token::Ident("class") => class_body_next = true,
+            Token::Ident(name) => {
+                *counts.entry(name).or_insert(0) += 1;
+                let innermost = brackets.last().cloned();
+                let exported = i >= 2 && tokens[i - 2] == Token::Ident("export");
+                if in_import || exported || prev == Token::Ident("class") {
+                    keep.insert(name);
+                }
+                // Object literal keys and shorthand properties.
+                if innermost.map(|b| b.0) == Some("{") &&
+                    (prev == Token::Punct("{") || prev == Token::Punct(",")) &&
+                    (next == Token::Punct(":") || next == Token::Punct(",") ||
+                     next == Token::Punct("}"))
+                {
+                    keep.insert(name);
+                }
+                // Members of a class.
+                if innermost.map(|b| b.1) == Some(true) {
+                    keep.insert(name);
+                }
+                match prev {
+                    Token::Ident("function") |
+                    Token::Ident("let") |
+                    Token::Ident("const") |
+                    Token::Ident("var") => {
+                        declared.insert(name);
+                    }
+                    _ => {}
+                }
+                // Parameters of a `function`.
+                if prev == Token::Punct("(") || prev == Token::Punct(",") ||
+                    prev == Token::Punct("...")
+                {
+                    let params_of_function = innermost.map(|b| b.0) == Some("(") &&
+                        function_params(tokens, i);
+                    if params_of_function {
+                        declared.insert(name);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut candidates = declared.iter()
+        .filter(|name| {
+            !keep.contains(*name) && !KEYWORDS.contains(name) && !GLOBALS.contains(name)
+        })
+        .map(|name| (counts[name], *name))
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+
+    let mut fresh = FreshNames { next: 0 };
+    let mut names = HashMap::new();
+    for &(_, name) in candidates.iter() {
+        let short = loop {
+            let candidate = fresh.next();
+            if !counts.contains_key(&candidate[..]) && !KEYWORDS.contains(&&candidate[..]) {
+                break candidate
+            }
+        };
+        if short.len() < name.len() {
+            names.insert(name, short);
+        }
+    }
+
+    names
+}
+
+/// Whether the identifier at `i`, directly inside a parenthesized list, is a
+/// parameter of a `function` rather than, say, a call argument.
+fn function_params(tokens: &[Token], i: usize) -> bool {
+    let mut depth = 0;
+    for j in (0..i).rev() {
+        match tokens[j] {
+            Token::Punct(")") | Token::Punct("]") | Token::Punct("}") => depth += 1,
+            Token::Punct("(") if depth == 0 => {
+                return match tokens[..j].last() {
+                    Some(&Token::Ident("function")) => true,
+                    Some(&Token::Ident(_)) => {
+                        j >= 2 && tokens[j - 2] == Token::Ident("function")
+                    }
+                    _ => false,
+                }
+            }
+            Token::Punct("(") | Token::Punct("[") | Token::Punct("{") => {
+                if depth == 0 {
+                    return false
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Generates `a`, `b`, ..., `Z`, `aa`, `ab`, ...
+struct FreshNames {
+    next: usize,
+}
+
+impl FreshNames {
+    fn next(&mut self) -> String {
+        const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        let mut n = self.next;
+        self.next += 1;
+        let mut name = Vec::new();
+        loop {
+            name.push(CHARS[n % CHARS.len()]);
+            n /= CHARS.len();
+            if n == 0 {
+                break
+            }
+            n -= 1;
+        }
+        name.reverse();
+        String::from_utf8(name).unwrap()
+    }
+}
+
+fn print(tokens: &[Token], names: &HashMap<&str, String>) -> String {
+    let mut ret = String::new();
+    let mut prev: Option<Token> = None;
+    for (i, &token) in tokens.iter().enumerate() {
+        if token == Token::Newline {
+            let next = tokens.get(i + 1).cloned();
+            if prev.is_some() && next.is_some() && !can_join(prev.unwrap(), next.unwrap()) {
+                ret.push('\n');
+                prev = Some(Token::Newline);
+            }
+            continue
+        }
+        let text = match token {
+            // Names after a `.` are properties, which are never renamed.
+            Token::Ident(s) if prev != Some(Token::Punct(".")) => {
+                names.get(s).map(|s| &s[..]).unwrap_or(s)
+            }
+            Token::Ident(s) | Token::Punct(s) | Token::Literal(s) => s,
+            Token::Newline => unreachable!(),
+        };
+        if let Some(before) = ret.chars().last() {
+            let after = text.chars().next().unwrap();
+            let number = match prev {
+                Some(Token::Literal(s)) => s.as_bytes()[0].is_ascii_digit(),
+                _ => false,
+            };
+            let word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+            if (word(before) && word(after)) ||
+                (before == '+' && after == '+') ||
+                (before == '-' && after == '-') ||
+                (before == '/' && (after == '/' || after == '*')) ||
+                (number && after == '.')
+            {
+                ret.push(' ');
+            }
+        }
+        ret.push_str(text);
+        prev = Some(token);
+    }
+    ret.push('\n');
+    ret
+}
+
+/// Whether the line break between `prev` and `next` can be removed without
+/// changing where semicolons are automatically inserted.
+fn can_join(prev: Token, next: Token) -> bool {
+    match prev {
+        // An operator or opening bracket can't end a statement.
+        Token::Punct(p) if !["}", ")", "]", "++", "--"].contains(&p) => return true,
+        _ => {}
+    }
+    match next {
+        // Nor can these start one, so no semicolon goes before them.
+        Token::Punct(p) => ![ "(", "[", "+", "-", "++", "--", "/", "!", "~"].contains(&p),
+        Token::Ident(s) => prev == Token::Punct("}") && ["else", "catch", "finally"].contains(&s),
+        _ => false,
+    }
+}
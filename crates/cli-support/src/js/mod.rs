@@ -1,33 +1,141 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Write;
 use std::mem;
+use std::slice;
 
 use parity_wasm::elements::*;
-use parity_wasm;
+use rustc_demangle;
+use serde_json;
 use shared;
-use wasm_gc;
 
-use super::Bindgen;
-use descriptor::{Descriptor, VectorKind};
+use super::{Bindgen, CLASSES_STEM, EsVersion, GcStats, RUNTIME_STEM, WasmImportMode};
+use super::local_module_path;
+use descriptor::{Descriptor, Dictionary, Function, StringEnum, VectorKind};
+use metadata;
 
+mod conversion;
+pub mod externref;
 mod js2rust;
 use self::js2rust::Js2Rust;
 mod rust2js;
-use self::rust2js::Rust2Js;
+use self::rust2js::{Rust2Js, closure_ty};
+mod minify;
+pub use self::minify::minify;
+pub mod source_map;
+mod tokens;
+use self::tokens::{tokenize, Token};
+
+/// The JS values with a fixed index in the heap, in order, so that passing
+/// them around doesn't touch the slab. Rust knows them as the `JSIDX_*`
+/// constants.
+const RESERVED_VALUES: &[&str] = &["undefined", "null", "true", "false"];
+
+/// The first index of the heap which isn't reserved. Heap indices are shifted
+/// left by one, with the low bit set for the stack.
+const JSIDX_RESERVED: usize = 8;
+
+/// With string interning, the longest JS strings whose UTF-8 encoding is kept
+/// for the next time they're passed to wasm.
+const ENCODED_STRING_MAX_LEN: usize = 128;
+
+/// Documents exports returning a view into wasm's memory in TypeScript.
+const VIEW_DOC: &str = "\
+/**
+ * The returned array is a view of wasm's memory, not a copy: it's only valid
+ * until the next call into wasm or until wasm's memory grows, so copy it with
+ * `slice()` to keep it around.
+ */
+";
+
+/// The documentation of methods taking `self` by value in the `.d.ts`.
+const CONSUMED_DOC: &str = "\
+/**
+ * Consumes this instance: it can't be used once this method is called.
+ */
+";
 
 pub struct Context<'a> {
     pub globals: String,
     pub imports: String,
     pub footer: String,
     pub typescript: String,
-    pub exposed_globals: HashSet<&'static str>,
-    pub required_internal_exports: HashSet<&'static str>,
+    pub exposed_globals: BTreeSet<&'static str>,
+    pub required_internal_exports: BTreeSet<&'static str>,
     pub config: &'a Bindgen,
     pub module: &'a mut Module,
-    pub imported_names: HashSet<String>,
-    pub exported_classes: HashMap<String, ExportedClass>,
+    pub imported_names: BTreeSet<String>,
+    pub exported_classes: BTreeMap<String, ExportedClass>,
     pub function_table_needed: bool,
-    pub run_descriptor: &'a Fn(&str) -> Vec<u32>,
+    pub descriptors: &'a HashMap<String, Vec<u32>>,
+    pub gc_stats: GcStats,
+    pub exported_names: BTreeSet<String>,
+    /// JS modules imported with a relative path, and the names imported from
+    /// each, to be copied into the output directory.
+    pub local_modules: BTreeMap<String, BTreeSet<String>>,
+    /// Every name defined by the generated JS module (including class
+    /// members, as `Class.name` or `Class.prototype.name`) along with a
+    /// description of what defined it, to detect conflicts.
+    pub js_names: BTreeMap<String, String>,
+    /// What each imported module (keyed by the specifier it's imported with,
+    /// or `None` for the global scope) must provide, as TypeScript.
+    imported_shapes: BTreeMap<Option<String>, ImportShape>,
+    /// With `Bindgen::split_output`, the JS and TypeScript of each class, by
+    /// name, to be written into their own modules.
+    split_classes: Vec<(String, String, String)>,
+    /// The exported consts whose values are read from the wasm, which ES
+    /// modules assign once it's available.
+    const_getters: Vec<String>,
+    /// Whether any export returns a view into wasm's memory, in which case
+    /// debug mode tracks when those views are invalidated.
+    returns_views: bool,
+    /// The imported string enums whose lookup arrays have been emitted.
+    string_enums: BTreeSet<String>,
+    /// The dictionaries whose TypeScript interfaces have been emitted.
+    dictionaries: BTreeSet<String>,
+    /// The exported classes which extend a JS class, see `ptr_property`.
+    extended_classes: BTreeSet<String>,
+    /// Whether the wasm's memory is shared, for which `SharedArrayBuffer`
+    /// must be available.
+    shared_memory: bool,
+    /// The local names of the modules imported as a namespace object, by
+    /// specifier, for `lazy` imports.
+    module_namespaces: BTreeMap<String, String>,
+    /// The stem, JS and TypeScript of the modules generated besides the main
+    /// one, see `Bindgen::split_output`.
+    pub split_modules: Vec<(String, String, String)>,
+    /// The parts of the heap and stack management the glue imports from the
+    /// runtime module, keyed like `exposed_globals`, see
+    /// `Bindgen::shared_runtime`.
+    pub runtime: Vec<(&'static str, String)>,
+    /// The public API of the generated JS, see `Bindgen::emit_metadata`.
+    pub metadata: metadata::Bindings,
+    /// The id of the build which the glue checks the wasm it's given is
+    /// from, see `Bindgen::build_id`.
+    pub build_id: Option<String>,
+    /// The classes other inputs export too, with whether this input owns
+    /// them, see `Bindgen::add_input_path`.
+    pub shared_classes: BTreeMap<String, bool>,
+    /// The exports the glue expects to return a pointer along with a length,
+    /// and the types of the wrappers doing so, which are given their second
+    /// result once serialized, see `Bindgen::multi_value`.
+    pub multi_value_exports: BTreeSet<String>,
+    pub multi_value_types: Vec<u32>,
+    /// The exports and imports the glue passes 64-bit integers to and from
+    /// as two halves, see `Bindgen::split_i64`.
+    pub split_i64_exports: BTreeSet<String>,
+    pub split_i64_imports: BTreeSet<String>,
+    /// The exports and imports the glue passes `JsValue`s to and from as
+    /// `externref`s, and what's left to do once the module they're wrapped
+    /// in is serialized, see `Bindgen::reference_types`.
+    pub externref_exports: BTreeMap<String, externref::Signature>,
+    pub externref_imports: BTreeMap<String, externref::Signature>,
+    pub externref_patches: Option<externref::Patches>,
+    /// Whether `prepare` already ran, on the module before it was copied for
+    /// each of several targets.
+    pub prepared: bool,
+    /// Whether `finalize` rewrites the wasm for the glue, which only the one
+    /// of several targets sharing the wasm writing it does.
+    pub rewrite_wasm: bool,
 }
 
 #[derive(Default)]
@@ -36,6 +144,16 @@ pub struct ExportedClass {
     typescript: String,
     constructor: Option<String>,
     fields: Vec<ClassField>,
+    /// Methods implementing `[Symbol.iterator]`, the `len` method's name and
+    /// the `get` method's name along with the TypeScript type it returns.
+    iterator_len: Option<String>,
+    iterator_get: Option<(String, String)>,
+    /// The JS expression of the class this one extends, whether the
+    /// constructor's arguments are passed on to it, and the TypeScript
+    /// importing it if it comes from a module.
+    superclass: Option<(String, bool, String)>,
+    /// The `#[deprecated]` note of the struct, if it's deprecated.
+    deprecated: Option<String>,
 }
 
 struct ClassField {
@@ -43,21 +161,197 @@ struct ClassField {
     readonly: bool,
 }
 
+/// The shape of an imported JS value, as far as the generated JS uses it.
+#[derive(Default)]
+struct ImportShape {
+    /// Call and construct signatures, like `(arg0: number): void`.
+    signatures: BTreeSet<String>,
+    /// The type of a value that's only read or written, like a static.
+    ty: Option<String>,
+    members: BTreeMap<String, ImportShape>,
+    /// Whether the value is used by anything but `lazy` imports, which
+    /// tolerate it being missing.
+    required: bool,
+}
+
+/// The kinds of class members, see `Context::class_member`.
+#[derive(Clone, Copy, PartialEq)]
+enum Member {
+    Method,
+    Static,
+    Getter,
+    Setter,
+}
+
 pub struct SubContext<'a, 'b: 'a> {
     pub program: &'a shared::Program,
     pub cx: &'a mut Context<'b>,
 }
 
 impl<'a> Context<'a> {
+    pub fn new(config: &'a Bindgen,
+               module: &'a mut Module,
+               descriptors: &'a HashMap<String, Vec<u32>>,
+               shared_memory: bool) -> Context<'a> {
+        Context {
+            globals: String::new(),
+            imports: String::new(),
+            footer: String::new(),
+            typescript: String::new(),
+            exposed_globals: Default::default(),
+            required_internal_exports: Default::default(),
+            imported_names: Default::default(),
+            exported_classes: Default::default(),
+            config,
+            module,
+            function_table_needed: false,
+            descriptors,
+            gc_stats: GcStats::default(),
+            exported_names: Default::default(),
+            local_modules: Default::default(),
+            js_names: Default::default(),
+            imported_shapes: Default::default(),
+            split_classes: Vec::new(),
+            const_getters: Vec::new(),
+            returns_views: descriptors.values().any(|d| {
+                match Descriptor::decode(d) {
+                    Ok(Descriptor::Function(f)) => match f.ret {
+                        Some(ret) => ret.is_by_ref() && ret.vector_kind().is_some(),
+                        None => false,
+                    },
+                    _ => false,
+                }
+            }),
+            string_enums: BTreeSet::new(),
+            dictionaries: BTreeSet::new(),
+            extended_classes: BTreeSet::new(),
+            shared_memory,
+            module_namespaces: BTreeMap::new(),
+            split_modules: Vec::new(),
+            runtime: Vec::new(),
+            metadata: metadata::Bindings::new(),
+            build_id: None,
+            shared_classes: BTreeMap::new(),
+            multi_value_exports: BTreeSet::new(),
+            multi_value_types: Vec::new(),
+            split_i64_exports: BTreeSet::new(),
+            split_i64_imports: BTreeSet::new(),
+            externref_exports: BTreeMap::new(),
+            externref_imports: BTreeMap::new(),
+            externref_patches: None,
+            prepared: false,
+            rewrite_wasm: true,
+        }
+    }
+
+    /// The module, field and type of the memory the wasm imports, if it
+    /// doesn't define its own.
+    fn imported_memory(&self) -> Option<(String, String, MemoryType)> {
+        let imports = self.module.import_section()?;
+        imports.entries().iter().filter_map(|i| {
+            match *i.external() {
+                External::Memory(ref m) => {
+                    Some((i.module().to_string(), i.field().to_string(), *m))
+                }
+                _ => None,
+            }
+        }).next()
+    }
+
+    /// The property of instances of the exported `class` holding their
+    /// pointer into wasm. Classes extending a JS class use a name which the
+    /// superclass's own properties won't collide with.
+    pub fn ptr_property(&self, class: &str) -> &'static str {
+        if self.extended_classes.contains(class) {
+            "__wbg_ptr"
+        } else {
+            "ptr"
+        }
+    }
+
+    /// With `Bindgen::weak_refs`, the statement handing the pointer `ptr` of
+    /// `obj`, an instance of `class`, to the garbage collector to free once
+    /// `obj` is collected.
+    pub fn register_instance(&self, class: &str, obj: &str, ptr: &str) -> String {
+        if !self.config.weak_refs {
+            return String::new()
+        }
+        format!("{}Finalization.register({}, {}, {1});\n", class, obj, ptr)
+    }
+
+    /// With `Bindgen::weak_refs`, the statement taking back what
+    /// `register_instance` gave the garbage collector, for when `obj` is
+    /// freed or its pointer is moved into Rust.
+    pub fn unregister_instance(&self, class: &str, obj: &str) -> String {
+        if !self.config.weak_refs {
+            return String::new()
+        }
+        format!("{}Finalization.unregister({});\n", class, obj)
+    }
+
+    /// Records that the JS `name` is defined by `what`, failing if
+    /// something else already defined it since the generated JS would
+    /// otherwise be broken.
+    fn define_js_name(&mut self, name: &str, what: String) -> Result<(), String> {
+        if let Some(prev) = self.js_names.get(name) {
+            return Err(format!("the JS name `{}` is defined by both {} and {}, \
+                                only one item may be exported as `{}`",
+                               name, prev, what, name));
+        }
+        self.js_names.insert(name.to_string(), what);
+        Ok(())
+    }
+
+    /// Whether the glue must be written in ES5.
+    fn es5(&self) -> bool {
+        self.config.es_target == EsVersion::Es5
+    }
+
+    /// Declares `names` as the elements of the array `expr` evaluates to,
+    /// going through the temporary `tmp` in ES5 which lacks destructuring.
+    fn destructure(&self, names: &[&str], tmp: &str, expr: &str) -> String {
+        if !self.es5() {
+            return format!("const [{}] = {};\n", names.join(", "), expr)
+        }
+        let mut ret = format!("const {} = {};\n", tmp, expr);
+        for (i, name) in names.iter().enumerate() {
+            ret.push_str(&format!("const {} = {}[{}];\n", name, tmp, i));
+        }
+        ret
+    }
+
+    /// Returns the names of everything exported to JS by the generated glue,
+    /// so functions, classes and enums.
+    pub fn api_names(&self) -> BTreeSet<String> {
+        self.js_names.keys()
+            .filter(|name| !name.contains('.'))
+            .cloned()
+            .collect()
+    }
+
     fn export(&mut self, name: &str, contents: &str) {
+        self.export_with_doc(name, contents, "")
+    }
+
+    /// Like `export`, preceding the export with the comment `doc`.
+    fn export_with_doc(&mut self, name: &str, contents: &str, doc: &str) {
+        self.exported_names.insert(name.to_string());
         let contents = deindent(contents);
         let contents = contents.trim();
-        let global = if self.config.nodejs {
+        // Classes are referred to by name, so they're declared before being
+        // exported.
+        let global = if self.config.nodejs && contents.starts_with("class") {
+            format!("{}\nmodule.exports.{} = {1};\n", contents, name)
+        } else if self.config.nodejs {
             format!("module.exports.{} = {};\n", name, contents)
+        } else if self.config.no_modules && contents.starts_with("class") {
+            format!("{}\n__exports.{} = {1};\n", contents, name)
         } else if self.config.no_modules {
             format!("__exports.{} = {}\n", name, contents)
         } else {
-            if contents.starts_with("function") {
+            if contents == name {
+                format!("export {{ {} }};\n", name)
+            } else if contents.starts_with("function") {
                 format!("export function {}{}\n", name, &contents[8..])
             } else if contents.starts_with("class") {
                 format!("export {}\n", contents)
@@ -65,29 +359,46 @@ impl<'a> Context<'a> {
                 format!("export const {} = {};\n", name, contents)
             }
         };
-        self.global(&global);
+        self.global(&format!("{}{}", doc, global));
     }
 
+    /// Records that the glue calls the internal export `name`, which
+    /// `finalize` checks the wasm has.
     fn require_internal_export(&mut self, name: &'static str) {
-        if !self.required_internal_exports.insert(name) {
-            return
-        }
-        if let Some(s) = self.module.export_section() {
-            if s.entries().iter().any(|e| e.field() == name) {
-                return
+        self.required_internal_exports.insert(name);
+    }
+
+    fn check_internal_exports(&self) -> Result<(), String> {
+        for name in self.required_internal_exports.iter() {
+            let found = self.module.export_section()
+                .map(|s| s.entries().iter().any(|e| e.field() == *name))
+                .unwrap_or(false);
+            if !found {
+                return Err(format!("the exported function `{}` is required to generate \
+                                    bindings but it was not found in the wasm file, \
+                                    perhaps the `std` feature of the `wasm-bindgen` \
+                                    crate needs to be enabled?", name))
             }
         }
-
-        panic!("\n\nthe exported function `{}` is required to generate bindings \
-                but it was not found in the wasm file, perhaps the `std` feature \
-                of the `wasm-bindgen` crate needs to be enabled?\n\n",
-               name);
+        Ok(())
     }
 
-    pub fn finalize(&mut self, module_name: &str) -> (String, String) {
-        self.unexport_unused_internal_exports();
+    /// Removes what's only there to generate bindings from the wasm, along
+    /// with whatever that leaves unused. `finalize` does so first unless it's
+    /// already been done.
+    pub fn prepare(&mut self) -> Result<(), String> {
+        self.unexport_descriptors();
         self.gc();
-        self.write_classes();
+        self.remove_unused_placeholder_imports()?;
+        self.prepared = true;
+        Ok(())
+    }
+
+    pub fn finalize(&mut self, module_name: &str) -> Result<(String, String), String> {
+        if !self.prepared {
+            self.prepare()?;
+        }
+        self.write_classes()?;
         {
             let mut bind = |name: &str, f: &Fn(&mut Self) -> String| {
                 if !self.wasm_import_needed(name) {
@@ -100,6 +411,13 @@ impl<'a> Context<'a> {
             bind("__wbindgen_object_clone_ref", &|me| {
                 me.expose_add_heap_object();
                 me.expose_get_object();
+                if me.config.reference_types {
+                    return format!("
+                        function(idx) {{
+                            return idx < {} ? idx : addHeapObject(getObject(idx));
+                        }}
+                    ", JSIDX_RESERVED);
+                }
                 let bump_cnt = if me.config.debug {
                     String::from("
                         if (typeof(val) === 'number')
@@ -115,13 +433,17 @@ impl<'a> Context<'a> {
                         if ((idx & 1) === 1)
                             return addHeapObject(getObject(idx));
 
+                        // Reserved values aren't refcounted.
+                        if (idx < {reserved})
+                            return idx;
+
                         // Otherwise if the object is on the heap just bump the
                         // refcount and move on
                         const val = slab[idx >> 1];
-                        {}
+                        {bump_cnt}
                         return idx;
                     }}
-                ", bump_cnt)
+                ", bump_cnt = bump_cnt, reserved = JSIDX_RESERVED)
             });
 
             bind("__wbindgen_object_drop_ref", &|me| {
@@ -139,6 +461,22 @@ impl<'a> Context<'a> {
                 ")
             });
 
+            bind("__wbindgen_object_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("function() { return addHeapObject({}); }")
+            });
+
+            bind("__wbindgen_object_set", &|me| {
+                me.expose_get_object();
+                me.expose_get_string_from_wasm();
+                me.expose_take_object();
+                String::from("
+                    function(i, p, l, v) {
+                        getObject(i)[getStringFromWasm(p, l)] = takeObject(v);
+                    }
+                ")
+            });
+
             bind("__wbindgen_number_new", &|me| {
                 me.expose_add_heap_object();
                 String::from("function(i) { return addHeapObject(i); }")
@@ -158,20 +496,6 @@ impl<'a> Context<'a> {
                 ")
             });
 
-            bind("__wbindgen_undefined_new", &|me| {
-                me.expose_add_heap_object();
-                String::from("function() { return addHeapObject(undefined); }")
-            });
-
-            bind("__wbindgen_null_new", &|me| {
-                me.expose_add_heap_object();
-                String::from("
-                    function() {
-                        return addHeapObject(null);
-                    }
-                ")
-            });
-
             bind("__wbindgen_is_null", &|me| {
                 me.expose_get_object();
                 String::from("
@@ -190,15 +514,6 @@ impl<'a> Context<'a> {
                 ")
             });
 
-            bind("__wbindgen_boolean_new", &|me| {
-                me.expose_add_heap_object();
-                String::from("
-                    function(v) {
-                        return addHeapObject(v === 1);
-                    }
-                ")
-            });
-
             bind("__wbindgen_boolean_get", &|me| {
                 me.expose_get_object();
                 String::from("
@@ -239,6 +554,45 @@ impl<'a> Context<'a> {
                 ")
             });
 
+            // Interned strings are `'static` in Rust, so their memory stays
+            // as it is until they're uninterned.
+            bind("__wbindgen_intern", &|me| {
+                if !me.string_interning() {
+                    return String::from("function(ptr, len) {}")
+                }
+                me.expose_interned_strings();
+                me.expose_text_decoder();
+                me.expose_uint8_memory();
+                format!("
+                    function(ptr, len) {{
+                        const key = ptr + ',' + len;
+                        if (internedStrings[key] !== undefined)
+                            return;
+                        if (internedKeys.length >= {capacity})
+                            delete internedStrings[internedKeys.shift()];
+                        internedStrings[key] =
+                            cachedDecoder.decode(getUint8Memory().slice(ptr, ptr + len));
+                        internedKeys.push(key);
+                    }}
+                ", capacity = me.config.string_intern_capacity)
+            });
+
+            bind("__wbindgen_unintern", &|me| {
+                if !me.string_interning() {
+                    return String::from("function(ptr, len) {}")
+                }
+                me.expose_interned_strings();
+                String::from("
+                    function(ptr, len) {
+                        const key = ptr + ',' + len;
+                        if (internedStrings[key] === undefined)
+                            return;
+                        delete internedStrings[key];
+                        internedKeys.splice(internedKeys.indexOf(key), 1);
+                    }
+                ")
+            });
+
             bind("__wbindgen_throw", &|me| {
                 me.expose_get_string_from_wasm();
                 format!("
@@ -248,18 +602,106 @@ impl<'a> Context<'a> {
                 ")
             });
 
+            // Called by `wasm_bindgen::throw_val`, like for exports returning
+            // an `Err`.
+            bind("__wbindgen_rethrow", &|me| {
+                me.expose_take_object();
+                String::from("
+                    function(idx) {
+                        throw takeObject(idx);
+                    }
+                ")
+            });
+
+            // Called by `wasm_bindgen::set_panic_hook` with the formatted panic,
+            // after which wasm can't carry on anyway.
+            bind("__wbindgen_panic", &|me| {
+                me.expose_get_string_from_wasm();
+                let handler = if me.config.debug {
+                    me.expose_panic_handler();
+                    "
+                        if (panicHandler !== null)
+                            panicHandler(e);
+                    "
+                } else {
+                    ""
+                };
+                format!("
+                    function(ptr, len) {{
+                        const e = new Error(getStringFromWasm(ptr, len));
+                        {}
+                        throw e;
+                    }}
+                ", handler)
+            });
+
+            bind("__wbindgen_json_parse", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_string_from_wasm();
+                String::from("
+                    function(ptr, len) {
+                        return addHeapObject(JSON.parse(getStringFromWasm(ptr, len)));
+                    }
+                ")
+            });
+
+            bind("__wbindgen_json_serialize", &|me| {
+                me.expose_get_object();
+                me.expose_pass_string_to_wasm();
+                me.expose_uint32_memory();
+                let pass = me.destructure(
+                    &["ptr", "len"],
+                    "ret",
+                    "passStringToWasm(json === undefined ? 'undefined' : json)",
+                );
+                format!("
+                    function(idx, ptrptr) {{
+                        const json = JSON.stringify(getObject(idx));
+                        {}
+                        getUint32Memory()[ptrptr / 4] = ptr;
+                        return len;
+                    }}
+                ", pass)
+            });
+
+            bind("__wbindgen_promise_then", &|me| {
+                me.expose_get_object();
+                me.expose_add_heap_object();
+                me.function_table_needed = true;
+                String::from("
+                    function(idx, invoke, f) {
+                        const cb = wasm.__wbg_function_table.get(invoke);
+                        Promise.resolve(getObject(idx)).then(
+                            function(v) { cb(f, 1, addHeapObject(v)); },
+                            function(e) { cb(f, 0, addHeapObject(e)); }
+                        );
+                    }
+                ")
+            });
+
             bind("__wbindgen_string_get", &|me| {
                 me.expose_pass_string_to_wasm();
                 me.expose_get_object();
                 me.expose_uint32_memory();
-                String::from("
-                    function(i, len_ptr) {
+                let pass = me.destructure(&["ptr", "len"], "ret", "passStringToWasm(obj)");
+                format!("
+                    function(i, len_ptr) {{
                         let obj = getObject(i);
                         if (typeof(obj) !== 'string')
                             return 0;
-                        const [ptr, len] = passStringToWasm(obj);
+                        {}
                         getUint32Memory()[len_ptr / 4] = len;
                         return ptr;
+                    }}
+                ", pass)
+            });
+
+            // Only the descriptor shims call this, and they're only reachable
+            // from JS if the gc pass didn't run and remove them.
+            bind("__wbindgen_describe", &|_| {
+                String::from("
+                    function() {
+                        throw new Error('descriptors can only be run by wasm-bindgen');
                     }
                 ")
             });
@@ -283,41 +725,348 @@ impl<'a> Context<'a> {
                 ")
             });
         }
+        if self.exposed_globals.contains("panic_handler") {
+            self.define_js_name("setPanicHandler", "the debug mode panic handler".to_string())?;
+        }
+
+        // Lets tests and leak detection in debug mode see which JS values
+        // wasm is still holding on to.
+        // With weak refs it also counts the instances of exported classes
+        // freed each way. With reference types the values are in the table,
+        // which wasm fills without the glue.
+        let instance_counts = self.exposed_globals.contains("instance_counts");
+        let heap = self.exposed_globals.contains("add_heap_object") ||
+            self.config.reference_types;
+        if self.config.debug && (instance_counts || heap) {
+            self.expose_add_heap_object();
+            self.define_js_name("debugObjectStats", "the debug mode heap statistics".to_string())?;
+            let borrowed = if self.exposed_globals.contains("stack") {
+                "stack_pointer"
+            } else {
+                "0"
+            };
+            let (counts, counts_doc, counts_ts) = if instance_counts {
+                ("\nreclaimed: instance_counts.reclaimed,\nfreed: instance_counts.freed,",
+                 "\n * `reclaimed` and `freed` count the instances of exported classes freed\
+                  \n * by the garbage collector and by calling `free`.",
+                 "\n  reclaimed: number;\n  freed: number;")
+            } else {
+                ("", "", "")
+            };
+            if self.config.reference_types {
+                self.global(&format!("
+                    function __wbindgen_object_stats(sample) {{
+                        const table = wasm.{table};
+                        const stats = {{
+                            live: 0,
+                            capacity: table.length - {start},
+                            borrowed: {borrowed},{counts}
+                            sample: [],
+                        }};
+                        for (let i = {start}; i < table.length; i++) {{
+                            const obj = table.get(i);
+                            if (obj === null)
+                                continue;
+                            stats.live++;
+                            if (stats.sample.length < (sample || 0)) {{
+                                const ctor = obj !== undefined && obj.constructor;
+                                stats.sample.push(ctor && ctor.name ? ctor.name : typeof(obj));
+                            }}
+                        }}
+                        stats.live -= stats.borrowed;
+                        return stats;
+                    }}
+                ", table = externref::TABLE, start = JSIDX_RESERVED, borrowed = borrowed,
+                   counts = counts));
+            } else {
+                let start = RESERVED_VALUES.len();
+                self.global(&format!("
+                    function __wbindgen_object_stats(sample) {{
+                        const stats = {{
+                            live: heap_live,
+                            capacity: slab.length - {start},
+                            borrowed: {borrowed},{counts}
+                            sample: [],
+                        }};
+                        for (let i = {start}; i < slab.length && stats.sample.length < (sample || 0); i++) {{
+                            const entry = slab[i];
+                            if (typeof(entry) === 'number')
+                                continue;
+                            const ctor = entry.obj.constructor;
+                            stats.sample.push(ctor && ctor.name ? ctor.name : typeof(entry.obj));
+                        }}
+                        return stats;
+                    }}
+                ", start = start, borrowed = borrowed, counts = counts));
+            }
+            self.export("debugObjectStats", "__wbindgen_object_stats");
+            self.typescript.push_str(&format!("\
+/**
+ * Counts the JS values owned by wasm which haven't been dropped yet (`live`),
+ * the room for them before the heap grows (`capacity`) and the values only
+ * borrowed for the calls into wasm in progress (`borrowed`). With `sample`,
+ * also lists the constructor names of up to that many of the live values.{}
+ */
+export function debugObjectStats(sample?: number): {{
+  live: number;
+  capacity: number;
+  borrowed: number;{}
+  sample: string[];
+}};
+
+", counts_doc, counts_ts));
+        }
+
+        // The start function runs once the glue has the instance, so that
+        // it can call imports which call back into the wasm. Before that the
+        // glue checks the instance is of the wasm it was generated for, and
+        // fills the reserved slots of its table of JS values if it has one.
+        let has_start = self.export_start();
+        let mut start = String::new();
+        if self.build_id.is_some() {
+            start.push_str("\n__wbg_check_wasm();");
+        }
+        if self.config.reference_types {
+            self.expose_init_externrefs();
+            start.push_str("\n__wbg_init_externrefs();");
+        }
+        if has_start {
+            start.push_str("\nwasm.__wbindgen_start();");
+        }
+
+        // An imported memory isn't an export of the instance, so it's added
+        // to them for the glue to find as usual, and can be handed to other
+        // instances sharing it.
+        let memory = self.imported_memory();
+        if memory.is_some() {
+            if self.config.imports_wasm() {
+                return Err("the wasm imports its memory, which can't be provided when \
+                            the wasm is imported as an ES module; use the fetch-url or \
+                            deferred wasm import mode instead".to_string());
+            }
+            self.export("wasmMemory", "function() { return wasm.memory; }");
+            self.typescript.push_str("\
+/**
+ * The memory the wasm imports, to pass on to other instances sharing it like
+ * ones in workers.
+ */
+export function wasmMemory(): WebAssembly.Memory;
+
+");
+        }
 
+        self.check_placeholder_imports_wired()?;
         self.rewrite_imports(module_name);
+        if let Some(id) = self.build_id.clone() {
+            self.expose_check_wasm(&id);
+        }
+        let names = &self.config.output_names;
+        let glue = self.config.glue_module(module_name);
 
-        let mut js = if self.config.no_modules {
-            format!("
-                (function() {{
-                    var wasm;
-                    const __exports = {{}};
-                    {globals}
-                    function init(wasm_path) {{
-                        return fetch(wasm_path)
-                            .then(response => response.arrayBuffer())
-                            .then(buffer => WebAssembly.instantiate(buffer, {{ './{module}': __exports }}))
-                            .then(({{instance}}) => {{
-                                wasm = init.wasm = instance.exports;
-                                return;
-                            }});
-                    }};
-                    self.{global_name} = Object.assign(init, __exports);
-                }})();
+        // The prelude and epilogue only go in once nothing else reformats the
+        // glue, until then placeholders mark where.
+        let placeholder = |text: &str, marker: &str| {
+            if text.is_empty() { String::new() } else { format!("\n{}\n", marker) }
+        };
+        let prelude = placeholder(&self.config.js_prelude, PRELUDE);
+        let epilogue = placeholder(&self.config.js_epilogue, EPILOGUE);
+
+        let js = if self.config.no_modules {
+            // The memory the wasm imports is created unless one is passed to
+            // `init`.
+            let (params, setup, imports, exports) = match memory {
+                Some((ref module, ref field, ref ty)) => {
+                    ("input, memory",
+                     format!("{}memory = memory || {};\n\
+                              const imports = {{ '{}': __exports, '{}': {{ '{}': memory }} }};\n",
+                             if self.shared_memory { SHARED_MEMORY_CHECK } else { "" },
+                             memory_constructor(ty, self.shared_memory),
+                             glue, module, field),
+                     "imports".to_string(),
+                     "Object.assign({ memory: memory }, INSTANCE.exports)")
+                }
+                None => {
+                    ("input",
+                     String::new(),
+                     format!("{{ '{}': __exports }}", glue),
+                     "INSTANCE.exports")
+                }
+            };
+            let global_name = self.config.no_modules_global_name();
+            self.global(&instantiate_function(self.config.csp_safe));
+            self.global(&default_input(&self.config.wasm_path(&names.wasm_file(module_name))));
+            let mut setup = format!("if (input === undefined) input = __wbg_default_input;\n{}", setup);
+            if self.config.umd {
+                // Required from CommonJS, the wasm is read from next to the
+                // module rather than fetched.
+                setup = format!("\
+                    if (input === undefined && typeof __dirname === 'string') {{\n\
+                        input = require('fs').readFileSync(require('path').join(__dirname, '{}'));\n\
+                    }}\n{}\
+                ", self.config.wasm_path(&names.wasm_file(module_name)), setup);
+            }
+            let load = format!("return __wbg_instantiate(input, {})", imports);
+            let (instantiate, assign_exports, loader) = if self.es5() {
+                (format!("
+                    {}
+                        .then(function(result) {{
+                            wasm = init.wasm = {};{}
+                            return init;
+                        }});
+                ", load.trim(), exports.replace("INSTANCE", "result.instance"), start), "
+                    Object.keys(__exports).forEach(function(key) {
+                        init[key] = __exports[key];
+                    });
+                ", "init")
+            } else {
+                (format!("
+                    {}
+                        .then(({{instance}}) => {{
+                            wasm = init.wasm = {};{}
+                            return init;
+                        }});
+                ", load.trim(), exports.replace("INSTANCE", "instance"), start),
+                 "",
+                 "Object.assign(init, __exports)")
+            };
+            // With UMD the global is only needed without a module loader.
+            let body = format!("
+                {prelude}var wasm;
+                const __exports = {{}};
+                {global_object}
+                {globals}
+                function init({params}) {{
+                    {setup}{instantiate}
+                }};
+                {assign_exports}
             ",
+                    prelude = prelude,
+                    global_object = if self.config.umd { "" } else { GLOBAL_OBJECT },
                     globals = self.globals,
-                    module = module_name,
-                    global_name = self.config.no_modules_global
-                        .as_ref()
-                        .map(|s| &**s)
-                        .unwrap_or("wasm_bindgen"),
-            )
+                    params = params,
+                    setup = setup,
+                    instantiate = instantiate.trim(),
+                    assign_exports = assign_exports.trim(),
+            );
+            if self.config.umd {
+                format!("
+                    (function(factory) {{
+                        if (typeof define === 'function' && define.amd) {{
+                            define([], factory);
+                        }} else if (typeof module === 'object' && module.exports) {{
+                            module.exports = factory();
+                        }} else {{
+                            {global_object}
+                            {assign}
+                        }}
+                    }})(function() {{
+                        {body}{epilogue}
+                        return {loader};
+                    }});
+                ",
+                        global_object = GLOBAL_OBJECT,
+                        assign = assign_global(global_name, "factory()"),
+                        body = body.trim(),
+                        epilogue = epilogue,
+                        loader = loader,
+                )
+            } else {
+                format!("
+                    (function() {{
+                        {body}
+                        {assign}{epilogue}
+                    }})();
+                ",
+                        body = body.trim(),
+                        assign = assign_global(global_name, loader),
+                        epilogue = epilogue,
+                )
+            }
         } else {
-            let import_wasm = if self.config.nodejs {
-                self.footer.push_str(&format!("wasm = require('./{}_bg');",
-                                              module_name));
+            // The consts read from the wasm are assigned as soon as it's
+            // available.
+            let assign_consts = self.const_getters.iter()
+                .map(|name| format!("{} = {}();", name, shared::const_getter(name)))
+                .collect::<Vec<_>>();
+            let inline = start.replace("\n", " ") +
+                &assign_consts.iter().map(|a| format!(" {}", a)).collect::<String>();
+            // Until the wasm has been instantiated any use of it (and so of any
+            // export) fails with a descriptive error.
+            let (then, get) = if self.es5() {
+                (format!("function(exports) {{ wasm = exports;{} }}", inline),
+                 "get: function()")
+            } else {
+                (format!("exports => {{ wasm = exports;{} }}", inline), "get()")
+            };
+            let not_ready = |what: &str| {
+                format!("\
+                    var wasm = new Proxy({{}}, {{\n\
+                        {} {{\n\
+                            throw new Error('wasm not yet initialized; {} first');\n\
+                        }},\n\
+                    }});\
+                ", get, what)
+            };
+            let import_wasm = if self.config.nodejs && self.config.nodejs_async {
+                self.footer.push_str(&format!("\
+                    module.exports.ready = require('{}').ready\n\
+                        .then({});\n\
+                ", names.wasm_loader_import(module_name, false), then));
+                self.typescript.push_str("export const ready: Promise<void>;\n");
+                not_ready("await `ready`")
+            } else if self.config.nodejs {
+                self.footer.push_str(&format!("wasm = require('{}');{}",
+                                              names.wasm_loader_import(module_name, false), start));
                 format!("var wasm;")
+            } else if self.config.awaits_wasm() {
+                // The loader is evaluated before the glue, starting to load
+                // the wasm, and the glue waits for it once all of it has run.
+                self.footer.push_str(&format!("wasm = await __wbg_ready;{}\n", inline));
+                format!("import {{ ready as __wbg_ready }} from '{}';\nvar wasm;",
+                        names.wasm_loader_import(module_name, true))
             } else {
-                format!("import * as wasm from './{}_bg';", module_name)
+                match self.config.wasm_import_mode {
+                    WasmImportMode::EsModule => {
+                        if !start.is_empty() {
+                            self.footer.push_str(&format!("{}\n", start.trim()));
+                        }
+                        for assign in assign_consts.iter() {
+                            self.footer.push_str(&format!("{}\n", assign));
+                        }
+                        let wasm = names.wasm_import(module_name, self.config.bundler);
+                        format!("import * as wasm from '{}';", self.config.wasm_path(&wasm))
+                    }
+                    WasmImportMode::FetchUrl => {
+                        self.footer.push_str(&format!("\
+                            export const ready = __wbg_ready.then({});\n\
+                        ", then));
+                        self.typescript.push_str("export const ready: Promise<void>;\n");
+                        format!("import {{ ready as __wbg_ready }} from '{}';\n{}",
+                                names.wasm_loader_import(module_name, false),
+                                not_ready("await `ready`"))
+                    }
+                    WasmImportMode::Deferred => {
+                        // The host creates the memory the wasm imports, which
+                        // it passes along with the instance.
+                        let (params, exports, ts) = if memory.is_some() {
+                            ("instance, memory",
+                             "Object.assign({ memory: memory }, instance.exports)",
+                             "instance: WebAssembly.Instance, memory: WebAssembly.Memory")
+                        } else {
+                            ("instance", "instance.exports", "instance: WebAssembly.Instance")
+                        };
+                        self.footer.push_str(&format!("\
+                            export function setWasm({}) {{\n\
+                                wasm = {};{}{}\n\
+                            }}\n\
+                        ", params, exports, start,
+                           assign_consts.iter().map(|a| format!("\n{}", a)).collect::<String>()));
+                        self.typescript.push_str(&format!("\
+                            export function setWasm({}): void;\n\
+                        ", ts));
+                        not_ready("call `setWasm`")
+                    }
+                }
             };
 
             format!("\
@@ -325,8 +1074,11 @@ impl<'a> Context<'a> {
                 {import_wasm}\n\
                 {imports}\n\
 
+                {prelude}\
                 {globals}\n\
-                {footer}",
+                {footer}{epilogue}",
+                    prelude = prelude,
+                    epilogue = epilogue,
                     import_wasm = import_wasm,
                     globals = self.globals,
                     imports = self.imports,
@@ -334,67 +1086,339 @@ impl<'a> Context<'a> {
             )
         };
 
-        self.export_table();
-        self.gc();
+        self.check_internal_exports()?;
+
+        // Now that all the glue has been generated we know exactly which
+        // internal exports it uses, and everything else can be gc'd. The
+        // 64-bit integers are split first so that wrappers returning two
+        // values wrap functions taking only 32-bit ones. The wrappers passing
+        // `externref`s come last, as their bodies refer to the functions by
+        // the indices gc leaves them with.
+        if self.rewrite_wasm {
+            self.split_i64_exports();
+            self.split_i64_imports();
+            self.wrap_multi_value_exports();
+            self.unexport_unused_internal_exports();
+            self.export_table();
+            self.gc();
+            if self.config.reference_types {
+                self.externref_patches = Some(externref::run(self.module,
+                                                             &self.externref_exports,
+                                                             &self.externref_imports));
+            }
+            self.demangle_name_section();
+            self.strip_debug_sections();
+            self.multi_value_types = self.multi_value_types();
+        }
+
+        let js = if self.runtime.is_empty() { js } else { self.import_runtime(js) };
+        let js = if self.es5() { var_declarations(&js) } else { js };
+        self.metadata.imports = self.imported_shapes.iter()
+            .map(|(module, shape)| {
+                let mut members = Vec::new();
+                shape.used_paths("", &mut members);
+                metadata::ImportedModule { module: module.clone(), members }
+            })
+            .collect();
+        self.write_imports_interface();
+        // Without modules everything is reached through the global, so the
+        // declarations are of a namespace of the same name.
+        let ts = if self.config.no_modules {
+            let params = if memory.is_some() {
+                "input?: string | ArrayBuffer | ArrayBufferView, memory?: WebAssembly.Memory"
+            } else {
+                "input?: string | ArrayBuffer | ArrayBufferView"
+            };
+            let name = self.config.no_modules_global_name();
+            let mut ts = global_namespace(name, params, &self.typescript);
+            if self.config.umd {
+                ts.push_str(&format!("export = {};\n", name));
+                // Only a plain identifier can name the global of a UMD module.
+                if !name.contains('.') {
+                    ts.push_str(&format!("export as namespace {};\n", name));
+                }
+            }
+            ts
+        } else {
+            self.typescript.clone()
+        };
+        let ts = format!("{}{}{}",
+                         placeholder(&self.config.ts_prelude, PRELUDE),
+                         ts,
+                         placeholder(&self.config.ts_epilogue, EPILOGUE));
+        if self.config.splits_output() {
+            return Ok(self.split(module_name, js, ts))
+        }
+        Ok((reindent(&js), reindent(&format!("/* tslint:disable */\n{}", ts))))
+    }
+
+    /// Imports what the ES module `js` (and the classes to be split out of it)
+    /// uses from the runtime module, see `Bindgen::shared_runtime`.
+    fn import_runtime(&self, js: String) -> String {
+        let runtime = self.runtime.iter().map(|p| &*p.1).collect::<Vec<_>>().join("\n");
+        let (declared, _) = module_names(&runtime);
+        let mut used = free_names(&js);
+        for class in self.split_classes.iter() {
+            used.extend(free_names(&class.1));
+        }
+        let names = declared.iter()
+            .filter(|name| used.contains(&name[..]))
+            .cloned()
+            .collect::<Vec<_>>();
+        let ext = if self.config.awaits_wasm() { ".js" } else { "" };
+        let import = format!("import {{ {} }} from './{}{}';\n", names.join(", "), RUNTIME_STEM, ext);
+        js.replacen("/* tslint:disable */\n", &format!("/* tslint:disable */\n{}", import), 1)
+    }
+
+    /// Moves each class into its own module, where the rest of the glue is
+    /// the core module, see `Bindgen::split_output`. Returns the module
+    /// re-exporting everything in place of the glue.
+    ///
+    /// Every module imports the names it uses which another one declares,
+    /// with the core exporting those of its own which aren't exports anyway.
+    fn split(&mut self, module_name: &str, js: String, ts: String) -> (String, String) {
+        let core = format!("{}_core", module_name);
+        // Deno and node's ES modules only resolve imports naming the file.
+        let ext = if self.config.awaits_wasm() { ".js" } else { "" };
+        let mut modules = vec![(core.clone(), js, ts)];
+        let mut class_modules = BTreeMap::new();
+        for (class, js, ts) in mem::take(&mut self.split_classes) {
+            let stem = format!("{}_class_{}", module_name, class);
+            let js = if self.es5() { var_declarations(&js) } else { js };
+            class_modules.insert(class, stem.clone());
+            modules.push((stem, js, ts));
+        }
+
+        let (core_declared, core_exported) = module_names(&modules[0].1);
+        let mut core_exports = BTreeSet::new();
+        let mut imported = Vec::new();
+        for (stem, js, ts) in modules.iter() {
+            let mut js_imports = BTreeMap::new();
+            for name in free_names(js) {
+                let from = if class_modules.contains_key(name) {
+                    &class_modules[name]
+                } else if core_declared.contains(name) && *stem != core {
+                    if !core_exported.contains(name) {
+                        core_exports.insert(name.to_string());
+                    }
+                    &core
+                } else {
+                    continue
+                };
+                if from != stem {
+                    js_imports.entry(from).or_insert_with(BTreeSet::new).insert(name);
+                }
+            }
+            let mut ts_imports = BTreeMap::new();
+            for name in free_names(ts) {
+                match class_modules.get(name) {
+                    Some(from) if from != stem => {
+                        ts_imports.entry(from).or_insert_with(BTreeSet::new).insert(name);
+                    }
+                    _ => {}
+                }
+            }
+            let import_list = |imports: BTreeMap<&String, BTreeSet<&str>>| {
+                let mut list = String::new();
+                for (from, names) in imports.iter() {
+                    let names = names.iter().cloned().collect::<Vec<_>>();
+                    list.push_str(&format!("import {{ {} }} from './{}{}';\n",
+                                           names.join(", "), from, ext));
+                }
+                if !list.is_empty() {
+                    list.push('\n');
+                }
+                list
+            };
+            imported.push((import_list(js_imports), import_list(ts_imports)));
+        }
+
+        let mut index_js = String::from("/* tslint:disable */\n");
+        let mut index_ts = String::from("/* tslint:disable */\n");
+        let core_exported = core_exported.into_iter().collect::<Vec<_>>();
+        index_js.push_str(&format!("export {{ {} }} from './{}{}';\n",
+                                   core_exported.join(", "), core, ext));
+        for (class, stem) in class_modules.iter() {
+            index_js.push_str(&format!("export {{ {} }} from './{}{}';\n", class, stem, ext));
+        }
+        for (stem, _, _) in modules.iter() {
+            index_ts.push_str(&format!("export * from './{}{}';\n", stem, ext));
+        }
 
-        while js.contains("\n\n\n") {
-            js = js.replace("\n\n\n", "\n\n");
+        for ((stem, js, ts), (js_imports, ts_imports)) in modules.into_iter().zip(imported) {
+            let mut js = if stem == core {
+                js.replacen("/* tslint:disable */\n", &format!("/* tslint:disable */\n{}", js_imports), 1)
+            } else {
+                format!("/* tslint:disable */\n{}{}", js_imports, js)
+            };
+            if stem == core && !core_exports.is_empty() {
+                let names = core_exports.iter().cloned().collect::<Vec<_>>();
+                js.push_str(&format!("\nexport {{ {} }};\n", names.join(", ")));
+            }
+            let ts = format!("/* tslint:disable */\n{}{}", ts_imports, ts);
+            self.split_modules.push((stem, reindent(&js), reindent(&ts)));
         }
+        (reindent(&index_js), reindent(&index_ts))
+    }
 
-        (js, self.typescript.clone())
+    /// Declares the `Imports` interface, describing what each imported
+    /// module must export (or without modules what globals must exist) for
+    /// the generated JS to work.
+    fn write_imports_interface(&mut self) {
+        let shapes = mem::take(&mut self.imported_shapes);
+        let mut members = String::new();
+        for (module, shape) in shapes {
+            match module {
+                Some(module) => {
+                    members.push_str(&format!("'{}': {{\n{}}};\n", module, shape.members_ts()));
+                }
+                None if self.config.no_modules => members.push_str(&shape.members_ts()),
+                None => {}
+            }
+        }
+        if members.is_empty() {
+            return
+        }
+        let mut name = String::from("Imports");
+        while self.js_names.contains_key(&name) {
+            name.push('_');
+        }
+        if !self.typescript.is_empty() && !self.typescript.ends_with("\n\n") {
+            self.typescript.push('\n');
+        }
+        self.typescript.push_str(&format!("export interface {} {{\n{}}}\n", name, members));
     }
 
-    fn write_classes(&mut self) {
-        let classes = mem::replace(&mut self.exported_classes, Default::default());
+    fn write_classes(&mut self) -> Result<(), String> {
+        let classes = mem::take(&mut self.exported_classes);
+        for class in classes.keys() {
+            if js_identifier(class) != *class {
+                return Err(format!("the struct `{}` can't be exported to JS as `{0}` is \
+                                    a reserved word in JS, the struct must be renamed",
+                                   class));
+            }
+            self.define_js_name(class, format!("class `{}`", class))?;
+        }
         for (class, exports) in classes {
-            self.write_class(&class, &exports);
+            self.write_class(&class, &exports)?;
         }
+        Ok(())
     }
 
-    fn write_class(&mut self, name: &str, class: &ExportedClass) {
-        let mut dst = format!("class {} {{\n", name);
-        let mut ts_dst = format!("export {}", dst);
+    fn write_class(&mut self, name: &str, class: &ExportedClass) -> Result<(), String> {
+        let mut dst = String::new();
+        let ptr = self.ptr_property(name);
+        // A class other inputs export too extends the one they share, which
+        // extends any JS class in its place. The typings are those of the
+        // owner's class, so they only name the JS class.
+        let (ts_extends, ts_import) = match class.superclass {
+            Some((ref base, _, ref ts_import)) => (format!(" extends {}", base), &ts_import[..]),
+            None => (String::new(), ""),
+        };
+        let shared = self.shared_classes.get(name).cloned();
+        let (superclass, shared_import) = match shared {
+            Some(_) => {
+                let forward_args = class.superclass.as_ref().is_some_and(|s| s.1);
+                let (import, local) = self.import_shared_class(name);
+                (Some((local, forward_args, String::new())), import)
+            }
+            None => (class.superclass.clone(), String::new()),
+        };
+        let extends = match superclass {
+            Some((ref base, _, _)) => format!(" extends {}", base),
+            None => String::new(),
+        };
+        let mut ts_dst = match (&class.iterator_len, &class.iterator_get) {
+            (&Some(_), &Some((_, ref ty))) => {
+                format!("{}export class {}{} implements Iterable<{}> {{\n",
+                        ts_import, name, ts_extends, ty)
+            }
+            _ => format!("{}export class {}{} {{\n", ts_import, name, ts_extends),
+        };
+        // A subclass has to call the superclass's constructor before it can
+        // use `this`. In ES5 only shared classes are extended, whose
+        // constructor does nothing for their subclasses.
+        let (super_call, super_args) = match superclass {
+            _ if self.es5() => ("", ""),
+            Some((_, true, _)) => ("super();\n", "super(...args);\n"),
+            Some((_, false, _)) => ("super();\n", "super();\n"),
+            None => ("", ""),
+        };
 
-        if self.config.debug || class.constructor.is_some() {
+        // ES5 has no rest parameters, but the same array can be made out of
+        // `arguments`.
+        let (params, mut body) = if self.es5() {
+            ("()", String::from("const args = Array.prototype.slice.call(arguments);\n"))
+        } else {
+            ("(...args)", String::new())
+        };
+        let (construct, params) = if self.config.debug || class.constructor.is_some() {
             self.expose_constructor_token();
 
-            dst.push_str(&format!("
-                static __construct(ptr) {{
-                    return new {}(new ConstructorToken(ptr));
+            body.push_str(&format!("
+                if (args.length === 1 && args[0] instanceof ConstructorToken) {{
+                    {}this.{} = args[0].ptr;
+                    return;
                 }}
-
-                constructor(...args) {{
-                    if (args.length === 1 && args[0] instanceof ConstructorToken) {{
-                        this.ptr = args[0].ptr;
-                        return;
-                    }}
-            ", name));
+            ", super_call, ptr));
 
             if let Some(ref constructor) = class.constructor {
                 ts_dst.push_str(&format!("constructor(...args: any[]);\n"));
 
-                dst.push_str(&format!("
+                let invoc = if self.es5() {
+                    format!("{}.{}.apply({0}, args)", name, constructor)
+                } else {
+                    format!("{}.{}(...args)", name, constructor)
+                };
+                if let (Some(ref message), true) = (&class.deprecated, self.config.debug) {
+                    self.expose_warn_deprecated();
+                    let what = format!("new {}", name);
+                    body.push_str(&format!("_warnDeprecated({}, {});\n",
+                                           js_string(&what),
+                                           js_string(&deprecation_warning(&what, message))));
+                }
+                body.push_str(super_args);
+                body.push_str(&format!("
                     // This invocation of new will call this constructor with a ConstructorToken
-                    let instance = {class}.{constructor}(...args);
-                    this.ptr = instance.ptr;
-                ", class = name, constructor = constructor));
+                    let instance = {};
+                    this.{ptr} = instance.{ptr};
+                ", invoc, ptr = ptr));
+                // The pointer now belongs to `this` rather than `instance`.
+                body.push_str(&self.unregister_instance(name, "instance"));
+                body.push_str(&self.register_instance(name, "this", &format!("this.{}", ptr)));
             } else {
-                dst.push_str("throw new Error('you cannot invoke `new` directly without having a \
-            method annotated a constructor');");
+                body.push_str("throw new Error('you cannot invoke `new` directly without having a \
+            method annotated a constructor');\n");
             }
 
-            dst.push_str("}");
+            (format!("new {}(new ConstructorToken(ptr))", name), params)
         } else {
-            dst.push_str(&format!("
-                static __construct(ptr) {{
-                    return new {}(ptr);
-                }}
-
-                constructor(ptr) {{
-                    this.ptr = ptr;
-                }}
-            ", name));
-        }
+            body = format!("{}this.{} = ptr;\n", super_call, ptr);
+            (format!("new {}(ptr)", name), "(ptr)")
+        };
+        let construct = if self.config.weak_refs {
+            self.expose_finalization(name);
+            format!("const obj = {};\n{}return obj;", construct,
+                    self.register_instance(name, "obj", "ptr"))
+        } else {
+            format!("return {};", construct)
+        };
+        let construct = format!("(ptr) {{\n{}\n}}", construct);
+        let construct = self.class_member(name, Member::Static, "__construct", &construct);
+        // In ES5 the class is a constructor function followed by statements
+        // defining its members.
+        if self.es5() {
+            dst.push_str(&format!("function {}{} {{\n{}}}\n", name, params, body));
+            if let Some((ref base, _, _)) = superclass {
+                dst.push_str(&format!("{0}.prototype = Object.create({1}.prototype);\n\
+                                       {0}.prototype.constructor = {0};\n", name, base));
+            }
+            dst.push_str(&construct);
+        } else {
+            dst.push_str(&format!("class {}{} {{\n", name, extends));
+            dst.push_str(&construct);
+            dst.push_str(&format!("\nconstructor{} {{\n{}}}\n", params, body));
+        }
 
         let new_name = shared::new_function(&name);
         if self.wasm_import_needed(&new_name) {
@@ -407,54 +1431,506 @@ impl<'a> Context<'a> {
             ", name));
         }
 
+        let instanceof_name = shared::instanceof_function(name);
+        if self.wasm_import_needed(&instanceof_name) {
+            self.expose_get_object();
+
+            self.export(&instanceof_name, &format!("
+                function(idx) {{
+                    return getObject(idx) instanceof {} ? 1 : 0;
+                }}
+            ", name));
+        }
+
+        let ptr_name = shared::ptr_function(name);
+        if self.wasm_import_needed(&ptr_name) {
+            self.expose_get_object();
+
+            self.export(&ptr_name, &format!("
+                function(idx) {{
+                    const obj = getObject(idx);
+                    return obj instanceof {} ? obj.{} : 0;
+                }}
+            ", name, ptr));
+        }
+
+        // Unwrapping hands ownership to Rust, so like `free` this leaves the
+        // JS object with a null pointer.
+        let unwrap_name = shared::unwrap_function(name);
+        if self.wasm_import_needed(&unwrap_name) {
+            self.expose_get_object();
+
+            self.export(&unwrap_name, &format!("
+                function(idx) {{
+                    const obj = getObject(idx);
+                    if (!(obj instanceof {})) {{
+                        return 0;
+                    }}
+                    const ptr = obj.{ptr};
+                    obj.{ptr} = 0;
+                    {}return ptr;
+                }}
+            ", name, self.unregister_instance(name, "obj"), ptr = ptr));
+        }
+
+        self.define_js_name(&format!("{}.prototype.free", name),
+                            format!("the generated `{}.free` method", name))?;
         for field in class.fields.iter() {
+            self.define_js_name(&format!("{}.prototype.{}", name, field.name),
+                                format!("field `{}::{}`", name, field.name))?;
             let wasm_getter = shared::struct_field_get(name, &field.name);
             let wasm_setter = shared::struct_field_set(name, &field.name);
-            let descriptor = self.describe(&wasm_getter);
+            let descriptor = self.describe(&wasm_getter)?;
+            self.metadata.class(name).fields.push(metadata::Field {
+                name: field.name.clone(),
+                ty: descriptor.metadata_ty(),
+                readonly: field.readonly,
+            });
 
             let set = {
                 let mut cx = Js2Rust::new(&field.name, self);
-                cx.method(true)
-                    .argument(&descriptor)
-                    .ret(&None);
-                ts_dst.push_str(&format!("{}{}: {}\n",
+                cx.method(name)
+                    .assert_not_moved(&format!("{}.{}", name, field.name))
+                    .argument(&descriptor)?
+                    .ret(&None)?;
+                ts_dst.push_str(&format!("{}{}: {};\n",
                                          if field.readonly { "readonly " } else { "" },
                                          field.name,
                                          &cx.js_arguments[0].1));
                 cx.finish("", &format!("wasm.{}", wasm_setter)).0
             };
             let (get, _ts) = Js2Rust::new(&field.name, self)
-                .method(true)
-                .ret(&Some(descriptor))
+                .method(name)
+                .assert_not_moved(&format!("{}.{}", name, field.name))
+                .ret(&Some(descriptor))?
                 .finish("", &format!("wasm.{}", wasm_getter));
 
-            dst.push_str("get ");
-            dst.push_str(&field.name);
-            dst.push_str(&get);
             dst.push_str("\n");
+            dst.push_str(&self.class_member(name, Member::Getter, &field.name, &get));
             if !field.readonly {
-                dst.push_str("set ");
-                dst.push_str(&field.name);
-                dst.push_str(&set);
+                dst.push_str(&self.class_member(name, Member::Setter, &field.name, &set));
+            } else if self.config.debug {
+                // Without a setter sloppy-mode assignments are silently
+                // ignored, so in debug mode make them an error everywhere.
+                let set = format!("
+                    (_value) {{
+                        throw new TypeError(\"cannot assign to readonly field '{field}' of {class}\");
+                    }}
+                ", field = field.name, class = name);
+                dst.push('\n');
+                dst.push_str(&self.class_member(name, Member::Setter, &field.name, set.trim()));
             }
         }
 
-        dst.push_str(&format!("
-            free() {{
-                const ptr = this.ptr;
-                this.ptr = 0;
-                wasm.{}(ptr);
+        // In debug mode a double free is reported rather than handing a
+        // dangling pointer back to Rust.
+        let check_freed = if self.config.debug {
+            format!("
+                if (ptr === 0) {{
+                    console.warn('Attempt to free an already freed value: {}');
+                    return;
+                }}
+            ", name)
+        } else {
+            String::new()
+        };
+        let mut unregister = self.unregister_instance(name, "this");
+        if self.config.weak_refs && self.config.debug {
+            self.expose_instance_counts();
+            unregister.push_str("instance_counts.freed++;\n");
+        }
+        let free = format!("
+            () {{
+                const ptr = this.{ptr};
+                {}
+                this.{ptr} = 0;
+                {}wasm.{}(ptr);
             }}
-        ", shared::free_function(&name)));
+        ", check_freed, unregister, shared::free_function(name), ptr = ptr);
+        dst.push('\n');
+        dst.push_str(&self.class_member(name, Member::Method, "free", free.trim()));
         ts_dst.push_str("free(): void;\n");
 
+        // Make instances readable when logged and, in debug mode, when
+        // stringified. Methods exported from Rust with the same name win.
+        let tag = format!("() {{\nreturn '{}';\n}}", name);
+        dst.push('\n');
+        dst.push_str(&self.class_member(name, Member::Getter, "[Symbol.toStringTag]", &tag));
+        if self.config.debug {
+            if !self.js_names.contains_key(&format!("{}.prototype.toJSON", name)) {
+                let fields = class.fields.iter()
+                    .map(|f| format!("{0}: this.{0}", f.name))
+                    .collect::<Vec<_>>();
+                let to_json = format!("() {{\nreturn {{ {} }};\n}}", fields.join(", "));
+                dst.push('\n');
+                dst.push_str(&self.class_member(name, Member::Method, "toJSON", &to_json));
+                ts_dst.push_str("toJSON(): any;\n");
+            }
+            if !self.js_names.contains_key(&format!("{}.prototype.toString", name)) {
+                let to_string = format!("() {{\nreturn '{} {{ ptr: ' + this.{} + ' }}';\n}}",
+                                        name, ptr);
+                dst.push('\n');
+                dst.push_str(&self.class_member(name, Member::Method, "toString", &to_string));
+                ts_dst.push_str("toString(): string;\n");
+            }
+        }
+
+        match (&class.iterator_len, &class.iterator_get) {
+            (Some(len), Some((get, ty))) => {
+                self.define_js_name(&format!("{}.prototype[Symbol.iterator]", name),
+                                    format!("the generated `{}` iterator", name))?;
+                ts_dst.push_str(&format!("[Symbol.iterator](): Iterator<{}>;\n", ty));
+                // ES5 has no arrow functions to capture `this` with.
+                let (capture, next, this) = if self.es5() {
+                    ("const instance = this;", "function()", "instance")
+                } else {
+                    ("", "() =>", "this")
+                };
+                // The length is re-read on each step so that the usual moved
+                // value checks fire if the instance is freed mid-iteration.
+                let iterator = format!("
+                    () {{
+                        {capture}
+                        let i = 0;
+                        return {{
+                            next: {next} {{
+                                if (i < {this}.{len}()) {{
+                                    return {{ value: {this}.{get}(i++), done: false }};
+                                }}
+                                return {{ value: undefined, done: true }};
+                            }},
+                        }};
+                    }}
+                ", capture = capture, next = next, this = this, len = len, get = get);
+                dst.push('\n');
+                dst.push_str(&self.class_member(name, Member::Method, "[Symbol.iterator]",
+                                                iterator.trim()));
+            }
+            (None, None) => {}
+            (Some(_), None) => {
+                return Err(format!("the struct `{}` has an `iterator = len` method but \
+                                    no `iterator = get` method", name))
+            }
+            (None, Some(_)) => {
+                return Err(format!("the struct `{}` has an `iterator = get` method but \
+                                    no `iterator = len` method", name))
+            }
+        }
+
         dst.push_str(&class.contents);
         ts_dst.push_str(&class.typescript);
-        dst.push_str("}\n");
         ts_dst.push_str("}\n");
 
-        self.export(&name, &dst);
+        let doc = class.deprecated.as_ref().map(|m| deprecated_doc(m)).unwrap_or_default();
+        if !doc.is_empty() {
+            let start = ts_dst.find("export class").unwrap();
+            ts_dst.insert_str(start, &doc);
+        }
+
+        let globals = if self.config.splits_output() {
+            Some(mem::take(&mut self.globals))
+        } else {
+            None
+        };
+        // A split out class imports the shared one itself, as the core module
+        // is only evaluated after it.
+        if !shared_import.is_empty() {
+            if globals.is_some() || self.config.no_modules {
+                self.global(&shared_import);
+            } else {
+                self.imports.push_str(&shared_import);
+            }
+        }
+        if self.es5() {
+            self.global(&format!("{}{}", doc, dst));
+            self.export(name, name);
+        } else {
+            dst.push_str("}\n");
+            self.export_with_doc(name, &dst, &doc);
+        }
+        if let (Some(true), Some((ref base, _, _))) = (shared, &superclass) {
+            self.global(&format!("{}.__wbg_owner = {};\n", base, name));
+        }
+        if let Some(globals) = globals {
+            let js = mem::replace(&mut self.globals, globals);
+            self.split_classes.push((name.to_string(), js, ts_dst));
+            return Ok(())
+        }
         self.typescript.push_str(&ts_dst);
+        self.typescript.push('\n');
+        Ok(())
+    }
+
+    /// The statement importing the class `name` from the classes module, and
+    /// the name it's imported as, see `Bindgen::add_input_path`.
+    fn import_shared_class(&self, name: &str) -> (String, String) {
+        let local = format!("__wbg_shared_{}", name);
+        let import = if self.config.nodejs {
+            format!("const {} = require('./{}').{};\n", local, CLASSES_STEM, name)
+        } else if self.config.no_modules {
+            // The classes module is a script too, loaded before the glue.
+            let global = self.config.shared_classes_global.as_deref()
+                .unwrap_or_else(|| self.config.no_modules_global_name());
+            format!("const {} = __global.{}.{};\n", local, global, name)
+        } else {
+            let ext = if self.config.awaits_wasm() { ".js" } else { "" };
+            format!("import {{ {} as {} }} from './{}{}';\n", name, local, CLASSES_STEM, ext)
+        };
+        (import, local)
+    }
+
+    /// Formats the member `name` of the class `class`, where `function` is
+    /// its parameter list and body like `(arg0) { ... }`. The `name` may be a
+    /// computed key like `[Symbol.iterator]`.
+    ///
+    /// In ES5 a class is a constructor function, so its members are assigned
+    /// (or for accessors, defined) on it or its prototype instead.
+    fn class_member(&self, class: &str, kind: Member, name: &str, function: &str) -> String {
+        if !self.es5() {
+            let prefix = match kind {
+                Member::Method => "",
+                Member::Static => "static ",
+                Member::Getter => "get ",
+                Member::Setter => "set ",
+            };
+            return format!("{}{}{}\n", prefix, name, function)
+        }
+        let target = match kind {
+            Member::Static => class.to_string(),
+            _ => format!("{}.prototype", class),
+        };
+        let computed = name.starts_with('[');
+        let member = match kind {
+            Member::Method | Member::Static => {
+                let key = if computed { name.to_string() } else { format!(".{}", name) };
+                format!("{}{} = function{};\n", target, key, function)
+            }
+            Member::Getter | Member::Setter => {
+                let key = if computed {
+                    name[1..name.len() - 1].to_string()
+                } else {
+                    format!("'{}'", name)
+                };
+                let accessor = if kind == Member::Getter { "get" } else { "set" };
+                format!("Object.defineProperty({}, {}, {{\n\
+                             {}: function{},\n\
+                             configurable: true,\n\
+                         }});\n",
+                        target, key, accessor, function)
+            }
+        };
+        // Engines old enough to need ES5 may not have symbols either.
+        if computed {
+            format!("if (typeof Symbol === 'function') {{\n{}}}\n", member)
+        } else {
+            member
+        }
+    }
+
+    /// Points each of the `multi_value_exports` at a new function which calls
+    /// the original and returns its result along with the length it left in
+    /// the global argument.
+    ///
+    /// `parity_wasm` can't describe functions with more than one result, so
+    /// the wrappers are given single result types for now, see
+    /// `multi_value_types`.
+    fn wrap_multi_value_exports(&mut self) {
+        if self.multi_value_exports.is_empty() {
+            return
+        }
+        let imported = self.module.import_count(ImportCountType::Function) as u32;
+        let global_argument_ptr = exported_function(self.module, "__wbindgen_global_argument_ptr")
+            .expect("the global argument is needed to return two values");
+        for name in self.multi_value_exports.iter() {
+            let idx = match exported_function(self.module, name) {
+                Some(i) if i >= imported => i,
+                _ => continue,
+            };
+            let params = function_type(self.module, idx).params().to_vec();
+            let mut code = (0..params.len() as u32).map(Opcode::GetLocal).collect::<Vec<_>>();
+            code.push(Opcode::Call(idx));
+            code.push(Opcode::Call(global_argument_ptr));
+            code.push(Opcode::I32Load(2, 0));
+            code.push(Opcode::End);
+
+            // Each wrapper gets a type of its own, even if it's the same as
+            // another's for now, so that only they get a second result.
+            let ty = FunctionType::new(params, Some(ValueType::I32));
+            let wrapper = add_function(self.module, ty, Vec::new(), code);
+            repoint_export(self.module, name, wrapper);
+        }
+    }
+
+    /// Points each of the `split_i64_exports` at a new function taking each
+    /// `i64` parameter as two `i32` halves, low one first, and returning the
+    /// low half of an `i64` result, leaving the high one in the global
+    /// argument.
+    fn split_i64_exports(&mut self) {
+        if self.split_i64_exports.is_empty() {
+            return
+        }
+        let imported = self.module.import_count(ImportCountType::Function) as u32;
+        for name in self.split_i64_exports.iter() {
+            let idx = match exported_function(self.module, name) {
+                Some(i) if i >= imported => i,
+                _ => continue,
+            };
+            let ty = function_type(self.module, idx).clone();
+            let mut params = Vec::new();
+            let mut code = Vec::new();
+            for param in ty.params() {
+                let local = params.len() as u32;
+                if *param == ValueType::I64 {
+                    params.push(ValueType::I32);
+                    params.push(ValueType::I32);
+                    code.push(Opcode::GetLocal(local));
+                    code.push(Opcode::I64ExtendUI32);
+                    code.push(Opcode::GetLocal(local + 1));
+                    code.push(Opcode::I64ExtendUI32);
+                    code.push(Opcode::I64Const(32));
+                    code.push(Opcode::I64Shl);
+                    code.push(Opcode::I64Or);
+                } else {
+                    params.push(*param);
+                    code.push(Opcode::GetLocal(local));
+                }
+            }
+            code.push(Opcode::Call(idx));
+            let mut locals = Vec::new();
+            let mut ret = ty.return_type();
+            if ret == Some(ValueType::I64) {
+                let global_argument_ptr =
+                    exported_function(self.module, "__wbindgen_global_argument_ptr")
+                        .expect("the global argument is needed to return a split i64");
+                let tmp = params.len() as u32;
+                locals.push(Local::new(1, ValueType::I64));
+                code.push(Opcode::SetLocal(tmp));
+                code.push(Opcode::Call(global_argument_ptr));
+                code.push(Opcode::GetLocal(tmp));
+                code.push(Opcode::I64Const(32));
+                code.push(Opcode::I64ShrU);
+                code.push(Opcode::I32WrapI64);
+                code.push(Opcode::I32Store(2, 0));
+                code.push(Opcode::GetLocal(tmp));
+                code.push(Opcode::I32WrapI64);
+                ret = Some(ValueType::I32);
+            }
+            code.push(Opcode::End);
+
+            let wrapper = add_function(self.module, FunctionType::new(params, ret), locals, code);
+            repoint_export(self.module, name, wrapper);
+        }
+    }
+
+    /// Gives each of the `split_i64_imports` the type the glue implements
+    /// for it, taking each `i64` parameter as two `i32` halves like
+    /// `split_i64_exports`, and returning the low half of an `i64` result
+    /// with the high one in the global argument. Everything using an import
+    /// then uses a new function with its original type doing the splitting
+    /// instead.
+    fn split_i64_imports(&mut self) {
+        if self.split_i64_imports.is_empty() {
+            return
+        }
+        let imports = match self.module.import_section() {
+            Some(s) => s.entries().to_vec(),
+            None => return,
+        };
+        let mut idx = 0;
+        for import in imports.iter() {
+            let ty = match *import.external() {
+                External::Function(ty) => ty,
+                _ => continue,
+            };
+            idx += 1;
+            if !self.split_i64_imports.contains(import.field()) {
+                continue
+            }
+            let idx = idx - 1;
+            let ty = match self.module.type_section().unwrap().types()[ty as usize] {
+                Type::Function(ref f) => f.clone(),
+            };
+            let mut params = Vec::new();
+            let mut code = Vec::new();
+            for (local, param) in ty.params().iter().enumerate() {
+                let local = local as u32;
+                if *param == ValueType::I64 {
+                    params.push(ValueType::I32);
+                    params.push(ValueType::I32);
+                    code.push(Opcode::GetLocal(local));
+                    code.push(Opcode::I32WrapI64);
+                    code.push(Opcode::GetLocal(local));
+                    code.push(Opcode::I64Const(32));
+                    code.push(Opcode::I64ShrU);
+                    code.push(Opcode::I32WrapI64);
+                } else {
+                    params.push(*param);
+                    code.push(Opcode::GetLocal(local));
+                }
+            }
+            code.push(Opcode::Call(idx));
+            let mut ret = ty.return_type();
+            if ret == Some(ValueType::I64) {
+                let global_argument_ptr =
+                    exported_function(self.module, "__wbindgen_global_argument_ptr")
+                        .expect("the global argument is needed to return a split i64");
+                code.push(Opcode::I64ExtendUI32);
+                code.push(Opcode::Call(global_argument_ptr));
+                code.push(Opcode::I32Load(2, 0));
+                code.push(Opcode::I64ExtendUI32);
+                code.push(Opcode::I64Const(32));
+                code.push(Opcode::I64Shl);
+                code.push(Opcode::I64Or);
+                ret = Some(ValueType::I32);
+            }
+            code.push(Opcode::End);
+
+            // The uses are redirected before the new function is added, as
+            // it's the one use which stays.
+            let split = self.module.import_count(ImportCountType::Function) as u32 +
+                self.module.function_section().map(|s| s.entries().len() as u32).unwrap_or(0);
+            redirect_calls(self.module, idx, split);
+            let mut split_ty = 0;
+            for section in self.module.sections_mut() {
+                match *section {
+                    Section::Type(ref mut s) => {
+                        s.types_mut().push(Type::Function(FunctionType::new(params.clone(), ret)));
+                        split_ty = s.types().len() as u32 - 1;
+                    }
+                    Section::Import(ref mut s) => {
+                        let entry = s.entries_mut().iter_mut()
+                            .find(|e| e.field() == import.field())
+                            .unwrap();
+                        *entry.external_mut() = External::Function(split_ty);
+                    }
+                    _ => {}
+                }
+            }
+            add_function(self.module, ty, Vec::new(), code);
+        }
+    }
+
+    /// The types of the wrappers of `wrap_multi_value_exports` as of now.
+    fn multi_value_types(&self) -> Vec<u32> {
+        let imported = self.module.import_count(ImportCountType::Function) as u32;
+        let funcs = match self.module.function_section() {
+            Some(s) => s.entries(),
+            None => return Vec::new(),
+        };
+        let mut ret = Vec::new();
+        if let Some(s) = self.module.export_section() {
+            for e in s.entries() {
+                if !self.multi_value_exports.contains(e.field()) {
+                    continue
+                }
+                if let Internal::Function(i) = *e.internal() {
+                    if i >= imported {
+                        ret.push(funcs[(i - imported) as usize].type_ref());
+                    }
+                }
+            }
+        }
+        ret
     }
 
     fn export_table(&mut self) {
@@ -483,6 +1959,7 @@ impl<'a> Context<'a> {
                         -> Vec<(String, String)>
     {
         let mut math_imports = Vec::new();
+        let glue = self.config.glue_module(module_name);
         let imports = self.module.sections_mut()
             .iter_mut()
             .filter_map(|s| {
@@ -495,9 +1972,7 @@ impl<'a> Context<'a> {
 
         for import in imports {
             if import.module() == "__wbindgen_placeholder__" {
-                import.module_mut().truncate(0);
-                import.module_mut().push_str("./");
-                import.module_mut().push_str(module_name);
+                *import.module_mut() = glue.clone();
                 continue;
             }
 
@@ -552,15 +2027,60 @@ impl<'a> Context<'a> {
                 _ => continue,
             }
 
-            import.module_mut().truncate(0);
-            import.module_mut().push_str("./");
-            import.module_mut().push_str(module_name);
+            *import.module_mut() = glue.clone();
             *import.field_mut() = renamed_import.clone();
         }
 
         math_imports
     }
 
+    /// Removes the exports of all the descriptor shims, which are only used
+    /// while generating bindings.
+    fn unexport_descriptors(&mut self) {
+        for section in self.module.sections_mut() {
+            let exports = match *section {
+                Section::Export(ref mut s) => s,
+                _ => continue,
+            };
+            exports.entries_mut().retain(|export| {
+                !export.field().starts_with("__wbindgen_describe_")
+            });
+        }
+    }
+
+    /// Turns the wasm's start function, if it has one, into the
+    /// `__wbindgen_start` export for the glue to call, returning whether it
+    /// did. Otherwise it would run while the wasm is being instantiated, when
+    /// the glue isn't ready for calls into JS yet.
+    fn export_start(&mut self) -> bool {
+        let start = match self.module.start_section() {
+            Some(start) => start,
+            None => return false,
+        };
+        let export = ExportEntry::new("__wbindgen_start".to_string(), Internal::Function(start));
+        let has_exports = self.module.export_section().is_some();
+        let sections = self.module.sections_mut();
+        let mut pos = 0;
+        for (i, section) in sections.iter().enumerate() {
+            if let Section::Start(_) = *section {
+                pos = i;
+            }
+        }
+        if has_exports {
+            sections.remove(pos);
+            for section in sections.iter_mut() {
+                if let Section::Export(ref mut s) = *section {
+                    s.entries_mut().push(export.clone());
+                }
+            }
+        } else {
+            // The export section comes right before the start section.
+            sections[pos] = Section::Export(ExportSection::with_entries(vec![export]));
+        }
+        self.required_internal_exports.insert("__wbindgen_start");
+        true
+    }
+
     fn unexport_unused_internal_exports(&mut self) {
         let required = &self.required_internal_exports;
         for section in self.module.sections_mut() {
@@ -575,10 +2095,226 @@ impl<'a> Context<'a> {
         }
     }
 
+    /// Removes `__wbindgen_placeholder__` imports that nothing in the module
+    /// calls, so we don't generate JS for them either.
+    ///
+    /// The gc pass normally takes care of this, but this ensures the same
+    /// happens when it's disabled.
+    fn remove_unused_placeholder_imports(&mut self) -> Result<(), String> {
+        // Removing imports renumbers the functions called in the code.
+        if self.config.preserve_code_offsets {
+            return Ok(())
+        }
+        let mut used = BTreeSet::new();
+        for section in self.module.sections() {
+            match *section {
+                Section::Code(ref s) => {
+                    for body in s.bodies() {
+                        for op in body.code().elements() {
+                            if let Opcode::Call(i) = *op {
+                                used.insert(i);
+                            }
+                        }
+                    }
+                }
+                Section::Export(ref s) => {
+                    for e in s.entries() {
+                        if let Internal::Function(i) = *e.internal() {
+                            used.insert(i);
+                        }
+                    }
+                }
+                Section::Element(ref s) => {
+                    for seg in s.entries() {
+                        used.extend(seg.members().iter().cloned());
+                    }
+                }
+                Section::Start(i) => {
+                    used.insert(i);
+                }
+                _ => {}
+            }
+        }
+
+        // Figure out which imported functions to remove, and what every
+        // function's new index will be afterwards.
+        let mut new_indices = Vec::new();
+        let mut removed = 0;
+        if let Some(s) = self.module.import_section() {
+            for entry in s.entries() {
+                if let External::Function(_) = *entry.external() {
+                    let idx = new_indices.len() as u32;
+                    if entry.module() == "__wbindgen_placeholder__" && !used.contains(&idx) {
+                        new_indices.push(None);
+                        removed += 1;
+                    } else {
+                        new_indices.push(Some(idx - removed));
+                    }
+                }
+            }
+        }
+        if removed == 0 {
+            return Ok(())
+        }
+        let remap = |i: u32| -> Option<u32> {
+            match new_indices.get(i as usize) {
+                Some(&i) => i,
+                None => Some(i - removed),
+            }
+        };
+        // Anything else still using a removed import is reported afterwards.
+        let mut still_used = None;
+        let mut remap_used = |i: &mut u32| {
+            match remap(*i) {
+                Some(new) => *i = new,
+                None => still_used = Some(*i),
+            }
+        };
+
+        let module = mem::take(self.module);
+        // The name section refers to functions by index so it needs to be
+        // updated too, and if we can't parse it it'd just be wrong afterwards.
+        *self.module = match module.parse_names() {
+            Ok(m) => m,
+            Err((_, mut m)) => {
+                m.sections_mut().retain(|s| match *s {
+                    Section::Custom(ref s) => s.name() != "name",
+                    _ => true,
+                });
+                m
+            }
+        };
+        for section in self.module.sections_mut() {
+            match *section {
+                Section::Import(ref mut s) => {
+                    let mut idx = 0;
+                    s.entries_mut().retain(|entry| {
+                        if let External::Function(_) = *entry.external() {
+                            idx += 1;
+                            return remap(idx - 1).is_some()
+                        }
+                        true
+                    });
+                }
+                Section::Code(ref mut s) => {
+                    for body in s.bodies_mut() {
+                        for op in body.code_mut().elements_mut() {
+                            if let Opcode::Call(ref mut i) = *op {
+                                remap_used(i);
+                            }
+                        }
+                    }
+                }
+                Section::Export(ref mut s) => {
+                    for e in s.entries_mut() {
+                        if let Internal::Function(ref mut i) = *e.internal_mut() {
+                            remap_used(i);
+                        }
+                    }
+                }
+                Section::Element(ref mut s) => {
+                    for seg in s.entries_mut() {
+                        for i in seg.members_mut() {
+                            remap_used(i);
+                        }
+                    }
+                }
+                Section::Start(ref mut i) => remap_used(i),
+                Section::Name(NameSection::Function(ref mut f)) => {
+                    let names = mem::take(f.names_mut());
+                    *f.names_mut() = names.into_iter()
+                        .filter_map(|(i, name)| remap(i).map(|i| (i, name)))
+                        .collect();
+                }
+                Section::Name(NameSection::Local(ref mut l)) => {
+                    let names = mem::take(l.local_names_mut());
+                    *l.local_names_mut() = names.into_iter()
+                        .filter_map(|(i, names)| remap(i).map(|i| (i, names)))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        match still_used {
+            Some(i) => Err(format!("the unused import of function {} was removed but \
+                                    the wasm module still refers to it", i)),
+            None => Ok(()),
+        }
+    }
+
+    /// Every `__wbindgen_placeholder__` import left at this point is called
+    /// from wasm, so we must have generated JS for it, which we won't have
+    /// if the bindgen metadata doesn't describe it.
+    fn check_placeholder_imports_wired(&self) -> Result<(), String> {
+        let imports = match self.module.import_section() {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        for entry in imports.entries() {
+            if entry.module() != "__wbindgen_placeholder__" {
+                continue
+            }
+            if !self.exported_names.contains(entry.field()) {
+                return Err(format!("the wasm module uses the import `{}` but nothing \
+                                    describes it to generate JS for",
+                                   entry.field()))
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets the host see panics forwarded by `__wbindgen_panic` before they
+    /// surface as an exception, for example to report them.
+    fn expose_panic_handler(&mut self) {
+        if !self.exposed_globals.insert("panic_handler") {
+            return;
+        }
+        self.global("let panicHandler = null;");
+        self.export("setPanicHandler", "
+            function(handler) {
+                panicHandler = handler;
+            }
+        ");
+        self.typescript.push_str("\
+/**
+ * Calls `handler` with an `Error` for each Rust panic, before the call into
+ * wasm which panicked throws it.
+ */
+export function setPanicHandler(handler: ((error: Error) => void) | null): void;
+
+");
+    }
+
+    /// Warns on the console with `message` the first time it's called for
+    /// `name`, so deprecated APIs called in a loop don't flood it.
+    fn expose_warn_deprecated(&mut self) {
+        if !self.exposed_globals.insert("warn_deprecated") {
+            return;
+        }
+        self.global("
+            const warnedDeprecations = {};
+
+            function _warnDeprecated(name, message) {
+                if (warnedDeprecations[name] === true)
+                    return;
+                warnedDeprecations[name] = true;
+                console.warn(message);
+            }
+        ");
+    }
+
     fn expose_drop_ref(&mut self) {
         if !self.exposed_globals.insert("drop_ref") {
             return;
         }
+        if self.config.reference_types {
+            self.runtime_global("drop_ref", &format!("
+                function dropRef(idx) {{
+                    wasm.{}(idx);
+                }}
+            ", externref::DEALLOC));
+            return;
+        }
         self.expose_global_slab();
         self.expose_global_slab_next();
         let validate_owned = if self.config.debug {
@@ -596,6 +2332,7 @@ impl<'a> Context<'a> {
                 obj.cnt -= 1;
                 if (obj.cnt > 0)
                     return;
+                heap_live--;
             ")
         } else {
             String::from("
@@ -604,10 +2341,14 @@ impl<'a> Context<'a> {
                     return;
             ")
         };
-        self.global(&format!("
+        self.runtime_global("drop_ref", &format!("
             function dropRef(idx) {{
                 {}
 
+                // Reserved values are never freed.
+                if (idx < {})
+                    return;
+
                 let obj = slab[idx >> 1];
                 {}
 
@@ -615,38 +2356,85 @@ impl<'a> Context<'a> {
                 slab[idx >> 1] = slab_next;
                 slab_next = idx >> 1;
             }}
-        ", validate_owned, dec_ref));
+        ", validate_owned, JSIDX_RESERVED, dec_ref));
+    }
+
+    /// Puts the reserved values in their slots of the table of JS values,
+    /// see `Bindgen::reference_types`. `null` is the empty slot already.
+    fn expose_init_externrefs(&mut self) {
+        let slots = RESERVED_VALUES.iter()
+            .enumerate()
+            .filter(|&(_, v)| *v != "null")
+            .map(|(i, v)| format!("table.set({}, {});\n", i << 1, v))
+            .collect::<String>();
+        self.global(&format!("
+            function __wbg_init_externrefs() {{
+                const table = wasm.{};
+                {}
+            }}
+        ", externref::TABLE, slots));
     }
 
     fn expose_global_stack(&mut self) {
         if !self.exposed_globals.insert("stack") {
             return;
         }
-        self.global(&format!("
-            let stack = [];
-        "));
+        self.runtime_global("stack", &format!("
+            let stack = new Array({});
+            let stack_pointer = 0;
+        ", self.config.heap_stack_size));
     }
 
+    /// The slab holds owned JS values, and free entries hold the index of the
+    /// next free one. Its first entries hold the reserved values, see
+    /// `RESERVED_VALUES`, and it's allocated up front with
+    /// `Bindgen::initial_heap_size` free entries.
     fn expose_global_slab(&mut self) {
         if !self.exposed_globals.insert("slab") {
             return;
         }
-        self.global(&format!("let slab = [];"));
+        let reserved = RESERVED_VALUES.iter()
+            .map(|v| format!("{{ obj: {} }}", v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let start = RESERVED_VALUES.len();
+        let end = start + self.config.heap_slab_size;
+        let free = if end > start {
+            format!("\nfor (let i = {}; i < {}; i++)\n    slab.push(i + 1);", start, end)
+        } else {
+            String::new()
+        };
+        self.runtime_global("slab", &format!("let slab = [{}];{}", reserved, free));
+        if self.config.debug {
+            self.runtime_global("heap_live", "
+                // Owned entries of the slab which haven't been dropped yet,
+                // see `debugObjectStats`.
+                let heap_live = 0;
+            ");
+        }
     }
 
     fn expose_global_slab_next(&mut self) {
         if !self.exposed_globals.insert("slab_next") {
             return;
         }
-        self.global(&format!("
-            let slab_next = 0;
-        "));
+        self.runtime_global("slab_next", &format!("
+            let slab_next = {};
+        ", RESERVED_VALUES.len()));
     }
 
     fn expose_get_object(&mut self) {
         if !self.exposed_globals.insert("get_object") {
             return;
         }
+        if self.config.reference_types {
+            self.runtime_global("get_object", &format!("
+                function getObject(idx) {{
+                    return wasm.{}.get(idx);
+                }}
+            ", externref::TABLE));
+            return;
+        }
         self.expose_global_stack();
         self.expose_global_slab();
 
@@ -661,7 +2449,7 @@ impl<'a> Context<'a> {
                 return val.obj;
             ")
         };
-        self.global(&format!("
+        self.runtime_global("get_object", &format!("
             function getObject(idx) {{
                 if ((idx & 1) === 1) {{
                     return stack[idx >> 1];
@@ -673,30 +2461,216 @@ impl<'a> Context<'a> {
         ", get_obj));
     }
 
+    /// Emits the array of the JS values of the string enum `e`, which its
+    /// discriminants index, and returns its name.
+    fn expose_string_enum(&mut self, e: &StringEnum) -> String {
+        let name = format!("__wbg_{}_values", e.name);
+        if !self.string_enums.insert(e.name.clone()) {
+            return name;
+        }
+        let values = e.values.iter()
+            .map(|v| js_string(v))
+            .collect::<Vec<_>>();
+        self.global(&format!("const {} = [{}];\n", name, values.join(", ")));
+        name
+    }
+
+    /// Returns JS converting the JS string `value` to the discriminant of
+    /// the string enum `e`.
+    fn string_enum_index(&mut self, e: &StringEnum, value: &str, what: &str) -> String {
+        let values = self.expose_string_enum(e);
+        if !self.config.debug {
+            return format!("{}.indexOf({})", values, value);
+        }
+        if self.exposed_globals.insert("string_enum_index") {
+            self.global("
+                function _stringEnumIndex(values, s, what) {
+                    const idx = values.indexOf(s);
+                    if (idx === -1)
+                        throw new Error('expected one of ' +
+                            values.map(function(v) { return JSON.stringify(v); }).join(', ') +
+                            ', found ' + s + ' (' + what + ')');
+                    return idx;
+                }
+            ");
+        }
+        format!("_stringEnumIndex({}, {}, '{}')", values, value, what)
+    }
+
+    /// Emits the TypeScript interface of the dictionary `d`, and returns its
+    /// name.
+    fn expose_dictionary(&mut self, d: &Dictionary) -> Result<String, String> {
+        if !self.dictionaries.insert(d.name.clone()) {
+            return Ok(d.name.clone());
+        }
+        let mut ts = format!("export interface {} {{\n", d.name);
+        for field in d.fields.iter() {
+            let ty = self.dictionary_field_ty(&field.ty)?;
+            let optional = if field.optional { "?" } else { "" };
+            ts.push_str(&format!("    {}{}: {};\n", field.name, optional, ty));
+        }
+        ts.push_str("}\n\n");
+        self.typescript.push_str(&ts);
+        Ok(d.name.clone())
+    }
+
+    fn dictionary_field_ty(&mut self, ty: &Descriptor) -> Result<String, String> {
+        if let Some(kind) = ty.vector_kind() {
+            return Ok(kind.js_ty().to_string())
+        }
+        if let Some(e) = ty.string_enum() {
+            return Ok(e.js_ty())
+        }
+        if let Some(d) = ty.dictionary() {
+            return self.expose_dictionary(d)
+        }
+        if ty.is_number() {
+            return Ok("number".to_string())
+        }
+        match *ty {
+            Descriptor::Boolean => Ok("boolean".to_string()),
+            Descriptor::Anyref => Ok("any".to_string()),
+            _ => Err(format!("unsupported field type in dictionary: {:?}", ty)),
+        }
+    }
+
     fn expose_assert_num(&mut self) {
         if !self.exposed_globals.insert("assert_num") {
             return;
         }
         self.global(&format!("
-            function _assertNum(n) {{
+            function _assertNum(n, what) {{
                 if (typeof(n) !== 'number')
-                    throw new Error('expected a number argument');
+                    throw new Error('expected a number argument, found ' + typeof(n) + ' (' + what + ')');
             }}
         "));
     }
 
-    fn expose_assert_bool(&mut self) {
-        if !self.exposed_globals.insert("assert_bool") {
+    fn expose_assert_bigint(&mut self) {
+        if !self.exposed_globals.insert("assert_bigint") {
             return;
         }
-        self.global(&format!("
-            function _assertBoolean(n) {{
-                if (typeof(n) !== 'boolean')
-                    throw new Error('expected a boolean argument');
-            }}
-        "));
-    }
-
+        self.global("
+            function _assertBigInt(n, what) {
+                if (typeof(n) !== 'bigint')
+                    throw new Error('expected a bigint argument, found ' + typeof(n) + ' (' + what + ')');
+            }
+        ");
+    }
+
+    fn expose_assert_int(&mut self) {
+        if !self.exposed_globals.insert("assert_int") {
+            return;
+        }
+        self.expose_assert_num();
+        self.global(&format!("
+            function _assertInt(n, min, max, what) {{
+                _assertNum(n, what);
+                if (!Number.isInteger(n) || n < min || n > max)
+                    throw new Error('expected an integer argument in [' + min + ', ' + max + '], found ' +
+                                    n + ' (' + what + ')');
+            }}
+        "));
+    }
+
+    fn expose_assert_string(&mut self) {
+        if !self.exposed_globals.insert("assert_string") {
+            return;
+        }
+        self.global(&format!("
+            function _assertString(s, what) {{
+                if (typeof(s) !== 'string')
+                    throw new Error('expected a string argument, found ' + typeof(s) + ' (' + what + ')');
+            }}
+        "));
+    }
+
+    fn expose_assert_array(&mut self) {
+        if !self.exposed_globals.insert("assert_array") {
+            return;
+        }
+        self.global(&format!("
+            function _assertArray(arg, klass, what) {{
+                if (arg instanceof klass)
+                    return;
+                if (!Array.isArray(arg))
+                    throw new Error('expected a ' + klass.name + ' or Array argument (' + what + ')');
+                for (let i = 0; i < arg.length; i++) {{
+                    if (typeof(arg[i]) !== 'number')
+                        throw new Error('expected only numbers in the array, found ' +
+                                        typeof(arg[i]) + ' at index ' + i + ' (' + what + ')');
+                }}
+            }}
+        "));
+    }
+
+    /// Copies a view of wasm's memory into the array `arg`, which is either a
+    /// typed array or a plain array of numbers.
+    fn expose_copy_back_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("copy_back_from_wasm") {
+            return;
+        }
+        self.global("
+            function copyBackFromWasm(arg, view) {
+                if (typeof(arg.set) === 'function') {
+                    arg.set(view);
+                    return;
+                }
+                for (let i = 0; i < view.length; i++)
+                    arg[i] = view[i];
+            }
+        ");
+    }
+
+    /// Wraps views of wasm's memory in a proxy which throws once the view is
+    /// invalidated, either because wasm was called again (every shim bumps
+    /// `viewGeneration`) or because its memory grew. Engines without `Proxy`
+    /// get the plain view.
+    fn expose_view_of_wasm(&mut self) {
+        if !self.exposed_globals.insert("view_of_wasm") {
+            return;
+        }
+        self.global("
+            let viewGeneration = 0;
+
+            function viewOfWasm(view, what) {
+                if (typeof Proxy === 'undefined')
+                    return view;
+                const generation = viewGeneration;
+                const check = function() {
+                    if (generation !== viewGeneration || view.buffer !== wasm.memory.buffer)
+                        throw new Error('the view of wasm memory returned by ' + what +
+                                        ' was used after another call into wasm or after ' +
+                                        'its memory grew, copy it with `slice()` to keep it');
+                };
+                return new Proxy(view, {
+                    get: function(target, key) {
+                        check();
+                        const value = target[key];
+                        return typeof(value) === 'function' ? value.bind(target) : value;
+                    },
+                    set: function(target, key, value) {
+                        check();
+                        target[key] = value;
+                        return true;
+                    },
+                });
+            }
+        ");
+    }
+
+    fn expose_assert_bool(&mut self) {
+        if !self.exposed_globals.insert("assert_bool") {
+            return;
+        }
+        self.global(&format!("
+            function _assertBoolean(n, what) {{
+                if (typeof(n) !== 'boolean')
+                    throw new Error('expected a boolean argument, found ' + typeof(n) + ' (' + what + ')');
+            }}
+        "));
+    }
+
     fn expose_pass_string_to_wasm(&mut self) {
         if !self.exposed_globals.insert("pass_string_to_wasm") {
             return;
@@ -712,15 +2686,64 @@ impl<'a> Context<'a> {
         } else {
             ""
         };
+        if !self.string_interning() {
+            self.global(&format!("
+                function passStringToWasm(arg) {{
+                    {}
+                    const buf = cachedEncoder.encode(arg);
+                    const ptr = wasm.__wbindgen_malloc(buf.length);
+                    getUint8Memory().set(buf, ptr);
+                    return [ptr, buf.length];
+                }}
+            ", debug));
+            return
+        }
+
+        // Wasm takes ownership of what's passed, so only the encoding can be
+        // reused, and only short strings are worth keeping around.
         self.global(&format!("
+            const encodedStrings = Object.create(null);
+            const encodedKeys = [];
+
             function passStringToWasm(arg) {{
-                {}
-                const buf = cachedEncoder.encode(arg);
+                {debug}
+                let buf = arg.length <= {max_len} ? encodedStrings[arg] : undefined;
+                if (buf === undefined) {{
+                    buf = cachedEncoder.encode(arg);
+                    if (arg.length <= {max_len}) {{
+                        if (encodedKeys.length >= {capacity})
+                            delete encodedStrings[encodedKeys.shift()];
+                        encodedStrings[arg] = buf;
+                        encodedKeys.push(arg);
+                    }}
+                }}
                 const ptr = wasm.__wbindgen_malloc(buf.length);
                 getUint8Memory().set(buf, ptr);
                 return [ptr, buf.length];
             }}
-        ", debug));
+        ",
+            debug = debug,
+            max_len = ENCODED_STRING_MAX_LEN,
+            capacity = self.config.string_intern_capacity,
+        ));
+    }
+
+    /// Whether strings crossing between JS and wasm are cached.
+    fn string_interning(&self) -> bool {
+        self.config.string_interning && self.config.string_intern_capacity > 0
+    }
+
+    /// The JS strings decoded from the (ptr, len) pairs registered by
+    /// `wasm_bindgen::intern`, keyed by `ptr + ',' + len`, with their keys
+    /// oldest first.
+    fn expose_interned_strings(&mut self) {
+        if !self.exposed_globals.insert("interned_strings") {
+            return;
+        }
+        self.global("
+            const internedStrings = Object.create(null);
+            const internedKeys = [];
+        ");
     }
 
     fn expose_pass_array8_to_wasm(&mut self) {
@@ -731,11 +2754,12 @@ impl<'a> Context<'a> {
         self.expose_uint8_memory();
         self.global(&format!("
             function passArray8ToWasm(arg) {{
+                {from}
                 const ptr = wasm.__wbindgen_malloc(arg.length);
                 getUint8Memory().set(arg, ptr);
                 return [ptr, arg.length];
             }}
-        "));
+        ", from = self.typed_array_from("arg", "Uint8Array")));
     }
 
     fn expose_pass_array16_to_wasm(&mut self) {
@@ -746,11 +2770,12 @@ impl<'a> Context<'a> {
         self.expose_uint16_memory();
         self.global(&format!("
             function passArray16ToWasm(arg) {{
+                {from}
                 const ptr = wasm.__wbindgen_malloc(arg.length * 2);
                 getUint16Memory().set(arg, ptr / 2);
                 return [ptr, arg.length];
             }}
-        "));
+        ", from = self.typed_array_from("arg", "Uint16Array")));
     }
 
     fn expose_pass_array32_to_wasm(&mut self) {
@@ -761,11 +2786,12 @@ impl<'a> Context<'a> {
         self.expose_uint32_memory();
         self.global(&format!("
             function passArray32ToWasm(arg) {{
+                {from}
                 const ptr = wasm.__wbindgen_malloc(arg.length * 4);
                 getUint32Memory().set(arg, ptr / 4);
                 return [ptr, arg.length];
             }}
-        "));
+        ", from = self.typed_array_from("arg", "Uint32Array")));
     }
 
     fn expose_pass_array_f32_to_wasm(&mut self) {
@@ -773,13 +2799,15 @@ impl<'a> Context<'a> {
             return;
         }
         self.require_internal_export("__wbindgen_malloc");
+        self.expose_float32_memory();
         self.global(&format!("
             function passArrayF32ToWasm(arg) {{
+                {from}
                 const ptr = wasm.__wbindgen_malloc(arg.length * 4);
-                new Float32Array(wasm.memory.buffer).set(arg, ptr / 4);
+                getFloat32Memory().set(arg, ptr / 4);
                 return [ptr, arg.length];
             }}
-        "));
+        ", from = self.typed_array_from("arg", "Float32Array")));
     }
 
     fn expose_pass_array_f64_to_wasm(&mut self) {
@@ -787,13 +2815,36 @@ impl<'a> Context<'a> {
             return;
         }
         self.require_internal_export("__wbindgen_malloc");
+        self.expose_float64_memory();
         self.global(&format!("
             function passArrayF64ToWasm(arg) {{
+                {from}
                 const ptr = wasm.__wbindgen_malloc(arg.length * 8);
-                new Float64Array(wasm.memory.buffer).set(arg, ptr / 8);
+                getFloat64Memory().set(arg, ptr / 8);
                 return [ptr, arg.length];
             }}
-        "));
+        ", from = self.typed_array_from("arg", "Float64Array")));
+    }
+
+    /// Converts `arg` from a plain `Array` to a `klass` typed array, for the
+    /// helpers passing arrays of numbers to wasm.
+    fn typed_array_from(&self, arg: &str, klass: &str) -> String {
+        let from = if self.es5() {
+            format!("
+                if (Array.isArray({arg})) {{
+                    var copy = new {klass}({arg}.length);
+                    for (var i = 0; i < {arg}.length; i++)
+                        copy[i] = {arg}[i];
+                    {arg} = copy;
+                }}
+            ", arg = arg, klass = klass)
+        } else {
+            format!("
+                if (Array.isArray({arg}))
+                    {arg} = {klass}.from({arg});
+            ", arg = arg, klass = klass)
+        };
+        from.trim().to_string()
     }
 
     fn expose_text_encoder(&mut self) {
@@ -804,7 +2855,7 @@ impl<'a> Context<'a> {
             self.global(&format!("
                 const TextEncoder = require('util').TextEncoder;
             "));
-        } else if !(self.config.browser || self.config.no_modules) {
+        } else if !(self.config.browser || self.config.no_modules || self.config.awaits_wasm()) {
             self.global(&format!("
                 const TextEncoder = typeof self === 'object' && self.TextEncoder
                     ? self.TextEncoder
@@ -824,7 +2875,7 @@ impl<'a> Context<'a> {
             self.global(&format!("
                 const TextDecoder = require('util').TextDecoder;
             "));
-        } else if !(self.config.browser || self.config.no_modules) {
+        } else if !(self.config.browser || self.config.no_modules || self.config.awaits_wasm()) {
             self.global(&format!("
                 const TextDecoder = typeof self === 'object' && self.TextDecoder
                     ? self.TextDecoder
@@ -836,18 +2887,91 @@ impl<'a> Context<'a> {
         "));
     }
 
+    /// Defines the `FinalizationRegistry` freeing the instances of `class`
+    /// which are collected while they still own their pointer, or one doing
+    /// nothing where the engine doesn't have them.
+    fn expose_finalization(&mut self, class: &str) {
+        let reclaimed = if self.config.debug {
+            self.expose_instance_counts();
+            "instance_counts.reclaimed++;\n"
+        } else {
+            ""
+        };
+        self.global(&format!("
+            const {}Finalization = typeof(FinalizationRegistry) === 'undefined'
+                ? {{ register: function() {{}}, unregister: function() {{}} }}
+                : new FinalizationRegistry(function(ptr) {{
+                    {}wasm.{}(ptr);
+                }});
+        ", class, reclaimed, shared::free_function(class)));
+    }
+
+    /// Defines `__wbg_check_wasm()`, which throws if the instance of the wasm
+    /// isn't of the build `id`. In debug mode it also checks the instance has
+    /// all the internal exports the glue uses, naming any missing, so it must
+    /// be defined once nothing else adds to them.
+    fn expose_check_wasm(&mut self, id: &str) {
+        self.require_internal_export("__wbindgen_build_id");
+        let check_exports = if self.config.debug {
+            let mut required = self.required_internal_exports.iter()
+                .map(|name| js_string(name))
+                .collect::<Vec<_>>();
+            if self.function_table_needed {
+                required.push(js_string("__wbg_function_table"));
+            }
+            format!("
+                const missing = [{}].filter(function(name) {{
+                    return !(name in wasm);
+                }});
+                if (missing.length > 0)
+                    throw new Error('wasm-bindgen: the wasm module is missing exports ' +
+                                    'the JS glue uses: ' + missing.join(', '));
+            ", required.join(", "))
+        } else {
+            String::new()
+        };
+        self.global(&format!("
+            function __wbg_check_wasm() {{
+                const id = typeof(wasm.__wbindgen_build_id) === 'function'
+                    ? ('0000000' + (wasm.__wbindgen_build_id() >>> 0).toString(16)).slice(-8)
+                    : 'none';
+                if (id !== '{id}')
+                    throw new Error('wasm-bindgen: JS glue and wasm module are from ' +
+                                    'different builds (js={id}, wasm=' + id + ')');
+                {check_exports}
+            }}
+        ", id = id, check_exports = check_exports));
+    }
+
+    /// How many instances of exported classes were freed by the garbage
+    /// collector and by `free`, for `debugObjectStats`.
+    fn expose_instance_counts(&mut self) {
+        if !self.exposed_globals.insert("instance_counts") {
+            return;
+        }
+        self.global("const instance_counts = { reclaimed: 0, freed: 0 };");
+    }
+
     fn expose_constructor_token(&mut self) {
         if !self.exposed_globals.insert("ConstructorToken") {
             return;
         }
 
-        self.global("
-            class ConstructorToken {
-                constructor(ptr) {
+        if self.es5() {
+            self.global("
+                function ConstructorToken(ptr) {
                     this.ptr = ptr;
                 }
-            }
-        ");
+            ");
+        } else {
+            self.global("
+                class ConstructorToken {
+                    constructor(ptr) {
+                        this.ptr = ptr;
+                    }
+                }
+            ");
+        }
     }
 
     fn expose_get_string_from_wasm(&mut self) {
@@ -856,8 +2980,22 @@ impl<'a> Context<'a> {
         }
         self.expose_text_decoder();
         self.expose_uint8_memory();
+        if !self.string_interning() {
+            self.global(&format!("
+                function getStringFromWasm(ptr, len) {{
+                    return cachedDecoder.decode(getUint8Memory().slice(ptr, ptr + len));
+                }}
+            "));
+            return
+        }
+        self.expose_interned_strings();
         self.global(&format!("
             function getStringFromWasm(ptr, len) {{
+                if (internedKeys.length !== 0) {{
+                    const interned = internedStrings[ptr + ',' + len];
+                    if (interned !== undefined)
+                        return interned;
+                }}
                 return cachedDecoder.decode(getUint8Memory().slice(ptr, ptr + len));
             }}
         "));
@@ -874,14 +3012,74 @@ impl<'a> Context<'a> {
                 const mem = getUint32Memory();
                 const slice = mem.slice(ptr / 4, ptr / 4 + len);
                 const result = [];
-                for (ptr in slice) {{
-                    result.push(getObject(ptr))
+                for (let i = 0; i < slice.length; i++) {{
+                    result.push(getObject(slice[i]));
                 }}
                 return result;
             }}
         "));
     }
 
+    fn expose_take_array_js_value_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_array_js_value_from_wasm") {
+            return;
+        }
+        self.expose_uint32_memory();
+        self.expose_take_object();
+        self.global("
+            function takeArrayJsValueFromWasm(ptr, len) {
+                const mem = getUint32Memory();
+                const slice = mem.slice(ptr / 4, ptr / 4 + len);
+                const result = [];
+                for (let i = 0; i < slice.length; i++) {
+                    result.push(takeObject(slice[i]));
+                }
+                return result;
+            }
+        ");
+    }
+
+    fn expose_pass_array_js_value_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_array_js_value_to_wasm") {
+            return;
+        }
+        self.require_internal_export("__wbindgen_malloc");
+        self.expose_uint32_memory();
+        self.expose_add_heap_object();
+        self.global("
+            function passArrayJsValueToWasm(array) {
+                const ptr = wasm.__wbindgen_malloc(array.length * 4);
+                const mem = getUint32Memory();
+                for (let i = 0; i < array.length; i++) {
+                    mem[ptr / 4 + i] = addHeapObject(array[i]);
+                }
+                return [ptr, array.length];
+            }
+        ");
+    }
+
+    /// Like `passArrayJsValueToWasm`, but for a slice which is only
+    /// borrowed: the values are pushed on the stack rather than the heap, to
+    /// be popped once the call returns.
+    fn expose_pass_borrowed_array_js_value_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_borrowed_array_js_value_to_wasm") {
+            return;
+        }
+        self.require_internal_export("__wbindgen_malloc");
+        self.expose_uint32_memory();
+        self.expose_borrowed_objects();
+        self.global("
+            function passBorrowedArrayJsValueToWasm(array) {
+                const ptr = wasm.__wbindgen_malloc(array.length * 4);
+                const mem = getUint32Memory();
+                for (let i = 0; i < array.length; i++) {
+                    mem[ptr / 4 + i] = addBorrowedObject(array[i]);
+                }
+                return [ptr, array.length];
+            }
+        ");
+    }
+
     fn expose_get_array_i8_from_wasm(&mut self) {
         self.expose_uint8_memory();
         if !self.exposed_globals.insert("get_array_i8_from_wasm") {
@@ -970,9 +3168,10 @@ impl<'a> Context<'a> {
         if !self.exposed_globals.insert("get_array_f32_from_wasm") {
             return;
         }
+        self.expose_float32_memory();
         self.global(&format!("
             function getArrayF32FromWasm(ptr, len) {{
-                const mem = new Float32Array(wasm.memory.buffer);
+                const mem = getFloat32Memory();
                 const slice = mem.slice(ptr / 4,  ptr / 4 + len);
                 return new Float32Array(slice);
             }}
@@ -983,9 +3182,10 @@ impl<'a> Context<'a> {
         if !self.exposed_globals.insert("get_array_f64_from_wasm") {
             return;
         }
+        self.expose_float64_memory();
         self.global(&format!("
             function getArrayF64FromWasm(ptr, len) {{
-                const mem = new Float64Array(wasm.memory.buffer);
+                const mem = getFloat64Memory();
                 const slice = mem.slice(ptr / 8,  ptr / 8 + len);
                 return new Float64Array(slice);
             }}
@@ -1037,14 +3237,44 @@ impl<'a> Context<'a> {
         "));
     }
 
+    fn expose_float32_memory(&mut self) {
+        if !self.exposed_globals.insert("float32_memory") {
+            return;
+        }
+        self.global(&format!("
+            let cachedFloat32Memory = null;
+            function getFloat32Memory() {{
+                if (cachedFloat32Memory === null ||
+                    cachedFloat32Memory.buffer !== wasm.memory.buffer)
+                    cachedFloat32Memory = new Float32Array(wasm.memory.buffer);
+                return cachedFloat32Memory;
+            }}
+        "));
+    }
+
+    fn expose_float64_memory(&mut self) {
+        if !self.exposed_globals.insert("float64_memory") {
+            return;
+        }
+        self.global(&format!("
+            let cachedFloat64Memory = null;
+            function getFloat64Memory() {{
+                if (cachedFloat64Memory === null ||
+                    cachedFloat64Memory.buffer !== wasm.memory.buffer)
+                    cachedFloat64Memory = new Float64Array(wasm.memory.buffer);
+                return cachedFloat64Memory;
+            }}
+        "));
+    }
+
     fn expose_assert_class(&mut self) {
         if !self.exposed_globals.insert("assert_class") {
             return;
         }
         self.global(&format!("
-            function _assertClass(instance, klass) {{
+            function _assertClass(instance, klass, what) {{
                 if (!(instance instanceof klass))
-                    throw new Error(`expected instance of ${{klass.name}}`);
+                    throw new Error('expected instance of ' + klass.name + ' (' + what + ')');
                 return instance.ptr;
             }}
         "));
@@ -1055,12 +3285,36 @@ impl<'a> Context<'a> {
             return;
         }
         self.expose_global_stack();
-        self.global(&format!("
-            function addBorrowedObject(obj) {{
-                stack.push(obj);
-                return ((stack.length - 1) << 1) | 1;
-            }}
-        "));
+        // With reference types wasm can only find JS values in its table, so
+        // the stack holds the slots of those lent to it.
+        if self.config.reference_types {
+            self.expose_add_heap_object();
+            self.expose_drop_ref();
+            self.runtime_global("borrowed_objects", "
+                function addBorrowedObject(obj) {
+                    const idx = addHeapObject(obj);
+                    stack[stack_pointer++] = idx;
+                    return idx;
+                }
+
+                function dropBorrowedObjects(sp) {
+                    while (stack_pointer > sp)
+                        dropRef(stack[--stack_pointer]);
+                }
+            ");
+            return;
+        }
+        self.runtime_global("borrowed_objects", "
+            function addBorrowedObject(obj) {
+                stack[stack_pointer] = obj;
+                return (stack_pointer++ << 1) | 1;
+            }
+
+            function dropBorrowedObjects(sp) {
+                while (stack_pointer > sp)
+                    stack[--stack_pointer] = undefined;
+            }
+        ");
     }
 
     fn expose_take_object(&mut self) {
@@ -1069,43 +3323,66 @@ impl<'a> Context<'a> {
         }
         self.expose_get_object();
         self.expose_drop_ref();
-        self.global(&format!("
-            function takeObject(idx) {{
+        self.runtime_global("take_object", "
+            function takeObject(idx) {
                 const ret = getObject(idx);
                 dropRef(idx);
                 return ret;
-            }}
-        "));
+            }
+        ");
     }
 
     fn expose_add_heap_object(&mut self) {
         if !self.exposed_globals.insert("add_heap_object") {
             return;
         }
+        if self.config.reference_types {
+            self.runtime_global("add_heap_object", &format!("
+                function addHeapObject(obj) {{
+                    return wasm.{}(obj);
+                }}
+            ", externref::ALLOC));
+            return;
+        }
         self.expose_global_slab();
         self.expose_global_slab_next();
         let set_slab_next = if self.config.debug {
-            String::from("
+            let warn = match self.config.heap_warning_threshold {
+                0 => String::new(),
+                n => format!("
+                    if (heap_live === {n})
+                        console.warn('wasm holds on to {n} JS values, which may be a leak, ' +
+                                     'see `debugObjectStats()`');
+                ", n = n),
+            };
+            format!("
                 if (typeof(next) !== 'number')
                     throw new Error('corrupt slab');
                 slab_next = next;
-            ")
+                heap_live++;
+                {}
+            ", warn)
         } else {
             String::from("
                 slab_next = next;
             ")
         };
-        self.global(&format!("
+        let reserved = RESERVED_VALUES.iter()
+            .enumerate()
+            .map(|(i, v)| format!("if (obj === {})\n    return {};\n", v, i << 1))
+            .collect::<String>();
+        self.runtime_global("add_heap_object", &format!("
             function addHeapObject(obj) {{
+                {}
                 if (slab_next === slab.length)
                     slab.push(slab.length + 1);
                 const idx = slab_next;
                 const next = slab[idx];
                 {}
-                slab[idx] = {{ obj, cnt: 1 }};
+                slab[idx] = {{ obj: obj, cnt: 1 }};
                 return idx << 1;
             }}
-        ", set_slab_next));
+        ", reserved, set_slab_next));
     }
 
     fn wasm_import_needed(&self, name: &str) -> bool {
@@ -1149,8 +3426,21 @@ impl<'a> Context<'a> {
                 "passArrayF64ToWasm"
             }
             VectorKind::Anyref => {
-                panic!("cannot pass list of JsValue to wasm yet")
+                self.expose_pass_array_js_value_to_wasm();
+                "passArrayJsValueToWasm"
+            }
+        }
+    }
+
+    /// Like `expose_get_vector_from_wasm`, but for a vector which JS takes
+    /// ownership of, so JS values are removed from the heap as they're read.
+    fn expose_take_vector_from_wasm(&mut self, ty: VectorKind) -> &'static str {
+        match ty {
+            VectorKind::Anyref => {
+                self.expose_take_array_js_value_from_wasm();
+                "takeArrayJsValueFromWasm"
             }
+            _ => self.expose_get_vector_from_wasm(ty),
         }
     }
 
@@ -1213,6 +3503,60 @@ impl<'a> Context<'a> {
         ");
     }
 
+    /// Wraps the Rust function pointer `ptr` in a JS function calling it
+    /// through `shim`, which converts to and from the signature `f` (both
+    /// are indices into the function table). Returns the JS expression
+    /// looking the wrapper up and its TypeScript type.
+    pub fn fn_pointer(&mut self, f: &Function, shim: &str, ptr: &str)
+        -> Result<(String, String), String>
+    {
+        let signature = f.rust_signature("fn");
+        let (js, ts) = {
+            let mut builder = Js2Rust::new(&signature, self);
+            builder.through_table().rust_argument("p").process(f)?;
+            (builder.finish("function", "f").0, closure_ty(&builder))
+        };
+        self.expose_get_fn_pointer();
+        self.function_table_needed = true;
+        let js = format!("\
+            getFnPointer({}, {}, {}, function(f, p) {{\n\
+                return {};\n\
+            }})\
+        ", shim, ptr, js_string(&signature), js);
+        Ok((js, ts))
+    }
+
+    /// Defines `getFnPointer`, which creates the wrapper of a function
+    /// pointer with `wrap` the first time it's seen. JS code often tells
+    /// callbacks apart by identity (like to unregister them), so the same
+    /// pointer always gets the same wrapper.
+    fn expose_get_fn_pointer(&mut self) {
+        if !self.exposed_globals.insert("get_fn_pointer") {
+            return;
+        }
+        self.global("
+            const fnPointers = {};
+            function getFnPointer(shim, ptr, signature, wrap) {
+                const key = shim + ':' + ptr;
+                if (!fnPointers.hasOwnProperty(key)) {
+                    const f = wrap(wasm.__wbg_function_table.get(shim), ptr);
+                    fnPointers[key] = function() {
+                        try {
+                            return f.apply(this, arguments);
+                        } catch (e) {
+                            if (e instanceof WebAssembly.RuntimeError && /signature/.test(e.message))
+                                throw new Error('the function pointer at table index ' + ptr +
+                                                ' was called as `' + signature + '`, which ' +
+                                                'isn\\'t its signature in wasm (' + e.message + ')');
+                            throw e;
+                        }
+                    };
+                }
+                return fnPointers[key];
+            }
+        ");
+    }
+
     fn expose_get_global_argument(&mut self) {
         if !self.exposed_globals.insert("get_global_argument") {
             return;
@@ -1268,19 +3612,77 @@ impl<'a> Context<'a> {
     }
 
     fn gc(&mut self) {
+        if !self.config.run_gc || self.config.preserve_code_offsets {
+            return
+        }
+        ::gc::run(self.module, &mut self.gc_stats);
+    }
+
+    /// When keeping the `name` section, demangles the function names in it.
+    ///
+    /// The gc pass already does this, but it may not run. Names which aren't
+    /// Rust symbols are left as is, and so is a `name` section which fails
+    /// to parse.
+    fn demangle_name_section(&mut self) {
+        if !self.config.demangle || !self.config.keeps_debug() || self.config.remove_name_section {
+            return
+        }
         let module = mem::replace(self.module, Module::default());
-        let wasm_bytes = parity_wasm::serialize(module).unwrap();
-        let bytes = wasm_gc::Config::new()
-            .demangle(self.config.demangle)
-            .gc(&wasm_bytes)
-            .unwrap();
-        *self.module = deserialize_buffer(&bytes).unwrap();
+        *self.module = match module.parse_names() {
+            Ok(module) => module,
+            Err((_, module)) => module,
+        };
+        for section in self.module.sections_mut() {
+            let names = match *section {
+                Section::Name(NameSection::Function(ref mut f)) => f.names_mut(),
+                _ => continue,
+            };
+            let mut demangled = IndexMap::with_capacity(names.len());
+            for (idx, name) in names.iter() {
+                demangled.insert(idx, rustc_demangle::demangle(name).to_string());
+            }
+            *names = demangled;
+        }
+    }
+
+    /// Unless we're keeping debug information, removes the `name` section,
+    /// any DWARF `.debug_*` custom sections and the `sourceMappingURL`
+    /// section from the output module. The `name` and `producers` sections
+    /// are also removed when asked to, see `Bindgen::remove_name_section`.
+    fn strip_debug_sections(&mut self) {
+        let keep_debug = self.config.keeps_debug();
+        let keep_names = keep_debug && !self.config.remove_name_section;
+        let keep_producers = !self.config.remove_producers_section;
+        self.module.sections_mut().retain(|s| {
+            match *s {
+                Section::Custom(ref s) => match s.name() {
+                    "name" => keep_names,
+                    "producers" => keep_producers,
+                    name => keep_debug || !::is_debug_section(name),
+                },
+                Section::Name(_) => keep_names,
+                _ => true,
+            }
+        });
     }
 
-    fn describe(&self, name: &str) -> Descriptor {
+    fn describe(&self, name: &str) -> Result<Descriptor, String> {
         let name = format!("__wbindgen_describe_{}", name);
-        let ret = (self.run_descriptor)(&name);
-        Descriptor::decode(&ret)
+        match self.descriptors.get(&name) {
+            Some(d) => {
+                Descriptor::decode(d).map_err(|e| {
+                    format!("failed to decode the descriptor of `{}`: {} (descriptor: \
+                             {:?}), this usually means that the wasm file was compiled \
+                             with a different version of the `wasm-bindgen` crate than \
+                             this binary", &name["__wbindgen_describe_".len()..], e, d)
+                })
+            }
+            None => {
+                Err(format!("failed to find the descriptor export `{}`, this usually \
+                             means that the wasm file was compiled with a different \
+                             version of the `wasm-bindgen` crate than this binary", name))
+            }
+        }
     }
 
     fn global(&mut self, s: &str) {
@@ -1295,59 +3697,278 @@ impl<'a> Context<'a> {
         self.globals.push_str(s);
         self.globals.push_str("\n");
     }
+
+    /// Defines the part `key` of the heap and stack management, which is
+    /// generated into the runtime module rather than the glue with
+    /// `Bindgen::shared_runtime`.
+    fn runtime_global(&mut self, key: &'static str, s: &str) {
+        if self.config.shares_runtime() {
+            self.runtime.push((key, deindent(s).trim().to_string()));
+        } else {
+            self.global(s);
+        }
+    }
 }
 
 impl<'a, 'b> SubContext<'a, 'b> {
-    pub fn generate(&mut self) {
+    /// Generates bindings for everything in the program.
+    ///
+    /// Rather than stopping at the first export or import that bindings
+    /// can't be generated for this records a problem in `errors` for each of
+    /// them, naming the crate it came from, and carries on with the rest.
+    pub fn generate(&mut self, errors: &mut Vec<String>) {
+        let krate = self.program.crate_name.clone();
+        let mut record = |item: String, result: Result<(), String>| {
+            if let Err(e) = result {
+                errors.push(format!("{} in crate `{}`: {}", item, krate, e));
+            }
+        };
+        let used = metadata::Crate { name: krate.clone(), version: self.program.version.clone() };
+        if !self.cx.metadata.crates.contains(&used) {
+            self.cx.metadata.crates.push(used);
+        }
+
+        // Structs come first as how their instances are passed depends on
+        // whether they extend a JS class.
+        for s in self.program.structs.iter() {
+            let result = self.generate_struct(s);
+            record(format!("struct `{}`", s.name), result);
+        }
         for f in self.program.exports.iter() {
-            self.generate_export(f);
+            let item = match f.class {
+                Some(ref class) => format!("export `{}::{}`", class, f.function.name),
+                None => format!("export `{}`", f.function.name),
+            };
+            let result = self.generate_export(f);
+            record(item, result);
         }
         for f in self.program.imports.iter() {
-            self.generate_import(f);
+            let item = match f.kind {
+                shared::ImportKind::Function(ref f) => {
+                    format!("import `{}`", f.function.name)
+                }
+                shared::ImportKind::Static(ref s) => format!("import `{}`", s.name),
+                // Nothing is generated for these.
+                shared::ImportKind::Type(_) |
+                shared::ImportKind::Enum(_) => continue,
+            };
+            let result = self.generate_import(f);
+            record(item, result);
         }
         for e in self.program.enums.iter() {
-            self.generate_enum(e);
+            let result = self.generate_enum(e);
+            record(format!("enum `{}`", e.name), result);
         }
-        for s in self.program.structs.iter() {
-            self.cx.exported_classes
-                .entry(s.name.clone())
-                .or_insert_with(Default::default)
-                .fields
-                .extend(s.fields.iter().map(|s| {
-                    ClassField {
-                        name: s.name.clone(),
-                        readonly: s.readonly,
-                    }
-                }));
+        for c in self.program.consts.iter() {
+            let result = self.generate_const(c);
+            record(format!("const `{}`", c.name), result);
         }
     }
 
-    pub fn generate_export(&mut self, export: &shared::Export) {
-        if let Some(ref class) = export.class {
-            return self.generate_export_for_class(class, export);
+    fn generate_struct(&mut self, s: &shared::Struct) -> Result<(), String> {
+        let superclass = match s.superclass {
+            Some(ref base) => Some(self.generate_superclass(s, base)?),
+            None => None,
+        };
+        let class = self.cx.exported_classes
+            .entry(s.name.clone())
+            .or_default();
+        class.fields.extend(s.fields.iter().map(|s| {
+            ClassField {
+                name: s.name.clone(),
+                readonly: s.readonly,
+            }
+        }));
+        class.superclass = superclass;
+        class.deprecated = s.deprecated.clone();
+        self.cx.metadata.class(&s.name).deprecated = s.deprecated.clone();
+        Ok(())
+    }
+
+    /// Imports the JS class `base` which the class of `s` extends, returning
+    /// what `ExportedClass::superclass` records of it.
+    fn generate_superclass(&mut self, s: &shared::Struct, base: &shared::Superclass)
+        -> Result<(String, bool, String), String>
+    {
+        if self.cx.es5() {
+            return Err(format!("the struct `{}` extends the JS class `{}`, which isn't \
+                                supported when targeting ES5", s.name, base.name));
         }
-        let descriptor = self.cx.describe(&export.function.name);
-        let (js, ts) = Js2Rust::new(&export.function.name, self.cx)
-            .process(descriptor.unwrap_function())
-            .finish("function", &format!("wasm.{}", export.function.name));
-        self.cx.export(&export.function.name, &js);
-        self.cx.globals.push_str("\n");
+        if let Some(module) = &base.module {
+            if self.cx.config.no_modules {
+                return Err(format!("the struct `{}` extends `{}` from the module `{}`, \
+                                    which can't be imported with `--no-modules`; use a \
+                                    global class instead or another target",
+                                   s.name, base.name, module));
+            }
+        }
+        let import = shared::Import {
+            module: base.module.clone(),
+            js_namespace: base.js_namespace.clone(),
+            kind: shared::ImportKind::Type(shared::ImportType {}),
+        };
+        let expr = self.import_name(&import, &base.name);
+        self.imported_shape(&import, slice::from_ref(&base.name), true)
+            .signatures
+            .insert("new (...args: any[]): any".to_string());
+        let ts_import = match import.module {
+            Some(ref module) => {
+                let mut module = module.clone();
+                if !self.cx.config.local_module_roots.is_empty() {
+                    if let Some(path) = local_module_path(&module) {
+                        module = path;
+                    }
+                }
+                let name = import.js_namespace.as_ref().unwrap_or(&base.name);
+                let local = js_identifier(name);
+                if local == *name {
+                    format!("import {{ {} }} from '{}';\n", name, module)
+                } else {
+                    format!("import {{ {} as {} }} from '{}';\n", name, local, module)
+                }
+            }
+            None => String::new(),
+        };
+        self.cx.extended_classes.insert(s.name.clone());
+        Ok((expr, base.forward_args, ts_import))
+    }
+
+    /// Describes where items of this program come from, for error messages.
+    fn origin(&self) -> String {
+        if self.program.crate_name.is_empty() {
+            String::new()
+        } else {
+            format!(" (from crate `{}`)", self.program.crate_name)
+        }
+    }
+
+    pub fn generate_export(&mut self, export: &shared::Export) -> Result<(), String> {
+        if let Some(ref class) = export.class {
+            return self.generate_export_for_class(class, export);
+        }
+        let js_name = js_identifier(&export.function.name);
+        let what = format!("function `{}`{}", export.function.name, self.origin());
+        self.cx.define_js_name(&js_name, what)?;
+        let descriptor = self.cx.describe(&export.function.name)?;
+        let (arguments, returns) = descriptor.unwrap_function().metadata_tys();
+        self.cx.metadata.functions.push(metadata::Function {
+            name: js_name.clone(),
+            arguments,
+            returns,
+            is_async: export.is_async,
+            deprecated: export.deprecated.clone(),
+        });
+        let mut cx = Js2Rust::new(&js_name, self.cx);
+        if let Some(ref message) = export.deprecated {
+            cx.deprecated(&js_name, message);
+        }
+        let (js, ts) = cx
+            .returns_promise(export.is_async)
+            .process(descriptor.unwrap_function())?
+            .finish("function", &format!("wasm.{}", export.function.name));
+        let returns_view = cx.returns_view;
+        let doc = export.deprecated.as_ref().map(|m| deprecated_doc(m)).unwrap_or_default();
+        self.cx.export_with_doc(&js_name, &js, &doc);
+        self.cx.globals.push_str("\n");
+        if returns_view {
+            self.cx.typescript.push_str(VIEW_DOC);
+        }
+        self.cx.typescript.push_str(&doc);
         self.cx.typescript.push_str("export ");
         self.cx.typescript.push_str(&ts);
         self.cx.typescript.push_str("\n");
+        Ok(())
     }
 
-    pub fn generate_export_for_class(&mut self, class_name: &str, export: &shared::Export) {
+    pub fn generate_export_for_class(&mut self, class_name: &str, export: &shared::Export)
+        -> Result<(), String>
+    {
+        let js_name = if export.method {
+            format!("{}.prototype.{}", class_name, export.function.name)
+        } else {
+            format!("{}.{}", class_name, export.function.name)
+        };
+        let what = format!("method `{}::{}`{}",
+                           class_name, export.function.name, self.origin());
+        self.cx.define_js_name(&js_name, what.clone())?;
         let wasm_name = shared::struct_function_export_name(class_name, &export.function.name);
-        let descriptor = self.cx.describe(&wasm_name);
-        let (js, ts) = Js2Rust::new(&export.function.name, self.cx)
-            .method(export.method)
-            .process(descriptor.unwrap_function())
-            .finish("", &format!("wasm.{}", wasm_name));
+        let descriptor = self.cx.describe(&wasm_name)?;
+        let (arguments, mut returns) = descriptor.unwrap_function().metadata_tys();
+        if export.returns_self {
+            returns = format!("class:{}", class_name);
+        }
+        self.cx.metadata.class(class_name).methods.push(metadata::Method {
+            name: export.function.name.clone(),
+            is_static: !export.method,
+            consumes: export.consumed,
+            returns_self: export.returns_self,
+            arguments,
+            returns,
+            is_async: export.is_async,
+            deprecated: export.deprecated.clone(),
+        });
+        let mut cx = Js2Rust::new(&export.function.name, self.cx);
+        if let Some(ref message) = export.deprecated {
+            cx.deprecated(&format!("{}.{}", class_name, export.function.name), message);
+        }
+        if export.method {
+            cx.method(class_name)
+                .assert_not_moved(&format!("{}.{}", class_name, export.function.name));
+            if export.consumed {
+                cx.consumes_this();
+            }
+        }
+        cx.returns_promise(export.is_async)
+            .process(descriptor.unwrap_function())?;
+        if export.returns_self {
+            cx.returns_this();
+        }
+        let (js, ts) = cx.finish("", &format!("wasm.{}", wasm_name));
+        let arg_tys = cx.js_arguments.iter()
+            .map(|a| a.1.clone())
+            .collect::<Vec<_>>();
+        let ret_ty = cx.ret_ty.clone();
+        let returns_view = cx.returns_view;
+        let kind = if export.method { Member::Method } else { Member::Static };
+        let member = self.cx.class_member(class_name, kind, &export.function.name, &js);
         let class = self.cx.exported_classes.entry(class_name.to_string())
             .or_insert(ExportedClass::default());
+        if let Some(ref role) = export.iterator {
+            let valid = export.method && !export.consumed && !export.is_async && match &role[..] {
+                "len" => arg_tys.is_empty() && ret_ty == "number",
+                _ => arg_tys == ["number"] && ret_ty != "void",
+            };
+            if !valid {
+                return Err(format!("the `iterator = {}` {} must be a synchronous method \
+                                    with the signature {}", role, what, match &role[..] {
+                                        "len" => "`fn(&self) -> u32`",
+                                        _ => "`fn(&self, u32) -> T`",
+                                    }));
+            }
+            let duplicate = if role == "len" {
+                let name = Some(export.function.name.clone());
+                mem::replace(&mut class.iterator_len, name).is_some()
+            } else {
+                let get = Some((export.function.name.clone(), ret_ty));
+                mem::replace(&mut class.iterator_get, get).is_some()
+            };
+            if duplicate {
+                return Err(format!("the struct `{}` has more than one `iterator = {}` method",
+                                   class_name, role));
+            }
+        }
+        let doc = export.deprecated.as_ref().map(|m| deprecated_doc(m)).unwrap_or_default();
+        class.contents.push_str("\n");
+        class.contents.push_str(&doc);
+        class.contents.push_str(&member);
+        if returns_view {
+            class.typescript.push_str(VIEW_DOC);
+        }
+        if export.consumed {
+            class.typescript.push_str(CONSUMED_DOC);
+        }
+        class.typescript.push_str(&doc);
         if !export.method {
-            class.contents.push_str("static ");
             class.typescript.push_str("static ");
         }
 
@@ -1360,25 +3981,33 @@ impl<'a, 'b> SubContext<'a, 'b> {
         class.constructor = match constructors.len() {
             0 => None,
             1 => Some(constructors[0].clone()),
-            x @ _ => panic!("There must be only one constructor, not {}", x),
+            x => return Err(format!("There must be only one constructor, not {}", x)),
         };
+        self.cx.metadata.class(class_name).constructor = class.constructor.clone();
 
-        class.contents.push_str(&export.function.name);
-        class.contents.push_str(&js);
-        class.contents.push_str("\n");
-        class.typescript.push_str(&ts);
+        class.typescript.push_str(ts.trim());
         class.typescript.push_str("\n");
+        Ok(())
     }
 
-    pub fn generate_import(&mut self, import: &shared::Import) {
+    pub fn generate_import(&mut self, import: &shared::Import) -> Result<(), String> {
+        if let Some(ref module) = import.module {
+            if self.cx.config.no_modules {
+                return Err(format!("import from `{}` module not allowed in `--no-modules`. \
+                                    use `--nodejs` or `--browser` instead", module))
+            }
+        }
         match import.kind {
             shared::ImportKind::Function(ref f) => {
                 self.generate_import_function(import, f)
             }
             shared::ImportKind::Static(ref s) => {
-                self.generate_import_static(import, s)
+                self.generate_import_static(import, s);
+                Ok(())
             }
-            shared::ImportKind::Type(_) => {}
+            // The values of string enums are emitted when they're used.
+            shared::ImportKind::Type(_) |
+            shared::ImportKind::Enum(_) => Ok(()),
         }
     }
 
@@ -1387,6 +4016,7 @@ impl<'a, 'b> SubContext<'a, 'b> {
                                   import: &shared::ImportStatic) {
         // TODO: should support more types to import here
         let obj = self.import_name(info, &import.name);
+        self.imported_shape(info, slice::from_ref(&import.name), true).ty = Some("any".to_string());
         self.cx.expose_add_heap_object();
         self.cx.export(&import.shim, &format!("
             function() {{
@@ -1397,15 +4027,28 @@ impl<'a, 'b> SubContext<'a, 'b> {
 
     pub fn generate_import_function(&mut self,
                                     info: &shared::Import,
-                                    import: &shared::ImportFunction) {
-        let descriptor = self.cx.describe(&import.shim);
+                                    import: &shared::ImportFunction)
+        -> Result<(), String>
+    {
+        let descriptor = self.cx.describe(&import.shim)?;
 
+        // Lazy imports look their target up when they're first called, so a
+        // missing one is only an error for its callers.
+        let lazy = import.lazy && !(import.method && import.structural);
         let target = match import.class {
+            Some(ref class) if import.js_new && lazy => {
+                let class = self.lazy_import_name(info, class);
+                format!("new ({})", self.lazy_lookup(import, &class))
+            }
             Some(ref class) if import.js_new => {
                 format!("new {}", self.import_name(info, class))
             }
             Some(ref class) if import.method => {
-                let class = self.import_name(info, class);
+                let class = if lazy {
+                    self.lazy_import_name(info, class)
+                } else {
+                    self.import_name(info, class)
+                };
                 let target = if let Some(ref g) = import.getter {
                     if import.structural {
                         format!("function() {{ return this.{}; }}", g)
@@ -1413,7 +4056,7 @@ impl<'a, 'b> SubContext<'a, 'b> {
                         self.cx.expose_get_inherited_descriptor();
                         format!(
                             "GetOwnOrInheritedPropertyDescriptor\
-                                ({}.prototype, '{}').get;",
+                                ({}.prototype, '{}').get",
                             class,
                             g,
                         )
@@ -1425,28 +4068,47 @@ impl<'a, 'b> SubContext<'a, 'b> {
                         self.cx.expose_get_inherited_descriptor();
                         format!(
                             "GetOwnOrInheritedPropertyDescriptor\
-                                ({}.prototype, '{}').set;",
+                                ({}.prototype, '{}').set",
                             class,
                             s,
                         )
                     }
                 } else {
-                    if import.structural {
+                    if import.structural && import.variadic && self.cx.es5() {
+                        // Without spread syntax the arguments, trailing slice
+                        // already spread, are forwarded as they are.
+                        format!("function() {{ return this.{}.apply(this, arguments); }}",
+                                import.function.name)
+                    } else if import.structural {
                         let nargs = descriptor.unwrap_function().arguments.len();
                         let mut s = format!("function(");
                         for i in 0..nargs - 1 {
                             if i > 0 {
                                 drop(write!(s, ", "));
                             }
+                            if import.variadic && i == nargs - 2 {
+                                s.push_str("...");
+                            }
                             drop(write!(s, "x{}", i));
                         }
-                        s.push_str(") { return this.");
+                        s.push_str(") { ");
+                        if import.lazy {
+                            s.push_str(&format!(
+                                "if (typeof(this.{}) !== 'function') throw {}; ",
+                                import.function.name,
+                                unavailable_error(&import.function.name),
+                            ));
+                        }
+                        s.push_str("return this.");
                         s.push_str(&import.function.name);
                         s.push_str("(");
                         for i in 0..nargs - 1 {
                             if i > 0 {
                                 drop(write!(s, ", "));
                             }
+                            if import.variadic && i == nargs - 2 {
+                                s.push_str("...");
+                            }
                             drop(write!(s, "x{}", i));
                         }
                         s.push_str("); }");
@@ -1455,10 +4117,19 @@ impl<'a, 'b> SubContext<'a, 'b> {
                         format!("{}.prototype.{}", class, import.function.name)
                     }
                 };
-                self.cx.global(&format!("
-                    const {}_target = {};
-                ", import.shim, target));
-                format!("{}_target.call", import.shim)
+                if lazy {
+                    format!("{}.call", self.lazy_lookup(import, &target))
+                } else {
+                    self.cx.global(&format!("
+                        const {}_target = {};
+                    ", import.shim, target));
+                    format!("{}_target.call", import.shim)
+                }
+            }
+            Some(ref class) if lazy => {
+                let class = self.lazy_import_name(info, class);
+                let target = format!("{}.{}", class, import.function.name);
+                self.lazy_lookup(import, &target)
             }
             Some(ref class) => {
                 let class = self.import_name(info, class);
@@ -1467,6 +4138,10 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 ", import.shim, class, import.function.name));
                 format!("{}_target", import.shim)
             }
+            None if lazy => {
+                let name = self.lazy_import_name(info, &import.function.name);
+                self.lazy_lookup(import, &name)
+            }
             None => {
                 let name = self.import_name(info, &import.function.name);
                 if name.contains(".") {
@@ -1480,49 +4155,247 @@ impl<'a, 'b> SubContext<'a, 'b> {
             }
         };
 
-        let js = Rust2Js::new(self.cx)
-            .catch(import.catch)
-            .process(descriptor.unwrap_function())
-            .finish(&target);
+        let mut js = Rust2Js::new(self.cx);
+        js.catch(import.catch)
+            .variadic(import.variadic)
+            .process(descriptor.unwrap_function())?;
+        let signature = js.ts_signature(import.method);
+        let ty = if import.getter.is_some() {
+            Some(js.ret_ty.clone())
+        } else if import.setter.is_some() {
+            Some(js.ts_arguments[1].clone())
+        } else {
+            None
+        };
+        let splits_i64 = js.splits_i64;
+        let externrefs = js.externref_signature().clone();
+        let js = js.finish(&target);
         self.cx.export(&import.shim, &js);
+        if splits_i64 {
+            self.cx.split_i64_imports.insert(import.shim.clone());
+        }
+        if !externrefs.is_empty() {
+            self.cx.externref_imports.insert(import.shim.clone(), externrefs);
+        }
+
+        // Structural methods are looked up on the receiver rather than the
+        // class, so they aren't part of what the import must provide.
+        let path = match import.class {
+            Some(_) if import.method && import.structural => return Ok(()),
+            Some(ref class) if import.js_new => vec![class.clone()],
+            Some(ref class) if import.method => {
+                let name = import.getter.as_ref()
+                    .or(import.setter.as_ref())
+                    .unwrap_or(&import.function.name);
+                vec![class.clone(), "prototype".to_string(), name.clone()]
+            }
+            Some(ref class) => vec![class.clone(), import.function.name.clone()],
+            None => vec![import.function.name.clone()],
+        };
+        let shape = self.imported_shape(info, &path, !import.lazy);
+        match ty {
+            Some(ty) => shape.ty = Some(ty),
+            None if import.js_new => { shape.signatures.insert(format!("new {}", signature)); }
+            None => { shape.signatures.insert(signature); }
+        }
+        Ok(())
     }
 
-    pub fn generate_enum(&mut self, enum_: &shared::Enum) {
-        let mut variants = String::new();
+    /// The shape in the `Imports` interface of the JS value at `path`, under
+    /// the `js_namespace` if any, of the module `info` imports from. Unless
+    /// the value is `required` it's left optional, as are the values leading
+    /// to it.
+    fn imported_shape(&mut self, info: &shared::Import, path: &[String], required: bool)
+        -> &mut ImportShape
+    {
+        let module = info.module.as_ref().map(|module| {
+            if !self.cx.config.local_module_roots.is_empty() {
+                if let Some(path) = local_module_path(module) {
+                    return path
+                }
+            }
+            module.clone()
+        });
+        let mut shape = self.cx.imported_shapes.entry(module).or_default();
+        for name in info.js_namespace.iter().chain(path) {
+            shape = {shape}.members.entry(name.clone()).or_default();
+            shape.required |= required;
+        }
+        shape
+    }
 
+    /// Declares the function looking up the target of the lazy `import`,
+    /// which is `expr`, returning the expression calling it. The target is
+    /// cached once it's been found.
+    fn lazy_lookup(&mut self, import: &shared::ImportFunction, expr: &str) -> String {
+        let name = match import.class {
+            Some(ref class) if import.js_new => class.clone(),
+            Some(ref class) => format!("{}.{}", class, import.function.name),
+            None => import.function.name.clone(),
+        };
+        self.cx.global(&format!("
+            let {shim}_target;
+            function {shim}_lookup() {{
+                if ({shim}_target === undefined) {{
+                    let target;
+                    try {{
+                        target = {expr};
+                    }} catch (e) {{}}
+                    if (typeof(target) !== 'function')
+                        throw {error};
+                    {shim}_target = target;
+                }}
+                return {shim}_target;
+            }}
+        ", shim = import.shim, expr = expr, error = unavailable_error(&name)));
+        format!("{}_lookup()", import.shim)
+    }
+
+    pub fn generate_enum(&mut self, enum_: &shared::Enum) -> Result<(), String> {
+        let variants = enum_.variants.iter()
+            .map(|v| format!("{}: {}", v.name, v.value))
+            .collect::<Vec<_>>();
+        let name = js_identifier(&enum_.name);
+        self.cx.define_js_name(&name, format!("enum `{}`{}", enum_.name, self.origin()))?;
+        self.cx.export(&name, &format!("Object.freeze({{ {} }})", variants.join(", ")));
+        self.cx.metadata.enums.push(metadata::Enum {
+            name: name.clone(),
+            variants: enum_.variants.iter()
+                .map(|v| metadata::Variant { name: v.name.clone(), value: v.value })
+                .collect(),
+        });
+        self.cx.typescript.push_str(&format!("export enum {} {{\n", name));
         for variant in enum_.variants.iter() {
-            variants.push_str(&format!("{}:{},", variant.name, variant.value));
+            self.cx.typescript.push_str(&format!("{},\n", variant.name));
         }
-        self.cx.export(&enum_.name, &format!("Object.freeze({{ {} }})", variants));
-        self.cx.typescript.push_str(&format!("export enum {} {{", enum_.name));
+        self.cx.typescript.push_str("}\n\n");
+        Ok(())
+    }
 
-        variants.clear();
-        for variant in enum_.variants.iter() {
-            variants.push_str(&format!("{},", variant.name));
+    pub fn generate_const(&mut self, const_: &shared::Const) -> Result<(), String> {
+        let name = js_identifier(&const_.name);
+        self.cx.define_js_name(&name, format!("const `{}`{}", const_.name, self.origin()))?;
+        let (value, ty, tag) = match const_.value {
+            // Rust writes the infinities as `inf`, and `NaN` as JS does.
+            shared::ConstValue::Number(n) if n.is_infinite() => {
+                let value = if n > 0.0 { "Infinity" } else { "-Infinity" };
+                (value.to_string(), "number", "f64")
+            }
+            shared::ConstValue::Number(n) => (n.to_string(), "number", "f64"),
+            shared::ConstValue::Boolean(b) => (b.to_string(), "boolean", "bool"),
+            shared::ConstValue::String(ref s) => (js_string(s), "string", "string"),
+            shared::ConstValue::Getter => return self.generate_const_getter(&name, &const_.name),
+        };
+        self.cx.metadata.consts.push(metadata::Const { name: name.clone(), ty: tag.to_string() });
+        self.cx.export(&name, &value);
+        self.cx.typescript.push_str(&format!("export const {}: {};\n\n", name, ty));
+        Ok(())
+    }
+
+    /// Exports a const whose value is read from the wasm, through an
+    /// accessor where there's an object to define one on and otherwise as
+    /// a binding assigned once the wasm is available.
+    fn generate_const_getter(&mut self, name: &str, rust_name: &str) -> Result<(), String> {
+        let getter = shared::const_getter(rust_name);
+        let descriptor = self.cx.describe(&getter)?;
+        self.cx.metadata.consts.push(metadata::Const {
+            name: name.to_string(),
+            ty: descriptor.unwrap_function().metadata_tys().1,
+        });
+        let mut cx = Js2Rust::new(&getter, self.cx);
+        cx.process(descriptor.unwrap_function())?;
+        let (js, _) = cx.finish("function", &format!("wasm.{}", getter));
+        let ty = cx.ret_ty.clone();
+        self.cx.global(&format!("function {}{}\n", getter, &js[8..]));
+        let object = if self.cx.config.nodejs {
+            "module.exports"
+        } else if self.cx.config.no_modules {
+            // The global namespace is `init` itself, onto which the other
+            // exports are copied, which would read a getter defined on
+            // `__exports` before the wasm is loaded. Function declarations
+            // are hoisted, so `init` is defined already.
+            "init"
+        } else {
+            self.cx.global(&format!("export let {};\n", name));
+            self.cx.const_getters.push(name.to_string());
+            ""
+        };
+        if !object.is_empty() {
+            self.cx.global(&format!("\
+                Object.defineProperty({}, '{}', {{ get: {}, enumerable: true }});\n\
+            ", object, name, getter));
         }
-        self.cx.typescript.push_str(&variants);
-        self.cx.typescript.push_str("}\n");
+        self.cx.exported_names.insert(name.to_string());
+        self.cx.typescript.push_str(&format!("export const {}: {};\n\n", name, ty));
+        Ok(())
     }
 
-    fn import_name(&mut self, import: &shared::Import, item: &str) -> String {
-        if let Some(ref module) = import.module {
-            if self.cx.config.no_modules {
-                panic!("import from `{}` module not allowed in `--no-modules`. use `--nodejs` or `--browser` instead", module);
+    /// The specifier to import `name` from `module` with, which is changed
+    /// for the local modules copied into the output directory.
+    fn module_specifier(&mut self, module: &str, name: &str) -> String {
+        if !self.cx.config.local_module_roots.is_empty() {
+            if let Some(path) = local_module_path(module) {
+                self.cx.local_modules.entry(module.to_string())
+                    .or_default()
+                    .insert(name.to_string());
+                return path
+            }
+        }
+        module.to_string()
+    }
+
+    /// Like `import_name`, but a module is imported as a namespace object
+    /// so that it isn't an error for it not to export `item`, as lazy
+    /// imports only look it up when they're called.
+    fn lazy_import_name(&mut self, import: &shared::Import, item: &str) -> String {
+        let module = match import.module {
+            Some(ref module) => module,
+            None => return self.import_name(import, item),
+        };
+        let name = import.js_namespace.as_ref().map(|s| &**s).unwrap_or(item);
+        let module = self.module_specifier(module, name);
+        let next = format!("__wbg_module{}", self.cx.module_namespaces.len());
+        let imports = &mut self.cx.imports;
+        let nodejs = self.cx.config.nodejs;
+        let local = self.cx.module_namespaces.entry(module.clone()).or_insert_with(|| {
+            if nodejs {
+                imports.push_str(&format!("const {} = require('{}');\n", next, module));
+            } else {
+                imports.push_str(&format!("import * as {} from '{}';\n", next, module));
             }
+            next
+        });
+        match import.js_namespace {
+            Some(_) => format!("{}.{}.{}", local, name, item),
+            None => format!("{}.{}", local, name),
+        }
+    }
 
-            let name = import.js_namespace.as_ref().map(|s| &**s).unwrap_or(item);
+    fn import_name(&mut self, import: &shared::Import, item: &str) -> String {
+        if let Some(ref module) = import.module {
+            let name = import.js_namespace.as_deref().unwrap_or(item);
+            let module = self.module_specifier(module, name);
 
+            let local = js_identifier(name);
             if self.cx.imported_names.insert(name.to_string()) {
                 if self.cx.config.nodejs {
                     self.cx.imports.push_str(&format!("\
                         const {} = require('{}').{};\n\
-                    ", name, module, name));
+                    ", local, module, name));
+                } else if local != name {
+                    self.cx.imports.push_str(&format!("\
+                        import {{ {} as {} }} from '{}';\n\
+                    ", name, local, module));
                 } else {
                     self.cx.imports.push_str(&format!("\
                         import {{ {} }} from '{}';\n\
                     ", name, module));
                 }
             }
+            return match import.js_namespace {
+                Some(_) => format!("{}.{}", local, item),
+                None => local,
+            }
         }
         match import.js_namespace {
             Some(ref s) => format!("{}.{}", s, item),
@@ -1531,7 +4404,406 @@ impl<'a, 'b> SubContext<'a, 'b> {
     }
 }
 
-fn indent(s: &str) -> String {
+/// Words which can't be used as the name of a binding in the strict mode
+/// module code that we generate.
+const JS_RESERVED_WORDS: &[&str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const",
+    "continue", "debugger", "default", "delete", "do", "else", "enum", "eval",
+    "export", "extends", "false", "finally", "for", "function", "if",
+    "implements", "import", "in", "instanceof", "interface", "let", "new",
+    "null", "package", "private", "protected", "public", "return", "static",
+    "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void",
+    "while", "with", "yield",
+];
+
+/// The index of the function `module` exports as `name`, if any.
+fn exported_function(module: &Module, name: &str) -> Option<u32> {
+    module.export_section()?.entries().iter().filter_map(|e| {
+        match *e.internal() {
+            Internal::Function(i) if e.field() == name => Some(i),
+            _ => None,
+        }
+    }).next()
+}
+
+/// The type of the function `idx` defined, not imported, by `module`.
+fn function_type(module: &Module, idx: u32) -> &FunctionType {
+    let imported = module.import_count(ImportCountType::Function) as u32;
+    let ty = module.function_section().unwrap().entries()[(idx - imported) as usize]
+        .type_ref();
+    match module.type_section().unwrap().types()[ty as usize] {
+        Type::Function(ref f) => f,
+    }
+}
+
+/// Adds a function of type `ty` with `locals` and `code` to `module`,
+/// returning its index.
+fn add_function(module: &mut Module, ty: FunctionType, locals: Vec<Local>, code: Vec<Opcode>)
+    -> u32
+{
+    let imported = module.import_count(ImportCountType::Function) as u32;
+    let mut type_ref = 0;
+    let mut idx = 0;
+    for section in module.sections_mut() {
+        match *section {
+            Section::Type(ref mut s) => {
+                s.types_mut().push(Type::Function(ty.clone()));
+                type_ref = s.types().len() as u32 - 1;
+            }
+            Section::Function(ref mut s) => {
+                idx = imported + s.entries().len() as u32;
+                s.entries_mut().push(Func::new(type_ref));
+            }
+            Section::Code(ref mut s) => {
+                s.bodies_mut().push(FuncBody::new(locals.clone(), Opcodes::new(code.clone())));
+            }
+            _ => {}
+        }
+    }
+    idx
+}
+
+/// Makes everything in `module` calling, exporting or putting in a table the
+/// function `from` use the function `to` instead.
+fn redirect_calls(module: &mut Module, from: u32, to: u32) {
+    for section in module.sections_mut() {
+        match *section {
+            Section::Export(ref mut s) => {
+                for e in s.entries_mut() {
+                    if let Internal::Function(ref mut i) = *e.internal_mut() {
+                        if *i == from {
+                            *i = to;
+                        }
+                    }
+                }
+            }
+            Section::Element(ref mut s) => {
+                for segment in s.entries_mut() {
+                    for member in segment.members_mut() {
+                        if *member == from {
+                            *member = to;
+                        }
+                    }
+                }
+            }
+            Section::Code(ref mut s) => {
+                for body in s.bodies_mut() {
+                    for opcode in body.code_mut().elements_mut() {
+                        if let Opcode::Call(ref mut i) = *opcode {
+                            if *i == from {
+                                *i = to;
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Makes `module` export the function `idx` as `name` instead.
+fn repoint_export(module: &mut Module, name: &str, idx: u32) {
+    for section in module.sections_mut() {
+        if let Section::Export(ref mut s) = *section {
+            for e in s.entries_mut() {
+                if e.field() == name {
+                    *e.internal_mut() = Internal::Function(idx);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `name` can be used as the global the `no_modules` glue is assigned
+/// to, which is an identifier or a path of them like `my.app.wasm`.
+pub fn is_global_name(name: &str) -> bool {
+    name.split('.').all(|part| {
+        let mut chars = part.chars();
+        let ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        match chars.next() {
+            Some(c) if ident(c) && !c.is_numeric() => {}
+            _ => return false,
+        }
+        chars.all(ident) && !JS_RESERVED_WORDS.contains(&part)
+    })
+}
+
+/// Declares `__global`, the global object the `no_modules` glue reads and
+/// assigns globals on. That's `globalThis` where it's available, and
+/// otherwise `self` which both windows and workers define.
+pub const GLOBAL_OBJECT: &str =
+    "const __global = typeof globalThis !== 'undefined' ? globalThis : self;";
+
+/// Placeholder statements standing in for `Bindgen::js_prelude` and
+/// friends until the glue is final, see `insert_verbatim`.
+pub const PRELUDE: &str = "__wbindgen_prelude__;";
+pub const EPILOGUE: &str = "__wbindgen_epilogue__;";
+
+/// Replaces the placeholder statement `marker` in `js` with `text`, on lines
+/// of its own exactly as given. Nothing is inserted into JS without the
+/// placeholder.
+pub fn insert_verbatim(js: &str, marker: &str, text: &str) -> String {
+    let i = match js.find(marker) {
+        Some(i) => i,
+        None => return js.to_string(),
+    };
+    let line = js[..i].rfind('\n').map(|n| n + 1).unwrap_or(0);
+    let (before, sep) = if js[line..i].trim().is_empty() {
+        (&js[..line], "")
+    } else {
+        (&js[..i], "\n")
+    };
+    let mut after = &js[i + marker.len()..];
+    if after.starts_with('\n') {
+        after = &after[1..];
+    }
+    let end = if text.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}{}{}{}", before, sep, text, end, after)
+}
+
+/// Throws where shared memories can't be created for lack of
+/// `SharedArrayBuffer`, which browsers only provide to cross-origin isolated
+/// pages.
+pub const SHARED_MEMORY_CHECK: &str = "\
+if (typeof SharedArrayBuffer === 'undefined')
+    throw new Error('the wasm memory is shared but SharedArrayBuffer is not available, ' +
+                    'in browsers the page must be cross-origin isolated to use it');
+";
+
+/// Defines `__wbg_default_input`, the URL of the wasm file `wasm` next to the
+/// `no_modules` glue, which is what `init` loads when it isn't given one.
+///
+/// On a page that's next to the script, as `document.currentScript` says
+/// while it first runs. A worker loading the glue with `importScripts` has
+/// no `document`, and the wasm is looked for next to the worker's own script
+/// instead.
+fn default_input(wasm: &str) -> String {
+    format!("
+        function __wbg_script_dir() {{
+            let url = typeof location !== 'undefined' ? location.href : '';
+            if (typeof document !== 'undefined' && document.currentScript) {{
+                url = document.currentScript.src;
+            }}
+            return url.split(/[?#]/)[0].replace(/[^\\/]*$/, '');
+        }}
+        const __wbg_default_input = __wbg_script_dir() + '{}';
+    ", wasm)
+}
+
+/// Defines `__wbg_instantiate(source, imports)` for glue which loads the
+/// wasm itself, which instantiates the wasm in `source`, either its bytes or
+/// the URL to fetch it from.
+///
+/// A fetched wasm is compiled while it downloads when it's served as
+/// `application/wasm`, which `WebAssembly.instantiateStreaming` insists on,
+/// and otherwise downloaded first. With `csp_safe` the error of a Content
+/// Security Policy blocking it is named.
+pub fn instantiate_function(csp_safe: bool) -> String {
+    format!("{}{}", INSTANTIATE, if csp_safe { CSP_ERROR } else { ";\n}\n" })
+}
+
+const INSTANTIATE: &str = "\
+function __wbg_instantiate(source, imports) {
+    let instantiated;
+    if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+        instantiated = WebAssembly.instantiate(source, imports);
+    } else {
+        instantiated = fetch(source).then(function(response) {
+            const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+            const streams = typeof WebAssembly.instantiateStreaming === 'function';
+            if (streams && type.toLowerCase() === 'application/wasm') {
+                return WebAssembly.instantiateStreaming(response, imports);
+            }
+            return response.arrayBuffer()
+                .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+        });
+    }
+    return instantiated";
+
+const CSP_ERROR: &str = "\
+.catch(function(e) {
+        if (!/Content Security Policy|\\bCSP\\b|unsafe-eval/.test(String(e && e.message)))
+            throw e;
+        const error = new Error('compiling the wasm was blocked by the Content Security Policy, ' +
+            \"its script-src must allow 'wasm-unsafe-eval' (or 'unsafe-eval' \" +
+            'in older browsers): ' + e.message);
+        error.name = 'WasmCspError';
+        error.cause = e;
+        throw error;
+    });
+}
+";
+
+/// What a Content Security Policy without `unsafe-eval` blocks that `js`
+/// uses, if anything, see `Bindgen::csp_safe`.
+pub fn csp_violation(js: &str) -> Option<&'static str> {
+    let forbidden = [
+        ("eval", "eval"),
+        ("Function", "new Function"),
+        ("document.write", "document.write"),
+    ];
+    let ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    for &(name, what) in forbidden.iter() {
+        for (i, _) in js.match_indices(name) {
+            let before = js[..i].chars().next_back();
+            if before.is_some_and(|c| ident(c) || c == '.') {
+                continue
+            }
+            // `eval` and `Function` are only a problem when they're called
+            // (`Function` maybe with `new`), unlike `document.write` (or
+            // `document.writeln`) which is never needed.
+            let after = js[i + name.len()..].trim_start().chars().next();
+            if name == "document.write" || after == Some('(') {
+                return Some(what)
+            }
+        }
+    }
+    None
+}
+
+/// A JS expression creating a memory of the type `ty`.
+pub fn memory_constructor(ty: &MemoryType, shared: bool) -> String {
+    let extra = if shared { "shared: true, " } else { "" };
+    format!("new WebAssembly.Memory({})", super::js_limits(ty.limits(), extra))
+}
+
+/// Assigns `value` to the global `name` on `__global`. The name may be a path
+/// like `my.app.wasm`, in which case the objects along it are created if they
+/// don't exist yet.
+pub fn assign_global(name: &str, value: &str) -> String {
+    let parts = name.split('.').collect::<Vec<_>>();
+    let mut ret = String::new();
+    for i in 1..parts.len() {
+        ret.push_str(&format!("__global.{0} = __global.{0} || {{}};\n", parts[..i].join(".")));
+    }
+    ret.push_str(&format!("__global.{} = {};", name, value));
+    ret
+}
+
+/// Wraps TypeScript `declarations` into a namespace for the global `name`,
+/// nested to match if it's a path like `my.app.wasm`. The namespace is merged
+/// with a declaration of the loader function taking `params`.
+pub fn global_namespace(name: &str, params: &str, declarations: &str) -> String {
+    let loader = |name: &str| {
+        format!("function {0}({1}): Promise<typeof {0}>;\n", name, params)
+    };
+    match name.rfind('.') {
+        Some(i) => {
+            let (outer, name) = (&name[..i], &name[i + 1..]);
+            format!("declare namespace {} {{\n{}namespace {} {{\n{}}}\n}}\n",
+                    outer, loader(name), name, declarations)
+        }
+        None => {
+            format!("declare {}declare namespace {} {{\n{}}}\n",
+                    loader(name), name, declarations)
+        }
+    }
+}
+
+impl ImportShape {
+    /// Appends the paths under `prefix` of the values used from this shape
+    /// to `dst`, see `metadata::ImportedModule::members`.
+    fn used_paths(&self, prefix: &str, dst: &mut Vec<String>) {
+        for (name, member) in self.members.iter() {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            let used = member.members.is_empty() ||
+                member.ty.is_some() ||
+                !member.signatures.is_empty();
+            if used {
+                dst.push(path.clone());
+            }
+            member.used_paths(&path, dst);
+        }
+    }
+
+    /// The members of the TypeScript object type of this shape.
+    fn members_ts(&self) -> String {
+        let mut ts = String::new();
+        for signature in self.signatures.iter() {
+            ts.push_str(&format!("{};\n", signature));
+        }
+        for (name, member) in self.members.iter() {
+            let mut key = if is_global_name(name) && !name.contains('.') {
+                name.clone()
+            } else {
+                format!("'{}'", name)
+            };
+            if !member.required {
+                key.push('?');
+            }
+            if member.members.is_empty() && member.signatures.is_empty() {
+                let ty = member.ty.as_deref().unwrap_or("any");
+                ts.push_str(&format!("{}: {};\n", key, ty));
+            } else if member.members.is_empty() && member.ty.is_none() &&
+                member.signatures.iter().all(|s| !s.starts_with("new "))
+            {
+                for signature in member.signatures.iter() {
+                    ts.push_str(&format!("{}{};\n", key, signature));
+                }
+            } else {
+                ts.push_str(&format!("{}: {{\n{}}};\n", key, member.members_ts()));
+            }
+        }
+        ts
+    }
+}
+
+/// The JS expression of the error a lazy import throws when the function
+/// `name` it calls isn't available.
+fn unavailable_error(name: &str) -> String {
+    let message = format!("imported function `{}` is not available", name);
+    format!("new Error({})", js_string(&message))
+}
+
+/// Returns `s` as a JS string literal.
+pub fn js_string(s: &str) -> String {
+    // JSON is valid JS once the two line terminators it allows in strings
+    // are escaped.
+    serde_json::to_string(s).unwrap()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+/// The JSDoc comment marking an item `@deprecated` with the `#[deprecated]`
+/// note `message`, in both the JS and the `.d.ts`.
+fn deprecated_doc(message: &str) -> String {
+    let mut doc = String::from("/**\n * @deprecated");
+    for (i, line) in message.replace("*/", "*\\/").lines().enumerate() {
+        doc.push_str(if i == 0 { " " } else { "\n * " });
+        doc.push_str(line.trim_end());
+    }
+    doc.push_str("\n */\n");
+    doc
+}
+
+/// The console warning of debug mode the first time the deprecated `name` is
+/// used.
+fn deprecation_warning(name: &str, message: &str) -> String {
+    if message.is_empty() {
+        format!("`{}` is deprecated", name)
+    } else {
+        format!("`{}` is deprecated: {}", name, message)
+    }
+}
+
+/// Returns the name a Rust item called `name` gets in the generated JS.
+///
+/// Reserved words get an underscore appended, so an exported `fn delete` is
+/// available as `delete_`.
+fn js_identifier(name: &str) -> String {
+    if JS_RESERVED_WORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn indent(s: &str) -> String {
     let mut ret = String::new();
     for line in s.lines() {
         ret.push_str("    ");
@@ -1541,7 +4813,7 @@ fn indent(s: &str) -> String {
     return ret
 }
 
-fn deindent(s: &str) -> String {
+pub fn deindent(s: &str) -> String {
     let amt_to_strip = s.lines()
         .filter(|l| !l.trim().is_empty())
         .map(|s| s.len() - s.trim_left().len())
@@ -1556,3 +4828,506 @@ fn deindent(s: &str) -> String {
     }
     ret
 }
+
+/// The runtime module made of the parts of the heap and stack management in
+/// `parts`, exporting everything, see `Bindgen::shared_runtime`.
+pub fn runtime_module(config: &Bindgen, parts: &[(&'static str, String)]) -> String {
+    let mut js = String::from("/* tslint:disable */\n");
+    for part in parts.iter() {
+        js.push_str(&part.1);
+        js.push_str("\n\n");
+    }
+    let (declared, _) = module_names(&js);
+    let names = declared.into_iter().collect::<Vec<_>>();
+    js.push_str(&format!("export {{ {} }};\n", names.join(", ")));
+    let js = reindent(&js);
+    if config.es_target == EsVersion::Es5 { var_declarations(&js) } else { js }
+}
+
+/// The classes module, defining each of `classes` along with the static
+/// methods it has in the input owning it and the JS class it extends there,
+/// see `Bindgen::add_input_path`.
+///
+/// The class of each input exporting one extends it, and the owner's sets
+/// itself as its `__wbg_owner`, which constructing it and its static methods
+/// are forwarded to.
+pub fn classes_module(config: &Bindgen,
+                      classes: &[(String, Vec<String>, Option<shared::Superclass>)])
+    -> String
+{
+    let es5 = config.es_target == EsVersion::Es5;
+    let mut imports = String::new();
+    let mut js = String::new();
+    for (class, statics, superclass) in classes.iter() {
+        let base = superclass.as_ref().map(|base| {
+            let name = base.js_namespace.as_ref().unwrap_or(&base.name);
+            let expr = match base.module {
+                Some(ref module) => {
+                    let mut module = module.clone();
+                    if !config.local_module_roots.is_empty() {
+                        if let Some(path) = local_module_path(&module) {
+                            module = path;
+                        }
+                    }
+                    let local = format!("__wbg_base_{}", class);
+                    if config.nodejs {
+                        imports.push_str(&format!("const {} = require('{}').{};\n",
+                                                  local, module, name));
+                    } else {
+                        imports.push_str(&format!("import {{ {} as {} }} from '{}';\n",
+                                                  name, local, module));
+                    }
+                    local
+                }
+                None => name.clone(),
+            };
+            match base.js_namespace {
+                Some(_) => format!("{}.{}", expr, base.name),
+                None => expr,
+            }
+        });
+        let missing_owner = format!("
+            if ({0}.__wbg_owner === undefined) {{
+                throw new Error('the glue of the input owning `{0}` is not loaded');
+            }}
+        ", class);
+        // In ES5 the classes of the inputs don't call this constructor, and
+        // the arguments are bound to the owner's to construct it with them.
+        if es5 {
+            js.push_str(&format!("
+                function {0}() {{
+                    {1}
+                    const args = [null].concat(Array.prototype.slice.call(arguments));
+                    return new (Function.prototype.bind.apply({0}.__wbg_owner, args))();
+                }}
+            ", class, missing_owner.trim()));
+            for name in statics.iter() {
+                js.push_str(&format!("
+                    {0}.{1} = function() {{
+                        return {0}.__wbg_owner.{1}.apply({0}.__wbg_owner, arguments);
+                    }};
+                ", class, name));
+            }
+            js.push('\n');
+            continue
+        }
+        let (extends, super_call) = match base {
+            Some(ref base) => (format!(" extends {}", base), "super(...args);\n"),
+            None => (String::new(), ""),
+        };
+        js.push_str(&format!("
+            class {0}{1} {{
+                constructor(...args) {{
+                    if (new.target !== {0}) {{
+                        {2}return;
+                    }}
+                    {3}
+                    return new {0}.__wbg_owner(...args);
+                }}
+        ", class, extends, super_call, missing_owner.trim()));
+        for name in statics.iter() {
+            js.push_str(&format!("
+                static {1}(...args) {{
+                    return {0}.__wbg_owner.{1}(...args);
+                }}
+            ", class, name));
+        }
+        js.push_str("}\n\n");
+    }
+
+    let names = classes.iter().map(|c| &c.0[..]).collect::<Vec<_>>();
+    let js = if config.nodejs {
+        let exports = names.iter()
+            .map(|name| format!("module.exports.{0} = {0};\n", name))
+            .collect::<String>();
+        format!("/* tslint:disable */\n{}\n{}{}", imports, js, exports)
+    } else if config.no_modules {
+        // The classes are assigned to the global the entry is assigned to
+        // later, which re-exports them from there.
+        let fields = names.iter()
+            .map(|name| format!("{0}: {0}", name))
+            .collect::<Vec<_>>();
+        let global = config.no_modules_global_name();
+        format!("(function() {{\n{}\n{}{}\n}})();\n",
+                GLOBAL_OBJECT, js,
+                assign_global(global, &format!("{{ {} }}", fields.join(", "))))
+    } else {
+        format!("/* tslint:disable */\n{}\n{}export {{ {} }};\n",
+                imports, js, names.join(", "))
+    };
+    let js = reindent(&js);
+    if es5 { var_declarations(&js) } else { js }
+}
+
+/// The names (of functions, classes, variables and imports) declared at the
+/// top level of the ES module `js`, and the names it exports.
+fn module_names(js: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let tokens = tokenize(js);
+    let mut declared = BTreeSet::new();
+    let mut exported = BTreeSet::new();
+    let mut depth = 0;
+    for (i, &token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).cloned();
+        match token {
+            Token::Punct(p) if p == "{" || p == "(" || p == "[" => depth += 1,
+            Token::Punct(p) if p == "}" || p == ")" || p == "]" => depth -= 1,
+            Token::Ident(s) if depth == 0 && (s == "import" || s == "export") => {
+                // Both are lists like `* as a`, `a` or `{ a, b as c }`, where
+                // the names declared (or exported) are those not followed
+                // by `as`.
+                let mut names = Vec::new();
+                let mut j = i + 1;
+                while let Some(&token) = tokens.get(j) {
+                    j += 1;
+                    match token {
+                        Token::Ident("from") => break,
+                        Token::Ident("as") => {}
+                        Token::Ident(s) => {
+                            if tokens.get(j) != Some(&Token::Ident("as")) {
+                                names.push(s.to_string());
+                            }
+                        }
+                        Token::Punct("}") => break,
+                        Token::Punct(p) if p == "*" || p == "{" || p == "," => {}
+                        _ => break,
+                    }
+                }
+                if s == "import" {
+                    declared.extend(names);
+                } else if next == Some(Token::Punct("{")) {
+                    exported.extend(names);
+                }
+            }
+            Token::Ident(s) if depth == 0 &&
+                (s == "function" || s == "class" || s == "let" || s == "const" || s == "var") =>
+            {
+                if let Some(Token::Ident(name)) = next {
+                    declared.insert(name.to_string());
+                    if i > 0 && tokens[i - 1] == Token::Ident("export") {
+                        exported.insert(name.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    (declared, exported)
+}
+
+/// The names `js` refers to which aren't property names, like `b` in
+/// `a.b`.
+fn free_names(js: &str) -> BTreeSet<&str> {
+    let tokens = tokenize(js);
+    tokens.iter()
+        .enumerate()
+        .filter_map(|(i, token)| match *token {
+            Token::Ident(s) if i == 0 || tokens[i - 1] != Token::Punct(".") => Some(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Turns the `let` and `const` declarations in `js` into `var` ones, for ES5
+/// output.
+///
+/// Apart from in loops this is fine as the glue only redeclares a name in
+/// sibling blocks. A loop body creating closures would have them all share
+/// one `var` where each iteration had a binding of its own, so such bodies
+/// are wrapped in a function called on each iteration, which is passed the
+/// current values of the loop's own declarations. That's only done for
+/// bodies without a `break`, `continue`, `return`, `this` or `arguments`,
+/// which would mean something else in the function, and the body mustn't
+/// assign to the loop's declarations either.
+pub fn var_declarations(js: &str) -> String {
+    let tokens = tokenize(js);
+    let offset = |s: &str| s.as_ptr() as usize - js.as_ptr() as usize;
+    let mut edits = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match *token {
+            Token::Ident(s) if s == "let" || s == "const" => {
+                edits.push((offset(s), s.len(), "var".to_string()));
+            }
+            Token::Ident(s) if s == "for" || s == "while" => {
+                if let Some((open, close, params)) = per_iteration_body(&tokens, i) {
+                    let (open, close) = match (tokens[open], tokens[close]) {
+                        (Token::Punct(open), Token::Punct(close)) => (open, close),
+                        _ => unreachable!(),
+                    };
+                    edits.push((offset(open) + 1, 0, format!(" (function({}) {{", params)));
+                    edits.push((offset(close), 0, format!("}})({}); ", params)));
+                }
+            }
+            _ => {}
+        }
+    }
+    edits.sort_by_key(|e| e.0);
+
+    let mut ret = String::new();
+    let mut last = 0;
+    for (at, len, text) in edits {
+        ret.push_str(&js[last..at]);
+        ret.push_str(&text);
+        last = at + len;
+    }
+    ret.push_str(&js[last..]);
+    ret
+}
+
+/// For the `for` or `while` at `tokens[i]`, whose body needs wrapping as
+/// described in `var_declarations`, the indices of the braces around the
+/// body and the loop's declarations to pass to it.
+fn per_iteration_body(tokens: &[Token], i: usize) -> Option<(usize, usize, String)> {
+    let matching = |open: usize| {
+        let mut depth = 0;
+        for (j, token) in tokens.iter().enumerate().skip(open) {
+            match *token {
+                Token::Punct("(") | Token::Punct("[") | Token::Punct("{") => depth += 1,
+                Token::Punct(")") | Token::Punct("]") | Token::Punct("}") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(j)
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    };
+    if tokens.get(i + 1) != Some(&Token::Punct("(")) {
+        return None
+    }
+    let header = matching(i + 1)?;
+    let open = (header + 1..tokens.len()).find(|&j| tokens[j] != Token::Newline)?;
+    if tokens[open] != Token::Punct("{") {
+        return None
+    }
+    let close = matching(open)?;
+
+    fn declared<'a>(range: &[Token<'a>]) -> Vec<&'a str> {
+        range.windows(2)
+            .filter_map(|pair| match (pair[0], pair[1]) {
+                (Token::Ident("let"), Token::Ident(name)) |
+                (Token::Ident("const"), Token::Ident(name)) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+    let body = &tokens[open + 1..close];
+    let closures = body.iter()
+        .any(|t| *t == Token::Ident("function") || *t == Token::Punct("=>"));
+    let params = declared(&tokens[i + 2..header]);
+    if !closures || (params.is_empty() && declared(body).is_empty()) {
+        return None
+    }
+    // Inside the functions in the body these mean the same once it's wrapped.
+    let mut j = open + 1;
+    while j < close {
+        match tokens[j] {
+            Token::Ident("function") => {
+                let body = (j..close).find(|&k| tokens[k] == Token::Punct("{"))?;
+                j = matching(body)?;
+            }
+            Token::Ident("break") | Token::Ident("continue") | Token::Ident("return") |
+            Token::Ident("this") | Token::Ident("arguments") => return None,
+            _ => {}
+        }
+        j += 1;
+    }
+    Some((open, close, params.join(", ")))
+}
+
+/// Re-indents generated JS (or TypeScript) by its bracket nesting and
+/// normalizes blank lines.
+///
+/// The glue is stitched together from `format!` fragments each indented however
+/// the Rust source around it happened to be, so rather than keeping all of
+/// those in sync the final output is laid out again here: four spaces per
+/// level of nesting, at most one blank line in a row and none at the start or
+/// end of a block.
+pub fn reindent(s: &str) -> String {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Code,
+        Str(char),
+        Template,
+        BlockComment,
+    }
+
+    let mut ret = String::new();
+    // Indentation of the line that opened each bracket that's still open.
+    let mut open: Vec<usize> = Vec::new();
+    let mut state = State::Code;
+    let mut braceless_body = false;
+    let mut pending_blank = false;
+    let mut after_open = true;
+
+    for raw in s.lines() {
+        // Whitespace inside a multi-line template literal is significant, so
+        // such lines are passed through untouched.
+        let in_template = state == State::Template;
+        let in_comment = state == State::BlockComment;
+        let line = raw.trim();
+        if in_template {
+            ret.push_str(raw);
+            ret.push('\n');
+        } else if line.is_empty() {
+            pending_blank = true;
+            continue
+        }
+
+        // Find the brackets on this line outside of strings and comments.
+        let mut code = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match state {
+                State::Code => match c {
+                    '/' if chars.peek() == Some(&'/') => break,
+                    '/' if chars.peek() == Some(&'*') => {
+                        chars.next();
+                        state = State::BlockComment;
+                        continue
+                    }
+                    '\'' | '"' => state = State::Str(c),
+                    '`' => state = State::Template,
+                    _ => {}
+                },
+                State::Str(_) | State::Template if c == '\\' => {
+                    chars.next();
+                    continue
+                }
+                State::Str(q) => {
+                    if c == q {
+                        state = State::Code;
+                    }
+                    continue
+                }
+                State::Template => {
+                    if c == '`' {
+                        state = State::Code;
+                    }
+                    continue
+                }
+                State::BlockComment => {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        state = State::Code;
+                    }
+                    continue
+                }
+            }
+            code.push(c);
+        }
+        if let State::Str(_) = state {
+            state = State::Code;
+        }
+        if in_template {
+            continue
+        }
+        let code = code.trim();
+        let brackets = code.chars()
+            .filter(|&c| is_opener(c) || is_closer(c))
+            .collect::<Vec<_>>();
+        let leading_closes = code.chars()
+            .take_while(|&c| is_closer(c) || c == ' ')
+            .filter(|&c| is_closer(c))
+            .count();
+
+        let mut indent = open.last().map(|i| i + 1).unwrap_or(0);
+        for _ in 0..leading_closes {
+            if let Some(i) = open.pop() {
+                indent = i;
+            }
+        }
+        let continuation = code.starts_with('.') ||
+            code.starts_with("? ") ||
+            code.starts_with(": ");
+        if braceless_body || continuation {
+            indent += 1;
+        }
+
+        if pending_blank && !after_open && leading_closes == 0 {
+            ret.push('\n');
+        }
+        pending_blank = false;
+        for _ in 0..indent {
+            ret.push_str("    ");
+        }
+        if in_comment && line.starts_with('*') {
+            ret.push(' ');
+        }
+        ret.push_str(line);
+        ret.push('\n');
+
+        after_open = false;
+        for &c in brackets[leading_closes..].iter() {
+            if is_opener(c) {
+                open.push(indent);
+                after_open = true;
+            } else {
+                open.pop();
+                after_open = false;
+            }
+        }
+
+        // `if (x)` and friends without braces only indent the next line.
+        braceless_body = ["if ", "if(", "for ", "while ", "} else if "]
+            .iter()
+            .any(|p| code.starts_with(p)) || code == "else";
+        braceless_body &= !code.ends_with('{') && !code.ends_with(';') &&
+            !code.ends_with('}');
+    }
+    ret
+}
+
+fn is_opener(c: char) -> bool {
+    c == '{' || c == '(' || c == '['
+}
+
+fn is_closer(c: char) -> bool {
+    c == '}' || c == ')' || c == ']'
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::var_declarations;
+
+    #[test]
+    fn closures_in_loops() {
+        let js = var_declarations("
+            function callbacks(names) {
+                const ret = [];
+                for (let i = 0; i < names.length; i++) {
+                    const name = names[i];
+                    ret.push(function() { return i + ':' + name; });
+                }
+                let j = 0;
+                while (j < names.length) {
+                    let k = j++;
+                    ret.push(() => k);
+                }
+                return ret;
+            }
+            function firstNegative(values) {
+                for (let i = 0; i < values.length; i++) {
+                    const value = values[i];
+                    if (value < 0)
+                        return function() { return value; };
+                }
+            }
+        ");
+        assert!(!js.contains("let ") && !js.contains("const "), "{}", js);
+        let output = Command::new("node")
+            .arg("-e")
+            .arg(format!("{}
+                var assert = require('assert');
+                var called = callbacks(['a', 'b']).map(function(f) {{ return f(); }});
+                assert.deepStrictEqual(called, ['0:a', '1:b', 0, 1]);
+                assert.strictEqual(firstNegative([1, -2, -3])(), -2);
+            ", js))
+            .output()
+            .expect("failed to spawn `node`");
+        assert!(output.status.success(), "{}\n{}", js, String::from_utf8_lossy(&output.stderr));
+    }
+}
@@ -1,7 +1,8 @@
 use super::Context;
-use descriptor::{Descriptor, Function};
+use descriptor::{Descriptor, Function, VectorKind};
 
-use super::{indent, Js2Rust};
+use super::{externref, indent, Js2Rust};
+use super::conversion::{self, Cleanup, Conversion};
 
 /// Helper struct for manfuacturing a shim in JS used to translate Rust types to
 /// JS, then invoking an imported JS function.
@@ -12,16 +13,16 @@ pub struct Rust2Js<'a, 'b: 'a> {
     /// from Rust which are only numbers.
     shim_arguments: Vec<String>,
 
-    /// Arguments which are forwarded to the imported JS function
-    js_arguments: Vec<String>,
+    /// How each argument is forwarded to the imported JS function, in order,
+    /// such as by wrapping a function pointer in a JS function first.
+    conversions: Vec<Conversion>,
 
-    /// Conversions that happen before we invoke the wasm function, such as
-    /// converting a string to a ptr/length pair.
-    prelude: String,
+    /// The TypeScript types of `js_arguments`, where a variadic last argument
+    /// is the type of the array its elements are spread from.
+    pub ts_arguments: Vec<String>,
 
-    /// "Destructors" or cleanup that must happen after the wasm function
-    /// finishes. This is scheduled in a `finally` block.
-    finally: String,
+    /// The TypeScript type the imported JS function must return.
+    pub ret_ty: String,
 
     /// Next global index to write to when passing arguments via the single
     /// global stack.
@@ -36,23 +37,47 @@ pub struct Rust2Js<'a, 'b: 'a> {
 
     /// Whether or not we're catching JS exceptions
     catch: bool,
+
+    /// Whether the last argument is spread into the JS invocation
+    variadic: bool,
+
+    /// Whether any 64-bit integers are passed as two 32-bit halves, which
+    /// the wasm import is rewritten to do, see `Bindgen::split_i64`.
+    pub splits_i64: bool,
+
+    /// Whether `JsValue`s are passed as they are, as `externref`s which the
+    /// wasm import is rewritten to take and return, see
+    /// `Bindgen::reference_types`, along with which are.
+    externrefs: bool,
+    externref_signature: externref::Signature,
 }
 
 impl<'a, 'b> Rust2Js<'a, 'b> {
     pub fn new(cx: &'a mut Context<'b>) -> Rust2Js<'a, 'b> {
+        let externrefs = cx.config.reference_types;
         Rust2Js {
             cx,
             shim_arguments: Vec::new(),
-            js_arguments: Vec::new(),
-            prelude: String::new(),
-            finally: String::new(),
+            conversions: Vec::new(),
+            ts_arguments: Vec::new(),
+            ret_ty: String::new(),
             global_idx: 0,
             arg_idx: 0,
             ret_expr: String::new(),
             catch: false,
+            variadic: false,
+            splits_i64: false,
+            externrefs,
+            externref_signature: externref::Signature::default(),
         }
     }
 
+    /// Which of the values the wasm import takes and returns are
+    /// `externref`s, none unless `Bindgen::reference_types` is set.
+    pub fn externref_signature(&self) -> &externref::Signature {
+        &self.externref_signature
+    }
+
     pub fn catch(&mut self, catch: bool) -> &mut Self {
         if catch {
             self.cx.expose_uint32_memory();
@@ -62,97 +87,157 @@ impl<'a, 'b> Rust2Js<'a, 'b> {
         self
     }
 
+    pub fn variadic(&mut self, variadic: bool) -> &mut Self {
+        self.variadic = variadic;
+        self
+    }
+
     /// Generates all bindings necessary for the signature in `Function`,
     /// creating necessary argument conversions and return value processing.
-    pub fn process(&mut self, function: &Function) -> &mut Self {
+    pub fn process(&mut self, function: &Function) -> Result<&mut Self, String> {
         for arg in function.arguments.iter() {
-            self.argument(arg);
+            self.argument(arg)?;
         }
-        self.ret(&function.ret);
-        self
+        if self.variadic {
+            let kind = function.arguments.last().and_then(|a| a.vector_kind());
+            if let (Some(kind), Some(ty)) = (kind, self.ts_arguments.last_mut()) {
+                *ty = match kind {
+                    VectorKind::String => "string[]",
+                    VectorKind::Anyref => "any[]",
+                    _ => "number[]",
+                }.to_string();
+            }
+        }
+        self.ret(&function.ret)?;
+        Ok(self)
     }
 
-    fn argument(&mut self, arg: &Descriptor) {
+    fn argument(&mut self, arg: &Descriptor) -> Result<(), String> {
         let i = self.arg_idx;
         self.arg_idx += 1;
 
         self.shim_arguments.push(format!("arg{}", i));
 
+        if let Some(inner) = arg.optional() {
+            return self.optional_argument(i, inner)
+        }
+
         if let Some(ty) = arg.vector_kind() {
-            let f = self.cx.expose_get_vector_from_wasm(ty);
+            // Borrowed slices of numbers are lent to JS as a view of wasm's
+            // memory rather than a copy, which `&mut` ones are written through.
+            let view = match ty {
+                VectorKind::String | VectorKind::Anyref => false,
+                _ => arg.is_by_ref(),
+            };
+            let get = if view {
+                format!("new {}(wasm.memory.buffer, arg{1}, len{1})", ty.js_ty(), i)
+            } else {
+                let f = if arg.is_by_ref() {
+                    self.cx.expose_get_vector_from_wasm(ty)
+                } else {
+                    self.cx.expose_take_vector_from_wasm(ty)
+                };
+                format!("{}(arg{1}, len{1})", f, i)
+            };
             self.cx.expose_get_global_argument();
             let next_global = self.global_idx();
-            self.prelude(&format!("\
+            let mut prologue = format!("\
                 let len{0} = getGlobalArgument({next_global});\n\
-                let v{0} = {func}(arg{0}, len{0});\n\
-            ", i, func = f, next_global = next_global));
+                let v{0} = {get};\n\
+            ", i, get = get, next_global = next_global);
 
             if !arg.is_by_ref() {
-                self.prelude(&format!("\
-                    wasm.__wbindgen_free(arg{0}, len{0} * {size});\
+                prologue.push_str(&format!("\
+                    wasm.__wbindgen_free(arg{0}, len{0} * {size});\n\
                 ", i, size = ty.size()));
                 self.cx.require_internal_export("__wbindgen_free");
             }
-            self.js_arguments.push(format!("v{}", i));
-            return
+            self.argument_from(&prologue, format!("v{}", i), Cleanup::None);
+            self.ts_arguments.push(ty.js_ty().to_string());
+            return Ok(())
         }
 
         if let Some(class) = arg.rust_struct() {
             if arg.is_by_ref() {
-                panic!("cannot invoke JS functions with custom ref types yet")
+                return Err("cannot invoke JS functions with custom ref types yet".to_string())
             }
             let assign = format!("let c{0} = {1}.__construct(arg{0});", i, class);
-            self.prelude(&assign);
-            self.js_arguments.push(format!("c{}", i));
-            return
+            self.argument_from(&assign, format!("c{}", i), Cleanup::None);
+            self.ts_arguments.push(class.to_string());
+            return Ok(())
+        }
+
+        if let Some(d) = arg.dictionary() {
+            self.cx.expose_take_object();
+            self.conversions.push(Conversion::arg(&format!("takeObject(arg{})", i)));
+            let ty = self.cx.expose_dictionary(d)?;
+            self.ts_arguments.push(ty);
+            return Ok(())
+        }
+
+        if let Some(e) = arg.string_enum() {
+            let values = self.cx.expose_string_enum(e);
+            self.conversions.push(Conversion::arg(&format!("{}[arg{}]", values, i)));
+            self.ts_arguments.push(e.js_ty());
+            return Ok(())
         }
 
         if let Some((f, mutable)) = arg.stack_closure() {
-            let (js, _ts) = {
+            let (js, ts) = {
                 let mut builder = Js2Rust::new("", self.cx);
                 if mutable {
-                    builder.prelude("let a = this.a;\n")
-                        .prelude("this.a = 0;\n")
-                        .rust_argument("a")
-                        .finally("this.a = a;\n");
+                    let a = builder.local("a");
+                    builder.conversion(mutable_closure_state(&a));
                 } else {
                     builder.rust_argument("this.a");
                 }
                 builder
+                    .through_table()
                     .rust_argument("this.b")
-                    .process(f)
-                    .finish("function", "this.f")
+                    .process(f)?;
+                (builder.finish("function", "this.f").0, closure_ty(&builder))
             };
             self.cx.expose_get_global_argument();
             self.cx.function_table_needed = true;
             let next_global = self.global_idx();
             self.global_idx();
-            self.prelude(&format!("\
+            let prologue = format!("\
                 let cb{0} = {js};\n\
                 cb{0}.f = wasm.__wbg_function_table.get(arg{0});\n\
                 cb{0}.a = getGlobalArgument({next_global});\n\
                 cb{0}.b = getGlobalArgument({next_global} + 1);\n\
-            ", i, js = js, next_global = next_global));
-            self.finally(&format!("cb{0}.a = cb{0}.b = 0;", i));
-            self.js_arguments.push(format!("cb{0}.bind(cb{0})", i));
-            return
+            ", i, js = js, next_global = next_global);
+            let invalidate = format!("cb{0}.a = cb{0}.b = 0;", i);
+            self.argument_from(&prologue, format!("cb{0}.bind(cb{0})", i),
+                               Cleanup::Finally(invalidate));
+            self.ts_arguments.push(ts);
+            return Ok(())
+        }
+
+        if let Some(f) = arg.fn_pointer() {
+            self.cx.expose_get_global_argument();
+            let ptr = format!("getGlobalArgument({})", self.global_idx());
+            let (js, ts) = self.cx.fn_pointer(f, &format!("arg{}", i), &ptr)?;
+            let prologue = format!("const fp{} = {};\n", i, js);
+            self.argument_from(&prologue, format!("fp{}", i), Cleanup::None);
+            self.ts_arguments.push(ts);
+            return Ok(())
         }
 
         if let Some(closure) = arg.ref_closure() {
-            let (js, _ts) = {
+            let (js, ts) = {
                 let mut builder = Js2Rust::new("", self.cx);
                 if closure.mutable {
-                    builder.prelude("let a = this.a;\n")
-                        .prelude("this.a = 0;\n")
-                        .rust_argument("a")
-                        .finally("this.a = a;\n");
+                    let a = builder.local("a");
+                    builder.conversion(mutable_closure_state(&a));
                 } else {
                     builder.rust_argument("this.a");
                 }
                 builder
+                    .through_table()
                     .rust_argument("this.b")
-                    .process(&closure.function)
-                    .finish("function", "this.f")
+                    .process(&closure.function)?;
+                (builder.finish("function", "this.f").0, closure_ty(&builder))
             };
             self.cx.expose_get_global_argument();
             self.cx.expose_uint32_memory();
@@ -173,67 +258,230 @@ impl<'a, 'b> Rust2Js<'a, 'b> {
                 b = self.global_idx(),
                 c = self.global_idx(),
             );
-            self.prelude(&format!("\
+            let prologue = format!("\
                 let idx{0} = getUint32Memory()[arg{0} / 4];\n\
                 if (idx{0} === 0xffffffff) {{\n\
                 {1}\
                 }}\n\
-            ", i, indent(&reset_idx)));
+            ", i, indent(&reset_idx));
             self.cx.expose_get_object();
-            self.js_arguments.push(format!("getObject(idx{})", i));
-            return
+            self.argument_from(&prologue, format!("getObject(idx{})", i), Cleanup::None);
+            self.ts_arguments.push(ts);
+            return Ok(())
+        }
+
+        // The high half of a split 64-bit integer comes right after the low
+        // one, signed unless it's a `u64`.
+        if arg.is_64bit() && self.cx.config.split_i64 {
+            self.shim_arguments.push(format!("high{}", i));
+            self.splits_i64 = true;
+            let high = match *arg {
+                Descriptor::U64 => format!("(high{} >>> 0)", i),
+                _ => format!("high{}", i),
+            };
+            let invoc_arg = format!("{} * 4294967296 + (arg{} >>> 0)", high, i);
+            self.conversions.push(Conversion::arg(&invoc_arg));
+            self.ts_arguments.push("number".to_string());
+            return Ok(())
         }
 
-        let invoc_arg = match *arg {
-            ref d if d.is_number() => format!("arg{}", i),
-            Descriptor::Boolean => format!("arg{} !== 0", i),
+        let (invoc_arg, ts) = match *arg {
+            Descriptor::I64 => (format!("arg{}", i), "bigint"),
+            Descriptor::U64 => (format!("BigInt.asUintN(64, arg{})", i), "bigint"),
+            ref d if d.is_number() => (format!("arg{}", i), "number"),
+            Descriptor::Boolean => (format!("arg{} !== 0", i), "boolean"),
+            Descriptor::Anyref if self.externrefs => {
+                let pos = self.shim_arguments.len() - 1;
+                self.externref_signature.params.push((pos, true));
+                (format!("arg{}", i), "any")
+            }
+            ref d if d.is_ref_anyref() && self.externrefs => {
+                let pos = self.shim_arguments.len() - 1;
+                self.externref_signature.params.push((pos, false));
+                (format!("arg{}", i), "any")
+            }
             Descriptor::Anyref => {
                 self.cx.expose_take_object();
-                format!("takeObject(arg{})", i)
+                (format!("takeObject(arg{})", i), "any")
             }
             ref d if d.is_ref_anyref() => {
                 self.cx.expose_get_object();
-                format!("getObject(arg{})", i)
+                (format!("getObject(arg{})", i), "any")
+            }
+            _ => {
+                return Err(format!("unimplemented argument type in imported function: {:?}",
+                                   arg))
             }
-            _ => panic!("unimplemented argument type in imported function: {:?}", arg),
         };
-        self.js_arguments.push(invoc_arg);
+        self.conversions.push(Conversion::arg(&invoc_arg));
+        self.ts_arguments.push(ts.to_string());
+        Ok(())
     }
 
-    fn ret(&mut self, ret: &Option<Descriptor>) {
+    /// Arguments which are `Option`s of strings, vectors or exported
+    /// classes, whose `None` is a null pointer and `undefined` in JS. Numbers
+    /// come with whether they're `Some` in the global argument, and JS values
+    /// are `undefined` for `None` already.
+    fn optional_argument(&mut self, i: usize, arg: &Descriptor) -> Result<(), String> {
+        if arg.is_number() && !arg.is_64bit() {
+            self.cx.expose_get_global_argument();
+            let invoc_arg = format!("getGlobalArgument({}) === 0 ? undefined : arg{}",
+                                    self.global_idx(), i);
+            self.conversions.push(Conversion::arg(&invoc_arg));
+            self.ts_arguments.push("number | undefined".to_string());
+            return Ok(())
+        }
+
+        if arg.is_anyref() {
+            self.cx.expose_take_object();
+            self.conversions.push(Conversion::arg(&format!("takeObject(arg{})", i)));
+            self.ts_arguments.push("any".to_string());
+            return Ok(())
+        }
+
+        if let Some(ty) = arg.vector_kind() {
+            let (f, free) = if arg.is_by_ref() {
+                (self.cx.expose_get_vector_from_wasm(ty), String::new())
+            } else {
+                self.cx.require_internal_export("__wbindgen_free");
+                (self.cx.expose_take_vector_from_wasm(ty),
+                 format!("wasm.__wbindgen_free(arg{0}, len{0} * {1});\n", i, ty.size()))
+            };
+            self.cx.expose_get_global_argument();
+            let next_global = self.global_idx();
+            let prologue = format!("\
+                let len{0} = getGlobalArgument({next_global});\n\
+                let v{0};\n\
+                if (arg{0} !== 0) {{\n\
+                    v{0} = {func}(arg{0}, len{0});\n\
+                    {free}\
+                }}\n\
+            ", i, func = f, next_global = next_global, free = free);
+            self.argument_from(&prologue, format!("v{}", i), Cleanup::None);
+            self.ts_arguments.push(format!("{} | undefined", ty.js_ty()));
+            return Ok(())
+        }
+
+        if let (Some(class), false) = (arg.rust_struct(), arg.is_by_ref()) {
+            let assign = format!("let c{0} = arg{0} === 0 ? undefined : {1}.__construct(arg{0});",
+                                 i, class);
+            self.argument_from(&assign, format!("c{}", i), Cleanup::None);
+            self.ts_arguments.push(format!("{} | undefined", class));
+            return Ok(())
+        }
+
+        Err(format!("unimplemented optional argument type in imported function: {:?}", arg))
+    }
+
+    fn ret(&mut self, ret: &Option<Descriptor>) -> Result<(), String> {
         let ty = match *ret {
             Some(ref t) => t,
             None => {
                 self.ret_expr = "JS;".to_string();
-                return
+                self.ret_ty = "void".to_string();
+                return Ok(())
             }
         };
         if ty.is_by_ref() {
-            panic!("cannot return a reference from JS to Rust")
+            return Err("cannot return a reference from JS to Rust".to_string())
+        }
+        // `undefined` and `null` are both `None`, passed as a null pointer.
+        // Numbers leave whether they're `Some` in the global argument, and
+        // JS values have indices of their own for `undefined` and `null`.
+        match ty.optional() {
+            Some(t) if t.is_number() && !t.is_64bit() => {
+                self.cx.expose_set_global_argument();
+                self.ret_expr = "\
+                    const ret = JS;\n\
+                    setGlobalArgument(ret == null ? 0 : 1, 0);\n\
+                    return ret == null ? 0 : ret;\n\
+                ".to_string();
+                self.ret_ty = "number | undefined".to_string();
+                return Ok(())
+            }
+            Some(t) if t.is_anyref() => {
+                self.cx.expose_add_heap_object();
+                self.ret_expr = "return addHeapObject(JS);".to_string();
+                self.ret_ty = "any".to_string();
+                return Ok(())
+            }
+            _ => {}
+        }
+        if let Some(kind) = ty.optional().and_then(|t| t.vector_kind()) {
+            let f = self.cx.pass_to_wasm_function(kind);
+            self.cx.expose_set_global_argument();
+            let pass = self.cx.destructure(&["retptr", "retlen"], "pass", &format!("{}(ret)", f));
+            self.ret_expr = format!("\
+                const ret = JS;\n\
+                if (ret == null) {{\n\
+                    setGlobalArgument(0, 0);\n\
+                    return 0;\n\
+                }}\n\
+                {}\
+                setGlobalArgument(retlen, 0);\n\
+                return retptr;\n\
+            ", pass);
+            self.ret_ty = format!("{} | undefined", kind.js_arg_ty());
+            return Ok(())
         }
         if let Some(ty) = ty.vector_kind() {
             let f = self.cx.pass_to_wasm_function(ty);
             self.cx.expose_uint32_memory();
             self.cx.expose_set_global_argument();
+            let pass = self.cx.destructure(&["retptr", "retlen"], "ret", &format!("{}(JS)", f));
             self.ret_expr = format!("\
-                const [retptr, retlen] = {}(JS);\n\
+                {}\
                 setGlobalArgument(retlen, 0);\n\
                 return retptr;\n\
-            ", f);
-            return
+            ", pass);
+            self.ret_ty = ty.js_arg_ty();
+            return Ok(())
+        }
+        if let Some(e) = ty.string_enum() {
+            let idx = self.cx.string_enum_index(e, "ret", "return value");
+            self.ret_expr = format!("\
+                const ret = JS;\n\
+                return {};\n\
+            ", idx);
+            self.ret_ty = e.js_ty();
+            return Ok(())
+        }
+        if ty.is_64bit() && self.cx.config.split_i64 {
+            self.cx.expose_set_global_argument();
+            self.splits_i64 = true;
+            self.ret_expr = "\
+                const ret = JS;\n\
+                setGlobalArgument(Math.floor(ret / 4294967296), 0);\n\
+                return ret >>> 0;\n\
+            ".to_string();
+            self.ret_ty = "number".to_string();
+            return Ok(())
+        }
+        if ty.is_64bit() {
+            self.ret_expr = "return JS;".to_string();
+            self.ret_ty = "bigint".to_string();
+            return Ok(())
         }
         if ty.is_number() {
             self.ret_expr = "return JS;".to_string();
-            return
+            self.ret_ty = "number".to_string();
+            return Ok(())
         }
-        self.ret_expr = match *ty {
-            Descriptor::Boolean => "return JS ? 1 : 0;".to_string(),
+        let (ret_expr, ret_ty) = match *ty {
+            Descriptor::Boolean => ("return JS ? 1 : 0;", "boolean"),
+            Descriptor::Anyref if self.externrefs => {
+                self.externref_signature.ret = true;
+                ("return JS;", "any")
+            }
             Descriptor::Anyref => {
                 self.cx.expose_add_heap_object();
-                "return addHeapObject(JS);".to_string()
+                ("return addHeapObject(JS);", "any")
             }
-            _ => panic!("unimplemented return from JS to Rust: {:?}", ty),
-        }
+            _ => return Err(format!("unimplemented return from JS to Rust: {:?}", ty)),
+        };
+        self.ret_expr = ret_expr.to_string();
+        self.ret_ty = ret_ty.to_string();
+        Ok(())
     }
 
     pub fn finish(&self, invoc: &str) -> String {
@@ -247,62 +495,116 @@ impl<'a, 'b> Rust2Js<'a, 'b> {
             ret.push_str("exnptr");
         }
         ret.push_str(") {\n");
-        ret.push_str(&indent(&self.prelude));
 
-        let mut invoc = self.ret_expr.replace(
-            "JS",
-            &format!("{}({})", invoc, self.js_arguments.join(", ")),
-        );
+        let mut js_arguments = self.conversions.iter()
+            .flat_map(|c| c.args.iter().cloned())
+            .collect::<Vec<_>>();
+        let call = if self.variadic && self.cx.es5() {
+            // Without spread syntax the call goes through `apply`, with the
+            // trailing slice copied into a real array for `concat`.
+            let rest = js_arguments.pop()
+                .expect("variadic imports must have at least one argument");
+            let list = |fixed: &[String]| {
+                format!("[{}].concat(Array.prototype.slice.call({}))", fixed.join(", "), rest)
+            };
+            if let Some(class) = invoc.strip_prefix("new ") {
+                let mut fixed = vec![String::from("null")];
+                fixed.extend(js_arguments);
+                format!("new (Function.prototype.bind.apply({}, {}))()", class, list(&fixed))
+            } else if let Some(function) = invoc.strip_suffix(".call") {
+                let (this, fixed) = js_arguments.split_first()
+                    .expect("methods must have a receiver");
+                format!("{}.apply({}, {})", function, this, list(fixed))
+            } else {
+                format!("{}.apply(null, {})", invoc, list(&js_arguments))
+            }
+        } else {
+            if self.variadic {
+                let rest = js_arguments.pop()
+                    .expect("variadic imports must have at least one argument");
+                js_arguments.push(format!("...{}", rest));
+            }
+            format!("{}({})", invoc, js_arguments.join(", "))
+        };
+        let mut invoc = self.ret_expr.replacen("JS", &call, 1);
+        if !invoc.ends_with('\n') {
+            invoc.push('\n');
+        }
         if self.catch {
-            let catch = "\
+            // What's returned after an exception isn't looked at, though a
+            // JS value returned would be stored in the table regardless,
+            // unless it's `null`.
+            let mut catch = "\
                 const view = getUint32Memory();\n\
                 view[exnptr / 4] = 1;\n\
                 view[exnptr / 4 + 1] = addHeapObject(e);\n\
-            ";
+            ".to_string();
+            if self.externref_signature.ret {
+                catch.push_str("return null;\n");
+            }
 
             invoc = format!("\
             try {{\n\
             {}\
             }} catch (e) {{\n\
             {}\
-            }}\
-            ", indent(&invoc), indent(catch));
+            }}\n\
+            ", indent(&invoc), indent(&catch));
         };
-
-        if self.finally.len() > 0 {
-            invoc = format!("\
-            try {{\n\
-            {}\
-            }} finally {{\n\
-            {}\
-            }}\
-            ", indent(&invoc), indent(&self.finally));
-        }
-        ret.push_str(&indent(&invoc));
+        // Nothing passed to JS is moved, so nothing needs `called`.
+        ret.push_str(&indent(&conversion::render(&self.conversions, &invoc, "called")));
 
         ret.push_str("}\n");
         return ret
     }
 
+    /// The TypeScript signature, `(arg0: T, ...): R`, the imported JS
+    /// function must have, leaving out its receiver for `method`s.
+    pub fn ts_signature(&self, method: bool) -> String {
+        let skip = if method { 1 } else { 0 };
+        let last = self.ts_arguments.len().saturating_sub(1);
+        let args = self.ts_arguments.iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(i, ty)| {
+                let spread = if self.variadic && i == last { "..." } else { "" };
+                format!("{}arg{}: {}", spread, i - skip, ty)
+            })
+            .collect::<Vec<_>>();
+        format!("({}): {}", args.join(", "), self.ret_ty)
+    }
+
     fn global_idx(&mut self) -> usize {
         let ret = self.global_idx;
         self.global_idx += 1;
         ret
     }
 
-    fn prelude(&mut self, s: &str) -> &mut Self {
-        for line in s.lines() {
-            self.prelude.push_str(line);
-            self.prelude.push_str("\n");
-        }
-        self
+    /// Pass `arg` to the JS function once `prologue` has defined it.
+    fn argument_from(&mut self, prologue: &str, arg: String, cleanup: Cleanup) {
+        self.conversions.push(Conversion {
+            prologue: conversion::lines(prologue),
+            args: vec![arg],
+            cleanup,
+        });
     }
+}
 
-    fn finally(&mut self, s: &str) -> &mut Self {
-        for line in s.lines() {
-            self.finally.push_str(line);
-            self.finally.push_str("\n");
-        }
-        self
+/// The state of a mutable closure called from JS, which is taken out of
+/// `this` into the local `a` for the duration of the call, so that Rust
+/// refuses to run the closure recursively, and put back afterwards.
+fn mutable_closure_state(a: &str) -> Conversion {
+    Conversion {
+        prologue: format!("let {} = this.a;\nthis.a = 0;\n", a),
+        args: vec![a.to_string()],
+        cleanup: Cleanup::Finally(format!("this.a = {};", a)),
     }
 }
+
+/// The TypeScript function type of the JS closure `builder` generates.
+pub fn closure_ty(builder: &Js2Rust) -> String {
+    let args = builder.js_arguments.iter()
+        .map(|(name, ty)| format!("{}: {}", name, ty))
+        .collect::<Vec<_>>();
+    format!("({}) => {}", args.join(", "), builder.ret_ty)
+}
@@ -0,0 +1,259 @@
+//! Source maps of the generated JS glue, see `Bindgen::source_map`.
+//!
+//! Each shim is mapped as a whole to where the `#[wasm_bindgen]` macro
+//! recorded the Rust item it's generated for, so that stack traces through
+//! the glue point at the Rust code. Minifying and splitting the glue move the
+//! shims around, so they're found by name in the final JS.
+
+use std::collections::BTreeMap;
+
+use serde_json;
+use shared;
+
+use super::js_identifier;
+use super::tokens::{tokenize, Token};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Serialize)]
+struct SourceMap<'a> {
+    version: u32,
+    file: &'a str,
+    sources: Vec<&'a str>,
+    names: Vec<&'a str>,
+    mappings: String,
+}
+
+/// Where the Rust items of `programs` are, by the name the JS defining their
+/// shim gives them: `foo` for an exported function or the shim of an import,
+/// and `Foo.foo` for a method of the class `Foo`.
+pub fn locations(programs: &[shared::Program]) -> BTreeMap<String, &shared::Location> {
+    let mut ret = BTreeMap::new();
+    for program in programs.iter() {
+        for export in program.exports.iter() {
+            let location = match export.function.location {
+                Some(ref location) => location,
+                None => continue,
+            };
+            let name = match export.class {
+                Some(ref class) => format!("{}.{}", class, export.function.name),
+                None => js_identifier(&export.function.name),
+            };
+            ret.insert(name, location);
+        }
+        for import in program.imports.iter() {
+            if let shared::ImportKind::Function(ref f) = import.kind {
+                if let Some(ref location) = f.function.location {
+                    ret.insert(f.shim.clone(), location);
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// The source map of the JS `js` of the file `file`, mapping the shims
+/// defined in it to their `locations`.
+pub fn source_map(js: &str, file: &str, locations: &BTreeMap<String, &shared::Location>)
+    -> String
+{
+    // Where each line starts, and the first column of each with any code.
+    let mut starts = vec![0];
+    starts.extend(js.match_indices('\n').map(|(i, _)| i + 1));
+    let position = |offset: usize| {
+        let line = match starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        (line, offset - starts[line])
+    };
+    let indentation = |line: usize| {
+        let rest = &js[starts[line]..];
+        let rest = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        rest.find(|c: char| !c.is_whitespace())
+    };
+
+    let mut segments = Vec::new();
+    let mut sources: Vec<&str> = Vec::new();
+    for (start, end, location) in shims(js, locations) {
+        let source = match sources.iter().position(|s| *s == location.file) {
+            Some(i) => i,
+            None => {
+                sources.push(&location.file);
+                sources.len() - 1
+            }
+        };
+        let target = Some((source, location.line as usize - 1, location.column as usize));
+        let (first, col) = position(start);
+        let (last, end_col) = position(end);
+        segments.push((first, col, target));
+        for line in first + 1..last + 1 {
+            if let Some(col) = indentation(line) {
+                if line < last || col < end_col {
+                    segments.push((line, col, target));
+                }
+            }
+        }
+        // Whatever follows the shim isn't part of it.
+        segments.push((last, end_col, None));
+    }
+
+    let mut mappings = String::new();
+    let mut line = 0;
+    let mut prev_col = 0;
+    let mut prev = (0, 0, 0);
+    for (i, &(l, col, target)) in segments.iter().enumerate() {
+        // A later segment at the same position overrides this one.
+        if segments.get(i + 1).map(|s| (s.0, s.1)) == Some((l, col)) {
+            continue
+        }
+        if l > line {
+            for _ in line..l {
+                mappings.push(';');
+            }
+            line = l;
+            prev_col = 0;
+        } else if !mappings.is_empty() && !mappings.ends_with(';') {
+            mappings.push(',');
+        }
+        vlq(col as i64 - prev_col as i64, &mut mappings);
+        prev_col = col;
+        if let Some((source, src_line, src_col)) = target {
+            vlq(source as i64 - prev.0 as i64, &mut mappings);
+            vlq(src_line as i64 - prev.1 as i64, &mut mappings);
+            vlq(src_col as i64 - prev.2 as i64, &mut mappings);
+            prev = (source, src_line, src_col);
+        }
+    }
+
+    let map = SourceMap {
+        version: 3,
+        file,
+        sources,
+        names: Vec::new(),
+        mappings,
+    };
+    serde_json::to_string(&map).unwrap() + "\n"
+}
+
+/// The shims of `locations` defined in `js`, as the offsets of their name
+/// and of the end of their body, in order.
+fn shims<'a>(js: &str, locations: &BTreeMap<String, &'a shared::Location>)
+    -> Vec<(usize, usize, &'a shared::Location)>
+{
+    let tokens = tokenize(js);
+    let offset = |s: &str| s.as_ptr() as usize - js.as_ptr() as usize;
+    let mut ret = Vec::new();
+    // The classes whose body is open, with the brace depth outside of it.
+    let mut classes: Vec<(&str, usize)> = Vec::new();
+    let mut class_next = None;
+    let mut depth = 0;
+    for (i, &token) in tokens.iter().enumerate() {
+        let prev = if i > 0 { tokens[i - 1] } else { Token::Newline };
+        let next = tokens.get(i + 1).cloned().unwrap_or(Token::Newline);
+        let name = match token {
+            Token::Punct("{") => {
+                if let Some(class) = class_next.take() {
+                    classes.push((class, depth));
+                }
+                depth += 1;
+                continue
+            }
+            Token::Punct("}") => {
+                depth -= 1;
+                if classes.last().map(|c| c.1) == Some(depth) {
+                    classes.pop();
+                }
+                continue
+            }
+            Token::Ident(s) if prev == Token::Ident("class") => {
+                class_next = Some(s);
+                continue
+            }
+            // `function foo(`
+            Token::Ident(s) if prev == Token::Ident("function") => s.to_string(),
+            // `foo(` directly in the body of a class.
+            Token::Ident(s) if next == Token::Punct("(") &&
+                classes.last().map(|c| c.1 + 1) == Some(depth) =>
+            {
+                format!("{}.{}", classes.last().unwrap().0, s)
+            }
+            // `a.b.foo = function(`, for ES5 classes and CommonJS exports.
+            Token::Ident(s) if prev == Token::Punct(".") && next == Token::Punct("=") &&
+                tokens.get(i + 2) == Some(&Token::Ident("function")) =>
+            {
+                match member_of(&tokens[..i - 1]) {
+                    Some(class) => format!("{}.{}", class, s),
+                    None => s.to_string(),
+                }
+            }
+            _ => continue,
+        };
+        let location = match locations.get(&name) {
+            Some(location) => *location,
+            None => continue,
+        };
+        if let Some(end) = body_end(&tokens[i..]) {
+            let start = match token {
+                Token::Ident(s) => offset(s),
+                _ => unreachable!(),
+            };
+            ret.push((start, offset(end) + 1, location));
+        }
+    }
+    ret
+}
+
+/// The class whose member is assigned to by the path ending `tokens`, like
+/// `Foo` for `Foo` or `Foo.prototype`, or `None` for the exports object of
+/// a module.
+fn member_of<'a>(tokens: &[Token<'a>]) -> Option<&'a str> {
+    let mut path = Vec::new();
+    for token in tokens.iter().rev() {
+        match *token {
+            Token::Ident(s) => path.push(s),
+            Token::Punct(".") => {}
+            _ => break,
+        }
+    }
+    path.reverse();
+    match path.as_slice() {
+        ["module", "exports"] | ["__exports"] | ["exports"] => None,
+        [class, "prototype"] | [class] => Some(class),
+        _ => None,
+    }
+}
+
+/// The closing brace of the first block in `tokens`.
+fn body_end<'a>(tokens: &[Token<'a>]) -> Option<&'a str> {
+    let mut depth = 0;
+    for token in tokens.iter() {
+        match *token {
+            Token::Punct("{") => depth += 1,
+            Token::Punct(p @ "}") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(p)
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Appends `n` as a base64 VLQ.
+fn vlq(n: i64, dst: &mut String) {
+    let mut n = if n < 0 { ((-n as u64) << 1) | 1 } else { (n as u64) << 1 };
+    loop {
+        let mut digit = n & 31;
+        n >>= 5;
+        if n > 0 {
+            digit |= 32;
+        }
+        dst.push(BASE64[digit as usize] as char);
+        if n == 0 {
+            break
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! A tokenizer for the JS we generate, shared by the passes rewriting it.
+//!
+//! Like those passes it only needs to handle our own output, so for example
+//! a `/` is only taken to start a regular expression literal where a binary
+//! operator couldn't be, without tracking whether a `)` ends an `if (...)`.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Token<'a> {
+    Ident(&'a str),
+    Punct(&'a str),
+    /// Strings, numbers and pieces of template literals, emitted verbatim.
+    Literal(&'a str),
+    Newline,
+}
+
+/// Punctuation made of more than one character, longest first.
+const PUNCTS: &[&str] = &[
+    ">>>=", "...", "===", "!==", ">>>", "<<=", ">>=", "**=",
+    "=>", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "+=", "-=", "*=",
+    "/=", "%=", "&=", "|=", "^=", "<<", ">>", "**",
+];
+
+pub fn tokenize<'a>(js: &'a str) -> Vec<Token<'a>> {
+    let bytes = js.as_bytes();
+    let mut tokens = Vec::new();
+    // Brace depth at which each template literal's `${` currently open was
+    // opened, a `}` at that depth resumes the template.
+    let mut templates: Vec<usize> = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        if c == b'`' || (c == b'}' && templates.last() == Some(&depth)) {
+            if c == b'}' {
+                templates.pop();
+            }
+            i += 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'`' => {
+                        i += 1;
+                        break
+                    }
+                    b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                        i += 2;
+                        templates.push(depth);
+                        break
+                    }
+                    _ => i += 1,
+                }
+            }
+            tokens.push(Token::Literal(&js[start..i]));
+        } else if c == b'\n' {
+            if tokens.last().map(|t| *t != Token::Newline).unwrap_or(false) {
+                tokens.push(Token::Newline);
+            }
+            i += 1;
+        } else if c.is_ascii_whitespace() {
+            i += 1;
+        } else if js[i..].starts_with("//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if js[i..].starts_with("/*") {
+            i = match js[i + 2..].find("*/") {
+                Some(end) => i + 2 + end + 2,
+                None => bytes.len(),
+            };
+        } else if c == b'/' && regex_allowed(tokens.iter().rev().find(|t| **t != Token::Newline)) {
+            i += 1;
+            let mut class = false;
+            while i < bytes.len() && (class || bytes[i] != b'/') {
+                match bytes[i] {
+                    b'\\' => i += 1,
+                    b'[' => class = true,
+                    b']' => class = false,
+                    _ => {}
+                }
+                i += 1;
+            }
+            i += 1;
+            while i < bytes.len() && is_word(bytes[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Literal(&js[start..i]));
+        } else if c == b'\'' || c == b'"' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push(Token::Literal(&js[start..i]));
+        } else if c.is_ascii_digit() {
+            while i < bytes.len() && (is_word(bytes[i]) || bytes[i] == b'.') {
+                i += 1;
+            }
+            tokens.push(Token::Literal(&js[start..i]));
+        } else if is_word(c) {
+            while i < bytes.len() && is_word(bytes[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(&js[start..i]));
+        } else {
+            let len = PUNCTS.iter()
+                .find(|p| js[i..].starts_with(*p))
+                .map(|p| p.len())
+                .unwrap_or(1);
+            match c {
+                b'{' => depth += 1,
+                b'}' => depth -= 1,
+                _ => {}
+            }
+            i += len;
+            tokens.push(Token::Punct(&js[start..i]));
+        }
+    }
+    tokens
+}
+
+/// Whether a `/` after `prev` starts a regular expression literal, rather
+/// than being a division.
+fn regex_allowed(prev: Option<&Token>) -> bool {
+    match prev {
+        None => true,
+        Some(&Token::Punct(p)) => ![")", "]", "}", "++", "--"].contains(&p),
+        Some(&Token::Ident(s)) => {
+            ["return", "typeof", "case", "in", "of", "new", "delete", "void", "throw",
+             "instanceof", "else", "do", "yield", "await"].contains(&s)
+        }
+        Some(&Token::Literal(_)) | Some(&Token::Newline) => false,
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
@@ -1,210 +1,3395 @@
-extern crate parity_wasm;
+pub extern crate parity_wasm;
+extern crate rustc_demangle;
 extern crate wasm_bindgen_shared as shared;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
-extern crate wasm_gc;
 extern crate wasmi;
 
-use std::collections::BTreeSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
 use std::fmt;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::mem;
 use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use parity_wasm::elements::*;
 
 mod js;
 mod descriptor;
+mod gc;
+mod interpreter;
+mod wat;
+pub mod metadata;
 pub mod wasm2es6js;
 
 pub struct Bindgen {
-    path: Option<PathBuf>,
+    inputs: Vec<Input>,
+    /// Whether `input_path` or the like was called after an input was
+    /// already set, which generating reports.
+    input_set_twice: bool,
     nodejs: bool,
+    nodejs_async: bool,
     browser: bool,
+    bundler: bool,
+    deno: bool,
+    nodejs_module: bool,
+    umd: bool,
+    out_name: Option<String>,
     no_modules: bool,
     no_modules_global: Option<String>,
     debug: bool,
     typescript: bool,
     demangle: bool,
+    keep_debug: bool,
+    remove_name_section: bool,
+    remove_producers_section: bool,
+    preserve_code_offsets: bool,
+    wasm_opt: Option<String>,
+    multi_value: bool,
+    split_i64: bool,
+    reference_types: bool,
+    emit_wat: bool,
+    source_map: bool,
+    emit_metadata: bool,
+    output_names: OutputNames,
+    interpreter: bool,
+    run_gc: bool,
+    verbose: bool,
+    minify_js: bool,
+    csp_safe: bool,
+    js_prelude: String,
+    js_epilogue: String,
+    ts_prelude: String,
+    ts_epilogue: String,
+    es_target: EsVersion,
+    wasm_import_mode: WasmImportMode,
+    split_output: bool,
+    shared_runtime: bool,
+    heap_stack_size: usize,
+    heap_slab_size: usize,
+    heap_warning_threshold: usize,
+    string_interning: bool,
+    string_intern_capacity: usize,
+    weak_refs: bool,
+    build_id: bool,
+    local_module_roots: Vec<PathBuf>,
+    export_prefixes: BTreeMap<String, String>,
+    targets: Vec<Target>,
+    /// Set on the configuration of each target when generating several, see
+    /// `Bindgen::targets`.
+    shared_wasm: Option<SharedWasm>,
+    /// Set on the configuration of each input's `no_modules` glue when
+    /// generating several, the global the classes module assigns the classes
+    /// they share to, see `Bindgen::add_input_path`.
+    shared_classes_global: Option<String>,
+    stats: Option<BindgenStats>,
 }
 
+/// Why generating or validating bindings failed.
 #[derive(Debug)]
-pub struct Error(String);
+pub enum Error {
+    /// Reading an input or writing an output failed, `what` saying which.
+    Io { what: String, error: io::Error },
+    /// A crate in the input was built against a version of wasm-bindgen
+    /// using a different format for the bindgen metadata than this one.
+    SchemaMismatch {
+        /// The schema version of the first crate not matching.
+        found: String,
+        /// `wasm_bindgen_shared::SCHEMA_VERSION`.
+        expected: String,
+        /// The full explanation, listing the version of each crate.
+        message: String,
+    },
+    /// The bindgen metadata in the input couldn't be decoded.
+    Decode(String),
+    /// The input isn't a wasm module that can be parsed or run, or the
+    /// output couldn't be serialized.
+    Wasm(String),
+    /// Bindings can't be generated for what the input describes, with a
+    /// line for each problem found.
+    Bindings(Vec<String>),
+    /// The configuration doesn't make sense for the input, like a
+    /// `no_modules` global which isn't a JS identifier.
+    Config(String),
+}
+
+enum Input {
+    Path(PathBuf),
+    Reader(Box<dyn Read>, String),
+    Bytes(Vec<u8>, String),
+    /// Taken by the first call to `generate` or `validate`.
+    Module(Option<Module>, String),
+}
+
+impl Input {
+    /// The stem the output files are named after.
+    fn stem(&self) -> Result<String, Error> {
+        match *self {
+            Input::Path(ref path) => {
+                match path.file_stem().and_then(|stem| stem.to_str()) {
+                    Some(stem) => Ok(stem.to_string()),
+                    None => Err(Error::Config(format!("the output files can't be named \
+                                                       after the input `{}`, which has \
+                                                       no UTF-8 file name",
+                                                      path.display()))),
+                }
+            }
+            Input::Reader(_, ref stem) |
+            Input::Bytes(_, ref stem) |
+            Input::Module(_, ref stem) => Ok(stem.clone()),
+        }
+    }
+}
+
+/// An input as read by `Bindgen::read_inputs`, not parsed yet if it was
+/// given as bytes.
+enum Wasm {
+    Bytes(Vec<u8>),
+    Module(Module),
+}
+
+/// Destination for the files produced by `Bindgen::generate_to`.
+///
+/// Each file is written exactly once. Names are relative paths using `/` as
+/// the separator, like `out.js`, `out_bg.wasm` or, when generating several
+/// targets, `node/out.js`.
+pub trait OutputSink {
+    fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()>;
+}
+
+/// An `OutputSink` writing files into a directory, which is what
+/// `Bindgen::generate` uses.
+///
+/// The directory itself must exist, but subdirectories are created as
+/// needed.
+#[derive(Debug, Clone)]
+pub struct OutputDir {
+    path: PathBuf,
+}
+
+impl OutputDir {
+    pub fn new<P: AsRef<Path>>(path: P) -> OutputDir {
+        OutputDir { path: path.as_ref().to_path_buf() }
+    }
+}
+
+impl OutputSink for OutputDir {
+    fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let mut path = self.path.clone();
+        let mut parts = name.split('/').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_some() {
+                path.push(part);
+                fs::create_dir_all(&path)?;
+            } else {
+                path.push(part);
+            }
+        }
+        File::create(&path)?.write_all(contents)
+    }
+}
+
+/// An `OutputSink` holding on to the files written to it, see
+/// `Bindgen::generate_to`.
+#[derive(Default)]
+struct PendingOutput {
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl OutputSink for PendingOutput {
+    fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        self.files.push((name.to_string(), contents.to_vec()));
+        Ok(())
+    }
+}
+
+/// An `OutputSink` remembering the names of the files written through it,
+/// which local JS modules mustn't be copied over.
+struct WrittenNames<'a, S: 'a> {
+    sink: &'a mut S,
+    names: BTreeSet<String>,
+}
+
+impl<'a, S: OutputSink> OutputSink for WrittenNames<'a, S> {
+    fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        self.names.insert(name.to_string());
+        self.sink.write(name, contents)
+    }
+}
+
+/// Where the wasm shared by several targets is, see `Bindgen::targets`.
+#[derive(Clone)]
+struct SharedWasm {
+    /// The path from the directory of a target to the output directory,
+    /// like `../`.
+    up: String,
+    /// The directory of the target whose glue the wasm imports as an ES
+    /// module, if any does.
+    glue_dir: Option<String>,
+}
+
+/// What `Bindgen::generate_target` does with the wasm of an input.
+enum WasmOutput<'a> {
+    /// Writes it next to the glue, when generating a single flavor.
+    Own,
+    /// Leaves it to another target, adding the internal exports the glue
+    /// uses to those it keeps, by input.
+    Skip(&'a mut BTreeMap<String, BTreeSet<&'static str>>),
+    /// Writes it into the output directory, keeping the internal exports the
+    /// glue of every target uses.
+    Shared(&'a BTreeMap<String, BTreeSet<&'static str>>),
+}
+
+/// What `Bindgen::generate_target` returns: the names an input exports to JS,
+/// the local JS modules it imports and the parts of the runtime module it
+/// needs.
+type TargetOutput = (BTreeSet<String>, BTreeMap<String, BTreeSet<String>>,
+                     Vec<(&'static str, String)>);
+
+/// The files generated by `Bindgen::generate_output`.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// The JS glue.
+    pub js: String,
+    /// The TypeScript declarations of the glue, if `typescript` is set.
+    pub typescript: Option<String>,
+    /// The wasm the glue loads.
+    pub wasm: Vec<u8>,
+    /// Every other file generated, by name as `Bindgen::generate_to` would
+    /// write them, like the module loading the wasm for `nodejs` output or
+    /// the TypeScript declarations of the wasm's exports.
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+/// A flavor of JS glue to generate, see `Bindgen::targets`.
+#[derive(Debug, Clone)]
+pub struct Target {
+    kind: TargetKind,
+    dir: String,
+    typescript: Option<bool>,
+    no_modules_global: Option<String>,
+    es_target: Option<EsVersion>,
+    wasm_import_mode: Option<WasmImportMode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TargetKind {
+    Bundler,
+    Browser,
+    Nodejs,
+    NodejsModule,
+    Deno,
+    NoModules,
+}
+
+/// The version of JS the generated glue is written in, see
+/// `Bindgen::es_target`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EsVersion {
+    /// No classes, `let`, `const`, arrow functions, template literals,
+    /// spreads or destructuring, for engines which only support ES5.
+    Es5,
+    /// The default.
+    Es2015,
+}
+
+/// How the glue of ES module output gets hold of the wasm, see
+/// `Bindgen::wasm_import_mode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WasmImportMode {
+    /// `import * as wasm from './foo_bg'`, for bundlers which can import wasm
+    /// modules directly like webpack 4. The default.
+    EsModule,
+    /// `import wasm_url from './foo_bg.wasm'`, for bundlers configured to
+    /// turn imports of files into their URL, which is then fetched and
+    /// instantiated. The exported `ready` promise resolves once that's done.
+    ///
+    /// The wasm is compiled with `WebAssembly.instantiateStreaming` as it
+    /// downloads, falling back to compiling all of it once it's there when
+    /// that's missing or the server doesn't serve it as `application/wasm`.
+    FetchUrl,
+    /// The wasm isn't loaded at all, the host instantiates it and passes the
+    /// instance to the exported `setWasm`.
+    Deferred,
+}
+
+/// The names of the files generated for each input, see
+/// `Bindgen::output_names`.
+///
+/// A name is a file name in which `{stem}` stands for the stem of the input,
+/// like `{stem}.mjs`. Files that aren't renamed keep their usual name.
+#[derive(Debug, Clone, Default)]
+pub struct OutputNames {
+    js: Option<String>,
+    typescript: Option<String>,
+    wasm_loader: Option<String>,
+    wasm: Option<String>,
+}
+
+impl OutputNames {
+    pub fn new() -> OutputNames {
+        OutputNames::default()
+    }
+
+    /// The JS glue, `{stem}.js` by default. With `split_output` this is the
+    /// module re-exporting the split modules, which keep their names.
+    pub fn js(&mut self, name: &str) -> &mut OutputNames {
+        self.js = Some(name.to_string());
+        self
+    }
+
+    /// The TypeScript declarations of the JS glue, `{stem}.d.ts` by default.
+    pub fn typescript(&mut self, name: &str) -> &mut OutputNames {
+        self.typescript = Some(name.to_string());
+        self
+    }
+
+    /// The module instantiating the wasm for `nodejs`, `nodejs_module`, `deno`
+    /// or `WasmImportMode::FetchUrl` output, `{stem}_bg.js` by default.
+    pub fn wasm_loader(&mut self, name: &str) -> &mut OutputNames {
+        self.wasm_loader = Some(name.to_string());
+        self
+    }
+
+    /// The wasm, `{stem}_bg.wasm` by default. Its TypeScript declarations
+    /// and `emit_wat` rendering are named after it, with `.d.ts` and `.wat`
+    /// in place of `.wasm`.
+    pub fn wasm(&mut self, name: &str) -> &mut OutputNames {
+        self.wasm = Some(name.to_string());
+        self
+    }
+
+    fn js_file(&self, stem: &str) -> String {
+        file_name(&self.js, "{stem}.js", stem)
+    }
+
+    fn typescript_file(&self, stem: &str) -> String {
+        file_name(&self.typescript, "{stem}.d.ts", stem)
+    }
+
+    fn wasm_loader_file(&self, stem: &str) -> String {
+        file_name(&self.wasm_loader, "{stem}_bg.js", stem)
+    }
+
+    fn wasm_file(&self, stem: &str) -> String {
+        file_name(&self.wasm, "{stem}_bg.wasm", stem)
+    }
+
+    fn wasm_typescript_file(&self, stem: &str) -> String {
+        let wasm = self.wasm_file(stem);
+        format!("{}.d.ts", wasm.trim_end_matches(".wasm"))
+    }
+
+    fn wat_file(&self, stem: &str) -> String {
+        let wasm = self.wasm_file(stem);
+        format!("{}.wat", wasm.trim_end_matches(".wasm"))
+    }
+
+    fn metadata_file(&self, stem: &str) -> String {
+        format!("{}.bindings.json", stem)
+    }
+
+    /// How the other generated modules import the JS glue. Files keeping
+    /// their usual name are imported without an extension, as they always
+    /// have been, and renamed ones by their exact name. With `by_name`,
+    /// which Deno needs, every file is imported by its exact name.
+    pub(crate) fn js_import(&self, stem: &str, by_name: bool) -> String {
+        if by_name {
+            format!("./{}", self.js_file(stem))
+        } else {
+            import_path(&self.js, "{stem}", stem)
+        }
+    }
+
+    /// How the JS glue imports the wasm loader, see `js_import`.
+    pub(crate) fn wasm_loader_import(&self, stem: &str, by_name: bool) -> String {
+        if by_name {
+            format!("./{}", self.wasm_loader_file(stem))
+        } else {
+            import_path(&self.wasm_loader, "{stem}_bg", stem)
+        }
+    }
+
+    /// How the JS glue imports the wasm with `WasmImportMode::EsModule`, see
+    /// `js_import`. For `bundler` output it's always imported by its file
+    /// name.
+    pub(crate) fn wasm_import(&self, stem: &str, bundler: bool) -> String {
+        if bundler {
+            format!("./{}", self.wasm_file(stem))
+        } else {
+            import_path(&self.wasm, "{stem}_bg", stem)
+        }
+    }
+
+    /// Every file generated for an input, along with what it is.
+    fn files(&self, stem: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("the JS glue", self.js_file(stem)),
+            ("the TypeScript declarations", self.typescript_file(stem)),
+            ("the wasm loader", self.wasm_loader_file(stem)),
+            ("the wasm", self.wasm_file(stem)),
+            ("the wasm's TypeScript declarations", self.wasm_typescript_file(stem)),
+            ("the wasm's text rendering", self.wat_file(stem)),
+            ("the bindings metadata", self.metadata_file(stem)),
+        ]
+    }
+}
+
+fn file_name(name: &Option<String>, default: &str, stem: &str) -> String {
+    name.as_ref().map(|s| &s[..]).unwrap_or(default).replace("{stem}", stem)
+}
+
+fn import_path(name: &Option<String>, default: &str, stem: &str) -> String {
+    format!("./{}", file_name(name, default, stem))
+}
+
+/// Summary of what the gc pass removed from the output module.
+#[derive(Debug, Default, Clone)]
+pub struct GcStats {
+    /// Number of functions (defined or imported) that were removed.
+    pub functions_removed: usize,
+    /// How many bytes the removed function bodies and data segments took up
+    /// when serialized.
+    pub bytes_removed: usize,
+}
+
+/// Sizes, counts and timings gathered during a call to `Bindgen::generate`.
+///
+/// When generating multiple targets the JS and TypeScript sizes and the
+/// timings cover all of them.
+#[derive(Debug, Default, Clone)]
+pub struct BindgenStats {
+    /// Size of the input wasm file, not counting modules given to
+    /// `input_module`.
+    pub input_wasm_bytes: usize,
+    /// Size of the wasm file that was written out. The size before gc is
+    /// `output_wasm_bytes` plus `gc.bytes_removed`.
+    pub output_wasm_bytes: usize,
+    /// What the gc pass removed, or `None` if it didn't run.
+    pub gc: Option<GcStats>,
+    /// Number of exported functions and methods processed.
+    pub exports: usize,
+    /// Number of exported classes processed.
+    pub classes: usize,
+    /// Number of imports processed.
+    pub imports: usize,
+    /// The crates bindings were generated for, with the version of
+    /// wasm-bindgen each was built against.
+    pub crates: Vec<metadata::Crate>,
+    /// Bytes of JS glue emitted.
+    pub js_bytes: usize,
+    /// Bytes the JS glue would have had without `minify_js`, the same as
+    /// `js_bytes` if it's disabled.
+    pub unminified_js_bytes: usize,
+    /// Bytes of TypeScript definitions emitted (even if not written out).
+    pub ts_bytes: usize,
+    /// Time spent reading and parsing the input wasm file.
+    pub parse_time: Duration,
+    /// Time spent executing the descriptor shims.
+    pub descriptor_time: Duration,
+    /// Time spent generating JS and TypeScript, including gc passes.
+    pub js_time: Duration,
+    /// Time spent serializing and writing out all the output files.
+    pub serialize_time: Duration,
+    /// Names of the DWARF `.debug_*` and `sourceMappingURL` custom sections
+    /// found in the input.
+    pub debug_sections: Vec<String>,
+    /// Whether the `debug_sections` were kept in the output, which is only
+    /// the case with `keep_debug`.
+    pub debug_sections_kept: bool,
+    /// Whether the code section of the output has the same contents as the
+    /// one of the input. Only then do the code offsets recorded in DWARF,
+    /// which count from the start of the code section, still point at the
+    /// right code. Source maps count from the start of the file, so they also
+    /// need the sections before the code to be unchanged, which they rarely
+    /// are.
+    pub code_offsets_preserved: bool,
+    /// The descriptor shims `interpreter` couldn't run, which were run with
+    /// wasmi instead, each as its name followed by why in parentheses.
+    pub interpreter_fallbacks: Vec<String>,
+    /// The words each descriptor shim passed to `__wbindgen_describe`, keyed
+    /// by the shim's name. With several inputs a shim of a later input
+    /// replaces one of the same name in an earlier input.
+    pub descriptors: BTreeMap<String, Vec<u32>>,
+}
+
+impl fmt::Display for BindgenStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "input wasm:  {} bytes", self.input_wasm_bytes)?;
+        match self.gc {
+            Some(ref gc) => {
+                writeln!(f, "output wasm: {} bytes ({} before gc, {} functions removed)",
+                         self.output_wasm_bytes,
+                         self.output_wasm_bytes + gc.bytes_removed,
+                         gc.functions_removed)?;
+            }
+            None => writeln!(f, "output wasm: {} bytes (gc disabled)",
+                             self.output_wasm_bytes)?,
+        }
+        writeln!(f, "processed:   {} exports, {} classes, {} imports",
+                 self.exports, self.classes, self.imports)?;
+        if !self.crates.is_empty() {
+            let crates = self.crates.iter()
+                .map(|c| format!("{} {}", crate_label(&c.name), c.version))
+                .collect::<Vec<_>>();
+            writeln!(f, "crates:      {}", crates.join(", "))?;
+        }
+        if self.js_bytes == self.unminified_js_bytes {
+            writeln!(f, "emitted:     {} bytes of JS, {} bytes of TypeScript",
+                     self.js_bytes, self.ts_bytes)?;
+        } else {
+            writeln!(f, "emitted:     {} bytes of JS ({} before minifying), {} bytes \
+                         of TypeScript",
+                     self.js_bytes, self.unminified_js_bytes, self.ts_bytes)?;
+        }
+        if self.debug_sections.is_empty() {
+            writeln!(f, "debug info:  none")?;
+        } else if !self.debug_sections_kept {
+            writeln!(f, "debug info:  stripped ({})", self.debug_sections.join(", "))?;
+        } else if self.code_offsets_preserved {
+            writeln!(f, "debug info:  kept ({}), code offsets are unchanged",
+                     self.debug_sections.join(", "))?;
+        } else {
+            writeln!(f, "debug info:  kept ({}), but code offsets have shifted",
+                     self.debug_sections.join(", "))?;
+        }
+        if !self.interpreter_fallbacks.is_empty() {
+            writeln!(f, "interpreter: {} descriptors run with wasmi instead",
+                     self.interpreter_fallbacks.len())?;
+        }
+        writeln!(f, "parse:       {}", DisplayDuration(self.parse_time))?;
+        writeln!(f, "descriptors: {}", DisplayDuration(self.descriptor_time))?;
+        writeln!(f, "js:          {}", DisplayDuration(self.js_time))?;
+        write!(f, "serialize:   {}", DisplayDuration(self.serialize_time))
+    }
+}
+
+struct DisplayDuration(Duration);
+
+impl fmt::Display for DisplayDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:03}s", self.0.as_secs(), self.0.subsec_millis())
+    }
+}
+
+impl Target {
+    /// ES modules intended to be processed by a bundler, like
+    /// `Bindgen::bundler`. Generated into the `bundler` directory.
+    pub fn bundler() -> Target {
+        Target::new(TargetKind::Bundler, "bundler")
+    }
+
+    /// ES modules tailored for browsers, like `Bindgen::browser`. Generated
+    /// into the `web` directory.
+    pub fn browser() -> Target {
+        Target::new(TargetKind::Browser, "web")
+    }
+
+    /// CommonJS modules for node, like `Bindgen::nodejs`. Generated into the
+    /// `node` directory.
+    pub fn nodejs() -> Target {
+        Target::new(TargetKind::Nodejs, "node")
+    }
+
+    /// ES modules for node, like `Bindgen::nodejs_module`. Generated into the
+    /// `node-module` directory.
+    pub fn nodejs_module() -> Target {
+        Target::new(TargetKind::NodejsModule, "node-module")
+    }
+
+    /// ES modules for Deno, like `Bindgen::deno`. Generated into the `deno`
+    /// directory.
+    pub fn deno() -> Target {
+        Target::new(TargetKind::Deno, "deno")
+    }
+
+    /// A browser script without modules, like `Bindgen::no_modules`.
+    /// Generated into the `no-modules` directory.
+    pub fn no_modules() -> Target {
+        Target::new(TargetKind::NoModules, "no-modules")
+    }
+
+    fn new(kind: TargetKind, dir: &str) -> Target {
+        Target {
+            kind,
+            dir: dir.to_string(),
+            typescript: None,
+            no_modules_global: None,
+            es_target: None,
+            wasm_import_mode: None,
+        }
+    }
+
+    pub fn dir(&mut self, dir: &str) -> &mut Target {
+        self.dir = dir.to_string();
+        self
+    }
+
+    pub fn typescript(&mut self, typescript: bool) -> &mut Target {
+        self.typescript = Some(typescript);
+        self
+    }
+
+    pub fn no_modules_global(&mut self, name: &str) -> &mut Target {
+        self.no_modules_global = Some(name.to_string());
+        self
+    }
+
+    pub fn es_target(&mut self, version: EsVersion) -> &mut Target {
+        self.es_target = Some(version);
+        self
+    }
+
+    pub fn wasm_import_mode(&mut self, mode: WasmImportMode) -> &mut Target {
+        self.wasm_import_mode = Some(mode);
+        self
+    }
+}
+
+impl From<parity_wasm::elements::Error> for Error {
+    fn from(e: parity_wasm::elements::Error) -> Error {
+        Error::Wasm(format!("{:?}", e))
+    }
+}
+
+impl From<wasmi::Error> for Error {
+    fn from(e: wasmi::Error) -> Error {
+        Error::Wasm(e.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io { ref what, ref error } => write!(f, "{}: {}", what, error),
+            Error::SchemaMismatch { ref message, .. } => message.fmt(f),
+            Error::Decode(ref s) | Error::Wasm(ref s) | Error::Config(ref s) => s.fmt(f),
+            Error::Bindings(ref problems) => problems.join("\n").fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        "failed to generate bindings"
+    }
+
+    fn cause(&self) -> Option<&dyn std::error::Error> {
+        match *self {
+            Error::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Bindgen {
+    pub fn new() -> Bindgen {
+        Bindgen {
+            inputs: Vec::new(),
+            input_set_twice: false,
+            nodejs: false,
+            nodejs_async: false,
+            browser: false,
+            bundler: false,
+            deno: false,
+            nodejs_module: false,
+            umd: false,
+            out_name: None,
+            no_modules: false,
+            no_modules_global: None,
+            debug: false,
+            typescript: false,
+            demangle: true,
+            keep_debug: false,
+            remove_name_section: false,
+            remove_producers_section: false,
+            preserve_code_offsets: false,
+            wasm_opt: None,
+            multi_value: false,
+            split_i64: false,
+            reference_types: false,
+            emit_wat: false,
+            source_map: false,
+            emit_metadata: false,
+            output_names: OutputNames::default(),
+            interpreter: true,
+            run_gc: true,
+            verbose: false,
+            minify_js: false,
+            csp_safe: false,
+            js_prelude: String::new(),
+            js_epilogue: String::new(),
+            ts_prelude: String::new(),
+            ts_epilogue: String::new(),
+            es_target: EsVersion::Es2015,
+            wasm_import_mode: WasmImportMode::EsModule,
+            split_output: false,
+            shared_runtime: false,
+            heap_stack_size: 32,
+            heap_slab_size: 128,
+            heap_warning_threshold: 10_000,
+            string_interning: false,
+            string_intern_capacity: 1024,
+            weak_refs: false,
+            build_id: false,
+            local_module_roots: Vec::new(),
+            export_prefixes: BTreeMap::new(),
+            targets: Vec::new(),
+            shared_wasm: None,
+            shared_classes_global: None,
+            stats: None,
+        }
+    }
+
+    /// Generates bindings for the wasm file at `path`, naming the output
+    /// files after its file name.
+    ///
+    /// Only a single input can be set with this, `input_reader`,
+    /// `input_bytes` or `input_module`, generating fails if another was.
+    pub fn input_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Bindgen {
+        self.set_input(Input::Path(path.as_ref().to_path_buf()))
+    }
+
+    /// Adds another wasm file to generate bindings for alongside the input.
+    ///
+    /// Each input gets its own glue and `_bg.wasm` file named after it as
+    /// usual, and an `index.js` entry module re-exporting the exports of all
+    /// of them is generated as well.
+    ///
+    /// A class exported by several inputs is defined once in a
+    /// `wasm_bindgen_classes.js` module, which the entry exports it from.
+    /// Each input's class extends that one, so instances still belong to the
+    /// input which created them and can only be passed back to it, while
+    /// constructing the shared class and its static methods are forwarded
+    /// to the first input exporting it. With `no_modules` the classes module
+    /// is a script assigning them to the global, which has to be loaded
+    /// before those of the inputs.
+    pub fn add_input_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Bindgen {
+        self.inputs.push(Input::Path(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Adds another wasm module already in memory to generate bindings for
+    /// alongside the input, named `stem`, like `add_input_path`.
+    pub fn add_input_bytes(&mut self, bytes: Vec<u8>, stem: &str) -> &mut Bindgen {
+        self.inputs.push(Input::Bytes(bytes, stem.to_string()));
+        self
+    }
+
+    /// Prefixes the names of everything the input named `stem` exports in
+    /// the entry module generated for multiple inputs, which is needed when
+    /// two inputs export the same name. Classes several inputs export are
+    /// shared rather than prefixed, see `add_input_path`.
+    pub fn export_prefix(&mut self, stem: &str, prefix: &str) -> &mut Bindgen {
+        self.export_prefixes.insert(stem.to_string(), prefix.to_string());
+        self
+    }
+
+    /// Reads the input wasm from `reader` rather than a file, naming the
+    /// output files after `stem` as `input_path` would after the file name.
+    ///
+    /// The reader is consumed by the first call to `generate` or `validate`,
+    /// later calls reuse what was read.
+    ///
+    /// Generating fails if an input was already set, like with `input_path`.
+    pub fn input_reader<R: Read + 'static>(&mut self, reader: R, stem: &str)
+        -> &mut Bindgen
+    {
+        self.set_input(Input::Reader(Box::new(reader), stem.to_string()))
+    }
+
+    /// Generates bindings for the wasm file `bytes` which is already in
+    /// memory, like the output of an earlier build step, naming the output
+    /// files after `stem` as `input_path` would after the file name.
+    ///
+    /// Generating fails if an input was already set, like with `input_path`.
+    pub fn input_bytes(&mut self, bytes: Vec<u8>, stem: &str) -> &mut Bindgen {
+        self.set_input(Input::Bytes(bytes, stem.to_string()))
+    }
+
+    /// Generates bindings for a module which has already been parsed, for
+    /// example by a tool which also transforms it, naming the output files
+    /// after `stem` as `input_path` would after the file name.
+    ///
+    /// This saves serializing the module only for it to be parsed again.
+    /// Generating the bindings mutates the module, so it's taken by the
+    /// first call to `generate` or `validate` and later calls fail.
+    ///
+    /// The module must have been parsed with the version of `parity_wasm`
+    /// this crate re-exports.
+    ///
+    /// Generating fails if an input was already set, like with `input_path`.
+    pub fn input_module(&mut self, module: Module, stem: &str) -> &mut Bindgen {
+        self.set_input(Input::Module(Some(module), stem.to_string()))
+    }
+
+    fn set_input(&mut self, input: Input) -> &mut Bindgen {
+        if !self.inputs.is_empty() {
+            self.input_set_twice = true;
+        }
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn nodejs(&mut self, node: bool) -> &mut Bindgen {
+        self.nodejs = node;
+        self
+    }
+
+    pub fn nodejs_async(&mut self, nodejs_async: bool) -> &mut Bindgen {
+        self.nodejs_async = nodejs_async;
+        self
+    }
+
+    /// Generates native ES modules for node instead of the CommonJS of
+    /// `nodejs`, for packages with `"type": "module"`.
+    ///
+    /// The glue is loaded like `deno`'s, except that the wasm is read with
+    /// `fs.promises.readFile` from next to the `foo_bg.js` module. Can't be
+    /// combined with `nodejs` or `no_modules`.
+    pub fn nodejs_module(&mut self, nodejs_module: bool) -> &mut Bindgen {
+        self.nodejs_module = nodejs_module;
+        self
+    }
+
+    pub fn browser(&mut self, browser: bool) -> &mut Bindgen {
+        self.browser = browser;
+        self
+    }
+
+    /// Tailors ES module output for bundlers like webpack or Rollup, which
+    /// need `import * as wasm from './foo_bg.wasm'` to name the wasm file
+    /// itself to load it as a wasm module.
+    ///
+    /// Only changes glue importing the wasm with `WasmImportMode::EsModule`,
+    /// so it has no effect with `nodejs` or `no_modules`.
+    pub fn bundler(&mut self, bundler: bool) -> &mut Bindgen {
+        self.bundler = bundler;
+        self
+    }
+
+    /// Generates ES modules for Deno, which import each other by their file
+    /// names and don't `require` anything.
+    ///
+    /// A `foo_bg.js` module loads the wasm next to it, with `Deno.readFile`
+    /// when the glue was imported from a `file:` URL and with `fetch`
+    /// otherwise, and the glue awaits it at its top level so its exports can
+    /// be used as soon as it's imported. `wasm_import_mode` is ignored.
+    pub fn deno(&mut self, deno: bool) -> &mut Bindgen {
+        self.deno = deno;
+        self
+    }
+
+    pub fn no_modules(&mut self, no_modules: bool) -> &mut Bindgen {
+        self.no_modules = no_modules;
+        self
+    }
+
+    /// With `no_modules`, wraps the glue in a UMD header so that the same file
+    /// can also be loaded with AMD's `define` or CommonJS's `require`, which
+    /// get the loader the global is otherwise assigned.
+    ///
+    /// Required from CommonJS the loader reads the wasm next to the glue
+    /// when it's called without one. Only a single input is supported.
+    pub fn umd(&mut self, umd: bool) -> &mut Bindgen {
+        self.umd = umd;
+        self
+    }
+
+    /// Names the global the `no_modules` loader is assigned to, by default
+    /// `wasm_bindgen`.
+    ///
+    /// This may be a path like `my.app.wasm`, in which case any objects along
+    /// it that don't exist are created. `generate` and `validate` fail if the
+    /// name isn't an identifier or a path of them.
+    pub fn no_modules_global(&mut self, name: &str) -> &mut Bindgen {
+        self.no_modules_global = Some(name.to_string());
+        self
+    }
+
+    pub fn debug(&mut self, debug: bool) -> &mut Bindgen {
+        self.debug = debug;
+        self
+    }
+
+    pub fn typescript(&mut self, typescript: bool) -> &mut Bindgen {
+        self.typescript = typescript;
+        self
+    }
+
+    pub fn demangle(&mut self, demangle: bool) -> &mut Bindgen {
+        self.demangle = demangle;
+        self
+    }
+
+    /// Keeps the `name` section of the wasm, demangled unless `demangle` is
+    /// off, so profilers and devtools show readable function names, along
+    /// with its DWARF `.debug_*` sections and `sourceMappingURL` section. By
+    /// default all of them are stripped from `foo_bg.wasm`.
+    ///
+    /// DWARF and source maps describe code by its offset, which is only still
+    /// accurate if generating the bindings left the code section unchanged,
+    /// see `BindgenStats::code_offsets_preserved`.
+    pub fn keep_debug(&mut self, keep_debug: bool) -> &mut Bindgen {
+        self.keep_debug = keep_debug;
+        self
+    }
+
+    /// Removes the `name` section even with `keep_debug`, which then only
+    /// keeps the DWARF and `sourceMappingURL` sections.
+    pub fn remove_name_section(&mut self, remove: bool) -> &mut Bindgen {
+        self.remove_name_section = remove;
+        self
+    }
+
+    /// Removes the `producers` section, which lists the languages and tools
+    /// the wasm was built with and is otherwise copied through unchanged.
+    pub fn remove_producers_section(&mut self, remove: bool) -> &mut Bindgen {
+        self.remove_producers_section = remove;
+        self
+    }
+
+    /// Leaves the code section of `foo_bg.wasm` exactly as it is in the
+    /// input, so that its DWARF `.debug_*` sections, which this keeps as
+    /// `keep_debug` does, still describe the right code and browsers can
+    /// debug the Rust source.
+    ///
+    /// This skips the passes which would change the code: gc is disabled and
+    /// unused imports of intrinsics are kept (and given their JS), and the
+    /// code section is copied over from the input when the module is
+    /// serialized. The output is larger as a result. It can't be combined
+    /// with `build_id`, which adds a function to the code.
+    pub fn preserve_code_offsets(&mut self, preserve: bool) -> &mut Bindgen {
+        self.preserve_code_offsets = preserve;
+        self
+    }
+
+    /// Runs binaryen's `wasm-opt` with `-O{level}` over `foo_bg.wasm` once
+    /// the bindings are generated, where `level` is one of `0` to `4`, `s`
+    /// or `z`. Optimizing last means it can't undo anything the bindings
+    /// rely on, and the exports the JS glue uses are always kept.
+    ///
+    /// `wasm-opt` is looked up in `PATH` unless the `WASM_OPT` environment
+    /// variable gives the path to it. The `name` and debug sections are
+    /// only kept through it with `keep_debug`, and `emit_wat` shows the
+    /// module before it was optimized. Optimizing rewrites the code, so it
+    /// can't be combined with `preserve_code_offsets`.
+    pub fn wasm_opt(&mut self, level: &str) -> &mut Bindgen {
+        self.wasm_opt = Some(level.to_string());
+        self
+    }
+
+    /// Makes exports which return a pointer along with a length, like those
+    /// returning strings and vectors, return both as wasm multi-value
+    /// results. Otherwise the length is left in linear memory for the glue
+    /// to read back, which these exports then skip.
+    ///
+    /// Each such export is replaced by a wrapper calling the original, so
+    /// the output only runs on engines supporting multi-value. This adds to
+    /// the code, so it can't be combined with `preserve_code_offsets`.
+    pub fn multi_value(&mut self, multi_value: bool) -> &mut Bindgen {
+        self.multi_value = multi_value;
+        self
+    }
+
+    /// Passes `i64` and `u64` between JS and wasm as two 32-bit halves, for
+    /// engines without `BigInt`. In JS they're then numbers, which are only
+    /// exact up to 2^53, rather than `BigInt`s.
+    ///
+    /// The exports and imports involved are replaced by wrappers doing the
+    /// splitting and joining, so this can't be combined with
+    /// `preserve_code_offsets`.
+    pub fn split_i64(&mut self, split_i64: bool) -> &mut Bindgen {
+        self.split_i64 = split_i64;
+        self
+    }
+
+    /// Passes `JsValue` arguments and return values of exports and imports
+    /// between JS and wasm as reference types' `externref`s, rather than as
+    /// indices into a heap of JS values kept by the glue.
+    ///
+    /// The JS values wasm holds on to are kept in a wasm table of
+    /// `externref`s instead, exported as `__wbindgen_externrefs`, whose
+    /// slots are the indices Rust still works with. The exports and imports
+    /// involved are replaced by wrappers moving values in and out of the
+    /// table, so the output only runs on engines supporting reference types.
+    /// This adds to the code, so it can't be combined with
+    /// `preserve_code_offsets`, and it can't be combined with `multi_value`,
+    /// `shared_runtime` or `emit_wat` either.
+    pub fn reference_types(&mut self, reference_types: bool) -> &mut Bindgen {
+        self.reference_types = reference_types;
+        self
+    }
+
+    /// Also writes `foo_bg.wat` next to `foo_bg.wasm`, a text rendering of
+    /// the output module to see what generating the bindings did to it.
+    ///
+    /// Its types, imports, exports, tables, memories, globals and element
+    /// segments are listed in full and every function is disassembled, while
+    /// data segments and custom sections are only summarized by their size.
+    pub fn emit_wat(&mut self, emit_wat: bool) -> &mut Bindgen {
+        self.emit_wat = emit_wat;
+        self
+    }
+
+    /// Also writes a source map next to each JS module of the glue, like
+    /// `foo.js.map`, which the module refers to with a `sourceMappingURL`
+    /// comment. It maps the shim of each exported function and method, and
+    /// of each imported function, to the Rust item it was generated for, so
+    /// that stack traces through the glue point at the Rust code. Items of
+    /// crates built against versions of wasm-bindgen which didn't record
+    /// where they're defined stay unmapped.
+    pub fn source_map(&mut self, source_map: bool) -> &mut Bindgen {
+        self.source_map = source_map;
+        self
+    }
+
+    /// Also writes `foo.bindings.json` next to the JS glue, describing the
+    /// functions, classes, enums and consts it exports and what it imports
+    /// from which JS modules, for tools generating docs or comparing the API
+    /// of two builds. See the `metadata` module for the format.
+    pub fn emit_metadata(&mut self, emit_metadata: bool) -> &mut Bindgen {
+        self.emit_metadata = emit_metadata;
+        self
+    }
+
+    /// Renames the files generated for each input, for example to give the
+    /// JS glue an `.mjs` extension. Imports between the generated files
+    /// follow the new names.
+    ///
+    /// Generating fails if a name isn't a plain file name or if two of the
+    /// files would get the same name.
+    pub fn output_names(&mut self, names: &OutputNames) -> &mut Bindgen {
+        self.output_names = names.clone();
+        self
+    }
+
+    /// Names the output files after `name` rather than the input, so
+    /// `mylib.js`, `mylib.d.ts` and `mylib_bg.wasm`, which is the `{stem}`
+    /// of `output_names`. Useful as cargo names its artifacts after the
+    /// crate, sometimes with a hash.
+    ///
+    /// Only a single input can be renamed, generating fails with more or if
+    /// `name` isn't a plain file name.
+    pub fn out_name(&mut self, name: &str) -> &mut Bindgen {
+        self.out_name = Some(name.to_string());
+        self
+    }
+
+    pub fn interpreter(&mut self, interpreter: bool) -> &mut Bindgen {
+        self.interpreter = interpreter;
+        self
+    }
+
+    pub fn run_gc(&mut self, run_gc: bool) -> &mut Bindgen {
+        self.run_gc = run_gc;
+        self
+    }
+
+    pub fn verbose(&mut self, verbose: bool) -> &mut Bindgen {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Shortens the names of the JS glue's internal helpers and locals and
+    /// strips its comments and whitespace.
+    ///
+    /// Exported names, and so the API of the generated module, stay the same.
+    /// TypeScript definitions aren't affected.
+    pub fn minify_js(&mut self, minify: bool) -> &mut Bindgen {
+        self.minify_js = minify;
+        self
+    }
+
+    /// Makes sure the JS glue can run under a Content Security Policy
+    /// without `unsafe-eval`.
+    ///
+    /// Generating fails if any of the JS written out, the prelude and
+    /// epilogue included, uses `eval`, `new Function` or `document.write`.
+    /// If the glue loads the wasm itself (with `no_modules` or
+    /// `WasmImportMode::FetchUrl`) and the policy blocks compiling it, the
+    /// promise rejects with an error named `WasmCspError` which says what the
+    /// policy must allow, its `cause` being the original error.
+    pub fn csp_safe(&mut self, csp_safe: bool) -> &mut Bindgen {
+        self.csp_safe = csp_safe;
+        self
+    }
+
+    /// Inserts `js` into the JS glue before its own code, such as a polyfill
+    /// the glue relies on.
+    ///
+    /// It goes after the glue's imports (or `require`s), and without modules
+    /// it's the first thing inside the function wrapping the glue. With
+    /// `split_output` it goes into the core module, which the others import.
+    /// The text is inserted exactly as given, so it isn't re-indented or
+    /// minified.
+    pub fn js_prelude(&mut self, js: &str) -> &mut Bindgen {
+        self.js_prelude = js.to_string();
+        self
+    }
+
+    /// Inserts `js` into the JS glue after its own code, exports included,
+    /// for example to register the module with an application. Without
+    /// modules it's the last thing inside the function wrapping the glue.
+    ///
+    /// Like `js_prelude` the text is inserted exactly as given.
+    pub fn js_epilogue(&mut self, js: &str) -> &mut Bindgen {
+        self.js_epilogue = js.to_string();
+        self
+    }
+
+    /// Inserts `ts` at the start of the TypeScript declarations, after the
+    /// `tslint:disable` comment.
+    pub fn ts_prelude(&mut self, ts: &str) -> &mut Bindgen {
+        self.ts_prelude = ts.to_string();
+        self
+    }
+
+    /// Inserts `ts` at the end of the TypeScript declarations.
+    pub fn ts_epilogue(&mut self, ts: &str) -> &mut Bindgen {
+        self.ts_epilogue = ts.to_string();
+        self
+    }
+
+    /// Selects the version of JS to generate, `EsVersion::Es2015` by default.
+    ///
+    /// With `EsVersion::Es5` the glue can be loaded by older engines without
+    /// transpiling it first, which is mostly useful along with `no_modules`
+    /// as the other flavors of output still use `import` and `export` (or
+    /// `require`). Only the syntax is affected, the runtime APIs the glue
+    /// uses, like `WebAssembly`, `Promise` or `TextEncoder`, need to be
+    /// provided by the environment or a polyfill.
+    pub fn es_target(&mut self, version: EsVersion) -> &mut Bindgen {
+        self.es_target = version;
+        self
+    }
+
+    /// Selects how the glue gets hold of the wasm when it's an ES module
+    /// (without `nodejs` or `no_modules`), `WasmImportMode::EsModule` by
+    /// default.
+    ///
+    /// With `WasmImportMode::FetchUrl` a `foo_bg.js` module instantiating
+    /// the wasm is generated next to the glue, and with
+    /// `WasmImportMode::Deferred` the host must instantiate `foo_bg.wasm`
+    /// itself, giving it the exports of the glue as its `./foo` import.
+    /// Either way using an export of the glue before the wasm is ready
+    /// throws.
+    pub fn wasm_import_mode(&mut self, mode: WasmImportMode) -> &mut Bindgen {
+        self.wasm_import_mode = mode;
+        self
+    }
+
+    /// Generates a module per exported class when the glue is an ES module
+    /// (without `nodejs` or `no_modules`), so bundlers can leave out the
+    /// classes an application doesn't use.
+    ///
+    /// Everything else goes into `foo_core.js`, and each class into
+    /// `foo_class_Name.js` importing what it needs from the core and the
+    /// other classes. `foo.js` re-exports all of them, so it can be used just
+    /// like without this option. The TypeScript declarations are split the
+    /// same way.
+    pub fn split_output(&mut self, split: bool) -> &mut Bindgen {
+        self.split_output = split;
+        self
+    }
+
+    /// Generates the code managing the heap and stack of JS values which wasm
+    /// refers to into one `wasm_bindgen_runtime.js` module, which the glue of
+    /// every input imports instead of having its own copy. This is for ES
+    /// module output (without `nodejs` or `no_modules`) of multiple inputs,
+    /// which then also share the JS values they hold on to, and whose entry
+    /// module loads all of them.
+    pub fn shared_runtime(&mut self, shared: bool) -> &mut Bindgen {
+        self.shared_runtime = shared;
+        self
+    }
+
+    /// Sets how many JS values the generated heap has room for before it
+    /// needs to grow, as `stack` borrowed ones and `slab` owned ones. The
+    /// defaults are 32 and 128.
+    ///
+    /// Values borrowed for the duration of a call into wasm are pushed onto
+    /// and popped off the stack, while owned ones are allocated from the
+    /// slab's free list. `undefined`, `null`, `true` and `false` have fixed
+    /// indices and never use either.
+    pub fn initial_heap_size(&mut self, stack: usize, slab: usize) -> &mut Bindgen {
+        self.heap_stack_size = stack;
+        self.heap_slab_size = slab;
+        self
+    }
+
+    /// With `debug`, sets how many JS values wasm may own before the glue
+    /// warns on the console about a possible leak, 10,000 by default. Zero
+    /// turns the warning off.
+    pub fn heap_warning_threshold(&mut self, threshold: usize) -> &mut Bindgen {
+        self.heap_warning_threshold = threshold;
+        self
+    }
+
+    /// Makes the glue cache strings crossing between JS and wasm, off by
+    /// default.
+    ///
+    /// Strings registered from Rust with `wasm_bindgen::intern` are decoded
+    /// once and the same JS string is handed out each time they're passed to
+    /// JS afterwards, until `wasm_bindgen::unintern` drops them. Going the
+    /// other way the UTF-8 encoding of short strings is remembered, so
+    /// passing the same string again only copies it into wasm's memory.
+    /// Without this option `intern` and `unintern` do nothing.
+    pub fn string_interning(&mut self, interning: bool) -> &mut Bindgen {
+        self.string_interning = interning;
+        self
+    }
+
+    /// With `string_interning`, sets how many strings each direction's cache
+    /// holds before the oldest entry is dropped, 1024 by default.
+    pub fn string_intern_capacity(&mut self, capacity: usize) -> &mut Bindgen {
+        self.string_intern_capacity = capacity;
+        self
+    }
+
+    /// Frees the Rust value behind an instance of an exported class once
+    /// the garbage collector collects the JS object, if it's still owned by
+    /// the object then.
+    ///
+    /// The glue registers instances with a `FinalizationRegistry` where the
+    /// JS engine has one, and keeps to freeing them with `free` elsewhere.
+    /// An instance stops being tracked once `free` is called or the value is
+    /// moved into Rust. In debug mode `debugObjectStats` also counts how
+    /// many instances were freed each way.
+    pub fn weak_refs(&mut self, weak_refs: bool) -> &mut Bindgen {
+        self.weak_refs = weak_refs;
+        self
+    }
+
+    /// Makes the glue check that the wasm it's given is the one it was
+    /// generated along with, rather than one from another build.
+    ///
+    /// A short id hashed from the input's bindings and code is exported from
+    /// the output wasm as `__wbindgen_build_id` and written into the glue,
+    /// which throws an error naming both ids if they differ once it has the
+    /// instance. In debug mode it also checks that the instance has every
+    /// internal export the glue uses, naming all that are missing.
+    pub fn build_id(&mut self, build_id: bool) -> &mut Bindgen {
+        self.build_id = build_id;
+        self
+    }
+
+    /// Registers a directory in which JS modules imported with a relative
+    /// path (like `./snippets/foo.js`) are looked up.
+    ///
+    /// Once at least one root is registered all such modules are copied into
+    /// the output directory next to the generated JS, and it's an error for
+    /// one to not be found in any root. Since `..` components are dropped,
+    /// it's also an error for two modules (like `./a/x.js` and `../a/x.js`)
+    /// to be copied to the same place, or for one to be copied over a
+    /// generated file.
+    pub fn local_module_root<P: AsRef<Path>>(&mut self, path: P) -> &mut Bindgen {
+        self.local_module_roots.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Generates glue for each of `targets` from a single pass over the
+    /// input, each into its own subdirectory of the output directory.
+    ///
+    /// The wasm is only rewritten once, into a single `_bg.wasm` in the
+    /// output directory itself which the glue of every target loads, like
+    /// `../out_bg.wasm`. Since it can only import the glue of one target,
+    /// it's an error for more than one to import it as an ES module, see
+    /// `WasmImportMode::EsModule`.
+    ///
+    /// Settings a target doesn't override are taken from this builder. When
+    /// no targets are configured a single flavor of output selected with
+    /// `nodejs`, `browser` or `no_modules` is written to the output directory
+    /// itself.
+    pub fn targets(&mut self, targets: &[Target]) -> &mut Bindgen {
+        self.targets = targets.to_vec();
+        self
+    }
+
+    /// Returns what the gc pass removed during the last call to `generate`,
+    /// or `None` if it didn't run.
+    pub fn gc_stats(&self) -> Option<&GcStats> {
+        self.stats.as_ref().and_then(|s| s.gc.as_ref())
+    }
+
+    /// Returns statistics about the last successful call to `generate`.
+    pub fn stats(&self) -> Option<&BindgenStats> {
+        self.stats.as_ref()
+    }
+
+    pub fn generate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        self._generate(&mut OutputDir::new(path))
+    }
+
+    /// Like `generate`, but hands each generated file to `out` instead of
+    /// writing it into a directory.
+    pub fn generate_to<S: OutputSink>(&mut self, out: &mut S) -> Result<(), Error> {
+        self._generate(out)
+    }
+
+    /// Like `generate`, but returns the generated files instead of writing
+    /// them anywhere.
+    ///
+    /// This only supports a single input generated for a single flavor of
+    /// output. Use `generate_to` for multiple inputs or `targets`.
+    pub fn generate_output(&mut self) -> Result<Output, Error> {
+        self.check_inputs()?;
+        if self.inputs.len() > 1 || !self.targets.is_empty() {
+            return Err(Error::Config("`generate_output` can't generate bindings for \
+                                      multiple inputs or targets, use `generate_to` \
+                                      instead".to_string()))
+        }
+        let mut out = PendingOutput::default();
+        self._generate(&mut out)?;
+        let stem = match self.out_name {
+            Some(ref name) => name.clone(),
+            None => self.inputs[0].stem()?,
+        };
+        let mut files = out.files.into_iter().collect::<BTreeMap<_, _>>();
+        let js = files.remove(&self.output_names.js_file(&stem)).unwrap();
+        let typescript = files.remove(&self.output_names.typescript_file(&stem));
+        let wasm = files.remove(&self.output_names.wasm_file(&stem)).unwrap();
+        Ok(Output {
+            js: String::from_utf8(js).unwrap(),
+            typescript: typescript.map(|ts| String::from_utf8(ts).unwrap()),
+            wasm,
+            files,
+        })
+    }
+
+    /// Checks the inputs and how the files generated for them are named.
+    fn check_inputs(&self) -> Result<(), Error> {
+        if self.inputs.is_empty() {
+            return Err(Error::Config("must have an input, see `input_path`".to_string()))
+        }
+        if self.input_set_twice {
+            return Err(Error::Config("an input was already set, use `add_input_path` to \
+                                      generate bindings for several inputs".to_string()))
+        }
+        if let Some(ref name) = self.out_name {
+            if self.inputs.len() > 1 {
+                return Err(Error::Config("`out_name` can't be used with multiple inputs, \
+                                          see `output_names`".to_string()))
+            }
+            if !is_file_name(name) {
+                return Err(Error::Config(format!("the name `{}` given to `out_name` isn't \
+                                                  a file name", name)))
+            }
+        }
+        let files = self.output_names.files("{stem}");
+        for (i, &(what, ref name)) in files.iter().enumerate() {
+            if !is_file_name(name) {
+                return Err(Error::Config(format!("the name `{}` given to {} isn't a file \
+                                                  name", name, what)))
+            }
+            if let Some(&(other, _)) = files[..i].iter().find(|f| f.1 == *name) {
+                return Err(Error::Config(format!("{} and {} would both be named `{}`",
+                                                 other, what, name)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes of each input wasm file and the stem to name its
+    /// output files after.
+    fn read_inputs(&mut self) -> Result<Vec<(Wasm, String)>, Error> {
+        self.check_inputs()?;
+        let mut inputs = self.inputs.iter_mut()
+            .map(read_input)
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(ref name) = self.out_name {
+            inputs[0].1 = name.clone();
+        }
+        if inputs.len() > 1 {
+            for (what, name) in self.output_names.files("{stem}") {
+                if !name.contains("{stem}") {
+                    return Err(Error::Config(format!("the name `{}` given to {} must \
+                                                      contain `{{stem}}` when generating \
+                                                      bindings for multiple inputs",
+                                                     name, what)))
+                }
+            }
+            let mut stems = BTreeSet::new();
+            for (_, stem) in inputs.iter() {
+                if stem == ENTRY_STEM || stem == CLASSES_STEM {
+                    return Err(Error::Config(format!("an input can't be named `{}` when \
+                                                      generating bindings for multiple \
+                                                      inputs", stem)))
+                }
+                if !stems.insert(stem) {
+                    return Err(Error::Config(format!("multiple inputs are named `{}`", stem)))
+                }
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Runs the descriptors of and reads the bindgen metadata from each
+    /// input, returning the parsed modules alongside.
+    fn parse_inputs(&mut self, stats: &mut BindgenStats)
+        -> Result<(Vec<ParsedInput>, Vec<Module>), Error>
+    {
+        let mut parsed = Vec::new();
+        let mut modules = Vec::new();
+        for (wasm, stem) in self.read_inputs()? {
+            let start = Instant::now();
+            // Nothing is known about the bytes of an already parsed module.
+            let (code, code_contents) = match wasm {
+                Wasm::Bytes(ref bytes) => {
+                    stats.input_wasm_bytes += bytes.len();
+                    let contents = if self.preserve_code_offsets {
+                        code_section_contents(bytes)
+                    } else {
+                        None
+                    };
+                    (code_section(bytes), contents)
+                }
+                Wasm::Module(_) => (None, None),
+            };
+            let (mut module, shared_memory) = wasm.parse()?;
+            stats.parse_time += start.elapsed();
+
+            // Here we're actually instantiating the module we've parsed above
+            // for execution. Why, you might be asking, are we executing wasm
+            // code? A good question!
+            //
+            // Transmitting information from `#[wasm_bindgen]` here to the CLI
+            // tool is pretty tricky. Specifically information about the types
+            // involved with a function signature (especially generic ones)
+            // can be hefty to translate over. As a result, the macro emits a
+            // bunch of shims which, when executed, will describe to us what
+            // the types look like.
+            //
+            // This means that whenever we encounter an import or export we'll
+            // execute a shim function which informs us about its type so we
+            // can then generate the appropriate bindings.
+            //
+            // All descriptors are run up front, before the module is
+            // changed beyond removing the bindgen metadata, and the
+            // interpreter runs them on the module itself. Only descriptors it
+            // can't handle need a copy of the module (which for large debug
+            // builds is expensive) for wasmi.
+            let start = Instant::now();
+            let programs = extract_programs(&mut module)?;
+            stats.parse_time += start.elapsed();
+
+            let start = Instant::now();
+            let descriptors = run_descriptors(&module,
+                                              self.interpreter,
+                                              &programs,
+                                              &mut stats.interpreter_fallbacks)?;
+            stats.descriptor_time += start.elapsed();
+            stats.descriptors.extend(descriptors.iter().map(|(k, v)| (k.clone(), v.clone())));
+            for section in module.sections() {
+                if let Section::Custom(ref s) = *section {
+                    let name = s.name().to_string();
+                    if is_debug_section(&name) && !stats.debug_sections.contains(&name) {
+                        stats.debug_sections.push(name);
+                    }
+                }
+            }
+            for program in programs.iter() {
+                stats.exports += program.exports.len();
+                stats.imports += program.imports.len();
+                let krate = metadata::Crate {
+                    name: program.crate_name.clone(),
+                    version: program.version.clone(),
+                };
+                if !stats.crates.contains(&krate) {
+                    stats.crates.push(krate);
+                }
+            }
+            parsed.push(ParsedInput {
+                stem,
+                programs,
+                descriptors,
+                code,
+                code_contents,
+                shared_memory,
+                shared_classes: BTreeMap::new(),
+            });
+            modules.push(module);
+        }
+        let shared = shared_classes(&parsed.iter()
+            .map(|p| (&p.stem[..], &p.programs[..]))
+            .collect::<Vec<_>>())?;
+        for input in parsed.iter_mut() {
+            input.shared_classes = shared.clone();
+        }
+        Ok((parsed, modules))
+    }
+
+    /// Checks that bindings can be generated for the input, without writing
+    /// anything to disk.
+    ///
+    /// Failing descriptors, malformed bindgen metadata and every export or
+    /// import that bindings can't be generated for are reported together in
+    /// the returned error, one per line, as `generate` does once all the
+    /// descriptors could be run.
+    pub fn validate(&mut self) -> Result<(), Error> {
+        self.check_no_modules_globals()?;
+        self.check_flavor()?;
+        let mut inputs = Vec::new();
+        for (wasm, stem) in self.read_inputs()? {
+            let (mut module, shared_memory) = wasm.parse()?;
+            let programs = extract_programs(&mut module);
+            inputs.push((stem, module, shared_memory, programs));
+        }
+        let shared = shared_classes(&inputs.iter()
+            .map(|i| (&i.0[..], i.3.as_ref().map_or(&[][..], |p| &p[..])))
+            .collect::<Vec<_>>())?;
+        let multiple = inputs.len() > 1;
+        let mut errors = Vec::new();
+        let mut apis = Vec::new();
+        for (stem, module, shared_memory, programs) in inputs {
+            let mut input_errors = Vec::new();
+            let api = self.validate_input(module,
+                                          programs,
+                                          &stem,
+                                          shared_memory,
+                                          &shared,
+                                          &mut input_errors)?;
+            apis.push((stem.clone(), api));
+            errors.extend(input_errors.into_iter().map(|e| {
+                if multiple {
+                    format!("{}: {}", stem, e)
+                } else {
+                    e
+                }
+            }));
+        }
+        if multiple {
+            if let Err(e) = self.entry_exports(&apis, &shared) {
+                errors.push(e.to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Bindings(errors))
+        }
+    }
+
+    /// Validates a single input with the bindgen metadata read from it,
+    /// returning the names it exports to JS. The classes in `shared` are
+    /// exported by several inputs, see `shared_classes`.
+    fn validate_input(&self,
+                      mut module: Module,
+                      programs: Result<Vec<shared::Program>, Error>,
+                      stem: &str,
+                      shared_memory: bool,
+                      shared: &BTreeMap<String, String>,
+                      errors: &mut Vec<String>)
+        -> Result<BTreeSet<String>, Error>
+    {
+        let mut api = BTreeSet::new();
+        let programs = match programs {
+            Ok(programs) => Some(programs),
+            Err(e) => {
+                errors.push(format!("reading bindgen metadata: {}", e));
+                None
+            }
+        };
+        let descriptors = match run_descriptors(&module,
+                                                self.interpreter,
+                                                programs.as_ref().map_or(&[], |p| &p[..]),
+                                                &mut Vec::new()) {
+            Ok(descriptors) => descriptors,
+            Err(e) => {
+                errors.extend(e.to_string().lines().map(|l| l.to_string()));
+                return Ok(api)
+            }
+        };
+        if let Some(programs) = programs {
+            let mut cx = js::Context::new(self, &mut module, &descriptors, shared_memory);
+            cx.shared_classes = shared.iter()
+                .map(|(class, owner)| (class.clone(), owner == stem))
+                .collect();
+            for program in programs.iter() {
+                js::SubContext {
+                    program,
+                    cx: &mut cx,
+                }.generate(errors);
+            }
+            if let Err(e) = cx.finalize(stem) {
+                errors.push(format!("generating bindings: {}", e));
+            }
+            api = cx.api_names();
+        }
+        Ok(api)
+    }
+
+    /// Checks the names given for the `no_modules` global, which are used
+    /// as is in the generated JS.
+    fn check_no_modules_globals(&self) -> Result<(), Error> {
+        let names = self.no_modules_global.iter()
+            .chain(self.targets.iter().filter_map(|t| t.no_modules_global.as_ref()));
+        for name in names {
+            if !js::is_global_name(name) {
+                return Err(Error::Config(format!(
+                    "`{}` can't be used as the `no_modules` global, it must be a \
+                     JS identifier or a path of them like `my.app.wasm`",
+                    name,
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks at most one flavor of output is selected, rather than picking
+    /// one of them.
+    fn check_flavor(&self) -> Result<(), Error> {
+        let flavors = [
+            ("nodejs", self.nodejs),
+            ("browser", self.browser),
+            ("bundler", self.bundler),
+            ("deno", self.deno),
+            ("nodejs_module", self.nodejs_module),
+            ("no_modules", self.no_modules),
+        ];
+        let mut set = flavors.iter().filter(|f| f.1).map(|f| f.0);
+        match (set.next(), set.next()) {
+            (Some(first), Some(second)) => {
+                Err(Error::Config(format!("`{}` generates a different flavor of output, \
+                                           it can't be combined with `{}`", second, first)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn _generate<S: OutputSink>(&mut self, out: &mut S) -> Result<(), Error> {
+        self.check_no_modules_globals()?;
+        if self.umd && self.no_modules && self.inputs.len() > 1 {
+            return Err(Error::Config("`umd` can't be used with multiple inputs".to_string()))
+        }
+        self.check_flavor()?;
+        if self.preserve_code_offsets && self.build_id {
+            return Err(Error::Config("`build_id` adds to the code, it can't be combined \
+                                      with `preserve_code_offsets`".to_string()))
+        }
+        if self.preserve_code_offsets && self.multi_value {
+            return Err(Error::Config("`multi_value` adds to the code, it can't be combined \
+                                      with `preserve_code_offsets`".to_string()))
+        }
+        if self.preserve_code_offsets && self.split_i64 {
+            return Err(Error::Config("`split_i64` adds to the code, it can't be combined \
+                                      with `preserve_code_offsets`".to_string()))
+        }
+        if self.preserve_code_offsets && self.reference_types {
+            return Err(Error::Config("`reference_types` adds to the code, it can't be \
+                                      combined with `preserve_code_offsets`".to_string()))
+        }
+        if self.reference_types && self.multi_value {
+            return Err(Error::Config("`reference_types` and `multi_value` both rewrite the \
+                                      types of exports, they can't be combined".to_string()))
+        }
+        if self.reference_types && self.shared_runtime {
+            return Err(Error::Config("`reference_types` keeps JS values in a table of each \
+                                      wasm, which can't be shared with `shared_runtime`"
+                                      .to_string()))
+        }
+        if self.reference_types && self.emit_wat {
+            return Err(Error::Config("`emit_wat` prints the module before its `externref`s \
+                                      are patched in, it can't be combined with \
+                                      `reference_types`".to_string()))
+        }
+        if let Some(ref level) = self.wasm_opt {
+            if !["0", "1", "2", "3", "4", "s", "z"].contains(&level.as_str()) {
+                return Err(Error::Config(format!("`{}` isn't a wasm-opt level, expected \
+                                                  one of 0 to 4, s or z", level)))
+            }
+            if self.preserve_code_offsets {
+                return Err(Error::Config("`wasm_opt` rewrites the code, it can't be \
+                                          combined with `preserve_code_offsets`"
+                                          .to_string()))
+            }
+        }
+        let mut stats = BindgenStats::default();
+        let (parsed, mut modules) = self.parse_inputs(&mut stats)?;
+        stats.debug_sections_kept = self.keeps_debug() && !stats.debug_sections.is_empty();
+        stats.code_offsets_preserved = true;
+
+        // Nothing is written until all of the output is generated, so that
+        // a failure doesn't leave partial bindings behind.
+        let mut pending = PendingOutput::default();
+        if self.targets.is_empty() {
+            self.generate_modules(&parsed, modules, "", &mut WasmOutput::Own, &mut pending,
+                                  &mut stats)?;
+        } else {
+            let mut dirs = BTreeSet::new();
+            let mut glue_dir = None;
+            for target in self.targets.iter() {
+                if !dirs.insert(&target.dir) {
+                    return Err(Error::Config(format!("multiple targets are generated \
+                                                      into the `{}` directory",
+                                                     target.dir)))
+                }
+                if self.with_target(target).imports_wasm() {
+                    if let Some(dir) = glue_dir {
+                        return Err(Error::Config(format!("the targets in `{}` and `{}` both \
+                                                          import the wasm as an ES module, \
+                                                          which can only import the glue of \
+                                                          one of them; use the fetch-url or \
+                                                          deferred wasm import mode for the \
+                                                          other", dir, target.dir)))
+                    }
+                    glue_dir = Some(&target.dir);
+                }
+            }
+
+            // What's only there to generate bindings is removed once, before
+            // each target gets its own copies of the modules to generate its
+            // glue from. Only the last target rewrites and writes the wasm,
+            // keeping the internal exports the others' glue uses too.
+            stats.gc = if self.runs_gc() { Some(GcStats::default()) } else { None };
+            for (input, module) in parsed.iter().zip(modules.iter_mut()) {
+                let mut cx = js::Context::new(self,
+                                              module,
+                                              &input.descriptors,
+                                              input.shared_memory);
+                cx.prepare().map_err(|e| {
+                    Error::Bindings(vec![format!("generating bindings: {}", e)])
+                })?;
+                if let Some(ref mut gc) = stats.gc {
+                    gc.functions_removed += cx.gc_stats.functions_removed;
+                    gc.bytes_removed += cx.gc_stats.bytes_removed;
+                }
+            }
+            let mut required = BTreeMap::new();
+            for (i, target) in self.targets.iter().enumerate() {
+                let last = i + 1 == self.targets.len();
+                let modules = if last {
+                    mem::take(&mut modules)
+                } else {
+                    modules.clone()
+                };
+                let mut config = self.with_target(target);
+                config.shared_wasm = Some(SharedWasm {
+                    up: target.dir.split('/')
+                        .filter(|c| !c.is_empty() && *c != ".")
+                        .map(|_| "../")
+                        .collect(),
+                    glue_dir: glue_dir.cloned(),
+                });
+                let mut wasm = if last {
+                    WasmOutput::Shared(&required)
+                } else {
+                    WasmOutput::Skip(&mut required)
+                };
+                let dir = format!("{}/", target.dir);
+                config.generate_modules(&parsed, modules, &dir, &mut wasm, &mut pending,
+                                        &mut stats)?;
+            }
+        }
+        for (name, contents) in pending.files {
+            write(out, &name, &contents)?;
+        }
+
+        if self.verbose {
+            eprintln!("{}", stats);
+        }
+        self.stats = Some(stats);
+        Ok(())
+    }
+
+    /// Returns a copy of this configuration, without any inputs or targets.
+    fn config(&self) -> Bindgen {
+        Bindgen {
+            inputs: Vec::new(),
+            input_set_twice: false,
+            nodejs: self.nodejs,
+            nodejs_async: self.nodejs_async,
+            browser: self.browser,
+            bundler: self.bundler,
+            deno: self.deno,
+            nodejs_module: self.nodejs_module,
+            umd: self.umd,
+            out_name: self.out_name.clone(),
+            no_modules: self.no_modules,
+            no_modules_global: self.no_modules_global.clone(),
+            debug: self.debug,
+            typescript: self.typescript,
+            demangle: self.demangle,
+            keep_debug: self.keep_debug,
+            remove_name_section: self.remove_name_section,
+            remove_producers_section: self.remove_producers_section,
+            preserve_code_offsets: self.preserve_code_offsets,
+            wasm_opt: self.wasm_opt.clone(),
+            multi_value: self.multi_value,
+            split_i64: self.split_i64,
+            reference_types: self.reference_types,
+            emit_wat: self.emit_wat,
+            source_map: self.source_map,
+            emit_metadata: self.emit_metadata,
+            output_names: self.output_names.clone(),
+            interpreter: self.interpreter,
+            run_gc: self.run_gc,
+            verbose: self.verbose,
+            minify_js: self.minify_js,
+            csp_safe: self.csp_safe,
+            js_prelude: self.js_prelude.clone(),
+            js_epilogue: self.js_epilogue.clone(),
+            ts_prelude: self.ts_prelude.clone(),
+            ts_epilogue: self.ts_epilogue.clone(),
+            es_target: self.es_target,
+            wasm_import_mode: self.wasm_import_mode,
+            split_output: self.split_output,
+            shared_runtime: self.shared_runtime,
+            heap_stack_size: self.heap_stack_size,
+            heap_slab_size: self.heap_slab_size,
+            heap_warning_threshold: self.heap_warning_threshold,
+            string_interning: self.string_interning,
+            string_intern_capacity: self.string_intern_capacity,
+            weak_refs: self.weak_refs,
+            build_id: self.build_id,
+            local_module_roots: self.local_module_roots.clone(),
+            export_prefixes: self.export_prefixes.clone(),
+            targets: Vec::new(),
+            shared_wasm: self.shared_wasm.clone(),
+            shared_classes_global: self.shared_classes_global.clone(),
+            stats: None,
+        }
+    }
+
+    /// Returns a copy of this configuration which generates `target`.
+    fn with_target(&self, target: &Target) -> Bindgen {
+        let mut config = self.config();
+        config.nodejs = target.kind == TargetKind::Nodejs;
+        config.browser = target.kind == TargetKind::Browser;
+        config.bundler = target.kind == TargetKind::Bundler;
+        config.deno = target.kind == TargetKind::Deno;
+        config.nodejs_module = target.kind == TargetKind::NodejsModule;
+        config.no_modules = target.kind == TargetKind::NoModules;
+        if let Some(ref name) = target.no_modules_global {
+            config.no_modules_global = Some(name.clone());
+        }
+        config.typescript = target.typescript.unwrap_or(self.typescript);
+        config.es_target = target.es_target.unwrap_or(self.es_target);
+        config.wasm_import_mode = target.wasm_import_mode.unwrap_or(self.wasm_import_mode);
+        config
+    }
+
+    /// Name of the global the `no_modules` glue of one of several inputs is
+    /// assigned to, for the entry script to pick up.
+    fn no_modules_input_global(&self, stem: &str) -> String {
+        let stem = stem.chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        format!("{}_{}", self.no_modules_global_name(), stem)
+    }
+
+    /// With `csp_safe`, fails if the JS `js` of the file `file` uses what a
+    /// Content Security Policy without `unsafe-eval` blocks.
+    fn check_csp(&self, file: &str, js: &str) -> Result<(), Error> {
+        if !self.csp_safe {
+            return Ok(())
+        }
+        match js::csp_violation(js) {
+            Some(what) => Err(Error::Bindings(vec![format!(
+                "`{}` uses `{}`, which isn't allowed with `csp_safe`",
+                file, what,
+            )])),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the glue imports `ready` from a `_bg.js` module which fetches
+    /// and instantiates the wasm, see `WasmImportMode::FetchUrl`.
+    fn fetches_wasm(&self) -> bool {
+        !self.nodejs && !self.no_modules &&
+            (self.awaits_wasm() || self.wasm_import_mode == WasmImportMode::FetchUrl)
+    }
+
+    /// Whether the glue is an ES module waiting for the wasm at its top
+    /// level, for Deno or `nodejs_module`, which both only resolve imports
+    /// naming the file.
+    fn awaits_wasm(&self) -> bool {
+        self.deno || self.nodejs_module
+    }
+
+    /// Whether the glue imports the wasm as an ES module, see
+    /// `WasmImportMode::EsModule`.
+    fn imports_wasm(&self) -> bool {
+        !self.nodejs && !self.no_modules && !self.awaits_wasm() &&
+            self.wasm_import_mode == WasmImportMode::EsModule
+    }
+
+    /// The module the wasm imports the glue of the input `stem` as. When the
+    /// wasm is shared by several targets it's the same for all of them, see
+    /// `Bindgen::targets`.
+    fn glue_module(&self, stem: &str) -> String {
+        let names = &self.output_names;
+        match self.shared_wasm {
+            Some(SharedWasm { glue_dir: Some(ref dir), .. }) => {
+                format!("./{}/{}", dir, &names.js_import(stem, false)[2..])
+            }
+            Some(_) => names.js_import(stem, false),
+            None => names.js_import(stem, self.awaits_wasm()),
+        }
+    }
+
+    /// The path from the glue to `path`, as the wasm is referred to from the
+    /// output directory, like `out_bg.wasm` or `./out_bg`. It differs when the
+    /// wasm is shared by several targets, see `Bindgen::targets`.
+    fn wasm_path(&self, path: &str) -> String {
+        match self.shared_wasm {
+            Some(ref shared) if !shared.up.is_empty() => {
+                format!("{}{}", shared.up, path.trim_start_matches("./"))
+            }
+            _ => path.to_string(),
+        }
+    }
+
+    /// Whether the classes of the glue are generated into their own modules,
+    /// see `Bindgen::split_output`.
+    fn splits_output(&self) -> bool {
+        self.split_output && !self.nodejs && !self.no_modules
+    }
+
+    /// Whether the heap and stack management goes into the runtime module,
+    /// see `Bindgen::shared_runtime`.
+    fn shares_runtime(&self) -> bool {
+        self.shared_runtime && !self.nodejs && !self.no_modules
+    }
+
+    /// Whether the debug sections are kept, see `Bindgen::keep_debug` and
+    /// `Bindgen::preserve_code_offsets`.
+    fn keeps_debug(&self) -> bool {
+        self.keep_debug || self.preserve_code_offsets
+    }
+
+    /// Whether the gc pass runs, see `Bindgen::gc`.
+    fn runs_gc(&self) -> bool {
+        self.run_gc && !self.preserve_code_offsets
+    }
+
+    fn no_modules_global_name(&self) -> &str {
+        self.no_modules_global.as_deref().unwrap_or("wasm_bindgen")
+    }
+
+    /// Generates bindings for each of the `parsed` inputs in a single flavor
+    /// of output, writing each file to `out` with its name prefixed by `dir`,
+    /// and doing with the wasm what `wasm` says.
+    ///
+    /// With more than one input an entry module re-exporting all of them is
+    /// generated as well.
+    fn generate_modules<S: OutputSink>(&self,
+                                       parsed: &[ParsedInput],
+                                       modules: Vec<Module>,
+                                       dir: &str,
+                                       wasm: &mut WasmOutput,
+                                       out: &mut S,
+                                       stats: &mut BindgenStats)
+        -> Result<(), Error>
+    {
+        // Sizes of the output are per flavor, not summed over all of them.
+        // A wasm shared by several targets is only counted once.
+        stats.classes = 0;
+        if let WasmOutput::Own = *wasm {
+            stats.output_wasm_bytes = 0;
+            stats.gc = if self.runs_gc() { Some(GcStats::default()) } else { None };
+        }
+
+        let multiple = parsed.len() > 1;
+        let out = &mut WrittenNames { sink: out, names: BTreeSet::new() };
+        let shared = parsed.first().map(|p| p.shared_classes.clone()).unwrap_or_default();
+        let mut apis = Vec::new();
+        let mut all_local_modules = BTreeMap::new();
+        let mut runtime = Vec::new();
+        let mut errors = Vec::new();
+        for (input, module) in parsed.iter().zip(modules) {
+            let result = if multiple && self.no_modules {
+                let mut config = self.config();
+                config.no_modules_global = Some(self.no_modules_input_global(&input.stem));
+                config.shared_classes_global = Some(self.no_modules_global_name().to_string());
+                config.generate_target(module, input, dir, wasm, out, stats)
+            } else {
+                self.generate_target(module, input, dir, wasm, out, stats)
+            };
+            // Carry on with the other inputs to report their problems too.
+            let (api, local_modules, parts) = match result {
+                Ok(result) => result,
+                Err(e) if !multiple => return Err(e),
+                Err(e) => {
+                    errors.extend(e.to_string().lines()
+                        .map(|l| format!("{}: {}", input.stem, l)));
+                    continue
+                }
+            };
+            apis.push((input.stem.clone(), api));
+            for (key, js) in parts {
+                if !runtime.iter().any(|&(k, _)| k == key) {
+                    runtime.push((key, js));
+                }
+            }
+            for (module, items) in local_modules {
+                all_local_modules.entry(module)
+                    .or_insert_with(BTreeSet::new)
+                    .extend(items);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::Bindings(errors))
+        }
+        if !runtime.is_empty() {
+            let js = js::runtime_module(self, &runtime);
+            let js = if self.minify_js { js::minify(&js) } else { js };
+            let file = format!("{}.js", RUNTIME_STEM);
+            self.check_csp(&file, &js)?;
+            write(out, &format!("{}{}", dir, file), js.as_bytes())?;
+        }
+
+        if !shared.is_empty() {
+            self.generate_classes(parsed, &shared, dir, out)?;
+        }
+        if multiple {
+            self.generate_entry(&apis, &shared, dir, out)?;
+        }
+        let generated = out.names.clone();
+        self.copy_local_modules(&all_local_modules, dir, &generated, out)
+    }
+
+    /// Generates bindings for a single input, see `TargetOutput` and
+    /// `Bindgen::shared_runtime`.
+    fn generate_target<S: OutputSink>(&self,
+                                      mut module: Module,
+                                      input: &ParsedInput,
+                                      dir: &str,
+                                      wasm: &mut WasmOutput,
+                                      out: &mut S,
+                                      stats: &mut BindgenStats)
+        -> Result<TargetOutput, Error>
+    {
+        let stem = &input.stem;
+        let start = Instant::now();
+        let build_id = if self.build_id {
+            let id = build_id(input);
+            export_build_id(&mut module, id);
+            Some(format!("{:08x}", id))
+        } else {
+            None
+        };
+        let (js, ts, split, gc_stats, classes, api, bindings, mut local_modules,
+             multi_value_types, externref_patches, runtime) = {
+            let mut cx = js::Context::new(self,
+                                          &mut module,
+                                          &input.descriptors,
+                                          input.shared_memory);
+            cx.build_id = build_id;
+            cx.shared_classes = input.shared_classes.iter()
+                .map(|(class, owner)| (class.clone(), owner == stem))
+                .collect();
+            match *wasm {
+                WasmOutput::Own => {}
+                WasmOutput::Skip(_) => {
+                    cx.prepared = true;
+                    cx.rewrite_wasm = false;
+                }
+                WasmOutput::Shared(required) => {
+                    cx.prepared = true;
+                    if let Some(names) = required.get(stem) {
+                        cx.required_internal_exports.extend(names.iter().cloned());
+                    }
+                }
+            }
+            // All the problems are collected so that they can be reported at
+            // once, see `SubContext::generate`.
+            let mut errors = Vec::new();
+            for program in input.programs.iter() {
+                js::SubContext {
+                    program,
+                    cx: &mut cx,
+                }.generate(&mut errors);
+            }
+            if !errors.is_empty() {
+                return Err(Error::Bindings(errors))
+            }
+            let (js, ts) = cx.finalize(stem).map_err(|e| {
+                Error::Bindings(vec![format!("generating bindings: {}", e)])
+            })?;
+            let api = cx.api_names();
+            if let WasmOutput::Skip(ref mut required) = *wasm {
+                required.entry(stem.clone())
+                    .or_insert_with(BTreeSet::new)
+                    .extend(cx.required_internal_exports.iter().cloned());
+            }
+            let runtime = mem::take(&mut cx.runtime);
+            let split = mem::take(&mut cx.split_modules);
+            let bindings = mem::take(&mut cx.metadata);
+            (js, ts, split, cx.gc_stats, cx.exported_classes.len(), api, bindings,
+             cx.local_modules, cx.multi_value_types, cx.externref_patches, runtime)
+        };
+        stats.js_time += start.elapsed();
+        if let Some(ref mut gc) = stats.gc {
+            gc.functions_removed += gc_stats.functions_removed;
+            gc.bytes_removed += gc_stats.bytes_removed;
+        }
+        stats.classes += classes;
+        let start = Instant::now();
+
+        let locations = if self.source_map {
+            js::source_map::locations(&input.programs)
+        } else {
+            BTreeMap::new()
+        };
+        let modules = Some((stem.clone(), js, ts)).into_iter().chain(split);
+        for (stem, js, ts) in modules {
+            stats.unminified_js_bytes += js.len();
+            let js = if self.minify_js { js::minify(&js) } else { js };
+            // Only now that the glue won't be touched anymore are the prelude
+            // and epilogue put in place of their placeholders.
+            let js = js::insert_verbatim(&js, js::PRELUDE, &self.js_prelude);
+            let js = js::insert_verbatim(&js, js::EPILOGUE, &self.js_epilogue);
+            let ts = js::insert_verbatim(&ts, js::PRELUDE, &self.ts_prelude);
+            let ts = js::insert_verbatim(&ts, js::EPILOGUE, &self.ts_epilogue);
+
+            // Only the glue of the input itself is renamed, not the modules
+            // split out of it.
+            let (js_file, ts_file) = if stem == input.stem {
+                (self.output_names.js_file(&stem), self.output_names.typescript_file(&stem))
+            } else {
+                (format!("{}.js", stem), format!("{}.d.ts", stem))
+            };
+            let mut js = js;
+            if self.source_map {
+                let map = js::source_map::source_map(&js, &js_file, &locations);
+                js.push_str(&format!("//# sourceMappingURL={}.map\n", js_file));
+                write(out, &format!("{}{}.map", dir, js_file), map.as_bytes())?;
+            }
+            stats.js_bytes += js.len();
+            stats.ts_bytes += ts.len();
+            self.check_csp(&js_file, &js)?;
+            write(out, &format!("{}{}", dir, js_file), js.as_bytes())?;
+
+            if self.typescript {
+                write(out, &format!("{}{}", dir, ts_file), ts.as_bytes())?;
+            }
+        }
+
+        let names = &self.output_names;
+        if self.emit_metadata {
+            let mut json = serde_json::to_string_pretty(&bindings).unwrap();
+            json.push('\n');
+            write(out, &format!("{}{}", dir, names.metadata_file(stem)), json.as_bytes())?;
+        }
+        if self.nodejs || self.fetches_wasm() {
+            let shim = self.generate_wasm_loader(&module,
+                                                 stem,
+                                                 input.shared_memory,
+                                                 &mut local_modules);
+            let shim = if self.minify_js {
+                js::minify(&shim)
+            } else {
+                js::reindent(&shim)
+            };
+            let shim_file = names.wasm_loader_file(stem);
+            self.check_csp(&shim_file, &shim)?;
+            write(out, &format!("{}{}", dir, shim_file), shim.as_bytes())?;
+        }
+
+        let dir = match *wasm {
+            WasmOutput::Own => dir,
+            WasmOutput::Skip(_) => {
+                stats.serialize_time += start.elapsed();
+                return Ok((api, local_modules, runtime))
+            }
+            WasmOutput::Shared(_) => "",
+        };
+        if self.typescript {
+            // The typings are of the wasm as written out, after the changes
+            // below.
+            let ts = wasm2es6js::typescript_with(&module, |ty, value| {
+                if value.is_none() && multi_value_types.contains(&ty) {
+                    return Some("[number, number]")
+                }
+                externref_patches.as_ref().and_then(|p| p.typescript_type(ty, value))
+            });
+            write(out, &format!("{}{}", dir, names.wasm_typescript_file(stem)), ts.as_bytes())?;
+        }
+        let wat = if self.emit_wat {
+            Some(wat::print(&module, input.shared_memory))
+        } else {
+            None
+        };
+        let mut wasm_bytes = parity_wasm::serialize(module)?;
+        if input.shared_memory {
+            share_memory(&mut wasm_bytes);
+        }
+        if let Some(ref contents) = input.code_contents {
+            replace_code_section(&mut wasm_bytes, contents);
+        }
+        if !multi_value_types.is_empty() {
+            return_two_values(&mut wasm_bytes, &multi_value_types);
+        }
+        if let Some(ref patches) = externref_patches {
+            js::externref::patch(&mut wasm_bytes, patches);
+        }
+        if let Some(ref level) = self.wasm_opt {
+            wasm_bytes = self.run_wasm_opt(level, &wasm_bytes, input)?;
+        }
+        write(out, &format!("{}{}", dir, names.wasm_file(stem)), &wasm_bytes)?;
+        stats.output_wasm_bytes += wasm_bytes.len();
+        if let Some(wat) = wat {
+            write(out, &format!("{}{}", dir, names.wat_file(stem)), wat.as_bytes())?;
+        }
+
+        // Generating JS usually removes, renumbers or re-encodes functions,
+        // after which debug info describing the input's code is misleading.
+        if code_section(&wasm_bytes).map(|c| c.1) != input.code.map(|c| c.1) {
+            stats.code_offsets_preserved = false;
+            if self.keeps_debug() && has_debug_sections(&wasm_bytes) {
+                eprintln!("warning: the debug sections of `{}` were kept but the \
+                           code they describe was rewritten, so the code offsets \
+                           they refer to may be wrong", stem);
+            }
+        }
+        stats.serialize_time += start.elapsed();
+        Ok((api, local_modules, runtime))
+    }
+
+    /// Runs `wasm-opt` at `level` over the serialized output `wasm` of
+    /// `input`, see `Bindgen::wasm_opt`.
+    fn run_wasm_opt(&self, level: &str, wasm: &[u8], input: &ParsedInput)
+        -> Result<Vec<u8>, Error>
+    {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let wasm_opt = env::var_os("WASM_OPT").unwrap_or_else(|| "wasm-opt".into());
+        let base = env::temp_dir().join(format!("wasm-bindgen-{}-{}-{}",
+                                                process::id(),
+                                                NEXT.fetch_add(1, Ordering::SeqCst),
+                                                input.stem));
+        let before = base.with_extension("wasm");
+        let after = base.with_extension("opt.wasm");
+        fs::write(&before, wasm).map_err(|error| {
+            Error::Io { what: format!("failed to write `{}`", before.display()), error }
+        })?;
+
+        let mut cmd = Command::new(&wasm_opt);
+        cmd.arg(format!("-O{}", level)).arg(&before).arg("-o").arg(&after);
+        if self.keeps_debug() {
+            cmd.arg("--debuginfo");
+        }
+        if input.shared_memory {
+            cmd.arg("--enable-threads");
+        }
+        if self.multi_value {
+            cmd.arg("--enable-multivalue");
+        }
+        if self.reference_types {
+            cmd.arg("--enable-reference-types");
+        }
+        let output = cmd.output();
+        drop(fs::remove_file(&before));
+        let output = output.map_err(|error| {
+            Error::Io {
+                what: format!("failed to run `{}`, which `wasm_opt` needs",
+                              Path::new(&wasm_opt).display()),
+                error,
+            }
+        })?;
+        if !output.status.success() {
+            drop(fs::remove_file(&after));
+            return Err(Error::Wasm(format!("wasm-opt failed to optimize `{}` ({}):\n{}",
+                                           input.stem,
+                                           output.status,
+                                           String::from_utf8_lossy(&output.stderr))))
+        }
+        let ret = fs::read(&after).map_err(|error| {
+            Error::Io { what: format!("failed to read `{}`", after.display()), error }
+        });
+        drop(fs::remove_file(&after));
+        ret
+    }
+
+    /// Works out the name each input's exports get in the entry module,
+    /// failing if two inputs would export the same name. The classes in
+    /// `shared` are left out, as the entry exports them from the classes
+    /// module.
+    fn entry_exports(&self,
+                     apis: &[(String, BTreeSet<String>)],
+                     shared: &BTreeMap<String, String>)
+        -> Result<Vec<Vec<(String, String)>>, Error>
+    {
+        let mut owners = BTreeMap::new();
+        let mut ret = Vec::new();
+        for (stem, api) in apis.iter() {
+            let prefix = self.export_prefixes.get(stem).map(|s| &**s).unwrap_or("");
+            let mut exports = Vec::new();
+            for name in api.iter().filter(|name| !shared.contains_key(*name)) {
+                let exported = format!("{}{}", prefix, name);
+                if let Some(prev) = owners.insert(exported.clone(), stem) {
+                    return Err(Error::Config(format!(
+                        "`{}` is exported by both the `{}` and `{}` inputs, use \
+                         `export_prefix` to rename the exports of one of them",
+                        exported, prev, stem,
+                    )))
+                }
+                exports.push((name.clone(), exported));
+            }
+            ret.push(exports);
+        }
+        Ok(ret)
+    }
+
+    /// Generates the entry module for multiple inputs, which re-exports the
+    /// exports of each input's glue and the classes in `shared`.
+    fn generate_entry<S: OutputSink>(&self,
+                                     apis: &[(String, BTreeSet<String>)],
+                                     shared: &BTreeMap<String, String>,
+                                     dir: &str,
+                                     out: &mut S) -> Result<(), Error> {
+        let exports = self.entry_exports(apis, shared)?;
+        let names = &self.output_names;
+        let classes = shared.keys().cloned().collect::<Vec<_>>();
+
+        let mut ts = String::new();
+        if !classes.is_empty() {
+            let ext = if self.awaits_wasm() { ".js" } else { "" };
+            ts.push_str(&format!("export {{ {} }} from './{}{}';\n",
+                                 classes.join(", "), CLASSES_STEM, ext));
+        }
+        for ((stem, _), exports) in apis.iter().zip(exports.iter()) {
+            if exports.is_empty() {
+                continue
+            }
+            ts.push_str(&format!("export {{ {} }} from '{}';\n",
+                                 export_list(exports), names.js_import(stem, self.awaits_wasm())));
+        }
 
-impl<E: std::error::Error> From<E> for Error {
-    fn from(e: E) -> Error {
-        Error(e.to_string())
-    }
-}
+        let js = if self.nodejs {
+            let mut js = String::new();
+            js.push_str("const inputs = [\n");
+            for (stem, _) in apis.iter() {
+                js.push_str(&format!("    require('{}'),\n", names.js_import(stem, false)));
+            }
+            js.push_str("];\n");
+            for (i, exports) in exports.iter().enumerate() {
+                for (name, exported) in exports.iter() {
+                    js.push_str(&format!("module.exports.{} = inputs[{}].{};\n",
+                                         exported, i, name));
+                }
+            }
+            for class in classes.iter() {
+                js.push_str(&format!("module.exports.{} = require('./{}').{0};\n",
+                                     class, CLASSES_STEM));
+            }
+            if self.nodejs_async && self.es_target == EsVersion::Es5 {
+                js.push_str("module.exports.ready = Promise.all(inputs.map(function(m) { \
+                             return m.ready; }))\n    .then(function() {});\n");
+                ts.push_str("export const ready: Promise<void>;\n");
+            } else if self.nodejs_async {
+                js.push_str("module.exports.ready = Promise.all(inputs.map(m => m.ready))\n    \
+                             .then(() => {});\n");
+                ts.push_str("export const ready: Promise<void>;\n");
+            }
+            js
+        } else if self.no_modules {
+            // The classes module assigned the shared classes to the global
+            // which is about to be replaced.
+            let global = self.no_modules_global_name();
+            let mut fields = String::new();
+            for class in classes.iter() {
+                fields.push_str(&format!("\n    {}: __global.{}.{0},", class, global));
+            }
+            for (i, exports) in exports.iter().enumerate() {
+                for (name, exported) in exports.iter() {
+                    fields.push_str(&format!("\n    {}: inputs[{}].{},", exported, i, name));
+                }
+            }
+            let inputs = apis.iter()
+                .map(|(stem, _)| format!("__global.{}", self.no_modules_input_global(stem)))
+                .collect::<Vec<_>>();
+            let (map, then, assign) = if self.es_target == EsVersion::Es5 {
+                ("function(m, i) { return m(wasm_paths && wasm_paths[i]); }",
+                 "function() { return init; }",
+                 format!("const exports = {{{}\n}};\n\
+                          Object.keys(exports).forEach(function(key) {{ \
+                              init[key] = exports[key]; }});\n{}",
+                         fields, js::assign_global(global, "init")))
+            } else {
+                let value = format!("Object.assign(init, {{{}\n}})", fields);
+                ("(m, i) => m(wasm_paths && wasm_paths[i])",
+                 "() => init",
+                 js::assign_global(global, &value))
+            };
 
-impl Bindgen {
-    pub fn new() -> Bindgen {
-        Bindgen {
-            path: None,
-            nodejs: false,
-            browser: false,
-            no_modules: false,
-            no_modules_global: None,
-            debug: false,
-            typescript: false,
-            demangle: true,
+            // The inputs' declarations are of global namespaces too, which
+            // the entry's namespace re-exports from.
+            let mut declarations = String::new();
+            for (class, owner) in shared.iter() {
+                declarations.push_str(&format!("export import {} = {}.{0};\n",
+                                               class, self.no_modules_input_global(owner)));
+            }
+            for ((stem, _), exports) in apis.iter().zip(exports.iter()) {
+                for (name, exported) in exports.iter() {
+                    declarations.push_str(&format!("export import {} = {}.{};\n",
+                                                   exported,
+                                                   self.no_modules_input_global(stem),
+                                                   name));
+                }
+            }
+            ts = apis.iter()
+                .map(|(stem, _)| {
+                    format!("/// <reference path=\"./{}\" />\n", names.typescript_file(stem))
+                })
+                .collect();
+            ts.push_str(&js::reindent(&js::global_namespace(global,
+                                                            "wasm_paths?: (string | ArrayBuffer | ArrayBufferView)[]",
+                                                            &declarations)));
+
+            let js = js::deindent(&format!("
+                (function() {{
+                    {global_object}
+                    const inputs = [{inputs}];
+                    function init(wasm_paths) {{
+                        return Promise.all(inputs.map({map}))
+                            .then({then});
+                    }};
+                    {assign}
+                }})();
+            ",
+                global_object = js::GLOBAL_OBJECT,
+                inputs = inputs.join(", "),
+                map = map,
+                then = then,
+                assign = assign.replace("\n", "\n                    "),
+            )).trim().to_string() + "\n";
+            if self.es_target == EsVersion::Es5 { js::var_declarations(&js) } else { js }
+        } else {
+            // Each input's wasm is loaded separately, which is done once all
+            // of them are.
+            let mut js = ts.clone();
+            let es5 = self.es_target == EsVersion::Es5;
+            match self.wasm_import_mode {
+                // Glue awaiting the wasm is ready once it's imported.
+                _ if self.awaits_wasm() => {}
+                WasmImportMode::EsModule => {}
+                WasmImportMode::FetchUrl => {
+                    let mut readies = Vec::new();
+                    for (i, (stem, _)) in apis.iter().enumerate() {
+                        js.push_str(&format!("import {{ ready as ready{} }} from '{}';\n",
+                                             i, names.js_import(stem, false)));
+                        readies.push(format!("ready{}", i));
+                    }
+                    js.push_str(&format!("export const ready = Promise.all([{}]).then({});\n",
+                                         readies.join(", "),
+                                         if es5 { "function() {}" } else { "() => {}" }));
+                    ts.push_str("export const ready: Promise<void>;\n");
+                }
+                WasmImportMode::Deferred => {
+                    let mut calls = String::new();
+                    for (i, (stem, _)) in apis.iter().enumerate() {
+                        js.push_str(&format!("import {{ setWasm as setWasm{} }} from '{}';\n",
+                                             i, names.js_import(stem, false)));
+                        calls.push_str(&format!("    setWasm{0}(instances[{0}]);\n", i));
+                    }
+                    js.push_str(&format!("export function setWasm(instances) {{\n{}}}\n", calls));
+                    ts.push_str("export function setWasm(instances: WebAssembly.Instance[]): void;\n");
+                }
+            }
+            js
+        };
+
+        write(out, &format!("{}{}.js", dir, ENTRY_STEM), js.as_bytes())?;
+        if self.typescript {
+            write(out, &format!("{}{}.d.ts", dir, ENTRY_STEM), ts.as_bytes())?;
         }
+        Ok(())
     }
 
-    pub fn input_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Bindgen {
-        self.path = Some(path.as_ref().to_path_buf());
-        self
+    /// Generates the classes module, defining each of the classes in
+    /// `shared` once for all the inputs exporting it, see `shared_classes`.
+    fn generate_classes<S: OutputSink>(&self,
+                                       parsed: &[ParsedInput],
+                                       shared: &BTreeMap<String, String>,
+                                       dir: &str,
+                                       out: &mut S) -> Result<(), Error> {
+        let classes = shared.iter()
+            .map(|(class, owner)| {
+                let owner = parsed.iter().find(|p| p.stem == *owner).unwrap();
+                let statics = owner.programs.iter()
+                    .flat_map(|p| p.exports.iter())
+                    .filter(|e| e.class.as_ref() == Some(class) && !e.method)
+                    .map(|e| e.function.name.clone())
+                    .collect();
+                let superclass = owner.programs.iter()
+                    .flat_map(|p| p.structs.iter())
+                    .find(|s| s.name == *class)
+                    .and_then(|s| s.superclass.clone());
+                (class.clone(), statics, superclass)
+            })
+            .collect::<Vec<_>>();
+        let js = js::classes_module(self, &classes);
+        let js = if self.minify_js { js::minify(&js) } else { js };
+        let file = format!("{}.js", CLASSES_STEM);
+        self.check_csp(&file, &js)?;
+        write(out, &format!("{}{}", dir, file), js.as_bytes())?;
+
+        // The typings are those of the owner's class, which the shared one
+        // creates and forwards its static methods to. Without modules the
+        // entry's namespace declares them.
+        if self.typescript && !self.no_modules {
+            let mut ts = String::from("/* tslint:disable */\n");
+            for (class, owner) in shared.iter() {
+                ts.push_str(&format!("export {{ {} }} from '{}';\n",
+                                     class, self.output_names.js_import(owner, self.awaits_wasm())));
+            }
+            write(out, &format!("{}{}.d.ts", dir, CLASSES_STEM), ts.as_bytes())?;
+        }
+        Ok(())
     }
 
-    pub fn nodejs(&mut self, node: bool) -> &mut Bindgen {
-        self.nodejs = node;
-        self
+    /// Generates the `_bg.js` module which instantiates the wasm, for
+    /// `nodejs`, `nodejs_module`, `deno` or `WasmImportMode::FetchUrl` output.
+    fn generate_wasm_loader(&self,
+                                 m: &Module,
+                                 stem: &str,
+                                 shared_memory: bool,
+                                 local_modules: &mut BTreeMap<String, BTreeSet<String>>)
+        -> String
+    {
+        let mut imports = BTreeMap::new();
+        let mut values = Vec::new();
+        if let Some(i) = m.import_section() {
+            for i in i.entries() {
+                // Memories and tables can't come from a JS module, so we
+                // create them here to match the import's descriptor.
+                let value = match *i.external() {
+                    External::Memory(ref m) => js::memory_constructor(m, shared_memory),
+                    External::Table(ref t) => {
+                        format!("new WebAssembly.Table({})",
+                                js_limits(t.limits(), "element: 'anyfunc', "))
+                    }
+                    _ => {
+                        imports.entry(i.module()).or_insert_with(Vec::new).push(i.field());
+                        continue
+                    }
+                };
+                values.push((i.module(), i.field(), value));
+            }
+        }
+
+        // The JS glue itself always lives next to this shim, but the wasm
+        // may be shared with other targets.
+        let glue = self.glue_module(stem);
+        let wasm = self.wasm_path(&self.output_names.wasm_file(stem));
+        let wasm_url = self.wasm_path(&format!("./{}", self.output_names.wasm_file(stem)));
+        let mut shim = String::new();
+        if self.nodejs_module {
+            shim.push_str("import { promises as fs } from 'fs';\n");
+        }
+        if self.awaits_wasm() {
+            shim.push_str(&format!("const wasm_url = new URL('{}', import.meta.url);\n", wasm_url));
+        } else if !self.nodejs {
+            shim.push_str(&format!("import wasm_url from '{}';\n", wasm_url));
+        }
+        shim.push_str("let imports = {};\n");
+        for (i, (module, fields)) in imports.iter().enumerate() {
+            let mut require = if *module == glue {
+                self.output_names.js_import(stem, self.awaits_wasm())
+            } else {
+                module.to_string()
+            };
+            if *module != glue && !self.local_module_roots.is_empty() {
+                if let Some(path) = local_module_path(module) {
+                    local_modules.entry(module.to_string())
+                        .or_default()
+                        .extend(fields.iter().map(|s| s.to_string()));
+                    require = path;
+                }
+            }
+            if self.nodejs {
+                shim.push_str(&format!("imports['{}'] = require('{}');\n", module, require));
+            } else {
+                shim.push_str(&format!("import * as import{0} from '{1}';\n\
+                                        imports['{2}'] = import{0};\n",
+                                       i, require, module));
+            }
+        }
+
+        if shared_memory {
+            shim.push_str(js::SHARED_MEMORY_CHECK);
+        }
+        let mut value_modules = BTreeSet::new();
+        for &(module, field, ref value) in values.iter() {
+            if value_modules.insert(module) {
+                if imports.contains_key(module) {
+                    shim.push_str(&format!("imports['{0}'] = Object.assign({{}}, imports['{0}']);\n",
+                                           module));
+                } else {
+                    shim.push_str(&format!("imports['{}'] = {{}};\n", module));
+                }
+            }
+            shim.push_str(&format!("imports['{}']['{}'] = {};\n", module, field, value));
+        }
+
+        // The JS glue always accesses memory through the `memory` export, so
+        // if it's imported instead make it available under that name.
+        let memory = values.iter().find(|v| v.2.starts_with("new WebAssembly.Memory"));
+        let exports = match memory {
+            Some(&(module, field, _)) => {
+                format!("Object.assign({{ memory: imports['{}']['{}'] }}, instance.exports)",
+                        module, field)
+            }
+            None => "instance.exports".to_string(),
+        };
+
+        if !self.nodejs && !self.nodejs_module {
+            shim.push_str(&js::instantiate_function(self.csp_safe));
+        }
+        if self.nodejs_module {
+            shim.push_str(&format!("
+                export const ready = fs.readFile(wasm_url)
+                    .then(bytes => WebAssembly.instantiate(bytes, imports))
+                    .then(({{instance}}) => {});
+            ", exports));
+        } else if self.deno {
+            shim.push_str(&format!("
+                const wasm_source = wasm_url.protocol === 'file:'
+                    ? Deno.readFile(wasm_url)
+                    : Promise.resolve(wasm_url);
+                export const ready = wasm_source
+                    .then(source => __wbg_instantiate(source, imports))
+                    .then(({{instance}}) => {});
+            ", exports));
+        } else if !self.nodejs && self.es_target == EsVersion::Es5 {
+            shim.push_str(&format!("
+                export const ready = __wbg_instantiate(wasm_url, imports)
+                    .then(function(result) {{
+                        const instance = result.instance;
+                        return {};
+                    }});
+            ", exports));
+        } else if !self.nodejs {
+            shim.push_str(&format!("
+                export const ready = __wbg_instantiate(wasm_url, imports)
+                    .then(({{instance}}) => {});
+            ", exports));
+        } else if self.nodejs_async && self.es_target == EsVersion::Es5 {
+            shim.push_str(&format!("
+                const join = require('path').join;
+                module.exports.ready = require('fs').promises
+                    .readFile(join(__dirname, '{}'))
+                    .then(function(bytes) {{ return WebAssembly.instantiate(bytes, imports); }})
+                    .then(function(result) {{
+                        const instance = result.instance;
+                        return {};
+                    }});
+            ", wasm, exports));
+        } else if self.nodejs_async {
+            shim.push_str(&format!("
+                const join = require('path').join;
+                module.exports.ready = require('fs').promises
+                    .readFile(join(__dirname, '{}'))
+                    .then(bytes => WebAssembly.instantiate(bytes, imports))
+                    .then(({{instance}}) => {});
+            ", wasm, exports));
+        } else {
+            shim.push_str(&format!("
+                const join = require('path').join;
+                const bytes = require('fs').readFileSync(join(__dirname, '{}'));
+                const wasmModule = new WebAssembly.Module(bytes);
+                const instance = new WebAssembly.Instance(wasmModule, imports);
+                module.exports = {};
+            ", wasm, exports));
+        }
+
+        if self.es_target == EsVersion::Es5 {
+            js::var_declarations(&shim)
+        } else {
+            shim
+        }
     }
 
-    pub fn browser(&mut self, browser: bool) -> &mut Bindgen {
-        self.browser = browser;
-        self
+    /// Copies each JS module imported with a relative path into the output,
+    /// at the location `local_module_path` rewrote the import to.
+    ///
+    /// It's an error for two modules to end up at the same location, or for
+    /// one to end up at the location of one of the `generated` files.
+    fn copy_local_modules<S: OutputSink>(&self,
+                                         modules: &BTreeMap<String, BTreeSet<String>>,
+                                         dir: &str,
+                                         generated: &BTreeSet<String>,
+                                         out: &mut S) -> Result<(), Error> {
+        let mut copies = BTreeMap::new();
+        for module in modules.keys() {
+            let dst = format!("{}{}", dir, &local_module_path(module).unwrap()[2..]);
+            if generated.contains(&dst) {
+                return Err(Error::Config(format!(
+                    "the JS module `{}` would be copied to `{}`, which is a \
+                     generated file",
+                    module,
+                    dst,
+                )))
+            }
+            if let Some(other) = copies.insert(dst.clone(), module) {
+                return Err(Error::Config(format!(
+                    "the JS modules `{}` and `{}` would both be copied to `{}`",
+                    other,
+                    module,
+                    dst,
+                )))
+            }
+        }
+
+        for (dst, module) in copies {
+            let items = &modules[module];
+            let src = self.local_module_roots.iter()
+                .map(|root| root.join(module))
+                .find(|path| path.is_file());
+            let src = match src {
+                Some(src) => src,
+                None => {
+                    let items = items.iter()
+                        .map(|s| format!("`{}`", s))
+                        .collect::<Vec<_>>();
+                    let roots = self.local_module_roots.iter()
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>();
+                    return Err(Error::Config(format!(
+                        "failed to find the JS module `{}` (imported for {}) \
+                         in any of the local module roots: {}",
+                        module,
+                        items.join(", "),
+                        roots.join(", "),
+                    )))
+                }
+            };
+            let mut contents = Vec::new();
+            read_file(&src, &mut contents)?;
+            write(out, &dst, &contents)?;
+        }
+        Ok(())
     }
+}
 
-    pub fn no_modules(&mut self, no_modules: bool) -> &mut Bindgen {
-        self.no_modules = no_modules;
-        self
+/// Stem of the entry module generated for multiple inputs.
+const ENTRY_STEM: &str = "index";
+
+/// Stem of the module shared by the glue of all inputs, see
+/// `Bindgen::shared_runtime`.
+const RUNTIME_STEM: &str = "wasm_bindgen_runtime";
+
+/// Stem of the module defining the classes several inputs export, see
+/// `Bindgen::add_input_path`.
+const CLASSES_STEM: &str = "wasm_bindgen_classes";
+
+/// An input whose bindgen metadata has been read, see `Bindgen::parse_inputs`.
+struct ParsedInput {
+    stem: String,
+    programs: Vec<shared::Program>,
+    descriptors: HashMap<String, Vec<u32>>,
+    code: Option<(usize, u64)>,
+    /// The contents of the input's code section, only kept for
+    /// `Bindgen::preserve_code_offsets`.
+    code_contents: Option<Vec<u8>>,
+    /// Whether the input's memory is shared, see `unshare_memory`.
+    shared_memory: bool,
+    /// The classes exported by several of the inputs, see `shared_classes`.
+    shared_classes: BTreeMap<String, String>,
+}
+
+/// Whether the custom section `name` is DWARF or a pointer to a source map,
+/// both of which refer to offsets into the code.
+fn is_debug_section(name: &str) -> bool {
+    name.starts_with(".debug_") || name == "sourceMappingURL"
+}
+
+/// Calls `f` with the id and contents of each section of the wasm file
+/// `wasm`, along with the offset of the contents in the file.
+fn each_section<F: FnMut(u8, usize, &[u8])>(wasm: &[u8], mut f: F) {
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb(wasm, &mut pos);
+        if pos + size > wasm.len() {
+            return
+        }
+        f(id, pos, &wasm[pos..pos + size]);
+        pos += size;
     }
+}
 
-    pub fn no_modules_global(&mut self, name: &str) -> &mut Bindgen {
-        self.no_modules_global = Some(name.to_string());
-        self
+/// Reads the unsigned LEB128 number at `pos` in `bytes`, advancing `pos`
+/// past it.
+fn read_leb(bytes: &[u8], pos: &mut usize) -> usize {
+    let mut ret = 0;
+    let mut shift = 0;
+    while *pos < bytes.len() {
+        let byte = bytes[*pos];
+        *pos += 1;
+        ret |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break
+        }
     }
+    ret
+}
 
-    pub fn debug(&mut self, debug: bool) -> &mut Bindgen {
-        self.debug = debug;
-        self
+/// The id of the build of `input`, see `Bindgen::build_id`.
+fn build_id(input: &ParsedInput) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(&input.programs).unwrap().hash(&mut hasher);
+    input.descriptors.iter().collect::<BTreeMap<_, _>>().hash(&mut hasher);
+    input.code.hash(&mut hasher);
+    hasher.finish() as u32
+}
+
+/// Adds the `__wbindgen_build_id` export returning `id` to `module`, which
+/// like any module built by rustc has type, function, export and code
+/// sections.
+fn export_build_id(module: &mut Module, id: u32) {
+    let ty = Type::Function(FunctionType::new(Vec::new(), Some(ValueType::I32)));
+    let imported = module.import_count(ImportCountType::Function);
+    let mut type_idx = 0;
+    let mut func_idx = 0;
+    for section in module.sections_mut() {
+        match *section {
+            Section::Type(ref mut s) => {
+                type_idx = match s.types().iter().position(|t| *t == ty) {
+                    Some(i) => i,
+                    None => {
+                        s.types_mut().push(ty.clone());
+                        s.types().len() - 1
+                    }
+                };
+            }
+            Section::Function(ref mut s) => {
+                func_idx = imported + s.entries().len();
+                s.entries_mut().push(Func::new(type_idx as u32));
+            }
+            Section::Export(ref mut s) => {
+                s.entries_mut().push(ExportEntry::new("__wbindgen_build_id".to_string(),
+                                                      Internal::Function(func_idx as u32)));
+            }
+            Section::Code(ref mut s) => {
+                let code = vec![Opcode::I32Const(id as i32), Opcode::End];
+                s.bodies_mut().push(FuncBody::new(Vec::new(), Opcodes::new(code)));
+            }
+            _ => {}
+        }
     }
+}
 
-    pub fn typescript(&mut self, typescript: bool) -> &mut Bindgen {
-        self.typescript = typescript;
-        self
+/// Returns the offset and a hash of the contents of the code section of the
+/// wasm file `wasm`.
+fn code_section(wasm: &[u8]) -> Option<(usize, u64)> {
+    let mut ret = None;
+    each_section(wasm, |id, offset, contents| {
+        if id == 10 {
+            let mut hasher = DefaultHasher::new();
+            contents.hash(&mut hasher);
+            ret = Some((offset, hasher.finish()));
+        }
+    });
+    ret
+}
+
+/// Returns the contents of the code section of the wasm file `wasm`.
+fn code_section_contents(wasm: &[u8]) -> Option<Vec<u8>> {
+    let mut ret = None;
+    each_section(wasm, |id, _, contents| {
+        if id == 10 {
+            ret = Some(contents.to_vec());
+        }
+    });
+    ret
+}
+
+/// Gives the function `types` of the wasm file `wasm`, which return an
+/// `i32`, a second `i32` result, see `Bindgen::multi_value`.
+fn return_two_values(wasm: &mut Vec<u8>, types: &[u32]) {
+    let mut old = None;
+    each_section(wasm, |id, _, contents| {
+        if id == 1 {
+            old = Some(contents.to_vec());
+        }
+    });
+    let old = match old {
+        Some(old) => old,
+        None => return,
+    };
+
+    // Every type is a function type: 0x60, the parameters and the results,
+    // each a count followed by a byte per value type.
+    let mut pos = 0;
+    let count = read_leb(&old, &mut pos);
+    let mut contents = old[..pos].to_vec();
+    for i in 0..count {
+        let start = pos;
+        pos += 1;
+        let params = read_leb(&old, &mut pos);
+        pos += params;
+        let results_start = pos;
+        let results = read_leb(&old, &mut pos);
+        pos += results;
+        if types.contains(&(i as u32)) && results == 1 && old[pos - 1] == 0x7f {
+            contents.extend_from_slice(&old[start..results_start]);
+            contents.extend_from_slice(&[2, 0x7f, 0x7f]);
+        } else {
+            contents.extend_from_slice(&old[start..pos]);
+        }
     }
+    contents.extend_from_slice(&old[pos..]);
+    replace_section(wasm, 1, &contents);
+}
 
-    pub fn demangle(&mut self, demangle: bool) -> &mut Bindgen {
-        self.demangle = demangle;
-        self
+/// Replaces the code section of the wasm file `wasm` with one of the
+/// `contents` copied from the input, see `Bindgen::preserve_code_offsets`.
+///
+/// Serializing the module may encode the same code differently, but the
+/// function bodies must still be the input's. If their number has changed
+/// the code section is left alone, and the offsets are reported as changed.
+fn replace_code_section(wasm: &mut Vec<u8>, contents: &[u8]) {
+    let mut same_count = false;
+    each_section(wasm, |id, _, old| {
+        if id == 10 {
+            let (mut a, mut b) = (0, 0);
+            same_count = read_leb(old, &mut a) == read_leb(contents, &mut b);
+        }
+    });
+    if same_count {
+        replace_section(wasm, 10, contents);
     }
+}
 
-    pub fn generate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
-        self._generate(path.as_ref())
+/// Replaces the contents of the section with the id `id` of the wasm file
+/// `wasm` with `contents`.
+fn replace_section(wasm: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    let mut section = None;
+    let mut end = 8;
+    each_section(wasm, |i, offset, old| {
+        if i == id {
+            section = Some((end, offset + old.len()));
+        }
+        end = offset + old.len();
+    });
+    let (start, end) = match section {
+        Some(range) => range,
+        None => return,
+    };
+    let mut header = vec![id];
+    let mut size = contents.len();
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            header.push(byte);
+            break
+        }
+        header.push(byte | 0x80);
     }
+    let tail = wasm.split_off(end);
+    wasm.truncate(start);
+    wasm.extend_from_slice(&header);
+    wasm.extend_from_slice(contents);
+    wasm.extend_from_slice(&tail);
+}
 
-    fn _generate(&mut self, out_dir: &Path) -> Result<(), Error> {
-        let input = match self.path {
-            Some(ref path) => path,
-            None => panic!("must have a path input for now"),
-        };
-        let stem = input.file_stem().unwrap().to_str().unwrap();
-        let mut module = parity_wasm::deserialize_file(input)?;
-        let programs = extract_programs(&mut module);
-
-        // Here we're actually instantiating the module we've parsed above for
-        // execution. Why, you might be asking, are we executing wasm code? A
-        // good question!
-        //
-        // Transmitting information from `#[wasm_bindgen]` here to the CLI tool
-        // is pretty tricky. Specifically information about the types involved
-        // with a function signature (especially generic ones) can be hefty to
-        // translate over. As a result, the macro emits a bunch of shims which,
-        // when executed, will describe to us what the types look like.
-        //
-        // This means that whenever we encounter an import or export we'll
-        // execute a shim function which informs us about its type so we can
-        // then generate the appropriate bindings.
-        let instance = wasmi::Module::from_parity_wasm_module(module.clone())?;
-        let instance = wasmi::ModuleInstance::new(&instance, &MyResolver)?;
-        let instance = instance.not_started_instance();
-
-        let (js, ts) = {
-            let mut cx = js::Context {
-                globals: String::new(),
-                imports: String::new(),
-                footer: String::new(),
-                typescript: format!("/* tslint:disable */\n"),
-                exposed_globals: Default::default(),
-                required_internal_exports: Default::default(),
-                imported_names: Default::default(),
-                exported_classes: Default::default(),
-                config: &self,
-                module: &mut module,
-                function_table_needed: false,
-                run_descriptor: &|name| {
-                    let mut v = MyExternals(Vec::new());
-                    let ret = instance
-                        .invoke_export(name, &[], &mut v)
-                        .expect("failed to run export");
-                    assert!(ret.is_none());
-                    v.0
-                },
-            };
-            for program in programs.iter() {
-                js::SubContext {
-                    program,
-                    cx: &mut cx,
-                }.generate();
+/// Returns the offset in the wasm file `wasm` of the flags of its memory's
+/// limits, whether the memory is imported or defined.
+fn memory_flags(wasm: &[u8]) -> Option<usize> {
+    let mut ret = None;
+    each_section(wasm, |id, offset, contents| {
+        if ret.is_some() {
+            return
+        }
+        let mut pos = 0;
+        let count = read_leb(contents, &mut pos);
+        match id {
+            // The import section, where each entry is two names followed by
+            // the kind of import and its type.
+            2 => {
+                for _ in 0..count {
+                    for _ in 0..2 {
+                        let len = read_leb(contents, &mut pos);
+                        pos += len;
+                    }
+                    let kind = match contents.get(pos) {
+                        Some(kind) => *kind,
+                        None => return,
+                    };
+                    pos += 1;
+                    match kind {
+                        0 => { read_leb(contents, &mut pos); }
+                        1 => {
+                            pos += 1;
+                            skip_limits(contents, &mut pos);
+                        }
+                        2 => {
+                            ret = Some(offset + pos);
+                            return
+                        }
+                        _ => pos += 2,
+                    }
+                }
             }
-            cx.finalize(stem)
-        };
+            // The memory section.
+            5 if count > 0 => ret = Some(offset + pos),
+            _ => {}
+        }
+    });
+    ret.filter(|&pos| pos < wasm.len())
+}
+
+/// Advances `pos` past the limits of a table or memory in `bytes`.
+fn skip_limits(bytes: &[u8], pos: &mut usize) {
+    let flags = bytes.get(*pos).cloned().unwrap_or(0);
+    *pos += 1;
+    read_leb(bytes, pos);
+    if flags & 1 != 0 {
+        read_leb(bytes, pos);
+    }
+}
+
+/// Clears the flag marking the memory of the wasm file `wasm` as shared,
+/// returning whether it was set.
+///
+/// parity-wasm can't parse shared memories, so they're parsed (and
+/// serialized) as if they weren't and `share_memory` marks them as shared
+/// again in the output.
+fn unshare_memory(wasm: &mut [u8]) -> bool {
+    match memory_flags(wasm) {
+        Some(pos) if wasm[pos] & 2 != 0 => {
+            wasm[pos] &= !2;
+            true
+        }
+        _ => false,
+    }
+}
 
-        let js_path = out_dir.join(stem).with_extension("js");
-        File::create(&js_path).unwrap()
-            .write_all(js.as_bytes()).unwrap();
+/// Marks the memory of the wasm file `wasm` as shared, see `unshare_memory`.
+fn share_memory(wasm: &mut [u8]) {
+    if let Some(pos) = memory_flags(wasm) {
+        wasm[pos] |= 2;
+    }
+}
 
-        if self.typescript {
-            let ts_path = out_dir.join(stem).with_extension("d.ts");
-            File::create(&ts_path).unwrap()
-                .write_all(ts.as_bytes()).unwrap();
+/// Whether the wasm file `wasm` has any sections `is_debug_section` matches.
+fn has_debug_sections(wasm: &[u8]) -> bool {
+    let mut ret = false;
+    each_section(wasm, |id, _, contents| {
+        if id != 0 {
+            return
         }
+        let mut pos = 0;
+        let len = read_leb(contents, &mut pos);
+        if let Some(name) = contents.get(pos..pos + len) {
+            ret = ret || is_debug_section(&String::from_utf8_lossy(name));
+        }
+    });
+    ret
+}
 
-        let wasm_path = out_dir.join(format!("{}_bg", stem)).with_extension("wasm");
+/// Whether `name` can name a file in the output directory.
+fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && !name.contains('\\')
+}
 
-        if self.nodejs {
-            let js_path = wasm_path.with_extension("js");
-            let shim = self.generate_node_wasm_import(&module, &wasm_path);
-            File::create(&js_path)?.write_all(shim.as_bytes())?;
+/// Returns the bytes of the wasm file `input` and the stem to name output
+/// files after.
+///
+/// Readers can only be read once, so what they produce is kept in `input`
+/// for the next call to `generate`.
+fn read_input(input: &mut Input) -> Result<(Wasm, String), Error> {
+    let (bytes, stem) = match *input {
+        Input::Path(ref path) => {
+            let stem = input.stem()?;
+            let mut bytes = Vec::new();
+            read_file(path, &mut bytes)?;
+            return Ok((Wasm::Bytes(bytes), stem))
+        }
+        Input::Reader(ref mut reader, ref stem) => {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(|error| {
+                Error::Io { what: format!("failed to read the input `{}`", stem), error }
+            })?;
+            (bytes, stem.clone())
+        }
+        Input::Bytes(ref bytes, ref stem) => {
+            return Ok((Wasm::Bytes(bytes.clone()), stem.clone()))
+        }
+        Input::Module(ref mut module, ref stem) => {
+            return match module.take() {
+                Some(module) => Ok((Wasm::Module(module), stem.clone())),
+                None => Err(Error::Config(format!("the module `{}` given to \
+                                                   `input_module` was already used by \
+                                                   an earlier call to `generate` or \
+                                                   `validate`", stem))),
+            }
         }
+    };
+    *input = Input::Bytes(bytes.clone(), stem.clone());
+    Ok((Wasm::Bytes(bytes), stem))
+}
 
-        let wasm_bytes = parity_wasm::serialize(module).map_err(|e| {
-            Error(format!("{:?}", e))
-        })?;
-        File::create(&wasm_path)?.write_all(&wasm_bytes)?;
-        Ok(())
+impl Wasm {
+    /// Parses the input if needed, returning the module along with whether
+    /// its memory is shared, see `unshare_memory`.
+    ///
+    /// An already parsed module can't have a shared memory as parity-wasm
+    /// can't parse those.
+    fn parse(self) -> Result<(Module, bool), Error> {
+        match self {
+            Wasm::Bytes(mut bytes) => {
+                let shared_memory = unshare_memory(&mut bytes);
+                Ok((parity_wasm::deserialize_buffer(&bytes)?, shared_memory))
+            }
+            Wasm::Module(module) => Ok((module, false)),
+        }
     }
+}
 
-    fn generate_node_wasm_import(&self, m: &Module, path: &Path) -> String {
-        let mut imports = BTreeSet::new();
-        if let Some(i) = m.import_section() {
-            for i in i.entries() {
-                imports.insert(i.module());
+/// The classes exported by more than one of `inputs`, given as their stems
+/// and programs, each with the stem of the first input exporting it, which
+/// owns it, see `Bindgen::add_input_path`.
+///
+/// The inputs defining a shared class have to agree on the JS class it
+/// extends, which the classes module extends in their place.
+fn shared_classes(inputs: &[(&str, &[shared::Program])])
+    -> Result<BTreeMap<String, String>, Error>
+{
+    let mut owners: BTreeMap<&String, &str> = BTreeMap::new();
+    let mut superclasses = BTreeMap::new();
+    let mut shared = BTreeMap::new();
+    let mut errors = Vec::new();
+    for &(stem, programs) in inputs.iter() {
+        for s in programs.iter().flat_map(|p| p.structs.iter()) {
+            match superclasses.get(&s.name) {
+                Some(&(prev, ref superclass)) if *superclass != s.superclass => {
+                    errors.push(format!("the struct `{}` extends a different JS class in \
+                                         the `{}` input than in the `{}` one, which can't \
+                                         share its class", s.name, stem, prev));
+                }
+                Some(_) => {}
+                None => {
+                    superclasses.insert(&s.name, (stem, s.superclass.clone()));
+                }
+            }
+        }
+        let classes = programs.iter()
+            .flat_map(|p| {
+                p.structs.iter()
+                    .map(|s| &s.name)
+                    .chain(p.exports.iter().filter_map(|e| e.class.as_ref()))
+            })
+            .collect::<BTreeSet<_>>();
+        for class in classes {
+            match owners.get(class) {
+                Some(&owner) => {
+                    shared.insert(class.clone(), owner.to_string());
+                }
+                None => {
+                    owners.insert(class, stem);
+                }
             }
         }
+    }
+    if errors.is_empty() {
+        Ok(shared)
+    } else {
+        Err(Error::Bindings(errors))
+    }
+}
 
-        let mut shim = String::new();
-        shim.push_str("let imports = {};\n");
-        for module in imports {
-            shim.push_str(&format!("imports['{0}'] = require('{0}');\n", module));
+/// Formats the `(name, exported)` pairs of an ES module export list.
+fn export_list(exports: &[(String, String)]) -> String {
+    exports.iter()
+        .map(|(name, exported)| {
+            if name == exported {
+                name.clone()
+            } else {
+                format!("{} as {}", name, exported)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes the file `name` to `out`, naming it in the error if that fails.
+fn write<S: OutputSink>(out: &mut S, name: &str, contents: &[u8]) -> Result<(), Error> {
+    out.write(name, contents).map_err(|error| {
+        Error::Io { what: format!("failed to write `{}`", name), error }
+    })
+}
+
+/// Reads the file at `path` into `contents`, naming it in the error if that
+/// fails.
+fn read_file(path: &Path, contents: &mut Vec<u8>) -> Result<(), Error> {
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(contents))
+        .map(|_| ())
+        .map_err(|error| {
+            Error::Io { what: format!("failed to read `{}`", path.display()), error }
+        })
+}
+
+/// Formats `limits` as the descriptor object passed to the JS API's
+/// `WebAssembly.Memory` and `WebAssembly.Table` constructors.
+pub(crate) fn js_limits(limits: &ResizableLimits, extra: &str) -> String {
+    match limits.maximum() {
+        Some(max) => format!("{{ {}initial: {}, maximum: {} }}", extra, limits.initial(), max),
+        None => format!("{{ {}initial: {} }}", extra, limits.initial()),
+    }
+}
+
+/// If `module` is a relative path like `./foo.js` or `../js/foo.js`, returns
+/// the path it's imported from in the generated JS once copied into the
+/// output directory. `..` components are dropped so the copy always ends up
+/// inside the output directory.
+fn local_module_path(module: &str) -> Option<String> {
+    if !module.starts_with("./") && !module.starts_with("../") {
+        return None
+    }
+    let parts = module.split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .collect::<Vec<_>>();
+    Some(format!("./{}", parts.join("/")))
+}
+
+/// Runs every `__wbindgen_describe_*` export of `module`, returning the words
+/// each one passed to `__wbindgen_describe` keyed by the export's name.
+///
+/// If `interpreter` is set the shims are first run through our own minimal
+/// interpreter, and only those it can't handle are executed with wasmi.
+///
+/// The shims failing are reported one per line, naming what they describe
+/// as found in `programs`. Those the interpreter couldn't run are added to
+/// `fallbacks`, see `BindgenStats::interpreter_fallbacks`.
+fn run_descriptors(module: &Module,
+                   interpreter: bool,
+                   programs: &[shared::Program],
+                   fallbacks: &mut Vec<String>)
+    -> Result<HashMap<String, Vec<u32>>, Error>
+{
+    let mut names = module.export_section()
+        .map(|s| {
+            s.entries()
+                .iter()
+                .map(|e| e.field())
+                .filter(|name| name.starts_with("__wbindgen_describe_"))
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut ret = HashMap::new();
+    if interpreter {
+        let mut interp = interpreter::Interpreter::new(module);
+        let mut fallback = Vec::new();
+        for name in names {
+            match interp.interpret(&name) {
+                Ok(d) => {
+                    ret.insert(name, d);
+                }
+                Err(e) => {
+                    fallbacks.push(format!("{} ({})", name, e));
+                    fallback.push(name);
+                }
+            }
         }
+        names = fallback;
+    }
+    if names.is_empty() {
+        return Ok(ret)
+    }
 
-        shim.push_str(&format!("
-            const join = require('path').join;
-            const bytes = require('fs').readFileSync(join(__dirname, '{}'));
-            const wasmModule = new WebAssembly.Module(bytes);
-            const wasmInstance = new WebAssembly.Instance(wasmModule, imports);
-            module.exports = wasmInstance.exports;
-        ", path.file_name().unwrap().to_str().unwrap()));
+    let instance = wasmi::Module::from_parity_wasm_module(module.clone())?;
+    let instance = wasmi::ModuleInstance::new(&instance, &MyResolver)?;
+    let instance = instance.not_started_instance();
 
-        shim
+    // Run every descriptor even if one fails so all failures are reported at
+    // once.
+    let mut errors = Vec::new();
+    for name in names {
+        let mut v = MyExternals(Vec::new());
+        match instance.invoke_export(&name, &[], &mut v) {
+            Ok(None) => {
+                ret.insert(name, v.0);
+            }
+            Ok(Some(_)) => {
+                errors.push(format!("descriptor export `{}` unexpectedly \
+                                     returned a value", name));
+            }
+            Err(e) => {
+                let described = &name["__wbindgen_describe_".len()..];
+                let failed = match descriptor::Descriptor::incomplete_part(&v.0) {
+                    Some(part) => format!("{} failed ({})", part, e),
+                    None => format!("{}", e),
+                };
+                errors.push(format!("failed to describe {}: {} (descriptor so far: {:?}), \
+                                     this usually means a type in its signature isn't \
+                                     supported",
+                                    describe_name(described, programs), failed, v.0));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::Bindings(errors))
+    }
+    Ok(ret)
+}
+
+/// What the descriptor shim `__wbindgen_describe_{name}` describes according
+/// to `programs`, like ``export `counter_new` (`Counter::new` in Rust)``.
+fn describe_name(name: &str, programs: &[shared::Program]) -> String {
+    for program in programs {
+        for export in program.exports.iter() {
+            let f = &export.function.name;
+            let (wasm, rust) = match export.class {
+                Some(ref class) => {
+                    (shared::struct_function_export_name(class, f), format!("{}::{}", class, f))
+                }
+                None => (f.clone(), f.clone()),
+            };
+            if wasm == name {
+                return format!("export `{}` (`{}` in Rust)", name, rust)
+            }
+        }
+        for s in program.structs.iter() {
+            for field in s.fields.iter() {
+                if shared::struct_field_get(&s.name, &field.name) == name {
+                    return format!("the getter of field `{}` of `{}`", field.name, s.name)
+                }
+            }
+        }
+        for import in program.imports.iter() {
+            let rust = match import.kind {
+                shared::ImportKind::Function(ref f) if f.shim == name => {
+                    match f.class {
+                        Some(ref class) => format!("{}::{}", class, f.function.name),
+                        None => f.function.name.clone(),
+                    }
+                }
+                shared::ImportKind::Static(ref s) if s.shim == name => s.name.clone(),
+                _ => continue,
+            };
+            return format!("import `{}` (`{}` in Rust)", name, rust)
+        }
+        for c in program.consts.iter() {
+            if shared::const_getter(&c.name) == name {
+                return format!("const `{}`", c.name)
+            }
+        }
     }
+    format!("`{}`", name)
 }
 
-fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
+fn extract_programs(module: &mut Module) -> Result<Vec<shared::Program>, Error> {
     let version = shared::version();
-    let mut ret = Vec::new();
+    let mut blobs = Vec::new();
+    let mut truncated = false;
 
     module.sections_mut().retain(|s| {
         let custom = match *s {
@@ -217,32 +3402,70 @@ fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
 
         let mut payload = custom.payload();
         while payload.len() > 0 {
+            if payload.len() < 4 {
+                truncated = true;
+                break
+            }
             let len =
                 ((payload[0] as usize) << 0) |
                 ((payload[1] as usize) << 8) |
                 ((payload[2] as usize) << 16) |
                 ((payload[3] as usize) << 24);
+            if payload.len() - 4 < len {
+                truncated = true;
+                break
+            }
             let (a, b) = payload[4..].split_at(len as usize);
             payload = b;
-            let p: shared::ProgramOnlySchema = match serde_json::from_slice(&a) {
-                Ok(f) => f,
-                Err(e) => {
-                    panic!("failed to decode what looked like wasm-bindgen data: {}", e)
-                }
-            };
-            if p.schema_version != shared::SCHEMA_VERSION {
-                panic!("
+            blobs.push(a.to_vec());
+        }
+
+        false
+    });
+    if truncated {
+        return Err(Error::Decode("truncated wasm-bindgen custom section".to_string()))
+    }
 
-it looks like the Rust project used to create this wasm file was linked against
-a different version of wasm-bindgen than this binary:
+    // Each crate using `#[wasm_bindgen]` contributes its own blob, so the
+    // versions are checked for all of them at once to point at the ones
+    // which have to be updated.
+    let mut schemas = Vec::new();
+    for blob in blobs.iter() {
+        let p: shared::ProgramOnlySchema = serde_json::from_slice(blob).map_err(decode_error)?;
+        if !schemas.iter().any(|s: &shared::ProgramOnlySchema| {
+            s.crate_name == p.crate_name && s.version == p.version
+        }) {
+            schemas.push(p);
+        }
+    }
+    let width = schemas.iter()
+        .map(|p| crate_label(&p.crate_name).len())
+        .chain(Some("this binary".len()))
+        .max()
+        .unwrap();
+    let mut versions = format!("  {:2$}  {}\n", "this binary", version, width);
+    for p in schemas.iter() {
+        versions.push_str(&format!("  {:2$}  {}", crate_label(&p.crate_name), p.version, width));
+        if p.schema_version != shared::SCHEMA_VERSION {
+            versions.push_str(" (incompatible)");
+        }
+        versions.push('\n');
+    }
+    let outdated = schemas.iter()
+        .filter(|p| p.schema_version != shared::SCHEMA_VERSION)
+        .collect::<Vec<_>>();
+    if !outdated.is_empty() {
+        let labels = outdated.iter().map(|p| crate_label(&p.crate_name)).collect::<Vec<_>>();
+        let message = format!("\
+it looks like some of the crates used to create this wasm file were linked
+against a different version of wasm-bindgen than this binary:
 
-  rust wasm file: {}
-     this binary: {}
+{}
+Currently the bindgen format is unstable enough that these versions must
+exactly match, so it's required that they're kept in sync by either updating
+the wasm-bindgen dependency of {} or this binary.
 
-Currently the bindgen format is unstable enough that these two version must
-exactly match, so it's required that these two version are kept in sync by
-either updating the wasm-bindgen dependency or this binary. You should be able
-to update the wasm-bindgen dependency with:
+You should be able to update the wasm-bindgen dependency with:
 
     cargo update -p wasm-bindgen
 
@@ -251,22 +3474,37 @@ or you can update the binary with
     cargo install -f wasm-bindgen-cli
 
 if this warning fails to go away though and you're not sure what to do feel free
-to open an issue at https://github.com/alexcrichton/wasm-bindgen/issues!
-",
-    p.version, version);
-            }
-            let p: shared::Program = match serde_json::from_slice(&a) {
-                Ok(f) => f,
-                Err(e) => {
-                    panic!("failed to decode what looked like wasm-bindgen data: {}", e)
-                }
-            };
-            ret.push(p);
-        }
+to open an issue at https://github.com/alexcrichton/wasm-bindgen/issues!",
+            versions, labels.join(", "));
+        return Err(Error::SchemaMismatch {
+            found: outdated[0].schema_version.clone(),
+            expected: shared::SCHEMA_VERSION.to_string(),
+            message,
+        })
+    }
+    if schemas.iter().any(|p| p.version != version) {
+        eprintln!("warning: the crates in this wasm file were built against \
+                   versions of wasm-bindgen which are compatible with this \
+                   binary, but not the same:\n\n{}", versions);
+    }
 
-        false
-    });
-    return ret
+    blobs.iter()
+        .map(|blob| serde_json::from_slice(blob).map_err(decode_error))
+        .collect()
+}
+
+fn decode_error(e: serde_json::Error) -> Error {
+    Error::Decode(format!("failed to decode what looked like wasm-bindgen data: {}", e))
+}
+
+/// How a crate is referred to in messages, given its name as recorded in
+/// the bindgen metadata.
+fn crate_label(name: &str) -> String {
+    if name.is_empty() {
+        "an unknown crate".to_string()
+    } else {
+        format!("`{}`", name)
+    }
 }
 
 struct MyResolver;
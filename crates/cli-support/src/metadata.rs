@@ -0,0 +1,154 @@
+//! A description of the public API of the generated glue, see
+//! `Bindgen::emit_metadata`.
+//!
+//! Types are given as tags which don't change between releases: the name of
+//! a number type like `u32` or `f64`, `bool`, `string`, `any` for `JsValue`,
+//! `function` for closures and `fn` pointers, `void` for no return value,
+//! `enum` for the exported C-like enums (which are numbers in JS), and
+//! `class:Foo`, `string_enum:Foo` or `dictionary:Foo` for the other types
+//! defined by the bindings. `array:T` and `option:T` stand for slices
+//! or vectors and options of the type tagged `T`. Whether a value is borrowed
+//! isn't part of its tag, as that doesn't change how JS passes it.
+
+/// The version of the format, which changes whenever a field is removed or
+/// its meaning changes.
+pub const SCHEMA: &str = "1";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    /// Always `SCHEMA` when written by this version.
+    pub schema: String,
+    pub functions: Vec<Function>,
+    pub classes: Vec<Class>,
+    pub enums: Vec<Enum>,
+    pub consts: Vec<Const>,
+    /// The JS modules the glue imports from, in the order of their
+    /// specifiers.
+    pub imports: Vec<ImportedModule>,
+    /// The crates the bindings were generated for.
+    pub crates: Vec<Crate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    pub arguments: Vec<String>,
+    pub returns: String,
+    /// Whether the function returns a promise of `returns`.
+    pub is_async: bool,
+    /// The `#[deprecated]` note, if the function is deprecated.
+    pub deprecated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Class {
+    pub name: String,
+    /// The method `new` calls, if the class has a constructor.
+    pub constructor: Option<String>,
+    pub methods: Vec<Method>,
+    pub fields: Vec<Field>,
+    pub deprecated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Method {
+    pub name: String,
+    /// Whether this is a static method, called on the class rather than an
+    /// instance.
+    #[serde(rename = "static")]
+    pub is_static: bool,
+    /// Whether calling the method consumes the instance, which can't be
+    /// used afterwards.
+    pub consumes: bool,
+    /// Whether the method returns the instance it's called on, for chaining
+    /// calls, rather than a new one.
+    pub returns_self: bool,
+    pub arguments: Vec<String>,
+    pub returns: String,
+    pub is_async: bool,
+    pub deprecated: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub readonly: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Const {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crate {
+    /// Empty for crates built against versions of wasm-bindgen which didn't
+    /// record it.
+    pub name: String,
+    /// The version of wasm-bindgen the crate was built against.
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedModule {
+    /// The specifier the module is imported with, or `None` for values taken
+    /// from the global scope.
+    pub module: Option<String>,
+    /// The paths of the values the glue uses from the module, like `log` or
+    /// `Foo.prototype.bar`.
+    pub members: Vec<String>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings::new()
+    }
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings {
+            schema: SCHEMA.to_string(),
+            functions: Vec::new(),
+            classes: Vec::new(),
+            enums: Vec::new(),
+            consts: Vec::new(),
+            imports: Vec::new(),
+            crates: Vec::new(),
+        }
+    }
+
+    /// The class named `name`, added if it isn't there yet.
+    pub(crate) fn class(&mut self, name: &str) -> &mut Class {
+        let idx = match self.classes.iter().position(|c| c.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.classes.push(Class {
+                    name: name.to_string(),
+                    constructor: None,
+                    methods: Vec::new(),
+                    fields: Vec::new(),
+                    deprecated: None,
+                });
+                self.classes.len() - 1
+            }
+        };
+        &mut self.classes[idx]
+    }
+}
@@ -37,11 +37,9 @@ impl Config {
 
     pub fn generate(&mut self, wasm: &[u8]) -> Result<Output, Error> {
         if !self.base64 && !self.fetch_path.is_some() {
-            panic!("the option --base64 or --fetch is required");
+            return Err(::Error::Config("the option --base64 or --fetch is required".to_string()))
         }
-        let module = deserialize_buffer(wasm).map_err(|e| {
-            ::Error(format!("{:?}", e))
-        })?;
+        let module = deserialize_buffer(wasm)?;
         Ok(Output {
             module,
             base64: self.base64,
@@ -52,61 +50,8 @@ impl Config {
 
 impl Output {
     pub fn typescript(&self) -> String {
-        let mut exports = format!("/* tslint:disable */\n");
-
-        if let Some(i) = self.module.export_section() {
-            let imported_functions = self.module.import_section()
-                .map(|m| m.functions() as u32)
-                .unwrap_or(0);
-            for entry in i.entries() {
-                let idx = match *entry.internal() {
-                    Internal::Function(i) => i - imported_functions,
-                    Internal::Memory(_) => {
-                        exports.push_str(&format!("
-                            export const {}: WebAssembly.Memory;
-                        ", entry.field()));
-                        continue
-                    }
-                    Internal::Table(_) => {
-                        continue
-                    }
-                    Internal::Global(_) => {
-                        continue
-                    }
-                };
-
-                let functions = self.module.function_section()
-                    .expect("failed to find function section");
-                let idx = functions.entries()[idx as usize].type_ref();
-
-                let types = self.module.type_section()
-                    .expect("failed to find type section");
-                let ty = match types.types()[idx as usize] {
-                    Type::Function(ref f) => f,
-                };
-                let mut args = String::new();
-                for (i, _) in ty.params().iter().enumerate() {
-                    if i > 0 {
-                        args.push_str(", ");
-                    }
-                    args.push((b'a' + (i as u8)) as char);
-                    args.push_str(": number");
-                }
-
-                exports.push_str(&format!("
-                    export function {name}({args}): {ret};
-                ",
-                    name = entry.field(),
-                    args = args,
-                    ret = if ty.return_type().is_some() { "number" } else { "void" },
-                ));
-            }
-        }
-
-        if self.base64 {
-            exports.push_str("export const booted: Promise<boolean>;");
-        }
-
+        let mut exports = typescript(&self.module);
+        exports.push_str("export const booted: Promise<boolean>;");
         return exports
     }
 
@@ -114,24 +59,14 @@ impl Output {
         let mut js_imports = String::new();
         let mut exports = String::new();
         let mut imports = String::new();
-        let mut export_mem = false;
+        let mut value_exports = String::new();
+        let mut init_values = String::new();
 
         if let Some(i) = self.module.import_section() {
             let mut set = HashSet::new();
             for entry in i.entries() {
-                match *entry.external() {
-                    External::Function(_) => {}
-                    External::Table(_) => {
-                        panic!("wasm imports a table which isn't supported yet");
-                    }
-                    External::Memory(_) => {
-                        panic!("wasm imports memory which isn't supported yet");
-                    }
-                    External::Global(_) => {
-                        panic!("wasm imports globals which aren't supported yet");
-                    }
-                }
-
+                // Functions, memories, tables and globals are all taken from
+                // the ES module named by the import.
                 if !set.insert(entry.module()) {
                     continue
                 }
@@ -145,33 +80,27 @@ impl Output {
         }
 
         if let Some(i) = self.module.export_section() {
-            let imported_functions = self.module.import_section()
-                .map(|m| m.functions() as u32)
-                .unwrap_or(0);
             for entry in i.entries() {
                 let idx = match *entry.internal() {
-                    Internal::Function(i) => i - imported_functions,
-                    Internal::Memory(_) => {
-                        export_mem = true;
-                        continue
-                    }
-                    Internal::Table(_) => {
+                    Internal::Function(i) => i,
+                    Internal::Memory(_) | Internal::Table(_) => {
+                        value_exports.push_str(&format!("export let {};\n", entry.field()));
+                        init_values.push_str(&format!("{0} = wasm.exports.{0};\n",
+                                                      entry.field()));
                         continue
                     }
                     Internal::Global(_) => {
+                        // Depending on the engine exported globals are
+                        // either plain numbers or `WebAssembly.Global`
+                        // objects, and `valueOf` yields the number for both.
+                        value_exports.push_str(&format!("export let {};\n", entry.field()));
+                        init_values.push_str(&format!("{0} = wasm.exports.{0}.valueOf();\n",
+                                                      entry.field()));
                         continue
                     }
                 };
 
-                let functions = self.module.function_section()
-                    .expect("failed to find function section");
-                let idx = functions.entries()[idx as usize].type_ref();
-
-                let types = self.module.type_section()
-                    .expect("failed to find type section");
-                let ty = match types.types()[idx as usize] {
-                    Type::Function(ref f) => f,
-                };
+                let ty = function_type(&self.module, idx);
                 let mut args = String::new();
                 for (i, _) in ty.params().iter().enumerate() {
                     if i > 0 {
@@ -180,26 +109,35 @@ impl Output {
                     args.push((b'a' + (i as u8)) as char);
                 }
 
+                // When fetching, instantiation (and the start function)
+                // completes asynchronously, so guard against exports being
+                // called before then.
+                let check = if self.base64 {
+                    String::new()
+                } else {
+                    format!("if (wasm === undefined) \
+                                throw new Error('`{}` was called before the wasm \
+                                                 module was instantiated, wait \
+                                                 for `booted` to resolve first');",
+                            entry.field())
+                };
                 exports.push_str(&format!("
                     export function {name}({args}) {{
+                        {check}
                         {ret} wasm.exports.{name}({args});
                     }}
                 ",
                     name = entry.field(),
                     args = args,
+                    check = check,
                     ret = if ty.return_type().is_some() { "return" } else { "" },
                 ));
             }
         }
-        let inst = format!("WebAssembly.instantiate(bytes,{{ {imports} }})
-                .then(obj => {{
-                    wasm = obj.instance;
-                    {memory}
-                }})",
-                imports = imports,
-                memory = if export_mem { "memory = wasm.exports.memory;" } else { "" },
-            );
         let (bytes, booted) = if self.base64 {
+            // With the bytes inlined there's nothing to wait on, so compile
+            // and instantiate synchronously to make the exports usable as
+            // soon as this module has been evaluated.
             let wasm = serialize(self.module)
                 .expect("failed to serialize");
             (
@@ -210,15 +148,30 @@ impl Output {
                         bytes = Uint8Array.from(atob(base64), c => c.charCodeAt(0));
                     }} else {{
                         bytes = Buffer.from(base64, 'base64');
-                    }}", base64 = base64::encode(&wasm)),
-                inst
+                    }}
+                    wasm = new WebAssembly.Instance(new WebAssembly.Module(bytes), {{ {imports} }});
+                    {init_values}",
+                    base64 = base64::encode(&wasm),
+                    imports = imports,
+                    init_values = init_values,
+                ),
+                "Promise.resolve(true)".to_string(),
             )
         } else if let Some(ref path) = self.fetch_path {
             (
                 String::new(),
                 format!("fetch('{path}')
                 .then(res => res.arrayBuffer())
-                .then(bytes => {inst})", path = path, inst = inst)
+                .then(bytes => WebAssembly.instantiate(bytes,{{ {imports} }}))
+                .then(obj => {{
+                    wasm = obj.instance;
+                    {init_values}
+                    return true;
+                }})",
+                    path = path,
+                    imports = imports,
+                    init_values = init_values,
+                ),
             )
         } else {
             panic!("the option --base64 or --fetch is required");
@@ -226,8 +179,8 @@ impl Output {
         format!("
             {js_imports}
             let wasm;
+            {value_exports}
             {bytes}
-            {mem_export}
             export const booted = {booted};
             {exports}
         ",
@@ -235,7 +188,109 @@ impl Output {
             booted = booted,
             js_imports = js_imports,
             exports = exports,
-            mem_export = if export_mem { "export let memory;" } else { "" },
+            value_exports = value_exports,
         )
     }
 }
+
+/// Generates TypeScript declarations for all the exports of `module`, as
+/// they'd appear on a JS object of the instance's exports.
+pub fn typescript(module: &Module) -> String {
+    typescript_with(module, |_, _| None)
+}
+
+/// Like `typescript`, but `value_type` may give another type than `number`
+/// for the parameter `Some(i)` or the result `None` of the functions of the
+/// type with the given index, for types which only change once the module is
+/// serialized.
+pub fn typescript_with<F>(module: &Module, value_type: F) -> String
+    where F: Fn(u32, Option<usize>) -> Option<&'static str>
+{
+    let mut exports = format!("/* tslint:disable */\n");
+
+    if let Some(i) = module.export_section() {
+        for entry in i.entries() {
+            let idx = match *entry.internal() {
+                Internal::Function(i) => i,
+                Internal::Memory(_) => {
+                    exports.push_str(&format!("
+                        export const {}: WebAssembly.Memory;
+                    ", entry.field()));
+                    continue
+                }
+                Internal::Table(_) => {
+                    exports.push_str(&format!("
+                        export const {}: WebAssembly.Table;
+                    ", entry.field()));
+                    continue
+                }
+                Internal::Global(_) => {
+                    exports.push_str(&format!("
+                        export const {}: number;
+                    ", entry.field()));
+                    continue
+                }
+            };
+
+            let type_ref = function_type_ref(module, idx);
+            let ty = function_type(module, idx);
+            let mut args = String::new();
+            for (i, _) in ty.params().iter().enumerate() {
+                if i > 0 {
+                    args.push_str(", ");
+                }
+                args.push((b'a' + (i as u8)) as char);
+                args.push_str(": ");
+                args.push_str(value_type(type_ref, Some(i)).unwrap_or("number"));
+            }
+            let ret = match ty.return_type() {
+                Some(_) => value_type(type_ref, None).unwrap_or("number"),
+                None => "void",
+            };
+
+            exports.push_str(&format!("
+                export function {name}({args}): {ret};
+            ",
+                name = entry.field(),
+                args = args,
+                ret = ret,
+            ));
+        }
+    }
+
+    exports
+}
+
+/// Returns the signature of the function at `idx` in the function index
+/// space, which includes imported functions.
+fn function_type(module: &Module, idx: u32) -> &FunctionType {
+    let types = module.type_section()
+        .expect("failed to find type section");
+    match types.types()[function_type_ref(module, idx) as usize] {
+        Type::Function(ref f) => f,
+    }
+}
+
+/// Returns the index of the type of the function at `idx`, like
+/// `function_type`.
+fn function_type_ref(module: &Module, idx: u32) -> u32 {
+    let imported_functions = module.import_section()
+        .map(|m| m.functions() as u32)
+        .unwrap_or(0);
+    if idx < imported_functions {
+        module.import_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .filter_map(|e| match *e.external() {
+                External::Function(t) => Some(t),
+                _ => None,
+            })
+            .nth(idx as usize)
+            .unwrap()
+    } else {
+        let functions = module.function_section()
+            .expect("failed to find function section");
+        functions.entries()[(idx - imported_functions) as usize].type_ref()
+    }
+}
@@ -0,0 +1,236 @@
+//! A text rendering of the output wasm, see `Bindgen::emit_wat`.
+//!
+//! This follows the text format closely enough to read like the output of
+//! `wasm2wat`, but it's only meant for people looking into what happened to
+//! a module: custom sections and data segments are summarized in comments
+//! rather than written out, so it can't be assembled again.
+
+use std::fmt::Write;
+
+use parity_wasm::elements::*;
+
+/// Renders `module`, whose memory is shared if `shared_memory` is set (see
+/// `unshare_memory`).
+pub fn print(module: &Module, shared_memory: bool) -> String {
+    let mut dst = String::from("(module\n");
+    let names = module.sections()
+        .iter()
+        .filter_map(|s| {
+            match *s {
+                Section::Name(NameSection::Function(ref f)) => Some(f.names()),
+                _ => None,
+            }
+        })
+        .next();
+    let types = module.type_section().map(|s| s.types()).unwrap_or(&[]);
+    let mut funcs = module.import_section().map(|s| s.functions()).unwrap_or(0) as u32;
+    let mut tables = 0;
+    let mut memories = 0;
+    let mut globals = 0;
+
+    for section in module.sections() {
+        match *section {
+            Section::Type(ref s) => {
+                for (i, ty) in s.types().iter().enumerate() {
+                    let Type::Function(ref f) = *ty;
+                    writeln!(dst, "  (type (;{};) (func{}))", i, signature(f)).unwrap();
+                }
+            }
+            Section::Import(ref s) => {
+                let mut imported_funcs = 0;
+                for entry in s.entries() {
+                    let desc = match *entry.external() {
+                        External::Function(ty) => {
+                            imported_funcs += 1;
+                            format!("func (;{};) (type {})", imported_funcs - 1, ty)
+                        }
+                        External::Table(ref t) => {
+                            tables += 1;
+                            format!("table (;{};) {}", tables - 1, table(t))
+                        }
+                        External::Memory(ref m) => {
+                            memories += 1;
+                            format!("memory (;{};) {}", memories - 1, memory(m, shared_memory))
+                        }
+                        External::Global(ref g) => {
+                            globals += 1;
+                            format!("global (;{};) {}", globals - 1, global(g))
+                        }
+                    };
+                    writeln!(dst, "  (import {:?} {:?} ({}))",
+                             entry.module(), entry.field(), desc).unwrap();
+                }
+            }
+            // Printed along with the bodies in the code section.
+            Section::Function(_) => {}
+            Section::Table(ref s) => {
+                for t in s.entries() {
+                    writeln!(dst, "  (table (;{};) {})", tables, table(t)).unwrap();
+                    tables += 1;
+                }
+            }
+            Section::Memory(ref s) => {
+                for m in s.entries() {
+                    writeln!(dst, "  (memory (;{};) {})", memories, memory(m, shared_memory))
+                        .unwrap();
+                    memories += 1;
+                }
+            }
+            Section::Global(ref s) => {
+                for g in s.entries() {
+                    writeln!(dst, "  (global (;{};) {} ({}))",
+                             globals, global(g.global_type()), expr(g.init_expr())).unwrap();
+                    globals += 1;
+                }
+            }
+            Section::Export(ref s) => {
+                for entry in s.entries() {
+                    let desc = match *entry.internal() {
+                        Internal::Function(i) => format!("func {}", i),
+                        Internal::Table(i) => format!("table {}", i),
+                        Internal::Memory(i) => format!("memory {}", i),
+                        Internal::Global(i) => format!("global {}", i),
+                    };
+                    writeln!(dst, "  (export {:?} ({}))", entry.field(), desc).unwrap();
+                }
+            }
+            Section::Start(i) => writeln!(dst, "  (start {})", i).unwrap(),
+            Section::Element(ref s) => {
+                for (i, segment) in s.entries().iter().enumerate() {
+                    let members = segment.members()
+                        .iter()
+                        .map(|m| format!(" {}", m))
+                        .collect::<String>();
+                    writeln!(dst, "  (elem (;{};) ({}){})",
+                             i, expr(segment.offset()), members).unwrap();
+                }
+            }
+            Section::Code(ref s) => {
+                let decls = module.function_section().map(|s| s.entries()).unwrap_or(&[]);
+                for (decl, body) in decls.iter().zip(s.bodies()) {
+                    let ty = decl.type_ref();
+                    write!(dst, "  (func (;{};) (type {})", funcs, ty).unwrap();
+                    if let Some(Type::Function(f)) = types.get(ty as usize) {
+                        dst.push_str(&signature(f));
+                    }
+                    if let Some(name) = names.and_then(|n| n.get(funcs)) {
+                        write!(dst, " (;{};)", name).unwrap();
+                    }
+                    dst.push('\n');
+                    for local in body.locals() {
+                        let ty = format!(" {}", local.value_type());
+                        writeln!(dst, "    (local{})", ty.repeat(local.count() as usize))
+                            .unwrap();
+                    }
+                    code(&mut dst, body.code().elements());
+                    dst.push_str("  )\n");
+                    funcs += 1;
+                }
+            }
+            Section::Data(ref s) => {
+                for (i, segment) in s.entries().iter().enumerate() {
+                    writeln!(dst, "  (data (;{};) ({}) (;{} bytes;))",
+                             i, expr(segment.offset()), segment.value().len()).unwrap();
+                }
+            }
+            Section::Custom(ref s) => {
+                writeln!(dst, "  ;; custom section {:?}, {} bytes",
+                         s.name(), s.payload().len()).unwrap();
+            }
+            Section::Name(_) => dst.push_str("  ;; custom section \"name\"\n"),
+            Section::Reloc(ref s) => {
+                writeln!(dst, "  ;; custom section {:?}", s.name()).unwrap();
+            }
+            Section::Unparsed { id, ref payload } => {
+                writeln!(dst, "  ;; unknown section {}, {} bytes", id, payload.len()).unwrap();
+            }
+        }
+    }
+    dst.push_str(")\n");
+    dst
+}
+
+fn signature(f: &FunctionType) -> String {
+    let mut dst = String::new();
+    if !f.params().is_empty() {
+        dst.push_str(" (param");
+        for param in f.params() {
+            write!(dst, " {}", param).unwrap();
+        }
+        dst.push(')');
+    }
+    if let Some(ret) = f.return_type() {
+        write!(dst, " (result {})", ret).unwrap();
+    }
+    dst
+}
+
+fn limits(l: &ResizableLimits) -> String {
+    match l.maximum() {
+        Some(max) => format!("{} {}", l.initial(), max),
+        None => l.initial().to_string(),
+    }
+}
+
+fn table(t: &TableType) -> String {
+    format!("{} anyfunc", limits(t.limits()))
+}
+
+fn memory(m: &MemoryType, shared: bool) -> String {
+    format!("{}{}", limits(m.limits()), if shared { " shared" } else { "" })
+}
+
+fn global(g: &GlobalType) -> String {
+    if g.is_mutable() {
+        format!("(mut {})", g.content_type())
+    } else {
+        g.content_type().to_string()
+    }
+}
+
+/// The instructions of a constant expression, without its `end`.
+fn expr(e: &InitExpr) -> String {
+    e.code()
+        .iter()
+        .filter(|op| **op != Opcode::End)
+        .map(instruction)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes a function body one instruction per line, indenting the contents
+/// of blocks. The `end` closing the body itself is left out.
+fn code(dst: &mut String, ops: &[Opcode]) {
+    let mut depth = 2;
+    let ops = match ops.split_last() {
+        Some((&Opcode::End, rest)) => rest,
+        _ => ops,
+    };
+    for op in ops {
+        let opens = match *op {
+            Opcode::Block(_) | Opcode::Loop(_) | Opcode::If(_) => true,
+            Opcode::Else | Opcode::End => {
+                depth -= 1;
+                *op == Opcode::Else
+            }
+            _ => false,
+        };
+        writeln!(dst, "{}{}", "  ".repeat(depth), instruction(op)).unwrap();
+        if opens {
+            depth += 1;
+        }
+    }
+}
+
+fn instruction(op: &Opcode) -> String {
+    match *op {
+        // parity-wasm only prints the default target and the bits of floats.
+        Opcode::BrTable(ref targets, default) => {
+            let targets = targets.iter().map(|t| format!("{} ", t)).collect::<String>();
+            format!("br_table {}{}", targets, default)
+        }
+        Opcode::F32Const(bits) => format!("f32.const {}", f32::from_bits(bits)),
+        Opcode::F64Const(bits) => format!("f64.const {}", f64::from_bits(bits)),
+        ref op => op.to_string(),
+    }
+}
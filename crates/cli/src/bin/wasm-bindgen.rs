@@ -5,43 +5,122 @@ extern crate docopt;
 extern crate wasm_bindgen_shared;
 
 use std::path::PathBuf;
+use std::process;
 
 use docopt::Docopt;
-use wasm_bindgen_cli_support::Bindgen;
+use wasm_bindgen_cli_support::{Bindgen, EsVersion, Target, WasmImportMode};
 
 const USAGE: &'static str = "
 Generating JS bindings for a wasm file
 
 Usage:
-    wasm-bindgen [options] <input>
+    wasm-bindgen [options] [--local-module-root DIR]... [--target TARGET]... [--export-prefix SPEC]... <input>...
     wasm-bindgen -h | --help
     wasm-bindgen -V | --version
 
 Options:
     -h --help                Show this screen.
     --out-dir DIR            Output directory
+    --out-name NAME          Name the output files after NAME instead of the input
     --nodejs                 Generate output that only works in node.js
+    --nodejs-async           With --nodejs, load the wasm asynchronously
+    --nodejs-module          Generate ES modules that only work in node.js
     --browser                Generate output that only works in a browser
+    --bundler                Generate ES modules which import the wasm file by name
+                             for bundlers like webpack or Rollup
+    --deno                   Generate ES modules for Deno
     --no-modules             Generate output that only works in a browser (without modules)
     --no-modules-global VAR  Name of the global variable to initialize
+    --umd                    With --no-modules, also support loading with AMD or CommonJS
     --typescript             Output a TypeScript definition file
     --debug                  Include otherwise-extraneous debug checks in output
     --no-demangle            Don't demangle Rust symbol names
+    --no-gc                  Don't remove unused code from the output module
+    --minify-js              Shorten internal names and strip whitespace in the JS glue
+    --csp-safe               Generate JS that runs under a Content Security Policy
+                             without unsafe-eval
+    --es-target VERSION      Write the JS glue in VERSION of JS (es5 or es2015)
+    --wasm-import-mode MODE  How ES module glue loads the wasm (es-module, fetch-url
+                             or deferred)
+    --split-output           Generate a module for each exported class
+    --shared-runtime         Share the JS heap of all inputs in one runtime module
+    --initial-heap SIZES     Room for JS values in the JS heap before it grows, given
+                             as STACK,SLAB for borrowed and owned values
+    --heap-warning-threshold N  With --debug, warn once wasm owns N JS values
+    --string-interning       Cache strings passed between JS and wasm
+    --string-intern-capacity N  With --string-interning, how many strings each cache holds
+    --weak-refs              Free exported class instances once they're garbage collected
+    --build-id               Check the JS glue is given the wasm of the same build
+    --keep-debug             Keep the name section and DWARF debug info in the output
+    --remove-name-section    Remove the name section, even with --keep-debug
+    --remove-producers-section  Remove the producers section from the output
+    --preserve-code-offsets  Keep the code section as is, so DWARF debug info stays valid
+    --wasm-opt LEVEL         Optimize the output wasm with binaryen's wasm-opt -OLEVEL
+    --multi-value            Return strings and vectors from wasm as two values
+    --split-i64              Pass u64 and i64 as two 32-bit halves rather than BigInts
+    --reference-types        Pass JsValues to and from wasm as externrefs
+    --emit-wat               Also write a text rendering of the output wasm
+    --source-map             Also write source maps of the JS glue to the Rust code
+    --emit-metadata          Also write a JSON description of the generated API
+    --verbose                Print sizes and timings of each phase to stderr
+    --check                  Only check bindings can be generated, writing no output
+    --local-module-root DIR  Copy JS modules imported with a relative path from DIR
+    --target TARGET          Generate output for TARGET (bundler, browser, nodejs,
+                             nodejs-module, deno or no-modules), each into a
+                             subdirectory of the output directory when given more
+                             than once
+    --export-prefix SPEC     With multiple inputs, prefix the names exported by an input
+                             in `index.js`, given as STEM=PREFIX
     -V --version             Print the version number of wasm-bindgen
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
     flag_nodejs: bool,
+    flag_nodejs_async: bool,
+    flag_nodejs_module: bool,
     flag_browser: bool,
+    flag_bundler: bool,
+    flag_deno: bool,
     flag_no_modules: bool,
+    flag_umd: bool,
     flag_typescript: bool,
     flag_out_dir: Option<PathBuf>,
+    flag_out_name: Option<String>,
     flag_debug: bool,
     flag_version: bool,
     flag_no_demangle: bool,
+    flag_no_gc: bool,
+    flag_minify_js: bool,
+    flag_csp_safe: bool,
+    flag_split_output: bool,
+    flag_shared_runtime: bool,
+    flag_keep_debug: bool,
+    flag_remove_name_section: bool,
+    flag_remove_producers_section: bool,
+    flag_preserve_code_offsets: bool,
+    flag_multi_value: bool,
+    flag_split_i64: bool,
+    flag_reference_types: bool,
+    flag_emit_wat: bool,
+    flag_source_map: bool,
+    flag_emit_metadata: bool,
+    flag_verbose: bool,
+    flag_check: bool,
+    flag_local_module_root: Vec<PathBuf>,
+    flag_target: Vec<String>,
+    flag_export_prefix: Vec<String>,
     flag_no_modules_global: Option<String>,
-    arg_input: Option<PathBuf>,
+    flag_es_target: Option<String>,
+    flag_wasm_import_mode: Option<String>,
+    flag_initial_heap: Option<String>,
+    flag_heap_warning_threshold: Option<usize>,
+    flag_string_interning: bool,
+    flag_string_intern_capacity: Option<usize>,
+    flag_wasm_opt: Option<String>,
+    flag_weak_refs: bool,
+    flag_build_id: bool,
+    arg_input: Vec<PathBuf>,
 }
 
 fn main() {
@@ -54,27 +133,143 @@ fn main() {
         return;
     }
 
-    let input = match args.arg_input {
-        Some(s) => s,
-        None => panic!("input file expected"),
-    };
+    if args.arg_input.len() == 0 {
+        panic!("input file expected");
+    }
 
     let mut b = Bindgen::new();
-    b.input_path(&input)
+    b.input_path(&args.arg_input[0])
         .nodejs(args.flag_nodejs)
+        .nodejs_async(args.flag_nodejs_async)
+        .nodejs_module(args.flag_nodejs_module)
         .browser(args.flag_browser)
+        .bundler(args.flag_bundler)
+        .deno(args.flag_deno)
         .no_modules(args.flag_no_modules)
+        .umd(args.flag_umd)
         .debug(args.flag_debug)
         .demangle(!args.flag_no_demangle)
+        .run_gc(!args.flag_no_gc)
+        .minify_js(args.flag_minify_js)
+        .csp_safe(args.flag_csp_safe)
+        .split_output(args.flag_split_output)
+        .shared_runtime(args.flag_shared_runtime)
+        .keep_debug(args.flag_keep_debug)
+        .remove_name_section(args.flag_remove_name_section)
+        .remove_producers_section(args.flag_remove_producers_section)
+        .preserve_code_offsets(args.flag_preserve_code_offsets)
+        .multi_value(args.flag_multi_value)
+        .split_i64(args.flag_split_i64)
+        .reference_types(args.flag_reference_types)
+        .emit_wat(args.flag_emit_wat)
+        .source_map(args.flag_source_map)
+        .emit_metadata(args.flag_emit_metadata)
+        .string_interning(args.flag_string_interning)
+        .weak_refs(args.flag_weak_refs)
+        .build_id(args.flag_build_id)
+        .verbose(args.flag_verbose)
         .typescript(args.flag_typescript);
+    if let Some(ref name) = args.flag_out_name {
+        b.out_name(name);
+    }
     if let Some(ref name) = args.flag_no_modules_global {
         b.no_modules_global(name);
     }
+    if let Some(ref version) = args.flag_es_target {
+        b.es_target(match &version[..] {
+            "es5" => EsVersion::Es5,
+            "es2015" => EsVersion::Es2015,
+            _ => panic!("unknown JS version `{}`, expected es5 or es2015", version),
+        });
+    }
+    if let Some(ref mode) = args.flag_wasm_import_mode {
+        b.wasm_import_mode(match &mode[..] {
+            "es-module" => WasmImportMode::EsModule,
+            "fetch-url" => WasmImportMode::FetchUrl,
+            "deferred" => WasmImportMode::Deferred,
+            _ => panic!("unknown wasm import mode `{}`, expected es-module, \
+                         fetch-url or deferred", mode),
+        });
+    }
+    if let Some(ref sizes) = args.flag_initial_heap {
+        let mut parts = sizes.splitn(2, ',').map(|s| s.trim().parse::<usize>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(stack)), Some(Ok(slab))) => b.initial_heap_size(stack, slab),
+            _ => panic!("`--initial-heap` expects STACK,SLAB, not `{}`", sizes),
+        };
+    }
+    if let Some(threshold) = args.flag_heap_warning_threshold {
+        b.heap_warning_threshold(threshold);
+    }
+    if let Some(capacity) = args.flag_string_intern_capacity {
+        b.string_intern_capacity(capacity);
+    }
+    if let Some(ref level) = args.flag_wasm_opt {
+        b.wasm_opt(level);
+    }
+    for input in args.arg_input[1..].iter() {
+        b.add_input_path(input);
+    }
+    for spec in args.flag_export_prefix.iter() {
+        let mut parts = spec.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(stem), Some(prefix)) => b.export_prefix(stem, prefix),
+            _ => panic!("`--export-prefix` expects STEM=PREFIX, not `{}`", spec),
+        };
+    }
+    for dir in args.flag_local_module_root.iter() {
+        b.local_module_root(dir);
+    }
+    // A single target is generated into the output directory itself, as with
+    // the flag selecting it.
+    if args.flag_target.len() == 1 {
+        match &args.flag_target[0][..] {
+            "bundler" => b.bundler(true),
+            "browser" => b.browser(true),
+            "nodejs" => b.nodejs(true),
+            "nodejs-module" => b.nodejs_module(true),
+            "deno" => b.deno(true),
+            "no-modules" => b.no_modules(true),
+            t => panic!("unknown target `{}`", t),
+        };
+    } else {
+        let targets = args.flag_target.iter()
+            .map(|t| {
+                match &t[..] {
+                    "bundler" => Target::bundler(),
+                    "browser" => Target::browser(),
+                    "nodejs" => Target::nodejs(),
+                    "nodejs-module" => Target::nodejs_module(),
+                    "deno" => Target::deno(),
+                    "no-modules" => Target::no_modules(),
+                    _ => panic!("unknown target `{}`", t),
+                }
+            })
+            .collect::<Vec<_>>();
+        b.targets(&targets);
+    }
+
+    if args.flag_check {
+        if let Err(e) = b.validate() {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return
+    }
 
     let out_dir = match args.flag_out_dir {
         Some(ref p) => p,
         None => panic!("the `--out-dir` argument is now required"),
     };
 
-    b.generate(out_dir).expect("failed to generate bindings");
+    if let Err(e) = b.generate(out_dir) {
+        eprintln!("failed to generate bindings:\n{}", e);
+        process::exit(1);
+    }
+    // With `--verbose` they're counted in the stats printed already.
+    let fallbacks = b.stats().map_or(0, |s| s.interpreter_fallbacks.len());
+    if fallbacks > 0 && !args.flag_verbose {
+        eprintln!("warning: {} descriptors couldn't be interpreted and were run \
+                   with wasmi instead", fallbacks);
+    }
 }
@@ -1,12 +1,16 @@
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
-pub const SCHEMA_VERSION: &str = "4";
+pub const SCHEMA_VERSION: &str = "18";
 
 #[derive(Deserialize)]
 pub struct ProgramOnlySchema {
     pub schema_version: String,
     pub version: String,
+    /// Missing from the metadata of versions before it was added.
+    #[serde(default)]
+    pub crate_name: String,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -15,8 +19,10 @@ pub struct Program {
     pub enums: Vec<Enum>,
     pub imports: Vec<Import>,
     pub structs: Vec<Struct>,
+    pub consts: Vec<Const>,
     pub version: String,
     pub schema_version: String,
+    pub crate_name: String,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -32,6 +38,7 @@ pub enum ImportKind {
     Function(ImportFunction),
     Static(ImportStatic),
     Type(ImportType),
+    Enum(ImportEnum),
 }
 
 #[derive(Deserialize, Serialize)]
@@ -41,6 +48,10 @@ pub struct ImportFunction {
     pub method: bool,
     pub js_new: bool,
     pub structural: bool,
+    pub variadic: bool,
+    /// Whether the JS function is only looked up when it's first called,
+    /// so that it may be missing until then.
+    pub lazy: bool,
     pub getter: Option<String>,
     pub setter: Option<String>,
     pub class: Option<String>,
@@ -57,11 +68,31 @@ pub struct ImportStatic {
 pub struct ImportType {
 }
 
+/// A Rust enum standing for a closed set of JS strings, in the order of its
+/// variants.
+#[derive(Deserialize, Serialize)]
+pub struct ImportEnum {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Export {
     pub class: Option<String>,
     pub method: bool,
+    /// Whether the method takes `self` by value, moving it out of the JS
+    /// object.
+    pub consumed: bool,
+    /// Whether the method returns its receiver, like the `&mut Self` of a
+    /// builder's setters, which JS returns as `this` rather than wrapping.
+    #[serde(default)]
+    pub returns_self: bool,
     pub constructor: Option<String>,
+    pub iterator: Option<String>,
+    pub is_async: bool,
+    /// The message of a `#[deprecated]` attribute on the function, or an
+    /// empty string if it has none.
+    pub deprecated: Option<String>,
     pub function: Function,
 }
 
@@ -77,15 +108,62 @@ pub struct EnumVariant {
     pub value: u32
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct Const {
+    pub name: String,
+    pub value: ConstValue,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum ConstValue {
+    /// JSON has no infinities or `NaN`, so those are written as the strings
+    /// JS spells them with.
+    Number(#[serde(with = "number")] f64),
+    Boolean(bool),
+    String(String),
+    /// The value wasn't a literal, so it's read at runtime through the
+    /// exported `const_getter` shim.
+    Getter,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Function {
     pub name: String,
+    /// Where the Rust item is defined, missing from the metadata of versions
+    /// before it was recorded.
+    #[serde(default)]
+    pub location: Option<Location>,
+}
+
+/// A position in the Rust source, as the 1-based `line` and 0-based `column`
+/// in `file`, which is the path rustc was given.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct Struct {
     pub name: String,
     pub fields: Vec<StructField>,
+    pub superclass: Option<Superclass>,
+    /// Like `Export::deprecated`, for the struct itself.
+    pub deprecated: Option<String>,
+}
+
+/// A JS class which an exported class extends, imported from `module` or
+/// found in the global scope (under `js_namespace`, if given).
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct Superclass {
+    pub name: String,
+    pub module: Option<String>,
+    pub js_namespace: Option<String>,
+    /// Whether the arguments the class is constructed with are passed on to
+    /// the superclass's constructor, rather than calling it with none.
+    pub forward_args: bool,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -103,6 +181,33 @@ pub fn new_function(struct_name: &str) -> String {
     return name
 }
 
+pub fn instanceof_function(struct_name: &str) -> String {
+    let mut name = String::from("__wbg_");
+    name.extend(struct_name
+        .chars()
+        .flat_map(|s| s.to_lowercase()));
+    name.push_str("_instanceof");
+    name
+}
+
+pub fn ptr_function(struct_name: &str) -> String {
+    let mut name = String::from("__wbg_");
+    name.extend(struct_name
+        .chars()
+        .flat_map(|s| s.to_lowercase()));
+    name.push_str("_ptr");
+    name
+}
+
+pub fn unwrap_function(struct_name: &str) -> String {
+    let mut name = String::from("__wbg_");
+    name.extend(struct_name
+        .chars()
+        .flat_map(|s| s.to_lowercase()));
+    name.push_str("_unwrap");
+    name
+}
+
 pub fn free_function(struct_name: &str) -> String {
     let mut name = format!("__wbg_");
     name.extend(struct_name
@@ -146,6 +251,10 @@ pub fn struct_field_set(struct_: &str, f: &str) -> String {
     return name
 }
 
+pub fn const_getter(name: &str) -> String {
+    format!("__wbg_const_{}", name)
+}
+
 pub fn version() -> String {
     let mut v = env!("CARGO_PKG_VERSION").to_string();
     if let Some(s) = option_env!("WBG_VERSION") {
@@ -155,3 +264,55 @@ pub fn version() -> String {
     }
     return v
 }
+
+mod number {
+    use std::fmt;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if n.is_nan() {
+            serializer.serialize_str("NaN")
+        } else if n.is_infinite() {
+            serializer.serialize_str(if *n > 0.0 { "Infinity" } else { "-Infinity" })
+        } else {
+            serializer.serialize_f64(*n)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        deserializer.deserialize_any(NumberVisitor)
+    }
+
+    struct NumberVisitor;
+
+    impl<'de> Visitor<'de> for NumberVisitor {
+        type Value = f64;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number, `Infinity`, `-Infinity` or `NaN`")
+        }
+
+        fn visit_f64<E: de::Error>(self, n: f64) -> Result<f64, E> {
+            Ok(n)
+        }
+
+        fn visit_i64<E: de::Error>(self, n: i64) -> Result<f64, E> {
+            Ok(n as f64)
+        }
+
+        fn visit_u64<E: de::Error>(self, n: u64) -> Result<f64, E> {
+            Ok(n as f64)
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<f64, E> {
+            match s {
+                "Infinity" => Ok(f64::INFINITY),
+                "-Infinity" => Ok(f64::NEG_INFINITY),
+                "NaN" => Ok(f64::NAN),
+                _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+            }
+        }
+    }
+}
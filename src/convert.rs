@@ -6,7 +6,7 @@ use core::ops::{Deref, DerefMut};
 use core::slice;
 use core::str;
 
-use {JsValue, throw};
+use {JsValue, JSIDX_NULL, JSIDX_UNDEFINED, throw, throw_val};
 use describe::*;
 
 #[cfg(feature = "std")]
@@ -41,6 +41,25 @@ pub trait RefMutFromWasmAbi: WasmDescribe {
     unsafe fn ref_mut_from_abi(js: Self::Abi, extra: &mut Stack) -> Self::Anchor;
 }
 
+/// Types whose ABI has a value they never use themselves, which stands for
+/// `None` when passing an `Option` of them, or which push whether they're
+/// `Some` on the stack.
+pub trait OptionIntoWasmAbi: IntoWasmAbi {
+    /// The ABI of `None`, pushing on `extra` whatever `into_abi` would.
+    fn none(extra: &mut Stack) -> Self::Abi;
+
+    /// Pushes on `extra` whatever marks a value as `Some`, before `into_abi`
+    /// pushes anything.
+    fn some(_extra: &mut Stack) {}
+}
+
+/// The other direction of `OptionIntoWasmAbi`.
+pub trait OptionFromWasmAbi: FromWasmAbi {
+    /// Whether `js` stands for `None`, in which case whatever was pushed
+    /// along with it is popped off `extra`.
+    fn is_none(js: &Self::Abi, extra: &mut Stack) -> bool;
+}
+
 pub trait Stack {
     fn push(&mut self, bits: u32);
     fn pop(&mut self) -> u32;
@@ -60,6 +79,7 @@ unsafe impl WasmAbi for i32 {}
 unsafe impl WasmAbi for i64 {}
 unsafe impl WasmAbi for f32 {}
 unsafe impl WasmAbi for f64 {}
+unsafe impl WasmAbi for () {}
 
 macro_rules! simple {
     ($($t:tt)*) => ($(
@@ -93,6 +113,50 @@ macro_rules! as_u32 {
 
 as_u32!(i8 u8 i16 u16 isize usize);
 
+// Numbers use all of their ABI's values, so whether they're `Some` is pushed
+// on the stack, with a zero ABI for `None`.
+macro_rules! optional_numbers {
+    ($($t:tt)*) => ($(
+        impl OptionIntoWasmAbi for $t {
+            fn none(extra: &mut Stack) -> Self::Abi {
+                extra.push(0);
+                Default::default()
+            }
+
+            fn some(extra: &mut Stack) {
+                extra.push(1);
+            }
+        }
+
+        impl OptionFromWasmAbi for $t {
+            fn is_none(_js: &Self::Abi, extra: &mut Stack) -> bool {
+                extra.pop() == 0
+            }
+        }
+    )*)
+}
+
+optional_numbers!(i8 u8 i16 u16 i32 u32 isize usize f32 f64);
+
+impl IntoWasmAbi for () {
+    type Abi = ();
+
+    fn into_abi(self, _extra: &mut Stack) {}
+}
+
+// Only for returns of exports, whose `Err` is thrown in JS rather than
+// returned.
+impl<T: IntoWasmAbi> IntoWasmAbi for Result<T, JsValue> {
+    type Abi = T::Abi;
+
+    fn into_abi(self, extra: &mut Stack) -> T::Abi {
+        match self {
+            Ok(me) => me.into_abi(extra),
+            Err(e) => throw_val(e),
+        }
+    }
+}
+
 impl IntoWasmAbi for bool {
     type Abi = u32;
 
@@ -133,6 +197,32 @@ impl<T> FromWasmAbi for *mut T {
     }
 }
 
+impl<T: OptionIntoWasmAbi> IntoWasmAbi for Option<T> {
+    type Abi = T::Abi;
+
+    fn into_abi(self, extra: &mut Stack) -> T::Abi {
+        match self {
+            Some(me) => {
+                T::some(extra);
+                me.into_abi(extra)
+            }
+            None => T::none(extra),
+        }
+    }
+}
+
+impl<T: OptionFromWasmAbi> FromWasmAbi for Option<T> {
+    type Abi = T::Abi;
+
+    unsafe fn from_abi(js: T::Abi, extra: &mut Stack) -> Self {
+        if T::is_none(&js, extra) {
+            None
+        } else {
+            Some(T::from_abi(js, extra))
+        }
+    }
+}
+
 macro_rules! vectors {
     ($($t:ident)*) => ($(
         #[cfg(feature = "std")]
@@ -170,6 +260,17 @@ macro_rules! vectors {
             }
         }
 
+        impl<'a> IntoWasmAbi for &'a mut [$t] {
+            type Abi = u32;
+
+            fn into_abi(self, extra: &mut Stack) -> u32 {
+                let ptr = self.as_mut_ptr();
+                let len = self.len();
+                extra.push(len as u32);
+                ptr.into_abi(extra)
+            }
+        }
+
         impl RefFromWasmAbi for [$t] {
             type Abi = u32;
             type Anchor = &'static [$t];
@@ -181,6 +282,18 @@ macro_rules! vectors {
                 )
             }
         }
+
+        impl RefMutFromWasmAbi for [$t] {
+            type Abi = u32;
+            type Anchor = &'static mut [$t];
+
+            unsafe fn ref_mut_from_abi(js: u32, extra: &mut Stack) -> &'static mut [$t] {
+                slice::from_raw_parts_mut(
+                    <*mut $t>::from_abi(js, extra),
+                    extra.pop() as usize,
+                )
+            }
+        }
     )*)
 }
 
@@ -219,6 +332,24 @@ if_std! {
             String::from_utf8_unchecked(<Vec<u8>>::from_abi(js, extra))
         }
     }
+
+    // Even an empty string has a non-null pointer, so null is `None`.
+    impl OptionIntoWasmAbi for String {
+        fn none(extra: &mut Stack) -> u32 {
+            extra.push(0);
+            0
+        }
+    }
+
+    impl OptionFromWasmAbi for String {
+        fn is_none(js: &u32, extra: &mut Stack) -> bool {
+            if *js != 0 {
+                return false
+            }
+            extra.pop();
+            true
+        }
+    }
 }
 
 impl<'a> IntoWasmAbi for &'a str {
@@ -229,6 +360,13 @@ impl<'a> IntoWasmAbi for &'a str {
     }
 }
 
+impl<'a> OptionIntoWasmAbi for &'a str {
+    fn none(extra: &mut Stack) -> u32 {
+        extra.push(0);
+        0
+    }
+}
+
 impl RefFromWasmAbi for str {
     type Abi = <[u8] as RefFromWasmAbi>::Abi;
     type Anchor = &'static str;
@@ -238,6 +376,26 @@ impl RefFromWasmAbi for str {
     }
 }
 
+// Only for `Option<&str>` arguments, which JS frees once the call is over
+// just like for `&str`.
+impl<'a> FromWasmAbi for &'a str {
+    type Abi = <str as RefFromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi, extra: &mut Stack) -> Self {
+        <str as RefFromWasmAbi>::ref_from_abi(js, extra)
+    }
+}
+
+impl<'a> OptionFromWasmAbi for &'a str {
+    fn is_none(js: &u32, extra: &mut Stack) -> bool {
+        if *js != 0 {
+            return false
+        }
+        extra.pop();
+        true
+    }
+}
+
 impl IntoWasmAbi for JsValue {
     type Abi = u32;
 
@@ -256,6 +414,17 @@ impl FromWasmAbi for JsValue {
     }
 }
 
+// `undefined` and `null` have indices of their own, which are `None`.
+impl OptionIntoWasmAbi for JsValue {
+    fn none(_extra: &mut Stack) -> u32 { JSIDX_UNDEFINED }
+}
+
+impl OptionFromWasmAbi for JsValue {
+    fn is_none(js: &u32, _extra: &mut Stack) -> bool {
+        *js == JSIDX_UNDEFINED || *js == JSIDX_NULL
+    }
+}
+
 impl<'a> IntoWasmAbi for &'a JsValue {
     type Abi = u32;
     fn into_abi(self, _extra: &mut Stack) -> u32 {
@@ -272,6 +441,29 @@ impl RefFromWasmAbi for JsValue {
     }
 }
 
+impl<'a> IntoWasmAbi for &'a [JsValue] {
+    type Abi = u32;
+
+    fn into_abi(self, extra: &mut Stack) -> u32 {
+        let ptr = self.as_ptr();
+        let len = self.len();
+        extra.push(len as u32);
+        ptr.into_abi(extra)
+    }
+}
+
+impl RefFromWasmAbi for [JsValue] {
+    type Abi = u32;
+    type Anchor = &'static [JsValue];
+
+    unsafe fn ref_from_abi(js: u32, extra: &mut Stack) -> &'static [JsValue] {
+        slice::from_raw_parts(
+            <*const JsValue>::from_abi(js, extra),
+            extra.pop() as usize,
+        )
+    }
+}
+
 if_std! {
     impl IntoWasmAbi for Box<[JsValue]> {
         type Abi = u32;
@@ -472,3 +664,68 @@ stack_closures! {
     (A B C D E F)
     (A B C D E F G)
 }
+
+// Function pointers are passed as the table index of a shim converting the
+// arguments and return value, with the pointer itself (its own table index)
+// as an extra argument which the shim calls.
+macro_rules! fn_pointers {
+    ($( ($($var:ident)*) )*) => ($(
+        impl<$($var,)* R> IntoWasmAbi for fn($($var),*) -> R
+            where $($var: FromWasmAbi,)*
+                  R: IntoWasmAbi
+        {
+            type Abi = u32;
+
+            fn into_abi(self, extra: &mut Stack) -> u32 {
+                #[allow(non_snake_case)]
+                unsafe extern fn invoke<$($var: FromWasmAbi,)* R: IntoWasmAbi>(
+                    f: usize,
+                    $($var: <$var as FromWasmAbi>::Abi),*
+                ) -> <R as IntoWasmAbi>::Abi {
+                    let f: fn($($var),*) -> R = mem::transmute(f);
+                    let mut _stack = GlobalStack::new();
+                    $(
+                        let $var = <$var as FromWasmAbi>::from_abi($var, &mut _stack);
+                    )*
+                    f($($var),*).into_abi(&mut GlobalStack::new())
+                }
+                extra.push(self as usize as u32);
+                invoke::<$($var,)* R> as u32
+            }
+        }
+
+        impl<$($var,)*> IntoWasmAbi for fn($($var),*)
+            where $($var: FromWasmAbi,)*
+        {
+            type Abi = u32;
+
+            fn into_abi(self, extra: &mut Stack) -> u32 {
+                #[allow(non_snake_case)]
+                unsafe extern fn invoke<$($var: FromWasmAbi,)*>(
+                    f: usize,
+                    $($var: <$var as FromWasmAbi>::Abi),*
+                ) {
+                    let f: fn($($var),*) = mem::transmute(f);
+                    let mut _stack = GlobalStack::new();
+                    $(
+                        let $var = <$var as FromWasmAbi>::from_abi($var, &mut _stack);
+                    )*
+                    f($($var),*)
+                }
+                extra.push(self as usize as u32);
+                invoke::<$($var,)*> as u32
+            }
+        }
+    )*)
+}
+
+fn_pointers! {
+    ()
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+}
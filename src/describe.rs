@@ -37,6 +37,11 @@ tys! {
     ANYREF
     ENUM
     RUST_STRUCT
+    STRING_ENUM
+    DICTIONARY
+    FN_POINTER
+    OPTIONAL
+    UNIT
 }
 
 pub fn inform(a: u32) {
@@ -75,6 +80,15 @@ simple! {
     JsValue => ANYREF
 }
 
+impl WasmDescribe for () {
+    fn describe() { inform(UNIT) }
+}
+
+// JS only sees what's returned, as errors are thrown.
+impl<T: WasmDescribe> WasmDescribe for Result<T, JsValue> {
+    fn describe() { T::describe() }
+}
+
 impl<T> WasmDescribe for *const T {
     fn describe() { inform(I32) }
 }
@@ -83,6 +97,13 @@ impl<T> WasmDescribe for *mut T {
     fn describe() { inform(I32) }
 }
 
+impl<T: WasmDescribe> WasmDescribe for Option<T> {
+    fn describe() {
+        inform(OPTIONAL);
+        T::describe();
+    }
+}
+
 impl<T: WasmDescribe> WasmDescribe for [T] {
     fn describe() {
         inform(SLICE);
@@ -176,6 +197,25 @@ macro_rules! doit {
                 inform(0);
             }
         }
+
+        impl<$($var,)* R> WasmDescribe for fn($($var),*) -> R
+            where $($var: WasmDescribe,)*
+                  R: WasmDescribe
+        {
+            fn describe() {
+                inform(FN_POINTER);
+                <Fn($($var),*) -> R>::describe();
+            }
+        }
+
+        impl<$($var,)*> WasmDescribe for fn($($var),*)
+            where $($var: WasmDescribe,)*
+        {
+            fn describe() {
+                inform(FN_POINTER);
+                <Fn($($var),*)>::describe();
+            }
+        }
     )*)
 }
 
@@ -10,6 +10,11 @@
 
 extern crate wasm_bindgen_macro;
 
+#[cfg(feature = "serde-serialize")]
+extern crate serde;
+#[cfg(feature = "serde-serialize")]
+extern crate serde_json;
+
 use core::cell::UnsafeCell;
 use core::ops::Deref;
 use core::ptr;
@@ -30,6 +35,7 @@ macro_rules! if_std {
 pub mod prelude {
     pub use wasm_bindgen_macro::wasm_bindgen;
     pub use JsValue;
+    pub use TryFromJsValue;
 
     if_std! {
         pub use closure::Closure;
@@ -43,6 +49,7 @@ if_std! {
     extern crate std;
     use std::prelude::v1::*;
     pub mod closure;
+    pub mod promise;
 }
 
 /// Representation of an object owned by JS.
@@ -55,6 +62,14 @@ pub struct JsValue {
     idx: u32,
 }
 
+// Indices of the values the JS heap reserves, which are never allocated or
+// freed. Owned indices are even, as odd ones are borrowed from the stack.
+const JSIDX_UNDEFINED: u32 = 0;
+const JSIDX_NULL: u32 = 2;
+const JSIDX_TRUE: u32 = 4;
+const JSIDX_FALSE: u32 = 6;
+const JSIDX_RESERVED: u32 = 8;
+
 impl JsValue {
     /// Creates a new JS value which is a string.
     ///
@@ -78,26 +93,20 @@ impl JsValue {
 
     /// Creates a new JS value which is a boolean.
     ///
-    /// This function creates a JS object representing a boolean (a heap
-    /// allocated boolean) and returns a handle to the JS version of it.
+    /// Booleans have a fixed place in the JS heap, so this doesn't call into
+    /// JS at all.
     pub fn from_bool(b: bool) -> JsValue {
-        unsafe {
-            JsValue { idx: __wbindgen_boolean_new(b as u32) }
-        }
+        JsValue { idx: if b { JSIDX_TRUE } else { JSIDX_FALSE } }
     }
 
     /// Creates a new JS value representing `undefined`.
     pub fn undefined() -> JsValue {
-        unsafe {
-            JsValue { idx: __wbindgen_undefined_new() }
-        }
+        JsValue { idx: JSIDX_UNDEFINED }
     }
 
     /// Creates a new JS value representing `null`.
     pub fn null() -> JsValue {
-        unsafe {
-            JsValue { idx: __wbindgen_null_new() }
-        }
+        JsValue { idx: JSIDX_NULL }
     }
 
     /// Creates a new JS symbol with the optional description specified.
@@ -112,6 +121,60 @@ impl JsValue {
         }
     }
 
+    /// Creates a new `JsValue` from the JSON serialization of the object `t`
+    /// provided.
+    ///
+    /// This function will serialize the provided value `t` to a JSON string,
+    /// send the JSON string to JS, parse it into a JS object, and then return
+    /// a handle to the JS object. This is unlikely to be super speedy so it's
+    /// not recommended for hot loops, but it can be handy for passing complex
+    /// configuration around without writing bindings for each field.
+    ///
+    /// This function is only available when the `serde-serialize` feature of
+    /// the `wasm-bindgen` crate is activated.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error encountered when serializing `T` into JSON.
+    #[cfg(feature = "serde-serialize")]
+    pub fn from_serde<T>(t: &T) -> serde_json::Result<JsValue>
+        where T: serde::ser::Serialize + ?Sized,
+    {
+        let s = serde_json::to_string(t)?;
+        unsafe {
+            Ok(JsValue { idx: __wbindgen_json_parse(s.as_ptr(), s.len()) })
+        }
+    }
+
+    /// Invokes `JSON.stringify` on this value and then parses the resulting
+    /// JSON into an arbitrary Rust value.
+    ///
+    /// A JS `undefined` (or anything else `JSON.stringify` can't represent,
+    /// like a function) is deserialized as though it were `null`, so for
+    /// example an `Option<T>` will come out as `None`.
+    ///
+    /// This function is only available when the `serde-serialize` feature of
+    /// the `wasm-bindgen` crate is activated.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error encountered when parsing the JSON into a `T`.
+    #[cfg(feature = "serde-serialize")]
+    pub fn into_serde<T>(&self) -> serde_json::Result<T>
+        where T: for<'a> serde::de::Deserialize<'a>,
+    {
+        unsafe {
+            let mut ptr = ptr::null_mut();
+            let len = __wbindgen_json_serialize(self.idx, &mut ptr);
+            let s = Vec::from_raw_parts(ptr, len, len);
+            if s == b"undefined" {
+                serde_json::from_slice(b"null")
+            } else {
+                serde_json::from_slice(&s)
+            }
+        }
+    }
+
     // #[doc(hidden)]
     // pub unsafe fn __from_idx(idx: u32) -> JsValue {
     //     JsValue { idx }
@@ -214,6 +277,12 @@ if_std! {
             JsValue::from_str(s)
         }
     }
+
+    impl From<String> for JsValue {
+        fn from(s: String) -> JsValue {
+            JsValue::from_str(&s)
+        }
+    }
 }
 
 impl From<bool> for JsValue {
@@ -241,16 +310,23 @@ extern {
     fn __wbindgen_string_new(ptr: *const u8, len: usize) -> u32;
     fn __wbindgen_number_new(f: f64) -> u32;
     fn __wbindgen_number_get(idx: u32, invalid: *mut u8) -> f64;
-    fn __wbindgen_null_new() -> u32;
-    fn __wbindgen_undefined_new() -> u32;
     fn __wbindgen_is_null(idx: u32) -> u32;
     fn __wbindgen_is_undefined(idx: u32) -> u32;
-    fn __wbindgen_boolean_new(val: u32) -> u32;
     fn __wbindgen_boolean_get(idx: u32) -> u32;
     fn __wbindgen_symbol_new(ptr: *const u8, len: usize) -> u32;
     fn __wbindgen_is_symbol(idx: u32) -> u32;
     fn __wbindgen_string_get(idx: u32, len: *mut usize) -> *mut u8;
     fn __wbindgen_throw(a: *const u8, b: usize) -> !;
+    fn __wbindgen_rethrow(idx: u32) -> !;
+    fn __wbindgen_panic(a: *const u8, b: usize) -> !;
+    fn __wbindgen_intern(ptr: *const u8, len: usize);
+    fn __wbindgen_unintern(ptr: *const u8, len: usize);
+
+    fn __wbindgen_object_new() -> u32;
+    fn __wbindgen_object_set(idx: u32, key: *const u8, key_len: usize, val: u32);
+
+    fn __wbindgen_json_parse(ptr: *const u8, len: usize) -> u32;
+    fn __wbindgen_json_serialize(idx: u32, ptr: *mut *mut u8) -> usize;
 
     fn __wbindgen_cb_arity0(a: u32, b: u32, c: u32) -> u32;
     fn __wbindgen_cb_arity1(a: u32, b: u32, c: u32) -> u32;
@@ -263,6 +339,8 @@ extern {
     fn __wbindgen_cb_drop(idx: u32);
     fn __wbindgen_cb_forget(idx: u32);
 
+    fn __wbindgen_promise_then(promise: u32, invoke: u32, f: u32);
+
     fn __wbindgen_describe(v: u32);
 }
 
@@ -277,12 +355,37 @@ impl Clone for JsValue {
 
 impl Drop for JsValue {
     fn drop(&mut self) {
+        if self.idx < JSIDX_RESERVED {
+            return
+        }
         unsafe {
             __wbindgen_object_drop_ref(self.idx);
         }
     }
 }
 
+/// Conversion of a `JsValue` back into a Rust type exported to JS as a class,
+/// implemented by `#[wasm_bindgen]` for every exported struct.
+pub trait TryFromJsValue: Sized {
+    /// Returns whether `value` is an instance of the JS class this type is
+    /// exported as.
+    fn is_instance(value: &JsValue) -> bool;
+
+    /// Borrows the Rust value in `value`, returning `None` if it isn't a live
+    /// instance of the JS class this type is exported as.
+    ///
+    /// Just like while a `&Self` is passed to Rust, the JS object can't be
+    /// used mutably or freed until the returned guard is dropped.
+    fn try_ref<'a>(value: &'a JsValue) -> Option<__rt::Ref<'a, Self>>;
+
+    /// Takes the Rust value out of `value`, returning `value` itself if it
+    /// isn't a live instance of the JS class this type is exported as.
+    ///
+    /// Ownership moves into Rust, so just like after calling `free` the JS
+    /// object can't be used anymore.
+    fn try_from(value: JsValue) -> Result<Self, JsValue>;
+}
+
 /// Wrapper type for imported statics.
 ///
 /// This type is used whenever a `static` is imported from a JS module, for
@@ -357,6 +460,57 @@ pub fn throw(s: &str) -> ! {
     }
 }
 
+/// Throws `val` as a JS exception, the way exports returning an `Err` do.
+///
+/// Like `throw` this doesn't return, and nothing on the wasm stack is dropped.
+#[cold]
+#[inline(never)]
+pub fn throw_val(val: JsValue) -> ! {
+    let idx = val.idx;
+    core::mem::forget(val);
+    unsafe {
+        __wbindgen_rethrow(idx);
+    }
+}
+
+/// Registers `s` as a string which is passed to JS often, returning it.
+///
+/// When the bindings are generated with string interning turned on the glue
+/// decodes `s` once, and passing it to JS afterwards hands out the same JS
+/// string rather than copying it out of wasm's memory again. Otherwise this
+/// does nothing. Once the glue's cache is full the oldest string in it is
+/// dropped to make room.
+pub fn intern(s: &'static str) -> &'static str {
+    unsafe {
+        __wbindgen_intern(s.as_ptr(), s.len());
+    }
+    s
+}
+
+/// Drops `s` from the glue's cache of strings registered with `intern`,
+/// making room for others.
+pub fn unintern(s: &'static str) {
+    unsafe {
+        __wbindgen_unintern(s.as_ptr(), s.len());
+    }
+}
+
+/// Installs a panic hook which throws a JS `Error` with the panic message,
+/// including the file, line and column it happened at.
+///
+/// Without it a panic usually surfaces in JS as an opaque `unreachable`
+/// error. In debug mode the glue also passes the `Error` to the handler
+/// registered with its `setPanicHandler` export first.
+#[cfg(feature = "std")]
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let msg = info.to_string();
+        unsafe {
+            __wbindgen_panic(msg.as_ptr(), msg.len());
+        }
+    }));
+}
+
 #[doc(hidden)]
 pub mod __rt {
     use core::cell::{Cell, UnsafeCell};
@@ -377,6 +531,21 @@ pub mod __rt {
         ($($i:item)*) => ($($i)*)
     }
 
+    /// Creates an empty JS object, for dictionaries to fill in.
+    pub fn object_new() -> super::JsValue {
+        unsafe {
+            super::JsValue { idx: super::__wbindgen_object_new() }
+        }
+    }
+
+    /// Sets the property `key` of the JS object `obj` to `val`.
+    pub fn object_set(obj: &super::JsValue, key: &str, val: super::JsValue) {
+        unsafe {
+            super::__wbindgen_object_set(obj.idx, key.as_ptr(), key.len(), val.idx);
+            core::mem::forget(val);
+        }
+    }
+
     #[inline]
     pub fn assert_not_null<T>(s: *mut T) {
         if s.is_null() {
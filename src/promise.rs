@@ -0,0 +1,57 @@
+//! Support for consuming JS promises from Rust
+//!
+//! This module defines the `then` function which is used to register a Rust
+//! callback that's invoked once a JS `Promise` settles. Exports can also be
+//! tagged with `#[wasm_bindgen(async)]` to return a `Promise` to JS.
+
+use std::prelude::v1::*;
+
+use JsValue;
+
+/// Registers `f` to be invoked once `promise` settles.
+///
+/// The callback receives `Ok` with the resolved value if the promise is
+/// fulfilled and `Err` with the rejection reason if it's rejected. If
+/// `promise` isn't actually a `Promise` it's treated as one that's already
+/// been resolved with that value.
+///
+/// Note that `f` is only ever run from the JS event loop, after the current
+/// call into wasm has returned.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// #[wasm_bindgen]
+/// extern {
+///     fn fetch_something() -> JsValue;
+///
+///     #[wasm_bindgen(js_namespace = console)]
+///     fn log(s: &str);
+/// }
+///
+/// #[wasm_bindgen]
+/// pub fn run() {
+///     wasm_bindgen::promise::then(&fetch_something(), |result| {
+///         match result {
+///             Ok(_) => log("resolved!"),
+///             Err(_) => log("rejected!"),
+///         }
+///     });
+/// }
+/// ```
+pub fn then<F>(promise: &JsValue, f: F)
+    where F: FnOnce(Result<JsValue, JsValue>) + 'static,
+{
+    unsafe extern fn invoke<F>(f: u32, ok: u32, val: u32)
+        where F: FnOnce(Result<JsValue, JsValue>) + 'static,
+    {
+        let f = *Box::from_raw(f as *mut F);
+        let val = JsValue { idx: val };
+        f(if ok != 0 { Ok(val) } else { Err(val) })
+    }
+
+    let f = Box::into_raw(Box::new(f)) as u32;
+    unsafe {
+        super::__wbindgen_promise_then(promise.idx, invoke::<F> as u32, f);
+    }
+}
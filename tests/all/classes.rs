@@ -1,4 +1,7 @@
-use super::project;
+use cli::Bindgen;
+
+use super::{project, MemoryOutput};
+use super::fixtures::*;
 
 #[test]
 fn simple() {
@@ -597,3 +600,632 @@ fn readonly_fields() {
         "#)
         .test();
 }
+
+#[test]
+fn readonly_fields_debug() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[derive(Default)]
+            pub struct Foo {
+                #[wasm_bindgen(readonly)]
+                pub a: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo::default()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { Foo } from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                const a = Foo.new();
+                assert.strictEqual(a.a, 0);
+                assert.throws(() => (a as any).a = 3,
+                              /TypeError: cannot assign to readonly field 'a' of Foo/);
+                assert.strictEqual(a.a, 0);
+                a.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn use_after_free() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                pub a: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { a: 0 }
+                }
+
+                pub fn get(&self) -> u32 {
+                    self.a
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn consume(_f: Foo) {}
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Foo, consume } from "./out";
+
+            export function test() {
+                const a = Foo.new();
+                assert.strictEqual(a.get(), 0);
+                a.free();
+                assert.throws(() => a.get(), /Attempt to use a moved value: Foo.get/);
+                assert.throws(() => a.a, /Attempt to use a moved value: Foo.a/);
+                assert.throws(() => { a.a = 1; }, /Attempt to use a moved value: Foo.a/);
+                a.free();
+
+                const b = Foo.new();
+                consume(b);
+                assert.throws(() => b.get(), /Attempt to use a moved value: Foo.get/);
+                assert.throws(() => consume(b), /Attempt to use a moved value: Foo/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn many_classes() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn import_a() -> u32;
+                fn import_b() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub struct Zed { pub a: u32 }
+            #[wasm_bindgen]
+            pub struct Alpha { pub a: u32 }
+            #[wasm_bindgen]
+            pub struct Middle { pub a: u32 }
+            #[wasm_bindgen]
+            pub struct Beta { pub a: u32 }
+
+            #[wasm_bindgen]
+            impl Zed {
+                pub fn new() -> Zed { Zed { a: import_a() } }
+            }
+            #[wasm_bindgen]
+            impl Alpha {
+                pub fn new() -> Alpha { Alpha { a: import_b() } }
+            }
+            #[wasm_bindgen]
+            impl Middle {
+                pub fn new(s: &str) -> Middle { Middle { a: s.len() as u32 } }
+            }
+            #[wasm_bindgen]
+            impl Beta {
+                pub fn new(a: &[f64]) -> Beta { Beta { a: a.len() as u32 } }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Zed, Alpha, Middle, Beta } from "./out";
+
+            export function import_a(): number { return 1; }
+            export function import_b(): number { return 2; }
+
+            export function test() {
+                assert.strictEqual(Zed.new().a, 1);
+                assert.strictEqual(Alpha.new().a, 2);
+                assert.strictEqual(Middle.new("abc").a, 3);
+                assert.strictEqual(Beta.new(new Float64Array(4)).a, 4);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+#[should_panic(expected = "is defined by both")]
+fn class_and_function_with_the_same_name() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+            #![allow(non_snake_case)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo {}
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn Foo() -> u32 {
+                1
+            }
+        "#)
+        .file("test.ts", r#"
+            export function test() {}
+        "#)
+        .test();
+}
+
+#[test]
+fn debugging_helpers() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                pub a: u32,
+                pub b: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { a: 1, b: 2 }
+                }
+            }
+
+            #[wasm_bindgen]
+            pub struct Bar {}
+
+            #[wasm_bindgen]
+            impl Bar {
+                pub fn new() -> Bar {
+                    Bar {}
+                }
+
+                #[allow(non_snake_case)]
+                pub fn toString(&self) -> String {
+                    "a bar".to_string()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { Foo, Bar } from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                const foo = Foo.new();
+                assert.strictEqual(Object.prototype.toString.call(foo), "[object Foo]");
+                assert.strictEqual(JSON.stringify(foo), '{"a":1,"b":2}');
+                assert.ok(foo.toString().startsWith("Foo { ptr: "));
+                foo.free();
+
+                const bar = Bar.new();
+                assert.strictEqual(Object.prototype.toString.call(bar), "[object Bar]");
+                assert.strictEqual(bar.toString(), "a bar");
+                bar.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn try_from_js_value() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                a: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new(a: u32) -> Foo {
+                    Foo { a }
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn is_foo(value: &JsValue) -> bool {
+                Foo::is_instance(value)
+            }
+
+            #[wasm_bindgen]
+            pub fn peek_foo(value: &JsValue) -> u32 {
+                match Foo::try_ref(value) {
+                    Some(foo) => foo.a,
+                    None => 0,
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn take_foo(value: JsValue) -> u32 {
+                match Foo::try_from(value) {
+                    Ok(foo) => foo.a,
+                    Err(_) => 0,
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { Foo, is_foo, peek_foo, take_foo } from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                const foo = Foo.new(3);
+                assert.strictEqual(is_foo(foo), true);
+                assert.strictEqual(is_foo({}), false);
+                assert.strictEqual(is_foo(3), false);
+
+                assert.strictEqual(peek_foo({}), 0);
+                assert.strictEqual(peek_foo(foo), 3);
+                // borrowing leaves the instance usable
+                assert.strictEqual(peek_foo(foo), 3);
+
+                assert.strictEqual(take_foo({}), 0);
+                assert.strictEqual(take_foo(foo), 3);
+                // ownership moved into Rust
+                assert.strictEqual(take_foo(foo), 0);
+                assert.strictEqual(peek_foo(foo), 0);
+                assert.strictEqual(is_foo(foo), true);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn try_ref_glue() {
+    use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+    use super::fixtures::words::*;
+
+    // `frame_ptr_of` returns what `__wbg_frame_ptr` makes of its argument,
+    // the pointer `Frame::try_ref` borrows.
+    let exports = [("frame_ptr_of".to_string(), function(&[&[REF, ANYREF]], Some(&[U32])))];
+    let imports = [("__wbg_frame_ptr".to_string(), Vec::new())];
+    let ty = FunctionType::new(vec![ValueType::I32], Some(ValueType::I32));
+    let wasm = with_body(&described_wasm(&exports, &imports), "frame_ptr_of", ty.clone(),
+                         vec![Opcode::GetLocal(0), Opcode::Call(1), Opcode::End]);
+    let wasm = with_import_type(&wasm, "__wbg_frame_ptr", ty);
+    let mut program = program("try_ref");
+    program.exports.push(export("frame_ptr_of"));
+    program.structs.push(class("Frame"));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&wasm, &program), "out")
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    run_node("try-ref", &out.files, &[], r#"
+        const assert = require('assert');
+        const path = require('path');
+        const out = require(path.join(process.argv[1], 'out.js'));
+        const frame = out.Frame.__construct(24);
+        assert.strictEqual(out.frame_ptr_of(frame), 24);
+        assert.strictEqual(out.frame_ptr_of(frame), 24);
+        assert.strictEqual(out.frame_ptr_of({}), 0);
+        assert.strictEqual(out.frame_ptr_of(null), 0);
+        frame.free();
+        assert.strictEqual(out.frame_ptr_of(frame), 0);
+    "#);
+}
+
+#[test]
+fn iterator() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct List {
+                contents: Vec<String>,
+            }
+
+            #[wasm_bindgen]
+            impl List {
+                pub fn new() -> List {
+                    List { contents: Vec::new() }
+                }
+
+                pub fn push(&mut self, s: &str) {
+                    self.contents.push(s.to_string());
+                }
+
+                #[wasm_bindgen(iterator = len)]
+                pub fn len(&self) -> u32 {
+                    self.contents.len() as u32
+                }
+
+                #[wasm_bindgen(iterator = get)]
+                pub fn get(&self, idx: u32) -> String {
+                    self.contents[idx as usize].clone()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { List } from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                const list = List.new();
+                assert.deepStrictEqual(Array.from(list), []);
+                list.push("a");
+                list.push("b");
+                const contents: string[] = Array.from(list);
+                assert.deepStrictEqual(contents, ["a", "b"]);
+
+                const iter = list[Symbol.iterator]();
+                assert.deepStrictEqual(iter.next(), { value: "a", done: false });
+                list.free();
+                assert.throws(() => iter.next(), /Attempt to use a moved value/);
+                assert.throws(() => Array.from(list), /Attempt to use a moved value/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn consuming_methods() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct WidgetBuilder {
+                width: u32,
+                height: u32,
+            }
+
+            #[wasm_bindgen]
+            impl WidgetBuilder {
+                pub fn new() -> WidgetBuilder {
+                    WidgetBuilder { width: 1, height: 1 }
+                }
+
+                pub fn width(self, width: u32) -> WidgetBuilder {
+                    WidgetBuilder { width, ..self }
+                }
+
+                pub fn height(mut self, height: u32) -> WidgetBuilder {
+                    self.height = height;
+                    self
+                }
+
+                pub fn build(self) -> Widget {
+                    Widget { area: self.width * self.height }
+                }
+            }
+
+            #[wasm_bindgen]
+            pub struct Widget {
+                area: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Widget {
+                pub fn area(&self) -> u32 {
+                    self.area
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { WidgetBuilder, Widget } from "./out";
+
+            export function test() {
+                const builder = WidgetBuilder.new();
+                const widget = builder.width(3).height(4).build();
+                assert.ok(widget instanceof Widget);
+                assert.strictEqual(widget.area(), 12);
+                assert.strictEqual((builder as any).ptr, 0);
+                assert.throws(() => builder.build(), /Attempt to use a moved value: WidgetBuilder.build/);
+                widget.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn chained_methods() {
+    project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Config {
+                width: u32,
+                height: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Config {
+                pub fn new() -> Config {
+                    Config { width: 1, height: 1 }
+                }
+
+                pub fn width(&mut self, width: u32) -> &mut Self {
+                    self.width = width;
+                    self
+                }
+
+                pub fn height(&mut self, height: u32) -> &mut Config {
+                    self.height = height;
+                    self
+                }
+
+                pub fn same(&self) -> &Self {
+                    self
+                }
+
+                pub fn copy(&self) -> Config {
+                    Config { width: self.width, height: self.height }
+                }
+
+                pub fn area(&self) -> u32 {
+                    self.width * self.height
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Config } from "./out";
+
+            export function test() {
+                const config = Config.new();
+                assert.strictEqual(config.width(3).height(4).same(), config);
+                assert.strictEqual(config.area(), 12);
+
+                // Only methods returning something else wrap a new pointer.
+                const copy = config.copy();
+                assert.notStrictEqual(copy, config);
+                assert.strictEqual(copy.width(5).area(), 20);
+                assert.strictEqual(config.area(), 12);
+                copy.free();
+                config.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn weak_refs() {
+    use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+    use shared::Export;
+    use super::fixtures::words::*;
+
+    // `Frame::into_len` takes its receiver by value, and `__wbg_frame_free`
+    // counts its calls in the word at address 4.
+    let exports = [("frame_into_len".to_string(), function(&[&[U32]], Some(&[U32])))];
+    let wasm = with_body(
+        &described_wasm(&exports, &[]),
+        "__wbg_frame_free",
+        FunctionType::new(vec![ValueType::I32], None),
+        vec![
+            Opcode::I32Const(4),
+            Opcode::I32Const(4),
+            Opcode::I32Load(2, 0),
+            Opcode::I32Const(1),
+            Opcode::I32Add,
+            Opcode::I32Store(2, 0),
+            Opcode::End,
+        ],
+    );
+    let mut program = program("weak_refs");
+    program.exports.push(Export { consumed: true, ..method("Frame", "into_len") });
+    program.structs.push(class("Frame"));
+    let wasm = with_program(&wasm, &program);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .debug(true)
+        .weak_refs(true)
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // Instances are registered when they're wrapped and unregistered when
+    // they give up their pointer, by `free` or by being moved, which is
+    // undone if the move never happens.
+    let js = String::from_utf8(out.files["out.js"].clone()).unwrap();
+    assert!(js.contains("\
+const FrameFinalization = typeof(FinalizationRegistry) === 'undefined'
+    ? { register: function() {}, unregister: function() {} }
+    : new FinalizationRegistry(function(ptr) {
+        instance_counts.reclaimed++;
+        wasm.__wbg_frame_free(ptr);
+    });
+"));
+    let calls = js.lines()
+        .map(|l| l.trim())
+        .filter(|l| l.starts_with("FrameFinalization."))
+        .collect::<Vec<_>>();
+    assert_eq!(calls, [
+        "FrameFinalization.register(obj, ptr, obj);",
+        "FrameFinalization.unregister(this);",
+        "FrameFinalization.unregister(this);",
+        "FrameFinalization.register(this, ptr, this);",
+    ]);
+
+    // Only the instances still owning their pointer when they're collected
+    // are freed by the collector, and never twice.
+    run_node("weak-refs", &out.files, &["--expose-gc"], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'out.js'));
+            const memory = require(path.join(process.argv[1], 'out_bg')).memory;
+            const frees = () => new Uint32Array(memory.buffer)[1];
+            (() => {
+                out.Frame.__construct(24);
+                out.Frame.__construct(32).free();
+                out.Frame.__construct(40).into_len(1);
+                const frame = out.Frame.__construct(48);
+                assert.throws(() => frame.into_len('b'), /argument 0/);
+            })();
+            assert.strictEqual(frees(), 1);
+            // Finalizers run some time after the collection, so it's retried
+            // until they have.
+            const collect = tries => {
+                global.gc();
+                setTimeout(() => {
+                    if (frees() < 3 && tries > 0) {
+                        return collect(tries - 1);
+                    }
+                    assert.strictEqual(frees(), 3);
+                    const stats = out.debugObjectStats();
+                    assert.strictEqual(stats.reclaimed, 2);
+                    assert.strictEqual(stats.freed, 1);
+                }, 10);
+            };
+            collect(100);
+        "#);
+}
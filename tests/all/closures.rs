@@ -512,3 +512,69 @@ fn string_ret() {
         .test();
 }
 
+
+#[test]
+fn fn_pointers() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn register(f: fn(u32) -> u32);
+                fn register_greeter(f: fn(String) -> String);
+            }
+
+            fn double(a: u32) -> u32 {
+                a * 2
+            }
+
+            fn greet(name: String) -> String {
+                format!("Hello, {}!", name)
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                register(double);
+                register(double);
+                register_greeter(greet);
+            }
+
+            #[wasm_bindgen]
+            pub fn doubler() -> fn(u32) -> u32 {
+                double
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { run, doubler } from "./out";
+
+            const registered: ((a: number) => number)[] = [];
+            let greeter: ((name: string) => string) | null = null;
+
+            export function register(f: (a: number) => number) {
+                registered.push(f);
+            }
+
+            export function register_greeter(f: (name: string) => string) {
+                greeter = f;
+            }
+
+            export function test() {
+                run();
+                assert.strictEqual(registered.length, 2);
+                assert.strictEqual(registered[0], registered[1]);
+                assert.strictEqual(registered[0](21), 42);
+                assert.strictEqual(doubler(), registered[0]);
+                assert.strictEqual(greeter!("wasm"), "Hello, wasm!");
+
+                // Still callable after the import returned.
+                assert.strictEqual(doubler()(4), 8);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use cli::Bindgen;
+use shared::{Const, ConstValue};
+
+use super::{project, MemoryOutput};
+use super::fixtures::*;
+
+#[test]
+fn literal_consts() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub const VERSION: &str = "1.2.3";
+
+            #[wasm_bindgen]
+            pub const MAX_ITEMS: u32 = 64;
+
+            #[wasm_bindgen]
+            pub const OFFSET: f64 = -0.5;
+
+            #[wasm_bindgen]
+            pub static ENABLED: bool = true;
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.VERSION, "1.2.3");
+                assert.strictEqual(wasm.MAX_ITEMS, 64);
+                assert.strictEqual(wasm.OFFSET, -0.5);
+                assert.strictEqual(wasm.ENABLED, true);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn computed_consts() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            const BASE: u32 = 8;
+
+            #[wasm_bindgen]
+            pub const LIMIT: u32 = BASE * 2;
+
+            #[wasm_bindgen]
+            pub static NAME: &'static str = concat!("wasm", "-", "bindgen");
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.LIMIT, 16);
+                assert.strictEqual(wasm.NAME, "wasm-bindgen");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn const_module() {
+    let mut program = representative_program(Some("./logger"));
+    program.consts = vec![
+        Const { name: "VERSION".to_string(), value: ConstValue::String("1.0 \"beta\"".to_string()) },
+        Const { name: "LIMIT".to_string(), value: ConstValue::Number(-2.5) },
+        Const { name: "ENABLED".to_string(), value: ConstValue::Boolean(true) },
+        Const { name: "MAX".to_string(), value: ConstValue::Number(f64::INFINITY) },
+        Const { name: "MIN".to_string(), value: ConstValue::Number(f64::NEG_INFINITY) },
+        Const { name: "UNKNOWN".to_string(), value: ConstValue::Number(f64::NAN) },
+    ];
+    let wasm = with_program(REPRESENTATIVE_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.consts.js", &out.files["out.js"]);
+    assert_snapshot("representative.consts.d.ts", &out.files["out.d.ts"]);
+}
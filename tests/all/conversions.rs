@@ -0,0 +1,343 @@
+use cli::Bindgen;
+use shared::{Export, ImportKind, Program};
+
+use super::MemoryOutput;
+use super::fixtures::*;
+use super::fixtures::words::*;
+
+type Functions = Vec<(String, Vec<u32>)>;
+
+/// Each way a value can be passed: as an argument or return value of an
+/// export, or as an argument or return value of an import.
+const ALL: &[&str] = &["takes", "returns", "recv", "give"];
+
+/// The exports and imports passing each of `values` in the ways it's paired
+/// with, named after the way and the value, like `takes_string`.
+fn functions<S: AsRef<str>>(values: &[(S, Vec<u32>, &[&str])]) -> (Functions, Functions) {
+    let mut exports = Vec::new();
+    let mut imports = Vec::new();
+    for &(ref name, ref words, ways) in values {
+        for &way in ways {
+            let function = match way {
+                "takes" | "recv" => function(&[words], None),
+                _ => function(&[], Some(words)),
+            };
+            let name = format!("{}_{}", way, name.as_ref());
+            match way {
+                "takes" | "returns" => exports.push((name, function)),
+                _ => imports.push((name, function)),
+            }
+        }
+    }
+    (exports, imports)
+}
+
+/// The crate `kind` exporting `exports` as free functions and importing
+/// `imports` from the global `host` object.
+fn conversions_program(kind: &str, exports: &Functions, imports: &Functions) -> Program {
+    let mut program = program(kind);
+    for (name, _) in exports {
+        program.exports.push(export(name));
+    }
+    for (name, _) in imports {
+        program.imports.push(host_import(name));
+    }
+    program
+}
+
+/// Snapshots the debug mode glue and TypeScript of one kind of conversions.
+fn assert_conversions(kind: &str, wasm: Vec<u8>) {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .debug(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert_snapshot(&format!("conversions.{}.js", kind), &out.files["out.js"]);
+    assert_snapshot(&format!("conversions.{}.d.ts", kind), &out.files["out.d.ts"]);
+}
+
+fn numbers_wasm() -> Vec<u8> {
+    let numbers = [
+        ("i8", I8), ("u8", U8), ("i16", I16), ("u16", U16), ("i32", I32), ("u32", U32),
+        ("i64", I64), ("u64", U64), ("f32", F32), ("f64", F64), ("bool", BOOLEAN),
+        ("enum", ENUM),
+    ];
+    let values = numbers.iter()
+        .map(|&(name, word)| (name, vec![word], ALL))
+        .collect::<Vec<_>>();
+    let (exports, imports) = functions(&values);
+    let program = conversions_program("numbers", &exports, &imports);
+    with_program(&described_wasm(&exports, &imports), &program)
+}
+
+#[test]
+fn numbers() {
+    assert_conversions("numbers", numbers_wasm());
+
+    // Release mode doesn't check what it's given.
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(numbers_wasm(), "out");
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert_snapshot("conversions.numbers.release.js", &out.files["out.js"]);
+}
+
+fn strings_wasm() -> Vec<u8> {
+    let (mut exports, imports) = functions(&[
+        ("string", vec![STRING], ALL),
+        ("str", vec![REF, STRING], &["takes", "recv"]),
+        ("option_string", vec![OPTIONAL, STRING], ALL),
+        ("option_str", vec![OPTIONAL, REF, STRING], &["takes", "recv"]),
+    ]);
+    let mut program = conversions_program("strings", &exports, &imports);
+    exports.push(("measure".to_string(), function(&[&[REF, STRING]], Some(&[U32]))));
+    program.exports.push(Export { is_async: true, ..export("measure") });
+    with_program(&described_wasm(&exports, &imports), &program)
+}
+
+#[test]
+fn strings() {
+    assert_conversions("strings", strings_wasm());
+}
+
+fn slices_wasm() -> Vec<u8> {
+    // One of each view of wasm's memory they're read through.
+    let elements = [("u8", U8), ("i32", I32), ("f64", F64), ("anyref", ANYREF)];
+    let mut values = Vec::new();
+    for &(name, word) in elements.iter() {
+        let slice: &[&str] = if name == "anyref" {
+            &["takes", "recv"]
+        } else {
+            &["takes", "returns", "recv"]
+        };
+        values.push((format!("vec_{}", name), vec![VECTOR, word], ALL));
+        values.push((format!("slice_{}", name), vec![REF, SLICE, word], slice));
+    }
+    values.push(("option_vec_u8".to_string(), vec![OPTIONAL, VECTOR, U8], ALL));
+    values.push(("option_slice_u8".to_string(), vec![OPTIONAL, REF, SLICE, U8],
+                  &["takes", "recv"]));
+    let (exports, imports) = functions(&values);
+    let program = conversions_program("slices", &exports, &imports);
+    with_program(&described_wasm(&exports, &imports), &program)
+}
+
+#[test]
+fn slices() {
+    assert_conversions("slices", slices_wasm());
+}
+
+/// JS values lent or given to Rust, and taken back.
+pub fn anyref_wasm() -> Vec<u8> {
+    let (mut exports, imports) = functions(&[
+        ("anyref", vec![ANYREF], ALL),
+        ("ref_anyref", vec![REF, ANYREF], &["takes", "returns", "recv"]),
+    ]);
+    exports.push(("takes_ref_anyref_and_str".to_string(),
+                  function(&[&[REF, ANYREF], &[REF, STRING]], None)));
+    let program = conversions_program("anyref", &exports, &imports);
+    with_program(&described_wasm(&exports, &imports), &program)
+}
+
+#[test]
+fn anyref() {
+    assert_conversions("anyref", anyref_wasm());
+}
+
+/// The class `Frame`, passed every way it can be, and some methods of it.
+pub fn classes_wasm() -> Vec<u8> {
+    let (mut exports, imports) = functions(&[
+        ("frame", frame(), &["takes", "returns", "recv"]),
+        ("ref_frame", [&[REF][..], &frame()].concat(), &["takes"]),
+        ("mut_frame", [&[REFMUT][..], &frame()].concat(), &["takes"]),
+        ("option_frame", [&[OPTIONAL][..], &frame()].concat(), &["takes", "returns", "recv"]),
+        ("option_ref_frame", [&[OPTIONAL, REF][..], &frame()].concat(), &["takes"]),
+    ]);
+    exports.push(("takes_frame_and_str".to_string(),
+                  function(&[&frame(), &[REF, STRING]], Some(&[U32]))));
+    let mut program = conversions_program("classes", &exports, &imports);
+    exports.push(("frame_into_len".to_string(), function(&[&[U32]], Some(&[U32]))));
+    exports.push(("frame_pixels".to_string(), function(&[], Some(&[REF, SLICE, U8]))));
+    exports.push(("frame_resize".to_string(), function(&[&[U32]], None)));
+    program.exports.push(Export { consumed: true, ..method("Frame", "into_len") });
+    program.exports.push(method("Frame", "pixels"));
+    program.exports.push(Export { returns_self: true, ..method("Frame", "resize") });
+    program.structs.push(class("Frame"));
+    with_program(&described_wasm(&exports, &imports), &program)
+}
+
+#[test]
+fn classes() {
+    assert_conversions("classes", classes_wasm());
+}
+
+#[test]
+fn string_enums() {
+    let (exports, imports) = functions(&[("mode", mode(), ALL)]);
+    let program = conversions_program("string_enums", &exports, &imports);
+    assert_conversions("string_enums",
+                       with_program(&described_wasm(&exports, &imports), &program));
+}
+
+#[test]
+fn dictionaries() {
+    let (exports, imports) = functions(&[("point", point(), &["returns", "recv"])]);
+    let program = conversions_program("dictionaries", &exports, &imports);
+    assert_conversions("dictionaries",
+                       with_program(&described_wasm(&exports, &imports), &program));
+}
+
+#[test]
+fn closures() {
+    let (exports, mut imports) = functions(&[
+        ("fn_pointer", [&[FN_POINTER][..], &function(&[&[U32]], Some(&[U32]))].concat(),
+         &["returns", "recv"]),
+        ("closure", [&[REF][..], &function(&[&[U32]], None)].concat(), &["recv"]),
+        ("closure_mut", [&[REFMUT][..], &function(&[&[REF, STRING]], None)].concat(),
+         &["recv"]),
+        ("ref_closure", [&[REF, CLOSURE, REF][..], &function(&[&[U32]], None)].concat(),
+         &["recv"]),
+        ("ref_closure_mut",
+         [&[REF, CLOSURE, REFMUT][..], &function(&[&[REF, STRING]], Some(&[BOOLEAN]))].concat(),
+         &["recv"]),
+    ]);
+    imports.push(("recv_closure_and_str".to_string(), function(&[
+        &[&[REFMUT][..], &function(&[&[U32]], None)].concat(),
+        &[REF, STRING],
+    ], None)));
+    let mut program = conversions_program("closures", &exports, &imports);
+    imports.push(("try_recv_closure".to_string(),
+                  function(&[&[&[REF][..], &function(&[&[U32]], None)].concat()], None)));
+    let mut import = host_import("try_recv_closure");
+    if let ImportKind::Function(ref mut f) = import.kind {
+        f.catch = true;
+    }
+    program.imports.push(import);
+    assert_conversions("closures", with_program(&described_wasm(&exports, &imports), &program));
+}
+
+#[test]
+fn trailing_optional_arguments() {
+    // Only optional arguments no required one follows can be left out.
+    let exports = vec![
+        ("optional_last".to_string(),
+         function(&[&[U32], &[OPTIONAL, STRING], &[OPTIONAL, REF, STRING]], None)),
+        ("optional_first".to_string(), function(&[&[OPTIONAL, STRING], &[U32]], None)),
+        ("optional_between".to_string(),
+         function(&[&[OPTIONAL, STRING], &[U32], &[OPTIONAL, STRING]], None)),
+    ];
+    let program = conversions_program("trailing_optional", &exports, &Vec::new());
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&described_wasm(&exports, &[]), &program), "out")
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let ts = String::from_utf8(out.files["out.d.ts"].clone()).unwrap();
+    assert!(ts.contains("export function optional_last(arg0: number, arg1?: string, \
+                         arg2?: string): void;"), "{}", ts);
+    assert!(ts.contains("export function optional_first(arg0: string | undefined, \
+                         arg1: number): void;"), "{}", ts);
+    assert!(ts.contains("export function optional_between(arg0: string | undefined, \
+                         arg1: number, arg2?: string): void;"), "{}", ts);
+}
+
+#[test]
+fn conversions_undone_when_later_ones_throw() {
+    let exports = [
+        ("takes_two_strs".to_string(), function(&[&[REF, STRING], &[REF, STRING]], None)),
+        ("takes_string_and_u32".to_string(), function(&[&[STRING], &[U32]], None)),
+        ("frame_into_len".to_string(), function(&[&[U32]], Some(&[U32]))),
+    ];
+    let mut program = program("undone");
+    program.exports.push(export("takes_two_strs"));
+    program.exports.push(export("takes_string_and_u32"));
+    program.exports.push(Export { consumed: true, ..method("Frame", "into_len") });
+    program.structs.push(class("Frame"));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&described_wasm(&exports, &[]), &program), "out")
+        .debug(true)
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // A string lent to Rust is freed, and a string or class moved into it
+    // is freed or handed back, if a later argument can't be converted.
+    run_node("conversions", &out.files, &[], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'out.js'));
+            const memory = require(path.join(process.argv[1], 'out_bg')).memory;
+            const frees = () => new Uint32Array(memory.buffer)[0];
+            assert.throws(() => out.takes_two_strs('a', 1), /argument 1/);
+            assert.strictEqual(frees(), 1);
+            assert.throws(() => out.takes_string_and_u32('a', 'b'), /argument 1/);
+            assert.strictEqual(frees(), 2);
+            out.takes_string_and_u32('a', 1);
+            assert.strictEqual(frees(), 2);
+            const frame = out.Frame.__construct(24);
+            assert.throws(() => frame.into_len('b'), /argument 0/);
+            assert.strictEqual(frame.ptr, 24);
+            frame.into_len(1);
+            assert.strictEqual(frame.ptr, 0);
+        "#);
+}
+
+#[test]
+fn owned_returns_are_copied_and_freed() {
+    use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+
+    // Both leave their length in the global argument, and `__wbindgen_free`
+    // counts its calls at address 0.
+    let exports = [
+        ("returns_string".to_string(), function(&[], Some(&[STRING]))),
+        ("returns_bytes".to_string(), function(&[], Some(&[VECTOR, U8]))),
+    ];
+    let store = |ptr: i32, bytes: &[u8]| {
+        let mut code = vec![
+            Opcode::I32Const(8), Opcode::I32Const(bytes.len() as i32), Opcode::I32Store(2, 0),
+        ];
+        for (i, &b) in bytes.iter().enumerate() {
+            code.push(Opcode::I32Const(ptr + i as i32));
+            code.push(Opcode::I32Const(b as i32));
+            code.push(Opcode::I32Store8(0, 0));
+        }
+        code.push(Opcode::I32Const(ptr));
+        code.push(Opcode::End);
+        code
+    };
+    let ty = FunctionType::new(Vec::new(), Some(ValueType::I32));
+    let wasm = described_wasm(&exports, &[]);
+    let wasm = with_body(&wasm, "returns_string", ty.clone(), store(16, b"hi"));
+    let wasm = with_body(&wasm, "returns_bytes", ty, store(32, &[1, 2, 255]));
+    let mut program = program("owned_returns");
+    program.exports.push(export("returns_string"));
+    program.exports.push(export("returns_bytes"));
+    let wasm = with_program(&wasm, &program);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .nodejs(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let ts = String::from_utf8(out.files["out.d.ts"].clone()).unwrap();
+    assert!(ts.contains("export function returns_string(): string;"), "{}", ts);
+    assert!(ts.contains("export function returns_bytes(): Uint8Array;"), "{}", ts);
+
+    run_node("owned-returns", &out.files, &[], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'out.js'));
+            const memory = require(path.join(process.argv[1], 'out_bg')).memory;
+            assert.strictEqual(out.returns_string(), 'hi');
+            const bytes = out.returns_bytes();
+            assert.ok(bytes instanceof Uint8Array);
+            assert.deepStrictEqual(Array.from(bytes), [1, 2, 255]);
+
+            // A copy, which outlives wasm's memory changing.
+            new Uint8Array(memory.buffer)[32] = 7;
+            assert.strictEqual(bytes[0], 1);
+            assert.strictEqual(new Uint32Array(memory.buffer)[0], 2);
+        "#);
+}
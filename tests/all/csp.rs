@@ -0,0 +1,131 @@
+use std::io::Cursor;
+
+use cli::{Bindgen, EsVersion, WasmImportMode};
+
+use super::MemoryOutput;
+use super::fixtures::*;
+
+#[test]
+fn csp_safe_modes() {
+    let modes: &[Mode] = &[
+        ("bundler", |_| {}),
+        ("browser", |b| { b.browser(true); }),
+        ("node", |b| { b.nodejs(true); }),
+        ("node async", |b| { b.nodejs(true).nodejs_async(true); }),
+        ("no-modules", |b| { b.no_modules(true); }),
+        ("no-modules es5", |b| { b.no_modules(true).es_target(EsVersion::Es5); }),
+        ("fetch-url", |b| { b.wasm_import_mode(WasmImportMode::FetchUrl); }),
+        ("fetch-url es5", |b| {
+            b.wasm_import_mode(WasmImportMode::FetchUrl).es_target(EsVersion::Es5);
+        }),
+        ("deferred", |b| { b.wasm_import_mode(WasmImportMode::Deferred); }),
+        ("split", |b| { b.split_output(true); }),
+        ("no-modules minified", |b| { b.no_modules(true).minify_js(true); }),
+        ("no-modules debug", |b| { b.no_modules(true).debug(true); }),
+    ];
+    for &(name, mode) in modes.iter() {
+        let wasm = representative_wasm(if name.starts_with("no-modules") {
+            None
+        } else {
+            Some("./logger")
+        });
+        let mut bindgen = Bindgen::new();
+        bindgen.input_reader(Cursor::new(wasm), "out")
+            .csp_safe(true);
+        mode(&mut bindgen);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+        for (file, contents) in out.files.iter().filter(|f| f.0.ends_with(".js")) {
+            let js = String::from_utf8(contents.clone()).unwrap();
+            for forbidden in ["eval(", "Function(", "document.write"].iter() {
+                assert!(!js.contains(forbidden), "{} output `{}` uses `{}`", name, file, forbidden);
+            }
+        }
+        if name == "no-modules" {
+            assert_snapshot("representative.csp.no-modules.js", &out.files["out.js"]);
+        } else if name == "fetch-url" {
+            assert_snapshot("representative.csp.fetch-url.js", &out.files["out_bg.js"]);
+        }
+    }
+}
+
+#[test]
+fn csp_safe_rejects_eval() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .js_prelude("const polyfill = new Function('return 1');")
+        .csp_safe(true);
+    let mut out = MemoryOutput::default();
+    let err = bindgen.generate_to(&mut out).unwrap_err().to_string();
+    assert_eq!(err, "`out.js` uses `new Function`, which isn't allowed with `csp_safe`");
+    assert!(out.files.is_empty());
+
+    // Only calls count, the names themselves may well appear.
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .js_prelude("const evaluate = x => x.eval; myFunction(); // retrieval (soon)")
+        .csp_safe(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+}
+
+#[test]
+fn csp_blocked_instantiation() {
+    let wasm = representative_wasm(None);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .no_modules(true)
+        .csp_safe(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // Streams the wasm when it's given a URL, and when a (simulated) policy
+    // blocks compiling it the error says so.
+    run_node("csp", &out.files, &[], r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const vm = require('vm');
+            const dir = process.argv[1];
+            const bytes = fs.readFileSync(path.join(dir, 'out_bg.wasm'));
+            const load = webAssembly => {
+                const ctx = vm.createContext({
+                    WebAssembly: webAssembly,
+                    ArrayBuffer,
+                    log: () => {},
+                    fetch: () => Promise.resolve(new Response(bytes, {
+                        headers: { 'content-type': 'application/wasm' },
+                    })),
+                });
+                vm.runInContext('self = this', ctx);
+                vm.runInContext(fs.readFileSync(path.join(dir, 'out.js'), 'utf8'), ctx);
+                return ctx.self.wasm_bindgen('out_bg.wasm');
+            };
+            let streamed = false;
+            const blocked = new Error("WebAssembly.instantiate(): Refused to compile or " +
+                                      "instantiate WebAssembly module because 'unsafe-eval' " +
+                                      "is not an allowed source of script");
+            load(Object.assign({}, WebAssembly, {
+                instantiateStreaming: (response, imports) => {
+                    streamed = true;
+                    return WebAssembly.instantiateStreaming(response, imports);
+                },
+            }))
+                .then(bindings => {
+                    assert.ok(streamed);
+                    assert.strictEqual(bindings.halve(3), 1.5);
+                    return load(Object.assign({}, WebAssembly, {
+                        instantiateStreaming: () => Promise.reject(blocked),
+                    }));
+                })
+                .then(() => assert.fail('instantiating should have failed'), e => {
+                    assert.strictEqual(e.name, 'WasmCspError');
+                    assert.strictEqual(e.cause, blocked);
+                    assert.ok(/'wasm-unsafe-eval'/.test(e.message));
+                })
+                .catch(e => { console.error(e); process.exit(1); });
+        "#);
+}
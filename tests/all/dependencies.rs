@@ -1,4 +1,11 @@
-use super::project;
+use std::io::Cursor;
+
+use cli::{self, metadata, Bindgen};
+use serde_json;
+use shared::{self, Program};
+
+use super::{project, MemoryOutput};
+use super::fixtures::*;
 
 #[test]
 fn dependencies_work() {
@@ -90,3 +97,56 @@ fn dependencies_work() {
         )
         .test();
 }
+
+/// The metadata of a dependency with nothing to bind, built against wasm-bindgen
+/// `version` with the metadata format `schema_version`.
+fn dependency_program(version: &str, schema_version: &str) -> Program {
+    Program {
+        version: version.to_string(),
+        schema_version: schema_version.to_string(),
+        ..program("dependency")
+    }
+}
+
+#[test]
+fn crate_versions() {
+    let wasm = representative_wasm(Some("./logger"));
+    let wasm = with_program(&wasm, &dependency_program("0.2.0", shared::SCHEMA_VERSION));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .emit_metadata(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    let crates = vec![
+        metadata::Crate { name: "representative".to_string(), version: shared::version() },
+        metadata::Crate { name: "dependency".to_string(), version: "0.2.0".to_string() },
+    ];
+    assert_eq!(bindgen.stats().unwrap().crates, crates);
+    let json = String::from_utf8(out.files["out.bindings.json"].clone()).unwrap();
+    let bindings: metadata::Bindings = serde_json::from_str(&json).unwrap();
+    assert_eq!(bindings.crates, crates);
+}
+
+#[test]
+fn incompatible_crate_versions() {
+    let wasm = representative_wasm(Some("./logger"));
+    let wasm = with_program(&wasm, &dependency_program("0.1.0", "0"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out");
+    let err = bindgen.validate().unwrap_err().to_string();
+    assert!(err.contains(&format!("`representative`  {}\n", shared::version())),
+            "unexpected error: {}", err);
+    assert!(err.contains("`dependency`      0.1.0 (incompatible)\n"),
+            "unexpected error: {}", err);
+    assert!(err.contains("the wasm-bindgen dependency of `dependency` or this binary"),
+            "unexpected error: {}", err);
+
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::SchemaMismatch { ref found, ref expected, .. } => {
+            assert_eq!(found, "0");
+            assert_eq!(expected, shared::SCHEMA_VERSION);
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::Cursor;
+
+use cli::{self, Bindgen};
+
+use super::MemoryOutput;
+use super::fixtures::*;
+
+// (module
+//   (import "__wbindgen_placeholder__" "__wbindgen_describe" (func $describe (param i32)))
+//   (memory (export "memory") 1)
+//   (func (export "frame_encode") (param i32 i32) (result i32)
+//     local.get 0)
+//   ;; passes FUNCTION, 2 (arguments) and U32 to `$describe`, then traps
+//   ;; while describing the second argument
+//   (func (export "__wbindgen_describe_frame_encode") ...
+//     unreachable))
+const TRAPPING_DESCRIPTOR_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0e, 0x03, 0x60,
+    0x01, 0x7f, 0x00, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x00, 0x00,
+    0x02, 0x30, 0x01, 0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64,
+    0x65, 0x72, 0x5f, 0x5f, 0x13, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64,
+    0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65,
+    0x00, 0x00, 0x03, 0x03, 0x02, 0x01, 0x02, 0x05, 0x03, 0x01, 0x00, 0x01,
+    0x07, 0x3c, 0x03, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+    0x0c, 0x66, 0x72, 0x61, 0x6d, 0x65, 0x5f, 0x65, 0x6e, 0x63, 0x6f, 0x64,
+    0x65, 0x00, 0x01, 0x20, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x5f,
+    0x66, 0x72, 0x61, 0x6d, 0x65, 0x5f, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x65,
+    0x00, 0x02, 0x0a, 0x16, 0x02, 0x04, 0x00, 0x20, 0x00, 0x0b, 0x0f, 0x00,
+    0x41, 0x0b, 0x10, 0x00, 0x41, 0x02, 0x10, 0x00, 0x41, 0x05, 0x10, 0x00,
+    0x00, 0x0b,
+];
+
+// The same as `TRAPPING_DESCRIPTOR_WASM`, except that the descriptor is
+// FUNCTION, 1 (argument), 99 (no such type) and 0 (no return value).
+const UNKNOWN_DESCRIPTOR_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0e, 0x03, 0x60,
+    0x01, 0x7f, 0x00, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x00, 0x00,
+    0x02, 0x30, 0x01, 0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64,
+    0x65, 0x72, 0x5f, 0x5f, 0x13, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64,
+    0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65,
+    0x00, 0x00, 0x03, 0x03, 0x02, 0x01, 0x02, 0x05, 0x03, 0x01, 0x00, 0x01,
+    0x07, 0x3c, 0x03, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+    0x0c, 0x66, 0x72, 0x61, 0x6d, 0x65, 0x5f, 0x65, 0x6e, 0x63, 0x6f, 0x64,
+    0x65, 0x00, 0x01, 0x20, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x5f,
+    0x66, 0x72, 0x61, 0x6d, 0x65, 0x5f, 0x65, 0x6e, 0x63, 0x6f, 0x64, 0x65,
+    0x00, 0x02, 0x0a, 0x1a, 0x02, 0x04, 0x00, 0x20, 0x00, 0x0b, 0x13, 0x00,
+    0x41, 0x0b, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0xe3, 0x00, 0x10,
+    0x00, 0x41, 0x00, 0x10, 0x00, 0x0b,
+];
+
+#[test]
+fn failing_descriptors() {
+    let mut program = program("frames");
+    program.exports.push(method("Frame", "encode"));
+    program.structs.push(class("Frame"));
+
+    let wasm = with_program(TRAPPING_DESCRIPTOR_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("failed to describe export `frame_encode` (`Frame::encode` in Rust): \
+                          argument 1 failed"), "unexpected error: {}", err);
+    assert!(err.contains("(descriptor so far: [11, 2, 5])"), "unexpected error: {}", err);
+    let err = bindgen.validate().unwrap_err().to_string();
+    assert!(err.contains("failed to describe export `frame_encode`"), "unexpected error: {}", err);
+
+    let wasm = with_program(UNKNOWN_DESCRIPTOR_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out");
+    let err = bindgen.validate().unwrap_err().to_string();
+    assert!(err.contains("failed to decode the descriptor of `frame_encode`: unknown \
+                          descriptor discriminant 99 at word 3 (descriptor: [11, 1, 99, 0])"),
+            "unexpected error: {}", err);
+}
+
+#[test]
+fn interpreter_fallbacks() {
+    use cli::parity_wasm::elements::{ImportCountType, Internal, Module, Opcode, Section};
+
+    // Prefixes the descriptor of `halve` with an opcode the interpreter
+    // doesn't support, so that it's run with wasmi instead.
+    let wasm = representative_wasm(Some("./logger"));
+    let mut module: Module = cli::parity_wasm::deserialize_buffer(&wasm).unwrap();
+    let idx = module.export_section().unwrap().entries().iter()
+        .find(|e| e.field() == "__wbindgen_describe_halve")
+        .map(|e| match *e.internal() {
+            Internal::Function(i) => i as usize,
+            _ => panic!("not a function"),
+        })
+        .unwrap();
+    let idx = idx - module.import_count(ImportCountType::Function);
+    for section in module.sections_mut() {
+        if let Section::Code(ref mut code) = *section {
+            let ops = code.bodies_mut()[idx].code_mut().elements_mut();
+            ops.splice(0..0, vec![Opcode::I32Const(1), Opcode::I32Const(2),
+                                  Opcode::I32Xor, Opcode::Drop]);
+        }
+    }
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_module(module, "out");
+    bindgen.generate_to(&mut MemoryOutput::default()).expect("failed to run bindgen");
+    let stats = bindgen.stats().unwrap();
+    assert_eq!(stats.interpreter_fallbacks,
+               ["__wbindgen_describe_halve (unsupported opcode I32Xor)"]);
+    assert!(stats.to_string().contains("\ninterpreter: 1 descriptors run with wasmi instead\n"),
+            "unexpected stats: {}", stats);
+}
+
+#[test]
+fn interpreter_matches_wasmi() {
+    let mut outputs = Vec::new();
+    for &interpreter in [true, false].iter() {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes(representative_wasm(Some("./logger")), "out")
+            .interpreter(interpreter)
+            .typescript(true);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+        let stats = bindgen.stats().unwrap().clone();
+        assert!(stats.interpreter_fallbacks.is_empty(), "{:?}", stats.interpreter_fallbacks);
+        outputs.push((stats.descriptors, out.files));
+    }
+    let (ref descriptors, ref files) = outputs[0];
+    assert_eq!(descriptors.len(), 5);
+    assert_eq!(*descriptors, outputs[1].0);
+    assert_eq!(files["out.js"], outputs[1].1["out.js"]);
+    assert_eq!(files["out.d.ts"], outputs[1].1["out.d.ts"]);
+}
+
+#[test]
+fn all_unsupported_items() {
+    // `shrink` has no descriptor at all and the one of `Frame::encode` can't
+    // be decoded, both should be reported without anything being written.
+    let mut program = program("frames");
+    program.exports.push(method("Frame", "encode"));
+    program.exports.push(export("shrink"));
+    program.structs.push(class("Frame"));
+
+    let wasm = with_program(UNKNOWN_DESCRIPTOR_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out");
+    let mut out = MemoryOutput::default();
+    let err = bindgen.generate_to(&mut out).unwrap_err().to_string();
+    assert!(err.contains("export `Frame::encode` in crate `frames`: failed to decode the \
+                          descriptor of `frame_encode`: unknown descriptor discriminant 99"),
+            "unexpected error: {}", err);
+    assert!(err.contains("export `shrink` in crate `frames`: failed to find the descriptor \
+                          export `__wbindgen_describe_shrink`"),
+            "unexpected error: {}", err);
+    assert_eq!(err.lines().count(), 2, "unexpected error: {}", err);
+    assert!(out.files.is_empty());
+}
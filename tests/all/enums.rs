@@ -83,3 +83,58 @@ fn c_style_enum_with_custom_values() {
         "#)
         .test();
 }
+
+#[test]
+fn string_enum() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub enum Mode {
+                Cors = "cors",
+                NoCors = "no-cors",
+                SameOrigin = "same-origin",
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn next_mode(mode: Mode) -> Mode;
+            }
+
+            #[wasm_bindgen]
+            pub fn cycle(mode: Mode) -> Mode {
+                match mode {
+                    Mode::Cors => Mode::NoCors,
+                    Mode::NoCors => Mode::SameOrigin,
+                    Mode::SameOrigin => Mode::Cors,
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn cycle_in_js(mode: Mode) -> Mode {
+                next_mode(mode)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function next_mode(mode: "cors" | "no-cors" | "same-origin"): any {
+                return mode === "cors" ? "same-origin" : "typo";
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.cycle("cors"), "no-cors");
+                assert.strictEqual(wasm.cycle("same-origin"), "cors");
+                assert.strictEqual(wasm.cycle_in_js("cors"), "same-origin");
+                assert.throws(() => wasm.cycle("nope" as any), /expected one of "cors", "no-cors", "same-origin", found nope/);
+                assert.throws(() => wasm.cycle_in_js("no-cors"), /found typo \(return value\)/);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,487 @@
+//! What the tests generating bindings from hand-written modules share: the
+//! modules, the metadata of the crates they stand for and ways of checking
+//! the output.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Command;
+
+use cli::Bindgen;
+use cli::parity_wasm::elements::{FunctionType, Internal, Module, Opcode, Opcodes, Section, Type};
+use serde_json;
+use shared::{self, Enum, EnumVariant, Export, Function, Import, ImportFunction, ImportKind};
+use shared::{Program, Struct, StructField};
+
+// (module
+//   (import "__wbindgen_placeholder__" "__wbindgen_describe" (func $describe (param i32)))
+//   (import "__wbindgen_placeholder__" "__wbg_f_log_log_n" (func $log (param i32)))
+//   (memory (export "memory") 1)
+//   (func (export "halve") (param f64) (result f64)
+//     local.get 0
+//     f64.const 0.5
+//     f64.mul)
+//   (func (export "counter_new") (param i32) (result i32)
+//     local.get 0)
+//   (func (export "counter_add") (param i32 i32) (result i32)
+//     local.get 1
+//     call $log
+//     local.get 0
+//     local.get 1
+//     i32.add)
+//   (func (export "__wbg_counter_free") (param i32))
+//   (func (export "__wbg_get_counter_count") (param i32) (result i32)
+//     local.get 0)
+//   ;; each descriptor shim passes its words to `$describe` in turn
+//   (func (export "__wbindgen_describe_halve") ...)       ;; fn(f64) -> f64
+//   (func (export "__wbindgen_describe_counter_new") ...) ;; fn(u32) -> Counter
+//   (func (export "__wbindgen_describe_counter_add") ...) ;; fn(u32) -> u32
+//   (func (export "__wbindgen_describe___wbg_get_counter_count") ...) ;; u32
+//   (func (export "__wbindgen_describe___wbg_f_log_log_n") ...))      ;; fn(u32)
+pub const REPRESENTATIVE_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x18, 0x05, 0x60,
+    0x01, 0x7f, 0x00, 0x60, 0x01, 0x7c, 0x01, 0x7c, 0x60, 0x01, 0x7f, 0x01,
+    0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x00, 0x00, 0x02, 0x5d,
+    0x02, 0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e,
+    0x5f, 0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64, 0x65, 0x72,
+    0x5f, 0x5f, 0x13, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65,
+    0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x00, 0x00,
+    0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e, 0x5f,
+    0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64, 0x65, 0x72, 0x5f,
+    0x5f, 0x11, 0x5f, 0x5f, 0x77, 0x62, 0x67, 0x5f, 0x66, 0x5f, 0x6c, 0x6f,
+    0x67, 0x5f, 0x6c, 0x6f, 0x67, 0x5f, 0x6e, 0x00, 0x00, 0x03, 0x0b, 0x0a,
+    0x01, 0x02, 0x03, 0x00, 0x02, 0x04, 0x04, 0x04, 0x04, 0x04, 0x05, 0x03,
+    0x01, 0x00, 0x01, 0x07, 0x93, 0x02, 0x0b, 0x06, 0x6d, 0x65, 0x6d, 0x6f,
+    0x72, 0x79, 0x02, 0x00, 0x05, 0x68, 0x61, 0x6c, 0x76, 0x65, 0x00, 0x02,
+    0x0b, 0x63, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x6e, 0x65, 0x77,
+    0x00, 0x03, 0x0b, 0x63, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x61,
+    0x64, 0x64, 0x00, 0x04, 0x12, 0x5f, 0x5f, 0x77, 0x62, 0x67, 0x5f, 0x63,
+    0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x66, 0x72, 0x65, 0x65, 0x00,
+    0x05, 0x17, 0x5f, 0x5f, 0x77, 0x62, 0x67, 0x5f, 0x67, 0x65, 0x74, 0x5f,
+    0x63, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x63, 0x6f, 0x75, 0x6e,
+    0x74, 0x00, 0x06, 0x19, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x5f,
+    0x68, 0x61, 0x6c, 0x76, 0x65, 0x00, 0x07, 0x1f, 0x5f, 0x5f, 0x77, 0x62,
+    0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72,
+    0x69, 0x62, 0x65, 0x5f, 0x63, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f,
+    0x6e, 0x65, 0x77, 0x00, 0x08, 0x1f, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e,
+    0x64, 0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62,
+    0x65, 0x5f, 0x63, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x61, 0x64,
+    0x64, 0x00, 0x09, 0x2b, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67,
+    0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x5f,
+    0x5f, 0x5f, 0x77, 0x62, 0x67, 0x5f, 0x67, 0x65, 0x74, 0x5f, 0x63, 0x6f,
+    0x75, 0x6e, 0x74, 0x65, 0x72, 0x5f, 0x63, 0x6f, 0x75, 0x6e, 0x74, 0x00,
+    0x0a, 0x25, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e,
+    0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x5f, 0x5f, 0x5f,
+    0x77, 0x62, 0x67, 0x5f, 0x66, 0x5f, 0x6c, 0x6f, 0x67, 0x5f, 0x6c, 0x6f,
+    0x67, 0x5f, 0x6e, 0x00, 0x0b, 0x0a, 0xaf, 0x01, 0x0a, 0x0e, 0x00, 0x20,
+    0x00, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe0, 0x3f, 0xa2, 0x0b,
+    0x04, 0x00, 0x20, 0x00, 0x0b, 0x0b, 0x00, 0x20, 0x01, 0x10, 0x01, 0x20,
+    0x00, 0x20, 0x01, 0x6a, 0x0b, 0x02, 0x00, 0x0b, 0x04, 0x00, 0x20, 0x00,
+    0x0b, 0x16, 0x00, 0x41, 0x0b, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41,
+    0x09, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x09, 0x10, 0x00, 0x0b,
+    0x3d, 0x00, 0x41, 0x0b, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x05,
+    0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x14, 0x10, 0x00, 0x41, 0x07,
+    0x10, 0x00, 0x41, 0xc3, 0x00, 0x10, 0x00, 0x41, 0xef, 0x00, 0x10, 0x00,
+    0x41, 0xf5, 0x00, 0x10, 0x00, 0x41, 0xee, 0x00, 0x10, 0x00, 0x41, 0xf4,
+    0x00, 0x10, 0x00, 0x41, 0xe5, 0x00, 0x10, 0x00, 0x41, 0xf2, 0x00, 0x10,
+    0x00, 0x0b, 0x16, 0x00, 0x41, 0x0b, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00,
+    0x41, 0x05, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x05, 0x10, 0x00,
+    0x0b, 0x06, 0x00, 0x41, 0x05, 0x10, 0x00, 0x0b, 0x12, 0x00, 0x41, 0x0b,
+    0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x05, 0x10, 0x00, 0x41, 0x00,
+    0x10, 0x00, 0x0b,
+];
+
+/// The `#[wasm_bindgen]` metadata of `REPRESENTATIVE_WASM`, as the macro
+/// would have emitted it for the following, with `log` imported from `module`
+/// (or a global when that's `None`):
+///
+/// ```ignore
+/// #[wasm_bindgen(module = "./logger")]
+/// extern { fn log(n: u32); }
+///
+/// #[wasm_bindgen]
+/// pub fn halve(x: f64) -> f64 { ... }
+///
+/// #[wasm_bindgen]
+/// pub enum Color { Red, Green }
+///
+/// #[wasm_bindgen]
+/// pub struct Counter { #[wasm_bindgen(readonly)] pub count: u32 }
+///
+/// #[wasm_bindgen]
+/// impl Counter {
+///     pub fn new(count: u32) -> Counter { ... }
+///     pub fn add(&mut self, n: u32) -> u32 { ... }
+/// }
+/// ```
+pub fn representative_program(module: Option<&str>) -> Program {
+    let mut program = program("representative");
+    program.exports.push(export("halve"));
+    program.exports.push(static_method("Counter", "new"));
+    program.exports.push(method("Counter", "add"));
+    program.enums.push(Enum {
+        name: "Color".to_string(),
+        variants: vec![
+            EnumVariant { name: "Red".to_string(), value: 0 },
+            EnumVariant { name: "Green".to_string(), value: 1 },
+        ],
+    });
+    program.imports.push(import(module, "__wbg_f_log_log_n", "log"));
+    let mut counter = class("Counter");
+    counter.fields.push(StructField { name: "count".to_string(), readonly: true });
+    program.structs.push(counter);
+    program
+}
+
+/// `REPRESENTATIVE_WASM` along with its metadata, importing `log` from
+/// `module`.
+pub fn representative_wasm(module: Option<&str>) -> Vec<u8> {
+    with_program(REPRESENTATIVE_WASM, &representative_program(module))
+}
+
+/// The metadata of a crate with nothing to bind yet, as this version of
+/// wasm-bindgen would emit it.
+pub fn program(crate_name: &str) -> Program {
+    Program {
+        exports: Vec::new(),
+        enums: Vec::new(),
+        imports: Vec::new(),
+        structs: Vec::new(),
+        consts: Vec::new(),
+        version: shared::version(),
+        schema_version: shared::SCHEMA_VERSION.to_string(),
+        crate_name: crate_name.to_string(),
+    }
+}
+
+/// An exported free function.
+pub fn export(name: &str) -> Export {
+    Export {
+        class: None,
+        method: false,
+        consumed: false,
+        returns_self: false,
+        constructor: None,
+        iterator: None,
+        is_async: false,
+        deprecated: None,
+        function: Function { name: name.to_string(), location: None },
+    }
+}
+
+/// An exported function of `class` without a receiver, like `Counter::new`.
+pub fn static_method(class: &str, name: &str) -> Export {
+    Export { class: Some(class.to_string()), ..export(name) }
+}
+
+/// An exported method of `class` taking `&self` or `&mut self`.
+pub fn method(class: &str, name: &str) -> Export {
+    Export { method: true, ..static_method(class, name) }
+}
+
+/// An exported struct without any fields or a superclass.
+pub fn class(name: &str) -> Struct {
+    Struct { name: name.to_string(), fields: Vec::new(), superclass: None, deprecated: None }
+}
+
+/// The function `name` imported from `module` (or the global scope) through
+/// `shim`.
+pub fn import(module: Option<&str>, shim: &str, name: &str) -> Import {
+    Import {
+        module: module.map(|s| s.to_string()),
+        js_namespace: None,
+        kind: ImportKind::Function(ImportFunction {
+            shim: shim.to_string(),
+            catch: false,
+            method: false,
+            js_new: false,
+            structural: false,
+            variadic: false,
+            lazy: false,
+            getter: None,
+            setter: None,
+            class: None,
+            function: Function { name: name.to_string(), location: None },
+        }),
+    }
+}
+
+/// The function `name` of the global `host` object, which is also its shim.
+pub fn host_import(name: &str) -> Import {
+    Import { js_namespace: Some("host".to_string()), ..import(None, name, name) }
+}
+
+/// Appends the `__wasm_bindgen_unstable` custom section holding `program`.
+pub fn with_program(wasm: &[u8], program: &Program) -> Vec<u8> {
+    let program = serde_json::to_string(program).unwrap();
+    let len = program.len() as u32;
+    let mut payload = vec![len as u8, (len >> 8) as u8, (len >> 16) as u8, (len >> 24) as u8];
+    payload.extend_from_slice(program.as_bytes());
+    with_custom_section(wasm, "__wasm_bindgen_unstable", &payload)
+}
+
+/// Appends a custom section `name` holding `contents`.
+pub fn with_custom_section(wasm: &[u8], name: &str, contents: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    leb(&mut payload, name.len());
+    payload.extend_from_slice(name.as_bytes());
+    payload.extend_from_slice(contents);
+
+    let mut ret = wasm.to_vec();
+    ret.push(0);
+    leb(&mut ret, payload.len());
+    ret.extend(payload);
+    ret
+}
+
+fn leb(dst: &mut Vec<u8>, mut n: usize) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            dst.push(byte);
+            return
+        }
+        dst.push(byte | 0x80);
+    }
+}
+
+/// A label and a way of configuring `Bindgen`, for the tests going through
+/// each flavor of glue.
+pub type Mode = (&'static str, fn(&mut Bindgen));
+
+/// Compares `contents` with the checked-in `tests/all/snapshots/{name}`, or
+/// overwrites it when `BLESS_SNAPSHOTS` is set.
+pub fn assert_snapshot(name: &str, contents: &[u8]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/all/snapshots")
+        .join(name);
+    if env::var_os("BLESS_SNAPSHOTS").is_some() {
+        File::create(&path).unwrap().write_all(contents).unwrap();
+        return
+    }
+    let mut expected = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut expected).unwrap();
+    if expected != contents {
+        panic!("`{}` doesn't match the generated output, which was:\n\n{}\n\n\
+                rerun with `BLESS_SNAPSHOTS=1` if this change is intended",
+               path.display(), String::from_utf8_lossy(contents));
+    }
+}
+
+/// Writes `files` to the directory `name` of this test and runs `script` in
+/// node with `flags`, passing it the directory as `process.argv[1]`.
+pub fn run_node(name: &str, files: &BTreeMap<String, Vec<u8>>, flags: &[&str], script: &str) {
+    let root = super::root().join(name);
+    for (name, contents) in files.iter() {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(contents).unwrap();
+    }
+    let mut cmd = Command::new("node");
+    cmd.args(flags)
+        .arg("-e")
+        .arg(script)
+        .arg(&root);
+    super::run(&mut cmd, "node");
+}
+
+/// The words of descriptors, as in `crates/cli-support/src/descriptor.rs`.
+pub mod words {
+    pub const I8: u32 = 0;
+    pub const U8: u32 = 1;
+    pub const I16: u32 = 2;
+    pub const U16: u32 = 3;
+    pub const I32: u32 = 4;
+    pub const U32: u32 = 5;
+    pub const I64: u32 = 6;
+    pub const U64: u32 = 7;
+    pub const F32: u32 = 8;
+    pub const F64: u32 = 9;
+    pub const BOOLEAN: u32 = 10;
+    pub const FUNCTION: u32 = 11;
+    pub const CLOSURE: u32 = 12;
+    pub const STRING: u32 = 13;
+    pub const REF: u32 = 14;
+    pub const REFMUT: u32 = 15;
+    pub const SLICE: u32 = 16;
+    pub const VECTOR: u32 = 17;
+    pub const ANYREF: u32 = 18;
+    pub const ENUM: u32 = 19;
+    pub const RUST_STRUCT: u32 = 20;
+    pub const STRING_ENUM: u32 = 21;
+    pub const DICTIONARY: u32 = 22;
+    pub const FN_POINTER: u32 = 23;
+    pub const OPTIONAL: u32 = 24;
+
+    /// A string within a descriptor, as its length and characters.
+    pub fn string(s: &str) -> Vec<u32> {
+        let mut ret = vec![s.chars().count() as u32];
+        ret.extend(s.chars().map(|c| c as u32));
+        ret
+    }
+
+    pub fn function(arguments: &[&[u32]], ret: Option<&[u32]>) -> Vec<u32> {
+        let mut words = vec![FUNCTION, arguments.len() as u32];
+        for argument in arguments {
+            words.extend_from_slice(argument);
+        }
+        match ret {
+            Some(ret) => {
+                words.push(1);
+                words.extend_from_slice(ret);
+            }
+            None => words.push(0),
+        }
+        words
+    }
+
+    pub fn frame() -> Vec<u32> {
+        let mut words = vec![RUST_STRUCT];
+        words.extend(string("Frame"));
+        words
+    }
+
+    /// `enum Mode { Fast = "fast", Slow = "slow" }`
+    pub fn mode() -> Vec<u32> {
+        let mut words = vec![STRING_ENUM];
+        words.extend(string("Mode"));
+        words.push(2);
+        words.extend(string("fast"));
+        words.extend(string("slow"));
+        words
+    }
+
+    /// `struct Point { x: f64, label: Option<String> }`, with `label` left
+    /// out when it's `None`.
+    pub fn point() -> Vec<u32> {
+        let mut words = vec![DICTIONARY];
+        words.extend(string("Point"));
+        words.push(2);
+        words.extend(string("x"));
+        words.extend(&[0, F64]);
+        words.extend(string("label"));
+        words.extend(&[1, STRING]);
+        words
+    }
+}
+
+/// A module exporting a function for each of `exports` and importing one for
+/// each of `imports`, which pair the names of functions (or shims of imports)
+/// with the words their descriptor shims pass to `__wbindgen_describe`. Only
+/// the internal exports do anything: `__wbindgen_free` counts the calls to
+/// it in the word at address 0, the global argument is at address 8 and
+/// `__wbindgen_malloc` always returns address 16.
+pub fn described_wasm(exports: &[(String, Vec<u32>)], imports: &[(String, Vec<u32>)]) -> Vec<u8> {
+    use cli::parity_wasm::elements::*;
+
+    let placeholder = "__wbindgen_placeholder__".to_string();
+    let mut entries = vec![
+        ImportEntry::new(placeholder.clone(), "__wbindgen_describe".to_string(),
+                         External::Function(0)),
+    ];
+    for (shim, _) in imports {
+        entries.push(ImportEntry::new(placeholder.clone(), shim.clone(), External::Function(1)));
+    }
+    let mut funcs = Vec::new();
+    let mut bodies = Vec::new();
+    let mut exported = vec![ExportEntry::new("memory".to_string(), Internal::Memory(0))];
+    let mut define = |name: String, ty: u32, code: Vec<Opcode>| {
+        let idx = entries.len() + funcs.len();
+        funcs.push(Func::new(ty));
+        bodies.push(FuncBody::new(Vec::new(), Opcodes::new(code)));
+        exported.push(ExportEntry::new(name, Internal::Function(idx as u32)));
+    };
+    define("__wbindgen_malloc".to_string(), 2, vec![Opcode::I32Const(16), Opcode::End]);
+    define("__wbindgen_free".to_string(), 3, vec![
+        Opcode::I32Const(0),
+        Opcode::I32Const(0),
+        Opcode::I32Load(2, 0),
+        Opcode::I32Const(1),
+        Opcode::I32Add,
+        Opcode::I32Store(2, 0),
+        Opcode::End,
+    ]);
+    define("__wbindgen_global_argument_ptr".to_string(), 4,
+           vec![Opcode::I32Const(8), Opcode::End]);
+    define("__wbg_frame_free".to_string(), 0, vec![Opcode::End]);
+    for (name, _) in exports {
+        define(name.clone(), 1, vec![Opcode::End]);
+    }
+    for (name, words) in exports.iter().chain(imports) {
+        let mut code = Vec::new();
+        for &word in words {
+            code.push(Opcode::I32Const(word as i32));
+            code.push(Opcode::Call(0));
+        }
+        code.push(Opcode::End);
+        define(format!("__wbindgen_describe_{}", name), 1, code);
+    }
+
+    let module = Module::new(vec![
+        Section::Type(TypeSection::with_types(vec![
+            Type::Function(FunctionType::new(vec![ValueType::I32], None)),
+            Type::Function(FunctionType::new(Vec::new(), None)),
+            Type::Function(FunctionType::new(vec![ValueType::I32], Some(ValueType::I32))),
+            Type::Function(FunctionType::new(vec![ValueType::I32, ValueType::I32], None)),
+            Type::Function(FunctionType::new(Vec::new(), Some(ValueType::I32))),
+        ])),
+        Section::Import(ImportSection::with_entries(entries)),
+        Section::Function(FunctionSection::with_entries(funcs)),
+        Section::Table(TableSection::with_entries(vec![TableType::new(0, None)])),
+        Section::Memory(MemorySection::with_entries(vec![MemoryType::new(1, None)])),
+        Section::Export(ExportSection::with_entries(exported)),
+        Section::Code(CodeSection::with_bodies(bodies)),
+    ]);
+    cli::parity_wasm::serialize(module).unwrap()
+}
+
+/// Replaces the body of the export `name` of a `described_wasm` module with
+/// `code`, of the type `ty`.
+pub fn with_body(wasm: &[u8], name: &str, ty: FunctionType, code: Vec<Opcode>) -> Vec<u8> {
+    let mut module: Module = cli::parity_wasm::deserialize_buffer(wasm).unwrap();
+    let imports = module.import_section().map_or(0, |s| s.entries().len());
+    let idx = module.export_section().unwrap().entries().iter()
+        .filter_map(|e| match *e.internal() {
+            Internal::Function(i) if e.field() == name => Some(i as usize - imports),
+            _ => None,
+        })
+        .next()
+        .unwrap();
+    let ty_idx = module.type_section().unwrap().types().len() as u32;
+    for section in module.sections_mut() {
+        match *section {
+            Section::Type(ref mut s) => s.types_mut().push(Type::Function(ty.clone())),
+            Section::Function(ref mut s) => *s.entries_mut()[idx].type_ref_mut() = ty_idx,
+            Section::Code(ref mut s) => {
+                *s.bodies_mut()[idx].code_mut() = Opcodes::new(code.clone());
+            }
+            _ => {}
+        }
+    }
+    cli::parity_wasm::serialize(module).unwrap()
+}
+
+/// Changes the type of the import `shim` of a `described_wasm` module to
+/// `ty`.
+pub fn with_import_type(wasm: &[u8], shim: &str, ty: FunctionType) -> Vec<u8> {
+    use cli::parity_wasm::elements::External;
+
+    let mut module: Module = cli::parity_wasm::deserialize_buffer(wasm).unwrap();
+    let ty_idx = module.type_section().unwrap().types().len() as u32;
+    for section in module.sections_mut() {
+        match *section {
+            Section::Type(ref mut s) => s.types_mut().push(Type::Function(ty.clone())),
+            Section::Import(ref mut s) => {
+                for entry in s.entries_mut().iter_mut().filter(|e| e.field() == shim) {
+                    *entry.external_mut() = External::Function(ty_idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    cli::parity_wasm::serialize(module).unwrap()
+}
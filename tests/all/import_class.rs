@@ -392,3 +392,74 @@ fn rename_setter_getter() {
         "#)
         .test();
 }
+
+#[test]
+fn exported_class_extends_imported() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(extends = Base, module = "./another", super_args)]
+            pub struct Derived {
+                value: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Derived {
+                #[wasm_bindgen(constructor)]
+                pub fn new(value: u32) -> Derived {
+                    Derived { value }
+                }
+
+                pub fn value(&self) -> u32 {
+                    self.value
+                }
+
+                pub fn twice(&self) -> Derived {
+                    Derived { value: self.value * 2 }
+                }
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Base {
+                ptr: string = "base";
+                args: any[];
+
+                constructor(...args: any[]) {
+                    this.args = args;
+                }
+
+                describe(): string {
+                    return "base of " + this.args.length;
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Derived } from "./out";
+            import { Base } from "./another";
+
+            export function test() {
+                const d = new Derived(21);
+                assert.ok(d instanceof Base);
+                assert.ok(d instanceof Derived);
+                assert.deepStrictEqual(d.args, [21]);
+                assert.strictEqual(d.ptr, "base");
+                assert.strictEqual(d.describe(), "base of 1");
+                assert.strictEqual(d.value(), 21);
+
+                const e = d.twice();
+                assert.ok(e instanceof Base);
+                assert.deepStrictEqual(e.args, []);
+                assert.strictEqual(e.value(), 42);
+
+                e.free();
+                d.free();
+            }
+        "#)
+        .test();
+}
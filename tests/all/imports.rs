@@ -1,4 +1,10 @@
-use super::project;
+use std::io::Cursor;
+
+use cli::{self, Bindgen};
+use shared::ImportKind;
+
+use super::{project, MemoryOutput};
+use super::fixtures::*;
 
 #[test]
 fn simple() {
@@ -250,6 +256,54 @@ fn exn_caught() {
         .test();
 }
 
+#[test]
+fn lazy() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(js_namespace = polyfill, lazy, catch)]
+                fn feature(n: u32) -> Result<u32, JsValue>;
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(lazy)]
+                fn missing();
+            }
+
+            #[wasm_bindgen]
+            pub fn probe(n: u32) -> Option<u32> {
+                feature(n).ok()
+            }
+
+            #[wasm_bindgen]
+            pub fn call_missing() {
+                missing();
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { probe, call_missing } from "./out";
+
+            export function test() {
+                assert.strictEqual(probe(1), undefined);
+                assert.throws(call_missing, /imported function `missing` is not available/);
+
+                (global as any).polyfill = { feature: (n: number) => n + 1 };
+                assert.strictEqual(probe(1), 2);
+                assert.strictEqual(probe(2), 3);
+            }
+        "#)
+        .test();
+}
+
 #[test]
 fn free_imports() {
     project()
@@ -350,3 +404,272 @@ fn rename() {
         "#)
         .test();
 }
+
+#[test]
+fn variadic() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(variadic)]
+                fn count(first: u32, rest: &[f64]) -> u32;
+                #[wasm_bindgen(variadic)]
+                fn collect(rest: &[JsValue]) -> JsValue;
+                #[wasm_bindgen(variadic, catch)]
+                fn at_most_two(rest: &[u32]) -> Result<u32, JsValue>;
+
+                type Summer;
+                #[wasm_bindgen(constructor)]
+                fn new() -> Summer;
+                #[wasm_bindgen(method, structural, variadic)]
+                fn sum(this: &Summer, rest: &[i32]) -> i32;
+            }
+
+            #[wasm_bindgen(js_namespace = Math)]
+            extern {
+                #[wasm_bindgen(variadic)]
+                fn max(values: &[f64]) -> f64;
+            }
+
+            #[wasm_bindgen]
+            pub fn count_none() -> u32 {
+                count(1, &[])
+            }
+
+            #[wasm_bindgen]
+            pub fn count_some() -> u32 {
+                count(1, &[2.0, 3.5])
+            }
+
+            #[wasm_bindgen]
+            pub fn collect_values() -> JsValue {
+                collect(&[JsValue::from_str("a"), JsValue::from_f64(3.0), JsValue::null()])
+            }
+
+            #[wasm_bindgen]
+            pub fn caught(n: u32) -> bool {
+                let values = vec![1; n as usize];
+                at_most_two(&values).is_err()
+            }
+
+            #[wasm_bindgen]
+            pub fn structural_sum() -> i32 {
+                Summer::new().sum(&[1, 2, 3])
+            }
+
+            #[wasm_bindgen]
+            pub fn math_max(empty: bool) -> f64 {
+                if empty {
+                    max(&[])
+                } else {
+                    max(&[1.0, 7.0, 3.0])
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function count(first: number, ...rest: number[]): number {
+                assert.strictEqual(first, 1);
+                if (rest.length > 0) {
+                    assert.deepStrictEqual(rest, [2, 3.5]);
+                }
+                return arguments.length;
+            }
+
+            export function collect(...rest: any[]): any {
+                return rest;
+            }
+
+            export function at_most_two(...rest: number[]): number {
+                if (rest.length > 2) {
+                    throw new Error("too many");
+                }
+                return rest.length;
+            }
+
+            export class Summer {
+                sum(...rest: number[]): number {
+                    return rest.reduce((a, b) => a + b, 0);
+                }
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.count_none(), 1);
+                assert.strictEqual(wasm.count_some(), 3);
+                assert.deepStrictEqual(wasm.collect_values(), ["a", 3, null]);
+                assert.strictEqual(wasm.caught(2), false);
+                assert.strictEqual(wasm.caught(3), true);
+                assert.strictEqual(wasm.structural_sum(), 6);
+                assert.strictEqual(wasm.math_max(false), 7);
+                assert.strictEqual(wasm.math_max(true), -Infinity);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn dictionary() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub enum Cache {
+                Default = "default",
+                NoStore = "no-store",
+            }
+
+            #[wasm_bindgen(dictionary)]
+            pub struct Headers {
+                pub accept: String,
+            }
+
+            #[wasm_bindgen(dictionary)]
+            pub struct RequestInit {
+                pub method: String,
+                pub cache: Option<Cache>,
+                #[wasm_bindgen(js_name = keepAlive)]
+                pub keep_alive: Option<bool>,
+                pub priority: u32,
+                pub headers: Option<Headers>,
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn request(init: RequestInit) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() -> u32 {
+                let a = request(RequestInit {
+                    method: "POST".to_string(),
+                    cache: Some(Cache::NoStore),
+                    keep_alive: None,
+                    priority: 3,
+                    headers: Some(Headers { accept: "text/html".to_string() }),
+                });
+                let b = request(RequestInit {
+                    method: "GET".to_string(),
+                    cache: None,
+                    keep_alive: Some(false),
+                    priority: 1,
+                    headers: None,
+                });
+                a + b
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            let calls = 0;
+
+            export function request(init: wasm.RequestInit): number {
+                calls += 1;
+                if (calls === 1) {
+                    assert.deepStrictEqual(init, {
+                        method: "POST",
+                        cache: "no-store",
+                        priority: 3,
+                        headers: { accept: "text/html" },
+                    });
+                    assert.ok(!("keepAlive" in init));
+                } else {
+                    assert.deepStrictEqual(init, { method: "GET", keepAlive: false, priority: 1 });
+                    assert.ok(!("cache" in init));
+                }
+                return init.priority;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.run(), 4);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn lazy_import() {
+    let mut program = representative_program(Some("./logger"));
+    if let ImportKind::Function(ref mut f) = program.imports[0].kind {
+        f.lazy = true;
+    }
+    let wasm = with_program(REPRESENTATIVE_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm.clone()), "out")
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.lazy.js", &out.files["out.js"]);
+    assert_snapshot("representative.lazy.d.ts", &out.files["out.d.ts"]);
+
+    // Loading the glue doesn't need `log`, only calling it does, and once
+    // it's been provided it's found.
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    out.files.insert("logger.js".to_string(), b"".to_vec());
+    run_node("lazy", &out.files, &[], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'out.js'));
+            const logger = require(path.join(process.argv[1], 'logger.js'));
+            assert.strictEqual(out.halve(3), 1.5);
+            const counter = out.Counter.new(2);
+            assert.throws(() => counter.add(3),
+                          /imported function `log` is not available/);
+            const logged = [];
+            logger.log = n => logged.push(n);
+            assert.strictEqual(counter.add(3), 5);
+            assert.deepStrictEqual(logged, [3]);
+        "#);
+}
+
+#[test]
+fn local_module_collisions() {
+    use super::fixtures::words::*;
+
+    let wasm = representative_wasm(Some("./out.js"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out").local_module_root(env!("CARGO_MANIFEST_DIR"));
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Config(ref s) => {
+            assert_eq!(s, "the JS module `./out.js` would be copied to `out.js`, \
+                           which is a generated file");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let imports = [
+        ("from_parent".to_string(), function(&[], None)),
+        ("from_here".to_string(), function(&[], None)),
+    ];
+    let mut program = program("local_modules");
+    program.imports.push(import(Some("../a/x.js"), "from_parent", "f"));
+    program.imports.push(import(Some("./a/x.js"), "from_here", "f"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&described_wasm(&[], &imports), &program), "out")
+        .local_module_root(env!("CARGO_MANIFEST_DIR"));
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Config(ref s) => {
+            assert_eq!(s, "the JS modules `../a/x.js` and `./a/x.js` would both be \
+                           copied to `a/x.js`");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
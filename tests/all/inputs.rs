@@ -0,0 +1,134 @@
+use std::io::Cursor;
+
+use cli::{self, Bindgen};
+
+use super::MemoryOutput;
+use super::fixtures::*;
+
+#[test]
+fn bytes_input() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .debug(true)
+        .typescript(true);
+    for _ in 0..2 {
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+        assert_snapshot("representative.js", &out.files["out.js"]);
+        assert_snapshot("representative.d.ts", &out.files["out.d.ts"]);
+    }
+}
+
+#[test]
+fn returned_output() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .debug(true)
+        .typescript(true);
+    let output = bindgen.generate_output().expect("failed to run bindgen");
+
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert_snapshot("representative.js", output.js.as_bytes());
+    assert_snapshot("representative.d.ts", output.typescript.unwrap().as_bytes());
+    assert_eq!(output.wasm, out.files["out_bg.wasm"]);
+    assert_eq!(output.files.keys().collect::<Vec<_>>(), vec!["out_bg.d.ts"]);
+
+    bindgen.add_input_path("other.wasm");
+    let err = bindgen.generate_output().unwrap_err();
+    assert!(err.to_string().contains("use `generate_to` instead"), "unexpected error: {}", err);
+}
+
+#[test]
+fn parsed_module_input() {
+    let wasm = representative_wasm(Some("./logger"));
+    let module = cli::parity_wasm::deserialize_buffer(&wasm).unwrap();
+    let mut bindgen = Bindgen::new();
+    bindgen.input_module(module, "out")
+        .debug(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.js", &out.files["out.js"]);
+    assert_snapshot("representative.d.ts", &out.files["out.d.ts"]);
+
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err();
+    assert!(err.to_string().contains("was already used"), "unexpected error: {}", err);
+}
+
+#[test]
+fn multiple_input_sources() {
+    let wasm = representative_wasm(Some("./logger"));
+    let module = cli::parity_wasm::deserialize_buffer(&wasm).unwrap();
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .input_module(module, "out");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("an input was already set"), "unexpected error: {}", err);
+
+    let err = Bindgen::new().input_path("..").validate().unwrap_err().to_string();
+    assert_eq!(err, "the output files can't be named after the input `..`, which has no \
+                     UTF-8 file name");
+}
+
+#[test]
+fn unusable_inputs() {
+    let err = Bindgen::new().generate_to(&mut MemoryOutput::default()).unwrap_err();
+    assert_eq!(err.to_string(), "must have an input, see `input_path`");
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_path("does-not-exist.wasm");
+    match bindgen.validate().unwrap_err() {
+        cli::Error::Io { ref what, .. } => assert_eq!(what, "failed to read `does-not-exist.wasm`"),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(b"\0asm not really".to_vec(), "out");
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Wasm(_) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let mut payload = vec![10, 0, 0, 0];
+    payload.extend_from_slice(b"{ not json");
+    let wasm = with_custom_section(REPRESENTATIVE_WASM, "__wasm_bindgen_unstable", &payload);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out");
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Decode(ref s) => {
+            assert!(s.starts_with("failed to decode what looked like wasm-bindgen data"),
+                    "unexpected error: {}", s);
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    // Cut off in the length of a blob, and in a blob.
+    for payload in [&[5, 0][..], &[5, 0, 0, 0, b'{'][..]].iter() {
+        let wasm = with_custom_section(REPRESENTATIVE_WASM, "__wasm_bindgen_unstable", payload);
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes(wasm, "out");
+        match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+            cli::Error::Decode(ref s) => assert_eq!(s, "truncated wasm-bindgen custom section"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    // `counter_add` calls the `log` import, which the metadata leaves out.
+    let mut program = representative_program(None);
+    program.imports.clear();
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(REPRESENTATIVE_WASM, &program), "out");
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Bindings(ref problems) => {
+            assert_eq!(problems, &["generating bindings: the wasm module uses the import \
+                                    `__wbg_f_log_log_n` but nothing describes it to \
+                                    generate JS for"]);
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
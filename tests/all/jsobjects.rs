@@ -210,3 +210,206 @@ fn returning_vector() {
         "#)
         .test();
 }
+
+#[test]
+fn passing_vectors() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn first(values: &[JsValue]) -> JsValue;
+                fn pair(a: JsValue, b: JsValue) -> Vec<JsValue>;
+            }
+
+            #[wasm_bindgen]
+            pub fn count(values: &[JsValue]) -> usize {
+                values.len()
+            }
+
+            #[wasm_bindgen]
+            pub fn reverse(mut values: Vec<JsValue>) -> Vec<JsValue> {
+                values.reverse();
+                values
+            }
+
+            #[wasm_bindgen]
+            pub fn first_of(values: &[JsValue]) -> JsValue {
+                first(values)
+            }
+
+            #[wasm_bindgen]
+            pub fn make_pair(a: JsValue, b: JsValue) -> Vec<JsValue> {
+                pair(a, b)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function first(values: any[]): any { return values[0]; }
+            export function pair(a: any, b: any): any[] { return [a, b]; }
+
+            export function test() {
+                const obj = { a: 1 };
+                assert.strictEqual(wasm.count([obj, "x", null]), 3);
+                assert.strictEqual(wasm.count([]), 0);
+                assert.deepStrictEqual(wasm.reverse([1, "two", obj]), [obj, "two", 1]);
+                assert.strictEqual(wasm.first_of([obj, 2]), obj);
+                assert.deepStrictEqual(wasm.make_pair("a", obj), ["a", obj]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn reserved_values_and_live_count() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Holder {
+                values: Vec<JsValue>,
+            }
+
+            #[wasm_bindgen]
+            impl Holder {
+                pub fn new() -> Holder {
+                    Holder { values: Vec::new() }
+                }
+
+                pub fn hold(&mut self, value: JsValue) {
+                    self.values.push(value);
+                }
+
+                pub fn clear(&mut self) {
+                    self.values.clear();
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn constants() -> Vec<JsValue> {
+                vec![
+                    JsValue::undefined(),
+                    JsValue::null(),
+                    JsValue::from_bool(true),
+                    JsValue::from_bool(false),
+                ]
+            }
+
+            #[wasm_bindgen]
+            pub fn echo(value: &JsValue) -> JsValue {
+                value.clone()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                assert.deepStrictEqual(wasm.constants(), [undefined, null, true, false]);
+                assert.strictEqual(wasm.echo(null), null);
+                assert.strictEqual(wasm.echo("a"), "a");
+                assert.strictEqual(wasm.debugObjectStats().live, 0);
+
+                const holder = wasm.Holder.new();
+                holder.hold({});
+                holder.hold("b");
+                holder.hold(true);
+                holder.hold(undefined);
+                const stats = wasm.debugObjectStats(5);
+                assert.strictEqual(stats.live, 2);
+                assert.deepStrictEqual(stats.sample, ["Object", "String"]);
+                holder.clear();
+                assert.strictEqual(wasm.debugObjectStats().live, 0);
+                holder.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_stress() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn reenter(a: &JsValue, depth: u32) -> u32;
+                fn explode(a: &JsValue);
+            }
+
+            #[wasm_bindgen]
+            pub fn numbers(a: &JsValue, b: &JsValue) -> bool {
+                a.as_f64().is_some() && b.as_f64().is_some()
+            }
+
+            #[wasm_bindgen]
+            pub fn owned(a: &JsValue) -> JsValue {
+                a.clone()
+            }
+
+            #[wasm_bindgen]
+            pub fn nested(a: &JsValue, depth: u32) -> u32 {
+                if depth == 0 {
+                    return 0
+                }
+                reenter(a, depth - 1) + 1
+            }
+
+            #[wasm_bindgen]
+            pub fn forward(a: &JsValue) {
+                explode(a);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function reenter(a: any, depth: number): number {
+                // Each of the `nested` calls in progress still lends `a`.
+                assert.strictEqual(wasm.numbers(depth, depth), true);
+                assert.strictEqual(wasm.debugObjectStats().borrowed, 10 - depth);
+                return wasm.nested(a, depth);
+            }
+
+            export function explode(a: any) {
+                throw new Error(`boom ${a}`);
+            }
+
+            export function test() {
+                const a = {};
+                for (let i = 0; i < 100000; i++) {
+                    assert.strictEqual(wasm.numbers(i, i), true);
+                    assert.strictEqual(wasm.numbers(a, i), false);
+                    assert.strictEqual(wasm.owned(a), a);
+                }
+                assert.strictEqual(wasm.debugObjectStats().borrowed, 0);
+                assert.strictEqual(wasm.debugObjectStats().live, 0);
+
+                assert.strictEqual(wasm.nested(a, 10), 10);
+                assert.strictEqual(wasm.debugObjectStats().borrowed, 0);
+
+                for (let i = 0; i < 1000; i++)
+                    assert.throws(() => wasm.forward(i), /boom/);
+                assert.strictEqual(wasm.debugObjectStats().borrowed, 0);
+                assert.strictEqual(wasm.debugObjectStats().live, 0);
+            }
+        "#)
+        .test();
+}
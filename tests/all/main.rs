@@ -1,5 +1,8 @@
+extern crate serde_json;
 extern crate wasm_bindgen_cli_support as cli;
+extern crate wasm_bindgen_shared as shared;
 
+use std::collections::BTreeMap;
 use std::env;
 use std::fs::{self, File};
 use std::io::{self, Write, Read};
@@ -15,7 +18,17 @@ struct Project {
     files: Vec<(String, String)>,
     debug: bool,
     node: bool,
+    nodejs_async: bool,
     no_std: bool,
+    serde: bool,
+    gc: bool,
+    keep_debug: bool,
+    import_memory: bool,
+    string_interning: bool,
+    split_i64: bool,
+    multi_value: bool,
+    reference_types: bool,
+    local_module_roots: Vec<String>,
 }
 
 fn project() -> Project {
@@ -26,7 +39,17 @@ fn project() -> Project {
     Project {
         debug: true,
         node: false,
+        nodejs_async: false,
         no_std: false,
+        serde: false,
+        gc: true,
+        keep_debug: false,
+        import_memory: false,
+        string_interning: false,
+        split_i64: false,
+        multi_value: false,
+        reference_types: false,
+        local_module_roots: Vec::new(),
         files: vec![
             ("Cargo.toml".to_string(), format!(r#"
                 [package]
@@ -52,7 +75,7 @@ fn project() -> Project {
                 const test = import("./test");
 
                 test.then(test => {
-                  test.test();
+                  return test.test();
                 }).catch(error => {
                   console.error(error);
                   process.exit(1);
@@ -60,7 +83,10 @@ fn project() -> Project {
             "#.to_string()),
 
             ("run-node.js".to_string(), r#"
-                require("./test").test();
+                Promise.resolve(require("./test").test()).catch(error => {
+                  console.error(error);
+                  process.exit(1);
+                });
             "#.to_string()),
 
             ("webpack.config.js".to_string(), r#"
@@ -139,11 +165,61 @@ impl Project {
         self
     }
 
+    fn nodejs_async(&mut self, nodejs_async: bool) -> &mut Project {
+        self.nodejs_async = nodejs_async;
+        self
+    }
+
     fn no_std(&mut self, no_std: bool) -> &mut Project {
         self.no_std = no_std;
         self
     }
 
+    fn serde(&mut self, serde: bool) -> &mut Project {
+        self.serde = serde;
+        self
+    }
+
+    fn gc(&mut self, gc: bool) -> &mut Project {
+        self.gc = gc;
+        self
+    }
+
+    fn keep_debug(&mut self, keep_debug: bool) -> &mut Project {
+        self.keep_debug = keep_debug;
+        self
+    }
+
+    fn import_memory(&mut self, import_memory: bool) -> &mut Project {
+        self.import_memory = import_memory;
+        self
+    }
+
+    fn string_interning(&mut self, interning: bool) -> &mut Project {
+        self.string_interning = interning;
+        self
+    }
+
+    fn split_i64(&mut self, split_i64: bool) -> &mut Project {
+        self.split_i64 = split_i64;
+        self
+    }
+
+    fn multi_value(&mut self, multi_value: bool) -> &mut Project {
+        self.multi_value = multi_value;
+        self
+    }
+
+    fn reference_types(&mut self, reference_types: bool) -> &mut Project {
+        self.reference_types = reference_types;
+        self
+    }
+
+    fn local_module_root(&mut self, dir: &str) -> &mut Project {
+        self.local_module_roots.push(dir.to_string());
+        self
+    }
+
     fn add_local_dependency(&mut self, name: &str, path: &str) -> &mut Project {
         {
             let cargo_toml = self.files
@@ -168,10 +244,16 @@ impl Project {
             cargo_toml.1.push_str(env!("CARGO_MANIFEST_DIR"));
             if self.no_std {
                 cargo_toml.1.push_str("', default-features = false");
+            } else if self.serde {
+                cargo_toml.1.push_str("', features = ['serde-serialize']");
             } else {
                 cargo_toml.1.push_str("'");
             }
             cargo_toml.1.push_str(" }\n");
+            if self.serde {
+                cargo_toml.1.push_str("serde = '1.0'\n");
+                cargo_toml.1.push_str("serde_derive = '1.0'\n");
+            }
         }
         let root = root();
         drop(fs::remove_dir_all(&root));
@@ -184,14 +266,18 @@ impl Project {
         let target_dir = root.parent().unwrap() // chop off test name
             .parent().unwrap(); // chop off `generated-tests`
 
+        // Catch any warnings in generated code because we don't want any
+        let mut rustflags = "-Dwarnings".to_string();
+        if self.import_memory {
+            rustflags.push_str(" -C link-arg=--import-memory");
+        }
         let mut cmd = Command::new("cargo");
         cmd.arg("build")
             .arg("--target")
             .arg("wasm32-unknown-unknown")
             .current_dir(&root)
             .env("CARGO_TARGET_DIR", &target_dir)
-            // Catch any warnings in generated code because we don't want any
-            .env("RUSTFLAGS", "-Dwarnings");
+            .env("RUSTFLAGS", &rustflags);
         run(&mut cmd, "cargo");
 
         let idx = IDX.with(|x| *x);
@@ -200,24 +286,35 @@ impl Project {
         let as_a_module = root.join("out.wasm");
         fs::copy(&out, &as_a_module).unwrap();
 
-        cli::Bindgen::new()
-            .input_path(&as_a_module)
+        let mut bindgen = cli::Bindgen::new();
+        bindgen.input_path(&as_a_module)
             .typescript(true)
             .nodejs(self.node)
+            .nodejs_async(self.nodejs_async)
             .debug(self.debug)
-            .generate(&root)
-            .expect("failed to run bindgen");
-
-        let mut wasm = Vec::new();
-        File::open(root.join("out_bg.wasm")).unwrap()
-            .read_to_end(&mut wasm).unwrap();
-        let obj = cli::wasm2es6js::Config::new()
-            .base64(true)
-            .generate(&wasm)
-            .expect("failed to convert wasm to js");
-        File::create(root.join("out_bg.d.ts")).unwrap()
-            .write_all(obj.typescript().as_bytes()).unwrap();
-
+            .run_gc(self.gc)
+            .keep_debug(self.keep_debug)
+            .string_interning(self.string_interning)
+            .split_i64(self.split_i64)
+            .multi_value(self.multi_value)
+            .reference_types(self.reference_types);
+        for dir in self.local_module_roots.iter() {
+            bindgen.local_module_root(root.join(dir));
+        }
+        bindgen.generate(&root).expect("failed to run bindgen");
+
+        // Generating bindings a second time from the same wasm file should
+        // produce byte-for-byte identical output.
+        let mut again = MemoryOutput::default();
+        bindgen.generate_to(&mut again).expect("failed to run bindgen");
+        for file in ["out.js", "out.d.ts", "out_bg.wasm", "out_bg.d.ts"].iter() {
+            assert!(again.files.contains_key(*file), "`{}` wasn't generated", file);
+        }
+        for (file, b) in again.files.iter() {
+            let mut a = Vec::new();
+            File::open(root.join(file)).unwrap().read_to_end(&mut a).unwrap();
+            assert!(a == *b, "generating `{}` twice produced different output", file);
+        }
 
         // move files from the root into each test, it looks like this may be
         // needed for webpack to work well when invoked concurrently.
@@ -254,6 +351,20 @@ impl Project {
     }
 }
 
+/// Collects generated files in memory rather than writing them to disk.
+#[derive(Default)]
+struct MemoryOutput {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl cli::OutputSink for MemoryOutput {
+    fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        assert!(!self.files.contains_key(name), "`{}` was written twice", name);
+        self.files.insert(name.to_string(), contents.to_vec());
+        Ok(())
+    }
+}
+
 #[cfg(unix)]
 fn symlink_dir(a: &Path, b: &Path) -> io::Result<()> {
     use std::os::unix::fs::symlink;
@@ -289,14 +400,28 @@ fn run(cmd: &mut Command, program: &str) {
 mod api;
 mod classes;
 mod closures;
+mod consts;
+mod conversions;
+mod csp;
 mod dependencies;
+mod descriptors;
 mod enums;
+mod fixtures;
 mod import_class;
 mod imports;
+mod inputs;
 mod jsobjects;
 mod math;
+mod multiple_inputs;
 mod node;
 mod non_debug;
+mod option;
+mod outputs;
+mod promises;
+mod serde;
 mod simple;
+mod snapshot;
 mod slice;
 mod structural;
+mod targets;
+mod wasm2es6js;
@@ -0,0 +1,318 @@
+use cli::{Bindgen, EsVersion};
+use shared::{Export, Struct, Superclass};
+
+use super::MemoryOutput;
+use super::conversions::anyref_wasm;
+use super::fixtures::*;
+
+#[test]
+fn multiple_inputs() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(anyref_wasm(), "a")
+        .add_input_bytes(anyref_wasm(), "b")
+        .typescript(true);
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("is exported by both the `a` and `b` inputs, use `export_prefix`"),
+            "unexpected error: {}", err);
+
+    bindgen.export_prefix("b", "b_");
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert_eq!(out.files.keys().collect::<Vec<_>>(),
+               vec!["a.d.ts", "a.js", "a_bg.d.ts", "a_bg.wasm",
+                    "b.d.ts", "b.js", "b_bg.d.ts", "b_bg.wasm",
+                    "index.d.ts", "index.js"]);
+    // The entry only re-exports what each input's own glue exports.
+    let entry = String::from_utf8_lossy(&out.files["index.js"]).into_owned();
+    assert_eq!(entry.lines().count(), 2);
+    assert!(entry.starts_with("export { returns_anyref, "), "{}", entry);
+    assert!(entry.contains("export { returns_anyref as b_returns_anyref, "), "{}", entry);
+    assert!(entry.ends_with(" } from './b';\n"), "{}", entry);
+    assert_eq!(out.files["index.d.ts"], out.files["index.js"]);
+    // Each input has glue of its own, importing its own wasm.
+    let a = String::from_utf8_lossy(&out.files["a.js"]).into_owned();
+    let b = String::from_utf8_lossy(&out.files["b.js"]).into_owned();
+    assert!(a.contains("import * as wasm from './a_bg';"));
+    assert_eq!(b, a.replace("'./a_bg'", "'./b_bg'"));
+}
+
+#[test]
+fn shared_runtime() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(anyref_wasm(), "a")
+        .add_input_bytes(anyref_wasm(), "b")
+        .export_prefix("b", "b_")
+        .debug(true)
+        .nodejs_module(true)
+        .shared_runtime(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("conversions.anyref.runtime.js", &out.files["wasm_bindgen_runtime.js"]);
+    for name in ["a.js", "b.js"].iter() {
+        let js = String::from_utf8_lossy(&out.files[*name]);
+        assert!(js.contains("from './wasm_bindgen_runtime.js';"), "{}", js);
+        assert!(!js.contains("function addHeapObject"), "{}", js);
+    }
+    out.files.insert("package.json".to_string(), br#"{ "type": "module" }"#.to_vec());
+
+    // A JS value added to the heap by either input's glue is seen by both.
+    run_node("shared-runtime", &out.files, &["--input-type=module"], r#"
+            import assert from 'assert';
+            import { pathToFileURL } from 'url';
+            globalThis.host = {};
+            const dir = pathToFileURL(process.argv[1] + '/').href;
+            const out = await import(dir + 'index.js');
+            const runtime = await import(dir + 'wasm_bindgen_runtime.js');
+            runtime.addHeapObject({});
+            assert.strictEqual(out.debugObjectStats().live, 1);
+            assert.strictEqual(out.b_debugObjectStats().live, 1);
+        "#);
+}
+
+/// A module exporting the class `Frame`, whose constructor returns a frame at
+/// address 24 and whose `id` method returns `id`, along with a function
+/// borrowing a frame. The class extends `superclass`, if given.
+fn frame_wasm(id: i32, superclass: Option<Superclass>) -> Vec<u8> {
+    use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+    use super::fixtures::words::*;
+
+    let exports = [
+        ("frame_new".to_string(), function(&[], Some(&frame()))),
+        ("frame_id".to_string(), function(&[], Some(&[U32]))),
+        ("takes_ref_frame".to_string(), function(&[&[&[REF][..], &frame()].concat()], None)),
+    ];
+    let wasm = described_wasm(&exports, &[]);
+    let wasm = with_body(&wasm, "frame_new", FunctionType::new(Vec::new(), Some(ValueType::I32)),
+                         vec![Opcode::I32Const(24), Opcode::End]);
+    let wasm = with_body(&wasm, "frame_id",
+                         FunctionType::new(vec![ValueType::I32], Some(ValueType::I32)),
+                         vec![Opcode::I32Const(id), Opcode::End]);
+    let mut program = program("frames");
+    program.exports.push(Export {
+        constructor: Some("new".to_string()),
+        ..static_method("Frame", "new")
+    });
+    program.exports.push(method("Frame", "id"));
+    program.exports.push(export("takes_ref_frame"));
+    program.structs.push(Struct { superclass, ..class("Frame") });
+    with_program(&wasm, &program)
+}
+
+/// The `Base` class of the module `./base.js`, which the constructor's
+/// arguments are passed on to.
+fn base_class() -> Superclass {
+    Superclass {
+        name: "Base".to_string(),
+        module: Some("./base.js".to_string()),
+        js_namespace: None,
+        forward_args: true,
+    }
+}
+
+#[test]
+fn shared_classes() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(frame_wasm(1, None), "a")
+        .add_input_bytes(frame_wasm(2, None), "b")
+        .export_prefix("b", "b_")
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // `Frame` is defined once, and only by the classes module.
+    assert_snapshot("shared-classes.js", &out.files["wasm_bindgen_classes.js"]);
+    assert_eq!(String::from_utf8_lossy(&out.files["wasm_bindgen_classes.d.ts"]),
+               "/* tslint:disable */\nexport { Frame } from './a';\n");
+    let entry = String::from_utf8_lossy(&out.files["index.js"]).into_owned();
+    assert_eq!(entry, "export { Frame } from './wasm_bindgen_classes';\n\
+                       export { takes_ref_frame } from './a';\n\
+                       export { takes_ref_frame as b_takes_ref_frame } from './b';\n");
+    // Each input's class extends it, and the first input's owns it.
+    let a = String::from_utf8_lossy(&out.files["a.js"]).into_owned();
+    let b = String::from_utf8_lossy(&out.files["b.js"]).into_owned();
+    for js in [&a, &b].iter() {
+        assert!(js.contains("import { Frame as __wbg_shared_Frame } from './wasm_bindgen_classes';"),
+                "{}", js);
+        assert!(js.contains("class Frame extends __wbg_shared_Frame {"), "{}", js);
+    }
+    assert!(a.contains("__wbg_shared_Frame.__wbg_owner = Frame;"), "{}", a);
+    assert!(!b.contains("__wbg_owner"), "{}", b);
+    let ts = String::from_utf8_lossy(&out.files["a.d.ts"]).into_owned();
+    assert!(ts.contains("export class Frame {"), "{}", ts);
+
+    // The inputs have to agree on the JS class a shared one extends.
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(frame_wasm(1, Some(base_class())), "a")
+        .add_input_bytes(frame_wasm(2, None), "b")
+        .export_prefix("b", "b_");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    let expected = "the struct `Frame` extends a different JS class in the `b` input than in \
+                    the `a` one, which can't share its class";
+    assert!(err.contains(expected), "unexpected error: {}", err);
+    assert!(bindgen.validate().unwrap_err().to_string().contains(expected));
+}
+
+#[test]
+fn shared_classes_dispatch() {
+    for &es_target in [EsVersion::Es2015, EsVersion::Es5].iter() {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes(frame_wasm(1, None), "a")
+            .add_input_bytes(frame_wasm(2, None), "b")
+            .export_prefix("b", "b_")
+            .nodejs(true)
+            .es_target(es_target);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+        if es_target == EsVersion::Es5 {
+            assert_snapshot("shared-classes.es5.js", &out.files["wasm_bindgen_classes.js"]);
+        }
+
+        // Instances are those of the input creating them, which the shared
+        // class creates with the owner's constructor, and none of them can be
+        // passed to another input, even in release mode.
+        run_node("shared-classes", &out.files, &[], r#"
+                const assert = require('assert');
+                const path = require('path');
+                const out = require(path.join(process.argv[1], 'index.js'));
+                const a = require(path.join(process.argv[1], 'a.js'));
+                const b = require(path.join(process.argv[1], 'b.js'));
+                const frame = new out.Frame();
+                assert.ok(frame instanceof a.Frame);
+                assert.strictEqual(frame.id(), 1);
+                assert.ok(out.Frame.new() instanceof a.Frame);
+                const other = b.Frame.new();
+                assert.ok(other instanceof out.Frame);
+                assert.ok(!(other instanceof a.Frame));
+                assert.strictEqual(other.id(), 2);
+                out.takes_ref_frame(frame);
+                out.b_takes_ref_frame(other);
+                assert.throws(() => out.takes_ref_frame(other), /expected instance of Frame/);
+            "#);
+    }
+}
+
+#[test]
+fn shared_classes_no_modules() {
+    for &es_target in [EsVersion::Es2015, EsVersion::Es5].iter() {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes(frame_wasm(1, None), "a")
+            .add_input_bytes(frame_wasm(2, None), "b")
+            .export_prefix("b", "b_")
+            .no_modules(true)
+            .es_target(es_target)
+            .typescript(true);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+        // The classes are assigned to the entry's global, which re-exports
+        // them along with the owner's typings.
+        let classes = String::from_utf8_lossy(&out.files["wasm_bindgen_classes.js"]).into_owned();
+        assert!(classes.contains("__global.wasm_bindgen = { Frame: Frame };"), "{}", classes);
+        assert!(!out.files.contains_key("wasm_bindgen_classes.d.ts"));
+        let ts = String::from_utf8_lossy(&out.files["index.d.ts"]).into_owned();
+        assert!(ts.contains("export import Frame = wasm_bindgen_a.Frame;"), "{}", ts);
+
+        // The scripts are loaded in order, the classes first.
+        run_node("shared-classes-no-modules", &out.files, &[], r#"
+                const assert = require('assert');
+                const fs = require('fs');
+                const path = require('path');
+                const vm = require('vm');
+                const dir = process.argv[1];
+                const ctx = vm.createContext({});
+                for (const file of ['wasm_bindgen_classes.js', 'a.js', 'b.js', 'index.js']) {
+                    vm.runInContext(fs.readFileSync(path.join(dir, file), 'utf8'), ctx);
+                }
+                const out = ctx.wasm_bindgen;
+                const wasm = ['a_bg.wasm', 'b_bg.wasm'].map(f => fs.readFileSync(path.join(dir, f)));
+                out(wasm)
+                    .then(() => {
+                        const a = ctx.wasm_bindgen_a;
+                        const frame = new out.Frame();
+                        assert.ok(frame instanceof a.Frame);
+                        assert.strictEqual(frame.id(), 1);
+                        const other = ctx.wasm_bindgen_b.Frame.new();
+                        assert.ok(other instanceof out.Frame);
+                        assert.strictEqual(other.id(), 2);
+                        out.takes_ref_frame(frame);
+                        assert.throws(() => out.takes_ref_frame(other),
+                                      /expected instance of Frame/);
+                    })
+                    .catch(e => { console.error(e); process.exit(1); });
+            "#);
+    }
+}
+
+#[test]
+fn shared_classes_split() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(frame_wasm(1, None), "a")
+        .add_input_bytes(frame_wasm(2, None), "b")
+        .export_prefix("b", "b_")
+        .nodejs_module(true)
+        .split_output(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // The module of each input's class is evaluated before its core module,
+    // so it imports the shared class itself.
+    for name in ["a_class_Frame.js", "b_class_Frame.js"].iter() {
+        let js = String::from_utf8_lossy(&out.files[*name]);
+        assert!(js.contains("import { Frame as __wbg_shared_Frame } from \
+                             './wasm_bindgen_classes.js';"), "{}", js);
+    }
+    out.files.insert("package.json".to_string(), br#"{ "type": "module" }"#.to_vec());
+
+    run_node("shared-classes-split", &out.files, &["--input-type=module"], r#"
+            import assert from 'assert';
+            import { pathToFileURL } from 'url';
+            const dir = pathToFileURL(process.argv[1] + '/').href;
+            const out = await import(dir + 'index.js');
+            const b = await import(dir + 'b.js');
+            assert.strictEqual(new out.Frame().id(), 1);
+            assert.ok(new b.Frame() instanceof out.Frame);
+            assert.strictEqual(new b.Frame().id(), 2);
+        "#);
+}
+
+#[test]
+fn shared_classes_superclass() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(frame_wasm(1, Some(base_class())), "a")
+        .add_input_bytes(frame_wasm(2, Some(base_class())), "b")
+        .export_prefix("b", "b_")
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // The shared class extends the JS class in place of each input's, and
+    // passes the arguments it's constructed with on to it.
+    let classes = String::from_utf8_lossy(&out.files["wasm_bindgen_classes.js"]).into_owned();
+    assert!(classes.contains("const __wbg_base_Frame = require('./base.js').Base;"), "{}", classes);
+    assert!(classes.contains("class Frame extends __wbg_base_Frame {"), "{}", classes);
+    out.files.insert("base.js".to_string(), br#"
+        class Base {
+            constructor(...args) {
+                this.args = args;
+            }
+        }
+        module.exports.Base = Base;
+    "#.to_vec());
+
+    run_node("shared-classes-superclass", &out.files, &[], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'index.js'));
+            const b = require(path.join(process.argv[1], 'b.js'));
+            const { Base } = require(path.join(process.argv[1], 'base.js'));
+            const frame = new out.Frame(3, 4);
+            assert.ok(frame instanceof Base);
+            assert.deepStrictEqual(frame.args, [3, 4]);
+            assert.strictEqual(frame.id(), 1);
+            const other = new b.Frame(5);
+            assert.ok(other instanceof Base);
+            assert.deepStrictEqual(other.args, [5]);
+            assert.strictEqual(other.id(), 2);
+        "#);
+}
@@ -1,4 +1,8 @@
-use super::project;
+use cli::Bindgen;
+use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+
+use super::{MemoryOutput, project};
+use super::fixtures::*;
 
 #[test]
 fn works() {
@@ -152,3 +156,269 @@ fn works() {
         "#)
         .test();
 }
+
+#[test]
+fn debug_sections() {
+    let lib = r#"
+        #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+        extern crate wasm_bindgen;
+
+        use wasm_bindgen::prelude::*;
+
+        #[wasm_bindgen]
+        pub fn foo() -> u32 {
+            3
+        }
+    "#;
+    let demangled = r#"
+        const assert = require('assert');
+        const fs = require('fs');
+        const path = require('path');
+        const { foo } = require('./out');
+
+        module.exports.test = function() {
+            assert.strictEqual(foo(), 3);
+            const bytes = fs.readFileSync(path.join(__dirname, 'out_bg.wasm'));
+            const m = new WebAssembly.Module(bytes);
+            const names = WebAssembly.Module.customSections(m, 'name');
+            assert.strictEqual(names.length, 1);
+            // demangled names are in the section, not mangled ones
+            const contents = Buffer.from(names[0]).toString();
+            assert(contents.indexOf('foo') !== -1);
+            assert.strictEqual(contents.indexOf('_ZN'), -1);
+        };
+    "#;
+    project()
+        .node(true)
+        .keep_debug(true)
+        .file("src/lib.rs", lib)
+        .file("test.js", demangled)
+        .test();
+
+    // names are demangled even without the gc pass
+    project()
+        .node(true)
+        .keep_debug(true)
+        .gc(false)
+        .file("src/lib.rs", lib)
+        .file("test.js", demangled)
+        .test();
+
+    project()
+        .node(true)
+        .file("src/lib.rs", lib)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const { foo } = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(foo(), 3);
+                const bytes = fs.readFileSync(path.join(__dirname, 'out_bg.wasm'));
+                const m = new WebAssembly.Module(bytes);
+                assert.strictEqual(WebAssembly.Module.customSections(m, 'name').length, 0);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn local_module_roots() {
+    project()
+        .node(true)
+        .local_module_root("js")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./snippets/helpers.js")]
+            extern {
+                fn double(a: u32) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(double(2), 4);
+            }
+        "#)
+        .file("js/snippets/helpers.js", r#"
+            module.exports.double = function(a) {
+                return a * 2;
+            };
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert(fs.existsSync(path.join(__dirname, 'snippets', 'helpers.js')));
+                wasm.run();
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn nodejs_async() {
+    project()
+        .node(true)
+        .nodejs_async(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.throws(() => wasm.add(1, 2), /wasm not yet initialized; await `ready`/);
+                return wasm.ready.then(() => {
+                    assert.strictEqual(wasm.add(1, 2), 3);
+                });
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn imported_memory() {
+    project()
+        .node(true)
+        .import_memory(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str) -> String {
+                format!("hello {}", name)
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                const bytes = fs.readFileSync(path.join(__dirname, 'out_bg.wasm'));
+                const imports = WebAssembly.Module.imports(new WebAssembly.Module(bytes));
+                assert(imports.some(i => i.kind === 'memory'));
+                assert.strictEqual(wasm.greet('world'), 'hello world');
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn reference_types() {
+    project()
+        .node(true)
+        .reference_types(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn pick(a: JsValue, b: &JsValue) -> JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn relay(a: JsValue, b: &JsValue) -> JsValue {
+                pick(a, b)
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+
+            module.exports.pick = (a, b) => ({ a, b });
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                const a = {};
+                const b = [];
+                const picked = wasm.relay(a, b);
+                assert.strictEqual(picked.a, a);
+                assert.strictEqual(picked.b, b);
+                const reserved = wasm.relay(null, undefined);
+                assert.strictEqual(reserved.a, null);
+                assert.strictEqual(reserved.b, undefined);
+
+                // Nothing is left in the table once the calls have returned.
+                for (let i = 0; i < 100; i++)
+                    wasm.relay(i, String(i));
+                assert.strictEqual(wasm.debugObjectStats().live, 0);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn reference_types_fixture() {
+    use super::fixtures::words::*;
+
+    // `relay` passes its owned and borrowed arguments on to the imported
+    // `pick`, returning what that returns.
+    let pick = function(&[&[ANYREF], &[REF, ANYREF]], Some(&[ANYREF]));
+    let exports = [("relay".to_string(), pick.clone())];
+    let imports = [("pick".to_string(), pick)];
+    let ty = FunctionType::new(vec![ValueType::I32, ValueType::I32], Some(ValueType::I32));
+    let wasm = with_body(&described_wasm(&exports, &imports), "relay", ty.clone(),
+                         vec![Opcode::GetLocal(0), Opcode::GetLocal(1), Opcode::Call(1),
+                              Opcode::End]);
+    let wasm = with_import_type(&wasm, "pick", ty);
+    let mut relay = program("reference_types");
+    relay.exports.push(export("relay"));
+    relay.imports.push(host_import("pick"));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&wasm, &relay), "out")
+        .nodejs(true)
+        .debug(true)
+        .reference_types(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    run_node("reference-types", &out.files, &[], r#"
+        const assert = require('assert');
+        const path = require('path');
+        global.host = { pick: (a, b) => ({ a, b }) };
+        const out = require(path.join(process.argv[1], 'out.js'));
+        const a = {};
+        const b = [];
+        const picked = out.relay(a, b);
+        assert.strictEqual(picked.a, a);
+        assert.strictEqual(picked.b, b);
+        const reserved = out.relay(null, undefined);
+        assert.strictEqual(reserved.a, null);
+        assert.strictEqual(reserved.b, undefined);
+
+        // Nothing is left in the table once the calls have returned.
+        for (let i = 0; i < 100; i++)
+            out.relay(i, String(i));
+        assert.strictEqual(out.debugObjectStats().live, 0);
+    "#);
+}
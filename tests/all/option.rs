@@ -0,0 +1,299 @@
+use super::project;
+
+#[test]
+fn strings() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn maybe_greeting(name: Option<&str>) -> Option<String>;
+            }
+
+            #[wasm_bindgen]
+            pub fn label(text: Option<String>) -> Option<String> {
+                text.map(|s| format!("<{}>", s))
+            }
+
+            #[wasm_bindgen]
+            pub fn empty(some: bool) -> Option<String> {
+                if some { Some(String::new()) } else { None }
+            }
+
+            #[wasm_bindgen]
+            pub fn is_none(text: Option<String>) -> bool {
+                text.is_none()
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(name: Option<String>) -> Option<String> {
+                maybe_greeting(name.as_ref().map(|s| &s[..]))
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function maybe_greeting(name: string | undefined): string | undefined {
+                return name === undefined ? undefined : `hi ${name}`;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.label("a"), "<a>");
+                assert.strictEqual(wasm.label(""), "<>");
+                assert.strictEqual(wasm.label(undefined), undefined);
+                assert.strictEqual(wasm.label(null as any), undefined);
+                assert.strictEqual(wasm.label(), undefined);
+
+                assert.strictEqual(wasm.empty(true), "");
+                assert.strictEqual(wasm.empty(false), undefined);
+                assert.strictEqual(wasm.is_none(""), false);
+                assert.strictEqual(wasm.is_none(), true);
+
+                assert.strictEqual(wasm.greet("x"), "hi x");
+                assert.strictEqual(wasm.greet(""), "hi ");
+                assert.strictEqual(wasm.greet(), undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn classes() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Widget {
+                id: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Widget {
+                pub fn new(id: u32) -> Widget {
+                    Widget { id }
+                }
+
+                pub fn id(&self) -> u32 {
+                    self.id
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn find(id: u32) -> Option<Widget> {
+                if id < 10 { Some(Widget { id }) } else { None }
+            }
+
+            #[wasm_bindgen]
+            pub fn id_or(fallback: u32, widget: Option<Widget>) -> u32 {
+                widget.map(|w| w.id).unwrap_or(fallback)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Widget, find, id_or } from "./out";
+
+            export function test() {
+                const w = find(3);
+                assert.ok(w instanceof Widget);
+                assert.strictEqual(w!.id(), 3);
+                assert.strictEqual(find(10), undefined);
+
+                assert.strictEqual(id_or(1, w), 3);
+                assert.strictEqual(id_or(1), 1);
+                assert.strictEqual(id_or(2, null as any), 2);
+
+                const w2 = Widget.new(5);
+                w2.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn numbers() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn maybe_double(n: Option<u32>) -> Option<u32>;
+            }
+
+            #[wasm_bindgen]
+            pub fn checked_half(n: u32) -> Option<u32> {
+                if n % 2 == 0 { Some(n / 2) } else { None }
+            }
+
+            #[wasm_bindgen]
+            pub fn or_zero(n: Option<u32>) -> u32 {
+                n.unwrap_or(0)
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(a: Option<f64>, b: &str, c: Option<i32>) -> f64 {
+                a.unwrap_or(0.0) + b.len() as f64 + c.unwrap_or(0) as f64
+            }
+
+            #[wasm_bindgen]
+            pub fn double(n: Option<u32>) -> Option<u32> {
+                maybe_double(n)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function maybe_double(n: number | undefined): number | undefined {
+                return n === undefined ? undefined : n * 2;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.checked_half(4), 2);
+                assert.strictEqual(wasm.checked_half(0), 0);
+                assert.strictEqual(wasm.checked_half(3), undefined);
+
+                assert.strictEqual(wasm.or_zero(7), 7);
+                assert.strictEqual(wasm.or_zero(0), 0);
+                assert.strictEqual(wasm.or_zero(undefined), 0);
+                assert.strictEqual(wasm.or_zero(), 0);
+
+                assert.strictEqual(wasm.sum(0.5, "ab", -3), -0.5);
+                assert.strictEqual(wasm.sum(undefined, "ab"), 2);
+                assert.strictEqual(wasm.sum(null as any, "", 4), 4);
+
+                assert.strictEqual(wasm.double(3), 6);
+                assert.strictEqual(wasm.double(0), 0);
+                assert.strictEqual(wasm.double(), undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_strings_and_imported_types() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                type Thing;
+                fn find_thing(name: &str) -> Option<Thing>;
+                #[wasm_bindgen(method)]
+                fn name(this: &Thing) -> String;
+                fn describe(thing: Option<Thing>) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn length(text: Option<&str>) -> u32 {
+                text.map(|s| s.len() as u32).unwrap_or(0)
+            }
+
+            #[wasm_bindgen]
+            pub fn lookup(name: &str) -> Option<Thing> {
+                find_thing(name)
+            }
+
+            #[wasm_bindgen]
+            pub fn name_of(thing: Option<Thing>) -> Option<String> {
+                thing.map(|t| t.name())
+            }
+
+            #[wasm_bindgen]
+            pub fn describe_missing() -> String {
+                describe(find_thing("missing"))
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export class Thing {
+                constructor(private _name: string) {}
+                name(): string { return this._name; }
+            }
+
+            export function find_thing(name: string): Thing | undefined {
+                return name === "missing" ? undefined : new Thing(name);
+            }
+
+            export function describe(thing: Thing | undefined): string {
+                return thing === undefined ? "nothing" : thing.name();
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.length("abc"), 3);
+                assert.strictEqual(wasm.length(""), 0);
+                assert.strictEqual(wasm.length(), 0);
+
+                const t = wasm.lookup("a");
+                assert.ok(t instanceof Thing);
+                assert.strictEqual(wasm.lookup("missing"), undefined);
+
+                assert.strictEqual(wasm.name_of(new Thing("b")), "b");
+                assert.strictEqual(wasm.name_of(undefined), undefined);
+                assert.strictEqual(wasm.name_of(null), undefined);
+
+                assert.strictEqual(wasm.describe_missing(), "nothing");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn trailing_optional_parameters() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn draw(x: u32, y: u32, color: Option<String>) -> String {
+                format!("{},{} {}", x, y, color.unwrap_or("black".to_string()))
+            }
+
+            #[wasm_bindgen]
+            pub fn join(a: Option<String>, b: String, c: Option<String>) -> String {
+                format!("{}|{}|{}", a.unwrap_or_default(), b, c.unwrap_or_default())
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.draw(1, 2), "1,2 black");
+                assert.strictEqual(wasm.draw(1, 2, undefined), "1,2 black");
+                assert.strictEqual(wasm.draw(1, 2, "red"), "1,2 red");
+
+                // Only the last one can be left out, the first has to be
+                // passed explicitly.
+                assert.strictEqual(wasm.join(undefined, "b"), "|b|");
+                assert.strictEqual(wasm.join("a", "b", "c"), "a|b|c");
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,429 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Write};
+use std::process::Command;
+
+use cli::{self, Bindgen, OutputNames, WasmImportMode};
+use shared::{ImportKind, Location};
+
+use super::MemoryOutput;
+use super::conversions::anyref_wasm;
+use super::fixtures::*;
+
+#[test]
+fn renamed_outputs() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut names = OutputNames::new();
+    names.js("{stem}.mjs")
+        .typescript("{stem}.d.mts")
+        .wasm_loader("{stem}.loader.mjs")
+        .wasm("{stem}.module.wasm");
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .wasm_import_mode(WasmImportMode::FetchUrl)
+        .typescript(true)
+        .emit_wat(true)
+        .output_names(&names);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    let files = out.files.keys().map(|s| &s[..]).collect::<Vec<_>>();
+    assert_eq!(files, ["out.d.mts", "out.loader.mjs", "out.mjs", "out.module.d.ts",
+                       "out.module.wasm", "out.module.wat"]);
+    assert_snapshot("representative.renamed.mjs", &out.files["out.mjs"]);
+    assert_snapshot("representative.renamed.loader.mjs", &out.files["out.loader.mjs"]);
+    // The wasm imports the glue under its new name.
+    let wat = String::from_utf8_lossy(&out.files["out.module.wat"]).into_owned();
+    assert!(wat.contains("(import \"./out.mjs\" \"__wbg_f_log_log_n\""));
+}
+
+#[test]
+fn out_name() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm.clone()), "my_crate-1f2e3d")
+        .out_name("mylib")
+        .typescript(true)
+        .emit_wat(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    let files = out.files.keys().map(|s| &s[..]).collect::<Vec<_>>();
+    assert_eq!(files, ["mylib.d.ts", "mylib.js", "mylib_bg.d.ts", "mylib_bg.wasm",
+                       "mylib_bg.wat"]);
+    let js = String::from_utf8_lossy(&out.files["mylib.js"]).into_owned();
+    assert!(js.contains("import * as wasm from './mylib_bg';"));
+    let wat = String::from_utf8_lossy(&out.files["mylib_bg.wat"]).into_owned();
+    assert!(wat.contains("(import \"./mylib\" \"__wbg_f_log_log_n\""));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .add_input_path("worker.wasm")
+        .out_name("mylib");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert_eq!(err, "`out_name` can't be used with multiple inputs, see `output_names`");
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_path("out.wasm").out_name("dist/mylib");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert_eq!(err, "the name `dist/mylib` given to `out_name` isn't a file name");
+}
+
+#[test]
+fn conflicting_output_names() {
+    let err = Bindgen::new()
+        .input_path("out.wasm")
+        .output_names(OutputNames::new().wasm("{stem}.wasm"))
+        .validate()
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "the TypeScript declarations and the wasm's TypeScript declarations \
+                     would both be named `{stem}.d.ts`");
+
+    let err = Bindgen::new()
+        .input_path("out.wasm")
+        .output_names(OutputNames::new().js("dist/{stem}.js"))
+        .validate()
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("`dist/{stem}.js`"), "unexpected error: {}", err);
+    assert!(err.ends_with("isn't a file name"), "unexpected error: {}", err);
+}
+
+#[test]
+fn wat_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .emit_wat(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // The descriptors are gone by now, as the rendering is of the output.
+    let wat = &out.files["out_bg.wat"];
+    assert!(!String::from_utf8_lossy(wat).contains("__wbindgen_describe"));
+    assert_snapshot("representative_bg.wat", wat);
+}
+
+#[test]
+fn source_map() {
+    let location = |line: u32| {
+        Some(Location { file: "src/lib.rs".to_string(), line, column: 11 })
+    };
+    let mut program = representative_program(Some("./logger"));
+    program.exports[0].function.location = location(3);
+    program.exports[2].function.location = location(12);
+    if let ImportKind::Function(ref mut f) = program.imports[0].kind {
+        f.function.location = location(20);
+    }
+    let wasm = with_program(REPRESENTATIVE_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .debug(true)
+        .nodejs(true)
+        .source_map(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.js.map", &out.files["out.js.map"]);
+    let js = String::from_utf8_lossy(&out.files["out.js"]);
+    assert!(js.ends_with("\n//# sourceMappingURL=out.js.map\n"), "{}", js);
+    out.files.insert("logger.js".to_string(), b"exports.log = function(n) { throw new Error('logged ' + n); };\n".to_vec());
+
+    // The frames of the shims are of the Rust functions, while those of the
+    // helpers they call aren't mapped.
+    run_node("source-map", &out.files, &["--enable-source-maps"], r#"
+            const assert = require('assert');
+            const path = require('path');
+            const out = require(path.join(process.argv[1], 'out.js'));
+            const frames = f => {
+                try {
+                    f();
+                } catch (e) {
+                    return e.stack.split('\n').filter(l => l.includes(process.argv[1]));
+                }
+                assert.fail('expected an exception');
+            };
+            const halve = frames(() => out.halve('x'));
+            assert.ok(halve[0].includes('out.js:'), halve.join('\n'));
+            assert.ok(/src.lib\.rs:3:\d+/.test(halve[1]), halve.join('\n'));
+            const log = frames(() => out.__wbg_f_log_log_n(1));
+            assert.ok(log[0].includes('logger.js:'), log.join('\n'));
+            assert.ok(/src.lib\.rs:20:\d+/.test(log[1]), log.join('\n'));
+        "#);
+}
+
+#[test]
+fn removed_sections() {
+    use cli::parity_wasm::elements::{Module, Section};
+
+    let wasm = representative_wasm(Some("./logger"));
+    let wasm = with_custom_section(&wasm, "producers", &[0]);
+    let wasm = with_custom_section(&wasm, "name", &[1, 4, 1, 0, 1, b'f']);
+    let wasm = with_custom_section(&wasm, ".debug_info", &[0]);
+    let sections = |configure: &dyn Fn(&mut Bindgen)| {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_bytes(wasm.clone(), "out");
+        configure(&mut bindgen);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+        let module: Module = cli::parity_wasm::deserialize_buffer(&out.files["out_bg.wasm"]).unwrap();
+        let mut names = module.sections()
+            .iter()
+            .filter_map(|s| match *s {
+                Section::Custom(ref s) => Some(s.name().to_string()),
+                Section::Name(_) => Some("name".to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(sections(&|_| {}), ["producers"]);
+    assert_eq!(sections(&|b| { b.keep_debug(true); }), [".debug_info", "name", "producers"]);
+    assert_eq!(sections(&|b| { b.remove_producers_section(true); }), Vec::<String>::new());
+    assert_eq!(sections(&|b| {
+        b.keep_debug(true)
+            .remove_name_section(true)
+            .remove_producers_section(true);
+    }), [".debug_info"]);
+}
+
+#[test]
+fn preserved_code_offsets() {
+    let wasm = representative_wasm(Some("./logger"));
+    let wasm = with_custom_section(&wasm, ".debug_info", &[0]);
+    let code = |wasm: &[u8]| {
+        let mut pos = 8;
+        while pos < wasm.len() {
+            let id = wasm[pos];
+            let (mut size, mut shift) = (0, 0);
+            loop {
+                pos += 1;
+                size |= ((wasm[pos] & 0x7f) as usize) << shift;
+                shift += 7;
+                if wasm[pos] & 0x80 == 0 {
+                    break
+                }
+            }
+            pos += 1;
+            if id == 10 {
+                return wasm[pos..pos + size].to_vec()
+            }
+            pos += size;
+        }
+        panic!("no code section")
+    };
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm.clone(), "out")
+        .preserve_code_offsets(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert!(code(&out.files["out_bg.wasm"]) == code(&wasm));
+    let stats = bindgen.stats().unwrap();
+    assert!(stats.debug_sections_kept);
+    assert!(stats.code_offsets_preserved);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .preserve_code_offsets(true)
+        .build_id(true);
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("preserve_code_offsets"), "{}", err);
+}
+
+#[test]
+#[cfg(unix)]
+fn wasm_opt() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A stand-in for `wasm-opt` which records its arguments and appends a
+    // custom section to the module to show its output is used.
+    let dir = super::root().join("wasm-opt");
+    drop(fs::remove_dir_all(&dir));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("wasm-opt");
+    File::create(&script).unwrap().write_all(format!("#!/bin/sh
+echo \"$@\" > {args}
+cp \"$2\" \"$4\"
+printf '\\000\\004\\003opt' >> \"$4\"
+", args = dir.join("args").display()).as_bytes()).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    env::set_var("WASM_OPT", &script);
+
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm.clone(), "out")
+        .wasm_opt("z");
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert!(out.files["out_bg.wasm"].ends_with(b"\0\x04\x03opt"));
+    let mut args = String::new();
+    File::open(dir.join("args")).unwrap().read_to_string(&mut args).unwrap();
+    assert!(args.starts_with("-Oz "), "{}", args);
+    assert!(!args.contains("--debuginfo"), "{}", args);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .wasm_opt("5");
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("isn't a wasm-opt level"), "{}", err);
+}
+
+#[test]
+fn gc_renumbers_functions() {
+    use cli::parity_wasm::elements::{Module, NameSection, Section};
+
+    // Names `halve`, the first function after the two imports.
+    let wasm = representative_wasm(Some("./logger"));
+    let wasm = with_custom_section(&wasm, "name", &[1, 8, 1, 2, 5, b'h', b'a', b'l', b'v', b'e']);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out")
+        .keep_debug(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // `__wbindgen_describe` and the descriptor shims calling it are gone.
+    let gc = bindgen.stats().unwrap().gc.clone().unwrap();
+    assert_eq!(gc.functions_removed, 6);
+    let module: Module = cli::parity_wasm::deserialize_buffer(&out.files["out_bg.wasm"]).unwrap();
+    let module = module.parse_names().unwrap();
+    let imports = module.import_section().unwrap().entries();
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].field(), "__wbg_f_log_log_n");
+    assert_eq!(module.code_section().unwrap().bodies().len(), 5);
+    let names = module.sections()
+        .iter()
+        .filter_map(|s| match *s {
+            Section::Name(NameSection::Function(ref f)) => Some(f.names()),
+            _ => None,
+        })
+        .next()
+        .unwrap();
+    assert_eq!(names.get(1).map(|s| &s[..]), Some("halve"));
+    assert_eq!(names.get(2), None);
+}
+
+#[test]
+fn build_id() {
+    use cli::parity_wasm::elements::{Module, Section};
+
+    let generate = |wasm: Vec<u8>| {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_reader(Cursor::new(wasm), "out")
+            .debug(true)
+            .build_id(true)
+            .nodejs(true);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+        out.files
+    };
+    let files = generate(anyref_wasm());
+    let other = generate(with_program(&described_wasm(&[], &[]), &program("other")));
+    let js = String::from_utf8(files["out.js"].clone()).unwrap();
+    assert!(js.contains("__wbg_check_wasm();"), "the glue doesn't check the wasm");
+
+    // The debug mode check of the exports only fires for a wasm of the same
+    // build which lost some of them afterwards.
+    let mut stripped: Module = cli::parity_wasm::deserialize_buffer(&files["out_bg.wasm"]).unwrap();
+    for section in stripped.sections_mut() {
+        if let Section::Export(ref mut s) = *section {
+            s.entries_mut().retain(|e| e.field() != "__wbindgen_free");
+        }
+    }
+    let stripped = cli::parity_wasm::serialize(stripped).unwrap();
+
+    let root = super::root().join("build-id");
+    let wasms = [
+        ("same", &files["out_bg.wasm"]),
+        ("other", &other["out_bg.wasm"]),
+        ("stripped", &stripped),
+    ];
+    for &(dir, wasm) in wasms.iter() {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files.iter() {
+            let contents = if name == "out_bg.wasm" { wasm } else { contents };
+            File::create(dir.join(name)).unwrap().write_all(contents).unwrap();
+        }
+    }
+
+    let mut cmd = Command::new("node");
+    cmd.arg("-e")
+        .arg(r#"
+            const assert = require('assert');
+            const path = require('path');
+            global.host = {};
+            const load = dir => require(path.join(process.argv[1], dir, 'out.js'));
+            load('same');
+            assert.throws(() => load('other'),
+                          /^Error: wasm-bindgen: JS glue and wasm module are from different builds \(js=[0-9a-f]{8}, wasm=[0-9a-f]{8}\)$/);
+            assert.throws(() => load('stripped'),
+                          /^Error: wasm-bindgen: the wasm module is missing exports the JS glue uses: __wbindgen_free$/);
+        "#)
+        .arg(&root);
+    super::run(&mut cmd, "node");
+}
+
+#[test]
+fn rewritten_export_types() {
+    use cli::parity_wasm::elements::{FunctionType, Opcode, ValueType};
+    use super::fixtures::words::*;
+
+    // The `_bg.d.ts` has the types of the exports as they are once rewritten,
+    // for `multi_value` the pointer and length of a returned string...
+    let exports = [("returns_string".to_string(), function(&[], Some(&[STRING])))];
+    let ty = FunctionType::new(Vec::new(), Some(ValueType::I32));
+    let wasm = with_body(&described_wasm(&exports, &[]), "returns_string", ty,
+                         vec![Opcode::I32Const(16), Opcode::End]);
+    let mut strings = program("multi_value");
+    strings.exports.push(export("returns_string"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&wasm, &strings), "out")
+        .multi_value(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let js = String::from_utf8(out.files["out.js"].clone()).unwrap();
+    assert!(js.contains("const [ret, len] = wasm.returns_string();"), "{}", js);
+    assert!(!js.contains("getGlobalArgument"), "{}", js);
+    let ts = String::from_utf8(out.files["out_bg.d.ts"].clone()).unwrap();
+    assert!(ts.contains("export function returns_string(): [number, number];"), "{}", ts);
+
+    // ...and for `reference_types` the JS values passed as they are.
+    let exports = [
+        ("relay".to_string(), function(&[&[ANYREF], &[REF, ANYREF]], Some(&[ANYREF]))),
+    ];
+    let ty = FunctionType::new(vec![ValueType::I32, ValueType::I32], Some(ValueType::I32));
+    let wasm = with_body(&described_wasm(&exports, &[]), "relay", ty,
+                         vec![Opcode::GetLocal(0), Opcode::End]);
+    let mut relay = program("reference_types");
+    relay.exports.push(export("relay"));
+    let wasm = with_program(&wasm, &relay);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm.clone(), "out")
+        .debug(true)
+        .reference_types(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let js = String::from_utf8(out.files["out.js"].clone()).unwrap();
+    assert!(js.contains("wasm.relay(arg0, arg1)"), "{}", js);
+    assert!(!js.contains("addBorrowedObject"), "{}", js);
+    let ts = String::from_utf8(out.files["out_bg.d.ts"].clone()).unwrap();
+    assert!(ts.contains("export function relay(a: any, b: any): any;"), "{}", ts);
+    assert!(ts.contains("export function __wbindgen_externref_alloc(a: any): number;"),
+            "{}", ts);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out").reference_types(true).multi_value(true);
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Config(ref s) => {
+            assert_eq!(s, "`reference_types` and `multi_value` both rewrite the types of \
+                           exports, they can't be combined");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
@@ -0,0 +1,134 @@
+use super::project;
+
+#[test]
+fn async_exports() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(async)]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+
+            #[wasm_bindgen(async)]
+            pub fn fail() {
+                wasm_bindgen::throw("failed");
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                cnt: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                pub fn new() -> Counter {
+                    Counter { cnt: 0 }
+                }
+
+                #[wasm_bindgen(async)]
+                pub fn bump(&mut self) -> u32 {
+                    self.cnt += 1;
+                    self.cnt
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { add, fail, Counter } from "./out";
+
+            export async function test() {
+                const p = add(1, 2);
+                assert(p instanceof Promise);
+                assert.strictEqual(await p, 3);
+
+                let rejected = false;
+                try {
+                    await fail();
+                } catch (e) {
+                    rejected = true;
+                }
+                assert(rejected);
+
+                const c = Counter.new();
+                assert.strictEqual(await c.bump(), 1);
+                assert.strictEqual(await c.bump(), 2);
+                c.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn then() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn resolved() -> JsValue;
+                fn rejected() -> JsValue;
+                fn not_a_promise() -> JsValue;
+                fn done(ok: bool, val: JsValue);
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                wasm_bindgen::promise::then(&resolved(), |r| {
+                    let v = r.unwrap();
+                    assert_eq!(v.as_f64(), Some(1.0));
+                    done(true, v);
+                });
+                wasm_bindgen::promise::then(&rejected(), |r| {
+                    done(false, r.unwrap_err());
+                });
+                wasm_bindgen::promise::then(&not_a_promise(), |r| {
+                    done(true, r.unwrap());
+                });
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { run } from "./out";
+
+            const results: any[] = [];
+
+            export function resolved(): any {
+                return Promise.resolve(1);
+            }
+
+            export function rejected(): any {
+                return Promise.reject("nope");
+            }
+
+            export function not_a_promise(): any {
+                return "plain";
+            }
+
+            export function done(ok: boolean, val: any) {
+                results.push([ok, val]);
+            }
+
+            export async function test() {
+                run();
+                assert.strictEqual(results.length, 0);
+                await new Promise(resolve => setTimeout(resolve, 0));
+                assert.deepStrictEqual(results, [
+                    [true, 1],
+                    [false, "nope"],
+                    [true, "plain"],
+                ]);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,104 @@
+use super::project;
+
+#[test]
+fn works() {
+    project()
+        .serde(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+            #[macro_use]
+            extern crate serde_derive;
+
+            use std::collections::HashMap;
+            use wasm_bindgen::prelude::*;
+
+            #[derive(Deserialize, Serialize, Debug, PartialEq)]
+            pub struct Config {
+                name: String,
+                count: u32,
+                nested: Nested,
+                tags: Vec<String>,
+            }
+
+            #[derive(Deserialize, Serialize, Debug, PartialEq)]
+            pub struct Nested {
+                depth: Option<Box<Nested>>,
+                values: HashMap<String, f64>,
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn verify(a: JsValue) -> JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let mut values = HashMap::new();
+                values.insert("a".to_string(), 1.5);
+                let config = Config {
+                    name: "foo".to_string(),
+                    count: 3,
+                    nested: Nested {
+                        depth: Some(Box::new(Nested {
+                            depth: None,
+                            values: HashMap::new(),
+                        })),
+                        values,
+                    },
+                    tags: vec!["x".to_string(), "y".to_string()],
+                };
+                let js = JsValue::from_serde(&config).unwrap();
+                let back = verify(js);
+                let back: Config = back.into_serde().unwrap();
+                assert_eq!(back.name, "bar");
+                assert_eq!(back.count, 4);
+                assert_eq!(back.nested.depth.unwrap().depth, None);
+                assert_eq!(back.tags, ["x", "y", "z"]);
+            }
+
+            #[wasm_bindgen]
+            pub fn check_undefined(a: JsValue) {
+                assert!(a.is_undefined());
+                let x: Option<u32> = a.into_serde().unwrap();
+                assert_eq!(x, None);
+                assert!(a.into_serde::<u32>().is_err());
+            }
+
+            #[wasm_bindgen]
+            pub fn invalid(a: JsValue) {
+                assert!(a.into_serde::<Config>().is_err());
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { run, check_undefined, invalid } from "./out";
+
+            export function verify(a: any): any {
+                assert.deepStrictEqual(a, {
+                    name: "foo",
+                    count: 3,
+                    nested: {
+                        depth: {
+                            depth: null,
+                            values: {},
+                        },
+                        values: { a: 1.5 },
+                    },
+                    tags: ["x", "y"],
+                });
+                a.name = "bar";
+                a.count += 1;
+                a.tags.push("z");
+                return a;
+            }
+
+            export function test() {
+                run();
+                check_undefined(undefined);
+                invalid({ name: 1 });
+            }
+        "#)
+        .test();
+}
@@ -1,4 +1,15 @@
-use super::project;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process::Command;
+use std::time::Duration;
+
+use cli::Bindgen;
+use cli::parity_wasm::elements::{DataSection, DataSegment, FunctionType, InitExpr, MemoryType};
+use cli::parity_wasm::elements::{Module, Opcode, Section, ValueType};
+
+use super::{MemoryOutput, project};
+use super::fixtures::*;
 
 #[test]
 fn add() {
@@ -53,6 +64,145 @@ fn add() {
         .test();
 }
 
+#[test]
+fn i64_as_bigint() {
+    project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn twice(a: i64) -> i64;
+            }
+
+            #[wasm_bindgen]
+            pub fn negate_twice(a: i64) -> i64 {
+                -twice(a)
+            }
+
+            #[wasm_bindgen]
+            pub fn as_u64(a: i64) -> u64 {
+                a as u64
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+
+            module.exports.twice = a => a * 2n;
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.negate_twice(21n), -42n);
+                assert.strictEqual(wasm.negate_twice(-(2n ** 40n)), 2n ** 41n);
+                assert.strictEqual(wasm.as_u64(-1n), 2n ** 64n - 1n);
+            };
+        "#)
+        .test();
+}
+
+/// A module whose `negate_twice` negates what the imported `twice` makes of
+/// its argument, for the hand-written round trips of `i64`s.
+fn negate_twice_wasm() -> Vec<u8> {
+    use super::fixtures::words::*;
+
+    let exports = [("negate_twice".to_string(), function(&[&[I64]], Some(&[I64])))];
+    let imports = [("twice".to_string(), function(&[&[I64]], Some(&[I64])))];
+    let ty = FunctionType::new(vec![ValueType::I64], Some(ValueType::I64));
+    let wasm = with_body(&described_wasm(&exports, &imports), "negate_twice", ty.clone(),
+                         vec![Opcode::I64Const(0), Opcode::GetLocal(0), Opcode::Call(1),
+                              Opcode::I64Sub, Opcode::End]);
+    let mut negate = program("i64");
+    negate.exports.push(export("negate_twice"));
+    negate.imports.push(host_import("twice"));
+    with_program(&with_import_type(&wasm, "twice", ty), &negate)
+}
+
+fn negate_twice_glue(split_i64: bool) -> MemoryOutput {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(negate_twice_wasm(), "out")
+        .nodejs(true)
+        .debug(true)
+        .split_i64(split_i64);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let js = String::from_utf8_lossy(&out.files["out.js"]).into_owned();
+    assert_eq!(js.contains("BigInt"), !split_i64, "{}", js);
+    out
+}
+
+#[test]
+fn i64_as_bigint_fixture() {
+    run_node("i64", &negate_twice_glue(false).files, &[], r#"
+        const assert = require('assert');
+        const path = require('path');
+        global.host = { twice: x => x * 2n };
+        const out = require(path.join(process.argv[1], 'out.js'));
+        assert.strictEqual(out.negate_twice(21n), -42n);
+        assert.strictEqual(out.negate_twice(-(2n ** 40n)), 2n ** 41n);
+    "#);
+}
+
+#[test]
+fn split_i64() {
+    project()
+        .node(true)
+        .split_i64(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn twice(a: i64) -> i64;
+            }
+
+            #[wasm_bindgen]
+            pub fn negate_twice(a: i64) -> i64 {
+                -twice(a)
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            module.exports.twice = a => a * 2;
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                const js = fs.readFileSync(path.join(__dirname, 'out.js'), 'utf8');
+                assert.ok(!js.includes('BigInt'));
+                assert.strictEqual(wasm.negate_twice(21), -42);
+                assert.strictEqual(wasm.negate_twice(-(2 ** 40)), 2 ** 41);
+                assert.strictEqual(wasm.negate_twice(2 ** 40 + 3), -(2 ** 41 + 6));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn split_i64_fixture() {
+    run_node("split-i64", &negate_twice_glue(true).files, &[], r#"
+        const assert = require('assert');
+        const path = require('path');
+        global.host = { twice: x => x * 2 };
+        const out = require(path.join(process.argv[1], 'out.js'));
+        assert.strictEqual(out.negate_twice(21), -42);
+        assert.strictEqual(out.negate_twice(-(2 ** 40)), 2 ** 41);
+        assert.strictEqual(out.negate_twice(2 ** 40 + 3), -(2 ** 41 + 6));
+    "#);
+}
+
 #[test]
 fn string_arguments() {
     project()
@@ -119,6 +269,74 @@ fn return_a_string() {
         .test();
 }
 
+#[test]
+fn multi_value_strings() {
+    project()
+        .node(true)
+        .multi_value(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn clone(a: &str) -> String {
+                a.to_string()
+            }
+
+            #[wasm_bindgen]
+            pub fn bytes(n: u8) -> Vec<u8> {
+                (0..n).collect()
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.clone('foo'), 'foo');
+                assert.strictEqual(wasm.clone(''), '');
+                assert.deepStrictEqual(Array.from(wasm.bytes(3)), [0, 1, 2]);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn multi_value_strings_fixture() {
+    use super::fixtures::words::*;
+
+    // `returns_string` returns "hi", leaving its length in the global
+    // argument.
+    let exports = [("returns_string".to_string(), function(&[], Some(&[STRING])))];
+    let ty = FunctionType::new(Vec::new(), Some(ValueType::I32));
+    let wasm = with_body(&described_wasm(&exports, &[]), "returns_string", ty, vec![
+        Opcode::I32Const(8), Opcode::I32Const(2), Opcode::I32Store(2, 0),
+        Opcode::I32Const(16), Opcode::I32Const(b'h' as i32), Opcode::I32Store8(0, 0),
+        Opcode::I32Const(17), Opcode::I32Const(b'i' as i32), Opcode::I32Store8(0, 0),
+        Opcode::I32Const(16), Opcode::End,
+    ]);
+    let mut strings = program("multi_value");
+    strings.exports.push(export("returns_string"));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(with_program(&wasm, &strings), "out")
+        .nodejs(true)
+        .multi_value(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    run_node("multi-value", &out.files, &[], r#"
+        const assert = require('assert');
+        const path = require('path');
+        const out = require(path.join(process.argv[1], 'out.js'));
+        const memory = require(path.join(process.argv[1], 'out_bg')).memory;
+        assert.strictEqual(out.returns_string(), 'hi');
+        assert.strictEqual(new Uint32Array(memory.buffer)[0], 1);
+    "#);
+}
+
 #[test]
 fn exceptions() {
     project()
@@ -150,6 +368,379 @@ fn exceptions() {
         .test();
 }
 
+#[test]
+fn result_errors_are_thrown() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn checked_half(n: u32) -> Result<u32, JsValue> {
+                if n % 2 == 0 {
+                    Ok(n / 2)
+                } else {
+                    Err(JsValue::from_str("odd"))
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn check_name(name: &str) -> Result<(), JsValue> {
+                if name.is_empty() {
+                    return Err(JsValue::from(3u32))
+                }
+                Ok(())
+            }
+
+            #[wasm_bindgen]
+            pub fn greeting(name: String) -> Result<String, JsValue> {
+                if name.is_empty() {
+                    Err(JsValue::null())
+                } else {
+                    Ok(format!("hi {}", name))
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            function thrown(f: () => void): any {
+                try {
+                    f();
+                } catch (e) {
+                    return e;
+                }
+                throw new Error("nothing was thrown");
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.checked_half(4), 2);
+                assert.strictEqual(thrown(() => wasm.checked_half(3)), "odd");
+
+                assert.strictEqual(wasm.check_name("a"), undefined);
+                assert.strictEqual(thrown(() => wasm.check_name("")), 3);
+
+                assert.strictEqual(wasm.greeting("x"), "hi x");
+                assert.strictEqual(thrown(() => wasm.greeting("")), null);
+                assert.strictEqual(wasm.greeting("y"), "hi y");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn panic_hook() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn boom(n: u32) {
+                wasm_bindgen::set_panic_hook();
+                panic!("boom {}", n);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.throws(() => wasm.boom(1), /boom 1.*src\/lib\.rs:\d+:\d+/);
+
+                const panics: Error[] = [];
+                wasm.setPanicHandler(e => panics.push(e));
+                assert.throws(() => wasm.boom(2), /boom 2/);
+                assert.strictEqual(panics.length, 1);
+                assert.ok(panics[0] instanceof Error);
+                assert.ok(/boom 2/.test(panics[0].message));
+                wasm.setPanicHandler(null);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn debug_argument_checks() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            pub struct Bar {}
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo {}
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Bar {
+                pub fn new() -> Bar {
+                    Bar {}
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn small(_a: u8, _b: i16) {}
+
+            #[wasm_bindgen]
+            pub fn flag(_a: u32, _b: bool) {}
+
+            #[wasm_bindgen]
+            pub fn take_foo(_a: &Foo) {}
+
+            #[wasm_bindgen]
+            pub fn slice(_a: &[u32]) {}
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                wasm.small(255, -32768);
+                assert.throws(() => wasm.small(256, 0), /argument 0 of `small`/);
+                assert.throws(() => wasm.small(-1, 0), /expected an integer argument/);
+                assert.throws(() => wasm.small(1.5, 0), /expected an integer argument/);
+                assert.throws(() => wasm.small(0, 40000), /argument 1 of `small`/);
+
+                assert.throws(() => wasm.flag(1, 1), /expected a boolean argument, found number \(argument 1 of `flag`\)/);
+                assert.throws(() => wasm.flag('1', true), /expected a number argument, found string/);
+
+                const foo = wasm.Foo.new();
+                const bar = wasm.Bar.new();
+                wasm.take_foo(foo);
+                assert.throws(() => wasm.take_foo(bar), /expected instance of Foo \(argument 0 of `take_foo`\)/);
+                foo.free();
+                bar.free();
+
+                wasm.slice(new Uint32Array([1, 2]));
+                wasm.slice([1, 2]);
+                assert.throws(() => wasm.slice('ab'), /expected a Uint32Array or Array argument/);
+                assert.throws(() => wasm.slice([1, 'a']), /at index 1 \(argument 0 of `slice`\)/);
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+#[test]
+fn memory_growth() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn grow(mb: usize) {
+                // Leak a big allocation to force the wasm memory to grow,
+                // detaching any views JS has on the old buffer.
+                std::mem::forget(vec![1u8; mb << 20]);
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(a: &str) -> String {
+                grow(2);
+                format!("hello {}", a)
+            }
+
+            #[wasm_bindgen]
+            pub fn double(a: &[f64]) -> Vec<f64> {
+                grow(2);
+                a.iter().map(|x| x * 2.0).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(a: &[f32]) -> f32 {
+                a.iter().sum()
+            }
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.greet("a"), "hello a");
+                assert.strictEqual(wasm.greet("b"), "hello b");
+                assert.deepStrictEqual(wasm.double(new Float64Array([1, 2])), new Float64Array([2, 4]));
+                assert.strictEqual(wasm.sum(new Float32Array([1, 2])), 3);
+                wasm.grow(4);
+                assert.strictEqual(wasm.sum(new Float32Array([3, 4])), 7);
+                assert.deepStrictEqual(wasm.double(new Float64Array([3])), new Float64Array([6]));
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+#[test]
+fn large_module() {
+    // Checks that bindings for a module with a multi-megabyte data segment
+    // still work, `large_module_footprint` measures what they cost.
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            static DATA: [u8; 8 << 20] = [1; 8 << 20];
+
+            #[wasm_bindgen]
+            pub fn sum(start: usize, len: usize) -> u32 {
+                DATA[start..start + len].iter().map(|x| *x as u32).sum()
+            }
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.sum(0, 10), 10);
+                assert.strictEqual(wasm.sum(1 << 20, 1 << 10), 1 << 10);
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+/// The most time bindgen may spend on `large_module_footprint`'s module, as
+/// the sum of the times in its stats.
+const LARGE_MODULE_TIME: Duration = Duration::from_secs(2);
+
+/// The most the peak resident set of the process may grow by while bindgen
+/// runs on `large_module_footprint`'s module, in multiples of its size.
+const LARGE_MODULE_MEMORY: usize = 4;
+
+#[test]
+fn large_module_footprint() {
+    // The peak resident set is the one of the whole process, so the
+    // measurement is only meaningful in a process running this test alone.
+    if env::var_os("WASM_BINDGEN_LARGE_MODULE").is_none() {
+        let output = Command::new(env::current_exe().unwrap())
+            .arg("--exact")
+            .arg("simple::large_module_footprint")
+            .arg("--nocapture")
+            .env("WASM_BINDGEN_LARGE_MODULE", "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        println!("{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+        assert!(output.status.success());
+        assert!(stdout.contains("1 passed"), "the test didn't run");
+        return
+    }
+
+    let mut module: Module = cli::parity_wasm::deserialize_buffer(&described_wasm(&[], &[]))
+        .unwrap();
+    for section in module.sections_mut() {
+        if let Section::Memory(ref mut s) = *section {
+            *s.entries_mut() = vec![MemoryType::new(130, None)];
+        }
+    }
+    let offset = InitExpr::new(vec![Opcode::I32Const(1024), Opcode::End]);
+    let segment = DataSegment::new(0, offset, vec![1; 8 << 20]);
+    module.sections_mut().push(Section::Data(DataSection::with_entries(vec![segment])));
+    let wasm = with_program(&cli::parity_wasm::serialize(module).unwrap(), &program("large"));
+    let len = wasm.len();
+
+    let before = peak_rss();
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out").nodejs(true);
+    bindgen.generate_to(&mut MemoryOutput::default()).expect("failed to run bindgen");
+    let stats = bindgen.stats().unwrap();
+    println!("{}", stats);
+
+    let time = stats.parse_time + stats.descriptor_time + stats.js_time + stats.serialize_time;
+    assert!(time < LARGE_MODULE_TIME, "bindgen took {:?} for {} bytes", time, len);
+    if let (Some(before), Some(after)) = (before, peak_rss()) {
+        println!("peak rss:    {} -> {} bytes", before, after);
+        assert!(after - before < LARGE_MODULE_MEMORY * len,
+                "bindgen needed {} bytes for {} bytes", after - before, len);
+    }
+}
+
+/// The peak resident set of this process, as `VmHWM` in `/proc/self/status`,
+/// or `None` where there's no such file.
+fn peak_rss() -> Option<usize> {
+    let mut status = String::new();
+    File::open("/proc/self/status").ok()?.read_to_string(&mut status).ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb = line["VmHWM:".len()..].trim().trim_end_matches(" kB").parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
+
+#[test]
+fn no_gc() {
+    project()
+        .gc(false)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet(a: &str) -> String {
+                format!("hello {}", a)
+            }
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                pub a: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { a: 3 }
+                }
+            }
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.greet("a"), "hello a");
+                const foo = wasm.Foo.new();
+                assert.strictEqual(foo.a, 3);
+                foo.free();
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
 // #[test]
 // fn other_imports() {
 //     project()
@@ -287,3 +878,134 @@ fn no_std_class() {
         "#)
         .test();
 }
+
+#[test]
+fn unused_internals_not_exported() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import * as internals from "./out_bg";
+
+            export function test() {
+                assert.strictEqual(wasm.add(1, 2), 3);
+                const exports = internals as any;
+                assert.strictEqual(exports.__wbindgen_malloc, undefined);
+                assert.strictEqual(exports.__wbindgen_free, undefined);
+                assert.strictEqual(exports.__wbg_function_table, undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn reserved_words_get_a_suffix() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn new() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn delete(a: u32) -> u32 {
+                a + new()
+            }
+
+            #[wasm_bindgen]
+            pub fn default(s: &str) -> String {
+                format!("{}!", s)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { delete_, default_ } from "./out";
+
+            function create(): number {
+                return 3;
+            }
+
+            export { create as new };
+
+            export function test() {
+                assert.strictEqual(delete_(1), 4);
+                assert.strictEqual(default_("hi"), "hi!");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn interned_strings() {
+    project()
+        .string_interning(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn tag(name: &str);
+            }
+
+            #[wasm_bindgen]
+            pub fn render(n: u32) {
+                for _ in 0..n {
+                    tag(wasm_bindgen::intern("div"));
+                    tag(&"divs"[..3]);
+                    tag(&"divs"[1..]);
+                }
+                wasm_bindgen::unintern("div");
+                tag("div");
+            }
+
+            #[wasm_bindgen]
+            pub fn echo(s: &str) -> String {
+                s.to_string()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { render, echo } from "./out";
+
+            const TAGS: string[] = [];
+
+            export function tag(name: string) {
+                TAGS.push(name);
+            }
+
+            export function test() {
+                render(2);
+                assert.deepStrictEqual(TAGS, ["div", "div", "ivs", "div", "div", "ivs", "div"]);
+
+                for (let i = 0; i < 2000; i++)
+                    assert.strictEqual(echo("class"), "class");
+                for (let i = 0; i < 2000; i++)
+                    assert.strictEqual(echo(`attr${i}`), `attr${i}`);
+                assert.strictEqual(echo("class"), "class");
+                assert.strictEqual(echo(""), "");
+            }
+        "#)
+        .test();
+}
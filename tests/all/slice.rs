@@ -278,3 +278,159 @@ fn pass_array_works() {
         "#)
         .test();
 }
+
+#[test]
+fn pass_plain_arrays() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn sum(a: &[f64]) -> f64 {
+                a.iter().sum()
+            }
+
+            #[wasm_bindgen]
+            pub fn bytes(a: Vec<u8>) -> Vec<u8> {
+                a
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.sum([1, 2, 3.5]), 6.5);
+                assert.strictEqual(wasm.sum(new Float64Array([1, 2])), 3);
+                assert.deepStrictEqual(Array.from(wasm.bytes([1, 2, 255])), [1, 2, 255]);
+                assert.throws(() => wasm.sum([1, "2"] as any), /at index 1/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn return_views() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            static TABLE: [u32; 3] = [1, 2, 3];
+
+            #[wasm_bindgen]
+            pub fn table() -> &'static [u32] {
+                &TABLE
+            }
+
+            #[wasm_bindgen]
+            pub struct Buffer {
+                data: Vec<u8>,
+            }
+
+            #[wasm_bindgen]
+            impl Buffer {
+                pub fn new(len: u32) -> Buffer {
+                    Buffer { data: (0..len as u8).collect() }
+                }
+
+                pub fn data(&self) -> &[u8] {
+                    &self.data
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_bg";
+
+            export function test() {
+                assert.deepStrictEqual(Array.from(wasm.table()), [1, 2, 3]);
+
+                const buffer = wasm.Buffer.new(4);
+                const data = buffer.data();
+                assert.strictEqual(data.buffer, memory.buffer);
+                assert.deepStrictEqual(Array.from(data), [0, 1, 2, 3]);
+                const copy = data.slice();
+
+                buffer.data();
+                assert.throws(() => data[0], /after another call into wasm/);
+                assert.deepStrictEqual(Array.from(copy), [0, 1, 2, 3]);
+                buffer.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn mutable_slices_and_views() {
+    project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn js_sum(a: &[f32]) -> f32;
+                fn js_fill(a: &mut [u8], value: u8);
+            }
+
+            #[wasm_bindgen]
+            pub fn double(a: &mut [i32]) {
+                for x in a.iter_mut() {
+                    *x *= 2;
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn sum_in_js() -> f32 {
+                js_sum(&[1.0, 2.0, 3.5])
+            }
+
+            #[wasm_bindgen]
+            pub fn fill_in_js() -> Vec<u8> {
+                let mut data = vec![0; 4];
+                js_fill(&mut data, 7);
+                data
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_bg";
+
+            export function js_sum(a: Float32Array): number {
+                assert.ok(a instanceof Float32Array);
+                assert.strictEqual(a.buffer, memory.buffer);
+                return a.reduce((a, b) => a + b, 0);
+            }
+
+            export function js_fill(a: Uint8Array, value: number) {
+                assert.strictEqual(a.buffer, memory.buffer);
+                a.fill(value);
+            }
+
+            export function test() {
+                const typed = new Int32Array([1, 2, 3]);
+                wasm.double(typed);
+                assert.deepStrictEqual(Array.from(typed), [2, 4, 6]);
+                const plain = [1, -2];
+                wasm.double(plain);
+                assert.deepStrictEqual(plain, [2, -4]);
+
+                assert.strictEqual(wasm.sum_in_js(), 6.5);
+                assert.deepStrictEqual(Array.from(wasm.fill_in_js()), [7, 7, 7, 7]);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,213 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::process::Command;
+
+use cli::{metadata, Bindgen, EsVersion};
+use serde_json;
+use shared;
+
+use super::MemoryOutput;
+use super::fixtures::*;
+
+#[test]
+fn representative_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .debug(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.js", &out.files["out.js"]);
+    assert_snapshot("representative.d.ts", &out.files["out.d.ts"]);
+}
+
+#[test]
+fn minified_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .debug(true)
+        .minify_js(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    let js = &out.files["out.js"];
+    assert_snapshot("representative.min.js", js);
+    let stats = bindgen.stats().unwrap();
+    assert_eq!(stats.js_bytes, js.len());
+    assert!(stats.js_bytes < stats.unminified_js_bytes);
+}
+
+#[test]
+fn minified_no_modules() {
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(representative_wasm(None)), "out")
+        .no_modules(true)
+        .minify_js(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let js = String::from_utf8(out.files["out.js"].clone()).unwrap();
+    assert!(js.contains(".split(/[?#]/)[0].replace(/[^\\/]*$/,'')"), "{}", js);
+
+    // The regular expressions finding the wasm next to the script still work.
+    run_node("minified-no-modules", &out.files, &[], r#"
+        const assert = require('assert');
+        const fs = require('fs');
+        const path = require('path');
+        const vm = require('vm');
+        const dir = process.argv[1];
+        const bytes = fs.readFileSync(path.join(dir, 'out_bg.wasm'));
+        let fetched;
+        const ctx = vm.createContext({
+            WebAssembly,
+            log: () => {},
+            document: { currentScript: { src: 'https://example.com/pkg/out.js?v=1#top' } },
+            fetch: url => {
+                fetched = url;
+                return Promise.resolve(new Response(bytes, {
+                    headers: { 'content-type': 'application/wasm' },
+                }));
+            },
+        });
+        vm.runInContext('self = this', ctx);
+        vm.runInContext(fs.readFileSync(path.join(dir, 'out.js'), 'utf8'), ctx);
+        ctx.self.wasm_bindgen()
+            .then(bindings => {
+                assert.strictEqual(fetched, 'https://example.com/pkg/out_bg.wasm');
+                assert.strictEqual(bindings.halve(3), 1.5);
+            })
+            .catch(e => { console.error(e); process.exit(1); });
+    "#);
+}
+
+#[test]
+fn deprecated_module() {
+    let mut program = representative_program(Some("./logger"));
+    program.exports[0].deprecated = Some("use `quarter` */ instead".to_string());
+    program.exports[1].constructor = Some("new".to_string());
+    program.exports[2].deprecated = Some(String::new());
+    program.structs[0].deprecated = Some("counters are\nno longer needed".to_string());
+    let wasm = with_program(REPRESENTATIVE_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm.clone()), "out")
+        .debug(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.deprecated.js", &out.files["out.js"]);
+    assert_snapshot("representative.deprecated.d.ts", &out.files["out.d.ts"]);
+
+    // Only debug mode warns, but the JSDoc is always there.
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out");
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    let js = String::from_utf8(out.files["out.js"].clone()).unwrap();
+    assert!(!js.contains("_warnDeprecated"));
+    assert!(js.contains("@deprecated use `quarter` *\\/ instead"));
+}
+
+#[test]
+fn metadata_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .emit_metadata(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // Unlike the rest, the crate's version changes with every release.
+    let json = String::from_utf8(out.files["out.bindings.json"].clone()).unwrap();
+    assert_snapshot("representative.bindings.json",
+                    json.replace(&shared::version(), "VERSION").as_bytes());
+
+    // Tools read the file with these types, so nothing may be lost going
+    // through them.
+    let bindings: metadata::Bindings = serde_json::from_str(&json).unwrap();
+    assert_eq!(bindings.schema, metadata::SCHEMA);
+    assert_eq!(serde_json::to_string_pretty(&bindings).unwrap() + "\n", json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_value(&bindings).unwrap(), value);
+}
+
+#[test]
+fn es5_module() {
+    let wasm = representative_wasm(None);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .no_modules(true)
+        .debug(true)
+        .es_target(EsVersion::Es5);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    let js = &out.files["out.js"];
+    assert_snapshot("representative.es5.js", js);
+
+    // Make sure no newer syntax slipped in by parsing it as ES5.
+    let root = super::root();
+    fs::create_dir_all(&root).unwrap();
+    let path = root.join("out.es5.js");
+    File::create(&path).unwrap().write_all(js).unwrap();
+    let mut cmd = Command::new("node");
+    cmd.arg("-e")
+        .arg("require('acorn').parse(require('fs').readFileSync(process.argv[1], 'utf8'), \
+              { ecmaVersion: 5 })")
+        .arg(&path)
+        .current_dir(env!("CARGO_MANIFEST_DIR"));
+    super::run(&mut cmd, "node");
+}
+
+#[test]
+fn split_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .debug(true)
+        .split_output(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.split.js", &out.files["out.js"]);
+    assert_snapshot("representative.split.d.ts", &out.files["out.d.ts"]);
+    assert_snapshot("representative.split_core.js", &out.files["out_core.js"]);
+    assert_snapshot("representative.split_core.d.ts", &out.files["out_core.d.ts"]);
+    assert_snapshot("representative.split_class_Counter.js",
+                    &out.files["out_class_Counter.js"]);
+    assert_snapshot("representative.split_class_Counter.d.ts",
+                    &out.files["out_class_Counter.d.ts"]);
+}
+
+#[test]
+fn prelude_and_epilogue() {
+    let modes: &[Mode] = &[
+        ("", |_| {}),
+        (".node", |b| { b.nodejs(true); }),
+        (".no-modules", |b| { b.no_modules(true); }),
+    ];
+    for &(suffix, mode) in modes.iter() {
+        let wasm = representative_wasm(if suffix == ".no-modules" {
+            None
+        } else {
+            Some("./logger")
+        });
+        let mut bindgen = Bindgen::new();
+        bindgen.input_reader(Cursor::new(wasm), "out")
+            .js_prelude("if (typeof Feature === 'undefined') {\n  polyfillFeature();\n}")
+            .js_epilogue("  registry.loaded('out');\n")
+            .ts_prelude("/// <reference types=\"feature\" />")
+            .ts_epilogue("declare const registry: Registry;")
+            .typescript(true);
+        mode(&mut bindgen);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+        assert_snapshot(&format!("representative.prelude{}.js", suffix), &out.files["out.js"]);
+        assert_snapshot(&format!("representative.prelude{}.d.ts", suffix),
+                        &out.files["out.d.ts"]);
+    }
+}
@@ -0,0 +1,23 @@
+/* tslint:disable */
+export function takes_anyref(arg0: any): void;
+
+export function returns_anyref(): any;
+
+export function takes_ref_anyref(arg0: any): void;
+
+export function returns_ref_anyref(): any;
+
+export function takes_ref_anyref_and_str(arg0: any, arg1: string): void;
+
+/**
+ * Counts the JS values owned by wasm which haven't been dropped yet (`live`),
+ * the room for them before the heap grows (`capacity`) and the values only
+ * borrowed for the calls into wasm in progress (`borrowed`). With `sample`,
+ * also lists the constructor names of up to that many of the live values.
+ */
+export function debugObjectStats(sample?: number): {
+    live: number;
+    capacity: number;
+    borrowed: number;
+    sample: string[];
+};
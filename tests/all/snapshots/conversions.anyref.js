@@ -0,0 +1,216 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+let slab = [{ obj: undefined }, { obj: null }, { obj: true }, { obj: false }];
+for (let i = 4; i < 132; i++)
+    slab.push(i + 1);
+
+// Owned entries of the slab which haven't been dropped yet,
+// see `debugObjectStats`.
+let heap_live = 0;
+
+let slab_next = 4;
+
+function addHeapObject(obj) {
+    if (obj === undefined)
+        return 0;
+    if (obj === null)
+        return 2;
+    if (obj === true)
+        return 4;
+    if (obj === false)
+        return 6;
+
+    if (slab_next === slab.length)
+        slab.push(slab.length + 1);
+    const idx = slab_next;
+    const next = slab[idx];
+
+    if (typeof(next) !== 'number')
+        throw new Error('corrupt slab');
+    slab_next = next;
+    heap_live++;
+
+    if (heap_live === 10000)
+        console.warn('wasm holds on to 10000 JS values, which may be a leak, ' +
+            'see `debugObjectStats()`');
+
+    slab[idx] = { obj: obj, cnt: 1 };
+    return idx << 1;
+}
+
+export function takes_anyref(arg0) {
+    return wasm.takes_anyref(addHeapObject(arg0));
+}
+
+let stack = new Array(32);
+let stack_pointer = 0;
+
+function getObject(idx) {
+    if ((idx & 1) === 1) {
+        return stack[idx >> 1];
+    } else {
+        const val = slab[idx >> 1];
+
+        if (typeof(val) === 'number')
+            throw new Error('corrupt slab');
+        return val.obj;
+    }
+}
+
+function dropRef(idx) {
+    if ((idx & 1) === 1)
+        throw new Error('cannot drop ref of stack objects');
+
+    // Reserved values are never freed.
+    if (idx < 8)
+        return;
+
+    let obj = slab[idx >> 1];
+
+    if (typeof(obj) === 'number')
+        throw new Error('corrupt slab');
+    obj.cnt -= 1;
+    if (obj.cnt > 0)
+        return;
+    heap_live--;
+
+    // If we hit 0 then free up our space in the slab
+    slab[idx >> 1] = slab_next;
+    slab_next = idx >> 1;
+}
+
+function takeObject(idx) {
+    const ret = getObject(idx);
+    dropRef(idx);
+    return ret;
+}
+
+export function returns_anyref() {
+    return takeObject(wasm.returns_anyref());
+}
+
+function addBorrowedObject(obj) {
+    stack[stack_pointer] = obj;
+    return (stack_pointer++ << 1) | 1;
+}
+
+function dropBorrowedObjects(sp) {
+    while (stack_pointer > sp)
+        stack[--stack_pointer] = undefined;
+}
+
+export function takes_ref_anyref(arg0) {
+    const sp = stack_pointer;
+    try {
+        return wasm.takes_ref_anyref(addBorrowedObject(arg0));
+    } finally {
+        dropBorrowedObjects(sp);
+    }
+}
+
+export function returns_ref_anyref() {
+    return getObject(wasm.returns_ref_anyref());
+}
+
+function _assertString(s, what) {
+    if (typeof(s) !== 'string')
+        throw new Error('expected a string argument, found ' + typeof(s) + ' (' + what + ')');
+}
+
+const TextEncoder = typeof self === 'object' && self.TextEncoder
+    ? self.TextEncoder
+    : require('util').TextEncoder;
+
+let cachedEncoder = new TextEncoder('utf-8');
+
+let cachedUint8Memory = null;
+function getUint8Memory() {
+    if (cachedUint8Memory === null ||
+            cachedUint8Memory.buffer !== wasm.memory.buffer)
+    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+    return cachedUint8Memory;
+}
+
+function passStringToWasm(arg) {
+    if (typeof(arg) !== 'string')
+        throw new Error('expected a string argument');
+
+    const buf = cachedEncoder.encode(arg);
+    const ptr = wasm.__wbindgen_malloc(buf.length);
+    getUint8Memory().set(buf, ptr);
+    return [ptr, buf.length];
+}
+
+let cachedUint32Memory = null;
+function getUint32Memory() {
+    if (cachedUint32Memory === null ||
+            cachedUint32Memory.buffer !== wasm.memory.buffer)
+    cachedUint32Memory = new Uint32Array(wasm.memory.buffer);
+    return cachedUint32Memory;
+}
+
+let cachedGlobalArgumentPtr = null;
+function globalArgumentPtr() {
+    if (cachedGlobalArgumentPtr === null)
+        cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+    return cachedGlobalArgumentPtr;
+}
+
+function setGlobalArgument(arg, i) {
+    const idx = globalArgumentPtr() / 4 + i;
+    getUint32Memory()[idx] = arg;
+}
+
+export function takes_ref_anyref_and_str(arg0, arg1) {
+    const sp = stack_pointer;
+    try {
+        _assertString(arg1, 'argument 1 of `takes_ref_anyref_and_str`');
+        const [ptr1, len1] = passStringToWasm(arg1);
+        setGlobalArgument(len1, 0);
+        try {
+            return wasm.takes_ref_anyref_and_str(addBorrowedObject(arg0), ptr1);
+        } finally {
+            wasm.__wbindgen_free(ptr1, len1 * 1);
+        }
+    } finally {
+        dropBorrowedObjects(sp);
+    }
+}
+
+const recv_anyref_target = host.recv_anyref;
+
+export function recv_anyref(arg0) {
+    recv_anyref_target(takeObject(arg0));
+}
+
+const give_anyref_target = host.give_anyref;
+
+export function give_anyref() {
+    return addHeapObject(give_anyref_target());
+}
+
+const recv_ref_anyref_target = host.recv_ref_anyref;
+
+export function recv_ref_anyref(arg0) {
+    recv_ref_anyref_target(getObject(arg0));
+}
+
+function __wbindgen_object_stats(sample) {
+    const stats = {
+        live: heap_live,
+        capacity: slab.length - 4,
+        borrowed: stack_pointer,
+        sample: [],
+    };
+    for (let i = 4; i < slab.length && stats.sample.length < (sample || 0); i++) {
+        const entry = slab[i];
+        if (typeof(entry) === 'number')
+            continue;
+        const ctor = entry.obj.constructor;
+        stats.sample.push(ctor && ctor.name ? ctor.name : typeof(entry.obj));
+    }
+    return stats;
+}
+
+export const debugObjectStats = __wbindgen_object_stats;
@@ -0,0 +1,93 @@
+/* tslint:disable */
+let slab = [{ obj: undefined }, { obj: null }, { obj: true }, { obj: false }];
+for (let i = 4; i < 132; i++)
+    slab.push(i + 1);
+
+// Owned entries of the slab which haven't been dropped yet,
+// see `debugObjectStats`.
+let heap_live = 0;
+
+let slab_next = 4;
+
+function addHeapObject(obj) {
+    if (obj === undefined)
+        return 0;
+    if (obj === null)
+        return 2;
+    if (obj === true)
+        return 4;
+    if (obj === false)
+        return 6;
+
+    if (slab_next === slab.length)
+        slab.push(slab.length + 1);
+    const idx = slab_next;
+    const next = slab[idx];
+
+    if (typeof(next) !== 'number')
+        throw new Error('corrupt slab');
+    slab_next = next;
+    heap_live++;
+
+    if (heap_live === 10000)
+        console.warn('wasm holds on to 10000 JS values, which may be a leak, ' +
+            'see `debugObjectStats()`');
+
+    slab[idx] = { obj: obj, cnt: 1 };
+    return idx << 1;
+}
+
+let stack = new Array(32);
+let stack_pointer = 0;
+
+function getObject(idx) {
+    if ((idx & 1) === 1) {
+        return stack[idx >> 1];
+    } else {
+        const val = slab[idx >> 1];
+
+        if (typeof(val) === 'number')
+            throw new Error('corrupt slab');
+        return val.obj;
+    }
+}
+
+function dropRef(idx) {
+    if ((idx & 1) === 1)
+        throw new Error('cannot drop ref of stack objects');
+
+    // Reserved values are never freed.
+    if (idx < 8)
+        return;
+
+    let obj = slab[idx >> 1];
+
+    if (typeof(obj) === 'number')
+        throw new Error('corrupt slab');
+    obj.cnt -= 1;
+    if (obj.cnt > 0)
+        return;
+    heap_live--;
+
+    // If we hit 0 then free up our space in the slab
+    slab[idx >> 1] = slab_next;
+    slab_next = idx >> 1;
+}
+
+function takeObject(idx) {
+    const ret = getObject(idx);
+    dropRef(idx);
+    return ret;
+}
+
+function addBorrowedObject(obj) {
+    stack[stack_pointer] = obj;
+    return (stack_pointer++ << 1) | 1;
+}
+
+function dropBorrowedObjects(sp) {
+    while (stack_pointer > sp)
+        stack[--stack_pointer] = undefined;
+}
+
+export { addBorrowedObject, addHeapObject, dropBorrowedObjects, dropRef, getObject, heap_live, slab, slab_next, stack, stack_pointer, takeObject };
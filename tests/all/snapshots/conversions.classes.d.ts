@@ -0,0 +1,33 @@
+/* tslint:disable */
+export function takes_frame(arg0: Frame): void;
+
+export function returns_frame(): Frame;
+
+export function takes_ref_frame(arg0: Frame): void;
+
+export function takes_mut_frame(arg0: Frame): void;
+
+export function takes_option_frame(arg0?: Frame): void;
+
+export function returns_option_frame(): Frame | undefined;
+
+export function takes_option_ref_frame(arg0?: Frame): void;
+
+export function takes_frame_and_str(arg0: Frame, arg1: string): number;
+
+export class Frame {
+    free(): void;
+    toJSON(): any;
+    toString(): string;
+    /**
+     * Consumes this instance: it can't be used once this method is called.
+     */
+    into_len(arg0: number): number;
+    /**
+     * The returned array is a view of wasm's memory, not a copy: it's only valid
+     * until the next call into wasm or until wasm's memory grows, so copy it with
+     * `slice()` to keep it around.
+     */
+    pixels(): Uint8Array;
+    resize(arg0: number): this;
+}
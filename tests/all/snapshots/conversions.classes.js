@@ -0,0 +1,281 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+let viewGeneration = 0;
+
+function viewOfWasm(view, what) {
+    if (typeof Proxy === 'undefined')
+        return view;
+    const generation = viewGeneration;
+    const check = function() {
+        if (generation !== viewGeneration || view.buffer !== wasm.memory.buffer)
+            throw new Error('the view of wasm memory returned by ' + what +
+                ' was used after another call into wasm or after ' +
+                'its memory grew, copy it with `slice()` to keep it');
+    };
+    return new Proxy(view, {
+        get: function(target, key) {
+            check();
+            const value = target[key];
+            return typeof(value) === 'function' ? value.bind(target) : value;
+        },
+        set: function(target, key, value) {
+            check();
+            target[key] = value;
+            return true;
+        },
+    });
+}
+
+function _assertClass(instance, klass, what) {
+    if (!(instance instanceof klass))
+        throw new Error('expected instance of ' + klass.name + ' (' + what + ')');
+    return instance.ptr;
+}
+
+export function takes_frame(arg0) {
+    viewGeneration++;
+    _assertClass(arg0, Frame, 'argument 0 of `takes_frame`');
+    if (arg0.ptr === 0)
+        throw new Error('Attempt to use a moved value: Frame');
+    const ptr0 = arg0.ptr;
+    arg0.ptr = 0;
+    return wasm.takes_frame(ptr0);
+}
+
+export function returns_frame() {
+    viewGeneration++;
+    return Frame.__construct(wasm.returns_frame());
+}
+
+export function takes_ref_frame(arg0) {
+    viewGeneration++;
+    _assertClass(arg0, Frame, 'argument 0 of `takes_ref_frame`');
+    if (arg0.ptr === 0)
+        throw new Error('Attempt to use a moved value: Frame');
+    return wasm.takes_ref_frame(arg0.ptr);
+}
+
+export function takes_mut_frame(arg0) {
+    viewGeneration++;
+    _assertClass(arg0, Frame, 'argument 0 of `takes_mut_frame`');
+    if (arg0.ptr === 0)
+        throw new Error('Attempt to use a moved value: Frame');
+    return wasm.takes_mut_frame(arg0.ptr);
+}
+
+export function takes_option_frame(arg0) {
+    viewGeneration++;
+    let ptr0 = 0;
+    if (arg0 != null) {
+        _assertClass(arg0, Frame, 'argument 0 of `takes_option_frame`');
+        if (arg0.ptr === 0)
+            throw new Error('Attempt to use a moved value: Frame');
+        ptr0 = arg0.ptr;
+        arg0.ptr = 0;
+    }
+    return wasm.takes_option_frame(ptr0);
+}
+
+export function returns_option_frame() {
+    viewGeneration++;
+    const ret = wasm.returns_option_frame();
+    return ret === 0 ? undefined : Frame.__construct(ret);
+}
+
+export function takes_option_ref_frame(arg0) {
+    viewGeneration++;
+    if (arg0 != null) {
+        _assertClass(arg0, Frame, 'argument 0 of `takes_option_ref_frame`');
+        if (arg0.ptr === 0)
+            throw new Error('Attempt to use a moved value: Frame');
+    }
+    return wasm.takes_option_ref_frame(arg0 == null ? 0 : arg0.ptr);
+}
+
+function _assertString(s, what) {
+    if (typeof(s) !== 'string')
+        throw new Error('expected a string argument, found ' + typeof(s) + ' (' + what + ')');
+}
+
+const TextEncoder = typeof self === 'object' && self.TextEncoder
+    ? self.TextEncoder
+    : require('util').TextEncoder;
+
+let cachedEncoder = new TextEncoder('utf-8');
+
+let cachedUint8Memory = null;
+function getUint8Memory() {
+    if (cachedUint8Memory === null ||
+            cachedUint8Memory.buffer !== wasm.memory.buffer)
+    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+    return cachedUint8Memory;
+}
+
+function passStringToWasm(arg) {
+    if (typeof(arg) !== 'string')
+        throw new Error('expected a string argument');
+
+    const buf = cachedEncoder.encode(arg);
+    const ptr = wasm.__wbindgen_malloc(buf.length);
+    getUint8Memory().set(buf, ptr);
+    return [ptr, buf.length];
+}
+
+let cachedUint32Memory = null;
+function getUint32Memory() {
+    if (cachedUint32Memory === null ||
+            cachedUint32Memory.buffer !== wasm.memory.buffer)
+    cachedUint32Memory = new Uint32Array(wasm.memory.buffer);
+    return cachedUint32Memory;
+}
+
+let cachedGlobalArgumentPtr = null;
+function globalArgumentPtr() {
+    if (cachedGlobalArgumentPtr === null)
+        cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+    return cachedGlobalArgumentPtr;
+}
+
+function setGlobalArgument(arg, i) {
+    const idx = globalArgumentPtr() / 4 + i;
+    getUint32Memory()[idx] = arg;
+}
+
+export function takes_frame_and_str(arg0, arg1) {
+    viewGeneration++;
+    _assertClass(arg0, Frame, 'argument 0 of `takes_frame_and_str`');
+    if (arg0.ptr === 0)
+        throw new Error('Attempt to use a moved value: Frame');
+    const ptr0 = arg0.ptr;
+    arg0.ptr = 0;
+    let called = false;
+    try {
+        _assertString(arg1, 'argument 1 of `takes_frame_and_str`');
+        const [ptr1, len1] = passStringToWasm(arg1);
+        setGlobalArgument(len1, 0);
+        try {
+            called = true;
+            return wasm.takes_frame_and_str(ptr0, ptr1);
+        } finally {
+            wasm.__wbindgen_free(ptr1, len1 * 1);
+        }
+    } finally {
+        if (!called) {
+            arg0.ptr = ptr0;
+        }
+    }
+}
+
+function _assertNum(n, what) {
+    if (typeof(n) !== 'number')
+        throw new Error('expected a number argument, found ' + typeof(n) + ' (' + what + ')');
+}
+
+function _assertInt(n, min, max, what) {
+    _assertNum(n, what);
+    if (!Number.isInteger(n) || n < min || n > max)
+        throw new Error('expected an integer argument in [' + min + ', ' + max + '], found ' +
+            n + ' (' + what + ')');
+}
+
+function getGlobalArgument(arg) {
+    const idx = globalArgumentPtr() / 4 + arg;
+    return getUint32Memory()[idx];
+}
+
+const recv_frame_target = host.recv_frame;
+
+export function recv_frame(arg0) {
+    let c0 = Frame.__construct(arg0);
+    recv_frame_target(c0);
+}
+
+const recv_option_frame_target = host.recv_option_frame;
+
+export function recv_option_frame(arg0) {
+    let c0 = arg0 === 0 ? undefined : Frame.__construct(arg0);
+    recv_option_frame_target(c0);
+}
+
+class ConstructorToken {
+    constructor(ptr) {
+        this.ptr = ptr;
+    }
+}
+
+export class Frame {
+    static __construct(ptr) {
+        return new Frame(new ConstructorToken(ptr));
+    }
+
+    constructor(...args) {
+        if (args.length === 1 && args[0] instanceof ConstructorToken) {
+            this.ptr = args[0].ptr;
+            return;
+        }
+        throw new Error('you cannot invoke `new` directly without having a method annotated a constructor');
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        if (ptr === 0) {
+            console.warn('Attempt to free an already freed value: Frame');
+            return;
+        }
+
+        this.ptr = 0;
+        wasm.__wbg_frame_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Frame';
+    }
+
+    toJSON() {
+        return {  };
+    }
+
+    toString() {
+        return 'Frame { ptr: ' + this.ptr + ' }';
+    }
+
+    into_len(arg0) {
+        viewGeneration++;
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Frame.into_len');
+        const ptr = this.ptr;
+        this.ptr = 0;
+        let called = false;
+        try {
+            _assertInt(arg0, 0, 4294967295, 'argument 0 of `into_len`');
+            called = true;
+            return wasm.frame_into_len(ptr, arg0);
+        } finally {
+            if (!called) {
+                this.ptr = ptr;
+            }
+        }
+    }
+
+    pixels() {
+        viewGeneration++;
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Frame.pixels');
+        const ret = wasm.frame_pixels(this.ptr);
+        const len = getGlobalArgument(0);
+        // This is a view of wasm's memory rather than a copy, valid
+        // until the next call into wasm or until its memory grows.
+        return viewOfWasm(new Uint8Array(wasm.memory.buffer, ret, len), '`pixels`');
+    }
+
+    resize(arg0) {
+        viewGeneration++;
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Frame.resize');
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `resize`');
+        wasm.frame_resize(this.ptr, arg0);
+        return this;
+    }
+}
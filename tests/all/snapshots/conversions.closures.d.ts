@@ -0,0 +1,15 @@
+/* tslint:disable */
+export function returns_fn_pointer(): (arg0: number) => number;
+
+/**
+ * Counts the JS values owned by wasm which haven't been dropped yet (`live`),
+ * the room for them before the heap grows (`capacity`) and the values only
+ * borrowed for the calls into wasm in progress (`borrowed`). With `sample`,
+ * also lists the constructor names of up to that many of the live values.
+ */
+export function debugObjectStats(sample?: number): {
+    live: number;
+    capacity: number;
+    borrowed: number;
+    sample: string[];
+};
@@ -0,0 +1,336 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+let cachedUint32Memory = null;
+function getUint32Memory() {
+    if (cachedUint32Memory === null ||
+            cachedUint32Memory.buffer !== wasm.memory.buffer)
+    cachedUint32Memory = new Uint32Array(wasm.memory.buffer);
+    return cachedUint32Memory;
+}
+
+let cachedGlobalArgumentPtr = null;
+function globalArgumentPtr() {
+    if (cachedGlobalArgumentPtr === null)
+        cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+    return cachedGlobalArgumentPtr;
+}
+
+function getGlobalArgument(arg) {
+    const idx = globalArgumentPtr() / 4 + arg;
+    return getUint32Memory()[idx];
+}
+
+function _assertNum(n, what) {
+    if (typeof(n) !== 'number')
+        throw new Error('expected a number argument, found ' + typeof(n) + ' (' + what + ')');
+}
+
+function _assertInt(n, min, max, what) {
+    _assertNum(n, what);
+    if (!Number.isInteger(n) || n < min || n > max)
+        throw new Error('expected an integer argument in [' + min + ', ' + max + '], found ' +
+            n + ' (' + what + ')');
+}
+
+const fnPointers = {};
+function getFnPointer(shim, ptr, signature, wrap) {
+    const key = shim + ':' + ptr;
+    if (!fnPointers.hasOwnProperty(key)) {
+        const f = wrap(wasm.__wbg_function_table.get(shim), ptr);
+        fnPointers[key] = function() {
+            try {
+                return f.apply(this, arguments);
+            } catch (e) {
+                if (e instanceof WebAssembly.RuntimeError && /signature/.test(e.message))
+                    throw new Error('the function pointer at table index ' + ptr +
+                        ' was called as `' + signature + '`, which ' +
+                        'isn\'t its signature in wasm (' + e.message + ')');
+                throw e;
+            }
+        };
+    }
+    return fnPointers[key];
+}
+
+export function returns_fn_pointer() {
+    const ret = wasm.returns_fn_pointer();
+    return getFnPointer(ret, getGlobalArgument(0), "fn(u32) -> u32", function(f, p) {
+        return function(arg0) {
+            _assertInt(arg0, 0, 4294967295, 'argument 0 of `fn(u32) -> u32`');
+            return f(p, arg0);
+        };
+    });
+}
+
+const recv_fn_pointer_target = host.recv_fn_pointer;
+
+export function recv_fn_pointer(arg0) {
+    const fp0 = getFnPointer(arg0, getGlobalArgument(0), "fn(u32) -> u32", function(f, p) {
+        return function(arg0) {
+            _assertInt(arg0, 0, 4294967295, 'argument 0 of `fn(u32) -> u32`');
+            return f(p, arg0);
+        };
+    });
+    recv_fn_pointer_target(fp0);
+}
+
+const recv_closure_target = host.recv_closure;
+
+export function recv_closure(arg0) {
+    let cb0 = function(arg0) {
+        _assertInt(arg0, 0, 4294967295, 'argument 0');
+        return this.f(this.a, this.b, arg0);
+    };
+    cb0.f = wasm.__wbg_function_table.get(arg0);
+    cb0.a = getGlobalArgument(0);
+    cb0.b = getGlobalArgument(0 + 1);
+    try {
+        recv_closure_target(cb0.bind(cb0));
+    } finally {
+        cb0.a = cb0.b = 0;
+    }
+}
+
+const recv_closure_mut_target = host.recv_closure_mut;
+
+function _assertString(s, what) {
+    if (typeof(s) !== 'string')
+        throw new Error('expected a string argument, found ' + typeof(s) + ' (' + what + ')');
+}
+
+const TextEncoder = typeof self === 'object' && self.TextEncoder
+    ? self.TextEncoder
+    : require('util').TextEncoder;
+
+let cachedEncoder = new TextEncoder('utf-8');
+
+let cachedUint8Memory = null;
+function getUint8Memory() {
+    if (cachedUint8Memory === null ||
+            cachedUint8Memory.buffer !== wasm.memory.buffer)
+    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+    return cachedUint8Memory;
+}
+
+function passStringToWasm(arg) {
+    if (typeof(arg) !== 'string')
+        throw new Error('expected a string argument');
+
+    const buf = cachedEncoder.encode(arg);
+    const ptr = wasm.__wbindgen_malloc(buf.length);
+    getUint8Memory().set(buf, ptr);
+    return [ptr, buf.length];
+}
+
+function setGlobalArgument(arg, i) {
+    const idx = globalArgumentPtr() / 4 + i;
+    getUint32Memory()[idx] = arg;
+}
+
+export function recv_closure_mut(arg0) {
+    let cb0 = function(arg0) {
+        let a = this.a;
+        this.a = 0;
+        try {
+            _assertString(arg0, 'argument 0');
+            const [ptr0, len0] = passStringToWasm(arg0);
+            setGlobalArgument(len0, 0);
+            try {
+                return this.f(a, this.b, ptr0);
+            } finally {
+                wasm.__wbindgen_free(ptr0, len0 * 1);
+            }
+        } finally {
+            this.a = a;
+        }
+    };
+    cb0.f = wasm.__wbg_function_table.get(arg0);
+    cb0.a = getGlobalArgument(0);
+    cb0.b = getGlobalArgument(0 + 1);
+    try {
+        recv_closure_mut_target(cb0.bind(cb0));
+    } finally {
+        cb0.a = cb0.b = 0;
+    }
+}
+
+const recv_ref_closure_target = host.recv_ref_closure;
+
+let slab = [{ obj: undefined }, { obj: null }, { obj: true }, { obj: false }];
+for (let i = 4; i < 132; i++)
+    slab.push(i + 1);
+
+// Owned entries of the slab which haven't been dropped yet,
+// see `debugObjectStats`.
+let heap_live = 0;
+
+let slab_next = 4;
+
+function addHeapObject(obj) {
+    if (obj === undefined)
+        return 0;
+    if (obj === null)
+        return 2;
+    if (obj === true)
+        return 4;
+    if (obj === false)
+        return 6;
+
+    if (slab_next === slab.length)
+        slab.push(slab.length + 1);
+    const idx = slab_next;
+    const next = slab[idx];
+
+    if (typeof(next) !== 'number')
+        throw new Error('corrupt slab');
+    slab_next = next;
+    heap_live++;
+
+    if (heap_live === 10000)
+        console.warn('wasm holds on to 10000 JS values, which may be a leak, ' +
+            'see `debugObjectStats()`');
+
+    slab[idx] = { obj: obj, cnt: 1 };
+    return idx << 1;
+}
+
+let stack = new Array(32);
+let stack_pointer = 0;
+
+function getObject(idx) {
+    if ((idx & 1) === 1) {
+        return stack[idx >> 1];
+    } else {
+        const val = slab[idx >> 1];
+
+        if (typeof(val) === 'number')
+            throw new Error('corrupt slab');
+        return val.obj;
+    }
+}
+
+export function recv_ref_closure(arg0) {
+    let idx0 = getUint32Memory()[arg0 / 4];
+    if (idx0 === 0xffffffff) {
+        let cb0 = function(arg0) {
+            _assertInt(arg0, 0, 4294967295, 'argument 0');
+            return this.f(this.a, this.b, arg0);
+        };
+        cb0.a = getGlobalArgument(0);
+        cb0.b = getGlobalArgument(1);
+        cb0.f = wasm.__wbg_function_table.get(getGlobalArgument(2));
+        let real = cb0.bind(cb0);
+        real.original = cb0;
+        idx0 = getUint32Memory()[arg0 / 4] = addHeapObject(real);
+    }
+    recv_ref_closure_target(getObject(idx0));
+}
+
+const recv_ref_closure_mut_target = host.recv_ref_closure_mut;
+
+export function recv_ref_closure_mut(arg0) {
+    let idx0 = getUint32Memory()[arg0 / 4];
+    if (idx0 === 0xffffffff) {
+        let cb0 = function(arg0) {
+            let a = this.a;
+            this.a = 0;
+            try {
+                _assertString(arg0, 'argument 0');
+                const [ptr0, len0] = passStringToWasm(arg0);
+                setGlobalArgument(len0, 0);
+                try {
+                    return (this.f(a, this.b, ptr0)) !== 0;
+                } finally {
+                    wasm.__wbindgen_free(ptr0, len0 * 1);
+                }
+            } finally {
+                this.a = a;
+            }
+        };
+        cb0.a = getGlobalArgument(0);
+        cb0.b = getGlobalArgument(1);
+        cb0.f = wasm.__wbg_function_table.get(getGlobalArgument(2));
+        let real = cb0.bind(cb0);
+        real.original = cb0;
+        idx0 = getUint32Memory()[arg0 / 4] = addHeapObject(real);
+    }
+    recv_ref_closure_mut_target(getObject(idx0));
+}
+
+const recv_closure_and_str_target = host.recv_closure_and_str;
+
+const TextDecoder = typeof self === 'object' && self.TextDecoder
+    ? self.TextDecoder
+    : require('util').TextDecoder;
+
+let cachedDecoder = new TextDecoder('utf-8');
+
+function getStringFromWasm(ptr, len) {
+    return cachedDecoder.decode(getUint8Memory().slice(ptr, ptr + len));
+}
+
+export function recv_closure_and_str(arg0, arg1) {
+    let cb0 = function(arg0) {
+        let a = this.a;
+        this.a = 0;
+        try {
+            _assertInt(arg0, 0, 4294967295, 'argument 0');
+            return this.f(a, this.b, arg0);
+        } finally {
+            this.a = a;
+        }
+    };
+    cb0.f = wasm.__wbg_function_table.get(arg0);
+    cb0.a = getGlobalArgument(0);
+    cb0.b = getGlobalArgument(0 + 1);
+    try {
+        let len1 = getGlobalArgument(2);
+        let v1 = getStringFromWasm(arg1, len1);
+        recv_closure_and_str_target(cb0.bind(cb0), v1);
+    } finally {
+        cb0.a = cb0.b = 0;
+    }
+}
+
+const try_recv_closure_target = host.try_recv_closure;
+
+export function try_recv_closure(arg0, exnptr) {
+    let cb0 = function(arg0) {
+        _assertInt(arg0, 0, 4294967295, 'argument 0');
+        return this.f(this.a, this.b, arg0);
+    };
+    cb0.f = wasm.__wbg_function_table.get(arg0);
+    cb0.a = getGlobalArgument(0);
+    cb0.b = getGlobalArgument(0 + 1);
+    try {
+        try {
+            try_recv_closure_target(cb0.bind(cb0));
+        } catch (e) {
+            const view = getUint32Memory();
+            view[exnptr / 4] = 1;
+            view[exnptr / 4 + 1] = addHeapObject(e);
+        }
+    } finally {
+        cb0.a = cb0.b = 0;
+    }
+}
+
+function __wbindgen_object_stats(sample) {
+    const stats = {
+        live: heap_live,
+        capacity: slab.length - 4,
+        borrowed: stack_pointer,
+        sample: [],
+    };
+    for (let i = 4; i < slab.length && stats.sample.length < (sample || 0); i++) {
+        const entry = slab[i];
+        if (typeof(entry) === 'number')
+            continue;
+        const ctor = entry.obj.constructor;
+        stats.sample.push(ctor && ctor.name ? ctor.name : typeof(entry.obj));
+    }
+    return stats;
+}
+
+export const debugObjectStats = __wbindgen_object_stats;
@@ -0,0 +1,7 @@
+/* tslint:disable */
+export interface Point {
+    x: number;
+    label?: string;
+}
+
+export function returns_point(): Point;
@@ -0,0 +1,65 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+let stack = new Array(32);
+let stack_pointer = 0;
+
+let slab = [{ obj: undefined }, { obj: null }, { obj: true }, { obj: false }];
+for (let i = 4; i < 132; i++)
+    slab.push(i + 1);
+
+// Owned entries of the slab which haven't been dropped yet,
+// see `debugObjectStats`.
+let heap_live = 0;
+
+function getObject(idx) {
+    if ((idx & 1) === 1) {
+        return stack[idx >> 1];
+    } else {
+        const val = slab[idx >> 1];
+
+        if (typeof(val) === 'number')
+            throw new Error('corrupt slab');
+        return val.obj;
+    }
+}
+
+let slab_next = 4;
+
+function dropRef(idx) {
+    if ((idx & 1) === 1)
+        throw new Error('cannot drop ref of stack objects');
+
+    // Reserved values are never freed.
+    if (idx < 8)
+        return;
+
+    let obj = slab[idx >> 1];
+
+    if (typeof(obj) === 'number')
+        throw new Error('corrupt slab');
+    obj.cnt -= 1;
+    if (obj.cnt > 0)
+        return;
+    heap_live--;
+
+    // If we hit 0 then free up our space in the slab
+    slab[idx >> 1] = slab_next;
+    slab_next = idx >> 1;
+}
+
+function takeObject(idx) {
+    const ret = getObject(idx);
+    dropRef(idx);
+    return ret;
+}
+
+export function returns_point() {
+    return takeObject(wasm.returns_point());
+}
+
+const recv_point_target = host.recv_point;
+
+export function recv_point(arg0) {
+    recv_point_target(takeObject(arg0));
+}
@@ -0,0 +1,48 @@
+/* tslint:disable */
+export function takes_i8(arg0: number): void;
+
+export function returns_i8(): number;
+
+export function takes_u8(arg0: number): void;
+
+export function returns_u8(): number;
+
+export function takes_i16(arg0: number): void;
+
+export function returns_i16(): number;
+
+export function takes_u16(arg0: number): void;
+
+export function returns_u16(): number;
+
+export function takes_i32(arg0: number): void;
+
+export function returns_i32(): number;
+
+export function takes_u32(arg0: number): void;
+
+export function returns_u32(): number;
+
+export function takes_i64(arg0: bigint): void;
+
+export function returns_i64(): bigint;
+
+export function takes_u64(arg0: bigint): void;
+
+export function returns_u64(): bigint;
+
+export function takes_f32(arg0: number): void;
+
+export function returns_f32(): number;
+
+export function takes_f64(arg0: number): void;
+
+export function returns_f64(): number;
+
+export function takes_bool(arg0: boolean): void;
+
+export function returns_bool(): boolean;
+
+export function takes_enum(arg0: number): void;
+
+export function returns_enum(): number;
@@ -0,0 +1,242 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+export function takes_i8(arg0) {
+    return wasm.takes_i8(arg0);
+}
+
+export function returns_i8() {
+    return wasm.returns_i8();
+}
+
+export function takes_u8(arg0) {
+    return wasm.takes_u8(arg0);
+}
+
+export function returns_u8() {
+    return wasm.returns_u8();
+}
+
+export function takes_i16(arg0) {
+    return wasm.takes_i16(arg0);
+}
+
+export function returns_i16() {
+    return wasm.returns_i16();
+}
+
+export function takes_u16(arg0) {
+    return wasm.takes_u16(arg0);
+}
+
+export function returns_u16() {
+    return wasm.returns_u16();
+}
+
+export function takes_i32(arg0) {
+    return wasm.takes_i32(arg0);
+}
+
+export function returns_i32() {
+    return wasm.returns_i32();
+}
+
+export function takes_u32(arg0) {
+    return wasm.takes_u32(arg0);
+}
+
+export function returns_u32() {
+    return wasm.returns_u32();
+}
+
+export function takes_i64(arg0) {
+    return wasm.takes_i64(arg0);
+}
+
+export function returns_i64() {
+    return wasm.returns_i64();
+}
+
+export function takes_u64(arg0) {
+    return wasm.takes_u64(arg0);
+}
+
+export function returns_u64() {
+    return BigInt.asUintN(64, wasm.returns_u64());
+}
+
+export function takes_f32(arg0) {
+    return wasm.takes_f32(arg0);
+}
+
+export function returns_f32() {
+    return wasm.returns_f32();
+}
+
+export function takes_f64(arg0) {
+    return wasm.takes_f64(arg0);
+}
+
+export function returns_f64() {
+    return wasm.returns_f64();
+}
+
+export function takes_bool(arg0) {
+    return wasm.takes_bool(arg0 ? 1 : 0);
+}
+
+export function returns_bool() {
+    return (wasm.returns_bool()) !== 0;
+}
+
+export function takes_enum(arg0) {
+    return wasm.takes_enum(arg0);
+}
+
+export function returns_enum() {
+    return wasm.returns_enum();
+}
+
+const recv_i8_target = host.recv_i8;
+
+export function recv_i8(arg0) {
+    recv_i8_target(arg0);
+}
+
+const give_i8_target = host.give_i8;
+
+export function give_i8() {
+    return give_i8_target();
+}
+
+const recv_u8_target = host.recv_u8;
+
+export function recv_u8(arg0) {
+    recv_u8_target(arg0);
+}
+
+const give_u8_target = host.give_u8;
+
+export function give_u8() {
+    return give_u8_target();
+}
+
+const recv_i16_target = host.recv_i16;
+
+export function recv_i16(arg0) {
+    recv_i16_target(arg0);
+}
+
+const give_i16_target = host.give_i16;
+
+export function give_i16() {
+    return give_i16_target();
+}
+
+const recv_u16_target = host.recv_u16;
+
+export function recv_u16(arg0) {
+    recv_u16_target(arg0);
+}
+
+const give_u16_target = host.give_u16;
+
+export function give_u16() {
+    return give_u16_target();
+}
+
+const recv_i32_target = host.recv_i32;
+
+export function recv_i32(arg0) {
+    recv_i32_target(arg0);
+}
+
+const give_i32_target = host.give_i32;
+
+export function give_i32() {
+    return give_i32_target();
+}
+
+const recv_u32_target = host.recv_u32;
+
+export function recv_u32(arg0) {
+    recv_u32_target(arg0);
+}
+
+const give_u32_target = host.give_u32;
+
+export function give_u32() {
+    return give_u32_target();
+}
+
+const recv_i64_target = host.recv_i64;
+
+export function recv_i64(arg0) {
+    recv_i64_target(arg0);
+}
+
+const give_i64_target = host.give_i64;
+
+export function give_i64() {
+    return give_i64_target();
+}
+
+const recv_u64_target = host.recv_u64;
+
+export function recv_u64(arg0) {
+    recv_u64_target(BigInt.asUintN(64, arg0));
+}
+
+const give_u64_target = host.give_u64;
+
+export function give_u64() {
+    return give_u64_target();
+}
+
+const recv_f32_target = host.recv_f32;
+
+export function recv_f32(arg0) {
+    recv_f32_target(arg0);
+}
+
+const give_f32_target = host.give_f32;
+
+export function give_f32() {
+    return give_f32_target();
+}
+
+const recv_f64_target = host.recv_f64;
+
+export function recv_f64(arg0) {
+    recv_f64_target(arg0);
+}
+
+const give_f64_target = host.give_f64;
+
+export function give_f64() {
+    return give_f64_target();
+}
+
+const recv_bool_target = host.recv_bool;
+
+export function recv_bool(arg0) {
+    recv_bool_target(arg0 !== 0);
+}
+
+const give_bool_target = host.give_bool;
+
+export function give_bool() {
+    return give_bool_target() ? 1 : 0;
+}
+
+const recv_enum_target = host.recv_enum;
+
+export function recv_enum(arg0) {
+    recv_enum_target(arg0);
+}
+
+const give_enum_target = host.give_enum;
+
+export function give_enum() {
+    return give_enum_target();
+}
@@ -0,0 +1,64 @@
+/* tslint:disable */
+export function takes_vec_u8(arg0: Uint8Array | number[]): void;
+
+export function returns_vec_u8(): Uint8Array;
+
+export function takes_slice_u8(arg0: Uint8Array | number[]): void;
+
+/**
+ * The returned array is a view of wasm's memory, not a copy: it's only valid
+ * until the next call into wasm or until wasm's memory grows, so copy it with
+ * `slice()` to keep it around.
+ */
+export function returns_slice_u8(): Uint8Array;
+
+export function takes_vec_i32(arg0: Int32Array | number[]): void;
+
+export function returns_vec_i32(): Int32Array;
+
+export function takes_slice_i32(arg0: Int32Array | number[]): void;
+
+/**
+ * The returned array is a view of wasm's memory, not a copy: it's only valid
+ * until the next call into wasm or until wasm's memory grows, so copy it with
+ * `slice()` to keep it around.
+ */
+export function returns_slice_i32(): Int32Array;
+
+export function takes_vec_f64(arg0: Float64Array | number[]): void;
+
+export function returns_vec_f64(): Float64Array;
+
+export function takes_slice_f64(arg0: Float64Array | number[]): void;
+
+/**
+ * The returned array is a view of wasm's memory, not a copy: it's only valid
+ * until the next call into wasm or until wasm's memory grows, so copy it with
+ * `slice()` to keep it around.
+ */
+export function returns_slice_f64(): Float64Array;
+
+export function takes_vec_anyref(arg0: any[]): void;
+
+export function returns_vec_anyref(): any[];
+
+export function takes_slice_anyref(arg0: any[]): void;
+
+export function takes_option_vec_u8(arg0?: Uint8Array | number[]): void;
+
+export function returns_option_vec_u8(): Uint8Array | undefined;
+
+export function takes_option_slice_u8(arg0?: Uint8Array | number[]): void;
+
+/**
+ * Counts the JS values owned by wasm which haven't been dropped yet (`live`),
+ * the room for them before the heap grows (`capacity`) and the values only
+ * borrowed for the calls into wasm in progress (`borrowed`). With `sample`,
+ * also lists the constructor names of up to that many of the live values.
+ */
+export function debugObjectStats(sample?: number): {
+    live: number;
+    capacity: number;
+    borrowed: number;
+    sample: string[];
+};
@@ -0,0 +1,595 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+let viewGeneration = 0;
+
+function viewOfWasm(view, what) {
+    if (typeof Proxy === 'undefined')
+        return view;
+    const generation = viewGeneration;
+    const check = function() {
+        if (generation !== viewGeneration || view.buffer !== wasm.memory.buffer)
+            throw new Error('the view of wasm memory returned by ' + what +
+                ' was used after another call into wasm or after ' +
+                'its memory grew, copy it with `slice()` to keep it');
+    };
+    return new Proxy(view, {
+        get: function(target, key) {
+            check();
+            const value = target[key];
+            return typeof(value) === 'function' ? value.bind(target) : value;
+        },
+        set: function(target, key, value) {
+            check();
+            target[key] = value;
+            return true;
+        },
+    });
+}
+
+function _assertArray(arg, klass, what) {
+    if (arg instanceof klass)
+        return;
+    if (!Array.isArray(arg))
+        throw new Error('expected a ' + klass.name + ' or Array argument (' + what + ')');
+    for (let i = 0; i < arg.length; i++) {
+        if (typeof(arg[i]) !== 'number')
+            throw new Error('expected only numbers in the array, found ' +
+                typeof(arg[i]) + ' at index ' + i + ' (' + what + ')');
+    }
+}
+
+let cachedUint8Memory = null;
+function getUint8Memory() {
+    if (cachedUint8Memory === null ||
+            cachedUint8Memory.buffer !== wasm.memory.buffer)
+    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+    return cachedUint8Memory;
+}
+
+function passArray8ToWasm(arg) {
+    if (Array.isArray(arg))
+        arg = Uint8Array.from(arg);
+    const ptr = wasm.__wbindgen_malloc(arg.length);
+    getUint8Memory().set(arg, ptr);
+    return [ptr, arg.length];
+}
+
+let cachedUint32Memory = null;
+function getUint32Memory() {
+    if (cachedUint32Memory === null ||
+            cachedUint32Memory.buffer !== wasm.memory.buffer)
+    cachedUint32Memory = new Uint32Array(wasm.memory.buffer);
+    return cachedUint32Memory;
+}
+
+let cachedGlobalArgumentPtr = null;
+function globalArgumentPtr() {
+    if (cachedGlobalArgumentPtr === null)
+        cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+    return cachedGlobalArgumentPtr;
+}
+
+function setGlobalArgument(arg, i) {
+    const idx = globalArgumentPtr() / 4 + i;
+    getUint32Memory()[idx] = arg;
+}
+
+export function takes_vec_u8(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Uint8Array, 'argument 0 of `takes_vec_u8`');
+    const [ptr0, len0] = passArray8ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_vec_u8(ptr0);
+}
+
+function getArrayU8FromWasm(ptr, len) {
+    const mem = getUint8Memory();
+    const slice = mem.slice(ptr, ptr + len);
+    return new Uint8Array(slice);
+}
+
+function getGlobalArgument(arg) {
+    const idx = globalArgumentPtr() / 4 + arg;
+    return getUint32Memory()[idx];
+}
+
+export function returns_vec_u8() {
+    viewGeneration++;
+    const ret = wasm.returns_vec_u8();
+    const len = getGlobalArgument(0);
+    const realRet = getArrayU8FromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 1);
+    return realRet;
+}
+
+export function takes_slice_u8(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Uint8Array, 'argument 0 of `takes_slice_u8`');
+    const [ptr0, len0] = passArray8ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_slice_u8(ptr0);
+    } finally {
+        wasm.__wbindgen_free(ptr0, len0 * 1);
+    }
+}
+
+export function returns_slice_u8() {
+    viewGeneration++;
+    const ret = wasm.returns_slice_u8();
+    const len = getGlobalArgument(0);
+    // This is a view of wasm's memory rather than a copy, valid
+    // until the next call into wasm or until its memory grows.
+    return viewOfWasm(new Uint8Array(wasm.memory.buffer, ret, len), '`returns_slice_u8`');
+}
+
+function passArray32ToWasm(arg) {
+    if (Array.isArray(arg))
+        arg = Uint32Array.from(arg);
+    const ptr = wasm.__wbindgen_malloc(arg.length * 4);
+    getUint32Memory().set(arg, ptr / 4);
+    return [ptr, arg.length];
+}
+
+export function takes_vec_i32(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Int32Array, 'argument 0 of `takes_vec_i32`');
+    const [ptr0, len0] = passArray32ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_vec_i32(ptr0);
+}
+
+function getArrayI32FromWasm(ptr, len) {
+    const mem = getUint32Memory();
+    const slice = mem.slice(ptr / 4, ptr / 4 + len);
+    return new Int32Array(slice);
+}
+
+export function returns_vec_i32() {
+    viewGeneration++;
+    const ret = wasm.returns_vec_i32();
+    const len = getGlobalArgument(0);
+    const realRet = getArrayI32FromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 4);
+    return realRet;
+}
+
+export function takes_slice_i32(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Int32Array, 'argument 0 of `takes_slice_i32`');
+    const [ptr0, len0] = passArray32ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_slice_i32(ptr0);
+    } finally {
+        wasm.__wbindgen_free(ptr0, len0 * 4);
+    }
+}
+
+export function returns_slice_i32() {
+    viewGeneration++;
+    const ret = wasm.returns_slice_i32();
+    const len = getGlobalArgument(0);
+    // This is a view of wasm's memory rather than a copy, valid
+    // until the next call into wasm or until its memory grows.
+    return viewOfWasm(new Int32Array(wasm.memory.buffer, ret, len), '`returns_slice_i32`');
+}
+
+let cachedFloat64Memory = null;
+function getFloat64Memory() {
+    if (cachedFloat64Memory === null ||
+            cachedFloat64Memory.buffer !== wasm.memory.buffer)
+    cachedFloat64Memory = new Float64Array(wasm.memory.buffer);
+    return cachedFloat64Memory;
+}
+
+function passArrayF64ToWasm(arg) {
+    if (Array.isArray(arg))
+        arg = Float64Array.from(arg);
+    const ptr = wasm.__wbindgen_malloc(arg.length * 8);
+    getFloat64Memory().set(arg, ptr / 8);
+    return [ptr, arg.length];
+}
+
+export function takes_vec_f64(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Float64Array, 'argument 0 of `takes_vec_f64`');
+    const [ptr0, len0] = passArrayF64ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_vec_f64(ptr0);
+}
+
+function getArrayF64FromWasm(ptr, len) {
+    const mem = getFloat64Memory();
+    const slice = mem.slice(ptr / 8,  ptr / 8 + len);
+    return new Float64Array(slice);
+}
+
+export function returns_vec_f64() {
+    viewGeneration++;
+    const ret = wasm.returns_vec_f64();
+    const len = getGlobalArgument(0);
+    const realRet = getArrayF64FromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 8);
+    return realRet;
+}
+
+export function takes_slice_f64(arg0) {
+    viewGeneration++;
+    _assertArray(arg0, Float64Array, 'argument 0 of `takes_slice_f64`');
+    const [ptr0, len0] = passArrayF64ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_slice_f64(ptr0);
+    } finally {
+        wasm.__wbindgen_free(ptr0, len0 * 8);
+    }
+}
+
+export function returns_slice_f64() {
+    viewGeneration++;
+    const ret = wasm.returns_slice_f64();
+    const len = getGlobalArgument(0);
+    // This is a view of wasm's memory rather than a copy, valid
+    // until the next call into wasm or until its memory grows.
+    return viewOfWasm(new Float64Array(wasm.memory.buffer, ret, len), '`returns_slice_f64`');
+}
+
+let slab = [{ obj: undefined }, { obj: null }, { obj: true }, { obj: false }];
+for (let i = 4; i < 132; i++)
+    slab.push(i + 1);
+
+// Owned entries of the slab which haven't been dropped yet,
+// see `debugObjectStats`.
+let heap_live = 0;
+
+let slab_next = 4;
+
+function addHeapObject(obj) {
+    if (obj === undefined)
+        return 0;
+    if (obj === null)
+        return 2;
+    if (obj === true)
+        return 4;
+    if (obj === false)
+        return 6;
+
+    if (slab_next === slab.length)
+        slab.push(slab.length + 1);
+    const idx = slab_next;
+    const next = slab[idx];
+
+    if (typeof(next) !== 'number')
+        throw new Error('corrupt slab');
+    slab_next = next;
+    heap_live++;
+
+    if (heap_live === 10000)
+        console.warn('wasm holds on to 10000 JS values, which may be a leak, ' +
+            'see `debugObjectStats()`');
+
+    slab[idx] = { obj: obj, cnt: 1 };
+    return idx << 1;
+}
+
+function passArrayJsValueToWasm(array) {
+    const ptr = wasm.__wbindgen_malloc(array.length * 4);
+    const mem = getUint32Memory();
+    for (let i = 0; i < array.length; i++) {
+        mem[ptr / 4 + i] = addHeapObject(array[i]);
+    }
+    return [ptr, array.length];
+}
+
+export function takes_vec_anyref(arg0) {
+    viewGeneration++;
+    const [ptr0, len0] = passArrayJsValueToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_vec_anyref(ptr0);
+}
+
+let stack = new Array(32);
+let stack_pointer = 0;
+
+function getObject(idx) {
+    if ((idx & 1) === 1) {
+        return stack[idx >> 1];
+    } else {
+        const val = slab[idx >> 1];
+
+        if (typeof(val) === 'number')
+            throw new Error('corrupt slab');
+        return val.obj;
+    }
+}
+
+function dropRef(idx) {
+    if ((idx & 1) === 1)
+        throw new Error('cannot drop ref of stack objects');
+
+    // Reserved values are never freed.
+    if (idx < 8)
+        return;
+
+    let obj = slab[idx >> 1];
+
+    if (typeof(obj) === 'number')
+        throw new Error('corrupt slab');
+    obj.cnt -= 1;
+    if (obj.cnt > 0)
+        return;
+    heap_live--;
+
+    // If we hit 0 then free up our space in the slab
+    slab[idx >> 1] = slab_next;
+    slab_next = idx >> 1;
+}
+
+function takeObject(idx) {
+    const ret = getObject(idx);
+    dropRef(idx);
+    return ret;
+}
+
+function takeArrayJsValueFromWasm(ptr, len) {
+    const mem = getUint32Memory();
+    const slice = mem.slice(ptr / 4, ptr / 4 + len);
+    const result = [];
+    for (let i = 0; i < slice.length; i++) {
+        result.push(takeObject(slice[i]));
+    }
+    return result;
+}
+
+export function returns_vec_anyref() {
+    viewGeneration++;
+    const ret = wasm.returns_vec_anyref();
+    const len = getGlobalArgument(0);
+    const realRet = takeArrayJsValueFromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 4);
+    return realRet;
+}
+
+function addBorrowedObject(obj) {
+    stack[stack_pointer] = obj;
+    return (stack_pointer++ << 1) | 1;
+}
+
+function dropBorrowedObjects(sp) {
+    while (stack_pointer > sp)
+        stack[--stack_pointer] = undefined;
+}
+
+function passBorrowedArrayJsValueToWasm(array) {
+    const ptr = wasm.__wbindgen_malloc(array.length * 4);
+    const mem = getUint32Memory();
+    for (let i = 0; i < array.length; i++) {
+        mem[ptr / 4 + i] = addBorrowedObject(array[i]);
+    }
+    return [ptr, array.length];
+}
+
+export function takes_slice_anyref(arg0) {
+    const sp = stack_pointer;
+    try {
+        viewGeneration++;
+        const [ptr0, len0] = passBorrowedArrayJsValueToWasm(arg0);
+        setGlobalArgument(len0, 0);
+        try {
+            return wasm.takes_slice_anyref(ptr0);
+        } finally {
+            wasm.__wbindgen_free(ptr0, len0 * 4);
+        }
+    } finally {
+        dropBorrowedObjects(sp);
+    }
+}
+
+export function takes_option_vec_u8(arg0) {
+    viewGeneration++;
+    if (arg0 != null) {
+        _assertArray(arg0, Uint8Array, 'argument 0 of `takes_option_vec_u8`');
+    }
+    const [ptr0, len0] = arg0 == null ? [0, 0] : passArray8ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_option_vec_u8(ptr0);
+}
+
+export function returns_option_vec_u8() {
+    viewGeneration++;
+    const ret = wasm.returns_option_vec_u8();
+    if (ret === 0)
+        return undefined;
+    const len = getGlobalArgument(0);
+    const realRet = getArrayU8FromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 1);
+    return realRet;
+}
+
+export function takes_option_slice_u8(arg0) {
+    viewGeneration++;
+    if (arg0 != null) {
+        _assertArray(arg0, Uint8Array, 'argument 0 of `takes_option_slice_u8`');
+    }
+    const [ptr0, len0] = arg0 == null ? [0, 0] : passArray8ToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_option_slice_u8(ptr0);
+    } finally {
+        if (ptr0 !== 0)
+            wasm.__wbindgen_free(ptr0, len0 * 1);
+    }
+}
+
+const recv_vec_u8_target = host.recv_vec_u8;
+
+export function recv_vec_u8(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getArrayU8FromWasm(arg0, len0);
+    wasm.__wbindgen_free(arg0, len0 * 1);
+    recv_vec_u8_target(v0);
+}
+
+const give_vec_u8_target = host.give_vec_u8;
+
+export function give_vec_u8() {
+    const [retptr, retlen] = passArray8ToWasm(give_vec_u8_target());
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_slice_u8_target = host.recv_slice_u8;
+
+export function recv_slice_u8(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = new Uint8Array(wasm.memory.buffer, arg0, len0);
+    recv_slice_u8_target(v0);
+}
+
+const recv_vec_i32_target = host.recv_vec_i32;
+
+export function recv_vec_i32(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getArrayI32FromWasm(arg0, len0);
+    wasm.__wbindgen_free(arg0, len0 * 4);
+    recv_vec_i32_target(v0);
+}
+
+const give_vec_i32_target = host.give_vec_i32;
+
+export function give_vec_i32() {
+    const [retptr, retlen] = passArray32ToWasm(give_vec_i32_target());
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_slice_i32_target = host.recv_slice_i32;
+
+export function recv_slice_i32(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = new Int32Array(wasm.memory.buffer, arg0, len0);
+    recv_slice_i32_target(v0);
+}
+
+const recv_vec_f64_target = host.recv_vec_f64;
+
+export function recv_vec_f64(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getArrayF64FromWasm(arg0, len0);
+    wasm.__wbindgen_free(arg0, len0 * 8);
+    recv_vec_f64_target(v0);
+}
+
+const give_vec_f64_target = host.give_vec_f64;
+
+export function give_vec_f64() {
+    const [retptr, retlen] = passArrayF64ToWasm(give_vec_f64_target());
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_slice_f64_target = host.recv_slice_f64;
+
+export function recv_slice_f64(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = new Float64Array(wasm.memory.buffer, arg0, len0);
+    recv_slice_f64_target(v0);
+}
+
+const recv_vec_anyref_target = host.recv_vec_anyref;
+
+export function recv_vec_anyref(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = takeArrayJsValueFromWasm(arg0, len0);
+    wasm.__wbindgen_free(arg0, len0 * 4);
+    recv_vec_anyref_target(v0);
+}
+
+const give_vec_anyref_target = host.give_vec_anyref;
+
+export function give_vec_anyref() {
+    const [retptr, retlen] = passArrayJsValueToWasm(give_vec_anyref_target());
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_slice_anyref_target = host.recv_slice_anyref;
+
+function getArrayU32FromWasm(ptr, len) {
+    const mem = getUint32Memory();
+    const slice = mem.slice(ptr / 4, ptr / 4 + len);
+    return new Uint32Array(slice);
+}
+
+function getArrayJsValueFromWasm(ptr, len) {
+    const mem = getUint32Memory();
+    const slice = mem.slice(ptr / 4, ptr / 4 + len);
+    const result = [];
+    for (let i = 0; i < slice.length; i++) {
+        result.push(getObject(slice[i]));
+    }
+    return result;
+}
+
+export function recv_slice_anyref(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getArrayJsValueFromWasm(arg0, len0);
+    recv_slice_anyref_target(v0);
+}
+
+const recv_option_vec_u8_target = host.recv_option_vec_u8;
+
+export function recv_option_vec_u8(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0;
+    if (arg0 !== 0) {
+        v0 = getArrayU8FromWasm(arg0, len0);
+        wasm.__wbindgen_free(arg0, len0 * 1);
+    }
+    recv_option_vec_u8_target(v0);
+}
+
+const give_option_vec_u8_target = host.give_option_vec_u8;
+
+export function give_option_vec_u8() {
+    const ret = give_option_vec_u8_target();
+    if (ret == null) {
+        setGlobalArgument(0, 0);
+        return 0;
+    }
+    const [retptr, retlen] = passArray8ToWasm(ret);
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_option_slice_u8_target = host.recv_option_slice_u8;
+
+export function recv_option_slice_u8(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0;
+    if (arg0 !== 0) {
+        v0 = getArrayU8FromWasm(arg0, len0);
+    }
+    recv_option_slice_u8_target(v0);
+}
+
+function __wbindgen_object_stats(sample) {
+    const stats = {
+        live: heap_live,
+        capacity: slab.length - 4,
+        borrowed: stack_pointer,
+        sample: [],
+    };
+    for (let i = 4; i < slab.length && stats.sample.length < (sample || 0); i++) {
+        const entry = slab[i];
+        if (typeof(entry) === 'number')
+            continue;
+        const ctor = entry.obj.constructor;
+        stats.sample.push(ctor && ctor.name ? ctor.name : typeof(entry.obj));
+    }
+    return stats;
+}
+
+export const debugObjectStats = __wbindgen_object_stats;
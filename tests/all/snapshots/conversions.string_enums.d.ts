@@ -0,0 +1,4 @@
+/* tslint:disable */
+export function takes_mode(arg0: "fast" | "slow"): void;
+
+export function returns_mode(): "fast" | "slow";
@@ -0,0 +1,35 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+const __wbg_Mode_values = ["fast", "slow"];
+
+function _stringEnumIndex(values, s, what) {
+    const idx = values.indexOf(s);
+    if (idx === -1)
+        throw new Error('expected one of ' +
+            values.map(function(v) { return JSON.stringify(v); }).join(', ') +
+            ', found ' + s + ' (' + what + ')');
+    return idx;
+}
+
+export function takes_mode(arg0) {
+    return wasm.takes_mode(_stringEnumIndex(__wbg_Mode_values, arg0, 'argument 0 of `takes_mode`'));
+}
+
+export function returns_mode() {
+    const ret = wasm.returns_mode();
+    return __wbg_Mode_values[ret];
+}
+
+const recv_mode_target = host.recv_mode;
+
+export function recv_mode(arg0) {
+    recv_mode_target(__wbg_Mode_values[arg0]);
+}
+
+const give_mode_target = host.give_mode;
+
+export function give_mode() {
+    const ret = give_mode_target();
+    return _stringEnumIndex(__wbg_Mode_values, ret, 'return value');
+}
@@ -0,0 +1,14 @@
+/* tslint:disable */
+export function takes_string(arg0: string): void;
+
+export function returns_string(): string;
+
+export function takes_str(arg0: string): void;
+
+export function takes_option_string(arg0?: string): void;
+
+export function returns_option_string(): string | undefined;
+
+export function takes_option_str(arg0?: string): void;
+
+export function measure(arg0: string): Promise<number>;
@@ -0,0 +1,203 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+
+function _assertString(s, what) {
+    if (typeof(s) !== 'string')
+        throw new Error('expected a string argument, found ' + typeof(s) + ' (' + what + ')');
+}
+
+const TextEncoder = typeof self === 'object' && self.TextEncoder
+    ? self.TextEncoder
+    : require('util').TextEncoder;
+
+let cachedEncoder = new TextEncoder('utf-8');
+
+let cachedUint8Memory = null;
+function getUint8Memory() {
+    if (cachedUint8Memory === null ||
+            cachedUint8Memory.buffer !== wasm.memory.buffer)
+    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+    return cachedUint8Memory;
+}
+
+function passStringToWasm(arg) {
+    if (typeof(arg) !== 'string')
+        throw new Error('expected a string argument');
+
+    const buf = cachedEncoder.encode(arg);
+    const ptr = wasm.__wbindgen_malloc(buf.length);
+    getUint8Memory().set(buf, ptr);
+    return [ptr, buf.length];
+}
+
+let cachedUint32Memory = null;
+function getUint32Memory() {
+    if (cachedUint32Memory === null ||
+            cachedUint32Memory.buffer !== wasm.memory.buffer)
+    cachedUint32Memory = new Uint32Array(wasm.memory.buffer);
+    return cachedUint32Memory;
+}
+
+let cachedGlobalArgumentPtr = null;
+function globalArgumentPtr() {
+    if (cachedGlobalArgumentPtr === null)
+        cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+    return cachedGlobalArgumentPtr;
+}
+
+function setGlobalArgument(arg, i) {
+    const idx = globalArgumentPtr() / 4 + i;
+    getUint32Memory()[idx] = arg;
+}
+
+export function takes_string(arg0) {
+    _assertString(arg0, 'argument 0 of `takes_string`');
+    const [ptr0, len0] = passStringToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_string(ptr0);
+}
+
+const TextDecoder = typeof self === 'object' && self.TextDecoder
+    ? self.TextDecoder
+    : require('util').TextDecoder;
+
+let cachedDecoder = new TextDecoder('utf-8');
+
+function getStringFromWasm(ptr, len) {
+    return cachedDecoder.decode(getUint8Memory().slice(ptr, ptr + len));
+}
+
+function getGlobalArgument(arg) {
+    const idx = globalArgumentPtr() / 4 + arg;
+    return getUint32Memory()[idx];
+}
+
+export function returns_string() {
+    const ret = wasm.returns_string();
+    const len = getGlobalArgument(0);
+    const realRet = getStringFromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 1);
+    return realRet;
+}
+
+export function takes_str(arg0) {
+    _assertString(arg0, 'argument 0 of `takes_str`');
+    const [ptr0, len0] = passStringToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_str(ptr0);
+    } finally {
+        wasm.__wbindgen_free(ptr0, len0 * 1);
+    }
+}
+
+export function takes_option_string(arg0) {
+    if (arg0 != null) {
+        _assertString(arg0, 'argument 0 of `takes_option_string`');
+    }
+    const [ptr0, len0] = arg0 == null ? [0, 0] : passStringToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    return wasm.takes_option_string(ptr0);
+}
+
+export function returns_option_string() {
+    const ret = wasm.returns_option_string();
+    if (ret === 0)
+        return undefined;
+    const len = getGlobalArgument(0);
+    const realRet = getStringFromWasm(ret, len);
+    wasm.__wbindgen_free(ret, len * 1);
+    return realRet;
+}
+
+export function takes_option_str(arg0) {
+    if (arg0 != null) {
+        _assertString(arg0, 'argument 0 of `takes_option_str`');
+    }
+    const [ptr0, len0] = arg0 == null ? [0, 0] : passStringToWasm(arg0);
+    setGlobalArgument(len0, 0);
+    try {
+        return wasm.takes_option_str(ptr0);
+    } finally {
+        if (ptr0 !== 0)
+            wasm.__wbindgen_free(ptr0, len0 * 1);
+    }
+}
+
+export function measure(arg0) {
+    try {
+        return Promise.resolve((() => {
+            _assertString(arg0, 'argument 0 of `measure`');
+            const [ptr0, len0] = passStringToWasm(arg0);
+            setGlobalArgument(len0, 0);
+            try {
+                return wasm.measure(ptr0);
+            } finally {
+                wasm.__wbindgen_free(ptr0, len0 * 1);
+            }
+        })());
+    } catch (e) {
+        return Promise.reject(e);
+    }
+}
+
+const recv_string_target = host.recv_string;
+
+export function recv_string(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getStringFromWasm(arg0, len0);
+    wasm.__wbindgen_free(arg0, len0 * 1);
+    recv_string_target(v0);
+}
+
+const give_string_target = host.give_string;
+
+export function give_string() {
+    const [retptr, retlen] = passStringToWasm(give_string_target());
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_str_target = host.recv_str;
+
+export function recv_str(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0 = getStringFromWasm(arg0, len0);
+    recv_str_target(v0);
+}
+
+const recv_option_string_target = host.recv_option_string;
+
+export function recv_option_string(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0;
+    if (arg0 !== 0) {
+        v0 = getStringFromWasm(arg0, len0);
+        wasm.__wbindgen_free(arg0, len0 * 1);
+    }
+    recv_option_string_target(v0);
+}
+
+const give_option_string_target = host.give_option_string;
+
+export function give_option_string() {
+    const ret = give_option_string_target();
+    if (ret == null) {
+        setGlobalArgument(0, 0);
+        return 0;
+    }
+    const [retptr, retlen] = passStringToWasm(ret);
+    setGlobalArgument(retlen, 0);
+    return retptr;
+}
+
+const recv_option_str_target = host.recv_option_str;
+
+export function recv_option_str(arg0) {
+    let len0 = getGlobalArgument(0);
+    let v0;
+    if (arg0 !== 0) {
+        v0 = getStringFromWasm(arg0, len0);
+    }
+    recv_option_str_target(v0);
+}
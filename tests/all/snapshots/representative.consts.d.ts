@@ -0,0 +1,32 @@
+/* tslint:disable */
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+export const VERSION: string;
+
+export const LIMIT: number;
+
+export const ENABLED: boolean;
+
+export const MAX: number;
+
+export const MIN: number;
+
+export const UNKNOWN: number;
+
+export class Counter {
+    readonly count: number;
+    free(): void;
+    static new(arg0: number): Counter;
+    add(arg0: number): number;
+}
+
+export interface Imports {
+    './logger': {
+        log(arg0: number): void;
+    };
+}
@@ -0,0 +1,33 @@
+import wasm_url from './out_bg.wasm';
+let imports = {};
+import * as import0 from './out';
+imports['./out'] = import0;
+function __wbg_instantiate(source, imports) {
+    let instantiated;
+    if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+        instantiated = WebAssembly.instantiate(source, imports);
+    } else {
+        instantiated = fetch(source).then(function(response) {
+            const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+            const streams = typeof WebAssembly.instantiateStreaming === 'function';
+            if (streams && type.toLowerCase() === 'application/wasm') {
+                return WebAssembly.instantiateStreaming(response, imports);
+            }
+            return response.arrayBuffer()
+                .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+        });
+    }
+    return instantiated.catch(function(e) {
+        if (!/Content Security Policy|\bCSP\b|unsafe-eval/.test(String(e && e.message)))
+            throw e;
+        const error = new Error('compiling the wasm was blocked by the Content Security Policy, ' +
+            "its script-src must allow 'wasm-unsafe-eval' (or 'unsafe-eval' " +
+            'in older browsers): ' + e.message);
+        error.name = 'WasmCspError';
+        error.cause = e;
+        throw error;
+    });
+}
+
+export const ready = __wbg_instantiate(wasm_url, imports)
+    .then(({instance}) => instance.exports);
@@ -0,0 +1,22 @@
+/* tslint:disable */
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+export class Counter {
+    readonly count: number;
+    free(): void;
+    toJSON(): any;
+    toString(): string;
+    static new(arg0: number): Counter;
+    add(arg0: number): number;
+}
+
+export interface Imports {
+    './logger': {
+        log(arg0: number): void;
+    };
+}
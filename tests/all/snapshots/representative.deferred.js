@@ -0,0 +1,54 @@
+/* tslint:disable */
+var wasm = new Proxy({}, {
+    get() {
+        throw new Error('wasm not yet initialized; call `setWasm` first');
+    },
+});
+import { log } from './logger';
+
+export function halve(arg0) {
+    return wasm.halve(arg0);
+}
+
+export function __wbg_f_log_log_n(arg0) {
+    log(arg0);
+}
+
+export const Color = Object.freeze({ Red: 0, Green: 1 });
+
+export class Counter {
+    static __construct(ptr) {
+        return new Counter(ptr);
+    }
+
+    constructor(ptr) {
+        this.ptr = ptr;
+    }
+
+    get count() {
+        return wasm.__wbg_get_counter_count(this.ptr);
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Counter';
+    }
+
+    static new(arg0) {
+        return Counter.__construct(wasm.counter_new(arg0));
+    }
+
+    add(arg0) {
+        return wasm.counter_add(this.ptr, arg0);
+    }
+}
+
+export function setWasm(instance) {
+    wasm = instance.exports;
+}
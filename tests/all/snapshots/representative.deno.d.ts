@@ -0,0 +1,20 @@
+/* tslint:disable */
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+export class Counter {
+    readonly count: number;
+    free(): void;
+    static new(arg0: number): Counter;
+    add(arg0: number): number;
+}
+
+export interface Imports {
+    './logger.js': {
+        log(arg0: number): void;
+    };
+}
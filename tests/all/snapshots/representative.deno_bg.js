@@ -0,0 +1,28 @@
+const wasm_url = new URL('./out_bg.wasm', import.meta.url);
+let imports = {};
+import * as import0 from './out.js';
+imports['./out.js'] = import0;
+function __wbg_instantiate(source, imports) {
+    let instantiated;
+    if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+        instantiated = WebAssembly.instantiate(source, imports);
+    } else {
+        instantiated = fetch(source).then(function(response) {
+            const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+            const streams = typeof WebAssembly.instantiateStreaming === 'function';
+            if (streams && type.toLowerCase() === 'application/wasm') {
+                return WebAssembly.instantiateStreaming(response, imports);
+            }
+            return response.arrayBuffer()
+                .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+        });
+    }
+    return instantiated;
+}
+
+const wasm_source = wasm_url.protocol === 'file:'
+    ? Deno.readFile(wasm_url)
+    : Promise.resolve(wasm_url);
+export const ready = wasm_source
+    .then(source => __wbg_instantiate(source, imports))
+    .then(({instance}) => instance.exports);
@@ -0,0 +1,33 @@
+/* tslint:disable */
+/**
+ * @deprecated use `quarter` *\/ instead
+ */
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+/**
+ * @deprecated counters are
+ * no longer needed
+ */
+export class Counter {
+    constructor(...args: any[]);
+    readonly count: number;
+    free(): void;
+    toJSON(): any;
+    toString(): string;
+    static new(arg0: number): Counter;
+    /**
+     * @deprecated
+     */
+    add(arg0: number): number;
+}
+
+export interface Imports {
+    './logger': {
+        log(arg0: number): void;
+    };
+}
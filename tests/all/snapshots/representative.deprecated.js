@@ -0,0 +1,117 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+import { log } from './logger';
+
+const warnedDeprecations = {};
+
+function _warnDeprecated(name, message) {
+    if (warnedDeprecations[name] === true)
+        return;
+    warnedDeprecations[name] = true;
+    console.warn(message);
+}
+
+function _assertNum(n, what) {
+    if (typeof(n) !== 'number')
+        throw new Error('expected a number argument, found ' + typeof(n) + ' (' + what + ')');
+}
+
+/**
+ * @deprecated use `quarter` *\/ instead
+ */
+export function halve(arg0) {
+    _warnDeprecated("halve", "`halve` is deprecated: use `quarter` */ instead");
+    _assertNum(arg0, 'argument 0 of `halve`');
+    return wasm.halve(arg0);
+}
+
+function _assertInt(n, min, max, what) {
+    _assertNum(n, what);
+    if (!Number.isInteger(n) || n < min || n > max)
+        throw new Error('expected an integer argument in [' + min + ', ' + max + '], found ' +
+            n + ' (' + what + ')');
+}
+
+export function __wbg_f_log_log_n(arg0) {
+    log(arg0);
+}
+
+export const Color = Object.freeze({ Red: 0, Green: 1 });
+
+class ConstructorToken {
+    constructor(ptr) {
+        this.ptr = ptr;
+    }
+}
+
+/**
+ * @deprecated counters are
+ * no longer needed
+ */
+export class Counter {
+    static __construct(ptr) {
+        return new Counter(new ConstructorToken(ptr));
+    }
+
+    constructor(...args) {
+        if (args.length === 1 && args[0] instanceof ConstructorToken) {
+            this.ptr = args[0].ptr;
+            return;
+        }
+        _warnDeprecated("new Counter", "`new Counter` is deprecated: counters are\nno longer needed");
+
+        // This invocation of new will call this constructor with a ConstructorToken
+        let instance = Counter.new(...args);
+        this.ptr = instance.ptr;
+    }
+
+    get count() {
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Counter.count');
+        return wasm.__wbg_get_counter_count(this.ptr);
+    }
+
+    set count(_value) {
+        throw new TypeError("cannot assign to readonly field 'count' of Counter");
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        if (ptr === 0) {
+            console.warn('Attempt to free an already freed value: Counter');
+            return;
+        }
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Counter';
+    }
+
+    toJSON() {
+        return { count: this.count };
+    }
+
+    toString() {
+        return 'Counter { ptr: ' + this.ptr + ' }';
+    }
+
+    static new(arg0) {
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `new`');
+        return Counter.__construct(wasm.counter_new(arg0));
+    }
+
+    /**
+     * @deprecated
+     */
+    add(arg0) {
+        _warnDeprecated("Counter.add", "`Counter.add` is deprecated");
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Counter.add');
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `add`');
+        return wasm.counter_add(this.ptr, arg0);
+    }
+}
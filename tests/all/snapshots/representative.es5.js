@@ -0,0 +1,144 @@
+(function() {
+    var wasm;
+    var __exports = {};
+    var __global = typeof globalThis !== 'undefined' ? globalThis : self;
+
+    function _assertNum(n, what) {
+        if (typeof(n) !== 'number')
+            throw new Error('expected a number argument, found ' + typeof(n) + ' (' + what + ')');
+    }
+
+    __exports.halve = function(arg0) {
+        _assertNum(arg0, 'argument 0 of `halve`');
+        return wasm.halve(arg0);
+    }
+
+    function _assertInt(n, min, max, what) {
+        _assertNum(n, what);
+        if (!Number.isInteger(n) || n < min || n > max)
+            throw new Error('expected an integer argument in [' + min + ', ' + max + '], found ' +
+                n + ' (' + what + ')');
+    }
+
+    __exports.__wbg_f_log_log_n = function(arg0) {
+        log(arg0);
+    }
+
+    __exports.Color = Object.freeze({ Red: 0, Green: 1 })
+
+    function ConstructorToken(ptr) {
+        this.ptr = ptr;
+    }
+
+    function Counter() {
+        var args = Array.prototype.slice.call(arguments);
+
+        if (args.length === 1 && args[0] instanceof ConstructorToken) {
+            this.ptr = args[0].ptr;
+            return;
+        }
+        throw new Error('you cannot invoke `new` directly without having a method annotated a constructor');
+    }
+    Counter.__construct = function(ptr) {
+        return new Counter(new ConstructorToken(ptr));
+    };
+
+    Object.defineProperty(Counter.prototype, 'count', {
+        get: function() {
+            if (this.ptr === 0)
+                throw new Error('Attempt to use a moved value: Counter.count');
+            return wasm.__wbg_get_counter_count(this.ptr);
+        },
+        configurable: true,
+    });
+
+    Object.defineProperty(Counter.prototype, 'count', {
+        set: function(_value) {
+            throw new TypeError("cannot assign to readonly field 'count' of Counter");
+        },
+        configurable: true,
+    });
+
+    Counter.prototype.free = function() {
+        var ptr = this.ptr;
+
+        if (ptr === 0) {
+            console.warn('Attempt to free an already freed value: Counter');
+            return;
+        }
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    };
+
+    if (typeof Symbol === 'function') {
+        Object.defineProperty(Counter.prototype, Symbol.toStringTag, {
+            get: function() {
+                return 'Counter';
+            },
+            configurable: true,
+        });
+    }
+
+    Counter.prototype.toJSON = function() {
+        return { count: this.count };
+    };
+
+    Counter.prototype.toString = function() {
+        return 'Counter { ptr: ' + this.ptr + ' }';
+    };
+
+    Counter.new = function(arg0) {
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `new`');
+        return Counter.__construct(wasm.counter_new(arg0));
+    };
+
+    Counter.prototype.add = function(arg0) {
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Counter.add');
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `add`');
+        return wasm.counter_add(this.ptr, arg0);
+    };
+
+    __exports.Counter = Counter
+
+    function __wbg_instantiate(source, imports) {
+        var instantiated;
+        if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+            instantiated = WebAssembly.instantiate(source, imports);
+        } else {
+            instantiated = fetch(source).then(function(response) {
+                var type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+                var streams = typeof WebAssembly.instantiateStreaming === 'function';
+                if (streams && type.toLowerCase() === 'application/wasm') {
+                    return WebAssembly.instantiateStreaming(response, imports);
+                }
+                return response.arrayBuffer()
+                    .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+            });
+        }
+        return instantiated;
+    }
+
+    function __wbg_script_dir() {
+        var url = typeof location !== 'undefined' ? location.href : '';
+        if (typeof document !== 'undefined' && document.currentScript) {
+            url = document.currentScript.src;
+        }
+        return url.split(/[?#]/)[0].replace(/[^\/]*$/, '');
+    }
+    var __wbg_default_input = __wbg_script_dir() + 'out_bg.wasm';
+
+    function init(input) {
+        if (input === undefined) input = __wbg_default_input;
+        return __wbg_instantiate(input, { './out': __exports })
+            .then(function(result) {
+                wasm = init.wasm = result.instance.exports;
+                return init;
+            });
+    };
+    Object.keys(__exports).forEach(function(key) {
+        init[key] = __exports[key];
+    });
+    __global.wasm_bindgen = init;
+})();
@@ -0,0 +1,60 @@
+/* tslint:disable */
+import * as wasm from './out_bg';
+import * as __wbg_module0 from './logger';
+
+export function halve(arg0) {
+    return wasm.halve(arg0);
+}
+
+let __wbg_f_log_log_n_target;
+function __wbg_f_log_log_n_lookup() {
+    if (__wbg_f_log_log_n_target === undefined) {
+        let target;
+        try {
+            target = __wbg_module0.log;
+        } catch (e) {}
+        if (typeof(target) !== 'function')
+            throw new Error("imported function `log` is not available");
+        __wbg_f_log_log_n_target = target;
+    }
+    return __wbg_f_log_log_n_target;
+}
+
+export function __wbg_f_log_log_n(arg0) {
+    __wbg_f_log_log_n_lookup()(arg0);
+}
+
+export const Color = Object.freeze({ Red: 0, Green: 1 });
+
+export class Counter {
+    static __construct(ptr) {
+        return new Counter(ptr);
+    }
+
+    constructor(ptr) {
+        this.ptr = ptr;
+    }
+
+    get count() {
+        return wasm.__wbg_get_counter_count(this.ptr);
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Counter';
+    }
+
+    static new(arg0) {
+        return Counter.__construct(wasm.counter_new(arg0));
+    }
+
+    add(arg0) {
+        return wasm.counter_add(this.ptr, arg0);
+    }
+}
@@ -0,0 +1,21 @@
+import*as wasm from'./out_bg';import{log}from'./logger';function f(n,d){if(typeof(n)!=='number')
+throw new Error('expected a number argument, found '+typeof(n)+' ('+d+')');}
+export function halve(b){f(b,'argument 0 of `halve`');return wasm.halve(b);}
+function e(n,h,g,d){f(n,d);if(!Number.isInteger(n)||n<h||n>g)
+throw new Error('expected an integer argument in ['+h+', '+g+'], found '+n+' ('+d+')');}
+export function __wbg_f_log_log_n(b){log(b);}
+export const Color=Object.freeze({Red:0,Green:1});class ConstructorToken{constructor(a){this.ptr=a;}}
+export class Counter{static __construct(a){return new Counter(new ConstructorToken(a));}
+constructor(...args){if(args.length===1&&args[0]instanceof ConstructorToken){this.ptr=args[0].ptr;return;}
+throw new Error('you cannot invoke `new` directly without having a method annotated a constructor');}
+get count(){if(this.ptr===0)
+throw new Error('Attempt to use a moved value: Counter.count');return wasm.__wbg_get_counter_count(this.ptr);}
+set count(_value){throw new TypeError("cannot assign to readonly field 'count' of Counter");}
+free(){const a=this.ptr;if(a===0){console.warn('Attempt to free an already freed value: Counter');return;}
+this.ptr=0;wasm.__wbg_counter_free(a);}
+get[Symbol.toStringTag](){return'Counter';}
+toJSON(){return{count:this.count};}
+toString(){return'Counter { ptr: '+this.ptr+' }';}
+static new(b){e(b,0,4294967295,'argument 0 of `new`');return Counter.__construct(wasm.counter_new(b));}
+add(b){if(this.ptr===0)
+throw new Error('Attempt to use a moved value: Counter.add');e(b,0,4294967295,'argument 0 of `add`');return wasm.counter_add(this.ptr,b);}}
@@ -0,0 +1,23 @@
+/* tslint:disable */
+declare namespace my.app {
+    function wasm(input?: string | ArrayBuffer | ArrayBufferView): Promise<typeof wasm>;
+    namespace wasm {
+        export function halve(arg0: number): number;
+
+        export enum Color {
+            Red,
+            Green,
+        }
+
+        export class Counter {
+            readonly count: number;
+            free(): void;
+            static new(arg0: number): Counter;
+            add(arg0: number): number;
+        }
+
+        export interface Imports {
+            log(arg0: number): void;
+        }
+    }
+}
@@ -0,0 +1,9 @@
+import { promises as fs } from 'fs';
+const wasm_url = new URL('./out_bg.wasm', import.meta.url);
+let imports = {};
+import * as import0 from './out.js';
+imports['./out.js'] = import0;
+
+export const ready = fs.readFile(wasm_url)
+    .then(bytes => WebAssembly.instantiate(bytes, imports))
+    .then(({instance}) => instance.exports);
@@ -0,0 +1,24 @@
+/* tslint:disable */
+
+/// <reference types="feature" />
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+export class Counter {
+    readonly count: number;
+    free(): void;
+    static new(arg0: number): Counter;
+    add(arg0: number): number;
+}
+
+export interface Imports {
+    './logger': {
+        log(arg0: number): void;
+    };
+}
+
+declare const registry: Registry;
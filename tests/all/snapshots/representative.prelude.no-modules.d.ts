@@ -0,0 +1,25 @@
+/* tslint:disable */
+
+/// <reference types="feature" />
+declare function wasm_bindgen(input?: string | ArrayBuffer | ArrayBufferView): Promise<typeof wasm_bindgen>;
+declare namespace wasm_bindgen {
+    export function halve(arg0: number): number;
+
+    export enum Color {
+        Red,
+        Green,
+    }
+
+    export class Counter {
+        readonly count: number;
+        free(): void;
+        static new(arg0: number): Counter;
+        add(arg0: number): number;
+    }
+
+    export interface Imports {
+        log(arg0: number): void;
+    }
+}
+
+declare const registry: Registry;
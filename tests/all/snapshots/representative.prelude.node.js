@@ -0,0 +1,54 @@
+/* tslint:disable */
+var wasm;
+const log = require('./logger').log;
+
+if (typeof Feature === 'undefined') {
+  polyfillFeature();
+}
+
+module.exports.halve = function(arg0) {
+    return wasm.halve(arg0);
+};
+
+module.exports.__wbg_f_log_log_n = function(arg0) {
+    log(arg0);
+};
+
+module.exports.Color = Object.freeze({ Red: 0, Green: 1 });
+
+class Counter {
+    static __construct(ptr) {
+        return new Counter(ptr);
+    }
+
+    constructor(ptr) {
+        this.ptr = ptr;
+    }
+
+    get count() {
+        return wasm.__wbg_get_counter_count(this.ptr);
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Counter';
+    }
+
+    static new(arg0) {
+        return Counter.__construct(wasm.counter_new(arg0));
+    }
+
+    add(arg0) {
+        return wasm.counter_add(this.ptr, arg0);
+    }
+}
+module.exports.Counter = Counter;
+
+wasm = require('./out_bg');
+  registry.loaded('out');
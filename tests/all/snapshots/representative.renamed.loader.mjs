@@ -0,0 +1,24 @@
+import wasm_url from './out.module.wasm';
+let imports = {};
+import * as import0 from './out.mjs';
+imports['./out.mjs'] = import0;
+function __wbg_instantiate(source, imports) {
+    let instantiated;
+    if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+        instantiated = WebAssembly.instantiate(source, imports);
+    } else {
+        instantiated = fetch(source).then(function(response) {
+            const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+            const streams = typeof WebAssembly.instantiateStreaming === 'function';
+            if (streams && type.toLowerCase() === 'application/wasm') {
+                return WebAssembly.instantiateStreaming(response, imports);
+            }
+            return response.arrayBuffer()
+                .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+        });
+    }
+    return instantiated;
+}
+
+export const ready = __wbg_instantiate(wasm_url, imports)
+    .then(({instance}) => instance.exports);
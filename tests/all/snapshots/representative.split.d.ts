@@ -0,0 +1,3 @@
+/* tslint:disable */
+export * from './out_core';
+export * from './out_class_Counter';
@@ -0,0 +1,3 @@
+/* tslint:disable */
+export { Color, __wbg_f_log_log_n, halve } from './out_core';
+export { Counter } from './out_class_Counter';
@@ -0,0 +1,9 @@
+/* tslint:disable */
+export class Counter {
+    readonly count: number;
+    free(): void;
+    toJSON(): any;
+    toString(): string;
+    static new(arg0: number): Counter;
+    add(arg0: number): number;
+}
@@ -0,0 +1,62 @@
+/* tslint:disable */
+import { ConstructorToken, _assertInt, wasm } from './out_core';
+
+export class Counter {
+    static __construct(ptr) {
+        return new Counter(new ConstructorToken(ptr));
+    }
+
+    constructor(...args) {
+        if (args.length === 1 && args[0] instanceof ConstructorToken) {
+            this.ptr = args[0].ptr;
+            return;
+        }
+        throw new Error('you cannot invoke `new` directly without having a method annotated a constructor');
+    }
+
+    get count() {
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Counter.count');
+        return wasm.__wbg_get_counter_count(this.ptr);
+    }
+
+    set count(_value) {
+        throw new TypeError("cannot assign to readonly field 'count' of Counter");
+    }
+
+    free() {
+        const ptr = this.ptr;
+
+        if (ptr === 0) {
+            console.warn('Attempt to free an already freed value: Counter');
+            return;
+        }
+
+        this.ptr = 0;
+        wasm.__wbg_counter_free(ptr);
+    }
+
+    get [Symbol.toStringTag]() {
+        return 'Counter';
+    }
+
+    toJSON() {
+        return { count: this.count };
+    }
+
+    toString() {
+        return 'Counter { ptr: ' + this.ptr + ' }';
+    }
+
+    static new(arg0) {
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `new`');
+        return Counter.__construct(wasm.counter_new(arg0));
+    }
+
+    add(arg0) {
+        if (this.ptr === 0)
+            throw new Error('Attempt to use a moved value: Counter.add');
+        _assertInt(arg0, 0, 4294967295, 'argument 0 of `add`');
+        return wasm.counter_add(this.ptr, arg0);
+    }
+}
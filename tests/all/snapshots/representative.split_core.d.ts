@@ -0,0 +1,13 @@
+/* tslint:disable */
+export function halve(arg0: number): number;
+
+export enum Color {
+    Red,
+    Green,
+}
+
+export interface Imports {
+    './logger': {
+        log(arg0: number): void;
+    };
+}
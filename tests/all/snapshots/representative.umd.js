@@ -0,0 +1,97 @@
+(function(factory) {
+    if (typeof define === 'function' && define.amd) {
+        define([], factory);
+    } else if (typeof module === 'object' && module.exports) {
+        module.exports = factory();
+    } else {
+        const __global = typeof globalThis !== 'undefined' ? globalThis : self;
+        __global.wasm_bindgen = factory();
+    }
+})(function() {
+    var wasm;
+    const __exports = {};
+
+    __exports.halve = function(arg0) {
+        return wasm.halve(arg0);
+    }
+
+    __exports.__wbg_f_log_log_n = function(arg0) {
+        log(arg0);
+    }
+
+    __exports.Color = Object.freeze({ Red: 0, Green: 1 })
+
+    class Counter {
+        static __construct(ptr) {
+            return new Counter(ptr);
+        }
+
+        constructor(ptr) {
+            this.ptr = ptr;
+        }
+
+        get count() {
+            return wasm.__wbg_get_counter_count(this.ptr);
+        }
+
+        free() {
+            const ptr = this.ptr;
+
+            this.ptr = 0;
+            wasm.__wbg_counter_free(ptr);
+        }
+
+        get [Symbol.toStringTag]() {
+            return 'Counter';
+        }
+
+        static new(arg0) {
+            return Counter.__construct(wasm.counter_new(arg0));
+        }
+
+        add(arg0) {
+            return wasm.counter_add(this.ptr, arg0);
+        }
+    }
+    __exports.Counter = Counter;
+
+    function __wbg_instantiate(source, imports) {
+        let instantiated;
+        if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+            instantiated = WebAssembly.instantiate(source, imports);
+        } else {
+            instantiated = fetch(source).then(function(response) {
+                const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+                const streams = typeof WebAssembly.instantiateStreaming === 'function';
+                if (streams && type.toLowerCase() === 'application/wasm') {
+                    return WebAssembly.instantiateStreaming(response, imports);
+                }
+                return response.arrayBuffer()
+                    .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+            });
+        }
+        return instantiated;
+    }
+
+    function __wbg_script_dir() {
+        let url = typeof location !== 'undefined' ? location.href : '';
+        if (typeof document !== 'undefined' && document.currentScript) {
+            url = document.currentScript.src;
+        }
+        return url.split(/[?#]/)[0].replace(/[^\/]*$/, '');
+    }
+    const __wbg_default_input = __wbg_script_dir() + 'out_bg.wasm';
+
+    function init(input) {
+        if (input === undefined && typeof __dirname === 'string') {
+            input = require('fs').readFileSync(require('path').join(__dirname, 'out_bg.wasm'));
+        }
+        if (input === undefined) input = __wbg_default_input;
+        return __wbg_instantiate(input, { './out': __exports })
+            .then(({instance}) => {
+                wasm = init.wasm = instance.exports;
+                return init;
+            });
+    };
+    return Object.assign(init, __exports);
+});
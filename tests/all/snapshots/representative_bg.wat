@@ -0,0 +1,34 @@
+(module
+  (type (;0;) (func (param i32)))
+  (type (;1;) (func (param f64) (result f64)))
+  (type (;2;) (func (param i32) (result i32)))
+  (type (;3;) (func (param i32 i32) (result i32)))
+  (import "./out" "__wbg_f_log_log_n" (func (;0;) (type 0)))
+  (memory (;0;) 1)
+  (export "memory" (memory 0))
+  (export "halve" (func 1))
+  (export "counter_new" (func 2))
+  (export "counter_add" (func 3))
+  (export "__wbg_counter_free" (func 4))
+  (export "__wbg_get_counter_count" (func 5))
+  (func (;1;) (type 1) (param f64) (result f64)
+    get_local 0
+    f64.const 0.5
+    f64.mul
+  )
+  (func (;2;) (type 2) (param i32) (result i32)
+    get_local 0
+  )
+  (func (;3;) (type 3) (param i32 i32) (result i32)
+    get_local 1
+    call 0
+    get_local 0
+    get_local 1
+    i32.add
+  )
+  (func (;4;) (type 0) (param i32)
+  )
+  (func (;5;) (type 2) (param i32) (result i32)
+    get_local 0
+  )
+)
@@ -0,0 +1,15 @@
+/* tslint:disable */
+
+function Frame() {
+    if (Frame.__wbg_owner === undefined) {
+        throw new Error('the glue of the input owning `Frame` is not loaded');
+    }
+    var args = [null].concat(Array.prototype.slice.call(arguments));
+    return new (Function.prototype.bind.apply(Frame.__wbg_owner, args))();
+}
+
+Frame.new = function() {
+    return Frame.__wbg_owner.new.apply(Frame.__wbg_owner, arguments);
+};
+
+module.exports.Frame = Frame;
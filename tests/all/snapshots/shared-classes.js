@@ -0,0 +1,19 @@
+/* tslint:disable */
+
+class Frame {
+    constructor(...args) {
+        if (new.target !== Frame) {
+            return;
+        }
+        if (Frame.__wbg_owner === undefined) {
+            throw new Error('the glue of the input owning `Frame` is not loaded');
+        }
+        return new Frame.__wbg_owner(...args);
+    }
+
+    static new(...args) {
+        return Frame.__wbg_owner.new(...args);
+    }
+}
+
+export { Frame };
@@ -0,0 +1,11 @@
+/* tslint:disable */
+declare function wasm_bindgen(input?: string | ArrayBuffer | ArrayBufferView, memory?: WebAssembly.Memory): Promise<typeof wasm_bindgen>;
+declare namespace wasm_bindgen {
+    export function get(): bigint;
+
+    /**
+     * The memory the wasm imports, to pass on to other instances sharing it like
+     * ones in workers.
+     */
+    export function wasmMemory(): WebAssembly.Memory;
+}
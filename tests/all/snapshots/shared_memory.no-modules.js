@@ -0,0 +1,53 @@
+(function() {
+    var wasm;
+    const __exports = {};
+    const __global = typeof globalThis !== 'undefined' ? globalThis : self;
+
+    __exports.get = function() {
+        return wasm.get();
+    }
+
+    __exports.wasmMemory = function() { return wasm.memory; }
+
+    function __wbg_instantiate(source, imports) {
+        let instantiated;
+        if (source instanceof ArrayBuffer || ArrayBuffer.isView(source)) {
+            instantiated = WebAssembly.instantiate(source, imports);
+        } else {
+            instantiated = fetch(source).then(function(response) {
+                const type = (response.headers.get('Content-Type') || '').split(';')[0].trim();
+                const streams = typeof WebAssembly.instantiateStreaming === 'function';
+                if (streams && type.toLowerCase() === 'application/wasm') {
+                    return WebAssembly.instantiateStreaming(response, imports);
+                }
+                return response.arrayBuffer()
+                    .then(function(bytes) { return WebAssembly.instantiate(bytes, imports); });
+            });
+        }
+        return instantiated;
+    }
+
+    function __wbg_script_dir() {
+        let url = typeof location !== 'undefined' ? location.href : '';
+        if (typeof document !== 'undefined' && document.currentScript) {
+            url = document.currentScript.src;
+        }
+        return url.split(/[?#]/)[0].replace(/[^\/]*$/, '');
+    }
+    const __wbg_default_input = __wbg_script_dir() + 'out_bg.wasm';
+
+    function init(input, memory) {
+        if (input === undefined) input = __wbg_default_input;
+        if (typeof SharedArrayBuffer === 'undefined')
+            throw new Error('the wasm memory is shared but SharedArrayBuffer is not available, ' +
+                'in browsers the page must be cross-origin isolated to use it');
+        memory = memory || new WebAssembly.Memory({ shared: true, initial: 1, maximum: 2 });
+        const imports = { './out': __exports, 'env': { 'memory': memory } };
+        return __wbg_instantiate(input, imports)
+            .then(({instance}) => {
+                wasm = init.wasm = Object.assign({ memory: memory }, instance.exports);
+                return init;
+            });
+    };
+    __global.wasm_bindgen = Object.assign(init, __exports);
+})();
@@ -0,0 +1,9 @@
+/* tslint:disable */
+var wasm;
+
+module.exports.get_value = function() {
+    return wasm.get_value();
+};
+
+wasm = require('./out_bg');
+wasm.__wbindgen_start();
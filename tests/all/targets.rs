@@ -0,0 +1,456 @@
+use std::io::Cursor;
+
+use cli::{self, Bindgen, OutputNames, Target, WasmImportMode};
+
+use super::MemoryOutput;
+use super::fixtures::*;
+
+#[test]
+fn nested_no_modules_global() {
+    let wasm = representative_wasm(None);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .no_modules(true)
+        .no_modules_global("my.app.wasm")
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.no-modules.js", &out.files["out.js"]);
+    assert_snapshot("representative.no-modules.d.ts", &out.files["out.d.ts"]);
+}
+
+#[test]
+fn invalid_no_modules_global() {
+    for name in ["my-bindings", "my..app", "my.class", "1st"].iter() {
+        let wasm = representative_wasm(None);
+        let mut bindgen = Bindgen::new();
+        bindgen.input_reader(Cursor::new(wasm), "out")
+            .no_modules(true)
+            .no_modules_global(name);
+        let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err();
+        assert!(err.to_string().contains("can't be used as the `no_modules` global"),
+                "unexpected error for `{}`: {}", name, err);
+    }
+}
+
+#[test]
+fn worker_no_modules() {
+    let wasm = representative_wasm(None);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .no_modules(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // Load the glue with `importScripts` in a context without any of the
+    // DOM, like a worker, from both a URL and the bytes themselves. The URL
+    // is served both as `application/wasm`, which is compiled while it's
+    // streamed, and with a type `instantiateStreaming` would reject. Without
+    // a URL the wasm is fetched from next to the worker's script, and on a
+    // page from next to the glue's.
+    run_node("worker", &out.files, &[], r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const vm = require('vm');
+            const dir = process.argv[1];
+            const logged = [];
+            const fetched = [];
+            let served = 'application/wasm';
+            const fetch = url => {
+                fetched.push(url);
+                const bytes = fs.readFileSync(path.join(dir, path.basename(url)));
+                return Promise.resolve(new Response(bytes, {
+                    headers: { 'Content-Type': served },
+                }));
+            };
+            const ctx = vm.createContext({
+                console,
+                log: n => logged.push(n),
+                fetch,
+                location: { href: 'https://example.com/app/worker.js?v=1' },
+                importScripts: file => {
+                    vm.runInContext(fs.readFileSync(path.join(dir, file), 'utf8'), ctx);
+                },
+            });
+            vm.runInContext('self = this', ctx);
+            let streamed = 0;
+            const wasm = vm.runInContext('WebAssembly', ctx);
+            const instantiateStreaming = wasm.instantiateStreaming;
+            wasm.instantiateStreaming = function() {
+                streamed += 1;
+                return instantiateStreaming.apply(this, arguments);
+            };
+            vm.runInContext('importScripts("out.js")', ctx);
+            const bindings = ctx.self.wasm_bindgen;
+            const check = result => {
+                assert.strictEqual(result, bindings);
+                assert.strictEqual(result.halve(3), 1.5);
+                assert.strictEqual(result.Counter.new(2).add(3), 5);
+                assert.deepStrictEqual(logged.splice(0), [3]);
+            };
+            bindings('out_bg.wasm')
+                .then(check)
+                .then(() => assert.strictEqual(streamed, 1))
+                .then(() => { served = 'application/octet-stream'; })
+                .then(() => bindings('out_bg.wasm'))
+                .then(check)
+                .then(() => bindings(fs.readFileSync(path.join(dir, 'out_bg.wasm'))))
+                .then(check)
+                .then(() => assert.strictEqual(streamed, 1))
+                .then(() => bindings())
+                .then(check)
+                .then(() => {
+                    const page = vm.createContext({
+                        console,
+                        log: n => logged.push(n),
+                        fetch,
+                        location: { href: 'https://example.com/index.html' },
+                        document: { currentScript: { src: 'https://example.com/js/out.js' } },
+                    });
+                    vm.runInContext('self = this', page);
+                    vm.runInContext(fs.readFileSync(path.join(dir, 'out.js'), 'utf8'), page);
+                    return page.self.wasm_bindgen();
+                })
+                .then(result => result.halve(3))
+                .then(() => assert.deepStrictEqual(fetched.splice(2), [
+                    'https://example.com/app/out_bg.wasm',
+                    'https://example.com/js/out_bg.wasm',
+                ]))
+                .catch(e => { console.error(e); process.exit(1); });
+        "#);
+}
+
+#[test]
+fn umd_module() {
+    let wasm = representative_wasm(None);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm.clone()), "out")
+        .no_modules(true)
+        .umd(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+    assert_snapshot("representative.umd.js", &out.files["out.js"]);
+    assert_snapshot("representative.umd.d.ts", &out.files["out.d.ts"]);
+
+    // The same file is loaded with `require`, `define` and a script.
+    run_node("umd", &out.files, &[], r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const vm = require('vm');
+            const dir = process.argv[1];
+            const js = fs.readFileSync(path.join(dir, 'out.js'), 'utf8');
+            const bytes = fs.readFileSync(path.join(dir, 'out_bg.wasm'));
+            const logged = [];
+            global.log = n => logged.push(n);
+            const check = init => init(bytes).then(result => {
+                assert.strictEqual(result, init);
+                assert.strictEqual(result.Counter.new(2).add(3), 5);
+            });
+
+            const required = require(path.join(dir, 'out.js'));
+            let defined;
+            const amd = vm.createContext({ log: global.log });
+            amd.define = (deps, factory) => {
+                assert.strictEqual(deps.length, 0);
+                defined = factory();
+            };
+            amd.define.amd = {};
+            vm.runInContext(js, amd);
+            const script = vm.createContext({ log: global.log });
+            vm.runInContext('self = this', script);
+            vm.runInContext(js, script);
+            assert.strictEqual(script.self.wasm_bindgen.name, 'init');
+
+            required()
+                .then(result => assert.strictEqual(result.halve(3), 1.5))
+                .then(() => check(required))
+                .then(() => check(defined))
+                .then(() => check(script.self.wasm_bindgen))
+                .then(() => assert.deepStrictEqual(logged, [3, 3, 3]))
+                .catch(e => { console.error(e); process.exit(1); });
+        "#);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .add_input_path("worker.wasm")
+        .no_modules(true)
+        .umd(true);
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert_eq!(err, "`umd` can't be used with multiple inputs");
+}
+
+#[test]
+fn fetch_url_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .wasm_import_mode(WasmImportMode::FetchUrl)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.fetch-url.js", &out.files["out.js"]);
+    assert_snapshot("representative.fetch-url_bg.js", &out.files["out_bg.js"]);
+    assert_snapshot("representative.fetch-url.d.ts", &out.files["out.d.ts"]);
+}
+
+#[test]
+fn deferred_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .wasm_import_mode(WasmImportMode::Deferred)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert!(!out.files.contains_key("out_bg.js"));
+    assert_snapshot("representative.deferred.js", &out.files["out.js"]);
+    assert_snapshot("representative.deferred.d.ts", &out.files["out.d.ts"]);
+}
+
+#[test]
+fn bundler_module() {
+    let wasm = representative_wasm(Some("./logger"));
+    let js = |configure: fn(&mut Bindgen), file: &str| {
+        let mut bindgen = Bindgen::new();
+        bindgen.input_reader(Cursor::new(wasm.clone()), "out");
+        configure(&mut bindgen);
+        let mut out = MemoryOutput::default();
+        bindgen.generate_to(&mut out).expect("failed to run bindgen");
+        String::from_utf8(out.files[file].clone()).unwrap()
+    };
+
+    let default = js(|_| {}, "out.js");
+    assert!(default.contains("import * as wasm from './out_bg';"));
+    let bundler = js(|b| { b.bundler(true); }, "out.js");
+    assert_eq!(bundler, default.replace("'./out_bg'", "'./out_bg.wasm'"));
+    let target = js(|b| { b.targets(&[Target::bundler()]); }, "bundler/out.js");
+    assert_eq!(target, bundler.replace("'./out_bg.wasm'", "'../out_bg.wasm'"));
+    let renamed = js(|b| {
+        b.bundler(true).output_names(OutputNames::new().wasm("{stem}.module.wasm"));
+    }, "out.js");
+    assert!(renamed.contains("import * as wasm from './out.module.wasm';"));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out").browser(true).bundler(true);
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert_eq!(err, "`bundler` generates a different flavor of output, it can't be \
+                     combined with `browser`");
+}
+
+#[test]
+fn multiple_targets() {
+    let wasm = representative_wasm(Some("./logger"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm.clone(), "out")
+        .typescript(true)
+        .targets(&[Target::browser(), Target::nodejs()]);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    // A single wasm, which imports the glue of the target importing it as an
+    // ES module, is shared by all of them.
+    let names = out.files.keys().map(|s| &s[..]).collect::<Vec<_>>();
+    assert_eq!(names, ["node/out.d.ts", "node/out.js", "node/out_bg.js", "out_bg.d.ts",
+                       "out_bg.wasm", "web/out.d.ts", "web/out.js"]);
+    let web = String::from_utf8(out.files["web/out.js"].clone()).unwrap();
+    assert!(web.contains("import * as wasm from '../out_bg';"));
+    let node = String::from_utf8(out.files["node/out_bg.js"].clone()).unwrap();
+    assert!(node.contains("imports['./web/out'] = require('./out');"));
+    assert!(node.contains("join(__dirname, '../out_bg.wasm')"));
+    let glue = b"./web/out";
+    assert!(out.files["out_bg.wasm"].windows(glue.len()).any(|w| w == glue));
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm.clone(), "out")
+        .targets(&[Target::browser(), Target::nodejs().dir("web").clone()]);
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Config(ref s) => {
+            assert_eq!(s, "multiple targets are generated into the `web` directory");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_bytes(wasm, "out").targets(&[Target::browser(), Target::bundler()]);
+    match bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err() {
+        cli::Error::Config(ref s) => {
+            assert!(s.starts_with("the targets in `web` and `bundler` both import the wasm"));
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn deno_module() {
+    let wasm = representative_wasm(Some("./logger.js"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .deno(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.deno.js", &out.files["out.js"]);
+    assert_snapshot("representative.deno_bg.js", &out.files["out_bg.js"]);
+    assert_snapshot("representative.deno.d.ts", &out.files["out.d.ts"]);
+    assert!(!out.files.values().any(|f| String::from_utf8_lossy(f).contains("require(")));
+
+    // Node runs the modules as they are given a stand-in for `Deno.readFile`,
+    // and the exports work as soon as the glue is imported.
+    out.files.insert("package.json".to_string(), br#"{ "type": "module" }"#.to_vec());
+    let logger = b"export const logged = [];\nexport function log(n) { logged.push(n); }\n";
+    out.files.insert("logger.js".to_string(), logger.to_vec());
+    run_node("deno", &out.files, &["--input-type=module"], r#"
+            import assert from 'assert';
+            import fs from 'fs';
+            import path from 'path';
+            import { pathToFileURL } from 'url';
+            const read = [];
+            globalThis.Deno = {
+                readFile: url => {
+                    read.push(path.basename(url.pathname));
+                    return fs.promises.readFile(url);
+                },
+            };
+            const dir = pathToFileURL(process.argv[1] + '/').href;
+            const out = await import(dir + 'out.js');
+            const logger = await import(dir + 'logger.js');
+            assert.deepStrictEqual(read, ['out_bg.wasm']);
+            assert.strictEqual(out.halve(3), 1.5);
+            assert.strictEqual(out.Counter.new(2).add(3), 5);
+            assert.deepStrictEqual(logger.logged, [3]);
+        "#);
+}
+
+#[test]
+fn nodejs_es_module() {
+    let wasm = representative_wasm(Some("./logger.js"));
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm.clone()), "out")
+        .nodejs_module(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("representative.node-module_bg.js", &out.files["out_bg.js"]);
+    assert!(!out.files.values().any(|f| String::from_utf8_lossy(f).contains("require(")));
+    out.files.insert("package.json".to_string(), br#"{ "type": "module" }"#.to_vec());
+    let logger = b"export const logged = [];\nexport function log(n) { logged.push(n); }\n";
+    out.files.insert("logger.js".to_string(), logger.to_vec());
+    run_node("node-module", &out.files, &["--input-type=module"], r#"
+            import assert from 'assert';
+            import { pathToFileURL } from 'url';
+            const dir = pathToFileURL(process.argv[1] + '/').href;
+            const out = await import(dir + 'out.js');
+            const logger = await import(dir + 'logger.js');
+            assert.strictEqual(out.halve(3), 1.5);
+            assert.strictEqual(out.Counter.new(2).add(3), 5);
+            assert.deepStrictEqual(logger.logged, [3]);
+        "#);
+
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .nodejs(true)
+        .nodejs_module(true);
+    let err = bindgen.generate_to(&mut MemoryOutput::default()).unwrap_err().to_string();
+    assert!(err.contains("can't be combined with `nodejs`"), "{}", err);
+}
+
+// (module
+//   (import "__wbindgen_placeholder__" "__wbindgen_describe" (func $describe (param i32)))
+//   (import "env" "memory" (memory 1 2 shared))
+//   (func (export "get") (result i32)
+//     i32.const 0
+//     i32.load)
+//   (func (export "__wbindgen_describe_get") ...)) ;; fn() -> u32
+const SHARED_MEMORY_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0c, 0x03, 0x60,
+    0x01, 0x7f, 0x00, 0x60, 0x00, 0x01, 0x7f, 0x60, 0x00, 0x00, 0x02, 0x3f,
+    0x02, 0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e,
+    0x5f, 0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64, 0x65, 0x72,
+    0x5f, 0x5f, 0x13, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65,
+    0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x00, 0x00,
+    0x03, 0x65, 0x6e, 0x76, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02,
+    0x03, 0x01, 0x02, 0x03, 0x03, 0x02, 0x01, 0x02, 0x07, 0x21, 0x02, 0x03,
+    0x67, 0x65, 0x74, 0x00, 0x01, 0x17, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e,
+    0x64, 0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62,
+    0x65, 0x5f, 0x67, 0x65, 0x74, 0x00, 0x02, 0x0a, 0x1c, 0x02, 0x07, 0x00,
+    0x41, 0x00, 0x28, 0x02, 0x00, 0x0b, 0x12, 0x00, 0x41, 0x0b, 0x10, 0x00,
+    0x41, 0x00, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41, 0x06, 0x10, 0x00,
+    0x0b,
+];
+
+#[test]
+fn shared_memory_module() {
+    let mut program = program("shared_memory");
+    program.exports.push(export("get"));
+    let wasm = with_program(SHARED_MEMORY_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .no_modules(true)
+        .typescript(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("shared_memory.no-modules.js", &out.files["out.js"]);
+    assert_snapshot("shared_memory.no-modules.d.ts", &out.files["out.d.ts"]);
+
+    // The memory import is still `(memory 1 2 shared)`.
+    let import = b"\x06memory\x02\x03\x01\x02";
+    assert!(out.files["out_bg.wasm"].windows(import.len()).any(|w| w == &import[..]));
+}
+
+// (module
+//   (import "__wbindgen_placeholder__" "__wbindgen_describe" (func $describe (param i32)))
+//   (memory (export "memory") 1)
+//   (global $value (mut i32) (i32.const 0))
+//   (func (export "get_value") (result i32)
+//     global.get $value)
+//   (func $start
+//     i32.const 42
+//     global.set $value)
+//   (start $start)
+//   (func (export "__wbindgen_describe_get_value") ...)) ;; fn() -> u32
+const START_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0c, 0x03, 0x60,
+    0x01, 0x7f, 0x00, 0x60, 0x00, 0x01, 0x7f, 0x60, 0x00, 0x00, 0x02, 0x30,
+    0x01, 0x18, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e,
+    0x5f, 0x70, 0x6c, 0x61, 0x63, 0x65, 0x68, 0x6f, 0x6c, 0x64, 0x65, 0x72,
+    0x5f, 0x5f, 0x13, 0x5f, 0x5f, 0x77, 0x62, 0x69, 0x6e, 0x64, 0x67, 0x65,
+    0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x00, 0x00,
+    0x03, 0x04, 0x03, 0x01, 0x02, 0x02, 0x05, 0x03, 0x01, 0x00, 0x01, 0x06,
+    0x06, 0x01, 0x7f, 0x01, 0x41, 0x00, 0x0b, 0x07, 0x36, 0x03, 0x06, 0x6d,
+    0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x09, 0x67, 0x65, 0x74, 0x5f,
+    0x76, 0x61, 0x6c, 0x75, 0x65, 0x00, 0x01, 0x1d, 0x5f, 0x5f, 0x77, 0x62,
+    0x69, 0x6e, 0x64, 0x67, 0x65, 0x6e, 0x5f, 0x64, 0x65, 0x73, 0x63, 0x72,
+    0x69, 0x62, 0x65, 0x5f, 0x67, 0x65, 0x74, 0x5f, 0x76, 0x61, 0x6c, 0x75,
+    0x65, 0x00, 0x03, 0x08, 0x01, 0x02, 0x0a, 0x20, 0x03, 0x04, 0x00, 0x23,
+    0x00, 0x0b, 0x06, 0x00, 0x41, 0x2a, 0x24, 0x00, 0x0b, 0x12, 0x00, 0x41,
+    0x0b, 0x10, 0x00, 0x41, 0x00, 0x10, 0x00, 0x41, 0x01, 0x10, 0x00, 0x41,
+    0x06, 0x10, 0x00, 0x0b,
+];
+
+#[test]
+fn start_module() {
+    let mut program = program("start");
+    program.exports.push(export("get_value"));
+    let wasm = with_program(START_WASM, &program);
+    let mut bindgen = Bindgen::new();
+    bindgen.input_reader(Cursor::new(wasm), "out")
+        .nodejs(true);
+    let mut out = MemoryOutput::default();
+    bindgen.generate_to(&mut out).expect("failed to run bindgen");
+
+    assert_snapshot("start.node.js", &out.files["out.js"]);
+
+    // The start function runs once the glue is loaded, and not again.
+    run_node("start", &out.files, &[], "
+        const out = require(require('path').join(process.argv[1], 'out.js'));
+        require('assert').strictEqual(out.get_value(), 42);
+    ");
+}
@@ -0,0 +1,130 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Command;
+
+use cli::wasm2es6js::Config;
+
+use super::{root, run};
+
+// (module
+//   (memory (export "memory") 1)
+//   (func $start
+//     i32.const 0
+//     i32.const 42
+//     i32.store)
+//   (func (export "get") (result i32)
+//     i32.const 0
+//     i32.load)
+//   (start $start))
+const START_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x60,
+    0x00, 0x00, 0x60, 0x00, 0x01, 0x7f, 0x03, 0x03, 0x02, 0x00, 0x01, 0x05,
+    0x03, 0x01, 0x00, 0x01, 0x07, 0x10, 0x02, 0x06, 0x6d, 0x65, 0x6d, 0x6f,
+    0x72, 0x79, 0x02, 0x00, 0x03, 0x67, 0x65, 0x74, 0x00, 0x01, 0x08, 0x01,
+    0x00, 0x0a, 0x13, 0x02, 0x09, 0x00, 0x41, 0x00, 0x41, 0x2a, 0x36, 0x02,
+    0x00, 0x0b, 0x07, 0x00, 0x41, 0x00, 0x28, 0x02, 0x00, 0x0b,
+];
+
+// (module
+//   (memory (export "mem") 1)
+//   (table (export "tbl") 1 anyfunc)
+//   (global (export "answer") i32 (i32.const 42)))
+const VALUES_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x04, 0x04, 0x01, 0x70,
+    0x00, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x06, 0x06, 0x01, 0x7f, 0x00,
+    0x41, 0x2a, 0x0b, 0x07, 0x16, 0x03, 0x03, 0x6d, 0x65, 0x6d, 0x02, 0x00,
+    0x03, 0x74, 0x62, 0x6c, 0x01, 0x00, 0x06, 0x61, 0x6e, 0x73, 0x77, 0x65,
+    0x72, 0x03, 0x00,
+];
+
+// (module
+//   (import "./values.mjs" "mem" (memory 1))
+//   (func (export "load") (result i32)
+//     i32.const 0
+//     i32.load))
+const IMPORTS_MEMORY_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60,
+    0x00, 0x01, 0x7f, 0x02, 0x15, 0x01, 0x0c, 0x2e, 0x2f, 0x76, 0x61, 0x6c,
+    0x75, 0x65, 0x73, 0x2e, 0x6d, 0x6a, 0x73, 0x03, 0x6d, 0x65, 0x6d, 0x02,
+    0x00, 0x01, 0x03, 0x02, 0x01, 0x00, 0x07, 0x08, 0x01, 0x04, 0x6c, 0x6f,
+    0x61, 0x64, 0x00, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0x41, 0x00, 0x28,
+    0x02, 0x00, 0x0b,
+];
+
+/// Converts each of `modules` to `NAME.mjs` (with the wasm itself written
+/// to `NAME.wasm`) and then runs `test` with node.
+fn run_modules(modules: &mut [(&str, &mut Config, &[u8])], test: &str) {
+    let root = root();
+    drop(fs::remove_dir_all(&root));
+    fs::create_dir_all(&root).unwrap();
+
+    for &mut (name, ref mut config, wasm) in modules.iter_mut() {
+        File::create(root.join(format!("{}.wasm", name))).unwrap()
+            .write_all(wasm).unwrap();
+        let js = config.generate(wasm)
+            .expect("failed to convert wasm to js")
+            .js();
+        File::create(root.join(format!("{}.mjs", name))).unwrap()
+            .write_all(js.as_bytes()).unwrap();
+    }
+    File::create(root.join("run.mjs")).unwrap()
+        .write_all(test.as_bytes()).unwrap();
+
+    let mut cmd = Command::new("node");
+    cmd.arg("--experimental-modules")
+        .arg(root.join("run.mjs"))
+        .current_dir(&root);
+    run(&mut cmd, "node");
+}
+
+#[test]
+fn start_runs_before_first_export_base64() {
+    run_modules(&mut [("start", Config::new().base64(true), START_WASM)], r#"
+        import * as assert from "assert";
+        import { get } from "./start.mjs";
+
+        assert.strictEqual(get(), 42);
+    "#);
+}
+
+#[test]
+fn start_runs_before_first_export_fetch() {
+    let mut config = Config::new();
+    config.fetch(Some("start.wasm".to_string()));
+    run_modules(&mut [("start", &mut config, START_WASM)], r#"
+        import * as assert from "assert";
+        import * as fs from "fs";
+        import * as process from "process";
+
+        global.fetch = path => Promise.resolve({
+            arrayBuffer: () => Promise.resolve(fs.readFileSync(path)),
+        });
+
+        import("./start.mjs").then(wasm => {
+            assert.throws(() => wasm.get(), /before the wasm module was instantiated/);
+            return wasm.booted.then(() => assert.strictEqual(wasm.get(), 42));
+        }).catch(error => {
+            console.error(error);
+            process.exit(1);
+        });
+    "#);
+}
+
+#[test]
+fn exports_memory_table_and_globals() {
+    run_modules(&mut [
+        ("values", Config::new().base64(true), VALUES_WASM),
+        ("imports_memory", Config::new().base64(true), IMPORTS_MEMORY_WASM),
+    ], r#"
+        import * as assert from "assert";
+        import { mem, tbl, answer } from "./values.mjs";
+        import { load } from "./imports_memory.mjs";
+
+        assert.ok(mem instanceof WebAssembly.Memory);
+        assert.ok(tbl instanceof WebAssembly.Table);
+        assert.strictEqual(answer, 42);
+
+        new Uint32Array(mem.buffer)[0] = 7;
+        assert.strictEqual(load(), 7);
+    "#);
+}